@@ -142,6 +142,7 @@ pub use crate::{
     pattern::{
         pattern_from_bytes, pattern_from_os, patterns_from_path,
         patterns_from_reader, patterns_from_stdin, InvalidPatternError,
+        PatternsBuilder,
     },
     process::{CommandError, CommandReader, CommandReaderBuilder},
     wtr::{
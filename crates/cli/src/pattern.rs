@@ -73,6 +73,111 @@ pub fn pattern_from_bytes(
     })
 }
 
+/// A builder for reading patterns, one per line, with configurable
+/// semantics.
+///
+/// The default configuration matches [`patterns_from_reader`]: empty lines
+/// are preserved as empty patterns (which match every line), a trailing line
+/// terminator on the final line does not produce a phantom empty pattern,
+/// and both `\n` and `\r\n` line terminators are recognized and stripped.
+/// Additionally, patterns containing a NUL byte are rejected with an error
+/// that reports the position of the offending byte, since a NUL can never be
+/// matched when binary detection is enabled.
+#[derive(Clone, Debug, Default)]
+pub struct PatternsBuilder {
+    comments: bool,
+}
+
+impl PatternsBuilder {
+    /// Return a new builder for reading patterns with the default
+    /// configuration.
+    pub fn new() -> PatternsBuilder {
+        PatternsBuilder::default()
+    }
+
+    /// When enabled, lines beginning with a `#` are treated as comments and
+    /// skipped instead of being interpreted as patterns.
+    ///
+    /// This is disabled by default, in which case a leading `#` is part of
+    /// the pattern.
+    pub fn comments(&mut self, yes: bool) -> &mut PatternsBuilder {
+        self.comments = yes;
+        self
+    }
+
+    /// Read patterns from a file path, one per line.
+    ///
+    /// If there was a problem reading or if any of the patterns are invalid,
+    /// then an error is returned. If there was a problem with a specific
+    /// pattern, then the error message will include the line number and the
+    /// file path.
+    pub fn patterns_from_path<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<Vec<String>> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("{}: {}", path.display(), err),
+            )
+        })?;
+        self.patterns_from_reader(file).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("{}:{}", path.display(), err),
+            )
+        })
+    }
+
+    /// Read patterns from any reader, one per line.
+    ///
+    /// If there was a problem reading or if any of the patterns are invalid,
+    /// then an error is returned. If there was a problem with a specific
+    /// pattern, then the error message will include the line number.
+    ///
+    /// Note that this routine uses its own internal buffer, so the caller
+    /// should not provide their own buffered reader if possible.
+    pub fn patterns_from_reader<R: io::Read>(
+        &self,
+        rdr: R,
+    ) -> io::Result<Vec<String>> {
+        use bstr::ByteSlice;
+
+        let comments = self.comments;
+        let mut patterns = vec![];
+        let mut line_number = 0;
+        io::BufReader::new(rdr).for_byte_line(|line| {
+            line_number += 1;
+            if comments && line.first() == Some(&b'#') {
+                return Ok(true);
+            }
+            if let Some(offset) = line.find_byte(b'\x00') {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "{}: found NUL byte in pattern at byte offset {} \
+                         (use the escape sequence '\\x00' to match a NUL \
+                         byte in a pattern)",
+                        line_number, offset,
+                    ),
+                ));
+            }
+            match pattern_from_bytes(line) {
+                Ok(pattern) => {
+                    patterns.push(pattern.to_string());
+                    Ok(true)
+                }
+                Err(err) => Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("{}: {}", line_number, err),
+                )),
+            }
+        })?;
+        Ok(patterns)
+    }
+}
+
 /// Read patterns from a file path, one per line.
 ///
 /// If there was a problem reading or if any of the patterns contain invalid
@@ -80,19 +185,7 @@ pub fn pattern_from_bytes(
 /// pattern, then the error message will include the line number and the file
 /// path.
 pub fn patterns_from_path<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
-    let path = path.as_ref();
-    let file = std::fs::File::open(path).map_err(|err| {
-        io::Error::new(
-            io::ErrorKind::Other,
-            format!("{}: {}", path.display(), err),
-        )
-    })?;
-    patterns_from_reader(file).map_err(|err| {
-        io::Error::new(
-            io::ErrorKind::Other,
-            format!("{}:{}", path.display(), err),
-        )
-    })
+    PatternsBuilder::new().patterns_from_path(path)
 }
 
 /// Read patterns from stdin, one per line.
@@ -115,6 +208,12 @@ pub fn patterns_from_stdin() -> io::Result<Vec<String>> {
 /// UTF-8, then an error is returned. If there was a problem with a specific
 /// pattern, then the error message will include the line number.
 ///
+/// Empty lines are preserved as empty patterns, which match every line. A
+/// trailing line terminator on the final line does not produce a phantom
+/// empty pattern, so a completely empty reader produces no patterns at all
+/// while a reader containing just a line terminator produces one empty
+/// pattern. To skip `#` comments, use [`PatternsBuilder`].
+///
 /// Note that this routine uses its own internal buffer, so the caller should
 /// not provide their own buffered reader if possible.
 ///
@@ -139,28 +238,72 @@ pub fn patterns_from_stdin() -> io::Result<Vec<String>> {
 /// # Ok::<(), Box<dyn std::error::Error>>(())
 /// ```
 pub fn patterns_from_reader<R: io::Read>(rdr: R) -> io::Result<Vec<String>> {
-    let mut patterns = vec![];
-    let mut line_number = 0;
-    io::BufReader::new(rdr).for_byte_line(|line| {
-        line_number += 1;
-        match pattern_from_bytes(line) {
-            Ok(pattern) => {
-                patterns.push(pattern.to_string());
-                Ok(true)
-            }
-            Err(err) => Err(io::Error::new(
-                io::ErrorKind::Other,
-                format!("{}: {}", line_number, err),
-            )),
-        }
-    })?;
-    Ok(patterns)
+    PatternsBuilder::new().patterns_from_reader(rdr)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn reader_empty_input() {
+        // An empty reader (e.g., /dev/null) produces no patterns at all.
+        let pats = patterns_from_reader(&b""[..]).unwrap();
+        assert!(pats.is_empty());
+    }
+
+    #[test]
+    fn reader_single_newline() {
+        // A reader containing just a line terminator produces one empty
+        // pattern, which matches everything.
+        let pats = patterns_from_reader(&b"\n"[..]).unwrap();
+        assert_eq!(vec!["".to_string()], pats);
+    }
+
+    #[test]
+    fn reader_preserves_interior_empty_lines() {
+        let pats = patterns_from_reader(&b"foo\n\nbar\n"[..]).unwrap();
+        assert_eq!(vec!["foo", "", "bar"], pats);
+    }
+
+    #[test]
+    fn reader_trailing_newline_is_not_a_pattern() {
+        // A trailing line terminator on the final line doesn't produce a
+        // phantom empty pattern, so these are equivalent.
+        let with = patterns_from_reader(&b"foo\nbar\n"[..]).unwrap();
+        let without = patterns_from_reader(&b"foo\nbar"[..]).unwrap();
+        assert_eq!(vec!["foo", "bar"], with);
+        assert_eq!(with, without);
+    }
+
+    #[test]
+    fn reader_crlf() {
+        let pats = patterns_from_reader(&b"foo\r\n\r\nbar\r\n"[..]).unwrap();
+        assert_eq!(vec!["foo", "", "bar"], pats);
+    }
+
+    #[test]
+    fn reader_nul_byte() {
+        let err = patterns_from_reader(&b"foo\nb\x00ar\n"[..]).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.starts_with("2: found NUL byte"), "bad message: {msg}");
+        assert!(msg.contains("byte offset 1"), "bad message: {msg}");
+    }
+
+    #[test]
+    fn reader_comments() {
+        let haystack = &b"# a comment\nfoo\n#bar\n"[..];
+        // Comments are only skipped when requested...
+        let pats = PatternsBuilder::new()
+            .comments(true)
+            .patterns_from_reader(haystack)
+            .unwrap();
+        assert_eq!(vec!["foo"], pats);
+        // ... and are otherwise patterns like any other.
+        let pats = patterns_from_reader(haystack).unwrap();
+        assert_eq!(vec!["# a comment", "foo", "#bar"], pats);
+    }
+
     #[test]
     fn bytes() {
         let pat = b"abc\xFFxyz";
@@ -0,0 +1,254 @@
+/*!
+Defines a matcher for exact whole-line literal matching.
+
+This is a fast path for `-x/--line-regexp` combined with
+`-F/--fixed-strings`, which amounts to asking "is this line exactly equal to
+one of these strings?" That question is a common way to check membership in
+an allowlist, often with a large number of patterns via `-f/--file`. The
+general path answers it by compiling all of the literals into one big regex
+alternation, but a hash set lookup per line is dramatically faster when the
+number of patterns is large.
+*/
+
+use std::collections::HashSet;
+
+use {
+    bstr::ByteSlice,
+    grep::matcher::{LineTerminator, Match, Matcher, NoCaptures, NoError},
+};
+
+/// A matcher that reports a match for every line that is exactly equal to
+/// one of a set of literal patterns.
+///
+/// This implements the same semantics as compiling the patterns with
+/// `-x/--line-regexp` and `-F/--fixed-strings` through the regex engine, but
+/// answers each line with a hash set lookup instead.
+///
+/// When case-insensitivity is enabled, both the patterns and each candidate
+/// line are case folded via ASCII lowercasing before comparison. Only ASCII
+/// folding is offered because it is the only folding that can be applied
+/// byte-wise to a whole line while agreeing with the regex engine: Unicode
+/// simple case folding (what the regex engine does) and Unicode lowercasing
+/// (what we could do to a line) disagree on inputs like `ΑΣ` or `İ`. See
+/// `HiArgs::matcher_exact_line` for the gate.
+#[derive(Clone, Debug)]
+pub(crate) struct ExactLineMatcher {
+    /// The set of lines to look for. When `case_insensitive` is set, these
+    /// are the case folded versions of the patterns.
+    lines: HashSet<Vec<u8>>,
+    /// Whether to case fold candidate lines before lookup.
+    case_insensitive: bool,
+}
+
+impl ExactLineMatcher {
+    /// Create a new matcher for the given literal patterns.
+    ///
+    /// Callers must ensure that no pattern contains a line terminator, since
+    /// such a pattern could never be equal to a single line.
+    pub(crate) fn new<P: AsRef<[u8]>>(
+        patterns: &[P],
+        case_insensitive: bool,
+    ) -> ExactLineMatcher {
+        let lines = patterns
+            .iter()
+            .map(|p| {
+                let p = p.as_ref();
+                assert!(!p.contains(&b'\n'), "pattern contains a \\n");
+                if case_insensitive {
+                    fold(p)
+                } else {
+                    p.to_vec()
+                }
+            })
+            .collect();
+        ExactLineMatcher { lines, case_insensitive }
+    }
+
+    /// Returns true if and only if the given line (without its terminator)
+    /// is equal to one of the patterns.
+    fn is_match_line(&self, line: &[u8]) -> bool {
+        if !self.case_insensitive {
+            return self.lines.contains(line);
+        }
+        self.lines.contains(&fold(line))
+    }
+}
+
+impl Matcher for ExactLineMatcher {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn find_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+    ) -> Result<Option<Match>, NoError> {
+        let mut start = at;
+        // If `at` isn't at the beginning of a line, then the line containing
+        // it began before `at` and can't correspond to a whole-line match
+        // starting at or after `at`. Skip ahead to the next line.
+        if start > 0 && haystack[start - 1] != b'\n' {
+            match haystack[start..].find_byte(b'\n') {
+                None => return Ok(None),
+                Some(i) => start += i + 1,
+            }
+        }
+        while start <= haystack.len() {
+            let end = match haystack[start..].find_byte(b'\n') {
+                Some(i) => start + i,
+                None => haystack.len(),
+            };
+            if self.is_match_line(&haystack[start..end]) {
+                return Ok(Some(Match::new(start, end)));
+            }
+            if end == haystack.len() {
+                break;
+            }
+            start = end + 1;
+        }
+        Ok(None)
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+
+    fn line_terminator(&self) -> Option<LineTerminator> {
+        // Patterns never contain a line terminator, so a match can never
+        // contain one either. This unlocks the searcher's line oriented
+        // fast paths.
+        Some(LineTerminator::byte(b'\n'))
+    }
+}
+
+/// Case folds the given line for use as (or comparison with) a hash set key.
+///
+/// This is ASCII-only lowercasing, matching what the regex engine does with
+/// case-insensitivity enabled and Unicode disabled.
+fn fold(line: &[u8]) -> Vec<u8> {
+    line.to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collect all matches reported by the given matcher on the haystack.
+    fn find_all<M: Matcher>(m: &M, haystack: &[u8]) -> Vec<(usize, usize)>
+    where
+        M::Error: std::fmt::Debug,
+    {
+        let mut matches = vec![];
+        m.find_iter(haystack, |m| {
+            matches.push((m.start(), m.end()));
+            true
+        })
+        .unwrap();
+        matches
+    }
+
+    /// Build the equivalent matcher through the regex engine, i.e., what
+    /// `-x -F` compiles to without the fast path. Since the fast path only
+    /// handles case-insensitivity with Unicode disabled, we mirror that
+    /// configuration here.
+    fn regex_matcher(
+        patterns: &[&str],
+        case_insensitive: bool,
+    ) -> grep::regex::RegexMatcher {
+        grep::regex::RegexMatcherBuilder::new()
+            .multi_line(true)
+            .fixed_strings(true)
+            .whole_line(true)
+            .case_insensitive(case_insensitive)
+            .unicode(!case_insensitive)
+            .build_many(patterns)
+            .unwrap()
+    }
+
+    /// Assert that the exact line matcher and the regex path agree on the
+    /// given haystack.
+    fn assert_agrees(
+        patterns: &[&str],
+        case_insensitive: bool,
+        haystack: &str,
+    ) {
+        let exact = ExactLineMatcher::new(patterns, case_insensitive);
+        let regex = regex_matcher(patterns, case_insensitive);
+        assert_eq!(
+            find_all(&regex, haystack.as_bytes()),
+            find_all(&exact, haystack.as_bytes()),
+            "disagreement on {haystack:?} with patterns {patterns:?} \
+             (case_insensitive: {case_insensitive})",
+        );
+    }
+
+    #[test]
+    fn agrees_with_regex_path() {
+        let patterns = &["foo", "bar baz", ""];
+        let haystacks = &[
+            "foo\nbar baz\nquux\n",
+            "foo",
+            "foo\n",
+            "xfoo\nfoox\n",
+            "\n\nfoo\n\n",
+            "bar baz",
+            "FOO\n",
+            "",
+        ];
+        for haystack in haystacks {
+            assert_agrees(patterns, false, haystack);
+        }
+    }
+
+    #[test]
+    fn agrees_with_regex_path_case_insensitive() {
+        let patterns = &["foo", "Bar Baz"];
+        let haystacks =
+            &["FOO\nbar baz\n", "Foo", "foO\nBAR BAZ", "xFOO\n", "foo\n"];
+        for haystack in haystacks {
+            assert_agrees(patterns, true, haystack);
+        }
+    }
+
+    #[test]
+    fn agrees_with_regex_path_non_ascii() {
+        // Non-ASCII case folding is precisely where a byte-wise fold would
+        // disagree with the regex engine: Unicode simple case folding matches
+        // `ΑΣ` against `ασ` and `ας`, while `İ` lowercases to `i` followed
+        // by U+0307. That's why the fast path is only used when the search
+        // is case-sensitive or when Unicode is disabled, and in those modes
+        // the two paths must still agree on such inputs.
+        let patterns = &["ΑΣ", "İ", "ß"];
+        let haystacks =
+            &["ΑΣ\nασ\nας\nΑς\n", "İ\ni\u{307}\nI\u{307}\n", "ß\nẞ\nss\nSS\n"];
+        for haystack in haystacks {
+            assert_agrees(patterns, false, haystack);
+            assert_agrees(patterns, true, haystack);
+        }
+    }
+
+    #[test]
+    fn match_at_offset() {
+        let m = ExactLineMatcher::new(&["foo"], false);
+        let haystack = b"foo\nfoo\n";
+        // Starting mid-line skips the remainder of that line.
+        assert_eq!(Ok(Some(Match::new(4, 7))), m.find_at(haystack, 1));
+        assert_eq!(Ok(Some(Match::new(4, 7))), m.find_at(haystack, 4));
+        assert_eq!(Ok(None), m.find_at(haystack, 5));
+    }
+
+    #[test]
+    fn large_pattern_set() {
+        // This is of course not a proper benchmark, but it exercises the
+        // intended use case: membership tests against very large pattern
+        // lists, where building a regex alternation is impractical.
+        let patterns: Vec<String> =
+            (0..100_000).map(|i| format!("pattern-{i}")).collect();
+        let m = ExactLineMatcher::new(&patterns, false);
+        let haystack = "pattern-99999\nnot-a-pattern\npattern-0\n";
+        assert_eq!(
+            vec![(0, 13), (28, 37)],
+            find_all(&m, haystack.as_bytes()),
+        );
+    }
+}
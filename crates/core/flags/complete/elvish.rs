@@ -0,0 +1,82 @@
+/*!
+Provides completions for ripgrep's CLI for the elvish shell.
+*/
+
+use crate::flags::{defs::FLAGS, Flag};
+
+const TEMPLATE: &'static str = "
+set edit:completion:arg-completer[rg] = {|@words|
+    fn cand {|text desc|
+        edit:complex-candidate $text &display=$text' ('$desc')'
+    }
+!CANDS!
+    edit:complete-filename $words[-1] | each {|c| put $c }
+}
+";
+
+/// Generate completions for elvish.
+pub(crate) fn generate() -> String {
+    generate_with_flags(FLAGS)
+}
+
+/// Generate completions for the flags given.
+///
+/// This is split out from `generate` so that tests can pin down the output
+/// for a stable subset of flags without needing to be regenerated whenever a
+/// new flag is added.
+fn generate_with_flags(flags: &[&dyn Flag]) -> String {
+    let mut cands = String::new();
+    for flag in flags.iter() {
+        // Elvish single quoted strings escape quotes by doubling them.
+        let doc = flag.doc_short().replace("'", "''");
+        cands.push_str(&format!("    cand --{} '{}'\n", flag.name_long(), doc));
+        if let Some(byte) = flag.name_short() {
+            cands.push_str(&format!(
+                "    cand -{} '{}'\n",
+                char::from(byte),
+                doc
+            ));
+        }
+        if let Some(negated) = flag.name_negated() {
+            cands.push_str(&format!("    cand --{negated} '{doc}'\n"));
+        }
+    }
+    TEMPLATE.trim_start().replace("!CANDS!", cands.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Completions for a stable subset of flags: one with a short name, one
+    /// without and one switch with a negation. This keeps the test
+    /// independent of future flag additions.
+    #[test]
+    fn golden_subset() {
+        let flags: Vec<&dyn Flag> = FLAGS
+            .iter()
+            .copied()
+            .filter(|flag| {
+                matches!(
+                    flag.name_long(),
+                    "after-context" | "color" | "fixed-strings"
+                )
+            })
+            .collect();
+        let expected = "\
+set edit:completion:arg-completer[rg] = {|@words|
+    fn cand {|text desc|
+        edit:complex-candidate $text &display=$text' ('$desc')'
+    }
+    cand --after-context 'Show NUM lines after each match.'
+    cand -A 'Show NUM lines after each match.'
+    cand --color 'When to use color.'
+    cand --fixed-strings 'Treat all patterns as literals.'
+    cand -F 'Treat all patterns as literals.'
+    cand --no-fixed-strings 'Treat all patterns as literals.'
+    edit:complete-filename $words[-1] | each {|c| put $c }
+}
+";
+        assert_eq!(expected, generate_with_flags(&flags));
+    }
+}
@@ -0,0 +1,97 @@
+/*!
+Provides completions for ripgrep's CLI for Nushell.
+*/
+
+use crate::flags::{defs::FLAGS, CompletionType, Flag};
+
+/// Generate completions for Nushell.
+pub(crate) fn generate() -> String {
+    generate_with_flags(FLAGS)
+}
+
+/// Generate completions for the flags given.
+///
+/// This is split out from `generate` so that tests can pin down the output
+/// for a stable subset of flags without needing to be regenerated whenever a
+/// new flag is added.
+fn generate_with_flags(flags: &[&dyn Flag]) -> String {
+    let mut out = String::from("# Completions for ripgrep.\n\n");
+    // Flags with a fixed set of choices each get a completer command that
+    // the extern signature below references.
+    for flag in flags.iter() {
+        if flag.doc_choices().is_empty() {
+            continue;
+        }
+        let choices = flag
+            .doc_choices()
+            .iter()
+            .map(|choice| format!("\"{choice}\""))
+            .collect::<Vec<String>>()
+            .join(" ");
+        out.push_str(&format!(
+            "def \"nu-complete rg {long}\" [] {{\n  [{choices}]\n}}\n\n",
+            long = flag.name_long(),
+        ));
+    }
+    out.push_str("export extern \"rg\" [\n");
+    for flag in flags.iter() {
+        let long = flag.name_long();
+        let short = match flag.name_short() {
+            None => String::new(),
+            Some(byte) => format!("(-{})", char::from(byte)),
+        };
+        let arg = if flag.is_switch() {
+            String::new()
+        } else if !flag.doc_choices().is_empty() {
+            format!(": string@\"nu-complete rg {long}\"")
+        } else if matches!(flag.completion_type(), CompletionType::Filename) {
+            ": path".to_string()
+        } else {
+            ": string".to_string()
+        };
+        let doc = flag.doc_short();
+        out.push_str(&format!("  --{long}{short}{arg} # {doc}\n"));
+        if let Some(negated) = flag.name_negated() {
+            out.push_str(&format!("  --{negated} # {doc}\n"));
+        }
+    }
+    out.push_str("  ...paths: path # Patterns and paths to search.\n]\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Completions for a stable subset of flags: one with a short name and
+    /// an argument, one with a fixed set of choices and one switch with a
+    /// negation. This keeps the test independent of future flag additions.
+    #[test]
+    fn golden_subset() {
+        let flags: Vec<&dyn Flag> = FLAGS
+            .iter()
+            .copied()
+            .filter(|flag| {
+                matches!(
+                    flag.name_long(),
+                    "after-context" | "color" | "fixed-strings"
+                )
+            })
+            .collect();
+        let expected = r#"# Completions for ripgrep.
+
+def "nu-complete rg color" [] {
+  ["never" "auto" "always" "ansi"]
+}
+
+export extern "rg" [
+  --after-context(-A): string # Show NUM lines after each match.
+  --color: string@"nu-complete rg color" # When to use color.
+  --fixed-strings(-F) # Treat all patterns as literals.
+  --no-fixed-strings # Treat all patterns as literals.
+  ...paths: path # Patterns and paths to search.
+]
+"#;
+        assert_eq!(expected, generate_with_flags(&flags));
+    }
+}
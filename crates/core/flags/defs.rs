@@ -61,6 +61,8 @@ pub(super) const FLAGS: &[&dyn Flag] = &[
     &CountMatches,
     &Crlf,
     &Debug,
+    &DedupeLines,
+    &DedupeLinks,
     &DfaSizeLimit,
     &Encoding,
     &Engine,
@@ -124,6 +126,7 @@ pub(super) const FLAGS: &[&dyn Flag] = &[
     &Quiet,
     &RegexSizeLimit,
     &Replace,
+    &Retry,
     &SearchZip,
     &SmartCase,
     &Sort,
@@ -839,8 +842,12 @@ are limited to \fBnobold\fP, \fBbold\fP, \fBnointense\fP, \fBintense\fP,
 .sp
 The format of the flag is
 \fB{\fP\fItype\fP\fB}:{\fP\fIattribute\fP\fB}:{\fP\fIvalue\fP\fB}\fP.
-\fItype\fP should be one of \fBpath\fP, \fBline\fP, \fBcolumn\fP or
-\fBmatch\fP. \fIattribute\fP can be \fBfg\fP, \fBbg\fP or \fBstyle\fP.
+\fItype\fP should be one of \fBpath\fP, \fBline\fP, \fBcontext-line\fP,
+\fBcolumn\fP, \fBmatch\fP or \fBseparator\fP. The \fBcontext-line\fP type
+applies to line numbers of contextual lines and defaults to the \fBline\fP
+settings when unset. The \fBseparator\fP type applies to the context
+separator and the separator printed between sets of search results.
+\fIattribute\fP can be \fBfg\fP, \fBbg\fP or \fBstyle\fP.
 \fIvalue\fP is either a color (for \fBfg\fP and \fBbg\fP) or a text style. A
 special format, \fB{\fP\fItype\fP\fB}:none\fP, will clear all color settings
 for \fItype\fP.
@@ -1462,6 +1469,121 @@ fn test_debug() {
     assert_eq!(Some(LoggingMode::Debug), args.logging);
 }
 
+/// --dedupe-lines
+#[derive(Debug)]
+struct DedupeLines;
+
+impl Flag for DedupeLines {
+    fn is_switch(&self) -> bool {
+        true
+    }
+    fn name_long(&self) -> &'static str {
+        "dedupe-lines"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-dedupe-lines")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        "Print each distinct matching line once per file."
+    }
+    fn doc_long(&self) -> &'static str {
+        r"
+This flag prints each distinct matching line at most once per file. When a
+matching line is byte-for-byte identical to a matching line printed earlier in
+the same file, the repeat is suppressed. Once the search of a file completes,
+a trailer line of the form \fB(line \fIM\fB repeated \fIN\fB more times)\fR is
+printed for each suppressed line.
+.sp
+This is useful for searching log files that contain the same matching line
+many thousands of times.
+.sp
+To keep memory usage bounded, at most 10,000 distinct matching lines are
+tracked per file. Beyond that, new distinct lines print normally, although
+previously seen lines continue to be deduplicated.
+.sp
+This flag has no effect in summary output modes such as \flag{count} or
+\flag{files-with-matches}, or when JSON output is requested via \flag{json}.
+"
+    }
+
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.dedupe_lines = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_dedupe_lines() {
+    let args = parse_low_raw(None::<&str>).unwrap();
+    assert_eq!(false, args.dedupe_lines);
+
+    let args = parse_low_raw(["--dedupe-lines"]).unwrap();
+    assert_eq!(true, args.dedupe_lines);
+
+    let args = parse_low_raw(["--dedupe-lines", "--no-dedupe-lines"]).unwrap();
+    assert_eq!(false, args.dedupe_lines);
+}
+
+/// --dedupe-links
+#[derive(Debug)]
+struct DedupeLinks;
+
+impl Flag for DedupeLinks {
+    fn is_switch(&self) -> bool {
+        true
+    }
+    fn name_long(&self) -> &'static str {
+        "dedupe-links"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-dedupe-links")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Filter
+    }
+    fn doc_short(&self) -> &'static str {
+        "Search each file at most once, even via multiple paths."
+    }
+    fn doc_long(&self) -> &'static str {
+        r"
+When enabled, ripgrep tracks the identity of every file it searches and skips
+files it has already seen. This prevents duplicate results (and double counted
+statistics) when the same file is reachable via multiple paths, for example
+through hard links or overlapping search roots such as \fBrg\fP \fIPATTERN\fP
+\fBdir dir/sub\fP.
+.sp
+File identity is determined by device and inode numbers, and is currently only
+available on Unix. On other platforms this flag has no effect.
+.sp
+Note that which of the paths to a file gets reported depends on the order in
+which the files are visited, which is not deterministic when searching with
+multiple threads.
+"
+    }
+
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.dedupe_links = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_dedupe_links() {
+    let args = parse_low_raw(None::<&str>).unwrap();
+    assert_eq!(false, args.dedupe_links);
+
+    let args = parse_low_raw(["--dedupe-links"]).unwrap();
+    assert_eq!(true, args.dedupe_links);
+
+    let args = parse_low_raw(["--dedupe-links", "--no-dedupe-links"]).unwrap();
+    assert_eq!(false, args.dedupe_links);
+}
+
 /// --dfa-size-limit
 #[derive(Debug)]
 struct DfaSizeLimit;
@@ -2388,6 +2510,12 @@ Generates a completion script for the \fBfish\fP shell.
 .TP 15
 \fBcomplete\-powershell\fP
 Generates a completion script for PowerShell.
+.TP 15
+\fBcomplete\-nushell\fP
+Generates a completion script for Nushell.
+.TP 15
+\fBcomplete\-elvish\fP
+Generates a completion script for the \fBelvish\fP shell.
 .PP
 The output is written to \fBstdout\fP. The list above may expand over time.
 "
@@ -2399,6 +2527,8 @@ The output is written to \fBstdout\fP. The list above may expand over time.
             "complete-zsh",
             "complete-fish",
             "complete-powershell",
+            "complete-nushell",
+            "complete-elvish",
         ]
     }
 
@@ -2409,6 +2539,8 @@ The output is written to \fBstdout\fP. The list above may expand over time.
             "complete-zsh" => GenerateMode::CompleteZsh,
             "complete-fish" => GenerateMode::CompleteFish,
             "complete-powershell" => GenerateMode::CompletePowerShell,
+            "complete-nushell" => GenerateMode::CompleteNushell,
+            "complete-elvish" => GenerateMode::CompleteElvish,
             unk => anyhow::bail!("choice '{unk}' is unrecognized"),
         };
         args.mode.update(Mode::Generate(genmode));
@@ -2437,6 +2569,12 @@ fn test_generate() {
     let args = parse_low_raw(["--generate", "complete-powershell"]).unwrap();
     assert_eq!(Mode::Generate(GenerateMode::CompletePowerShell), args.mode);
 
+    let args = parse_low_raw(["--generate", "complete-nushell"]).unwrap();
+    assert_eq!(Mode::Generate(GenerateMode::CompleteNushell), args.mode);
+
+    let args = parse_low_raw(["--generate", "complete-elvish"]).unwrap();
+    assert_eq!(Mode::Generate(GenerateMode::CompleteElvish), args.mode);
+
     let args =
         parse_low_raw(["--generate", "complete-bash", "--generate=man"])
             .unwrap();
@@ -3645,12 +3783,16 @@ This is equivalent to surrounding every pattern with \fB^\fP and \fB$\fP. In
 other words, this only prints lines where the entire line participates in a
 match.
 .sp
-This overrides the \flag{word-regexp} flag.
+This overrides the \flag{word-regexp} flag regardless of the order in which
+the two flags appear, and prints a warning when both are given.
 "
     }
 
     fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
         assert!(v.unwrap_switch(), "--line-regexp has no negation");
+        if args.boundary == Some(BoundaryMode::Word) {
+            args.boundary_conflict = true;
+        }
         args.boundary = Some(BoundaryMode::Line);
         Ok(())
     }
@@ -5958,6 +6100,82 @@ fn test_replace() {
     assert_eq!(Some(BString::from("")), args.replace);
 }
 
+/// --retry
+#[derive(Debug)]
+struct Retry;
+
+impl Flag for Retry {
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn name_long(&self) -> &'static str {
+        "retry"
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("NUM")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Retry transient I/O errors when reading haystacks."
+    }
+    fn doc_long(&self) -> &'static str {
+        r"
+When set, transient I/O errors that occur while opening or reading a haystack
+are retried up to \fINUM\fP times before the error is reported. This is
+principally useful on flaky network file systems (such as NFS or SMB mounts)
+where a sporadic I/O or timeout error would otherwise pollute ripgrep's output
+even though an immediate retry would succeed.
+.sp
+The value may optionally include a delay, in milliseconds, to sleep between
+attempts by using the format \fINUM\fP\fB:\fP\fIDELAY_MS\fP. When no delay is
+given, ripgrep sleeps for 100 milliseconds between attempts.
+.sp
+Only errors that are plausibly transient (for example, I/O errors, timeouts or
+interrupted reads) are retried. Errors such as a file not existing or a
+permission failure are never retried. The total number of retries performed is
+reported in the statistics shown by the \flag{stats} flag.
+"
+    }
+
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        let v = v.unwrap_value();
+        args.retry = Some(convert::str(&v)?.parse()?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_retry() {
+    use crate::flags::lowargs::RetryPolicy;
+
+    let args = parse_low_raw(None::<&str>).unwrap();
+    assert_eq!(None, args.retry);
+
+    let args = parse_low_raw(["--retry", "3"]).unwrap();
+    assert_eq!(
+        Some(RetryPolicy {
+            limit: 3,
+            delay: std::time::Duration::from_millis(100),
+        }),
+        args.retry,
+    );
+
+    let args = parse_low_raw(["--retry", "5:250"]).unwrap();
+    assert_eq!(
+        Some(RetryPolicy {
+            limit: 5,
+            delay: std::time::Duration::from_millis(250),
+        }),
+        args.retry,
+    );
+
+    assert!(parse_low_raw(["--retry", "wat"]).is_err());
+    assert!(parse_low_raw(["--retry", "3:wat"]).is_err());
+}
+
 /// -z/--search-zip
 #[derive(Debug)]
 struct SearchZip;
@@ -7439,13 +7657,19 @@ When enabled, ripgrep will only show matches surrounded by word boundaries.
 This is equivalent to surrounding every pattern with \fB\\b{start-half}\fP
 and \fB\\b{end-half}\fP.
 .sp
-This overrides the \flag{line-regexp} flag.
+When the \flag{line-regexp} flag is also given, it takes precedence over
+this flag regardless of the order in which they appear, and a warning is
+printed.
 "
     }
 
     fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
         assert!(v.unwrap_switch(), "--word-regexp has no negation");
-        args.boundary = Some(BoundaryMode::Word);
+        if args.boundary == Some(BoundaryMode::Line) {
+            args.boundary_conflict = true;
+        } else {
+            args.boundary = Some(BoundaryMode::Word);
+        }
         Ok(())
     }
 }
@@ -7462,11 +7686,17 @@ fn test_word_regexp() {
     let args = parse_low_raw(["-w"]).unwrap();
     assert_eq!(Some(BoundaryMode::Word), args.boundary);
 
+    // -x/--line-regexp wins over -w/--word-regexp regardless of order.
     let args = parse_low_raw(["-x", "-w"]).unwrap();
-    assert_eq!(Some(BoundaryMode::Word), args.boundary);
+    assert_eq!(Some(BoundaryMode::Line), args.boundary);
+    assert!(args.boundary_conflict);
 
     let args = parse_low_raw(["-w", "-x"]).unwrap();
     assert_eq!(Some(BoundaryMode::Line), args.boundary);
+    assert!(args.boundary_conflict);
+
+    let args = parse_low_raw(["-w"]).unwrap();
+    assert!(!args.boundary_conflict);
 }
 
 mod convert {
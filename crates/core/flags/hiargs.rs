@@ -17,7 +17,8 @@ use crate::{
         BinaryMode, BoundaryMode, BufferMode, CaseMode, ColorChoice,
         ContextMode, ContextSeparator, EncodingMode, EngineChoice,
         FieldContextSeparator, FieldMatchSeparator, LowArgs, MmapMode, Mode,
-        PatternSource, SearchMode, SortMode, SortModeKind, TypeChange,
+        PatternSource, RetryPolicy, SearchMode, SortMode, SortModeKind,
+        TypeChange,
     },
     haystack::{Haystack, HaystackBuilder},
     search::{PatternMatcher, Printer, SearchWorker, SearchWorkerBuilder},
@@ -45,6 +46,8 @@ pub(crate) struct HiArgs {
     context: ContextMode,
     context_separator: ContextSeparator,
     crlf: bool,
+    dedupe_lines: bool,
+    dedupe_links: bool,
     dfa_size_limit: Option<usize>,
     encoding: EncodingMode,
     engine: EngineChoice,
@@ -93,6 +96,7 @@ pub(crate) struct HiArgs {
     quit_after_match: bool,
     regex_size_limit: Option<usize>,
     replace: Option<BString>,
+    retry: Option<RetryPolicy>,
     search_zip: bool,
     sort: Option<SortMode>,
     stats: Option<grep::printer::Stats>,
@@ -138,6 +142,14 @@ impl HiArgs {
             _ => {}
         }
 
+        if low.boundary_conflict {
+            message!(
+                "both -w/--word-regexp and -x/--line-regexp were given; \
+                 -x/--line-regexp takes precedence and \
+                 -w/--word-regexp is ignored",
+            );
+        }
+
         let mut state = State::new()?;
         let patterns = Patterns::from_low_args(&mut state, &mut low)?;
         let paths = Paths::from_low_args(&mut state, &patterns, &mut low)?;
@@ -262,6 +274,8 @@ impl HiArgs {
             context: low.context,
             context_separator: low.context_separator,
             crlf: low.crlf,
+            dedupe_lines: low.dedupe_lines,
+            dedupe_links: low.dedupe_links,
             dfa_size_limit: low.dfa_size_limit,
             encoding: low.encoding,
             engine: low.engine,
@@ -307,6 +321,7 @@ impl HiArgs {
             quit_after_match,
             regex_size_limit: low.regex_size_limit,
             replace: low.replace,
+            retry: low.retry,
             search_zip: low.search_zip,
             sort: low.sort,
             stats,
@@ -348,6 +363,33 @@ impl HiArgs {
         self.paths.has_implicit_path
     }
 
+    /// Returns a hint when it looks like the caller swapped the order of the
+    /// pattern and file path arguments. e.g., `rg src/main.rs foo` instead of
+    /// `rg foo src/main.rs`.
+    ///
+    /// This fires only when the pattern was given positionally (as opposed
+    /// to via -e/--regexp or -f/--file) and the pattern names a file or
+    /// directory that actually exists. Even then, the hint is only produced
+    /// when there is additional evidence of a mix-up: either one of the path
+    /// arguments doesn't exist, or the search found no matches at all.
+    ///
+    /// Callers should emit the hint via `message!` so that it can be
+    /// suppressed with --no-messages.
+    pub(crate) fn swapped_arguments_hint(
+        &self,
+        matched: bool,
+    ) -> Option<String> {
+        if !self.patterns.positional || self.patterns.patterns.len() != 1 {
+            return None;
+        }
+        swapped_arguments_hint(
+            &self.patterns.patterns[0],
+            &self.paths.paths,
+            self.paths.has_implicit_path,
+            matched,
+        )
+    }
+
     /// Return a properly configured builder for constructing haystacks.
     ///
     /// The builder can be used to turn a directory entry (from the `ignore`
@@ -355,6 +397,7 @@ impl HiArgs {
     pub(crate) fn haystack_builder(&self) -> HaystackBuilder {
         let mut builder = HaystackBuilder::new();
         builder.strip_dot_prefix(self.paths.has_implicit_path);
+        builder.dedupe_links(self.dedupe_links);
         builder
     }
 
@@ -364,6 +407,13 @@ impl HiArgs {
     /// If there was a problem building the matcher (e.g., a syntax error),
     /// then this returns an error.
     pub(crate) fn matcher(&self) -> anyhow::Result<PatternMatcher> {
+        if let Some(m) = self.matcher_exact_line() {
+            log::debug!(
+                "using exact line matcher for {} pattern(s)",
+                self.patterns.patterns.len(),
+            );
+            return Ok(m);
+        }
         match self.engine {
             EngineChoice::Default => match self.matcher_rust() {
                 Ok(m) => Ok(m),
@@ -399,6 +449,58 @@ impl HiArgs {
         }
     }
 
+    /// Build an exact line matcher, if the arguments permit it.
+    ///
+    /// This is a fast path for `-x -F`, where every pattern is a literal
+    /// that must match a whole line. In that case, each line can be checked
+    /// with a hash set lookup instead of running a regex, which is a big win
+    /// when the number of patterns is large (e.g., an allowlist given via
+    /// `-f`).
+    ///
+    /// This returns `None` whenever anything about the arguments requires
+    /// the regex engine: a different engine was requested explicitly, the
+    /// patterns aren't literals anchored to whole lines, smart case or
+    /// Unicode case folding is in play (where any byte-wise folding we could
+    /// do would disagree with the regex engine's simple case folding), a
+    /// replacement needs capture groups, or the line terminator isn't a
+    /// plain `\n`.
+    fn matcher_exact_line(&self) -> Option<PatternMatcher> {
+        if !matches!(self.engine, EngineChoice::Default | EngineChoice::Auto)
+        {
+            return None;
+        }
+        if !self.fixed_strings || self.boundary != Some(BoundaryMode::Line) {
+            return None;
+        }
+        if self.multiline || self.crlf || self.null_data {
+            return None;
+        }
+        if self.replace.is_some() {
+            return None;
+        }
+        let case_insensitive = match self.case {
+            CaseMode::Sensitive => false,
+            // ASCII-only case folding is byte-wise, so folded hash set keys
+            // trivially agree with the regex engine. Unicode folding does
+            // not: the regex engine uses simple case folding, while any
+            // folding we could apply to a whole line here (e.g., via
+            // `str::to_lowercase`) is full case folding, and the two
+            // disagree on inputs like `ΑΣ` (final sigma) or `İ` (which
+            // lowercases to `i` plus a combining dot).
+            CaseMode::Insensitive if self.no_unicode => true,
+            _ => return None,
+        };
+        let patterns = &self.patterns.patterns;
+        if patterns.iter().any(|p| p.contains('\n') || p.contains('\0')) {
+            return None;
+        }
+        let m = crate::exactline::ExactLineMatcher::new(
+            patterns,
+            case_insensitive,
+        );
+        Some(PatternMatcher::ExactLine(m))
+    }
+
     /// Build a matcher using PCRE2.
     ///
     /// If there was a problem building the matcher (such as a regex syntax
@@ -508,6 +610,10 @@ impl HiArgs {
                 anyhow::bail!(suggest_text(suggest_multiline(err.to_string())))
             }
         };
+        log::debug!(
+            "regex optimization report: {:?}",
+            m.optimization_report()
+        );
         Ok(PatternMatcher::RustRegex(m))
     }
 
@@ -603,6 +709,7 @@ impl HiArgs {
             .byte_offset(self.byte_offset)
             .color_specs(self.colors.clone())
             .column(self.column)
+            .dedupe_lines(self.dedupe_lines)
             .heading(self.heading)
             .hyperlink(self.hyperlink_config.clone())
             .max_columns_preview(self.max_columns_preview)
@@ -693,6 +800,7 @@ impl HiArgs {
             .preprocessor(self.pre.clone())?
             .preprocessor_globs(self.pre_globs.clone())
             .search_zip(self.search_zip)
+            .retry(self.retry.clone())
             .binary_detection_explicit(self.binary.explicit.clone())
             .binary_detection_implicit(self.binary.implicit.clone());
         Ok(builder.build(matcher, searcher, printer))
@@ -893,7 +1001,11 @@ impl HiArgs {
             .git_ignore(!self.no_ignore_vcs)
             .git_exclude(!self.no_ignore_vcs && !self.no_ignore_exclude)
             .require_git(!self.no_require_git)
-            .ignore_case_insensitive(self.ignore_file_case_insensitive);
+            .ignore_case_insensitive(if self.ignore_file_case_insensitive {
+                ignore::IgnoreCaseMode::Always
+            } else {
+                ignore::IgnoreCaseMode::Auto
+            });
         if !self.no_ignore_dot {
             builder.add_custom_ignore_filename(".rgignore");
         }
@@ -958,6 +1070,12 @@ impl State {
 struct Patterns {
     /// The actual patterns to match.
     patterns: Vec<String>,
+    /// Whether the pattern came from the first positional argument. This is
+    /// used for diagnostic purposes. For example, when the first positional
+    /// argument names a file that exists, it's possible the caller swapped
+    /// the order of the pattern and file path arguments. But when patterns
+    /// come from -e/--regexp or -f/--file, no such mix-up is possible.
+    positional: bool,
 }
 
 impl Patterns {
@@ -980,7 +1098,7 @@ impl Patterns {
         // No search means no patterns. Even if -e/--regexp or -f/--file is
         // given, we know we won't use them so don't bother collecting them.
         if !matches!(low.mode, Mode::Search(_)) {
-            return Ok(Patterns { patterns: vec![] });
+            return Ok(Patterns { patterns: vec![], positional: false });
         }
         // If we got nothing from -e/--regexp and -f/--file, then the first
         // positional is a pattern.
@@ -993,7 +1111,7 @@ impl Patterns {
             let Ok(pat) = ospat.into_string() else {
                 anyhow::bail!("pattern given is not valid UTF-8")
             };
-            return Ok(Patterns { patterns: vec![pat] });
+            return Ok(Patterns { patterns: vec![pat], positional: true });
         }
         // Otherwise, we need to slurp up our patterns from -e/--regexp and
         // -f/--file. We de-duplicate as we go. If we don't de-duplicate,
@@ -1035,7 +1153,7 @@ impl Patterns {
                 }
             }
         }
-        Ok(Patterns { patterns })
+        Ok(Patterns { patterns, positional: false })
     }
 }
 
@@ -1445,12 +1563,23 @@ and look-around.",
 /// return a new error message suggesting the use of -U/--multiline.
 fn suggest_multiline(msg: String) -> String {
     if msg.contains("the literal") && msg.contains("not allowed") {
-        format!(
-            "{msg}
+        if msg.contains(r#""\r\n""#) {
+            format!(
+                "{msg}
+
+Consider using (?m)$ to match at the end of a line. With the --crlf flag, $
+will match just before a \\r\\n. Alternatively, enable multiline mode with the
+--multiline flag (or -U for short). When multiline mode is enabled, new line
+characters can be matched.",
+            )
+        } else {
+            format!(
+                "{msg}
 
 Consider enabling multiline mode with the --multiline flag (or -U for short).
 When multiline mode is enabled, new line characters can be matched.",
-        )
+            )
+        }
     } else {
         msg
     }
@@ -1469,3 +1598,127 @@ binary detection is enabled and matching a NUL byte is impossible.",
         msg
     }
 }
+
+/// Returns a hint when it looks like the pattern and file path arguments
+/// were swapped.
+///
+/// `pattern` must be the first positional argument, `paths` the file paths
+/// that were searched, `has_implicit_path` whether ripgrep guessed to search
+/// the current working directory and `matched` whether the search found
+/// anything at all.
+///
+/// The heuristic is deliberately conservative. A pattern that merely *looks*
+/// like a file path (e.g., `src/main.rs` when no such file exists) never
+/// produces a hint, since path-looking patterns are perfectly legitimate.
+fn swapped_arguments_hint(
+    pattern: &str,
+    paths: &[PathBuf],
+    has_implicit_path: bool,
+    matched: bool,
+) -> Option<String> {
+    // The linchpin of the heuristic: the pattern must name something that
+    // actually exists on the file system.
+    if !Path::new(pattern).exists() {
+        return None;
+    }
+    if has_implicit_path {
+        // No explicit paths were given, e.g., `rg src/main.rs`. The only
+        // evidence we can gather here is that nothing matched.
+        if matched {
+            return None;
+        }
+        return Some(format!(
+            "the pattern '{pattern}' names an existing file or directory \
+             and nothing matched it; if you meant to search inside of it, \
+             then a pattern must come first: rg <PATTERN> {pattern}",
+        ));
+    }
+    // With explicit paths, the strongest evidence of a swap is a path
+    // argument that doesn't exist: that's likely the intended pattern.
+    // Failing that, a search that found nothing is weaker evidence, but
+    // still worth a hint.
+    let missing = paths
+        .iter()
+        .find(|path| *path != Path::new("-") && !path.exists())
+        .or_else(|| if matched { None } else { paths.first() })?;
+    Some(format!(
+        "the pattern '{pattern}' names an existing file or directory while \
+         the path argument '{missing}' may be a pattern; if the arguments \
+         are swapped, then try: rg {missing} {pattern}",
+        missing = missing.display(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A guard for a temporary directory that deletes it on drop.
+    struct TempDir(PathBuf);
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Creates a fresh temporary directory containing a `main.rs` file and
+    /// a `src` directory.
+    fn tmpdir(name: &str) -> TempDir {
+        let dir = std::env::temp_dir()
+            .join(format!("rg-hiargs-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("main.rs"), "fn main() {}\n").unwrap();
+        TempDir(dir)
+    }
+
+    #[test]
+    fn swapped_hint_fires_for_missing_path() {
+        let tmp = tmpdir("missing-path");
+        let pattern = tmp.0.join("main.rs").display().to_string();
+        let paths = vec![PathBuf::from("somepattern")];
+        let hint = swapped_arguments_hint(&pattern, &paths, false, false);
+        let hint = hint.unwrap();
+        assert!(hint.contains(&format!("rg somepattern {pattern}")), "{hint}");
+        // A swap of an existing directory works too.
+        let pattern = tmp.0.join("src").display().to_string();
+        let hint = swapped_arguments_hint(&pattern, &paths, false, false);
+        assert!(hint.is_some());
+    }
+
+    #[test]
+    fn swapped_hint_fires_when_nothing_matched() {
+        let tmp = tmpdir("nothing-matched");
+        let pattern = tmp.0.join("main.rs").display().to_string();
+        // All paths exist, but the search came up empty.
+        let paths = vec![tmp.0.join("src")];
+        let hint = swapped_arguments_hint(&pattern, &paths, false, false);
+        assert!(hint.is_some());
+        // ... and with no explicit paths at all.
+        let hint = swapped_arguments_hint(&pattern, &[], true, false);
+        let hint = hint.unwrap();
+        assert!(hint.contains(&format!("rg <PATTERN> {pattern}")), "{hint}");
+    }
+
+    #[test]
+    fn swapped_hint_quiet_when_pattern_does_not_exist() {
+        let tmp = tmpdir("pattern-missing");
+        // Path-looking, but names nothing that exists.
+        let pattern = tmp.0.join("nope/does-not-exist.rs").display().to_string();
+        let paths = vec![PathBuf::from("somepattern")];
+        assert!(swapped_arguments_hint(&pattern, &paths, false, false).is_none());
+    }
+
+    #[test]
+    fn swapped_hint_quiet_when_everything_checks_out() {
+        let tmp = tmpdir("all-good");
+        let pattern = tmp.0.join("main.rs").display().to_string();
+        // Paths exist and something matched. No hint.
+        let paths = vec![tmp.0.join("src")];
+        assert!(swapped_arguments_hint(&pattern, &paths, false, true).is_none());
+        // Stdin is never treated as a missing path.
+        let paths = vec![PathBuf::from("-")];
+        assert!(swapped_arguments_hint(&pattern, &paths, false, true).is_none());
+    }
+}
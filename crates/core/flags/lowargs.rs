@@ -39,6 +39,10 @@ pub(crate) struct LowArgs {
     // Everything else, sorted lexicographically.
     pub(crate) binary: BinaryMode,
     pub(crate) boundary: Option<BoundaryMode>,
+    /// Set when both -w/--word-regexp and -x/--line-regexp were given. The
+    /// line boundary mode wins, but we remember the conflict so that a
+    /// warning can be shown after parsing completes.
+    pub(crate) boundary_conflict: bool,
     pub(crate) buffer: BufferMode,
     pub(crate) byte_offset: bool,
     pub(crate) case: CaseMode,
@@ -48,6 +52,8 @@ pub(crate) struct LowArgs {
     pub(crate) context: ContextMode,
     pub(crate) context_separator: ContextSeparator,
     pub(crate) crlf: bool,
+    pub(crate) dedupe_lines: bool,
+    pub(crate) dedupe_links: bool,
     pub(crate) dfa_size_limit: Option<usize>,
     pub(crate) encoding: EncodingMode,
     pub(crate) engine: EngineChoice,
@@ -97,6 +103,7 @@ pub(crate) struct LowArgs {
     pub(crate) quiet: bool,
     pub(crate) regex_size_limit: Option<usize>,
     pub(crate) replace: Option<BString>,
+    pub(crate) retry: Option<RetryPolicy>,
     pub(crate) search_zip: bool,
     pub(crate) sort: Option<SortMode>,
     pub(crate) stats: bool,
@@ -226,6 +233,10 @@ pub(crate) enum GenerateMode {
     CompleteFish,
     /// Completions for PowerShell.
     CompletePowerShell,
+    /// Completions for Nushell.
+    CompleteNushell,
+    /// Completions for elvish.
+    CompleteElvish,
 }
 
 /// Indicates how ripgrep should treat binary data.
@@ -684,6 +695,46 @@ pub(crate) enum PatternSource {
     File(PathBuf),
 }
 
+/// The retry policy, as parsed from the `--retry` flag.
+///
+/// The flag value format is `N[:DELAY_MS]`, where `N` is the maximum number
+/// of retries for a single haystack and `DELAY_MS` is the number of
+/// milliseconds to sleep between attempts. When the delay is omitted, it
+/// defaults to 100 milliseconds.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct RetryPolicy {
+    /// The maximum number of retries for a single haystack.
+    pub(crate) limit: u64,
+    /// How long to sleep between attempts.
+    pub(crate) delay: std::time::Duration,
+}
+
+impl std::str::FromStr for RetryPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<RetryPolicy> {
+        let (limit, delay) = match s.split_once(':') {
+            None => (s, None),
+            Some((limit, delay)) => (limit, Some(delay)),
+        };
+        let limit = limit.parse::<u64>().map_err(|err| {
+            anyhow::anyhow!("failed to parse retry count from {s:?}: {err}")
+        })?;
+        let delay = match delay {
+            None => std::time::Duration::from_millis(100),
+            Some(delay) => {
+                let millis = delay.parse::<u64>().map_err(|err| {
+                    anyhow::anyhow!(
+                        "failed to parse retry delay from {s:?}: {err}"
+                    )
+                })?;
+                std::time::Duration::from_millis(millis)
+            }
+        };
+        Ok(RetryPolicy { limit, delay })
+    }
+}
+
 /// The sort criteria, if present.
 #[derive(Debug, Eq, PartialEq)]
 pub(crate) struct SortMode {
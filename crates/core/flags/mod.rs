@@ -18,7 +18,9 @@ use std::{
 pub(crate) use crate::flags::{
     complete::{
         bash::generate as generate_complete_bash,
+        elvish::generate as generate_complete_elvish,
         fish::generate as generate_complete_fish,
+        nushell::generate as generate_complete_nushell,
         powershell::generate as generate_complete_powershell,
         zsh::generate as generate_complete_zsh,
     },
@@ -35,7 +37,7 @@ pub(crate) use crate::flags::{
         },
     },
     hiargs::HiArgs,
-    lowargs::{GenerateMode, Mode, SearchMode, SpecialMode},
+    lowargs::{GenerateMode, Mode, RetryPolicy, SearchMode, SpecialMode},
     parse::{parse, ParseResult},
 };
 
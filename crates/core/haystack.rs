@@ -9,18 +9,25 @@ Effectively, a haystack wraps a directory entry and adds some light application
 level logic around it.
 */
 
-use std::path::Path;
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
 
 /// A builder for constructing things to search over.
 #[derive(Clone, Debug)]
 pub(crate) struct HaystackBuilder {
     strip_dot_prefix: bool,
+    dedupe: Option<Arc<DedupeTracker>>,
 }
 
 impl HaystackBuilder {
     /// Return a new haystack builder with a default configuration.
     pub(crate) fn new() -> HaystackBuilder {
-        HaystackBuilder { strip_dot_prefix: false }
+        HaystackBuilder { strip_dot_prefix: false, dedupe: None }
     }
 
     /// Create a new haystack from a possibly missing directory entry.
@@ -54,14 +61,14 @@ impl HaystackBuilder {
         // If this entry was explicitly provided by an end user, then we always
         // want to search it.
         if hay.is_explicit() {
-            return Some(hay);
+            return self.dedupe(hay);
         }
         // At this point, we only want to search something if it's explicitly a
         // file. This omits symlinks. (If ripgrep was configured to follow
         // symlinks, then they have already been followed by the directory
         // traversal.)
         if hay.is_file() {
-            return Some(hay);
+            return self.dedupe(hay);
         }
         // We got nothing. Emit a debug message, but only if this isn't a
         // directory. Otherwise, emitting messages for directories is just
@@ -89,6 +96,101 @@ impl HaystackBuilder {
         self.strip_dot_prefix = yes;
         self
     }
+
+    /// When enabled, a file whose identity has already been seen by this
+    /// builder (including through any of its clones) is skipped.
+    ///
+    /// This prevents searching the same file more than once when it is
+    /// reachable via multiple paths, e.g., through hard links or overlapping
+    /// search roots. File identity is determined by device and inode numbers
+    /// and is currently only available on Unix; on other platforms this is a
+    /// no-op.
+    pub(crate) fn dedupe_links(&mut self, yes: bool) -> &mut HaystackBuilder {
+        self.dedupe =
+            if yes { Some(Arc::new(DedupeTracker::default())) } else { None };
+        self
+    }
+
+    /// Returns the number of haystacks that were skipped because their file
+    /// identity had already been seen.
+    pub(crate) fn dedupe_skipped(&self) -> u64 {
+        self.dedupe
+            .as_ref()
+            .map_or(0, |tracker| tracker.skipped.load(Ordering::Relaxed))
+    }
+
+    /// Pass the given haystack through the duplicate file tracker, if one
+    /// was enabled.
+    ///
+    /// This returns `None` if the haystack's file identity has been seen
+    /// before, and records it otherwise.
+    fn dedupe(&self, hay: Haystack) -> Option<Haystack> {
+        let Some(ref tracker) = self.dedupe else { return Some(hay) };
+        // stdin has no file identity (and can't be searched twice anyway).
+        if hay.is_stdin() {
+            return Some(hay);
+        }
+        if tracker.insert(&hay.dent) {
+            Some(hay)
+        } else {
+            log::debug!(
+                "ignoring {}: already searched via another path \
+                 (hard link or overlapping search roots)",
+                hay.dent.path().display(),
+            );
+            None
+        }
+    }
+}
+
+/// Tracks the identity of every file seen across all clones of a haystack
+/// builder.
+///
+/// This is used to implement the `--dedupe-links` flag. The tracker is
+/// shared between the worker threads of a parallel search, so its state is
+/// synchronized internally.
+#[derive(Debug, Default)]
+struct DedupeTracker {
+    /// The identities seen so far.
+    seen: Mutex<std::collections::HashSet<(u64, u64)>>,
+    /// The number of files skipped because their identity was seen before.
+    skipped: AtomicU64,
+}
+
+impl DedupeTracker {
+    /// Record the identity of the given directory entry.
+    ///
+    /// This returns true if the entry should be searched, i.e., its identity
+    /// has not been seen before or could not be determined.
+    fn insert(&self, dent: &ignore::DirEntry) -> bool {
+        let Some(id) = file_id(dent) else { return true };
+        if self.seen.lock().unwrap().insert(id) {
+            true
+        } else {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+}
+
+/// Returns the identity of the file behind the given directory entry, if one
+/// can be determined.
+#[cfg(unix)]
+fn file_id(dent: &ignore::DirEntry) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let md = dent.metadata().ok()?;
+    Some((md.dev(), md.ino()))
+}
+
+/// Returns the identity of the file behind the given directory entry, if one
+/// can be determined.
+///
+/// The standard library doesn't expose a stable file identity on non-Unix
+/// platforms, so no identity is reported and deduplication is a no-op.
+#[cfg(not(unix))]
+fn file_id(_dent: &ignore::DirEntry) -> Option<(u64, u64)> {
+    None
 }
 
 /// A haystack is a thing we want to search.
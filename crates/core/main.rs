@@ -11,6 +11,7 @@ use crate::flags::{HiArgs, SearchMode};
 #[macro_use]
 mod messages;
 
+mod exactline;
 mod flags;
 mod haystack;
 mod logger;
@@ -91,6 +92,11 @@ fn run(result: crate::flags::ParseResult<HiArgs>) -> anyhow::Result<ExitCode> {
         Mode::Types => return types(&args),
         Mode::Generate(mode) => return generate(mode),
     };
+    if matches!(args.mode(), Mode::Search(_)) {
+        if let Some(hint) = args.swapped_arguments_hint(matched) {
+            message!("{hint}");
+        }
+    }
     Ok(if matched && (args.quiet() || !messages::errored()) {
         ExitCode::from(0)
     } else if messages::errored() {
@@ -116,6 +122,8 @@ fn search(args: &HiArgs, mode: SearchMode) -> anyhow::Result<bool> {
     let mut matched = false;
     let mut searched = false;
     let mut stats = args.stats();
+    let mut matched_dirs =
+        stats.as_ref().map(|_| crate::search::MatchedDirs::new());
     let mut searcher = args.search_worker(
         args.matcher()?,
         args.searcher()?,
@@ -135,6 +143,10 @@ fn search(args: &HiArgs, mode: SearchMode) -> anyhow::Result<bool> {
         matched = matched || search_result.has_match();
         if let Some(ref mut stats) = stats {
             *stats += search_result.stats().unwrap();
+            if search_result.has_match() {
+                let dirs = matched_dirs.as_mut().unwrap();
+                dirs.add_file_path(haystack.path());
+            }
         }
         if matched && args.quit_after_match() {
             break;
@@ -143,9 +155,11 @@ fn search(args: &HiArgs, mode: SearchMode) -> anyhow::Result<bool> {
     if args.has_implicit_path() && !searched {
         eprint_nothing_searched();
     }
-    if let Some(ref stats) = stats {
+    if let Some(ref mut stats) = stats {
+        stats.add_duplicates_skipped(haystack_builder.dedupe_skipped());
+        let matched_dirs = matched_dirs.as_ref().unwrap();
         let wtr = searcher.printer().get_mut();
-        let _ = print_stats(mode, stats, started_at, wtr);
+        let _ = print_stats(mode, stats, matched_dirs, started_at, wtr);
     }
     Ok(matched)
 }
@@ -163,7 +177,10 @@ fn search_parallel(args: &HiArgs, mode: SearchMode) -> anyhow::Result<bool> {
     let started_at = std::time::Instant::now();
     let haystack_builder = args.haystack_builder();
     let bufwtr = args.buffer_writer();
-    let stats = args.stats().map(std::sync::Mutex::new);
+    let stats = args
+        .stats()
+        .map(|stats| (stats, crate::search::MatchedDirs::new()))
+        .map(std::sync::Mutex::new);
     let matched = AtomicBool::new(false);
     let searched = AtomicBool::new(false);
 
@@ -198,8 +215,12 @@ fn search_parallel(args: &HiArgs, mode: SearchMode) -> anyhow::Result<bool> {
                 matched.store(true, Ordering::SeqCst);
             }
             if let Some(ref locked_stats) = *stats {
-                let mut stats = locked_stats.lock().unwrap();
+                let mut guard = locked_stats.lock().unwrap();
+                let (ref mut stats, ref mut matched_dirs) = *guard;
                 *stats += search_result.stats().unwrap();
+                if search_result.has_match() {
+                    matched_dirs.add_file_path(haystack.path());
+                }
             }
             if let Err(err) = bufwtr.print(searcher.printer().get_mut()) {
                 // A broken pipe means graceful termination.
@@ -220,9 +241,11 @@ fn search_parallel(args: &HiArgs, mode: SearchMode) -> anyhow::Result<bool> {
         eprint_nothing_searched();
     }
     if let Some(ref locked_stats) = stats {
-        let stats = locked_stats.lock().unwrap();
+        let mut guard = locked_stats.lock().unwrap();
+        guard.0.add_duplicates_skipped(haystack_builder.dedupe_skipped());
+        let (ref stats, ref matched_dirs) = *guard;
         let mut wtr = searcher.printer().get_mut();
-        let _ = print_stats(mode, &stats, started_at, &mut wtr);
+        let _ = print_stats(mode, stats, matched_dirs, started_at, &mut wtr);
         let _ = bufwtr.print(&mut wtr);
     }
     Ok(matched.load(Ordering::SeqCst))
@@ -363,6 +386,8 @@ fn generate(mode: crate::flags::GenerateMode) -> anyhow::Result<ExitCode> {
         GenerateMode::CompletePowerShell => {
             flags::generate_complete_powershell()
         }
+        GenerateMode::CompleteNushell => flags::generate_complete_nushell(),
+        GenerateMode::CompleteElvish => flags::generate_complete_elvish(),
     };
     writeln!(std::io::stdout(), "{}", output.trim_end())?;
     Ok(ExitCode::from(0))
@@ -434,6 +459,7 @@ fn eprint_nothing_searched() {
 fn print_stats<W: Write>(
     mode: SearchMode,
     stats: &grep::printer::Stats,
+    matched_dirs: &crate::search::MatchedDirs,
     started: std::time::Instant,
     mut wtr: W,
 ) -> std::io::Result<()> {
@@ -448,6 +474,7 @@ fn print_stats<W: Write>(
                 "type": "summary",
                 "data": {
                     "stats": stats,
+                    "matched_directories": matched_dirs.count(),
                     "elapsed_total": {
                         "secs": elapsed.as_secs(),
                         "nanos": elapsed.subsec_nanos(),
@@ -458,26 +485,28 @@ fn print_stats<W: Write>(
         )?;
         write!(wtr, "\n")
     } else {
+        write!(wtr, "\n")?;
+        grep::printer::write_stats(
+            termcolor::NoColor::new(&mut wtr),
+            stats,
+            &grep::printer::StatsRenderOptions::new(),
+        )?;
         write!(
             wtr,
-            "
-{matches} matches
-{lines} matched lines
-{searches_with_match} files contained matches
-{searches} files searched
-{bytes_printed} bytes printed
-{bytes_searched} bytes searched
-{search_time:0.6} seconds spent searching
+            "{matched_directories} directories contained matches
 {process_time:0.6} seconds
 ",
-            matches = stats.matches(),
-            lines = stats.matched_lines(),
-            searches_with_match = stats.searches_with_match(),
-            searches = stats.searches(),
-            bytes_printed = stats.bytes_printed(),
-            bytes_searched = stats.bytes_searched(),
-            search_time = stats.elapsed().as_secs_f64(),
+            matched_directories = matched_dirs.count(),
             process_time = elapsed.as_secs_f64(),
-        )
+        )?;
+        // Per-pattern counts are only recorded when a printer was asked to
+        // attribute matches to individual patterns.
+        if !stats.per_pattern().is_empty() {
+            write!(wtr, "\nmatches per pattern\n")?;
+            for (index, count) in stats.per_pattern().iter().enumerate() {
+                write!(wtr, "{index}: {count}\n")?;
+            }
+        }
+        Ok(())
     }
 }
@@ -22,6 +22,7 @@ struct Config {
     search_zip: bool,
     binary_implicit: grep::searcher::BinaryDetection,
     binary_explicit: grep::searcher::BinaryDetection,
+    retry: Option<crate::flags::RetryPolicy>,
 }
 
 impl Default for Config {
@@ -32,6 +33,7 @@ impl Default for Config {
             search_zip: false,
             binary_implicit: grep::searcher::BinaryDetection::none(),
             binary_explicit: grep::searcher::BinaryDetection::none(),
+            retry: None,
         }
     }
 }
@@ -147,6 +149,23 @@ impl SearchWorkerBuilder {
         self
     }
 
+    /// Set the policy for retrying transient I/O errors.
+    ///
+    /// When set, a search that fails with an error that looks transient
+    /// (e.g., a generic I/O error or a timeout) is re-executed, up to the
+    /// limit given by the policy, with a sleep between attempts. Errors that
+    /// can't plausibly succeed on a retry (such as a file not existing) are
+    /// reported immediately.
+    ///
+    /// By default, no retrying is performed.
+    pub(crate) fn retry(
+        &mut self,
+        policy: Option<crate::flags::RetryPolicy>,
+    ) -> &mut SearchWorkerBuilder {
+        self.config.retry = policy;
+        self
+    }
+
     /// Set the binary detection that should be used when searching files
     /// explicitly supplied by an end user.
     ///
@@ -195,6 +214,9 @@ impl SearchResult {
 #[derive(Clone, Debug)]
 pub(crate) enum PatternMatcher {
     RustRegex(grep::regex::RegexMatcher),
+    /// A hash set based matcher used when `-x -F` makes exact whole-line
+    /// matching possible. See `crate::exactline`.
+    ExactLine(crate::exactline::ExactLineMatcher),
     #[cfg(feature = "pcre2")]
     PCRE2(grep::pcre2::RegexMatcher),
 }
@@ -244,6 +266,27 @@ impl<W: WriteColor> SearchWorker<W> {
     pub(crate) fn search(
         &mut self,
         haystack: &crate::haystack::Haystack,
+    ) -> io::Result<SearchResult> {
+        let Some(policy) = self.config.retry.clone() else {
+            return self.search_impl(haystack);
+        };
+        let (result, retries) = retry(
+            &policy,
+            is_transient_error,
+            |duration| std::thread::sleep(duration),
+            || self.search_impl(haystack),
+        );
+        let mut result = result?;
+        if let Some(ref mut stats) = result.stats {
+            stats.add_retries(retries);
+        }
+        Ok(result)
+    }
+
+    /// The implementation of a single search attempt over the given haystack.
+    fn search_impl(
+        &mut self,
+        haystack: &crate::haystack::Haystack,
     ) -> io::Result<SearchResult> {
         let bin = if haystack.is_explicit() {
             self.config.binary_explicit.clone()
@@ -254,6 +297,7 @@ impl<W: WriteColor> SearchWorker<W> {
         log::trace!("{}: binary detection: {:?}", path.display(), bin);
 
         self.searcher.set_binary_detection(bin);
+        log_config_summary(&self.searcher);
         if haystack.is_stdin() {
             self.search_reader(path, &mut io::stdin().lock())
         } else if self.should_preprocess(path) {
@@ -343,6 +387,7 @@ impl<W: WriteColor> SearchWorker<W> {
         let (searcher, printer) = (&mut self.searcher, &mut self.printer);
         match self.matcher {
             RustRegex(ref m) => search_path(m, searcher, printer, path),
+            ExactLine(ref m) => search_path(m, searcher, printer, path),
             #[cfg(feature = "pcre2")]
             PCRE2(ref m) => search_path(m, searcher, printer, path),
         }
@@ -367,6 +412,9 @@ impl<W: WriteColor> SearchWorker<W> {
         let (searcher, printer) = (&mut self.searcher, &mut self.printer);
         match self.matcher {
             RustRegex(ref m) => search_reader(m, searcher, printer, path, rdr),
+            ExactLine(ref m) => {
+                search_reader(m, searcher, printer, path, rdr)
+            }
             #[cfg(feature = "pcre2")]
             PCRE2(ref m) => search_reader(m, searcher, printer, path, rdr),
         }
@@ -409,6 +457,70 @@ fn search_path<M: Matcher, W: WriteColor>(
     }
 }
 
+/// Execute the given operation, retrying errors classified as transient.
+///
+/// The operation is attempted up to `1 + policy.limit` times, sleeping (via
+/// the sleeper given) for `policy.delay` between attempts. An error is only
+/// retried when the given classifier returns true for it. The result of the
+/// final attempt is returned, along with the number of retries performed.
+///
+/// The classifier and sleeper are parameters principally so that tests can
+/// run deterministically without depending on real I/O errors or on real
+/// clock time.
+fn retry<T>(
+    policy: &crate::flags::RetryPolicy,
+    classify: impl Fn(&io::Error) -> bool,
+    mut sleep: impl FnMut(std::time::Duration),
+    mut op: impl FnMut() -> io::Result<T>,
+) -> (io::Result<T>, u64) {
+    let mut retries = 0;
+    loop {
+        match op() {
+            Ok(t) => return (Ok(t), retries),
+            Err(err) => {
+                if retries >= policy.limit || !classify(&err) {
+                    return (Err(err), retries);
+                }
+                log::debug!(
+                    "retrying after transient error \
+                     (attempt {attempt} of {limit}): {err}",
+                    attempt = retries + 1,
+                    limit = policy.limit,
+                );
+                retries += 1;
+                sleep(policy.delay);
+            }
+        }
+    }
+}
+
+/// Returns true if and only if the given error is plausibly transient.
+///
+/// A transient error is one where an immediate retry of the exact same
+/// operation could succeed, as seen on flaky network file systems. Errors
+/// that reflect a durable state, like a file not existing or a permission
+/// failure, are never considered transient.
+fn is_transient_error(err: &io::Error) -> bool {
+    use std::io::ErrorKind::*;
+
+    match err.kind() {
+        TimedOut | WouldBlock | Interrupted => true,
+        NotFound | PermissionDenied => false,
+        _ => {
+            // An I/O error (EIO on Unix) has no stable `ErrorKind`, so look
+            // at the raw OS error code. EIO is 5 on every Unix we support.
+            #[cfg(unix)]
+            {
+                err.raw_os_error() == Some(5)
+            }
+            #[cfg(not(unix))]
+            {
+                false
+            }
+        }
+    }
+}
+
 /// Search the contents of the given reader using the given matcher, searcher
 /// and printer.
 fn search_reader<M: Matcher, R: io::Read, W: WriteColor>(
@@ -445,3 +557,310 @@ fn search_reader<M: Matcher, R: io::Read, W: WriteColor>(
         }
     }
 }
+
+/// Log the searcher's effective configuration at the debug level.
+///
+/// To keep `--debug` output readable, each distinct configuration is only
+/// logged the first time it is observed during the lifetime of the process.
+/// In practice, distinct configurations arise from the binary detection
+/// strategy, which varies based on whether a haystack was explicitly given
+/// on the command line or not.
+fn log_config_summary(searcher: &grep::searcher::Searcher) {
+    use std::{
+        collections::HashSet,
+        sync::{Mutex, OnceLock},
+    };
+
+    if !log::log_enabled!(log::Level::Debug) {
+        return;
+    }
+    static LOGGED: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+    let summary = format!("{:?}", searcher.config_summary());
+    let mut logged =
+        LOGGED.get_or_init(|| Mutex::new(HashSet::new())).lock().unwrap();
+    if logged.insert(summary.clone()) {
+        log::debug!("searcher configuration: {summary}");
+    }
+}
+
+/// The maximum number of distinct directories counted exactly by
+/// `MatchedDirs`.
+///
+/// Beyond this many directories, `MatchedDirs` degrades to an approximate
+/// count with bounded memory. The cap corresponds to about half a megabyte
+/// of directory path hashes, which is big enough that essentially all
+/// real-world searches get an exact count.
+const MATCHED_DIRS_EXACT_LIMIT: usize = 1 << 16;
+
+/// A counter for the number of distinct directories containing matches.
+///
+/// Directories are never stored directly. Instead, a 64-bit hash of each
+/// directory path is kept in a set, so memory use is independent of path
+/// lengths. If the number of distinct directories exceeds
+/// `MATCHED_DIRS_EXACT_LIMIT`, then the set is dropped and counting degrades
+/// to a small HyperLogLog sketch fed with the same hashes.
+#[derive(Debug)]
+pub(crate) struct MatchedDirs(MatchedDirsInner);
+
+#[derive(Debug)]
+enum MatchedDirsInner {
+    Exact(std::collections::HashSet<u64>),
+    Approximate(HyperLogLog),
+}
+
+impl MatchedDirs {
+    /// Create a new counter with no directories in it.
+    pub(crate) fn new() -> MatchedDirs {
+        MatchedDirs(MatchedDirsInner::Exact(std::collections::HashSet::new()))
+    }
+
+    /// Record the parent directory of the matched file path given.
+    pub(crate) fn add_file_path(&mut self, path: &Path) {
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let hash = {
+            use std::hash::{Hash, Hasher};
+            // The default hasher is SipHash with fixed keys, so hashes are
+            // consistent for the lifetime of this process. That's all we
+            // need, since the hashes are never persisted anywhere.
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            dir.hash(&mut hasher);
+            hasher.finish()
+        };
+        match self.0 {
+            MatchedDirsInner::Exact(ref mut set) => {
+                set.insert(hash);
+                if set.len() > MATCHED_DIRS_EXACT_LIMIT {
+                    let mut sketch = HyperLogLog::new();
+                    for &hash in set.iter() {
+                        sketch.add_hash(hash);
+                    }
+                    self.0 = MatchedDirsInner::Approximate(sketch);
+                }
+            }
+            MatchedDirsInner::Approximate(ref mut sketch) => {
+                sketch.add_hash(hash);
+            }
+        }
+    }
+
+    /// Return the number of distinct directories recorded.
+    ///
+    /// The count is exact unless more than `MATCHED_DIRS_EXACT_LIMIT`
+    /// distinct directories have been recorded, in which case it is an
+    /// estimate.
+    pub(crate) fn count(&self) -> u64 {
+        match self.0 {
+            MatchedDirsInner::Exact(ref set) => {
+                u64::try_from(set.len()).unwrap()
+            }
+            MatchedDirsInner::Approximate(ref sketch) => sketch.count(),
+        }
+    }
+}
+
+/// A small HyperLogLog sketch for approximate count-distinct over 64-bit
+/// hashes.
+///
+/// This uses 2^12 one-byte registers, i.e., 4KB of memory, which gives a
+/// relative error of about 1.6%. That's plenty for reporting statistics
+/// about searches too big for `MatchedDirs` to count exactly.
+#[derive(Debug)]
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    /// The number of bits of each hash used to select a register.
+    const PRECISION: u32 = 12;
+
+    /// Create a new empty sketch.
+    fn new() -> HyperLogLog {
+        HyperLogLog { registers: vec![0; 1 << HyperLogLog::PRECISION] }
+    }
+
+    /// Add a single hash to this sketch.
+    ///
+    /// The hashes given must be uniformly distributed for the estimate to be
+    /// any good. Adding the same hash more than once has no effect.
+    fn add_hash(&mut self, hash: u64) {
+        // The top PRECISION bits pick the register and the rank is the
+        // position of the leftmost 1-bit among the remaining bits.
+        let index = (hash >> (64 - HyperLogLog::PRECISION)) as usize;
+        let rest = hash << HyperLogLog::PRECISION;
+        let rank = u8::try_from(rest.leading_zeros() + 1).unwrap();
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// Return an estimate of the number of distinct hashes added.
+    fn count(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let sum: f64 =
+            self.registers.iter().map(|&r| 2f64.powi(-i32::from(r))).sum();
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let raw = alpha * m * m / sum;
+        // Small range correction via linear counting, as prescribed by the
+        // HyperLogLog paper.
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        let estimate = if raw <= 2.5 * m && zeros > 0 {
+            m * (m / (zeros as f64)).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// A reader that fails with the given error kind some number of times
+    /// before producing its contents. This simulates, e.g., a flaky network
+    /// file system that produces sporadic I/O errors.
+    struct FlakyReader {
+        fails_remaining: u64,
+        kind: io::ErrorKind,
+        contents: &'static [u8],
+        pos: usize,
+    }
+
+    impl FlakyReader {
+        fn new(
+            fails_remaining: u64,
+            kind: io::ErrorKind,
+            contents: &'static [u8],
+        ) -> FlakyReader {
+            FlakyReader { fails_remaining, kind, contents, pos: 0 }
+        }
+    }
+
+    impl io::Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.fails_remaining > 0 {
+                self.fails_remaining -= 1;
+                return Err(io::Error::from(self.kind));
+            }
+            let n = (&self.contents[self.pos..]).read(buf)?;
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    fn policy(limit: u64, delay_ms: u64) -> crate::flags::RetryPolicy {
+        crate::flags::RetryPolicy {
+            limit,
+            delay: Duration::from_millis(delay_ms),
+        }
+    }
+
+    #[test]
+    fn retry_transient_then_succeed() {
+        use std::io::Read;
+
+        let mut rdr = FlakyReader::new(2, io::ErrorKind::TimedOut, b"ok");
+        let mut slept = vec![];
+        let (result, retries) = retry(
+            &policy(3, 10),
+            is_transient_error,
+            |duration| slept.push(duration),
+            || {
+                let mut buf = Vec::new();
+                rdr.read_to_end(&mut buf)?;
+                Ok(buf)
+            },
+        );
+        assert_eq!(b"ok".to_vec(), result.unwrap());
+        assert_eq!(2, retries);
+        assert_eq!(vec![Duration::from_millis(10); 2], slept);
+    }
+
+    #[test]
+    fn retry_never_for_non_transient() {
+        use std::io::Read;
+
+        let mut rdr = FlakyReader::new(1, io::ErrorKind::NotFound, b"ok");
+        let mut slept = vec![];
+        let (result, retries) = retry(
+            &policy(3, 10),
+            is_transient_error,
+            |duration| slept.push(duration),
+            || {
+                let mut buf = Vec::new();
+                rdr.read_to_end(&mut buf)?;
+                Ok(buf)
+            },
+        );
+        assert_eq!(
+            io::ErrorKind::NotFound,
+            result.unwrap_err().kind(),
+        );
+        assert_eq!(0, retries);
+        assert!(slept.is_empty());
+    }
+
+    #[test]
+    fn retry_limit_exhausted() {
+        let (result, retries) = retry(
+            &policy(2, 10),
+            is_transient_error,
+            |_| {},
+            || -> io::Result<()> {
+                Err(io::Error::from(io::ErrorKind::TimedOut))
+            },
+        );
+        assert_eq!(io::ErrorKind::TimedOut, result.unwrap_err().kind());
+        assert_eq!(2, retries);
+    }
+
+    #[test]
+    fn transient_classification() {
+        assert!(is_transient_error(&io::Error::from(
+            io::ErrorKind::TimedOut
+        )));
+        assert!(is_transient_error(&io::Error::from(
+            io::ErrorKind::Interrupted
+        )));
+        assert!(!is_transient_error(&io::Error::from(
+            io::ErrorKind::NotFound
+        )));
+        assert!(!is_transient_error(&io::Error::from(
+            io::ErrorKind::PermissionDenied
+        )));
+        #[cfg(unix)]
+        assert!(is_transient_error(&io::Error::from_raw_os_error(5)));
+    }
+
+    #[test]
+    fn matched_dirs_exact() {
+        let mut dirs = MatchedDirs::new();
+        dirs.add_file_path(Path::new("a/one.txt"));
+        dirs.add_file_path(Path::new("a/two.txt"));
+        dirs.add_file_path(Path::new("a/b/three.txt"));
+        dirs.add_file_path(Path::new("four.txt"));
+        assert_eq!(3, dirs.count());
+    }
+
+    #[test]
+    fn hyperloglog_estimate() {
+        use std::hash::{Hash, Hasher};
+
+        let mut sketch = HyperLogLog::new();
+        for i in 0u64..100_000 {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            i.hash(&mut hasher);
+            sketch.add_hash(hasher.finish());
+        }
+        let got = sketch.count();
+        // The sketch's relative error is about 1.6%, so 5% gives us plenty
+        // of headroom against an unlucky choice of hashes.
+        assert!(
+            (95_000..=105_000).contains(&got),
+            "estimate {} too far from 100000",
+            got
+        );
+    }
+}
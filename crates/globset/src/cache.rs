@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use regex_automata::meta::Regex;
+
+use crate::{new_regex, new_regex_set, Error};
+
+/// The number of shards in a cache. Sharding reduces contention when many
+/// builders use the same cache concurrently.
+const SHARDS: usize = 8;
+
+/// A cache of compiled regexes that can be shared across [`GlobSet`] builds.
+///
+/// Building a `GlobSet` converts some of its globs to regexes and compiles
+/// them. When the same globs are used to rebuild sets repeatedly (e.g., when
+/// a set is assembled from overlapping configuration fragments), that
+/// compilation work is redundant. A `GlobCompileCache` remembers compiled
+/// regexes keyed by the exact patterns they were built from, so subsequent
+/// builds reuse them.
+///
+/// Use [`GlobSetBuilder::with_cache`](crate::GlobSetBuilder::with_cache) to
+/// attach a cache to a builder. A cache is safe to share between concurrent
+/// builders. The cache is bounded: when it is full, the least recently used
+/// entry is evicted.
+///
+/// This is purely a build-time optimization. A `GlobSet` built with a cache
+/// matches exactly the same file paths as one built without it.
+///
+/// [`GlobSet`]: crate::GlobSet
+#[derive(Debug)]
+pub struct GlobCompileCache {
+    shards: Vec<Mutex<Shard>>,
+    shard_capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Shard {
+    map: HashMap<String, Entry>,
+    tick: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    regex: Regex,
+    last_used: u64,
+}
+
+impl GlobCompileCache {
+    /// Create a new cache that holds at most `max_entries` compiled regexes.
+    ///
+    /// `max_entries` is rounded up to a small minimum so that every shard of
+    /// the cache can hold at least one entry.
+    pub fn new(max_entries: usize) -> GlobCompileCache {
+        let max_entries = std::cmp::max(max_entries, SHARDS);
+        GlobCompileCache {
+            shards: (0..SHARDS).map(|_| Mutex::new(Shard::default())).collect(),
+            shard_capacity: max_entries / SHARDS,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the number of cache lookups that found an existing entry.
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of cache lookups that had to compile a regex.
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of compiled regexes currently in the cache.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().map.len()).sum()
+    }
+
+    /// Like `new_regex`, but consults the cache first.
+    pub(crate) fn regex(&self, pattern: &str) -> Result<Regex, Error> {
+        let key = format!("single:{}", pattern);
+        self.get_or_compile(key, || new_regex(pattern))
+    }
+
+    /// Like `new_regex_set`, but consults the cache first. The key is the
+    /// exact sequence of patterns, so a set is only reused when it is built
+    /// from identical patterns in identical order.
+    pub(crate) fn regex_set(
+        &self,
+        patterns: Vec<String>,
+    ) -> Result<Regex, Error> {
+        let mut key = String::from("set:");
+        for pattern in patterns.iter() {
+            key.push_str(pattern);
+            // Patterns never contain NUL bytes, so this makes the key
+            // unambiguous.
+            key.push('\0');
+        }
+        self.get_or_compile(key, move || new_regex_set(patterns))
+    }
+
+    fn get_or_compile(
+        &self,
+        key: String,
+        compile: impl FnOnce() -> Result<Regex, Error>,
+    ) -> Result<Regex, Error> {
+        let shard = &self.shards[self.shard_index(&key)];
+        {
+            let mut shard = shard.lock().unwrap();
+            shard.tick += 1;
+            let tick = shard.tick;
+            if let Some(entry) = shard.map.get_mut(&key) {
+                entry.last_used = tick;
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(entry.regex.clone());
+            }
+        }
+        // Compile outside the lock so that concurrent builders aren't
+        // serialized on compilation. Two builders racing on the same key may
+        // both compile, but the result is the same either way.
+        let regex = compile()?;
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let mut shard = shard.lock().unwrap();
+        shard.tick += 1;
+        let tick = shard.tick;
+        if shard.map.len() >= self.shard_capacity
+            && !shard.map.contains_key(&key)
+        {
+            let lru = shard
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(lru) = lru {
+                shard.map.remove(&lru);
+            }
+        }
+        shard
+            .map
+            .insert(key, Entry { regex: regex.clone(), last_used: tick });
+        Ok(regex)
+    }
+
+    fn shard_index(&self, key: &str) -> usize {
+        use std::hash::Hasher;
+
+        let mut hasher = crate::fnv::Hasher::default();
+        hasher.write(key.as_bytes());
+        (hasher.finish() as usize) % SHARDS
+    }
+}
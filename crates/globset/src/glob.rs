@@ -206,6 +206,12 @@ struct GlobOptions {
     /// Whether or not an empty case in an alternate will be removed.
     /// e.g., when enabled, `{,a}` will match "" and "a".
     empty_alternates: bool,
+    /// The maximum depth to which alternate groups may be nested.
+    alternates_nesting_limit: usize,
+    /// Whether a `**` that isn't alone in a path component is a parse error.
+    /// e.g., when enabled, `foo**bar` is rejected instead of being treated
+    /// like `foo*bar`.
+    strict_recursive_wildcard: bool,
 }
 
 impl GlobOptions {
@@ -215,6 +221,8 @@ impl GlobOptions {
             literal_separator: false,
             backslash_escape: !is_separator('\\'),
             empty_alternates: false,
+            alternates_nesting_limit: 10,
+            strict_recursive_wildcard: false,
         }
     }
 }
@@ -565,21 +573,18 @@ impl<'a> GlobBuilder<'a> {
         let mut p = Parser {
             glob: &self.glob,
             stack: vec![Tokens::default()],
+            alts: vec![],
             chars: self.glob.chars().peekable(),
             prev: None,
             cur: None,
+            pos: 0,
             opts: &self.opts,
         };
         p.parse()?;
-        if p.stack.is_empty() {
+        if let Some(&(pos, _)) = p.alts.first() {
             Err(Error {
                 glob: Some(self.glob.to_string()),
-                kind: ErrorKind::UnopenedAlternates,
-            })
-        } else if p.stack.len() > 1 {
-            Err(Error {
-                glob: Some(self.glob.to_string()),
-                kind: ErrorKind::UnclosedAlternates,
+                kind: ErrorKind::UnclosedAlternates(pos),
             })
         } else {
             let tokens = p.stack.pop().unwrap();
@@ -631,6 +636,42 @@ impl<'a> GlobBuilder<'a> {
         self.opts.empty_alternates = yes;
         self
     }
+
+    /// Set the maximum depth to which alternate groups may be nested.
+    ///
+    /// For example, `*.{c,{h,hpp}}` nests one group inside another and thus
+    /// requires a limit of at least `2`. Nesting groups beyond the limit
+    /// results in an error with the [`ErrorKind::NestedAlternates`] kind.
+    /// A limit of `0` rejects alternate groups entirely.
+    ///
+    /// The default limit is `10`.
+    pub fn alternates_nesting_limit(
+        &mut self,
+        limit: usize,
+    ) -> &mut GlobBuilder<'a> {
+        self.opts.alternates_nesting_limit = limit;
+        self
+    }
+
+    /// Toggle whether a `**` that isn't alone in a path component is a parse
+    /// error.
+    ///
+    /// By default, for consistency with git's glob specification, a `**` that
+    /// does not appear adjacent to a path separator or at the beginning/end
+    /// of a glob is treated as two consecutive `*` patterns. For example,
+    /// `foo**bar` matches the same file paths as `foo*bar`. When this is
+    /// enabled, such globs instead result in an error with the
+    /// [`ErrorKind::InvalidRecursive`] kind, which is useful for flagging
+    /// likely mistakes in configuration files.
+    ///
+    /// This is disabled by default.
+    pub fn strict_recursive_wildcard(
+        &mut self,
+        yes: bool,
+    ) -> &mut GlobBuilder<'a> {
+        self.opts.strict_recursive_wildcard = yes;
+        self
+    }
 }
 
 impl Tokens {
@@ -757,10 +798,19 @@ fn bytes_to_escaped_literal(bs: &[u8]) -> String {
 
 struct Parser<'a> {
     glob: &'a str,
+    /// The tokens for the top-level pattern followed by one entry for the
+    /// branch currently being parsed in each open alternate group. Invariant:
+    /// `stack.len() == alts.len() + 1`.
     stack: Vec<Tokens>,
+    /// One entry for each open alternate group, from outermost to innermost.
+    /// Each entry records the byte offset of the group's `{` along with the
+    /// branches completed (by a `,`) so far.
+    alts: Vec<(usize, Vec<Tokens>)>,
     chars: std::iter::Peekable<std::str::Chars<'a>>,
     prev: Option<char>,
     cur: Option<char>,
+    /// The byte offset of `cur` in `glob`.
+    pos: usize,
     opts: &'a GlobOptions,
 }
 
@@ -786,17 +836,18 @@ impl<'a> Parser<'a> {
     }
 
     fn push_alternate(&mut self) -> Result<(), Error> {
-        if self.stack.len() > 1 {
+        if self.alts.len() >= self.opts.alternates_nesting_limit {
             return Err(self.error(ErrorKind::NestedAlternates));
         }
+        self.alts.push((self.pos, vec![]));
         Ok(self.stack.push(Tokens::default()))
     }
 
     fn pop_alternate(&mut self) -> Result<(), Error> {
-        let mut alts = vec![];
-        while self.stack.len() >= 2 {
-            alts.push(self.stack.pop().unwrap());
-        }
+        let Some((_, mut alts)) = self.alts.pop() else {
+            return Err(self.error(ErrorKind::UnopenedAlternates(self.pos)));
+        };
+        alts.push(self.stack.pop().unwrap());
         self.push_token(Token::Alternates(alts))
     }
 
@@ -804,30 +855,32 @@ impl<'a> Parser<'a> {
         if let Some(ref mut pat) = self.stack.last_mut() {
             return Ok(pat.push(tok));
         }
-        Err(self.error(ErrorKind::UnopenedAlternates))
+        Err(self.error(ErrorKind::UnopenedAlternates(self.pos)))
     }
 
     fn pop_token(&mut self) -> Result<Token, Error> {
         if let Some(ref mut pat) = self.stack.last_mut() {
             return Ok(pat.pop().unwrap());
         }
-        Err(self.error(ErrorKind::UnopenedAlternates))
+        Err(self.error(ErrorKind::UnopenedAlternates(self.pos)))
     }
 
     fn have_tokens(&self) -> Result<bool, Error> {
         match self.stack.last() {
-            None => Err(self.error(ErrorKind::UnopenedAlternates)),
+            None => Err(self.error(ErrorKind::UnopenedAlternates(self.pos))),
             Some(ref pat) => Ok(!pat.is_empty()),
         }
     }
 
     fn parse_comma(&mut self) -> Result<(), Error> {
         // If we aren't inside a group alternation, then don't
-        // treat commas specially. Otherwise, we need to start
-        // a new alternate.
+        // treat commas specially. Otherwise, the comma finishes
+        // the current branch and starts a new one.
         if self.stack.len() <= 1 {
             self.push_token(Token::Literal(','))
         } else {
+            let branch = self.stack.pop().unwrap();
+            self.alts.last_mut().unwrap().1.push(branch);
             Ok(self.stack.push(Tokens::default()))
         }
     }
@@ -855,8 +908,7 @@ impl<'a> Parser<'a> {
         assert!(self.bump() == Some('*'));
         if !self.have_tokens()? {
             if !self.peek().map_or(true, is_separator) {
-                self.push_token(Token::ZeroOrMore)?;
-                self.push_token(Token::ZeroOrMore)?;
+                self.push_two_stars()?;
             } else {
                 self.push_token(Token::RecursivePrefix)?;
                 assert!(self.bump().map_or(true, is_separator));
@@ -868,8 +920,7 @@ impl<'a> Parser<'a> {
             if self.stack.len() <= 1
                 || (prev != Some(',') && prev != Some('{'))
             {
-                self.push_token(Token::ZeroOrMore)?;
-                self.push_token(Token::ZeroOrMore)?;
+                self.push_two_stars()?;
                 return Ok(());
             }
         }
@@ -884,8 +935,7 @@ impl<'a> Parser<'a> {
                 false
             }
             _ => {
-                self.push_token(Token::ZeroOrMore)?;
-                self.push_token(Token::ZeroOrMore)?;
+                self.push_two_stars()?;
                 return Ok(());
             }
         };
@@ -907,6 +957,18 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
+    /// Pushes tokens for a `**` that isn't alone in a path component, e.g.,
+    /// the `**` in `foo**bar`. Per git's glob specification, such a `**` is
+    /// treated as two consecutive `*` patterns, unless strict recursive
+    /// wildcards have been requested, in which case it is an error.
+    fn push_two_stars(&mut self) -> Result<(), Error> {
+        if self.opts.strict_recursive_wildcard {
+            return Err(self.error(ErrorKind::InvalidRecursive));
+        }
+        self.push_token(Token::ZeroOrMore)?;
+        self.push_token(Token::ZeroOrMore)
+    }
+
     fn parse_class(&mut self) -> Result<(), Error> {
         fn add_to_last_range(
             glob: &str,
@@ -990,6 +1052,9 @@ impl<'a> Parser<'a> {
 
     fn bump(&mut self) -> Option<char> {
         self.prev = self.cur;
+        if let Some(c) = self.cur {
+            self.pos += c.len_utf8();
+        }
         self.cur = self.chars.next();
         self.cur
     }
@@ -1206,6 +1271,54 @@ mod tests {
     syntaxerr!(err_unclosed4, "[!]", ErrorKind::UnclosedClass);
     syntaxerr!(err_range1, "[z-a]", ErrorKind::InvalidRange('z', 'a'));
     syntaxerr!(err_range2, "[z--]", ErrorKind::InvalidRange('z', '-'));
+    syntaxerr!(err_alt1, "{a,b", ErrorKind::UnclosedAlternates(0));
+    syntaxerr!(err_alt2, "x{a,{b,c}", ErrorKind::UnclosedAlternates(1));
+    syntaxerr!(err_alt3, "a}b", ErrorKind::UnopenedAlternates(1));
+    syntaxerr!(err_alt4, "{a,b}}", ErrorKind::UnopenedAlternates(5));
+
+    #[test]
+    fn strict_recursive_wildcard() {
+        let err = |pat: &str| {
+            let err = GlobBuilder::new(pat)
+                .strict_recursive_wildcard(true)
+                .build()
+                .unwrap_err();
+            assert_eq!(
+                &ErrorKind::InvalidRecursive,
+                err.kind(),
+                "expected invalid recursive error for {:?}",
+                pat
+            );
+        };
+        let ok = |pat: &str| {
+            GlobBuilder::new(pat)
+                .strict_recursive_wildcard(true)
+                .build()
+                .unwrap();
+        };
+        err("foo**");
+        err("**bar");
+        err("a/**b/c");
+        err("a**/b");
+        err("{a,x**y}");
+        ok("**");
+        ok("**/a");
+        ok("a/**");
+        ok("a/**/b");
+        ok("{a,**/b}");
+    }
+
+    #[test]
+    fn alternates_nesting_limit() {
+        let glob = "{a,{b,{c,d}}}";
+        let err = GlobBuilder::new(glob)
+            .alternates_nesting_limit(2)
+            .build()
+            .unwrap_err();
+        assert_eq!(&ErrorKind::NestedAlternates, err.kind());
+        // The default limit is deep enough to accept it.
+        assert!(GlobBuilder::new(glob).build().is_ok());
+    }
 
     const CASEI: Options =
         Options { casei: Some(true), litsep: None, bsesc: None, ealtre: None };
@@ -1265,7 +1378,9 @@ mod tests {
     toregex!(re32, "/a**", r"^/a.*.*$");
     toregex!(re33, "/**a", r"^/.*.*a$");
     toregex!(re34, "/a**b", r"^/a.*.*b$");
-    toregex!(re35, "{a,b}", r"^(?:b|a)$");
+    toregex!(re35, "{a,b}", r"^(?:a|b)$");
+    toregex!(re36, "{a,{b,c}}", r"^(?:a|(?:b|c))$");
+    toregex!(re37, r"\{a\,b\}", r"^\{a,b\}$", BSESC);
 
     matches!(match1, "a", "a");
     matches!(match2, "a*b", "a_b");
@@ -1305,6 +1420,26 @@ mod tests {
     matches!(matchrec25, "test/**", "test/one/two");
     matches!(matchrec26, "some/*/needle.txt", "some/one/needle.txt");
 
+    // A `**` that isn't alone in a path component matches like `*`, per
+    // git's glob specification. The expected results below correspond to
+    // what `git check-ignore` reports for the same patterns and paths.
+    matches!(matchrecmid1, "foo**", "foo", SLASHLIT);
+    matches!(matchrecmid2, "foo**", "foobar", SLASHLIT);
+    nmatches!(matchrecmid3, "foo**", "foo/bar", SLASHLIT);
+    matches!(matchrecmid4, "**bar", "bar", SLASHLIT);
+    matches!(matchrecmid5, "**bar", "foobar", SLASHLIT);
+    nmatches!(matchrecmid6, "**bar", "foo/bar", SLASHLIT);
+    matches!(matchrecmid7, "a/**b/c", "a/b/c", SLASHLIT);
+    matches!(matchrecmid8, "a/**b/c", "a/xb/c", SLASHLIT);
+    nmatches!(matchrecmid9, "a/**b/c", "a/x/b/c", SLASHLIT);
+    matches!(matchrecmid10, "a/**/b", "a/b", SLASHLIT);
+    matches!(matchrecmid11, "a/**/b", "a/x/b", SLASHLIT);
+    matches!(matchrecmid12, "a/**/b", "a/x/y/b", SLASHLIT);
+    // ... but without a literal separator, the `*`s can match a `/`.
+    matches!(matchrecmid13, "foo**", "foo/bar");
+    matches!(matchrecmid14, "**bar", "foo/bar");
+    matches!(matchrecmid15, "a/**b/c", "a/x/b/c");
+
     matches!(matchrange1, "a[0-9]b", "a0b");
     matches!(matchrange2, "a[0-9]b", "a9b");
     matches!(matchrange3, "a[!0-9]b", "a_b");
@@ -1353,6 +1488,15 @@ mod tests {
     matches!(matchalt14, "foo{,.txt}", "foo.txt");
     nmatches!(matchalt15, "foo{,.txt}", "foo");
     matches!(matchalt16, "foo{,.txt}", "foo", EALTRE);
+    matches!(matchalt17, "*.{c,{h,hpp}}", "x.c");
+    matches!(matchalt18, "*.{c,{h,hpp}}", "x.hpp");
+    nmatches!(matchalt19, "*.{c,{h,hpp}}", "x.cpp");
+    matches!(matchalt20, r"\{a\,b\}", "{a,b}", BSESC);
+    matches!(matchalt21, r"{a\,b,c}", "a,b", BSESC);
+    matches!(matchalt22, r"{a\,b,c}", "c", BSESC);
+    matches!(matchalt23, "foo{,{.txt,.md}}", "foo", EALTRE);
+    matches!(matchalt24, "foo{,{.txt,.md}}", "foo.md", EALTRE);
+    nmatches!(matchalt25, "foo{,{.txt,.md}}", "foo.rs", EALTRE);
 
     matches!(matchslash1, "abc/def", "abc/def", SLASHLIT);
     #[cfg(unix)]
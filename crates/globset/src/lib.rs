@@ -101,7 +101,7 @@ or to enable case insensitive matching.
 use std::{
     borrow::Cow,
     panic::{RefUnwindSafe, UnwindSafe},
-    path::Path,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
@@ -117,11 +117,19 @@ use {
 
 use crate::{
     glob::MatchStrategy,
-    pathutil::{file_name, file_name_ext, normalize_path},
+    pathutil::{
+        file_name, file_name_ext, is_file_name, normalize_backslashes,
+        normalize_path, normalize_unc_and_drive, normalize_verbatim,
+        strip_prefix, strip_verbatim_prefix,
+    },
 };
 
-pub use crate::glob::{Glob, GlobBuilder, GlobMatcher};
+pub use crate::{
+    cache::GlobCompileCache,
+    glob::{Glob, GlobBuilder, GlobMatcher},
+};
 
+mod cache;
 mod fnv;
 mod glob;
 mod pathutil;
@@ -151,13 +159,14 @@ pub struct Error {
 /// The kind of error that can occur when parsing a glob pattern.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum ErrorKind {
-    /// **DEPRECATED**.
+    /// Occurs when a `**` appears somewhere other than alone in a path
+    /// component, e.g., `foo**bar`.
     ///
-    /// This error used to occur for consistency with git's glob specification,
-    /// but the specification now accepts all uses of `**`. When `**` does not
-    /// appear adjacent to a path separator or at the beginning/end of a glob,
-    /// it is now treated as two consecutive `*` patterns. As such, this error
-    /// is no longer used.
+    /// By default, such globs are accepted for consistency with git's glob
+    /// specification, which treats a `**` that is not alone in a path
+    /// component as two consecutive `*` patterns. This error only occurs
+    /// when that permissive behavior has been disabled via
+    /// [`GlobBuilder::strict_recursive_wildcard`].
     InvalidRecursive,
     /// Occurs when a character class (e.g., `[abc]`) is not closed.
     UnclosedClass,
@@ -165,12 +174,15 @@ pub enum ErrorKind {
     /// example, if the range starts with a lexicographically larger character
     /// than it ends with.
     InvalidRange(char, char),
-    /// Occurs when a `}` is found without a matching `{`.
-    UnopenedAlternates,
-    /// Occurs when a `{` is found without a matching `}`.
-    UnclosedAlternates,
-    /// Occurs when an alternating group is nested inside another alternating
-    /// group, e.g., `{{a,b},{c,d}}`.
+    /// Occurs when a `}` is found without a matching `{`. The value is the
+    /// byte offset of the `}` in the glob.
+    UnopenedAlternates(usize),
+    /// Occurs when a `{` is found without a matching `}`. The value is the
+    /// byte offset of the `{` in the glob.
+    UnclosedAlternates(usize),
+    /// Occurs when alternating groups are nested more deeply than the
+    /// configured limit, e.g., `{a,{b,c}}` when the limit is `1`. See
+    /// [`GlobBuilder::alternates_nesting_limit`].
     NestedAlternates,
     /// Occurs when an unescaped '\' is found at the end of a glob.
     DanglingEscape,
@@ -213,16 +225,16 @@ impl ErrorKind {
                 "unclosed character class; missing ']'"
             }
             ErrorKind::InvalidRange(_, _) => "invalid character range",
-            ErrorKind::UnopenedAlternates => {
+            ErrorKind::UnopenedAlternates(_) => {
                 "unopened alternate group; missing '{' \
                 (maybe escape '}' with '[}]'?)"
             }
-            ErrorKind::UnclosedAlternates => {
+            ErrorKind::UnclosedAlternates(_) => {
                 "unclosed alternate group; missing '}' \
                 (maybe escape '{' with '[{]'?)"
             }
             ErrorKind::NestedAlternates => {
-                "nested alternate groups are not allowed"
+                "alternate groups are nested too deeply"
             }
             ErrorKind::DanglingEscape => "dangling '\\'",
             ErrorKind::Regex(ref err) => err,
@@ -247,11 +259,21 @@ impl std::fmt::Display for ErrorKind {
         match *self {
             ErrorKind::InvalidRecursive
             | ErrorKind::UnclosedClass
-            | ErrorKind::UnopenedAlternates
-            | ErrorKind::UnclosedAlternates
             | ErrorKind::NestedAlternates
             | ErrorKind::DanglingEscape
             | ErrorKind::Regex(_) => write!(f, "{}", self.description()),
+            ErrorKind::UnopenedAlternates(pos) => write!(
+                f,
+                "unopened alternate group at byte offset {}; missing '{{' \
+                 (maybe escape '}}' with '[}}]'?)",
+                pos,
+            ),
+            ErrorKind::UnclosedAlternates(pos) => write!(
+                f,
+                "unclosed alternate group at byte offset {}; missing '}}' \
+                 (maybe escape '{{' with '[{{]'?)",
+                pos,
+            ),
             ErrorKind::InvalidRange(s, e) => {
                 write!(f, "invalid range; '{}' > '{}'", s, e)
             }
@@ -295,12 +317,51 @@ fn new_regex_set(pats: Vec<String>) -> Result<Regex, Error> {
         })
 }
 
+/// The strategy used to normalize path separators in candidate paths before
+/// matching them.
+///
+/// Globs always use `/` as their separator, so candidate paths written with
+/// `\` need their separators converted for matching to work. How (and
+/// whether) that happens is controlled by this setting via
+/// [`GlobSetBuilder::path_normalization`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PathNormalization {
+    /// Normalize according to the host platform's rules.
+    ///
+    /// On Windows, every separator the platform recognizes is converted to
+    /// `/`, extended-length prefixes (`\\?\`) are stripped, the server and
+    /// share names of a UNC path become ordinary leading components and
+    /// drive-relative paths (`C:foo`) get a separator inserted after the
+    /// drive. On Unix, paths are used as-is. This is the default.
+    Auto,
+    /// Convert `\` to `/` in candidate paths on every platform, with the
+    /// same UNC and drive-relative adjustments that `Auto` performs on
+    /// Windows.
+    ///
+    /// This matches Windows-style paths regardless of the host platform,
+    /// which is useful for tools that process path strings originating
+    /// elsewhere.
+    ForwardSlash,
+    /// Use candidate paths exactly as given.
+    ///
+    /// A candidate containing `\` only matches globs that escape a literal
+    /// `\`, even on Windows.
+    None,
+}
+
+impl Default for PathNormalization {
+    fn default() -> PathNormalization {
+        PathNormalization::Auto
+    }
+}
+
 /// GlobSet represents a group of globs that can be matched together in a
 /// single pass.
 #[derive(Clone, Debug)]
 pub struct GlobSet {
     len: usize,
     strats: Vec<GlobSetMatchStrategy>,
+    normalization: PathNormalization,
 }
 
 impl GlobSet {
@@ -315,7 +376,11 @@ impl GlobSet {
     /// Create an empty `GlobSet`. An empty set matches nothing.
     #[inline]
     pub fn empty() -> GlobSet {
-        GlobSet { len: 0, strats: vec![] }
+        GlobSet {
+            len: 0,
+            strats: vec![],
+            normalization: PathNormalization::Auto,
+        }
     }
 
     /// Returns true if this set is empty, and therefore matches nothing.
@@ -330,9 +395,22 @@ impl GlobSet {
         self.len
     }
 
+    /// Return the path normalization strategy this set matches with.
+    ///
+    /// Callers constructing their own [`Candidate`] values for this set
+    /// should build them with the same strategy via
+    /// [`Candidate::with_normalization`].
+    #[inline]
+    pub fn path_normalization(&self) -> PathNormalization {
+        self.normalization
+    }
+
     /// Returns true if any glob in this set matches the path given.
     pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
-        self.is_match_candidate(&Candidate::new(path.as_ref()))
+        self.is_match_candidate(&Candidate::with_normalization(
+            path.as_ref(),
+            self.normalization,
+        ))
     }
 
     /// Returns true if any glob in this set matches the path given.
@@ -354,7 +432,10 @@ impl GlobSet {
     /// Returns the sequence number of every glob pattern that matches the
     /// given path.
     pub fn matches<P: AsRef<Path>>(&self, path: P) -> Vec<usize> {
-        self.matches_candidate(&Candidate::new(path.as_ref()))
+        self.matches_candidate(&Candidate::with_normalization(
+            path.as_ref(),
+            self.normalization,
+        ))
     }
 
     /// Returns the sequence number of every glob pattern that matches the
@@ -382,7 +463,10 @@ impl GlobSet {
         path: P,
         into: &mut Vec<usize>,
     ) {
-        self.matches_candidate_into(&Candidate::new(path.as_ref()), into);
+        self.matches_candidate_into(
+            &Candidate::with_normalization(path.as_ref(), self.normalization),
+            into,
+        );
     }
 
     /// Adds the sequence number of every glob pattern that matches the given
@@ -410,9 +494,13 @@ impl GlobSet {
         into.dedup();
     }
 
-    fn new(pats: &[Glob]) -> Result<GlobSet, Error> {
+    fn new(
+        pats: &[Glob],
+        cache: Option<&GlobCompileCache>,
+        normalization: PathNormalization,
+    ) -> Result<GlobSet, Error> {
         if pats.is_empty() {
-            return Ok(GlobSet { len: 0, strats: vec![] });
+            return Ok(GlobSet { len: 0, strats: vec![], normalization });
         }
         let mut lits = LiteralStrategy::new();
         let mut base_lits = BasenameLiteralStrategy::new();
@@ -463,6 +551,7 @@ impl GlobSet {
         );
         Ok(GlobSet {
             len: pats.len(),
+            normalization,
             strats: vec![
                 GlobSetMatchStrategy::Extension(exts),
                 GlobSetMatchStrategy::BasenameLiteral(base_lits),
@@ -470,9 +559,9 @@ impl GlobSet {
                 GlobSetMatchStrategy::Suffix(suffixes.suffix()),
                 GlobSetMatchStrategy::Prefix(prefixes.prefix()),
                 GlobSetMatchStrategy::RequiredExtension(
-                    required_exts.build()?,
+                    required_exts.build(cache)?,
                 ),
-                GlobSetMatchStrategy::Regex(regexes.regex_set()?),
+                GlobSetMatchStrategy::Regex(regexes.regex_set(cache)?),
             ],
         })
     }
@@ -490,6 +579,8 @@ impl Default for GlobSet {
 #[derive(Clone, Debug)]
 pub struct GlobSetBuilder {
     pats: Vec<Glob>,
+    cache: Option<Arc<GlobCompileCache>>,
+    normalization: PathNormalization,
 }
 
 impl GlobSetBuilder {
@@ -497,14 +588,18 @@ impl GlobSetBuilder {
     /// patterns. Once all patterns have been added, `build` should be called
     /// to produce a [`GlobSet`], which can then be used for matching.
     pub fn new() -> GlobSetBuilder {
-        GlobSetBuilder { pats: vec![] }
+        GlobSetBuilder {
+            pats: vec![],
+            cache: None,
+            normalization: PathNormalization::Auto,
+        }
     }
 
     /// Builds a new matcher from all of the glob patterns added so far.
     ///
     /// Once a matcher is built, no new patterns can be added to it.
     pub fn build(&self) -> Result<GlobSet, Error> {
-        GlobSet::new(&self.pats)
+        GlobSet::new(&self.pats, self.cache.as_deref(), self.normalization)
     }
 
     /// Add a new pattern to this set.
@@ -512,6 +607,171 @@ impl GlobSetBuilder {
         self.pats.push(pat);
         self
     }
+
+    /// Set the strategy used to normalize path separators in candidate
+    /// paths before matching. See [`PathNormalization`] for the choices.
+    ///
+    /// This defaults to [`PathNormalization::Auto`], which normalizes
+    /// according to the host platform's rules.
+    pub fn path_normalization(
+        &mut self,
+        normalization: PathNormalization,
+    ) -> &mut GlobSetBuilder {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Use the given cache of compiled regexes when building this set.
+    ///
+    /// Globs that need a regex for matching are compiled when `build` is
+    /// called. When the same globs are used to build sets repeatedly, a
+    /// cache shared between builders avoids recompiling them. See
+    /// [`GlobCompileCache`] for details.
+    ///
+    /// Attaching a cache never changes what the built set matches. By
+    /// default, no cache is used.
+    pub fn with_cache(
+        &mut self,
+        cache: Arc<GlobCompileCache>,
+    ) -> &mut GlobSetBuilder {
+        self.cache = Some(cache);
+        self
+    }
+}
+
+/// A glob set anchored to a root directory.
+///
+/// This wraps a [`GlobSet`] together with a root path. Before matching, a
+/// candidate path is made relative to the root via [`strip_root`], using the
+/// same lexical rules that gitignore matching uses. This is useful when
+/// matching paths produced by a directory traversal (which typically include
+/// the traversal's root as a prefix) against globs written relative to that
+/// root.
+///
+/// By default, a path that is not lexically contained in the root (e.g., an
+/// absolute path with a different prefix) is matched as given, under the
+/// assumption that it is already relative to the root. Use
+/// [`RootedGlobSet::require_containment`] to reject such paths instead.
+#[derive(Clone, Debug)]
+pub struct RootedGlobSet {
+    root: PathBuf,
+    set: GlobSet,
+    require_containment: bool,
+}
+
+impl RootedGlobSet {
+    /// Create a new rooted glob set that matches paths relative to the given
+    /// root.
+    ///
+    /// A leading `./` on the root is stripped, just as it is stripped from
+    /// candidate paths.
+    pub fn new<P: AsRef<Path>>(root: P, set: GlobSet) -> RootedGlobSet {
+        let root = strip_prefix("./", root.as_ref())
+            .unwrap_or(root.as_ref())
+            .to_path_buf();
+        RootedGlobSet { root, set, require_containment: false }
+    }
+
+    /// When enabled, a candidate path that is not lexically contained in
+    /// this set's root never matches. A relative candidate path is
+    /// interpreted as relative to the root, and so is contained unless its
+    /// `..` components pop above the root. An absolute candidate path is
+    /// contained only when it starts with the root. The check is lexical:
+    /// symlinks are not resolved.
+    ///
+    /// When disabled (the default), such paths are matched as given, without
+    /// any stripping.
+    pub fn require_containment(&mut self, yes: bool) -> &mut RootedGlobSet {
+        self.require_containment = yes;
+        self
+    }
+
+    /// Return the root that candidate paths are matched relative to.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Return the underlying glob set.
+    pub fn glob_set(&self) -> &GlobSet {
+        &self.set
+    }
+
+    /// Returns true if any glob in this set matches the path given, after
+    /// the path has been made relative to this set's root.
+    pub fn is_match<P: AsRef<Path>>(&self, path: P) -> bool {
+        let path = path.as_ref();
+        if self.require_containment && !self.contains(path) {
+            return false;
+        }
+        self.set.is_match(strip_root(&self.root, path))
+    }
+
+    /// Returns true when the given path is lexically contained in this
+    /// set's root.
+    fn contains(&self, path: &Path) -> bool {
+        use std::path::Component;
+
+        let path = strip_prefix("./", path).unwrap_or(path);
+        let stripped = strip_root(&self.root, path);
+        // If nothing was stripped from an absolute path, then it must live
+        // outside the root.
+        if path.is_absolute() && stripped.as_os_str() == path.as_os_str() {
+            return false;
+        }
+        // What remains is interpreted as relative to the root, so any `..`
+        // components that pop above the root escape it.
+        let mut depth = 0i64;
+        for component in stripped.components() {
+            match component {
+                Component::ParentDir => {
+                    depth -= 1;
+                    if depth < 0 {
+                        return false;
+                    }
+                }
+                Component::Normal(_) => depth += 1,
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+/// Strips the given root from a candidate path, using the lexical rules that
+/// gitignore matching uses.
+///
+/// Namely:
+///
+/// * A leading `./` is stripped from both the root and the candidate path,
+///   since it is superfluous.
+/// * When the candidate path is just a file name, nothing is stripped, since
+///   there is no directory component that could correspond to the root.
+/// * Otherwise, when the candidate path starts with the root, the root (and
+///   any subsequent `/`) is stripped. A root of `.` never strips anything.
+///
+/// A candidate path that does not start with the root is returned unchanged
+/// (minus a leading `./`), under the assumption that it is already relative
+/// to the root. The check is lexical: symlinks are not resolved.
+///
+/// On Windows, extended-length prefixes (`\\?\` and `\\?\UNC\`) are
+/// normalized away from both the root and the candidate path first, so that
+/// a path in extended-length form still strips against a root in normal
+/// form (and vice versa).
+pub fn strip_root<'a>(root: &Path, path: &'a Path) -> &'a Path {
+    let path = strip_prefix("./", path).unwrap_or(path);
+    let mut path = strip_verbatim_prefix(path);
+    let root = strip_prefix("./", root).unwrap_or(root);
+    let root = strip_verbatim_prefix(root);
+    if root != Path::new(".") && !is_file_name(path) {
+        if let Some(p) = strip_prefix(root, path) {
+            path = p;
+            // If we're left with a leading slash, get rid of it.
+            if let Some(p) = strip_prefix("/", path) {
+                path = p;
+            }
+        }
+    }
+    path
 }
 
 /// A candidate path for matching.
@@ -539,8 +799,42 @@ impl<'a> std::fmt::Debug for Candidate<'a> {
 
 impl<'a> Candidate<'a> {
     /// Create a new candidate for matching from the given path.
+    ///
+    /// The path's separators are normalized according to
+    /// [`PathNormalization::Auto`]. In particular, on Windows, any
+    /// extended-length prefix (`\\?\` or `\\?\UNC\`) is normalized away,
+    /// since the prefixed and normal forms of a path name the same file and
+    /// should match the same globs.
     pub fn new<P: AsRef<Path> + ?Sized>(path: &'a P) -> Candidate<'a> {
-        let path = normalize_path(Vec::from_path_lossy(path.as_ref()));
+        Candidate::with_normalization(path, PathNormalization::Auto)
+    }
+
+    /// Create a new candidate for matching from the given path, normalizing
+    /// its separators with the given strategy.
+    ///
+    /// A candidate matched against a [`GlobSet`] with a non-default
+    /// normalization setting should be built with that set's strategy (see
+    /// [`GlobSet::path_normalization`]).
+    pub fn with_normalization<P: AsRef<Path> + ?Sized>(
+        path: &'a P,
+        normalization: PathNormalization,
+    ) -> Candidate<'a> {
+        let path = Vec::from_path_lossy(path.as_ref());
+        let path = match normalization {
+            PathNormalization::Auto => {
+                let path = normalize_path(normalize_verbatim(path));
+                if cfg!(windows) {
+                    normalize_unc_and_drive(path)
+                } else {
+                    path
+                }
+            }
+            PathNormalization::ForwardSlash => {
+                let path = normalize_backslashes(normalize_verbatim(path));
+                normalize_unc_and_drive(path)
+            }
+            PathNormalization::None => path,
+        };
         let basename = file_name(&path).unwrap_or(Cow::Borrowed(B("")));
         let ext = file_name_ext(&basename).unwrap_or(Cow::Borrowed(B("")));
         Candidate { path, basename, ext }
@@ -881,8 +1175,14 @@ impl MultiStrategyBuilder {
         }
     }
 
-    fn regex_set(self) -> Result<RegexSetStrategy, Error> {
-        let matcher = new_regex_set(self.literals)?;
+    fn regex_set(
+        self,
+        cache: Option<&GlobCompileCache>,
+    ) -> Result<RegexSetStrategy, Error> {
+        let matcher = match cache {
+            None => new_regex_set(self.literals)?,
+            Some(cache) => cache.regex_set(self.literals)?,
+        };
         let pattern_len = matcher.pattern_len();
         let create: PatternSetPoolFn =
             Box::new(move || PatternSet::new(pattern_len));
@@ -911,12 +1211,18 @@ impl RequiredExtensionStrategyBuilder {
             .push((global_index, regex));
     }
 
-    fn build(self) -> Result<RequiredExtensionStrategy, Error> {
+    fn build(
+        self,
+        cache: Option<&GlobCompileCache>,
+    ) -> Result<RequiredExtensionStrategy, Error> {
         let mut exts = fnv::HashMap::default();
         for (ext, regexes) in self.0.into_iter() {
             exts.insert(ext.clone(), vec![]);
             for (global_index, regex) in regexes {
-                let compiled = new_regex(&regex)?;
+                let compiled = match cache {
+                    None => new_regex(&regex)?,
+                    Some(cache) => cache.regex(&regex)?,
+                };
                 exts.get_mut(&ext).unwrap().push((global_index, compiled));
             }
         }
@@ -949,9 +1255,11 @@ pub fn escape(s: &str) -> String {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::glob::Glob;
 
-    use super::{GlobSet, GlobSetBuilder};
+    use super::{GlobCompileCache, GlobSet, GlobSetBuilder};
 
     #[test]
     fn set_works() {
@@ -988,6 +1296,182 @@ mod tests {
         assert!(!set.is_match("a"));
     }
 
+    #[test]
+    fn strip_root_works() {
+        use std::path::Path;
+
+        use super::strip_root;
+
+        let root = Path::new("/home/foobar/rust/rg");
+        let strip = |path| strip_root(root, Path::new(path));
+
+        // The common case: the candidate starts with the root.
+        assert_eq!(
+            Path::new("src/main.rs"),
+            strip("/home/foobar/rust/rg/src/main.rs")
+        );
+        // A leading ./ on the candidate is superfluous.
+        assert_eq!(Path::new("src/main.rs"), strip("./src/main.rs"));
+        // A candidate that is just a file name is never stripped, even when
+        // a part of it overlaps with the root.
+        assert_eq!(Path::new("rg"), strip("rg"));
+        // A candidate already relative to the root passes through.
+        assert_eq!(Path::new("src/main.rs"), strip("src/main.rs"));
+        // So does a path outside the root.
+        assert_eq!(
+            Path::new("/other/place/src/main.rs"),
+            strip("/other/place/src/main.rs"),
+        );
+        // A root of `.` (possibly spelled with superfluous ./ prefixes)
+        // never strips anything, e.g., when the candidate begins with a `.`.
+        for dot in [".", "./.", "././", "././."] {
+            assert_eq!(
+                Path::new(".a/b"),
+                strip_root(Path::new(dot), Path::new(".a/b")),
+            );
+        }
+        // A leading ./ on the root is stripped too.
+        assert_eq!(
+            Path::new("m4/ltoptions.m4"),
+            strip_root(
+                Path::new("./third_party/protobuf"),
+                Path::new("./third_party/protobuf/m4/ltoptions.m4"),
+            ),
+        );
+    }
+
+    // Extended-length Windows paths name the same files as their normal
+    // forms, so a candidate in one form must strip against a root in the
+    // other.
+    #[test]
+    #[cfg(windows)]
+    fn strip_root_verbatim() {
+        use std::path::Path;
+
+        use super::strip_root;
+
+        let root = Path::new(r"C:\home\rg");
+        let verbatim = Path::new(r"\\?\C:\home\rg");
+        for root in [root, verbatim] {
+            assert_eq!(
+                Path::new(r"src\main.rs"),
+                strip_root(root, Path::new(r"\\?\C:\home\rg\src\main.rs")),
+            );
+            assert_eq!(
+                Path::new(r"src\main.rs"),
+                strip_root(root, Path::new(r"C:\home\rg\src\main.rs")),
+            );
+        }
+        // And likewise for UNC paths.
+        let root = Path::new(r"\\server\share\rg");
+        let verbatim = Path::new(r"\\?\UNC\server\share\rg");
+        for root in [root, verbatim] {
+            assert_eq!(
+                Path::new(r"src\main.rs"),
+                strip_root(
+                    root,
+                    Path::new(r"\\?\UNC\server\share\rg\src\main.rs"),
+                ),
+            );
+            assert_eq!(
+                Path::new(r"src\main.rs"),
+                strip_root(root, Path::new(r"\\server\share\rg\src\main.rs")),
+            );
+        }
+    }
+
+    // An extended-length candidate matches the same globs as its normal
+    // form.
+    #[test]
+    #[cfg(windows)]
+    fn candidate_verbatim() {
+        use super::Candidate;
+
+        let mut builder = GlobSetBuilder::new();
+        builder.add(Glob::new("C:/home/**/*.rs").unwrap());
+        let set = builder.build().unwrap();
+        assert!(set.is_match_candidate(&Candidate::new(r"C:\home\a\b.rs")));
+        assert!(set.is_match_candidate(&Candidate::new(r"\\?\C:\home\a\b.rs")));
+        assert!(!set.is_match_candidate(&Candidate::new(r"\\?\D:\x\b.rs")));
+    }
+
+    // ForwardSlash normalization matches Windows-style paths on any
+    // platform, since the conversion operates on the path's bytes.
+    #[test]
+    fn forward_slash_normalization() {
+        use super::PathNormalization;
+
+        let mut builder = GlobSetBuilder::new();
+        builder.add(Glob::new("**/*.txt").unwrap());
+        builder.add(Glob::new("/server/share/**").unwrap());
+        builder.add(Glob::new("C:/logs/*.txt").unwrap());
+        builder.path_normalization(PathNormalization::ForwardSlash);
+        let set = builder.build().unwrap();
+
+        // Mixed separators.
+        assert!(set.is_match(r"dir\sub\file.txt"));
+        assert_eq!(vec![0], set.matches(r"dir/sub\file.txt"));
+        // The server and share names of a UNC path are ordinary leading
+        // components, so neither gets eaten by `**` and both can be named
+        // in a glob.
+        assert_eq!(vec![0, 1], set.matches(r"\\server\share\a\b.txt"));
+        assert!(!set.is_match(r"\\other\share\a\b"));
+        // A drive-relative path gets a separator after the drive, so its
+        // first component is visible to globs.
+        assert_eq!(vec![0, 2], set.matches(r"C:logs\today.txt"));
+        assert_eq!(vec![0, 2], set.matches(r"C:\logs\today.txt"));
+    }
+
+    // With normalization disabled, candidate paths are used exactly as
+    // given.
+    #[test]
+    fn no_path_normalization() {
+        use super::PathNormalization;
+
+        let mut builder = GlobSetBuilder::new();
+        builder.add(Glob::new("a/*.txt").unwrap());
+        builder.path_normalization(PathNormalization::None);
+        let set = builder.build().unwrap();
+
+        assert!(set.is_match("a/b.txt"));
+        assert!(!set.is_match(r"a\b.txt"));
+    }
+
+    #[test]
+    fn rooted_set_works() {
+        use super::RootedGlobSet;
+
+        let mut builder = GlobSetBuilder::new();
+        builder.add(Glob::new("src/*.rs").unwrap());
+        builder.add(Glob::new("months").unwrap());
+        let set = builder.build().unwrap();
+        let rooted = RootedGlobSet::new("/home/foobar/rust/rg", set);
+
+        // Candidates are matched relative to the root.
+        assert!(rooted.is_match("/home/foobar/rust/rg/src/main.rs"));
+        assert!(rooted.is_match("src/main.rs"));
+        assert!(rooted.is_match("./src/main.rs"));
+        assert!(!rooted.is_match("/home/foobar/rust/rg/src/main.c"));
+        // The file-name-only fast path: nothing is stripped from a bare
+        // file name.
+        assert!(rooted.is_match("months"));
+        // By default, a path outside the root is matched as given...
+        assert!(!rooted.is_match("/other/place/src/main.rs"));
+        let mut builder = GlobSetBuilder::new();
+        builder.add(Glob::new("**/*.rs").unwrap());
+        let set = builder.build().unwrap();
+        let mut rooted = RootedGlobSet::new("/home/foobar/rust/rg", set);
+        assert!(rooted.is_match("/other/place/src/main.rs"));
+        assert!(rooted.is_match("../escape/main.rs"));
+        // ... while requiring containment rejects it.
+        rooted.require_containment(true);
+        assert!(!rooted.is_match("/other/place/src/main.rs"));
+        assert!(!rooted.is_match("../escape/main.rs"));
+        assert!(rooted.is_match("/home/foobar/rust/rg/src/main.rs"));
+        assert!(rooted.is_match("src/main.rs"));
+        assert!(rooted.is_match("src/../lib/main.rs"));
+    }
+
     #[test]
     fn escape() {
         use super::escape;
@@ -1019,4 +1503,99 @@ mod tests {
         let matches = set.matches("nada");
         assert_eq!(0, matches.len());
     }
+
+    // A grab bag of globs that exercises every match strategy: basename
+    // literals, literals, extensions, prefixes, suffixes, required
+    // extensions and plain regexes.
+    const CACHE_GLOBS: &[&str] = &[
+        "Cargo.toml",
+        "src/lib.rs",
+        "*.c",
+        "foo/**",
+        "**/bar",
+        "**/*.rs",
+        "src/**/*.rs",
+        "a[0-9]b",
+        "{foo,bar}/*.txt",
+        "!weird-but-literal",
+    ];
+
+    const CACHE_PATHS: &[&str] = &[
+        "Cargo.toml",
+        "sub/Cargo.toml",
+        "src/lib.rs",
+        "src/grep/src/main.rs",
+        "foo.c",
+        "src/foo.c",
+        "foo/deep/nested/thing",
+        "x/bar",
+        "bar",
+        "a5b",
+        "a55b",
+        "foo/note.txt",
+        "bar/note.txt",
+        "baz/note.txt",
+        "!weird-but-literal",
+    ];
+
+    fn cache_set(cache: Option<&Arc<GlobCompileCache>>) -> GlobSet {
+        let mut builder = GlobSetBuilder::new();
+        for glob in CACHE_GLOBS {
+            builder.add(Glob::new(glob).unwrap());
+        }
+        if let Some(cache) = cache {
+            builder.with_cache(Arc::clone(cache));
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn cache_does_not_change_semantics() {
+        let cache = Arc::new(GlobCompileCache::new(64));
+        let uncached = cache_set(None);
+        let cold = cache_set(Some(&cache));
+        // A second build from the same globs hits the cache.
+        let warm = cache_set(Some(&cache));
+        assert!(cache.misses() > 0);
+        assert!(cache.hits() > 0);
+
+        for path in CACHE_PATHS {
+            let expected = uncached.matches(path);
+            assert_eq!(expected, cold.matches(path), "path: {}", path);
+            assert_eq!(expected, warm.matches(path), "path: {}", path);
+        }
+    }
+
+    #[test]
+    fn cache_shared_across_threads() {
+        let cache = Arc::new(GlobCompileCache::new(64));
+        let uncached = cache_set(None);
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let cache = Arc::clone(&cache);
+                std::thread::spawn(move || cache_set(Some(&cache)))
+            })
+            .collect();
+        for handle in handles {
+            let set = handle.join().unwrap();
+            for path in CACHE_PATHS {
+                assert_eq!(uncached.matches(path), set.matches(path));
+            }
+        }
+    }
+
+    #[test]
+    fn cache_evicts_when_full() {
+        // The cache rounds its capacity up so that each of its shards can
+        // hold at least one entry, so `1` here really means "tiny."
+        let cache = Arc::new(GlobCompileCache::new(1));
+        for i in 0..100 {
+            let mut builder = GlobSetBuilder::new();
+            builder.add(Glob::new(&format!("a{}[0-9]b", i)).unwrap());
+            builder.with_cache(Arc::clone(&cache));
+            builder.build().unwrap();
+        }
+        assert!(cache.len() <= 8);
+        assert_eq!(100, cache.misses());
+    }
 }
@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, path::Path};
 
 use bstr::{ByteSlice, ByteVec};
 
@@ -80,6 +80,165 @@ pub(crate) fn normalize_path(mut path: Cow<[u8]>) -> Cow<[u8]> {
     path
 }
 
+/// Converts every `\` in the path to `/`, regardless of what the host
+/// platform considers a separator.
+///
+/// This is the normalization applied by
+/// [`PathNormalization::ForwardSlash`](crate::PathNormalization), which
+/// matches Windows-style paths on any platform.
+pub(crate) fn normalize_backslashes(mut path: Cow<'_, [u8]>) -> Cow<'_, [u8]> {
+    for i in 0..path.len() {
+        if path[i] == b'\\' {
+            path.to_mut()[i] = b'/';
+        }
+    }
+    path
+}
+
+/// Rewrites the two Windows path forms whose leading components would
+/// otherwise be invisible to globs. This expects a path whose separators
+/// have already been normalized to `/`.
+///
+/// A UNC path `//server/share/x` starts with an empty component, so neither
+/// `server` nor a glob anchored at the beginning of the path can see past
+/// it; the extra slash is dropped, making the server and share names
+/// ordinary leading components of an absolute path. A drive-relative path
+/// `C:foo` glues the drive to the first component, so `foo` never matches
+/// as a component of its own; a separator is inserted after the drive.
+pub(crate) fn normalize_unc_and_drive(
+    mut path: Cow<'_, [u8]>,
+) -> Cow<'_, [u8]> {
+    if path.starts_with(b"//") && path.get(2).map_or(false, |&b| b != b'/') {
+        match path {
+            Cow::Borrowed(path) => return Cow::Borrowed(&path[1..]),
+            Cow::Owned(ref mut path) => {
+                path.drain_bytes(..1);
+            }
+        }
+    } else if path.len() >= 3
+        && path[0].is_ascii_alphabetic()
+        && path[1] == b':'
+        && path[2] != b'/'
+    {
+        path.to_mut().insert(2, b'/');
+    }
+    path
+}
+
+/// Rewrites a Windows extended-length path into its normal form: `\\?\C:\x`
+/// becomes `C:\x` and `\\?\UNC\server\share` becomes `\\server\share`.
+/// Extended-length paths name the same files as their normal forms, so glob
+/// matching should not distinguish between them. Paths without an
+/// extended-length prefix are returned unchanged.
+#[cfg(windows)]
+pub(crate) fn normalize_verbatim(path: Cow<'_, [u8]>) -> Cow<'_, [u8]> {
+    if path.starts_with(br"\\?\UNC\") {
+        let mut normal = Vec::from(&br"\\"[..]);
+        normal.extend_from_slice(&path[8..]);
+        Cow::Owned(normal)
+    } else if path.starts_with(br"\\?\") {
+        match path {
+            Cow::Borrowed(path) => Cow::Borrowed(&path[4..]),
+            Cow::Owned(mut path) => {
+                path.drain_bytes(..4);
+                Cow::Owned(path)
+            }
+        }
+    } else {
+        path
+    }
+}
+
+/// Rewrites a Windows extended-length path into its normal form.
+///
+/// No other platform has such a form, so this is a no-op.
+#[cfg(not(windows))]
+pub(crate) fn normalize_verbatim(path: Cow<'_, [u8]>) -> Cow<'_, [u8]> {
+    path
+}
+
+/// Reduces the given path to a form in which Windows extended-length
+/// prefixes never appear, for comparing paths that may disagree about
+/// carrying one.
+///
+/// Both `\\?\C:\x` and `C:\x` reduce to `C:\x`, while `\\?\UNC\server\x`
+/// and `\\server\x` both reduce to `server\x`. (The latter cannot keep its
+/// leading `\\` without allocating, so UNC paths of both forms lose it.)
+/// The rare prefixed path whose bytes aren't valid Unicode is returned
+/// unchanged, since its prefix can't be reliably recognized byte-wise.
+#[cfg(windows)]
+pub(crate) fn strip_verbatim_prefix(path: &Path) -> &Path {
+    fn strip<'a>(prefix: &str, path: &'a Path) -> Option<&'a Path> {
+        path.to_str()?.strip_prefix(prefix).map(Path::new)
+    }
+    if let Some(path) = strip(r"\\?\UNC\", path) {
+        path
+    } else if let Some(path) = strip(r"\\?\", path) {
+        path
+    } else if let Some(stripped) = strip(r"\\", path) {
+        stripped
+    } else {
+        path
+    }
+}
+
+/// Reduces the given path to a form in which Windows extended-length
+/// prefixes never appear.
+///
+/// No other platform has such prefixes, so this is a no-op.
+#[cfg(not(windows))]
+pub(crate) fn strip_verbatim_prefix(path: &Path) -> &Path {
+    path
+}
+
+/// Strip `prefix` from the `path` and return the remainder.
+///
+/// If `path` doesn't have a prefix `prefix`, then return `None`. Note that
+/// unlike `std::path::Path::strip_prefix`, this strips a byte-wise prefix
+/// on Unix, not a component-wise one.
+#[cfg(unix)]
+pub(crate) fn strip_prefix<'a, P: AsRef<Path> + ?Sized>(
+    prefix: &P,
+    path: &'a Path,
+) -> Option<&'a Path> {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let prefix = prefix.as_ref().as_os_str().as_bytes();
+    let bytes = path.as_os_str().as_bytes();
+    if prefix.len() > bytes.len() || prefix != &bytes[0..prefix.len()] {
+        None
+    } else {
+        Some(Path::new(OsStr::from_bytes(&bytes[prefix.len()..])))
+    }
+}
+
+/// Strip `prefix` from the `path` and return the remainder.
+///
+/// If `path` doesn't have a prefix `prefix`, then return `None`.
+#[cfg(not(unix))]
+pub(crate) fn strip_prefix<'a, P: AsRef<Path> + ?Sized>(
+    prefix: &P,
+    path: &'a Path,
+) -> Option<&'a Path> {
+    path.strip_prefix(prefix.as_ref()).ok()
+}
+
+/// Returns true if this file path is just a file name. i.e., Its parent is
+/// the empty string.
+#[cfg(unix)]
+pub(crate) fn is_file_name<P: AsRef<Path>>(path: P) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    path.as_ref().as_os_str().as_bytes().find_byte(b'/').is_none()
+}
+
+/// Returns true if this file path is just a file name. i.e., Its parent is
+/// the empty string.
+#[cfg(not(unix))]
+pub(crate) fn is_file_name<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().parent().map(|p| p.as_os_str().is_empty()).unwrap_or(false)
+}
+
 #[cfg(test)]
 mod tests {
     use std::borrow::Cow;
@@ -0,0 +1,60 @@
+/*!
+A micro-benchmark for prefetching gitignore matchers ahead of a walk.
+
+This generates a synthetic directory tree containing a few thousand
+`.gitignore` files and compares a fully lazy walk, where each ignore file is
+parsed as its directory is first visited, with a prefetching walk, where all
+ignore files are parsed up front on multiple threads. It also reports the raw
+cost of `gitignore::build_many` on the generated files. Run it with an
+optional number of directories (defaulting to 5000):
+
+    cargo run --release --example gitignore-prefetch [count]
+*/
+
+use std::{env, time::Instant};
+
+use ignore::{
+    gitignore::{build_many, GitignoreOptions},
+    WalkBuilder,
+};
+
+fn main() {
+    let count = env::args()
+        .nth(1)
+        .map(|arg| arg.parse().unwrap())
+        .unwrap_or(5000usize);
+
+    let root = env::temp_dir()
+        .join(format!("ignore-gitignore-prefetch-{}", std::process::id()));
+    let mut gitignores = vec![];
+    for i in 0..count {
+        // A shallow fan-out keeps any one directory from getting huge.
+        let dir = root.join(format!("d{}", i / 100)).join(format!("d{}", i));
+        std::fs::create_dir_all(&dir).unwrap();
+        let gitignore = dir.join(".gitignore");
+        std::fs::write(&gitignore, "*.log\n!keep.log\ntarget/\n").unwrap();
+        std::fs::write(dir.join("keep.log"), "").unwrap();
+        gitignores.push(gitignore);
+    }
+
+    let start = Instant::now();
+    let results = build_many(&gitignores, &GitignoreOptions::default());
+    println!("build_many ({} files): {:?}", results.len(), start.elapsed());
+
+    let start = Instant::now();
+    let lazy = walk_count(WalkBuilder::new(&root));
+    println!("lazy walk: {:?}", start.elapsed());
+
+    let start = Instant::now();
+    let mut builder = WalkBuilder::new(&root);
+    builder.prefetch_gitignores(true);
+    let prefetched = walk_count(builder);
+    println!("prefetched walk: {:?}", start.elapsed());
+
+    assert_eq!(lazy, prefetched);
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+fn walk_count(builder: WalkBuilder) -> usize {
+    builder.build().filter_map(Result::ok).count()
+}
@@ -0,0 +1,61 @@
+/*!
+A micro-benchmark comparing the cost of constructing walkers from scratch
+with the cost of constructing them from pre-built matchers.
+
+This simulates a server that builds a `WalkBuilder` per request: without
+pre-built components, every build re-reads the global gitignore file and
+re-compiles override and type matchers. Run it with an optional directory
+argument (defaulting to the current directory):
+
+    cargo run --release --example walk-prebuilt [path]
+*/
+
+use std::{env, time::Instant};
+
+use ignore::{
+    gitignore::Gitignore, overrides::OverrideBuilder, types::TypesBuilder,
+    WalkBuilder,
+};
+
+const ITERS: usize = 1000;
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| ".".to_string());
+
+    let start = Instant::now();
+    for _ in 0..ITERS {
+        let mut ob = OverrideBuilder::new(&path);
+        ob.add("!*.log").unwrap();
+        let mut tb = TypesBuilder::new();
+        tb.add_defaults();
+        tb.select("rust");
+
+        let mut builder = WalkBuilder::new(&path);
+        builder.overrides(ob.build().unwrap());
+        builder.types(tb.build().unwrap());
+        drop(builder.build());
+    }
+    println!("from scratch: {:?}", start.elapsed());
+
+    let (global, err) = Gitignore::global();
+    if let Some(err) = err {
+        eprintln!("error reading global gitignore: {}", err);
+    }
+    let mut ob = OverrideBuilder::new(&path);
+    ob.add("!*.log").unwrap();
+    let overrides = ob.build().unwrap();
+    let mut tb = TypesBuilder::new();
+    tb.add_defaults();
+    tb.select("rust");
+    let types = tb.build().unwrap();
+
+    let start = Instant::now();
+    for _ in 0..ITERS {
+        let mut builder = WalkBuilder::new(&path);
+        builder.global_gitignore(global.clone());
+        builder.overrides(overrides.clone());
+        builder.types(types.clone());
+        drop(builder.build());
+    }
+    println!("pre-built: {:?}", start.elapsed());
+}
@@ -0,0 +1,19 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use ignore::gitignore::GitignoreBuilder;
+
+fuzz_target!(|data: &[u8]| {
+    // Parse the input as a whole gitignore file. Errors are fine here; the
+    // point is that parsing must not panic, and that a bad line must never
+    // prevent the lines after it from being read.
+    let mut builder = GitignoreBuilder::new("/");
+    let _ = builder.add_reader(None, data);
+    // And again line by line through the glob parser.
+    let mut builder = GitignoreBuilder::new("/");
+    for line in data.split(|&b| b == b'\n') {
+        let _ = builder.add_line(None, &String::from_utf8_lossy(line));
+    }
+    let _ = builder.build();
+});
@@ -0,0 +1,184 @@
+/*!
+The allowlist module provides a way to restrict a directory traversal to an
+explicit set of file paths, such as a manifest produced by a build system.
+*/
+
+use std::{
+    collections::HashSet,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+/// An allowlist of file paths to which a directory traversal is restricted.
+///
+/// An allowlist is the inverse of an ignore matcher: instead of dropping
+/// paths that match a set of globs, the traversal yields only the files that
+/// are members of the allowlist. This is useful when an explicit manifest of
+/// project files exists, e.g., as produced by a build system. Ignore rules
+/// still apply on top, so manifest entries that are, say, gitignored are not
+/// yielded.
+///
+/// Membership is decided by exact path comparison (after light normalization
+/// that removes `.` components and resolves `..` lexically), not by glob
+/// matching. Paths are interpreted as relative to the root of the traversal
+/// they are used with. In exchange for exactness, an allowlist supports
+/// cheap directory pruning: in addition to its files, it records the set of
+/// their ancestor directories, so a traversal can skip descending into any
+/// directory that cannot contain a member. This keeps walks cheap even for
+/// very large manifests over much larger trees.
+///
+/// Paths in the allowlist that don't exist on the file system are simply
+/// never yielded. Cloning an `Allowlist` is cheap; the underlying set is
+/// shared.
+///
+/// To use an allowlist, build one with [`Allowlist::new`] and attach it to a
+/// walk via [`WalkBuilder::allowlist`](crate::WalkBuilder::allowlist).
+#[derive(Clone, Debug)]
+pub struct Allowlist(Arc<AllowlistInner>);
+
+#[derive(Debug)]
+struct AllowlistInner {
+    /// The normalized relative paths of the files in this allowlist.
+    files: HashSet<PathBuf>,
+    /// Every proper ancestor of every file in `files`, excluding the empty
+    /// root path. A traversal may only descend into directories in this set.
+    dirs: HashSet<PathBuf>,
+}
+
+impl Allowlist {
+    /// Creates a new allowlist from an iterator of file paths.
+    ///
+    /// Each path is normalized by removing `.` components and resolving
+    /// `..` components lexically. Paths that escape the root via `..` are
+    /// silently dropped. A leading root or drive prefix is ignored, so
+    /// absolute paths are treated as relative to the traversal root.
+    pub fn new<P, I>(paths: I) -> Allowlist
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut files = HashSet::new();
+        let mut dirs = HashSet::new();
+        for path in paths {
+            let Some(file) = normalize(path.as_ref()) else { continue };
+            if file.as_os_str().is_empty() {
+                continue;
+            }
+            let mut dir = file.clone();
+            while dir.pop() {
+                if dir.as_os_str().is_empty() {
+                    break;
+                }
+                // Ancestors of a previous entry are already recorded.
+                if !dirs.insert(dir.clone()) {
+                    break;
+                }
+            }
+            files.insert(file);
+        }
+        Allowlist(Arc::new(AllowlistInner { files, dirs }))
+    }
+
+    /// Returns the number of file paths in this allowlist.
+    pub fn len(&self) -> usize {
+        self.0.files.len()
+    }
+
+    /// Returns true if this allowlist contains no file paths.
+    ///
+    /// Note that attaching an empty allowlist to a walk restricts it to
+    /// yielding nothing (other than the walk's roots), which is quite
+    /// different from attaching no allowlist at all.
+    pub fn is_empty(&self) -> bool {
+        self.0.files.is_empty()
+    }
+
+    /// Returns true if the file at the given root-relative path is a member
+    /// of this allowlist.
+    pub(crate) fn is_allowed_file(&self, path: &Path) -> bool {
+        match normalize(path) {
+            None => false,
+            Some(path) => self.0.files.contains(&path),
+        }
+    }
+
+    /// Returns true if a traversal may descend into the directory at the
+    /// given root-relative path, i.e., if some member of this allowlist
+    /// resides beneath it. The root itself (the empty path) may always be
+    /// descended into.
+    pub(crate) fn is_allowed_dir(&self, path: &Path) -> bool {
+        match normalize(path) {
+            None => false,
+            Some(path) => {
+                path.as_os_str().is_empty() || self.0.dirs.contains(&path)
+            }
+        }
+    }
+}
+
+/// Normalizes the path given to the form used for membership tests: `.`
+/// components are dropped, `..` components are resolved lexically and any
+/// root or drive prefix is ignored. Returns `None` when the path escapes its
+/// root via `..`.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::Normal(name) => normalized.push(name),
+        }
+    }
+    Some(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Allowlist;
+
+    #[test]
+    fn files_and_dirs() {
+        let allow = Allowlist::new(["src/lib.rs", "src/walk/mod.rs", "COPY"]);
+        assert_eq!(3, allow.len());
+        assert!(allow.is_allowed_file(Path::new("src/lib.rs")));
+        assert!(allow.is_allowed_file(Path::new("COPY")));
+        assert!(!allow.is_allowed_file(Path::new("src/main.rs")));
+        // A directory is never a member, even when listed as a file's parent.
+        assert!(!allow.is_allowed_file(Path::new("src")));
+
+        assert!(allow.is_allowed_dir(Path::new("")));
+        assert!(allow.is_allowed_dir(Path::new("src")));
+        assert!(allow.is_allowed_dir(Path::new("src/walk")));
+        assert!(!allow.is_allowed_dir(Path::new("tests")));
+        assert!(!allow.is_allowed_dir(Path::new("src/lib.rs")));
+    }
+
+    #[test]
+    fn normalization() {
+        let allow = Allowlist::new(["./a/./b.rs", "c/../d.rs", "/e.rs"]);
+        assert!(allow.is_allowed_file(Path::new("a/b.rs")));
+        assert!(allow.is_allowed_file(Path::new("d.rs")));
+        assert!(allow.is_allowed_file(Path::new("e.rs")));
+        assert!(allow.is_allowed_file(Path::new("./a/b.rs")));
+        // Entries that escape the root are dropped.
+        let allow = Allowlist::new(["../evil.rs"]);
+        assert!(allow.is_empty());
+        assert!(!allow.is_allowed_file(Path::new("evil.rs")));
+    }
+
+    #[test]
+    fn empty() {
+        let allow = Allowlist::new(std::iter::empty::<&str>());
+        assert!(allow.is_empty());
+        assert_eq!(0, allow.len());
+        assert!(!allow.is_allowed_file(Path::new("a")));
+        assert!(allow.is_allowed_dir(Path::new("")));
+        assert!(!allow.is_allowed_dir(Path::new("a")));
+    }
+}
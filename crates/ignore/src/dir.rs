@@ -20,6 +20,7 @@ use std::{
     io::{self, BufRead},
     path::{Path, PathBuf},
     sync::{Arc, RwLock, Weak},
+    time::Instant,
 };
 
 use crate::{
@@ -27,7 +28,10 @@ use crate::{
     overrides::{self, Override},
     pathutil::{is_hidden, strip_prefix},
     types::{self, Types},
-    walk::DirEntry,
+    walk::{
+        CustomIgnoreOpts, DirEntry, HiddenMode, IgnoreCaseMode,
+        IgnoreFileEvent, IgnoreFileKind, SubmoduleMode,
+    },
     {Error, Match, PartialErrorBuilder},
 };
 
@@ -46,6 +50,7 @@ enum IgnoreMatchInner<'a> {
     Gitignore(&'a gitignore::Glob),
     Types(types::Glob<'a>),
     Hidden,
+    Submodule,
 }
 
 impl<'a> IgnoreMatch<'a> {
@@ -64,6 +69,34 @@ impl<'a> IgnoreMatch<'a> {
     fn hidden() -> IgnoreMatch<'static> {
         IgnoreMatch(IgnoreMatchInner::Hidden)
     }
+
+    fn submodule() -> IgnoreMatch<'static> {
+        IgnoreMatch(IgnoreMatchInner::Submodule)
+    }
+
+    /// Returns the gitignore glob responsible for this match, if this match
+    /// came from a gitignore-style rule.
+    pub(crate) fn gitignore_glob(&self) -> Option<&'a gitignore::Glob> {
+        match self.0 {
+            IgnoreMatchInner::Gitignore(glob) => Some(glob),
+            _ => None,
+        }
+    }
+}
+
+/// A callback invoked for every ignore file read while building matchers.
+///
+/// This is a newtype so that `IgnoreInner` and `IgnoreBuilder` can keep
+/// deriving `Debug`.
+#[derive(Clone)]
+pub(crate) struct IgnoreFileCallback(
+    Arc<dyn Fn(IgnoreFileEvent) + Send + Sync>,
+);
+
+impl std::fmt::Debug for IgnoreFileCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IgnoreFileCallback").finish_non_exhaustive()
+    }
 }
 
 /// Options for the ignore matcher, shared between the matcher itself and the
@@ -72,6 +105,8 @@ impl<'a> IgnoreMatch<'a> {
 struct IgnoreOptions {
     /// Whether to ignore hidden file paths or not.
     hidden: bool,
+    /// The strategy used to decide whether a file is hidden.
+    hidden_mode: HiddenMode,
     /// Whether to read .ignore files.
     ignore: bool,
     /// Whether to respect any ignore files in parent directories.
@@ -82,11 +117,13 @@ struct IgnoreOptions {
     git_ignore: bool,
     /// Whether to read .git/info/exclude files.
     git_exclude: bool,
-    /// Whether to ignore files case insensitively
-    ignore_case_insensitive: bool,
+    /// Whether to match globs in ignore files case insensitively.
+    ignore_case_insensitive: IgnoreCaseMode,
     /// Whether a git repository must be present in order to apply any
     /// git-related ignore rules.
     require_git: bool,
+    /// How git submodules encountered during traversal are handled.
+    git_submodules: SubmoduleMode,
 }
 
 /// Ignore is a matcher useful for recursively walking one or more directories.
@@ -120,10 +157,12 @@ struct IgnoreInner {
     absolute_base: Option<Arc<PathBuf>>,
     /// Explicit global ignore matchers specified by the caller.
     explicit_ignores: Arc<Vec<Gitignore>>,
-    /// Ignore files used in addition to `.ignore`
-    custom_ignore_filenames: Arc<Vec<OsString>>,
-    /// The matcher for custom ignore files
-    custom_ignore_matcher: Gitignore,
+    /// Ignore files used in addition to `.ignore`, along with the options
+    /// controlling how each participates in matching.
+    custom_ignore_filenames: Arc<Vec<(OsString, CustomIgnoreOpts)>>,
+    /// The matchers for custom ignore files, one per file name, in the same
+    /// order as `custom_ignore_filenames`.
+    custom_ignore_matchers: Vec<(Gitignore, CustomIgnoreOpts)>,
     /// The matcher for .ignore files.
     ignore_matcher: Gitignore,
     /// A global gitignore matcher, usually from $XDG_CONFIG_HOME/git/ignore.
@@ -132,8 +171,29 @@ struct IgnoreInner {
     git_ignore_matcher: Gitignore,
     /// Special matcher for `.git/info/exclude` files.
     git_exclude_matcher: Gitignore,
+    /// Matchers for ignore files that were parsed ahead of the walk, keyed
+    /// by the path of the ignore file they were built from, along with the
+    /// case sensitivity they were built with. Consulted before reading
+    /// ignore files lazily.
+    prefetched: Option<Arc<(bool, HashMap<PathBuf, Gitignore>)>>,
+    /// A callback invoked for every ignore file read while building
+    /// matchers.
+    on_ignore_file: Option<IgnoreFileCallback>,
     /// Whether this directory contains a .git sub-directory.
     has_git: bool,
+    /// Whether globs in this directory's ignore files are matched case
+    /// insensitively. In `IgnoreCaseMode::Auto`, this is resolved from git's
+    /// `core.ignoreCase` setting at each repository root and inherited by
+    /// the directories below it.
+    case_insensitive: bool,
+    /// The submodule paths listed in this directory's `.gitmodules` file.
+    /// Only populated when submodule handling is enabled, i.e., when
+    /// `git_submodules` is not `SubmoduleMode::Descend`.
+    gitmodules: Option<Arc<Vec<PathBuf>>>,
+    /// Whether this directory is a submodule root being traversed in
+    /// `SubmoduleMode::DescendIsolated` mode. When true, matchers above this
+    /// one in the chain do not apply to paths below it.
+    isolated: bool,
     /// Ignore config.
     opts: IgnoreOptions,
 }
@@ -252,42 +312,99 @@ impl Ignore {
             None
         };
         let has_git = git_type.map(|_| true).unwrap_or(false);
+        let case_insensitive = match self.0.opts.ignore_case_insensitive {
+            IgnoreCaseMode::Always => true,
+            IgnoreCaseMode::Never => false,
+            // At a repository root, resolve the repository's own
+            // `core.ignoreCase`. Everywhere else, inherit the value resolved
+            // at the nearest enclosing repository root (or at the top of the
+            // walk, when there is none).
+            IgnoreCaseMode::Auto if has_git => {
+                let git_dir = resolve_git_commondir(dir, git_type)
+                    .unwrap_or_else(|_| dir.join(".git"));
+                gitignore::gitconfig_ignore_case(Some(&git_dir))
+                    .unwrap_or(false)
+            }
+            IgnoreCaseMode::Auto => self.0.case_insensitive,
+        };
 
         let mut errs = PartialErrorBuilder::default();
-        let custom_ig_matcher = if self.0.custom_ignore_filenames.is_empty() {
-            Gitignore::empty()
-        } else {
-            let (m, err) = create_gitignore(
-                &dir,
-                &dir,
-                &self.0.custom_ignore_filenames,
-                self.0.opts.ignore_case_insensitive,
-            );
-            errs.maybe_push(err);
-            m
-        };
+        let custom_ig_matchers = self
+            .0
+            .custom_ignore_filenames
+            .iter()
+            .map(|&(ref name, opts)| {
+                let started = self.ignore_file_timer();
+                let (m, err) = create_gitignore_with_opts(
+                    &dir,
+                    &dir,
+                    &[name],
+                    case_insensitive,
+                    opts.anchored_only,
+                );
+                errs.maybe_push(err);
+                self.report_ignore_file(
+                    started,
+                    dir.join(name),
+                    IgnoreFileKind::Custom,
+                    &m,
+                    false,
+                );
+                (m, opts)
+            })
+            .collect();
         let ig_matcher = if !self.0.opts.ignore {
             Gitignore::empty()
         } else {
-            let (m, err) = create_gitignore(
-                &dir,
-                &dir,
-                &[".ignore"],
-                self.0.opts.ignore_case_insensitive,
+            let started = self.ignore_file_timer();
+            let (m, cached) = if let Some(m) =
+                self.prefetched(dir, ".ignore", case_insensitive)
+            {
+                (m, true)
+            } else {
+                let (m, err) = create_gitignore(
+                    &dir,
+                    &dir,
+                    &[".ignore"],
+                    case_insensitive,
+                );
+                errs.maybe_push(err);
+                (m, false)
+            };
+            self.report_ignore_file(
+                started,
+                dir.join(".ignore"),
+                IgnoreFileKind::Ignore,
+                &m,
+                cached,
             );
-            errs.maybe_push(err);
             m
         };
         let gi_matcher = if !self.0.opts.git_ignore {
             Gitignore::empty()
         } else {
-            let (m, err) = create_gitignore(
-                &dir,
-                &dir,
-                &[".gitignore"],
-                self.0.opts.ignore_case_insensitive,
+            let started = self.ignore_file_timer();
+            let (m, cached) = if let Some(m) =
+                self.prefetched(dir, ".gitignore", case_insensitive)
+            {
+                (m, true)
+            } else {
+                let (m, err) = create_gitignore(
+                    &dir,
+                    &dir,
+                    &[".gitignore"],
+                    case_insensitive,
+                );
+                errs.maybe_push(err);
+                (m, false)
+            };
+            self.report_ignore_file(
+                started,
+                dir.join(".gitignore"),
+                IgnoreFileKind::Gitignore,
+                &m,
+                cached,
             );
-            errs.maybe_push(err);
             m
         };
         let gi_exclude_matcher = if !self.0.opts.git_exclude {
@@ -295,13 +412,21 @@ impl Ignore {
         } else {
             match resolve_git_commondir(dir, git_type) {
                 Ok(git_dir) => {
+                    let started = self.ignore_file_timer();
                     let (m, err) = create_gitignore(
                         &dir,
                         &git_dir,
                         &["info/exclude"],
-                        self.0.opts.ignore_case_insensitive,
+                        case_insensitive,
                     );
                     errs.maybe_push(err);
+                    self.report_ignore_file(
+                        started,
+                        git_dir.join("info/exclude"),
+                        IgnoreFileKind::GitExclude,
+                        &m,
+                        false,
+                    );
                     m
                 }
                 Err(err) => {
@@ -310,6 +435,15 @@ impl Ignore {
                 }
             }
         };
+        let gitmodules =
+            if self.0.opts.git_submodules == SubmoduleMode::Descend {
+                None
+            } else {
+                parse_gitmodules(&dir.join(".gitmodules")).map(Arc::new)
+            };
+        let isolated = self.0.opts.git_submodules
+            == SubmoduleMode::DescendIsolated
+            && self.is_submodule_root(dir);
         let ig = IgnoreInner {
             compiled: self.0.compiled.clone(),
             dir: dir.to_path_buf(),
@@ -320,17 +454,79 @@ impl Ignore {
             absolute_base: self.0.absolute_base.clone(),
             explicit_ignores: self.0.explicit_ignores.clone(),
             custom_ignore_filenames: self.0.custom_ignore_filenames.clone(),
-            custom_ignore_matcher: custom_ig_matcher,
+            custom_ignore_matchers: custom_ig_matchers,
             ignore_matcher: ig_matcher,
             git_global_matcher: self.0.git_global_matcher.clone(),
             git_ignore_matcher: gi_matcher,
             git_exclude_matcher: gi_exclude_matcher,
+            prefetched: self.0.prefetched.clone(),
+            on_ignore_file: self.0.on_ignore_file.clone(),
             has_git,
+            case_insensitive,
+            gitmodules,
+            isolated,
             opts: self.0.opts,
         };
         (ig, errs.into_error_option())
     }
 
+    /// Returns a pre-built matcher for the ignore file `name` in `dir`, if
+    /// one was prefetched.
+    ///
+    /// Only matchers whose build produced no error are prefetched, so a miss
+    /// here falls back to reading the file lazily. Matchers built with a
+    /// different case sensitivity than the one resolved for `dir` (which can
+    /// happen in `IgnoreCaseMode::Auto` when a repository's config disagrees
+    /// with the global one) are likewise skipped.
+    fn prefetched(
+        &self,
+        dir: &Path,
+        name: &str,
+        case_insensitive: bool,
+    ) -> Option<Gitignore> {
+        let (prefetched_ci, ref map) = **self.0.prefetched.as_ref()?;
+        if prefetched_ci != case_insensitive {
+            return None;
+        }
+        map.get(&dir.join(name)).cloned()
+    }
+
+    /// Returns the time at which building an ignore file matcher started,
+    /// but only when an ignore file callback is set. This avoids reading
+    /// the clock on the common uninstrumented path.
+    fn ignore_file_timer(&self) -> Option<Instant> {
+        self.0.on_ignore_file.as_ref().map(|_| Instant::now())
+    }
+
+    /// Reports an ignore file read to the callback, if one is set.
+    ///
+    /// `started` should come from `ignore_file_timer`. Events for ignore
+    /// files that do not exist are suppressed, since no file was actually
+    /// read. Cache hits are always reported, as a cached matcher always
+    /// corresponds to a file that was read before the walk started.
+    fn report_ignore_file(
+        &self,
+        started: Option<Instant>,
+        path: PathBuf,
+        kind: IgnoreFileKind,
+        matcher: &Gitignore,
+        cached: bool,
+    ) {
+        let Some(ref callback) = self.0.on_ignore_file else { return };
+        let Some(started) = started else { return };
+        let duration = started.elapsed();
+        if !cached && !path.exists() {
+            return;
+        }
+        (callback.0)(IgnoreFileEvent {
+            path,
+            kind,
+            duration,
+            glob_count: matcher.len() as u64,
+            cached,
+        });
+    }
+
     /// Returns true if at least one type of ignore rule should be matched.
     fn has_any_ignore_rules(&self) -> bool {
         let opts = self.0.opts;
@@ -351,8 +547,28 @@ impl Ignore {
         &'a self,
         dent: &DirEntry,
     ) -> Match<IgnoreMatch<'a>> {
-        let m = self.matched(dent.path(), dent.is_dir());
-        if m.is_none() && self.0.opts.hidden && is_hidden(dent) {
+        // This implements the same policy as `gitignore::dir_hint_for`, but
+        // uses the entry's cached file type to avoid touching the file
+        // system again: a symlink is matched as a file (as git does), unless
+        // the walker is following symlinks, in which case the entry's file
+        // type is already that of the symlink's target.
+        let is_dir = dent
+            .file_type()
+            .map_or(false, |ft| !ft.is_symlink() && ft.is_dir());
+        // In submodule skip mode, a submodule root is skipped outright,
+        // before any ignore rules are consulted. This matches `git grep`'s
+        // default behavior of not recursing into submodules.
+        if self.0.opts.git_submodules == SubmoduleMode::Skip
+            && is_dir
+            && self.is_submodule_root(dent.path())
+        {
+            return Match::Ignore(IgnoreMatch::submodule());
+        }
+        let m = self.matched(dent.path(), is_dir);
+        if m.is_none()
+            && self.0.opts.hidden
+            && is_hidden(dent, self.0.opts.hidden_mode)
+        {
             return Match::Ignore(IgnoreMatch::hidden());
         }
         m
@@ -425,12 +641,11 @@ impl Ignore {
         let any_git =
             !self.0.opts.require_git || self.parents().any(|ig| ig.0.has_git);
         let mut saw_git = false;
+        let mut saw_submodule = false;
         for ig in self.parents().take_while(|ig| !ig.0.is_absolute_parent) {
             if m_custom_ignore.is_none() {
                 m_custom_ignore =
-                    ig.0.custom_ignore_matcher
-                        .matched(path, is_dir)
-                        .map(IgnoreMatch::gitignore);
+                    ig.matched_custom_ignore(path, is_dir, false);
             }
             if m_ignore.is_none() {
                 m_ignore =
@@ -451,8 +666,16 @@ impl Ignore {
                         .map(IgnoreMatch::gitignore);
             }
             saw_git = saw_git || ig.0.has_git;
+            // A submodule boundary in isolated mode cuts the chain: ignore
+            // rules from directories above the submodule do not apply inside
+            // it. The boundary matcher itself still applies, since it holds
+            // the ignore files at the submodule's root.
+            if ig.0.isolated {
+                saw_submodule = true;
+                break;
+            }
         }
-        if self.0.opts.parents {
+        if self.0.opts.parents && !saw_submodule {
             if let Some(abs_parent_path) = self.absolute_base() {
                 // What we want to do here is take the absolute base path of
                 // this directory and join it with the path we're searching.
@@ -482,9 +705,7 @@ impl Ignore {
                 {
                     if m_custom_ignore.is_none() {
                         m_custom_ignore =
-                            ig.0.custom_ignore_matcher
-                                .matched(&path, is_dir)
-                                .map(IgnoreMatch::gitignore);
+                            ig.matched_custom_ignore(&path, is_dir, true);
                     }
                     if m_ignore.is_none() {
                         m_ignore =
@@ -531,6 +752,51 @@ impl Ignore {
             .or(m_explicit)
     }
 
+    /// Performs matching against the custom ignore files in this directory,
+    /// respecting the options attached to each custom ignore file name.
+    ///
+    /// `in_parent` should be true when this matcher corresponds to a parent
+    /// directory of the paths being walked, as added by `add_parents`.
+    ///
+    /// Later file names have higher precedence, so the matchers are consulted
+    /// in reverse order and the first authoritative decision wins.
+    fn matched_custom_ignore<'a>(
+        &'a self,
+        path: &Path,
+        is_dir: bool,
+        in_parent: bool,
+    ) -> Match<IgnoreMatch<'a>> {
+        for &(ref gi, opts) in self.0.custom_ignore_matchers.iter().rev() {
+            if in_parent && !opts.parents {
+                continue;
+            }
+            let m = gi.matched(path, is_dir);
+            if m.is_whitelist() && !opts.allow_whitelist {
+                continue;
+            }
+            if !m.is_none() {
+                return m.map(IgnoreMatch::gitignore);
+            }
+        }
+        Match::None
+    }
+
+    /// Returns true if `path` is the root directory of a git submodule,
+    /// i.e., it is listed in the `.gitmodules` file of some ancestor
+    /// directory and has its own `.git` (a file for submodules created by
+    /// modern git, or a directory for older ones).
+    ///
+    /// This never returns true when submodule handling is disabled, since
+    /// `.gitmodules` files are only parsed when it's enabled.
+    fn is_submodule_root(&self, path: &Path) -> bool {
+        let listed = self.parents().any(|ig| {
+            let Some(ref modules) = ig.0.gitmodules else { return false };
+            let Ok(rel) = path.strip_prefix(&ig.0.dir) else { return false };
+            modules.iter().any(|p| p == rel)
+        });
+        listed && path.join(".git").exists()
+    }
+
     /// Returns an iterator over parent ignore matchers, including this one.
     pub(crate) fn parents(&self) -> Parents<'_> {
         Parents(Some(self))
@@ -571,10 +837,21 @@ pub(crate) struct IgnoreBuilder {
     overrides: Arc<Override>,
     /// A type matcher (default is empty).
     types: Arc<Types>,
+    /// A pre-built global gitignore matcher supplied by the caller. When
+    /// present, it is used in lieu of reading and parsing the global
+    /// gitignore file on every build.
+    global_gitignore: Option<Gitignore>,
     /// Explicit global ignore matchers.
     explicit_ignores: Vec<Gitignore>,
-    /// Ignore files in addition to .ignore.
-    custom_ignore_filenames: Vec<OsString>,
+    /// Ignore files in addition to .ignore, along with their options.
+    custom_ignore_filenames: Vec<(OsString, CustomIgnoreOpts)>,
+    /// Matchers for ignore files that were parsed ahead of the walk, as
+    /// populated by `prefetch_gitignores`, along with the case sensitivity
+    /// they were built with.
+    prefetched: Option<Arc<(bool, HashMap<PathBuf, Gitignore>)>>,
+    /// A callback invoked for every ignore file read while building
+    /// matchers.
+    on_ignore_file: Option<IgnoreFileCallback>,
     /// Ignore config.
     opts: IgnoreOptions,
 }
@@ -589,17 +866,22 @@ impl IgnoreBuilder {
             dir: Path::new("").to_path_buf(),
             overrides: Arc::new(Override::empty()),
             types: Arc::new(Types::empty()),
+            global_gitignore: None,
             explicit_ignores: vec![],
             custom_ignore_filenames: vec![],
+            prefetched: None,
+            on_ignore_file: None,
             opts: IgnoreOptions {
                 hidden: true,
+                hidden_mode: HiddenMode::default(),
                 ignore: true,
                 parents: true,
                 git_global: true,
                 git_ignore: true,
                 git_exclude: true,
-                ignore_case_insensitive: false,
+                ignore_case_insensitive: IgnoreCaseMode::Auto,
                 require_git: true,
+                git_submodules: SubmoduleMode::Descend,
             },
         }
     }
@@ -609,17 +891,36 @@ impl IgnoreBuilder {
     /// The matcher returned won't match anything until ignore rules from
     /// directories are added to it.
     pub(crate) fn build(&self) -> Ignore {
+        let case_insensitive = self.resolved_case_insensitive();
         let git_global_matcher = if !self.opts.git_global {
             Gitignore::empty()
+        } else if let Some(ref gi) = self.global_gitignore {
+            gi.clone()
         } else {
+            let started = self.on_ignore_file.as_ref().map(|_| Instant::now());
             let mut builder = GitignoreBuilder::new("");
-            builder
-                .case_insensitive(self.opts.ignore_case_insensitive)
-                .unwrap();
+            builder.case_insensitive(case_insensitive).unwrap();
             let (gi, err) = builder.build_global();
             if let Some(err) = err {
                 log::debug!("{}", err);
             }
+            if let (Some(ref callback), Some(started)) =
+                (self.on_ignore_file.as_ref(), started)
+            {
+                // `build_global` only reads the file when it exists, so
+                // check again here to avoid reporting phantom reads.
+                if let Some(path) = gitignore::gitconfig_excludes_path() {
+                    if path.is_file() {
+                        (callback.0)(IgnoreFileEvent {
+                            path,
+                            kind: IgnoreFileKind::GitGlobal,
+                            duration: started.elapsed(),
+                            glob_count: gi.len() as u64,
+                            cached: false,
+                        });
+                    }
+                }
+            }
             gi
         };
 
@@ -635,12 +936,17 @@ impl IgnoreBuilder {
             custom_ignore_filenames: Arc::new(
                 self.custom_ignore_filenames.clone(),
             ),
-            custom_ignore_matcher: Gitignore::empty(),
+            custom_ignore_matchers: vec![],
             ignore_matcher: Gitignore::empty(),
             git_global_matcher: Arc::new(git_global_matcher),
             git_ignore_matcher: Gitignore::empty(),
             git_exclude_matcher: Gitignore::empty(),
+            prefetched: self.prefetched.clone(),
+            on_ignore_file: self.on_ignore_file.clone(),
             has_git: false,
+            case_insensitive,
+            gitmodules: None,
+            isolated: false,
             opts: self.opts,
         }))
     }
@@ -668,6 +974,83 @@ impl IgnoreBuilder {
         self
     }
 
+    /// Use a pre-built global gitignore matcher instead of reading and
+    /// parsing the global gitignore file when this builder is built.
+    ///
+    /// This has no effect when reading the global gitignore is disabled via
+    /// `git_global`.
+    pub(crate) fn global_gitignore(
+        &mut self,
+        gi: Gitignore,
+    ) -> &mut IgnoreBuilder {
+        self.global_gitignore = Some(gi);
+        self
+    }
+
+    /// Scans the given directories for ignore files and pre-builds matchers
+    /// for all of them before the walk begins.
+    ///
+    /// The pre-built matchers are stored on the `Ignore` built by this
+    /// builder and consulted before reading ignore files lazily. Only
+    /// `.ignore` and `.gitignore` files are prefetched, and only when the
+    /// corresponding options are enabled. The scan does not follow symbolic
+    /// links, so ignore files reached only through a symbolic link are still
+    /// read lazily. As with lazy loading, all I/O errors are ignored.
+    pub(crate) fn prefetch_gitignores(
+        &mut self,
+        roots: &[PathBuf],
+    ) -> &mut IgnoreBuilder {
+        let mut names = vec![];
+        if self.opts.ignore {
+            names.push(OsStr::new(".ignore"));
+        }
+        if self.opts.git_ignore {
+            names.push(OsStr::new(".gitignore"));
+        }
+        if names.is_empty() {
+            return self;
+        }
+        let mut paths = vec![];
+        for root in roots {
+            for result in walkdir::WalkDir::new(root) {
+                let Ok(ent) = result else { continue };
+                if ent.file_type().is_file()
+                    && names.contains(&ent.file_name())
+                {
+                    paths.push(ent.into_path());
+                }
+            }
+        }
+        let case_insensitive = self.resolved_case_insensitive();
+        let opts = gitignore::GitignoreOptions {
+            case_insensitive,
+            ..gitignore::GitignoreOptions::default()
+        };
+        let results = gitignore::build_many(&paths, &opts);
+        let mut map = HashMap::new();
+        for (path, (m, err)) in paths.into_iter().zip(results) {
+            // Only cache clean builds. A matcher whose build produced an
+            // error falls back to the lazy path, which reports the error.
+            if err.is_none() {
+                map.insert(path, m);
+            }
+        }
+        self.prefetched = Some(Arc::new((case_insensitive, map)));
+        self
+    }
+
+    /// Set a callback that is invoked for every ignore file read while
+    /// building matchers.
+    ///
+    /// By default, no callback is set.
+    pub(crate) fn on_ignore_file(
+        &mut self,
+        callback: Arc<dyn Fn(IgnoreFileEvent) + Send + Sync>,
+    ) -> &mut IgnoreBuilder {
+        self.on_ignore_file = Some(IgnoreFileCallback(callback));
+        self
+    }
+
     /// Adds a new global ignore matcher from the ignore file path given.
     pub(crate) fn add_ignore(&mut self, ig: Gitignore) -> &mut IgnoreBuilder {
         self.explicit_ignores.push(ig);
@@ -684,7 +1067,23 @@ impl IgnoreBuilder {
         &mut self,
         file_name: S,
     ) -> &mut IgnoreBuilder {
-        self.custom_ignore_filenames.push(file_name.as_ref().to_os_string());
+        self.add_custom_ignore_filename_with_opts(
+            file_name,
+            CustomIgnoreOpts::default(),
+        )
+    }
+
+    /// Add a custom ignore file name along with options controlling how
+    /// files with that name participate in matching.
+    ///
+    /// The default options correspond to standard gitignore semantics.
+    pub(crate) fn add_custom_ignore_filename_with_opts<S: AsRef<OsStr>>(
+        &mut self,
+        file_name: S,
+        opts: CustomIgnoreOpts,
+    ) -> &mut IgnoreBuilder {
+        self.custom_ignore_filenames
+            .push((file_name.as_ref().to_os_string(), opts));
         self
     }
 
@@ -696,6 +1095,17 @@ impl IgnoreBuilder {
         self
     }
 
+    /// Set the strategy used to decide whether a file is hidden.
+    ///
+    /// This defaults to [`HiddenMode::Both`].
+    pub(crate) fn hidden_mode(
+        &mut self,
+        mode: HiddenMode,
+    ) -> &mut IgnoreBuilder {
+        self.opts.hidden_mode = mode;
+        self
+    }
+
     /// Enables reading `.ignore` files.
     ///
     /// `.ignore` files have the same semantics as `gitignore` files and are
@@ -763,16 +1173,41 @@ impl IgnoreBuilder {
         self
     }
 
-    /// Process ignore files case insensitively
+    /// Set how git submodules encountered during traversal are handled.
+    ///
+    /// The default is [`SubmoduleMode::Descend`].
+    pub(crate) fn git_submodules(
+        &mut self,
+        mode: SubmoduleMode,
+    ) -> &mut IgnoreBuilder {
+        self.opts.git_submodules = mode;
+        self
+    }
+
+    /// Set whether the globs in ignore files are matched case insensitively
+    /// or not.
     ///
-    /// This is disabled by default.
+    /// The default is [`IgnoreCaseMode::Auto`].
     pub(crate) fn ignore_case_insensitive(
         &mut self,
-        yes: bool,
+        mode: IgnoreCaseMode,
     ) -> &mut IgnoreBuilder {
-        self.opts.ignore_case_insensitive = yes;
+        self.opts.ignore_case_insensitive = mode;
         self
     }
+
+    /// Resolves the configured `IgnoreCaseMode` to a concrete value for
+    /// matchers built outside the context of any particular repository. In
+    /// `Auto` mode, only git's global config is consulted.
+    fn resolved_case_insensitive(&self) -> bool {
+        match self.opts.ignore_case_insensitive {
+            IgnoreCaseMode::Always => true,
+            IgnoreCaseMode::Never => false,
+            IgnoreCaseMode::Auto => {
+                gitignore::gitconfig_ignore_case(None).unwrap_or(false)
+            }
+        }
+    }
 }
 
 /// Creates a new gitignore matcher for the directory given.
@@ -788,10 +1223,29 @@ pub(crate) fn create_gitignore<T: AsRef<OsStr>>(
     dir_for_ignorefile: &Path,
     names: &[T],
     case_insensitive: bool,
+) -> (Gitignore, Option<Error>) {
+    create_gitignore_with_opts(
+        dir,
+        dir_for_ignorefile,
+        names,
+        case_insensitive,
+        false,
+    )
+}
+
+/// Like `create_gitignore`, but additionally permits anchoring every glob to
+/// `dir`, as with `CustomIgnoreOpts::anchored_only`.
+fn create_gitignore_with_opts<T: AsRef<OsStr>>(
+    dir: &Path,
+    dir_for_ignorefile: &Path,
+    names: &[T],
+    case_insensitive: bool,
+    anchored_only: bool,
 ) -> (Gitignore, Option<Error>) {
     let mut builder = GitignoreBuilder::new(dir);
     let mut errs = PartialErrorBuilder::default();
     builder.case_insensitive(case_insensitive).unwrap();
+    builder.anchored_only(anchored_only);
     for name in names {
         let gipath = dir_for_ignorefile.join(name.as_ref());
         // This check is not necessary, but is added for performance. Namely,
@@ -820,6 +1274,30 @@ pub(crate) fn create_gitignore<T: AsRef<OsStr>>(
     (gi, errs.into_error_option())
 }
 
+/// Parse the submodule paths out of a `.gitmodules` file.
+///
+/// Only `path` values are extracted, since recognizing submodule directories
+/// is all that's needed; submodule names and URLs are irrelevant here. The
+/// extracted paths are relative to the directory containing the
+/// `.gitmodules` file.
+///
+/// Returns `None` if the file doesn't exist or couldn't be opened.
+/// Consistent with how ignore files are handled, all other I/O errors and
+/// any syntax this light-weight parse doesn't understand are ignored.
+fn parse_gitmodules(path: &Path) -> Option<Vec<PathBuf>> {
+    let file = File::open(path).ok()?;
+    let mut paths = vec![];
+    for line in io::BufReader::new(file).lines() {
+        let Ok(line) = line else { break };
+        let Some(rest) = line.trim().strip_prefix("path") else { continue };
+        let Some(value) = rest.trim_start().strip_prefix('=') else {
+            continue;
+        };
+        paths.push(value.trim().split('/').collect());
+    }
+    Some(paths)
+}
+
 /// Find the GIT_COMMON_DIR for the given git worktree.
 ///
 /// This is the directory that may contain a private ignore file
@@ -879,7 +1357,8 @@ mod tests {
     use std::{io::Write, path::Path};
 
     use crate::{
-        dir::IgnoreBuilder, gitignore::Gitignore, tests::TempDir, Error,
+        dir::IgnoreBuilder, gitignore::Gitignore, tests::TempDir,
+        walk::CustomIgnoreOpts, Error,
     };
 
     fn wfile<P: AsRef<Path>>(path: P, contents: &str) {
@@ -1032,6 +1511,96 @@ mod tests {
         assert!(ig.matched("foo", false).is_whitelist());
     }
 
+    // Tests that a custom ignore file with whitelisting disabled treats
+    // whitelist globs as if they weren't there at all.
+    #[test]
+    fn custom_ignore_no_whitelist() {
+        let td = tmpdir();
+        let custom_ignore = ".customignore";
+        wfile(td.path().join(custom_ignore), "foo\n!bar");
+
+        let opts =
+            CustomIgnoreOpts { allow_whitelist: false, ..Default::default() };
+        let (ig, err) = IgnoreBuilder::new()
+            .add_custom_ignore_filename_with_opts(custom_ignore, opts)
+            .build()
+            .add_child(td.path());
+        assert!(err.is_none());
+        assert!(ig.matched("foo", false).is_ignore());
+        assert!(ig.matched("bar", false).is_none());
+        assert!(ig.matched("baz", false).is_none());
+    }
+
+    // Tests that a custom ignore file with whitelisting disabled cannot
+    // override an .ignore, unlike the default dialect.
+    #[test]
+    fn custom_ignore_no_whitelist_over_ignore() {
+        let td = tmpdir();
+        let custom_ignore = ".customignore";
+        wfile(td.path().join(".ignore"), "foo");
+        wfile(td.path().join(custom_ignore), "!foo");
+
+        let opts =
+            CustomIgnoreOpts { allow_whitelist: false, ..Default::default() };
+        let (ig, err) = IgnoreBuilder::new()
+            .add_custom_ignore_filename_with_opts(custom_ignore, opts)
+            .build()
+            .add_child(td.path());
+        assert!(err.is_none());
+        assert!(ig.matched("foo", false).is_ignore());
+    }
+
+    // Tests that a custom ignore file with parent traversal disabled isn't
+    // read from parent directories, unlike the default dialect.
+    #[test]
+    fn custom_ignore_no_parents() {
+        let td = tmpdir();
+        let custom_ignore = ".customignore";
+        mkdirp(td.path().join("foo"));
+        wfile(td.path().join(custom_ignore), "bar");
+
+        // First, check that the default dialect does match from a parent
+        // directory. This establishes a baseline.
+        let ig0 = IgnoreBuilder::new()
+            .add_custom_ignore_filename(custom_ignore)
+            .build();
+        let (ig1, err) = ig0.add_parents(td.path().join("foo"));
+        assert!(err.is_none());
+        let (ig2, err) = ig1.add_child(td.path().join("foo"));
+        assert!(err.is_none());
+        assert!(ig2.matched("bar", false).is_ignore());
+
+        // Second, check that disabling parent traversal ignores it.
+        let opts = CustomIgnoreOpts { parents: false, ..Default::default() };
+        let ig0 = IgnoreBuilder::new()
+            .add_custom_ignore_filename_with_opts(custom_ignore, opts)
+            .build();
+        let (ig1, err) = ig0.add_parents(td.path().join("foo"));
+        assert!(err.is_none());
+        let (ig2, err) = ig1.add_child(td.path().join("foo"));
+        assert!(err.is_none());
+        assert!(ig2.matched("bar", false).is_none());
+    }
+
+    // Tests that a custom ignore file with anchoring enabled only matches
+    // globs against the directory containing the ignore file.
+    #[test]
+    fn custom_ignore_anchored_only() {
+        let td = tmpdir();
+        let custom_ignore = ".customignore";
+        wfile(td.path().join(custom_ignore), "foo");
+
+        let opts =
+            CustomIgnoreOpts { anchored_only: true, ..Default::default() };
+        let (ig, err) = IgnoreBuilder::new()
+            .add_custom_ignore_filename_with_opts(custom_ignore, opts)
+            .build()
+            .add_child(td.path());
+        assert!(err.is_none());
+        assert!(ig.matched("foo", false).is_ignore());
+        assert!(ig.matched("bar/foo", false).is_none());
+    }
+
     // Tests that an .ignore will override a .gitignore.
     #[test]
     fn ignore_over_gitignore() {
@@ -1219,4 +1788,33 @@ mod tests {
         let (_, err) = ib.add_child(td.path().join("linked-worktree"));
         assert!(err.is_none());
     }
+
+    #[test]
+    fn build_many() {
+        use crate::gitignore::{build_many, GitignoreOptions};
+
+        let td = tmpdir();
+        let mut paths = vec![];
+        for i in 0..17 {
+            let dir = td.path().join(format!("d{}", i));
+            mkdirp(&dir);
+            wfile(dir.join(".gitignore"), &format!("f{}", i));
+            paths.push(dir.join(".gitignore"));
+        }
+        // A missing file produces a valid empty matcher, as with
+        // Gitignore::new.
+        paths.push(td.path().join("does-not-exist/.gitignore"));
+
+        let results = build_many(&paths, &GitignoreOptions::default());
+        assert_eq!(paths.len(), results.len());
+        for (i, (gi, err)) in results.iter().take(17).enumerate() {
+            assert!(err.is_none());
+            assert_eq!(paths[i].parent().unwrap(), gi.path());
+            assert!(gi.matched(format!("f{}", i), false).is_ignore());
+            assert!(gi.matched("unrelated", false).is_none());
+        }
+        let (gi, err) = &results[17];
+        assert!(err.is_none());
+        assert!(gi.is_empty());
+    }
 }
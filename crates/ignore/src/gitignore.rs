@@ -9,7 +9,7 @@ the `git` command line tool.
 
 use std::{
     fs::File,
-    io::{BufRead, BufReader, Read},
+    io::{self, BufRead, BufReader, Read},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -32,6 +32,8 @@ use crate::{
 pub struct Glob {
     /// The file path that this glob was extracted from.
     from: Option<PathBuf>,
+    /// The line number in `from` that this glob was extracted from.
+    line: Option<u64>,
     /// The original glob string.
     original: String,
     /// The actual glob string used to convert to a regex.
@@ -48,6 +50,16 @@ impl Glob {
         self.from.as_ref().map(|p| &**p)
     }
 
+    /// Returns the line number, starting at `1`, at which this glob appears
+    /// in the file that defined it.
+    ///
+    /// This is `None` for globs that weren't read from a file via
+    /// [`GitignoreBuilder::add`], e.g., globs added directly with
+    /// [`GitignoreBuilder::add_line`].
+    pub fn line(&self) -> Option<u64> {
+        self.line
+    }
+
     /// The original glob as it was defined in a gitignore file.
     pub fn original(&self) -> &str {
         &self.original
@@ -77,13 +89,18 @@ impl Glob {
 
 /// Gitignore is a matcher for the globs in one or more gitignore files
 /// in the same directory.
+///
+/// Cloning a `Gitignore` is cheap. The compiled globs are shared behind an
+/// `Arc`, so clones can be freely handed out to other threads without
+/// re-parsing or re-compiling anything.
 #[derive(Clone, Debug)]
 pub struct Gitignore {
-    set: GlobSet,
+    set: Arc<GlobSet>,
     root: PathBuf,
-    globs: Vec<Glob>,
+    globs: Arc<Vec<Glob>>,
     num_ignores: u64,
     num_whitelists: u64,
+    require_containment: bool,
     matches: Option<Arc<Pool<Vec<usize>>>>,
 }
 
@@ -136,15 +153,53 @@ impl Gitignore {
     /// Its path is empty.
     pub fn empty() -> Gitignore {
         Gitignore {
-            set: GlobSet::empty(),
+            set: Arc::new(GlobSet::empty()),
             root: PathBuf::from(""),
-            globs: vec![],
+            globs: Arc::new(vec![]),
             num_ignores: 0,
             num_whitelists: 0,
+            require_containment: false,
             matches: None,
         }
     }
 
+    /// Merges two matchers into one, with `high`'s globs taking precedence
+    /// over `low`'s.
+    ///
+    /// The root of the merged matcher is the shallower of the two roots, and
+    /// the deeper root must be contained within it (equal roots are fine).
+    /// Globs from the matcher with the deeper root are re-rooted so that
+    /// they continue to match only paths below that root. When neither root
+    /// contains the other, an error is returned, since there is no single
+    /// root from which both sets of globs can be matched.
+    ///
+    /// The globs are recompiled as part of merging, using the default
+    /// builder settings (e.g., case sensitive matching).
+    pub fn merge(
+        low: &Gitignore,
+        high: &Gitignore,
+    ) -> Result<Gitignore, Error> {
+        let root = if low.path().starts_with(high.path()) {
+            high.path()
+        } else if high.path().starts_with(low.path()) {
+            low.path()
+        } else {
+            return Err(Error::Glob {
+                glob: None,
+                err: format!(
+                    "cannot merge gitignore matchers with unrelated roots \
+                     {:?} and {:?}",
+                    low.path(),
+                    high.path(),
+                ),
+            });
+        };
+        let mut builder = GitignoreBuilder::new(root);
+        builder.add_gitignore(low)?;
+        builder.add_gitignore(high)?;
+        builder.build()
+    }
+
     /// Returns the directory containing this gitignore matcher.
     ///
     /// All matches are done relative to this path.
@@ -185,17 +240,58 @@ impl Gitignore {
     /// determined by a common suffix of the directory containing this
     /// gitignore) is stripped. If there is no common suffix/prefix overlap,
     /// then `path` is assumed to be relative to this matcher.
+    ///
+    /// When `GitignoreBuilder::require_containment` is enabled, paths that
+    /// are not contained within this matcher's root never match. See
+    /// `matched_contained` for a description of the containment check.
     pub fn matched<P: AsRef<Path>>(
         &self,
         path: P,
         is_dir: bool,
     ) -> Match<&Glob> {
+        #[cfg(debug_assertions)]
+        self.check_dir_hint(path.as_ref(), is_dir);
         if self.is_empty() {
             return Match::None;
         }
+        if self.require_containment {
+            return self
+                .matched_contained(path, is_dir)
+                .unwrap_or(Match::None);
+        }
         self.matched_stripped(self.strip(path.as_ref()), is_dir)
     }
 
+    /// Like `matched`, but returns `None` when the given path is not
+    /// contained within this matcher's root.
+    ///
+    /// `matched` assumes that a path sharing no prefix with the root is
+    /// relative to the root, which can produce surprising results. For
+    /// example, a matcher rooted at `/repo` with the glob `build/` claims
+    /// that `/other/build/x` is ignored. This routine instead reports such
+    /// paths as outside the purview of this matcher.
+    ///
+    /// The containment check is purely lexical: the path must either start
+    /// with the root (a path exactly equal to the root is contained) or be
+    /// relative, and its `..` components must not escape the root. No
+    /// canonicalization is performed, so a path that escapes the root via a
+    /// symlink is still considered contained. Relative paths are interpreted
+    /// as relative to the root.
+    ///
+    /// `is_dir` should be true if the path refers to a directory and false
+    /// otherwise.
+    pub fn matched_contained<P: AsRef<Path>>(
+        &self,
+        path: P,
+        is_dir: bool,
+    ) -> Option<Match<&Glob>> {
+        let path = self.contained(path.as_ref())?;
+        if self.is_empty() {
+            return Some(Match::None);
+        }
+        Some(self.matched_stripped(path, is_dir))
+    }
+
     /// Returns whether the given path (file or directory, and expected to be
     /// under the root) or any of its parent directories (up to the root)
     /// matched a pattern in this gitignore matcher.
@@ -273,32 +369,218 @@ impl Gitignore {
         &'a self,
         path: &'a P,
     ) -> &'a Path {
-        let mut path = path.as_ref();
-        // A leading ./ is completely superfluous. We also strip it from
-        // our gitignore root path, so we need to strip it from our candidate
-        // path too.
+        // The actual stripping logic lives in globset so that it can be
+        // shared with `globset::RootedGlobSet`.
+        globset::strip_root(&self.root, path.as_ref())
+    }
+
+    /// Returns the given path stripped of this matcher's root if and only if
+    /// the path is lexically contained within the root, and `None` otherwise.
+    ///
+    /// This mirrors `strip`, except that paths not under the root are
+    /// rejected instead of being assumed to be relative to the root. The
+    /// check is lexical: symlinks are not resolved.
+    fn contained<'a>(&'a self, path: &'a Path) -> Option<&'a Path> {
+        use std::path::Component;
+
+        let mut path = path;
         if let Some(p) = strip_prefix("./", path) {
             path = p;
         }
-        // Strip any common prefix between the candidate path and the root
-        // of the gitignore, to make sure we get relative matching right.
-        // BUT, a file name might not have any directory components to it,
-        // in which case, we don't want to accidentally strip any part of the
-        // file name.
-        //
-        // As an additional special case, if the root is just `.`, then we
-        // shouldn't try to strip anything, e.g., when path begins with a `.`.
         if self.root != Path::new(".") && !is_file_name(path) {
-            if let Some(p) = strip_prefix(&self.root, path) {
-                path = p;
-                // If we're left with a leading slash, get rid of it.
-                if let Some(p) = strip_prefix("/", path) {
+            match strip_prefix(&self.root, path) {
+                Some(p) => {
                     path = p;
+                    if let Some(p) = strip_prefix("/", path) {
+                        path = p;
+                    }
                 }
+                None => {
+                    if path.is_absolute() {
+                        return None;
+                    }
+                }
+            }
+        } else if path.is_absolute() {
+            return None;
+        }
+        // What remains is interpreted as relative to the root, so any `..`
+        // components that pop above the root escape it.
+        let mut depth = 0i64;
+        for component in path.components() {
+            match component {
+                Component::ParentDir => {
+                    depth -= 1;
+                    if depth < 0 {
+                        return None;
+                    }
+                }
+                Component::Normal(_) => depth += 1,
+                _ => {}
+            }
+        }
+        Some(path)
+    }
+
+    /// In debug builds, cross-check the caller's `is_dir` hint against the
+    /// file system when the path actually exists. A wrong hint (most
+    /// commonly, `true` for a symlink pointing to a directory) produces
+    /// decisions that disagree with git, so mismatches are logged to aid
+    /// debugging. This has no effect on the match result.
+    #[cfg(debug_assertions)]
+    fn check_dir_hint(&self, path: &Path, is_dir: bool) {
+        let Ok(hint) = dir_hint_for(path, false) else { return };
+        if hint != is_dir {
+            log::debug!(
+                "gitignore: is_dir hint {is_dir} for {path} disagrees with \
+                 the file system ({hint}); note that git matches a symlink \
+                 as a file, even when it points to a directory (see \
+                 `dir_hint_for`)",
+                path = path.display(),
+            );
+        }
+    }
+}
+
+/// Returns the `is_dir` hint that should be passed to [`Gitignore::matched`]
+/// (and friends) for the path given.
+///
+/// Git matches a symlink as a file, even when the symlink points to a
+/// directory. Callers that compute the hint with `Path::is_dir` (which
+/// traverses symlinks) therefore produce decisions that disagree with git,
+/// e.g., for a directory-only pattern like `foo/`. This routine encapsulates
+/// the correct rule: a symlink gets a `false` hint unless `follow` is
+/// enabled, in which case the symlink is resolved and the hint reflects its
+/// target. `follow` should correspond to whether the caller traverses
+/// symlinks (e.g., `WalkBuilder::follow_links`).
+///
+/// If the path's metadata cannot be read (for example, because the path does
+/// not exist), then an error is returned.
+pub fn dir_hint_for<P: AsRef<Path>>(
+    path: P,
+    follow: bool,
+) -> io::Result<bool> {
+    let path = path.as_ref();
+    let md = path.symlink_metadata()?;
+    if md.file_type().is_symlink() {
+        if !follow {
+            return Ok(false);
+        }
+        return Ok(path.metadata()?.is_dir());
+    }
+    Ok(md.is_dir())
+}
+
+/// A diagnostic produced by [`analyze`] about a gitignore rule that can
+/// never influence matching.
+#[derive(Clone, Debug)]
+pub enum RuleDiagnostic {
+    /// A rule rendered redundant by a later rule with the same polarity and
+    /// an equal-or-broader pattern. Since the later rule wins whenever both
+    /// match, the shadowed rule never determines the outcome.
+    ShadowedBy {
+        /// The shadowed rule.
+        rule: Glob,
+        /// The later rule that shadows it.
+        by: Glob,
+    },
+    /// A rule that can never have an effect. For example, a whitelist for a
+    /// path whose every parent is ignored: git does not descend into ignored
+    /// directories, so the whitelist is unreachable.
+    NeverEffective {
+        /// The ineffective rule.
+        rule: Glob,
+    },
+}
+
+/// Analyze the rules in the given gitignore matcher and report rules that
+/// can never influence matching.
+///
+/// Two kinds of diagnostics are reported. A rule is shadowed when a later
+/// rule with the same polarity has an equal-or-broader pattern. A whitelist
+/// is never effective when every path it could match lies beneath a
+/// directory that the rules as a whole ignore.
+///
+/// The broadness comparison is conservative and purely syntactic (exact
+/// comparison of the compiled globs, modulo `**/` prefixes), so the absence
+/// of a diagnostic does not prove a rule is live, but every diagnostic
+/// reported is genuine.
+pub fn analyze(gitignore: &Gitignore) -> Vec<RuleDiagnostic> {
+    let mut diagnostics = vec![];
+    for (i, rule) in gitignore.globs.iter().enumerate() {
+        for by in gitignore.globs.iter().skip(i + 1) {
+            // `by` shadows `rule` when it matches at least everything that
+            // `rule` does. A directory-only rule matches fewer paths than
+            // one that isn't, hence the `is_only_dir` comparison.
+            if by.is_whitelist() == rule.is_whitelist()
+                && (!by.is_only_dir() || rule.is_only_dir())
+                && covers(by, rule)
+            {
+                diagnostics.push(RuleDiagnostic::ShadowedBy {
+                    rule: rule.clone(),
+                    by: by.clone(),
+                });
+                break;
             }
         }
-        path
     }
+    for rule in gitignore.globs.iter().filter(|g| g.is_whitelist()) {
+        for ancestor in literal_ancestors(rule.actual()) {
+            // Using the full matcher here means a later rule re-including
+            // the ancestor correctly suppresses the diagnostic.
+            if gitignore.matched(&ancestor, true).is_ignore() {
+                diagnostics.push(RuleDiagnostic::NeverEffective {
+                    rule: rule.clone(),
+                });
+                break;
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Returns true when `by` is guaranteed to match every path that `rule`
+/// matches, based on a conservative comparison of the glob strings.
+fn covers(by: &Glob, rule: &Glob) -> bool {
+    if by.actual() == "**" || by.actual() == rule.actual() {
+        return true;
+    }
+    if let Some(rest) = by.actual().strip_prefix("**/") {
+        // `**/foo` matches `foo` at any depth, so it covers both the
+        // anchored `foo` and anything ending with `/foo`.
+        if rest == rule.actual() {
+            return true;
+        }
+        if rule.actual().ends_with(&format!("/{}", rest)) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Returns the ancestor directories spelled out literally at the front of
+/// the given glob, e.g., `foo` and `foo/bar` for `foo/bar/*.txt`. Traversal
+/// stops at the first component containing glob syntax.
+fn literal_ancestors(actual: &str) -> Vec<String> {
+    let mut components = actual.split('/').collect::<Vec<&str>>();
+    // The final component names the matched path itself, not a parent.
+    components.pop();
+    let mut ancestors = vec![];
+    let mut cur = String::new();
+    for component in components {
+        let has_meta = component
+            .chars()
+            .any(|c| matches!(c, '*' | '?' | '[' | ']' | '{' | '}' | '\\'));
+        if has_meta {
+            break;
+        }
+        if !cur.is_empty() {
+            cur.push('/');
+        }
+        cur.push_str(component);
+        ancestors.push(cur.clone());
+    }
+    ancestors
 }
 
 /// Builds a matcher for a single set of globs from a .gitignore file.
@@ -308,6 +590,8 @@ pub struct GitignoreBuilder {
     root: PathBuf,
     globs: Vec<Glob>,
     case_insensitive: bool,
+    anchored_only: bool,
+    require_containment: bool,
 }
 
 impl GitignoreBuilder {
@@ -324,6 +608,8 @@ impl GitignoreBuilder {
             root: strip_prefix("./", root).unwrap_or(root).to_path_buf(),
             globs: vec![],
             case_insensitive: false,
+            anchored_only: false,
+            require_containment: false,
         }
     }
 
@@ -338,11 +624,12 @@ impl GitignoreBuilder {
             .build()
             .map_err(|err| Error::Glob { glob: None, err: err.to_string() })?;
         Ok(Gitignore {
-            set,
+            set: Arc::new(set),
             root: self.root.clone(),
-            globs: self.globs.clone(),
+            globs: Arc::new(self.globs.clone()),
             num_ignores: nignore as u64,
             num_whitelists: nwhite as u64,
+            require_containment: self.require_containment,
             matches: Some(Arc::new(Pool::new(|| vec![]))),
         })
     }
@@ -391,24 +678,153 @@ impl GitignoreBuilder {
             Ok(file) => file,
         };
         log::debug!("opened gitignore file: {}", path.display());
-        let rdr = BufReader::new(file);
+        self.add_reader(Some(path.to_path_buf()), file)
+    }
+
+    /// Add each glob line from the reader given.
+    ///
+    /// If this reader corresponds to a particular `gitignore` file, then its
+    /// path should be provided here.
+    ///
+    /// The bytes read should be formatted as a `gitignore` file. Lines are
+    /// read as raw bytes, so a line that is not valid UTF-8 never affects
+    /// the lines after it. Such a line is lossily converted to UTF-8 before
+    /// being parsed as a glob. (This differs from git, which matches the
+    /// bytes as-is. Globs in this crate are Unicode-oriented, so a glob
+    /// containing invalid UTF-8 may fail to match some paths that git would
+    /// match, but it is never silently dropped along with the rest of the
+    /// file.) A line longer than 64KB is skipped and reported as an error.
+    ///
+    /// Note that partial errors can be returned, as with
+    /// [`add`](GitignoreBuilder::add).
+    pub fn add_reader<R: Read>(
+        &mut self,
+        from: Option<PathBuf>,
+        rdr: R,
+    ) -> Option<Error> {
+        // Lines longer than this are almost certainly binary garbage and
+        // are skipped instead of being buffered without bound.
+        const MAX_LINE_LEN: u64 = 64 * (1 << 10);
+
+        let tag_path = from.clone().unwrap_or_else(PathBuf::new);
+        let mut rdr = BufReader::new(rdr);
         let mut errs = PartialErrorBuilder::default();
-        for (i, line) in rdr.lines().enumerate() {
-            let lineno = (i + 1) as u64;
-            let line = match line {
-                Ok(line) => line,
+        let mut buf = vec![];
+        let mut lineno = 0u64;
+        loop {
+            buf.clear();
+            let n = match (&mut rdr)
+                .take(MAX_LINE_LEN + 1)
+                .read_until(b'\n', &mut buf)
+            {
+                Ok(n) => n,
                 Err(err) => {
-                    errs.push(Error::Io(err).tagged(path, lineno));
+                    errs.push(Error::Io(err).tagged(&tag_path, lineno + 1));
                     break;
                 }
             };
-            if let Err(err) = self.add_line(Some(path.to_path_buf()), &line) {
-                errs.push(err.tagged(path, lineno));
+            if n == 0 {
+                break;
+            }
+            lineno += 1;
+            if buf.last() == Some(&b'\n') {
+                buf.pop();
+                if buf.last() == Some(&b'\r') {
+                    buf.pop();
+                }
+            } else if n as u64 > MAX_LINE_LEN {
+                let err = Error::LineTooLong { limit: MAX_LINE_LEN };
+                errs.push(err.tagged(&tag_path, lineno));
+                // Throw away the rest of the line, one bounded chunk at a
+                // time, and carry on with the line after it.
+                loop {
+                    buf.clear();
+                    match (&mut rdr)
+                        .take(MAX_LINE_LEN)
+                        .read_until(b'\n', &mut buf)
+                    {
+                        Ok(0) => break,
+                        Ok(_) if buf.last() == Some(&b'\n') => break,
+                        Ok(_) => {}
+                        Err(err) => {
+                            errs.push(
+                                Error::Io(err).tagged(&tag_path, lineno),
+                            );
+                            break;
+                        }
+                    }
+                }
+                continue;
+            }
+            let line = String::from_utf8_lossy(&buf);
+            let result =
+                self.add_line_with_number(from.clone(), Some(lineno), &line);
+            if let Err(err) = result {
+                errs.push(err.tagged(&tag_path, lineno));
             }
         }
         errs.into_error_option()
     }
 
+    /// Add every glob from an existing matcher to this builder.
+    ///
+    /// The globs are appended after any globs already added, so under
+    /// gitignore's last-match-wins semantics they take precedence over them.
+    /// Each glob is recompiled with this builder's settings, e.g., case
+    /// sensitivity.
+    ///
+    /// The matcher's root must be equal to this builder's root or contained
+    /// within it. When it is contained, the globs are re-rooted so that they
+    /// keep matching only paths below the matcher's root. Otherwise, an
+    /// error is returned.
+    pub fn add_gitignore(
+        &mut self,
+        gi: &Gitignore,
+    ) -> Result<&mut GitignoreBuilder, Error> {
+        let rel = match gi.path().strip_prefix(&self.root) {
+            Ok(rel) => rel,
+            Err(_) => {
+                return Err(Error::Glob {
+                    glob: None,
+                    err: format!(
+                        "cannot add globs rooted at {:?} to a builder \
+                         rooted at {:?}",
+                        gi.path(),
+                        self.root,
+                    ),
+                });
+            }
+        };
+        // Convert the matcher's root relative to this builder's root into a
+        // glob prefix, escaping any glob meta characters in it so that it
+        // only matches literally.
+        let mut prefix = String::new();
+        for component in rel.components() {
+            prefix.push_str(&globset::escape(
+                &component.as_os_str().to_string_lossy(),
+            ));
+            prefix.push('/');
+        }
+        for glob in gi.globs.iter() {
+            let mut glob = glob.clone();
+            if !prefix.is_empty() {
+                glob.actual = format!("{}{}", prefix, glob.actual);
+            }
+            let parsed = GlobBuilder::new(&glob.actual)
+                .literal_separator(true)
+                .case_insensitive(self.case_insensitive)
+                .backslash_escape(true)
+                .build()
+                .map_err(|err| Error::Glob {
+                    glob: Some(glob.original.clone()),
+                    err: err.kind().to_string(),
+                })?;
+            self.builder.add(parsed);
+            self.globs.push(glob);
+        }
+        Ok(self)
+    }
+
     /// Add each glob line from the string given.
     ///
     /// If this string came from a particular `gitignore` file, then its path
@@ -436,6 +852,17 @@ impl GitignoreBuilder {
     pub fn add_line(
         &mut self,
         from: Option<PathBuf>,
+        line: &str,
+    ) -> Result<&mut GitignoreBuilder, Error> {
+        self.add_line_with_number(from, None, line)
+    }
+
+    /// Add a line from a gitignore file to this builder, recording the line
+    /// number, starting at `1`, at which it appeared in that file.
+    fn add_line_with_number(
+        &mut self,
+        from: Option<PathBuf>,
+        lineno: Option<u64>,
         mut line: &str,
     ) -> Result<&mut GitignoreBuilder, Error> {
         #![allow(deprecated)]
@@ -451,6 +878,7 @@ impl GitignoreBuilder {
         }
         let mut glob = Glob {
             from,
+            line: lineno,
             original: line.to_string(),
             actual: String::new(),
             is_whitelist: false,
@@ -474,6 +902,9 @@ impl GitignoreBuilder {
                 is_absolute = true;
             }
         }
+        if self.anchored_only {
+            is_absolute = true;
+        }
         // If it ends with a slash, then this should only match directories,
         // but the slash should otherwise not be used while globbing.
         if line.as_bytes().last() == Some(&b'/') {
@@ -515,6 +946,42 @@ impl GitignoreBuilder {
         Ok(self)
     }
 
+    /// Toggle whether every glob should be treated as if it were anchored to
+    /// the root of this matcher, regardless of whether it begins with a `/`.
+    ///
+    /// That is, when enabled, a glob like `foo` matches only `foo` directly
+    /// beneath the root of this matcher, and not, e.g., `bar/foo`.
+    ///
+    /// When this option is changed, only globs added after the change will be
+    /// affected.
+    ///
+    /// This is disabled by default.
+    pub(crate) fn anchored_only(
+        &mut self,
+        yes: bool,
+    ) -> &mut GitignoreBuilder {
+        self.anchored_only = yes;
+        self
+    }
+
+    /// Toggle whether `Gitignore::matched` should treat paths that are not
+    /// contained within this matcher's root as never matching.
+    ///
+    /// By default, a path sharing no prefix with the root is assumed to be
+    /// relative to the root, which can produce surprising matches for paths
+    /// that actually live elsewhere. When this is enabled, `matched` behaves
+    /// like `Gitignore::matched_contained`, except that paths outside the
+    /// root report `Match::None` instead of `None`.
+    ///
+    /// The containment check is lexical and does not resolve symlinks. See
+    /// `Gitignore::matched_contained` for details.
+    ///
+    /// This is disabled by default.
+    pub fn require_containment(&mut self, yes: bool) -> &mut GitignoreBuilder {
+        self.require_containment = yes;
+        self
+    }
+
     /// Toggle whether the globs should be matched case insensitively or not.
     ///
     /// When this option is changed, only globs added after the change will be
@@ -532,6 +999,80 @@ impl GitignoreBuilder {
     }
 }
 
+/// Options for the matchers built by [`build_many`].
+///
+/// The default options correspond to the defaults of `GitignoreBuilder`.
+#[derive(Clone, Debug, Default)]
+pub struct GitignoreOptions {
+    /// Whether globs should be matched case insensitively or not.
+    ///
+    /// This is disabled by default.
+    pub case_insensitive: bool,
+    /// Whether a path must be contained within the directory of its gitignore
+    /// file in order for the globs to apply, as with
+    /// `GitignoreBuilder::require_containment`.
+    ///
+    /// This is disabled by default.
+    pub require_containment: bool,
+}
+
+/// Builds a matcher for each of the gitignore file paths given.
+///
+/// The matchers are returned in the same order as the paths given, and each
+/// is built exactly as [`Gitignore::new`] would build it. In particular, the
+/// root of each matcher is the directory containing its gitignore file, a
+/// valid (possibly empty) matcher is always returned and I/O errors are
+/// ignored.
+///
+/// When more than one path and more than one CPU are available, the files
+/// are parsed and compiled on a small number of scoped threads. This is
+/// useful for warming many matchers up front, e.g., before walking a deep
+/// directory tree containing thousands of gitignore files.
+pub fn build_many<P: AsRef<Path>>(
+    paths: &[P],
+    opts: &GitignoreOptions,
+) -> Vec<(Gitignore, Option<Error>)> {
+    let build_one = |path: &Path| {
+        let parent = path.parent().unwrap_or(Path::new("/"));
+        let mut builder = GitignoreBuilder::new(parent);
+        builder.case_insensitive(opts.case_insensitive).unwrap();
+        builder.require_containment(opts.require_containment);
+        let mut errs = PartialErrorBuilder::default();
+        errs.maybe_push_ignore_io(builder.add(path));
+        match builder.build() {
+            Ok(gi) => (gi, errs.into_error_option()),
+            Err(err) => {
+                errs.push(err);
+                (Gitignore::empty(), errs.into_error_option())
+            }
+        }
+    };
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(paths.len());
+    if threads <= 1 {
+        return paths.iter().map(|p| build_one(p.as_ref())).collect();
+    }
+    // `P` need not be `Sync`, but `&Path` is, so reborrow the paths before
+    // handing them out to the scoped threads.
+    let paths = paths.iter().map(AsRef::as_ref).collect::<Vec<&Path>>();
+    // One contiguous chunk of paths per thread. Concatenating the chunked
+    // results in spawn order preserves the order of the paths given.
+    let chunk_size = (paths.len() + threads - 1) / threads;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let build_one = &build_one;
+                scope.spawn(move || {
+                    chunk.iter().map(|p| build_one(p)).collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles.into_iter().flat_map(|h| h.join().unwrap()).collect()
+    })
+}
+
 /// Return the file path of the current environment's global gitignore file.
 ///
 /// Note that the file path returned may not exist.
@@ -591,40 +1132,179 @@ fn excludes_file_default() -> Option<PathBuf> {
         .map(|x| x.join("git/ignore"))
 }
 
+/// Returns the value of git's `core.ignoreCase` setting for the repository
+/// whose git directory is given, if one is set.
+///
+/// The repository's own config file takes precedence over the global config,
+/// which is consulted in the same order as for `core.excludesFile`. When no
+/// git directory is given, only the global config is consulted.
+pub(crate) fn gitconfig_ignore_case(git_dir: Option<&Path>) -> Option<bool> {
+    if let Some(git_dir) = git_dir {
+        let local = std::fs::read(git_dir.join("config")).ok();
+        if let Some(value) = local.as_deref().and_then(parse_ignore_case) {
+            return Some(value);
+        }
+    }
+    match gitconfig_home_contents().and_then(|x| parse_ignore_case(&x)) {
+        Some(value) => return Some(value),
+        None => {}
+    }
+    gitconfig_xdg_contents().and_then(|x| parse_ignore_case(&x))
+}
+
 /// Extract git's `core.excludesfile` config setting from the raw file contents
 /// given.
 fn parse_excludes_file(data: &[u8]) -> Option<PathBuf> {
-    use std::sync::OnceLock;
-
-    use regex_automata::{meta::Regex, util::syntax};
-
-    // N.B. This is the lazy approach, and isn't technically correct, but
-    // probably works in more circumstances. I guess we would ideally have
-    // a full INI parser. Yuck.
-    static RE: OnceLock<Regex> = OnceLock::new();
-    let re = RE.get_or_init(|| {
-        Regex::builder()
-            .configure(Regex::config().utf8_empty(false))
-            .syntax(syntax::Config::new().utf8(false))
-            .build(r#"(?im-u)^\s*excludesfile\s*=\s*"?\s*(\S+?)\s*"?\s*$"#)
-            .unwrap()
-    });
-    // We don't care about amortizing allocs here I think. This should only
-    // be called ~once per traversal or so? (Although it's not guaranteed...)
-    let mut caps = re.create_captures();
-    re.captures(data, &mut caps);
-    let span = caps.get_group(1)?;
-    let candidate = &data[span];
-    std::str::from_utf8(candidate).ok().map(|s| PathBuf::from(expand_tilde(s)))
+    let value = parse_config_value(data, "core", "excludesfile")?;
+    let mut value = std::str::from_utf8(value).ok()?.trim();
+    // The value may be surrounded by a single pair of double quotes. Beyond
+    // that, a path containing whitespace or quotes would need real gitconfig
+    // quoting rules to interpret, so give up rather than guess wrong.
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        value = value[1..value.len() - 1].trim();
+    }
+    if value.is_empty()
+        || value.contains(|c: char| c.is_whitespace() || c == '"')
+    {
+        return None;
+    }
+    Some(PathBuf::from(expand_tilde(value)))
 }
 
-/// Expands ~ in file paths to the value of $HOME.
-fn expand_tilde(path: &str) -> String {
-    let home = match home_dir() {
-        None => return path.to_string(),
-        Some(home) => home.to_string_lossy().into_owned(),
+/// Extract git's `core.ignoreCase` config setting from the raw file contents
+/// given.
+fn parse_ignore_case(data: &[u8]) -> Option<bool> {
+    parse_git_bool(parse_config_value(data, "core", "ignorecase")?)
+}
+
+/// Parses a boolean value as git's config format defines them.
+///
+/// An empty value corresponds to a key that was listed with no `=` at all,
+/// which git treats as true.
+fn parse_git_bool(value: &[u8]) -> Option<bool> {
+    match &*value.to_ascii_lowercase() {
+        b"" | b"true" | b"yes" | b"on" | b"1" => Some(true),
+        b"false" | b"no" | b"off" | b"0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Looks up the value of `section.key` in the raw contents of a git config
+/// file.
+///
+/// This is not a full INI parser, but unlike a simple line match, it does
+/// require the key to appear under a `[section]` header for the section
+/// given. Both the section and key are matched case insensitively, and as
+/// in git, the last assignment wins. Subsections (e.g. `[section "sub"]`)
+/// never match. The key and section given are expected to be lowercase.
+fn parse_config_value<'a>(
+    data: &'a [u8],
+    section: &str,
+    key: &str,
+) -> Option<&'a [u8]> {
+    let trim = |mut line: &'a [u8]| {
+        while line.first().map_or(false, |b| b.is_ascii_whitespace()) {
+            line = &line[1..];
+        }
+        while line.last().map_or(false, |b| b.is_ascii_whitespace()) {
+            line = &line[..line.len() - 1];
+        }
+        line
     };
-    path.replace("~", &home)
+    let mut in_section = false;
+    let mut found = None;
+    for line in data.split(|&b| b == b'\n') {
+        let line = trim(line);
+        if line.is_empty() || line[0] == b'#' || line[0] == b';' {
+            continue;
+        }
+        if line[0] == b'[' {
+            in_section = line
+                .last()
+                .map_or(false, |&b| b == b']')
+                .then(|| trim(&line[1..line.len() - 1]))
+                .map_or(false, |name| {
+                    name.eq_ignore_ascii_case(section.as_bytes())
+                });
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        let (name, value) = match line.iter().position(|&b| b == b'=') {
+            None => (line, &line[line.len()..]),
+            Some(i) => (trim(&line[..i]), trim(&line[i + 1..])),
+        };
+        if name.eq_ignore_ascii_case(key.as_bytes()) {
+            found = Some(value);
+        }
+    }
+    found
+}
+
+/// Expands a leading `~/`, `~user/`, `$VAR` or `${VAR}` in the file path
+/// given.
+///
+/// This mirrors git's handling of `core.excludesFile`. Only a tilde at the
+/// very beginning of the path is meaningful; a `~` anywhere else (e.g.,
+/// `/backups/~archive/ignore`) is an ordinary path component and is left
+/// untouched. If the home directory or environment variable can't be
+/// resolved, then the path is returned unchanged.
+fn expand_tilde(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix('~') {
+        let (user, rest) = match rest.find('/') {
+            None => (rest, ""),
+            Some(i) => (&rest[..i], &rest[i..]),
+        };
+        let home =
+            if user.is_empty() { home_dir() } else { user_home_dir(user) };
+        return match home {
+            None => path.to_string(),
+            Some(home) => format!("{}{}", home.to_string_lossy(), rest),
+        };
+    }
+    if let Some(rest) = path.strip_prefix('$') {
+        let (name, rest) = if let Some(rest) = rest.strip_prefix('{') {
+            match rest.find('}') {
+                None => return path.to_string(),
+                Some(i) => (&rest[..i], &rest[i + 1..]),
+            }
+        } else {
+            let i = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            (&rest[..i], &rest[i..])
+        };
+        return match std::env::var(name) {
+            Err(_) => path.to_string(),
+            Ok(value) => format!("{}{}", value, rest),
+        };
+    }
+    path.to_string()
+}
+
+/// Returns the home directory of the user with the name given, if it can be
+/// determined.
+///
+/// On Unix, this looks the user up in the passwd database. Note that this
+/// reads `/etc/passwd` directly instead of going through `getpwnam(3)`, so
+/// users only known to, e.g., LDAP won't be found. On non-Unix platforms
+/// this always returns `None`.
+fn user_home_dir(user: &str) -> Option<PathBuf> {
+    if !cfg!(unix) {
+        return None;
+    }
+    let file = File::open("/etc/passwd").ok()?;
+    for line in BufReader::new(file).lines() {
+        let line = line.ok()?;
+        // Each line is `name:passwd:uid:gid:gecos:home:shell`.
+        let mut fields = line.split(':');
+        if fields.next() != Some(user) {
+            continue;
+        }
+        return fields.nth(4).map(PathBuf::from);
+    }
+    None
 }
 
 /// Returns the location of the user's home directory.
@@ -789,6 +1469,114 @@ mod tests {
         assert!(super::parse_excludes_file(&data).is_none());
     }
 
+    #[test]
+    fn parse_excludes_file6() {
+        // The key must be in the [core] section.
+        let data = bytes("excludesFile = /foo/bar");
+        assert!(super::parse_excludes_file(&data).is_none());
+        let data = bytes("[user]\nexcludesFile = /foo/bar");
+        assert!(super::parse_excludes_file(&data).is_none());
+        let data = bytes("[user]\nname = foo\n[core]\nexcludesFile = /a/b");
+        let got = super::parse_excludes_file(&data).unwrap();
+        assert_eq!(path_string(got), "/a/b");
+    }
+
+    #[test]
+    fn parse_excludes_file7() {
+        // A later section ends the [core] section.
+        let data = bytes("[core]\nbare = false\n[user]\nexcludesFile = /a/b");
+        assert!(super::parse_excludes_file(&data).is_none());
+    }
+
+    #[test]
+    fn parse_ignore_case() {
+        let parse = |s: &str| super::parse_ignore_case(s.as_bytes());
+        assert_eq!(Some(true), parse("[core]\nignoreCase = true"));
+        assert_eq!(Some(true), parse("[core]\n\tignorecase = yes\n"));
+        assert_eq!(Some(true), parse("[core]\nignoreCase"));
+        assert_eq!(Some(false), parse("[core]\nignoreCase = false"));
+        assert_eq!(Some(false), parse("[core]\nIgnoreCase = Off"));
+        assert_eq!(None, parse("ignoreCase = true"));
+        assert_eq!(None, parse("[user]\nignoreCase = true"));
+        assert_eq!(None, parse("[core]\nignoreCase = wat"));
+        // Comments are skipped and the last assignment wins.
+        assert_eq!(
+            Some(false),
+            parse("[core]\n# ignoreCase = true\nignoreCase = false"),
+        );
+        assert_eq!(
+            Some(false),
+            parse("[core]\nignoreCase = true\nignoreCase = false"),
+        );
+    }
+
+    #[test]
+    fn expand_tilde_home() {
+        let home = match super::home_dir() {
+            None => return,
+            Some(home) => path_string(home),
+        };
+        assert_eq!(
+            format!("{}/foo/bar", home),
+            super::expand_tilde("~/foo/bar")
+        );
+    }
+
+    #[test]
+    fn expand_tilde_mid_path() {
+        // A tilde that isn't at the very beginning of the path is an
+        // ordinary path component and must not be expanded.
+        let path = "/backups/~archive/ignore";
+        assert_eq!(path, super::expand_tilde(path));
+        assert_eq!("/foo/~/bar", super::expand_tilde("/foo/~/bar"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn expand_tilde_user() {
+        match super::user_home_dir("root") {
+            // A system without root in /etc/passwd is strange, but possible.
+            // In that case, expansion must be skipped.
+            None => assert_eq!("~root/x", super::expand_tilde("~root/x")),
+            Some(home) => assert_eq!(
+                format!("{}/x", path_string(home)),
+                super::expand_tilde("~root/x")
+            ),
+        }
+    }
+
+    #[test]
+    fn expand_tilde_unknown_user() {
+        let path = "~hopefullynosuchuser/x";
+        assert_eq!(path, super::expand_tilde(path));
+    }
+
+    #[test]
+    fn expand_env_var() {
+        // Use bespoke variable names to avoid interfering with other tests
+        // in this process, since the environment is global state.
+        std::env::set_var("RIPGREP_TEST_EXPAND_ENV", "/my/config");
+        assert_eq!(
+            "/my/config/git/ignore",
+            super::expand_tilde("$RIPGREP_TEST_EXPAND_ENV/git/ignore")
+        );
+        assert_eq!(
+            "/my/config/git/ignore",
+            super::expand_tilde("${RIPGREP_TEST_EXPAND_ENV}/git/ignore")
+        );
+    }
+
+    #[test]
+    fn expand_env_var_unset() {
+        let path = "$RIPGREP_TEST_EXPAND_ENV_UNSET/git/ignore";
+        assert_eq!(path, super::expand_tilde(path));
+        let path = "${RIPGREP_TEST_EXPAND_ENV_UNSET}/git/ignore";
+        assert_eq!(path, super::expand_tilde(path));
+        // An unterminated ${ is similarly left alone.
+        let path = "${RIPGREP_TEST_EXPAND_ENV_UNSET/git/ignore";
+        assert_eq!(path, super::expand_tilde(path));
+    }
+
     // See: https://github.com/BurntSushi/ripgrep/issues/106
     #[test]
     fn regression_106() {
@@ -814,4 +1602,254 @@ mod tests {
     not_ignored!(cs2, ROOT, "*.html", "foo.HTML");
     not_ignored!(cs3, ROOT, "*.html", "foo.htm");
     not_ignored!(cs4, ROOT, "*.html", "foo.HTM");
+
+    #[test]
+    fn matched_contained_outside_root() {
+        let gi = gi_from_str("/repo", "build/");
+        assert!(gi.matched_contained("/other/build", true).is_none());
+        // The non-strict API assumes such paths are relative to the root,
+        // which yields a surprising match.
+        assert!(gi.matched("/other/build", true).is_ignore());
+    }
+
+    #[test]
+    fn matched_contained_inside_root() {
+        let gi = gi_from_str("/repo", "build/");
+        let m = gi.matched_contained("/repo/build", true).unwrap();
+        assert!(m.is_ignore());
+        // Relative paths are interpreted as relative to the root.
+        let m = gi.matched_contained("build", true).unwrap();
+        assert!(m.is_ignore());
+    }
+
+    #[test]
+    fn matched_contained_escape_via_parent_dir() {
+        let gi = gi_from_str("/repo", "build/");
+        assert!(gi.matched_contained("/repo/../other/build", true).is_none());
+        assert!(gi.matched_contained("../build", true).is_none());
+        assert!(gi.matched_contained("a/../../build", true).is_none());
+        // A `..` that stays within the root is fine.
+        let m = gi.matched_contained("a/../build", true).unwrap();
+        assert!(m.is_ignore());
+    }
+
+    #[test]
+    fn matched_contained_root_itself() {
+        let gi = gi_from_str("/repo", "build/");
+        let m = gi.matched_contained("/repo", true).unwrap();
+        assert!(m.is_none());
+    }
+
+    #[test]
+    fn require_containment() {
+        let mut builder = GitignoreBuilder::new("/repo");
+        builder.add_line(None, "build/").unwrap();
+        builder.require_containment(true);
+        let gi = builder.build().unwrap();
+        assert!(gi.matched("/other/build", true).is_none());
+        assert!(gi.matched("/repo/build", true).is_ignore());
+        assert!(gi.matched("build", true).is_ignore());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dir_hint_for_symlink() {
+        use super::dir_hint_for;
+        use crate::tests::TempDir;
+
+        let td = TempDir::new().unwrap();
+        std::fs::create_dir(td.path().join("real")).unwrap();
+        std::os::unix::fs::symlink(
+            td.path().join("real"),
+            td.path().join("link"),
+        )
+        .unwrap();
+
+        assert!(dir_hint_for(td.path().join("real"), false).unwrap());
+        assert!(dir_hint_for(td.path().join("real"), true).unwrap());
+        // git matches a symlink as a file, even when it points to a
+        // directory. Only when symlinks are followed does the hint reflect
+        // the target.
+        assert!(!dir_hint_for(td.path().join("link"), false).unwrap());
+        assert!(dir_hint_for(td.path().join("link"), true).unwrap());
+        assert!(dir_hint_for(td.path().join("missing"), false).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn analyze_shadowed_by_broader() {
+        use super::{analyze, RuleDiagnostic};
+
+        let gi = gi_from_str(ROOT, "/foo\n**/foo\n");
+        let diags = analyze(&gi);
+        assert_eq!(1, diags.len());
+        match diags[0] {
+            RuleDiagnostic::ShadowedBy { ref rule, ref by } => {
+                assert_eq!("/foo", rule.original());
+                assert_eq!("**/foo", by.original());
+            }
+            ref diag => panic!("unexpected diagnostic: {:?}", diag),
+        }
+    }
+
+    #[test]
+    fn analyze_shadowed_by_duplicate() {
+        use super::{analyze, RuleDiagnostic};
+
+        let gi = gi_from_str(ROOT, "*.log\nfoo\n*.log\n");
+        let diags = analyze(&gi);
+        assert_eq!(1, diags.len());
+        assert!(matches!(diags[0], RuleDiagnostic::ShadowedBy { .. }));
+    }
+
+    #[test]
+    fn analyze_polarity_and_dir_rules_not_shadowed() {
+        use super::analyze;
+
+        // A whitelist never shadows an ignore, and a directory-only rule
+        // never shadows one that also matches files.
+        let gi = gi_from_str(ROOT, "/foo\n!**/foo\n/bar\n**/bar/\n");
+        assert!(analyze(&gi).is_empty());
+    }
+
+    #[test]
+    fn analyze_never_effective_whitelist() {
+        use super::{analyze, RuleDiagnostic};
+
+        let gi = gi_from_str(ROOT, "target/\n!target/debug\n");
+        let diags = analyze(&gi);
+        assert_eq!(1, diags.len());
+        match diags[0] {
+            RuleDiagnostic::NeverEffective { ref rule } => {
+                assert_eq!("!target/debug", rule.original());
+            }
+            ref diag => panic!("unexpected diagnostic: {:?}", diag),
+        }
+    }
+
+    #[test]
+    fn analyze_reincluded_parent_is_effective() {
+        use super::analyze;
+
+        // The re-inclusion of `target/` makes the deeper whitelist
+        // reachable again.
+        let gi = gi_from_str(ROOT, "target/\n!target/\n!target/debug\n");
+        assert!(analyze(&gi).is_empty());
+    }
+
+    #[test]
+    fn analyze_live_rules_are_clean() {
+        use super::analyze;
+
+        let gi = gi_from_str(ROOT, "*.log\n!important.log\nbuild/\n");
+        assert!(analyze(&gi).is_empty());
+    }
+
+    #[test]
+    fn symlink_dir_matches_like_git() {
+        use super::dir_hint_for;
+        use crate::tests::TempDir;
+
+        let td = TempDir::new().unwrap();
+        std::fs::create_dir(td.path().join("real")).unwrap();
+        std::os::unix::fs::symlink(
+            td.path().join("real"),
+            td.path().join("link"),
+        )
+        .unwrap();
+
+        // `link/` only matches directories. git treats a symlink to a
+        // directory as a file, so the pattern must not ignore it...
+        let gi = gi_from_str(td.path(), "link/");
+        let link = td.path().join("link");
+        let hint = dir_hint_for(&link, false).unwrap();
+        assert!(gi.matched(&link, hint).is_none());
+        // ... but when symlinks are followed, the path resolves to a
+        // directory and the directory-only pattern applies.
+        let hint = dir_hint_for(&link, true).unwrap();
+        assert!(gi.matched(&link, hint).is_ignore());
+        // A pattern without the trailing slash ignores the symlink either
+        // way.
+        let gi = gi_from_str(td.path(), "link");
+        let hint = dir_hint_for(&link, false).unwrap();
+        assert!(gi.matched(&link, hint).is_ignore());
+    }
+
+    #[test]
+    fn add_reader_invalid_utf8_line_does_not_stop_parsing() {
+        let src = b"first\nbad\xFF\xFEglob\nlast\n";
+        let mut builder = GitignoreBuilder::new(ROOT);
+        assert!(builder.add_reader(None, &src[..]).is_none());
+        let gi = builder.build().unwrap();
+        assert!(gi.matched("first", false).is_ignore());
+        assert!(gi.matched("last", false).is_ignore());
+    }
+
+    #[test]
+    fn add_reader_skips_over_long_lines() {
+        let mut src = vec![];
+        src.extend_from_slice(b"first\n");
+        src.extend_from_slice(&vec![b'a'; 70 * (1 << 10)]);
+        src.extend_from_slice(b"\nlast\n");
+        let mut builder = GitignoreBuilder::new(ROOT);
+        let err = builder.add_reader(None, &src[..]).unwrap();
+        assert_eq!(Some(2), err.line());
+        let gi = builder.build().unwrap();
+        assert!(gi.matched("first", false).is_ignore());
+        assert!(gi.matched("last", false).is_ignore());
+        assert!(!gi.matched("aaaa", false).is_ignore());
+    }
+
+    #[test]
+    fn add_reader_no_trailing_newline() {
+        let mut builder = GitignoreBuilder::new(ROOT);
+        assert!(builder.add_reader(None, &b"foo\nbar"[..]).is_none());
+        let gi = builder.build().unwrap();
+        assert!(gi.matched("foo", false).is_ignore());
+        assert!(gi.matched("bar", false).is_ignore());
+    }
+
+    #[test]
+    fn merge_same_root() {
+        let low = gi_from_str(ROOT, "foo\n!bar\n");
+        let high = gi_from_str(ROOT, "!foo\nbar\n");
+        let gi = Gitignore::merge(&low, &high).unwrap();
+        // `high`'s rules come last, so they win.
+        assert!(gi.matched("foo", false).is_whitelist());
+        assert!(gi.matched("bar", false).is_ignore());
+
+        let gi = Gitignore::merge(&high, &low).unwrap();
+        assert!(gi.matched("foo", false).is_ignore());
+        assert!(gi.matched("bar", false).is_whitelist());
+    }
+
+    #[test]
+    fn merge_differing_roots() {
+        let low = gi_from_str(ROOT, "*.log\n");
+        let high =
+            gi_from_str(format!("{}/sub", ROOT), "!debug.log\n/build\n");
+        let gi = Gitignore::merge(&low, &high).unwrap();
+        assert_eq!(Path::new(ROOT), gi.path());
+        // `high`'s globs only apply below its own root, re-rooted.
+        assert!(gi.matched("wat.log", false).is_ignore());
+        assert!(gi.matched("sub/wat.log", false).is_ignore());
+        assert!(gi.matched("sub/debug.log", false).is_whitelist());
+        assert!(gi.matched("debug.log", false).is_ignore());
+        // Anchored globs stay anchored to `high`'s root.
+        assert!(gi.matched("sub/build", false).is_ignore());
+        assert!(gi.matched("sub/x/build", false).is_none());
+        assert!(gi.matched("build", false).is_none());
+
+        // The order of the arguments doesn't change the root.
+        let gi = Gitignore::merge(&high, &low).unwrap();
+        assert_eq!(Path::new(ROOT), gi.path());
+        assert!(gi.matched("sub/debug.log", false).is_ignore());
+    }
+
+    #[test]
+    fn merge_unrelated_roots() {
+        let low = gi_from_str("/foo", "*.log\n");
+        let high = gi_from_str("/bar", "!debug.log\n");
+        assert!(Gitignore::merge(&low, &high).is_err());
+    }
 }
@@ -48,11 +48,16 @@ See the documentation for `WalkBuilder` for many other options.
 
 use std::path::{Path, PathBuf};
 
+pub use crate::allowlist::Allowlist;
 pub use crate::walk::{
-    DirEntry, ParallelVisitor, ParallelVisitorBuilder, Walk, WalkBuilder,
-    WalkParallel, WalkState,
+    CustomIgnoreOpts, DirEntry, DirErrorPolicy, FdBudget, FdPermit,
+    HiddenMode, IgnoreCaseMode, IgnoreFileEvent, IgnoreFileKind,
+    IgnoreProvenance, ParallelVisitor, ParallelVisitorBuilder, PruneDecision,
+    SubmoduleMode, TraversalOrder, Walk, WalkBuilder, WalkParallel,
+    WalkSnapshot, WalkState, WalkVerifier,
 };
 
+mod allowlist;
 mod default_types;
 mod dir;
 pub mod gitignore;
@@ -111,6 +116,25 @@ pub enum Error {
         /// The underlying glob error as a string.
         err: String,
     },
+    /// An error that occurs when a recursive directory walk was terminated
+    /// because the cumulative size of the files it yielded exceeded a
+    /// configured budget.
+    ///
+    /// See [`WalkBuilder::max_total_bytes`].
+    ByteBudgetExceeded {
+        /// The configured budget, in bytes.
+        budget: u64,
+        /// The cumulative size of the files yielded, in bytes, at the point
+        /// the budget was exceeded.
+        seen: u64,
+    },
+    /// An error that occurs when a line in an ignore file exceeds the
+    /// maximum supported length. The line is skipped, but lines after it
+    /// are still read.
+    LineTooLong {
+        /// The maximum supported line length, in bytes.
+        limit: u64,
+    },
     /// A type selection for a file type that is not defined.
     UnrecognizedFileType(String),
     /// A user specified file type definition could not be parsed.
@@ -143,6 +167,10 @@ impl Clone for Error {
             Error::Glob { ref glob, ref err } => {
                 Error::Glob { glob: glob.clone(), err: err.clone() }
             }
+            Error::ByteBudgetExceeded { budget, seen } => {
+                Error::ByteBudgetExceeded { budget, seen }
+            }
+            Error::LineTooLong { limit } => Error::LineTooLong { limit },
             Error::UnrecognizedFileType(ref err) => {
                 Error::UnrecognizedFileType(err.clone())
             }
@@ -177,6 +205,8 @@ impl Error {
             Error::Loop { .. } => false,
             Error::Io(_) => true,
             Error::Glob { .. } => false,
+            Error::ByteBudgetExceeded { .. } => false,
+            Error::LineTooLong { .. } => false,
             Error::UnrecognizedFileType(_) => false,
             Error::InvalidDefinition => false,
         }
@@ -217,6 +247,8 @@ impl Error {
             Error::Loop { .. } => None,
             Error::Io(ref err) => Some(err),
             Error::Glob { .. } => None,
+            Error::ByteBudgetExceeded { .. } => None,
+            Error::LineTooLong { .. } => None,
             Error::UnrecognizedFileType(_) => None,
             Error::InvalidDefinition => None,
         }
@@ -242,11 +274,69 @@ impl Error {
             Error::Loop { .. } => None,
             Error::Io(err) => Some(err),
             Error::Glob { .. } => None,
+            Error::ByteBudgetExceeded { .. } => None,
+            Error::LineTooLong { .. } => None,
             Error::UnrecognizedFileType(_) => None,
             Error::InvalidDefinition => None,
         }
     }
 
+    /// Returns the [`std::io::ErrorKind`] of the underlying I/O error, if
+    /// this error corresponds to one.
+    ///
+    /// This is a convenience routine for `io_error().map(|e| e.kind())`,
+    /// useful for answering questions like "was this a permission error?"
+    /// without matching on the error variants directly.
+    pub fn io_kind(&self) -> Option<std::io::ErrorKind> {
+        self.io_error().map(|err| err.kind())
+    }
+
+    /// Returns the file path associated with this error, if one exists.
+    ///
+    /// For a file system loop, this returns the child path, i.e., the path
+    /// at which the loop was detected.
+    pub fn path(&self) -> Option<&Path> {
+        match *self {
+            Error::Partial(ref errs) if errs.len() == 1 => errs[0].path(),
+            Error::WithLineNumber { ref err, .. } => err.path(),
+            Error::WithPath { ref path, .. } => Some(path),
+            Error::WithDepth { ref err, .. } => err.path(),
+            Error::Loop { ref child, .. } => Some(child),
+            _ => None,
+        }
+    }
+
+    /// Returns the line number associated with this error, if one exists.
+    ///
+    /// Line numbers are associated with errors that arise from parsing a
+    /// particular line in an ignore file, and are 1-indexed.
+    pub fn line(&self) -> Option<u64> {
+        match *self {
+            Error::Partial(ref errs) if errs.len() == 1 => errs[0].line(),
+            Error::WithLineNumber { line, .. } => Some(line),
+            Error::WithPath { ref err, .. } => err.line(),
+            Error::WithDepth { ref err, .. } => err.line(),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the constituent errors that make up this
+    /// error.
+    ///
+    /// For most errors, the iterator yields just the error itself. For
+    /// partial errors, the iterator yields each of its component errors,
+    /// with nested partial errors flattened recursively.
+    pub fn iter(&self) -> impl Iterator<Item = &Error> {
+        let mut stack = vec![self];
+        std::iter::from_fn(move || loop {
+            let err = stack.pop()?;
+            match *err {
+                Error::Partial(ref errs) => stack.extend(errs.iter().rev()),
+                _ => return Some(err),
+            }
+        })
+    }
+
     /// Returns a depth associated with recursively walking a directory (if
     /// this error was generated from a recursive directory iterator).
     pub fn depth(&self) -> Option<usize> {
@@ -313,10 +403,33 @@ impl std::error::Error for Error {
             Error::Loop { .. } => "file system loop found",
             Error::Io(ref err) => err.description(),
             Error::Glob { ref err, .. } => err,
+            Error::ByteBudgetExceeded { .. } => "byte budget exceeded",
+            Error::LineTooLong { .. } => "line too long",
             Error::UnrecognizedFileType(_) => "unrecognized file type",
             Error::InvalidDefinition => "invalid definition",
         }
     }
+
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            // A partial error is a collection, so there is no single
+            // underlying error unless it has exactly one component.
+            Error::Partial(ref errs) if errs.len() == 1 => Some(&errs[0]),
+            Error::Partial(_) => None,
+            Error::WithLineNumber { ref err, .. } => Some(&**err),
+            Error::WithPath { ref err, .. } => Some(&**err),
+            Error::WithDepth { ref err, .. } => Some(&**err),
+            Error::Io(ref err) => Some(err),
+            // The underlying glob error is stored as a string, so there is
+            // nothing to chain to.
+            Error::Glob { .. }
+            | Error::Loop { .. }
+            | Error::ByteBudgetExceeded { .. }
+            | Error::LineTooLong { .. }
+            | Error::UnrecognizedFileType(_)
+            | Error::InvalidDefinition => None,
+        }
+    }
 }
 
 impl std::fmt::Display for Error {
@@ -346,6 +459,17 @@ impl std::fmt::Display for Error {
             Error::Glob { glob: Some(ref glob), ref err } => {
                 write!(f, "error parsing glob '{}': {}", glob, err)
             }
+            Error::ByteBudgetExceeded { budget, seen } => write!(
+                f,
+                "byte budget exceeded: \
+                 yielded {} bytes with a budget of {}",
+                seen, budget,
+            ),
+            Error::LineTooLong { limit } => write!(
+                f,
+                "line exceeds the maximum supported length of {} bytes",
+                limit,
+            ),
             Error::UnrecognizedFileType(ref ty) => {
                 write!(f, "unrecognized file type: {}", ty)
             }
@@ -502,6 +626,52 @@ mod tests {
         }
     }
 
+    #[test]
+    fn error_accessors() {
+        let io = std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "permission denied",
+        );
+        let err = crate::Error::Io(io).tagged("boop", 5);
+        assert_eq!(Some(std::io::ErrorKind::PermissionDenied), err.io_kind());
+        assert_eq!(Some(Path::new("boop")), err.path());
+        assert_eq!(Some(5), err.line());
+    }
+
+    #[test]
+    fn error_source_chain() {
+        let io =
+            std::io::Error::new(std::io::ErrorKind::NotFound, "not found");
+        let err = crate::Error::Io(io).with_path("a/b");
+        // WithPath -> Io -> io::Error
+        let source = std::error::Error::source(&err).unwrap();
+        let source = source.source().unwrap();
+        let io = source.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(std::io::ErrorKind::NotFound, io.kind());
+    }
+
+    #[test]
+    fn error_iter_flattens_partial() {
+        use crate::Error;
+
+        let err = Error::Partial(vec![
+            Error::InvalidDefinition,
+            Error::Partial(vec![
+                Error::UnrecognizedFileType("wat".to_string()),
+                Error::InvalidDefinition,
+            ]),
+            Error::InvalidDefinition.with_path("a/b"),
+        ]);
+        let flat: Vec<&Error> = err.iter().collect();
+        assert_eq!(4, flat.len());
+        assert!(matches!(flat[0], Error::InvalidDefinition));
+        assert!(matches!(flat[1], Error::UnrecognizedFileType(_)));
+        assert!(matches!(flat[2], Error::InvalidDefinition));
+        assert_eq!(Some(Path::new("a/b")), flat[3].path());
+        // A non-partial error yields just itself.
+        assert_eq!(1, Error::InvalidDefinition.iter().count());
+    }
+
     /// A simple wrapper for creating a temporary directory that is
     /// automatically deleted when it's dropped.
     ///
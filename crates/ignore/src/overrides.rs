@@ -4,11 +4,16 @@ This provides functionality similar to `--include` or `--exclude` in command
 line tools.
 */
 
-use std::path::Path;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
 
 use crate::{
     gitignore::{self, Gitignore, GitignoreBuilder},
-    Error, Match,
+    Error, Match, PartialErrorBuilder,
 };
 
 /// Glob represents a single glob in an override matcher.
@@ -42,37 +47,55 @@ impl<'a> Glob<'a> {
 }
 
 /// Manages a set of overrides provided explicitly by the end user.
+///
+/// Cloning an `Override` is cheap. Like [`Gitignore`], the compiled globs
+/// are shared behind an `Arc`, so clones can be freely handed out to other
+/// threads without re-compiling anything.
 #[derive(Clone, Debug)]
-pub struct Override(Gitignore);
+pub struct Override {
+    /// The matcher for globs matched relative to the root path given when
+    /// building the override matcher.
+    relative: Gitignore,
+    /// The matcher for globs matched against a path's absolute path. This is
+    /// only non-empty when `OverrideBuilder::match_absolute` is enabled.
+    absolute: Gitignore,
+    /// The working directory captured when this matcher was built, used to
+    /// make relative paths absolute for the `absolute` matcher.
+    cwd: Option<PathBuf>,
+}
 
 impl Override {
     /// Returns an empty matcher that never matches any file path.
     pub fn empty() -> Override {
-        Override(Gitignore::empty())
+        Override {
+            relative: Gitignore::empty(),
+            absolute: Gitignore::empty(),
+            cwd: None,
+        }
     }
 
     /// Returns the directory of this override set.
     ///
     /// All matches are done relative to this path.
     pub fn path(&self) -> &Path {
-        self.0.path()
+        self.relative.path()
     }
 
     /// Returns true if and only if this matcher is empty.
     ///
     /// When a matcher is empty, it will never match any file path.
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.relative.is_empty() && self.absolute.is_empty()
     }
 
     /// Returns the total number of ignore globs.
     pub fn num_ignores(&self) -> u64 {
-        self.0.num_whitelists()
+        self.relative.num_whitelists() + self.absolute.num_whitelists()
     }
 
     /// Returns the total number of whitelisted globs.
     pub fn num_whitelists(&self) -> u64 {
-        self.0.num_ignores()
+        self.relative.num_ignores() + self.absolute.num_ignores()
     }
 
     /// Returns whether the given file path matched a pattern in this override
@@ -93,6 +116,11 @@ impl Override {
     /// given) is stripped. If there is no common suffix/prefix overlap, then
     /// `path` is assumed to reside in the same directory as the root path for
     /// this set of overrides.
+    ///
+    /// Globs matched against absolute paths (see
+    /// [`OverrideBuilder::match_absolute`]) are consulted first and take
+    /// precedence over the relative globs, regardless of the order in which
+    /// the globs were added.
     pub fn matched<'a, P: AsRef<Path>>(
         &'a self,
         path: P,
@@ -101,18 +129,43 @@ impl Override {
         if self.is_empty() {
             return Match::None;
         }
-        let mat = self.0.matched(path, is_dir).invert();
+        let path = path.as_ref();
+        let mut mat = Match::None;
+        if !self.absolute.is_empty() {
+            mat = self
+                .absolute
+                .matched(self.absolute_path(path), is_dir)
+                .invert();
+        }
+        if mat.is_none() {
+            mat = self.relative.matched(path, is_dir).invert();
+        }
         if mat.is_none() && self.num_whitelists() > 0 && !is_dir {
             return Match::Ignore(Glob::unmatched());
         }
         mat.map(move |giglob| Glob(GlobInner::Matched(giglob)))
     }
+
+    /// Returns the absolute form of the path given, joining it with the
+    /// working directory captured at build time when it is relative. When no
+    /// working directory could be determined, the path is used as is.
+    fn absolute_path<'p>(&self, path: &'p Path) -> Cow<'p, Path> {
+        if path.is_absolute() {
+            return Cow::Borrowed(path);
+        }
+        match self.cwd {
+            Some(ref cwd) => Cow::Owned(cwd.join(path)),
+            None => Cow::Borrowed(path),
+        }
+    }
 }
 
 /// Builds a matcher for a set of glob overrides.
 #[derive(Clone, Debug)]
 pub struct OverrideBuilder {
     builder: GitignoreBuilder,
+    abs_builder: GitignoreBuilder,
+    match_absolute: bool,
 }
 
 impl OverrideBuilder {
@@ -120,14 +173,22 @@ impl OverrideBuilder {
     ///
     /// Matching is done relative to the directory path provided.
     pub fn new<P: AsRef<Path>>(path: P) -> OverrideBuilder {
-        OverrideBuilder { builder: GitignoreBuilder::new(path) }
+        OverrideBuilder {
+            builder: GitignoreBuilder::new(path),
+            abs_builder: GitignoreBuilder::new("/"),
+            match_absolute: false,
+        }
     }
 
     /// Builds a new override matcher from the globs added so far.
     ///
     /// Once a matcher is built, no new globs can be added to it.
     pub fn build(&self) -> Result<Override, Error> {
-        Ok(Override(self.builder.build()?))
+        Ok(Override {
+            relative: self.builder.build()?,
+            absolute: self.abs_builder.build()?,
+            cwd: std::env::current_dir().ok(),
+        })
     }
 
     /// Add a glob to the set of overrides.
@@ -137,10 +198,82 @@ impl OverrideBuilder {
     /// namely, `!` at the beginning of a glob will ignore a file. Without `!`,
     /// all matches of the glob provided are treated as whitelist matches.
     pub fn add(&mut self, glob: &str) -> Result<&mut OverrideBuilder, Error> {
-        self.builder.add_line(None, glob)?;
+        self.add_line(None, glob)?;
         Ok(self)
     }
 
+    /// Add a glob to whichever of the two underlying matchers it belongs to.
+    fn add_line(
+        &mut self,
+        from: Option<PathBuf>,
+        glob: &str,
+    ) -> Result<(), Error> {
+        if self.match_absolute && is_absolute_glob(glob) {
+            self.abs_builder.add_line(from, glob)?;
+        } else {
+            self.builder.add_line(from, glob)?;
+        }
+        Ok(())
+    }
+
+    /// Add each glob from the file path given.
+    ///
+    /// Globs are read one per line with the same comment (`#`), blank line
+    /// and escaping rules as a `gitignore` file, but with the semantics of
+    /// [`OverrideBuilder::add`]: a bare glob whitelists matches while a glob
+    /// starting with `!` ignores them.
+    ///
+    /// Note that partial errors can be returned. For example, if there was
+    /// a problem adding one glob, an error for that will be returned, but
+    /// all other valid globs will still be added.
+    pub fn add_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<&mut OverrideBuilder, Error> {
+        let path = path.as_ref();
+        let file = match File::open(path) {
+            Err(err) => return Err(Error::Io(err).with_path(path)),
+            Ok(file) => file,
+        };
+        self.add_reader(Some(path.to_path_buf()), file)
+    }
+
+    /// Add each glob read from the reader given.
+    ///
+    /// This is like [`OverrideBuilder::add_file`], except that the globs are
+    /// read from an arbitrary reader. If the globs came from a particular
+    /// file, then its path should be provided here so that errors can be
+    /// tagged with it.
+    pub fn add_reader<R: io::Read>(
+        &mut self,
+        from: Option<PathBuf>,
+        rdr: R,
+    ) -> Result<&mut OverrideBuilder, Error> {
+        let rdr = BufReader::new(rdr);
+        let mut errs = PartialErrorBuilder::default();
+        for (i, line) in rdr.lines().enumerate() {
+            let lineno = (i + 1) as u64;
+            let tag = |err: Error| match from {
+                Some(ref path) => err.tagged(path, lineno),
+                None => err,
+            };
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => {
+                    errs.push(tag(Error::Io(err)));
+                    break;
+                }
+            };
+            if let Err(err) = self.add_line(from.clone(), &line) {
+                errs.push(tag(err));
+            }
+        }
+        match errs.into_error_option() {
+            None => Ok(self),
+            Some(err) => Err(err),
+        }
+    }
+
     /// Toggle whether the globs should be matched case insensitively or not.
     ///
     /// When this option is changed, only globs added after the change will be affected.
@@ -153,8 +286,47 @@ impl OverrideBuilder {
         // TODO: This should not return a `Result`. Fix this in the next semver
         // release.
         self.builder.case_insensitive(yes)?;
+        self.abs_builder.case_insensitive(yes)?;
         Ok(self)
     }
+
+    /// Toggle whether globs that look like absolute paths are matched against
+    /// a path's absolute path.
+    ///
+    /// When enabled, a glob beginning with a `/` (or a drive prefix like
+    /// `C:` on Windows) matches against the absolute form of each path,
+    /// instead of being anchored to the root of this override set. This is
+    /// useful when globs are copied from absolute paths. Relative paths are
+    /// made absolute by joining them with the working directory at the time
+    /// the matcher is built. All other globs keep their usual relative
+    /// matching, and the two kinds can be freely mixed, including with their
+    /// `!` forms; see [`Override::matched`] for how they combine.
+    ///
+    /// When this option is changed, only globs added after the change will
+    /// be affected.
+    ///
+    /// This is disabled by default.
+    pub fn match_absolute(&mut self, yes: bool) -> &mut OverrideBuilder {
+        self.match_absolute = yes;
+        self
+    }
+}
+
+/// Returns true if the glob given looks like an absolute path: it begins
+/// with a `/`, or on Windows, with a drive prefix like `C:`. A leading `!`
+/// is ignored.
+fn is_absolute_glob(glob: &str) -> bool {
+    let body = glob.strip_prefix('!').unwrap_or(glob);
+    if body.starts_with('/') {
+        return true;
+    }
+    if cfg!(windows) {
+        let bytes = body.as_bytes();
+        return bytes.len() >= 2
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':';
+    }
+    false
 }
 
 #[cfg(test)]
@@ -240,6 +412,43 @@ mod tests {
         assert!(ov.matched("./foo/bar", false).is_none());
     }
 
+    #[test]
+    fn add_reader() {
+        let globs = "\
+# build artifacts are excluded
+*.rs
+!target/**
+
+*.toml
+";
+        let mut builder = OverrideBuilder::new(ROOT);
+        builder.add_reader(None, globs.as_bytes()).unwrap();
+        let ov = builder.build().unwrap();
+        assert!(ov.matched("foo.rs", false).is_whitelist());
+        assert!(ov.matched("Cargo.toml", false).is_whitelist());
+        assert!(ov.matched("target/foo.rs", false).is_ignore());
+        assert!(ov.matched("foo.c", false).is_ignore());
+    }
+
+    #[test]
+    fn add_file_partial_error() {
+        let td = crate::tests::TempDir::new().unwrap();
+        let path = td.path().join("globs");
+        std::fs::write(&path, "# comment\n*.foo\n\na{\n!*.bar\n").unwrap();
+
+        let mut builder = OverrideBuilder::new(ROOT);
+        let err = builder.add_file(&path).unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("globs"), "{}", msg);
+        assert!(msg.contains("line 4"), "{}", msg);
+
+        // The globs surrounding the invalid one should still be added.
+        let ov = builder.build().unwrap();
+        assert!(ov.matched("a.foo", false).is_whitelist());
+        assert!(ov.matched("a.bar", false).is_ignore());
+        assert!(ov.matched("a.baz", false).is_ignore());
+    }
+
     #[test]
     fn case_insensitive() {
         let ov = OverrideBuilder::new(ROOT)
@@ -264,4 +473,58 @@ mod tests {
         assert!(ov.matched("foo.htm", false).is_ignore());
         assert!(ov.matched("foo.HTM", false).is_ignore());
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn match_absolute() {
+        let mut builder = OverrideBuilder::new(ROOT);
+        builder.match_absolute(true);
+        builder.add("/home/andrew/foo/*.rs").unwrap();
+        builder.add("!/home/andrew/foo/secret.rs").unwrap();
+        let ov = builder.build().unwrap();
+        assert!(ov.matched("/home/andrew/foo/bar.rs", false).is_whitelist());
+        assert!(ov.matched("/home/andrew/foo/secret.rs", false).is_ignore());
+        assert!(ov.matched("/home/andrew/foo/bar.c", false).is_ignore());
+        assert!(ov.matched("/elsewhere/bar.rs", false).is_ignore());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn match_absolute_mixed() {
+        let mut builder = OverrideBuilder::new(ROOT);
+        builder.match_absolute(true);
+        builder.add("*.foo").unwrap();
+        builder.add("!/home/andrew/foo/keep.foo").unwrap();
+        let ov = builder.build().unwrap();
+        // The absolute glob takes precedence over the relative one.
+        assert!(ov.matched("/home/andrew/foo/keep.foo", false).is_ignore());
+        assert!(ov.matched("/home/andrew/foo/a.foo", false).is_whitelist());
+        assert!(ov.matched("a.foo", false).is_whitelist());
+        assert!(ov.matched("a.bar", false).is_ignore());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn match_absolute_only_later_globs() {
+        let mut builder = OverrideBuilder::new(ROOT);
+        builder.add("/bar").unwrap();
+        builder.match_absolute(true);
+        builder.add("/home/andrew/foo/*.rs").unwrap();
+        let ov = builder.build().unwrap();
+        // `/bar` was added before enabling absolute matching, so it keeps
+        // its usual meaning of being anchored to the override root.
+        assert!(ov.matched("bar", false).is_whitelist());
+        assert!(ov.matched("/home/andrew/foo/lib.rs", false).is_whitelist());
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn match_absolute_drive_letter() {
+        let mut builder = OverrideBuilder::new(ROOT);
+        builder.match_absolute(true);
+        builder.add("C:/foo/*.rs").unwrap();
+        let ov = builder.build().unwrap();
+        assert!(ov.matched("C:/foo/bar.rs", false).is_whitelist());
+        assert!(ov.matched("C:/elsewhere/bar.rs", false).is_ignore());
+    }
 }
@@ -1,14 +1,26 @@
-use std::{ffi::OsStr, path::Path};
-
-use crate::walk::DirEntry;
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use crate::walk::{DirEntry, HiddenMode};
+
+/// Returns true if and only if this entry is considered to be hidden under
+/// the given mode.
+pub(crate) fn is_hidden(dent: &DirEntry, mode: HiddenMode) -> bool {
+    match mode {
+        HiddenMode::DotOnly => is_dot_hidden(dent),
+        HiddenMode::Native => is_native_hidden(dent),
+        HiddenMode::Both => is_dot_hidden(dent) || is_native_hidden(dent),
+    }
+}
 
-/// Returns true if and only if this entry is considered to be hidden.
-///
-/// This only returns true if the base name of the path starts with a `.`.
+/// Returns true if and only if the base name of this entry's path starts
+/// with a `.`.
 ///
 /// On Unix, this implements a more optimized check.
 #[cfg(unix)]
-pub(crate) fn is_hidden(dent: &DirEntry) -> bool {
+fn is_dot_hidden(dent: &DirEntry) -> bool {
     use std::os::unix::ffi::OsStrExt;
 
     if let Some(name) = file_name(dent.path()) {
@@ -18,14 +30,21 @@ pub(crate) fn is_hidden(dent: &DirEntry) -> bool {
     }
 }
 
-/// Returns true if and only if this entry is considered to be hidden.
-///
-/// On Windows, this returns true if one of the following is true:
-///
-/// * The base name of the path starts with a `.`.
-/// * The file attributes have the `HIDDEN` property set.
+/// Returns true if and only if the base name of this entry's path starts
+/// with a `.`.
+#[cfg(not(unix))]
+fn is_dot_hidden(dent: &DirEntry) -> bool {
+    if let Some(name) = file_name(dent.path()) {
+        name.to_str().map(|s| s.starts_with(".")).unwrap_or(false)
+    } else {
+        false
+    }
+}
+
+/// Returns true if and only if this entry's file attributes have the
+/// `HIDDEN` property set.
 #[cfg(windows)]
-pub(crate) fn is_hidden(dent: &DirEntry) -> bool {
+fn is_native_hidden(dent: &DirEntry) -> bool {
     use std::os::windows::fs::MetadataExt;
     use winapi_util::file;
 
@@ -33,29 +52,122 @@ pub(crate) fn is_hidden(dent: &DirEntry) -> bool {
     // directory traverser reuses the metadata retrieved from each directory
     // entry and stores it on the DirEntry itself. So this is "free."
     if let Ok(md) = dent.metadata() {
-        if file::is_hidden(md.file_attributes() as u64) {
-            return true;
-        }
+        file::is_hidden(md.file_attributes() as u64)
+    } else {
+        false
     }
-    if let Some(name) = file_name(dent.path()) {
-        name.to_str().map(|s| s.starts_with(".")).unwrap_or(false)
+}
+
+/// Returns true if and only if this entry has the `UF_HIDDEN` file flag set,
+/// which is what Finder uses to decide whether to show a file.
+#[cfg(target_os = "macos")]
+fn is_native_hidden(dent: &DirEntry) -> bool {
+    use std::os::macos::fs::MetadataExt;
+
+    // From <sys/stat.h>: the file may be hidden from GUI displays.
+    const UF_HIDDEN: u32 = 0x0000_8000;
+
+    if let Ok(md) = dent.metadata() {
+        md.st_flags() & UF_HIDDEN != 0
     } else {
         false
     }
 }
 
-/// Returns true if and only if this entry is considered to be hidden.
+/// Returns false, since this platform has no native notion of a hidden file
+/// beyond the leading-dot convention.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn is_native_hidden(_dent: &DirEntry) -> bool {
+    false
+}
+
+/// Converts the given path to the Windows extended-length form (with a
+/// `\\?\` prefix), which lifts the `MAX_PATH` limit on filesystem calls
+/// made with it.
 ///
-/// This only returns true if the base name of the path starts with a `.`.
-#[cfg(not(any(unix, windows)))]
-pub(crate) fn is_hidden(dent: &DirEntry) -> bool {
-    if let Some(name) = file_name(dent.path()) {
-        name.to_str().map(|s| s.starts_with(".")).unwrap_or(false)
+/// Extended-length paths are handed to the filesystem verbatim, so `.`
+/// components are dropped and `..` components are resolved (lexically)
+/// here. Relative paths, paths already in extended-length form and paths
+/// with exotic prefixes (e.g., device paths) are returned unchanged.
+#[cfg(windows)]
+pub(crate) fn to_long_path(path: &Path) -> PathBuf {
+    use std::{
+        ffi::OsString,
+        path::{Component, Prefix},
+    };
+
+    if !path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let mut components = path.components();
+    let prefix = match components.next() {
+        Some(Component::Prefix(prefix)) => prefix,
+        _ => return path.to_path_buf(),
+    };
+    let mut long = OsString::new();
+    match prefix.kind() {
+        Prefix::Disk(_) => {
+            long.push(r"\\?\");
+            long.push(prefix.as_os_str());
+        }
+        Prefix::UNC(server, share) => {
+            long.push(r"\\?\UNC\");
+            long.push(server);
+            long.push(r"\");
+            long.push(share);
+        }
+        _ => return path.to_path_buf(),
+    }
+    long.push(r"\");
+    let mut long = PathBuf::from(long);
+    for component in components {
+        match component {
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+            Component::ParentDir => {
+                long.pop();
+            }
+            Component::Normal(name) => long.push(name),
+        }
+    }
+    long
+}
+
+/// Converts the given path to the Windows extended-length form.
+///
+/// On non-Windows platforms there is no such form, so the path is returned
+/// unchanged.
+#[cfg(not(windows))]
+pub(crate) fn to_long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Returns the normal (non extended-length) form of the given path when it
+/// carries a `\\?\` or `\\?\UNC\` prefix, and `None` otherwise.
+///
+/// `None` is also returned for the (unusual) prefixed path whose bytes
+/// aren't valid Unicode, since its prefix can't be reliably rewritten.
+#[cfg(windows)]
+pub(crate) fn strip_long_path_prefix(path: &Path) -> Option<PathBuf> {
+    let path = path.to_str()?;
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        Some(PathBuf::from(format!(r"\\{}", rest)))
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        Some(PathBuf::from(rest))
     } else {
-        false
+        None
     }
 }
 
+/// Returns the normal (non extended-length) form of the given path when it
+/// carries a `\\?\` or `\\?\UNC\` prefix, and `None` otherwise.
+///
+/// On non-Windows platforms there is no such prefix, so this always returns
+/// `None`.
+#[cfg(not(windows))]
+pub(crate) fn strip_long_path_prefix(_path: &Path) -> Option<PathBuf> {
+    None
+}
+
 /// Strip `prefix` from the `path` and return the remainder.
 ///
 /// If `path` doesn't have a prefix `prefix`, then return `None`.
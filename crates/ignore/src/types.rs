@@ -146,6 +146,7 @@ impl<'a> Glob<'a> {
 pub struct FileTypeDef {
     name: String,
     globs: Vec<String>,
+    builtin: bool,
 }
 
 impl FileTypeDef {
@@ -158,24 +159,37 @@ impl FileTypeDef {
     pub fn globs(&self) -> &[String] {
         &self.globs
     }
+
+    /// Returns true if and only if this definition came from the built-in
+    /// set of default file type definitions, unmodified.
+    ///
+    /// Adding a glob to a built-in definition clears this flag, since the
+    /// definition no longer corresponds to what ships by default.
+    pub fn is_builtin(&self) -> bool {
+        self.builtin
+    }
 }
 
 /// Types is a file type matcher.
+///
+/// Cloning a `Types` is cheap. The type definitions and compiled globs are
+/// shared behind an `Arc`, so clones can be freely handed out to other
+/// threads without re-compiling anything.
 #[derive(Clone, Debug)]
 pub struct Types {
     /// All of the file type definitions, sorted lexicographically by name.
-    defs: Vec<FileTypeDef>,
+    defs: Arc<Vec<FileTypeDef>>,
     /// All of the selections made by the user.
-    selections: Vec<Selection<FileTypeDef>>,
+    selections: Arc<Vec<Selection<FileTypeDef>>>,
     /// Whether there is at least one Selection::Select in our selections.
     /// When this is true, a Match::None is converted to Match::Ignore.
     has_selected: bool,
     /// A mapping from glob index in the set to two indices. The first is an
     /// index into `selections` and the second is an index into the
     /// corresponding file type definition's list of globs.
-    glob_to_selection: Vec<(usize, usize)>,
+    glob_to_selection: Arc<Vec<(usize, usize)>>,
     /// The set of all glob selections, used for actual matching.
-    set: GlobSet,
+    set: Arc<GlobSet>,
     /// Temporary storage for globs that match.
     matches: Arc<Pool<Vec<usize>>>,
 }
@@ -226,11 +240,11 @@ impl Types {
     /// contains no file type definitions.
     pub fn empty() -> Types {
         Types {
-            defs: vec![],
-            selections: vec![],
+            defs: Arc::new(vec![]),
+            selections: Arc::new(vec![]),
             has_selected: false,
-            glob_to_selection: vec![],
-            set: GlobSetBuilder::new().build().unwrap(),
+            glob_to_selection: Arc::new(vec![]),
+            set: Arc::new(GlobSetBuilder::new().build().unwrap()),
             matches: Arc::new(Pool::new(|| vec![])),
         }
     }
@@ -352,11 +366,11 @@ impl TypesBuilder {
             .build()
             .map_err(|err| Error::Glob { glob: None, err: err.to_string() })?;
         Ok(Types {
-            defs,
-            selections,
+            defs: Arc::new(defs),
+            selections: Arc::new(selections),
             has_selected,
-            glob_to_selection,
-            set,
+            glob_to_selection: Arc::new(glob_to_selection),
+            set: Arc::new(set),
             matches: Arc::new(Pool::new(|| vec![])),
         })
     }
@@ -404,29 +418,57 @@ impl TypesBuilder {
     }
 
     /// Clear any file type definitions for the type name given.
+    ///
+    /// Note that any selections of the name are left in place, so building
+    /// after clearing a selected type fails with an unrecognized file type
+    /// error. To excise a type entirely, use
+    /// [`remove`](TypesBuilder::remove).
     pub fn clear(&mut self, name: &str) -> &mut TypesBuilder {
         self.types.remove(name);
         self
     }
 
+    /// Remove the file type definition for the name given, along with any
+    /// selections of it.
+    ///
+    /// This is a stronger form of [`clear`](TypesBuilder::clear): the type
+    /// no longer participates in matching at all and does not appear in the
+    /// definitions reported by
+    /// [`definitions`](TypesBuilder::definitions).
+    pub fn remove(&mut self, name: &str) -> &mut TypesBuilder {
+        self.types.remove(name);
+        self.selections.retain(|sel| sel.name() != name);
+        self
+    }
+
     /// Add a new file type definition. `name` can be arbitrary and `pat`
     /// should be a glob recognizing file paths belonging to the `name` type.
     ///
     /// If `name` is `all` or otherwise contains any character that is not a
     /// Unicode letter or number, then an error is returned.
     pub fn add(&mut self, name: &str, glob: &str) -> Result<(), Error> {
+        self.add_impl(name, glob, false)
+    }
+
+    fn add_impl(
+        &mut self,
+        name: &str,
+        glob: &str,
+        builtin: bool,
+    ) -> Result<(), Error> {
         if name == "all" || !name.chars().all(|c| c.is_alphanumeric()) {
             return Err(Error::InvalidDefinition);
         }
         let (key, glob) = (name.to_string(), glob.to_string());
-        self.types
-            .entry(key)
-            .or_insert_with(|| FileTypeDef {
-                name: name.to_string(),
-                globs: vec![],
-            })
-            .globs
-            .push(glob);
+        let def = self.types.entry(key).or_insert_with(|| FileTypeDef {
+            name: name.to_string(),
+            globs: vec![],
+            builtin,
+        });
+        def.globs.push(glob);
+        // A definition is only "built-in" while every glob in it came from
+        // the default table.
+        def.builtin = def.builtin && builtin;
         Ok(())
     }
 
@@ -484,7 +526,7 @@ impl TypesBuilder {
         for &(names, exts) in DEFAULT_TYPES {
             for name in names {
                 for ext in exts {
-                    self.add(name, ext).expect(MSG);
+                    self.add_impl(name, ext, true).expect(MSG);
                 }
             }
         }
@@ -492,6 +534,40 @@ impl TypesBuilder {
     }
 }
 
+/// Returns a hash of the built-in file type definition table.
+///
+/// The hash is computed with a fixed algorithm (FNV-1a) over the names and
+/// globs of every built-in definition, so it is fully determined by the
+/// definition table itself. It is stable across platforms and builds of the
+/// same table, and changes whenever a built-in definition is added, removed
+/// or altered. This makes it cheap for tools to detect that an upgrade
+/// changed the built-in definitions without diffing the entire table.
+pub fn builtin_definitions_hash() -> u64 {
+    fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+        const PRIME: u64 = 0x100000001b3;
+        for &byte in bytes {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+        hash
+    }
+
+    let mut hash = 0xcbf29ce484222325;
+    for &(names, exts) in DEFAULT_TYPES {
+        for name in names {
+            hash = fnv1a(hash, name.as_bytes());
+            // Hash a separator after every string so that the boundaries
+            // between names and globs contribute to the hash.
+            hash = fnv1a(hash, &[0]);
+        }
+        for ext in exts {
+            hash = fnv1a(hash, ext.as_bytes());
+            hash = fnv1a(hash, &[0]);
+        }
+    }
+    hash
+}
+
 #[cfg(test)]
 mod tests {
     use super::TypesBuilder;
@@ -581,4 +657,51 @@ mod tests {
             assert_eq!(btypes.definitions(), original_defs);
         }
     }
+
+    #[test]
+    fn builtin_flag() {
+        let mut btypes = TypesBuilder::new();
+        btypes.add_defaults();
+        let defs = btypes.definitions();
+        assert!(!defs.is_empty());
+        assert!(defs.iter().all(|def| def.is_builtin()));
+
+        // A user-added definition is not built-in, and adding a glob to a
+        // built-in definition clears its flag.
+        btypes.add("foo", "*.foo").unwrap();
+        btypes.add("rust", "*.foo").unwrap();
+        let find = |name: &str| {
+            btypes.definitions().iter().find(|d| d.name() == name).cloned()
+        };
+        assert!(!find("foo").unwrap().is_builtin());
+        assert!(!find("rust").unwrap().is_builtin());
+        assert!(find("py").unwrap().is_builtin());
+    }
+
+    #[test]
+    fn remove_type() {
+        // Clearing a selected type makes building fail, since the selection
+        // refers to a type that no longer exists...
+        let mut btypes = TypesBuilder::new();
+        btypes.add_defaults();
+        btypes.select("rust");
+        btypes.clear("rust");
+        assert!(btypes.build().is_err());
+
+        // ... while removal excises the selection too.
+        let mut btypes = TypesBuilder::new();
+        btypes.add_defaults();
+        btypes.select("rust");
+        btypes.remove("rust");
+        let types = btypes.build().unwrap();
+        assert!(types.definitions().iter().all(|def| def.name() != "rust"));
+        assert!(types.matched("main.rs", false).is_none());
+    }
+
+    #[test]
+    fn builtin_hash_stable() {
+        let hash = super::builtin_definitions_hash();
+        assert_ne!(0, hash);
+        assert_eq!(hash, super::builtin_definitions_hash());
+    }
 }
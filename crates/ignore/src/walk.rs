@@ -4,8 +4,11 @@ use std::{
     fs::{self, FileType, Metadata},
     io,
     path::{Path, PathBuf},
-    sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering},
-    sync::Arc,
+    sync::atomic::{
+        AtomicBool, AtomicU64, AtomicUsize, Ordering as AtomicOrdering,
+    },
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, SystemTime},
 };
 
 use {
@@ -15,13 +18,22 @@ use {
 };
 
 use crate::{
+    allowlist::Allowlist,
     dir::{Ignore, IgnoreBuilder},
-    gitignore::GitignoreBuilder,
+    gitignore::{GitignoreBuilder, Glob},
     overrides::Override,
     types::Types,
-    Error, PartialErrorBuilder,
+    Error, Match, PartialErrorBuilder,
 };
 
+/// The default cap on the number of threads chosen automatically.
+///
+/// Directory traversal tends to be bound by storage rather than CPU, so
+/// there's little to gain (and contention to lose) by spawning a worker for
+/// every core on machines with many of them. See `WalkBuilder::threads_cap`
+/// for lifting this cap.
+const DEFAULT_THREADS_CAP: usize = 12;
+
 /// A directory entry with a possible error attached.
 ///
 /// The error typically refers to a problem parsing ignore files in a
@@ -30,18 +42,37 @@ use crate::{
 pub struct DirEntry {
     dent: DirEntryInner,
     err: Option<Error>,
+    /// The index of the root path this entry was reached from. Stamped by
+    /// the walkers when an entry is created.
+    root_index: usize,
+    /// The raw target of the symbolic link this entry corresponds to, if
+    /// any. Stamped by the walkers when an entry is created.
+    symlink_target: Option<PathBuf>,
+    /// The whitelist rule that caused this entry to be included, if any.
+    /// Stamped by the walkers when the ignore rules are consulted.
+    ignore_provenance: Option<IgnoreProvenance>,
+    /// The path of this entry in normal (non extended-length) form, stamped
+    /// by the walkers when long-path support converted the root to
+    /// extended-length form. See [`WalkBuilder::long_paths`].
+    normal_path: Option<PathBuf>,
 }
 
 impl DirEntry {
     /// The full path that this entry represents.
     pub fn path(&self) -> &Path {
-        self.dent.path()
+        match self.normal_path {
+            Some(ref path) => path,
+            None => self.dent.path(),
+        }
     }
 
     /// The full path that this entry represents.
     /// Analogous to [`DirEntry::path`], but moves ownership of the path.
     pub fn into_path(self) -> PathBuf {
-        self.dent.into_path()
+        match self.normal_path {
+            Some(path) => path,
+            None => self.dent.into_path(),
+        }
     }
 
     /// Whether this entry corresponds to a symbolic link or not.
@@ -49,6 +80,41 @@ impl DirEntry {
         self.dent.path_is_symlink()
     }
 
+    /// Returns true if and only if this entry was reached via a symbolic
+    /// link.
+    ///
+    /// This is true regardless of whether symbolic links are being followed.
+    /// When they are followed, [`DirEntry::file_type`] and
+    /// [`DirEntry::metadata`] describe the link's target while
+    /// [`DirEntry::path`] continues to report the link-side path.
+    pub fn is_via_symlink(&self) -> bool {
+        self.dent.path_is_symlink()
+    }
+
+    /// Returns the raw target of the symbolic link this entry corresponds
+    /// to, as reported by `readlink`.
+    ///
+    /// The target is captured when the entry is created by a walker and is
+    /// returned verbatim: it may be relative to the link's parent directory
+    /// and it may refer to a path that does not exist. `None` is returned
+    /// when this entry does not correspond to a symbolic link (or, rarely,
+    /// when the link could not be read).
+    pub fn symlink_target(&self) -> Option<&Path> {
+        self.symlink_target.as_deref()
+    }
+
+    /// Returns the canonical path of the file this entry points to, with
+    /// all symbolic links resolved.
+    ///
+    /// For entries not reached via a symbolic link, this is equivalent to
+    /// canonicalizing [`DirEntry::path`]. This returns an error for dangling
+    /// symbolic links, since they have no target to resolve. Use
+    /// [`DirEntry::symlink_target`] to inspect the raw target of such links.
+    pub fn resolved_path(&self) -> Result<PathBuf, Error> {
+        fs::canonicalize(self.path())
+            .map_err(|err| Error::Io(err).with_path(self.path()))
+    }
+
     /// Returns true if and only if this entry corresponds to stdin.
     ///
     /// i.e., The entry has depth 0 and its file name is `-`.
@@ -76,11 +142,33 @@ impl DirEntry {
         self.dent.file_name()
     }
 
+    /// Returns true if and only if this entry is considered hidden under the
+    /// given mode.
+    ///
+    /// This is the same decision input used by a walker configured with that
+    /// mode (see [`WalkBuilder::hidden_mode`]) when hidden file filtering is
+    /// enabled.
+    pub fn is_hidden(&self, mode: HiddenMode) -> bool {
+        crate::pathutil::is_hidden(self, mode)
+    }
+
     /// Returns the depth at which this entry was created relative to the root.
     pub fn depth(&self) -> usize {
         self.dent.depth()
     }
 
+    /// Returns the index of the root path this entry was reached from.
+    ///
+    /// The index corresponds to the order in which root paths were added to
+    /// a [`WalkBuilder`]: `0` for the path given to the constructor and
+    /// increasing by one for each call to [`WalkBuilder::add`]. Root entries
+    /// themselves and entries reached through symlink following carry the
+    /// index of their originating root. The full list of roots in the same
+    /// order is available via [`WalkBuilder::roots`].
+    pub fn root_index(&self) -> usize {
+        self.root_index
+    }
+
     /// Returns the underlying inode number if one exists.
     ///
     /// If this entry doesn't have an inode number, then `None` is returned.
@@ -89,6 +177,17 @@ impl DirEntry {
         self.dent.ino()
     }
 
+    /// Returns the whitelist rule that caused this entry to be included, if
+    /// any.
+    ///
+    /// This is populated when the walker's evaluation of the ignore rules
+    /// for this entry ended in a whitelist match, i.e., a `!`-prefixed glob
+    /// in a gitignore-style file overrode an ignore rule. It is `None` when
+    /// the entry simply didn't match any rule.
+    pub fn ignore_provenance(&self) -> Option<&IgnoreProvenance> {
+        self.ignore_provenance.as_ref()
+    }
+
     /// Returns an error, if one exists, associated with processing this entry.
     ///
     /// An example of an error is one that occurred while parsing an ignore
@@ -104,15 +203,100 @@ impl DirEntry {
     }
 
     fn new_stdin() -> DirEntry {
-        DirEntry { dent: DirEntryInner::Stdin, err: None }
+        DirEntry {
+            dent: DirEntryInner::Stdin,
+            err: None,
+            root_index: 0,
+            symlink_target: None,
+            ignore_provenance: None,
+            normal_path: None,
+        }
     }
 
     fn new_walkdir(dent: walkdir::DirEntry, err: Option<Error>) -> DirEntry {
-        DirEntry { dent: DirEntryInner::Walkdir(dent), err }
+        DirEntry {
+            dent: DirEntryInner::Walkdir(dent),
+            err,
+            root_index: 0,
+            symlink_target: None,
+            ignore_provenance: None,
+            normal_path: None,
+        }
     }
 
     fn new_raw(dent: DirEntryRaw, err: Option<Error>) -> DirEntry {
-        DirEntry { dent: DirEntryInner::Raw(dent), err }
+        DirEntry {
+            dent: DirEntryInner::Raw(dent),
+            err,
+            root_index: 0,
+            symlink_target: None,
+            ignore_provenance: None,
+            normal_path: None,
+        }
+    }
+
+    /// Captures the raw target of this entry's symbolic link, if it is one.
+    ///
+    /// The walkers call this when an entry is created so that the target
+    /// remains available even if the link changes before the caller looks
+    /// at it.
+    fn stamp_symlink_target(&mut self) {
+        if self.dent.path_is_symlink() {
+            self.symlink_target = fs::read_link(self.path()).ok();
+        }
+    }
+
+    /// Re-expresses this entry's path in normal (non extended-length) form.
+    ///
+    /// The walkers call this when long-path support converted the walk's
+    /// roots to extended-length form, so that the paths reported to callers
+    /// don't carry a `\\?\` prefix. This is a no-op for paths without such
+    /// a prefix.
+    fn stamp_normal_path(&mut self) {
+        self.normal_path =
+            crate::pathutil::strip_long_path_prefix(self.path());
+    }
+}
+
+/// Describes the whitelist rule that caused a directory entry to be
+/// included in a walk.
+///
+/// This is reported via [`DirEntry::ignore_provenance`] when a `!`-prefixed
+/// glob in a gitignore-style file overrode an ignore rule for the entry.
+#[derive(Clone, Debug)]
+pub struct IgnoreProvenance {
+    from: Option<PathBuf>,
+    line: Option<u64>,
+    original: String,
+}
+
+impl IgnoreProvenance {
+    fn from_gitignore(glob: &Glob) -> IgnoreProvenance {
+        IgnoreProvenance {
+            from: glob.from().map(|p| p.to_path_buf()),
+            line: glob.line(),
+            original: glob.original().to_string(),
+        }
+    }
+
+    /// Returns the path of the ignore file that defined the whitelist glob.
+    ///
+    /// This is `None` for globs that weren't read from a file, e.g., global
+    /// gitignore rules added programmatically.
+    pub fn from(&self) -> Option<&Path> {
+        self.from.as_deref()
+    }
+
+    /// Returns the line number, starting at `1`, at which the whitelist glob
+    /// appears in the file that defined it, if known.
+    pub fn line(&self) -> Option<u64> {
+        self.line
+    }
+
+    /// The original glob as it was defined in the ignore file, including its
+    /// `!` prefix.
+    pub fn original(&self) -> &str {
+        &self.original
     }
 }
 
@@ -431,6 +615,266 @@ impl DirEntryRaw {
     }
 }
 
+/// Options controlling how a custom ignore file participates in matching.
+///
+/// These options permit emulating the dialects of custom ignore files used
+/// by other tools, which do not always follow gitignore semantics to the
+/// letter. The default options correspond to standard gitignore semantics,
+/// which is how custom ignore files added without options behave.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CustomIgnoreOpts {
+    /// Whether ignore files with this name in parent directories of the
+    /// paths walked are respected.
+    ///
+    /// This is enabled by default. Note that it only has an effect when
+    /// [`WalkBuilder::parents`] is also enabled.
+    pub parents: bool,
+    /// Whether `!`-prefixed whitelist globs in ignore files with this name
+    /// may override ignore rules from lower precedence ignore files, such as
+    /// `.gitignore`.
+    ///
+    /// When disabled, a whitelist match from this ignore file is treated as
+    /// if the file had no opinion about the path at all.
+    ///
+    /// This is enabled by default.
+    pub allow_whitelist: bool,
+    /// Whether every glob in ignore files with this name is anchored to the
+    /// directory containing the ignore file, regardless of whether it begins
+    /// with a `/`.
+    ///
+    /// This is disabled by default.
+    pub anchored_only: bool,
+}
+
+impl Default for CustomIgnoreOpts {
+    fn default() -> CustomIgnoreOpts {
+        CustomIgnoreOpts {
+            parents: true,
+            allow_whitelist: true,
+            anchored_only: false,
+        }
+    }
+}
+
+/// The policy for handling errors that occur while reading a directory's
+/// contents.
+///
+/// When reading a directory fails (e.g., with `EACCES` on an unreadable
+/// directory, or a transient `EIO` on a flaky network mount), its entire
+/// subtree is dropped from the walk. By default the failure is reported as a
+/// non-fatal `Err` entry, which visitors commonly ignore, making such
+/// dropped subtrees easy to miss.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DirErrorPolicy {
+    /// Report the error as an `Err` entry and continue the walk.
+    ///
+    /// This is the default.
+    Warn,
+    /// Report the error as the final entry and terminate the walk.
+    Fatal,
+    /// Retry reading the directory before giving up.
+    ///
+    /// Reading is attempted up to `attempts` additional times, sleeping for
+    /// `backoff` before each retry. If every attempt fails, the last error
+    /// is reported as with `Warn`.
+    ///
+    /// In the parallel walker, the backoff sleep happens on the worker that
+    /// encountered the error, so other workers keep making progress. The
+    /// serial walker cannot re-read a directory once its iterator has
+    /// failed, so it treats this policy like `Warn`.
+    Retry {
+        /// The number of additional read attempts to make.
+        attempts: u32,
+        /// How long to sleep before each retry.
+        backoff: std::time::Duration,
+    },
+}
+
+impl Default for DirErrorPolicy {
+    fn default() -> DirErrorPolicy {
+        DirErrorPolicy::Warn
+    }
+}
+
+/// The order in which a directory is yielded relative to its contents.
+///
+/// This only affects when directory entries are yielded. Which entries are
+/// yielded, their depths and all ignore semantics are the same in both
+/// orders.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TraversalOrder {
+    /// Yield a directory before its contents.
+    ///
+    /// This is the default.
+    PreOrder,
+    /// Yield a directory after all of its contents.
+    ///
+    /// This is useful for computing per-directory rollups (e.g., disk
+    /// usage), since every entry beneath a directory is seen before the
+    /// directory itself. A directory that is skipped (e.g., by an ignore
+    /// rule or a `filter_entry` predicate) is not descended into and is not
+    /// yielded at all, just as in pre-order.
+    PostOrder,
+}
+
+impl Default for TraversalOrder {
+    fn default() -> TraversalOrder {
+        TraversalOrder::PreOrder
+    }
+}
+
+/// The strategy to use for git submodules encountered during traversal.
+///
+/// A directory is treated as a submodule when it is listed in the
+/// `.gitmodules` file of a containing repository and has its own `.git`,
+/// which is a file for submodules created by modern git and a directory for
+/// older ones. A directory with its own `.git` that isn't listed in any
+/// `.gitmodules` file is a plain nested repository and is always walked.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SubmoduleMode {
+    /// Descend into submodules and apply the parent repository's ignore
+    /// rules to them.
+    ///
+    /// This is the default, and matches the behavior of walks that predate
+    /// submodule awareness. In this mode, `.gitmodules` files aren't read at
+    /// all.
+    Descend,
+    /// Do not walk into submodules.
+    ///
+    /// This matches the default behavior of `git grep`, which doesn't
+    /// recurse into submodules unless asked to.
+    Skip,
+    /// Descend into submodules, but only apply the submodule's own ignore
+    /// rules below the submodule boundary.
+    ///
+    /// This matches git semantics: ignore rules from a parent repository do
+    /// not apply to the contents of its submodules. Global gitignore rules
+    /// and explicitly added ignore matchers still apply everywhere.
+    DescendIsolated,
+}
+
+impl Default for SubmoduleMode {
+    fn default() -> SubmoduleMode {
+        SubmoduleMode::Descend
+    }
+}
+
+/// The strategy used to decide whether a file is hidden.
+///
+/// This controls what "hidden" means when hidden file filtering is enabled
+/// (see [`WalkBuilder::hidden`]).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HiddenMode {
+    /// A file is hidden if and only if its base name starts with a `.`.
+    DotOnly,
+    /// A file is hidden if and only if the platform's native hidden marker
+    /// says so: the `HIDDEN` file attribute on Windows and the `UF_HIDDEN`
+    /// file flag on macOS. On platforms with no such marker, nothing is
+    /// hidden in this mode.
+    Native,
+    /// A file is hidden when either of the above applies. This matches what
+    /// Explorer and Finder consider hidden.
+    ///
+    /// This is the default. Note that on Windows, the `HIDDEN` attribute
+    /// has always been respected, but on macOS, respecting `UF_HIDDEN` is
+    /// new. Use `DotOnly` for the dotfile-only semantics.
+    Both,
+}
+
+impl Default for HiddenMode {
+    fn default() -> HiddenMode {
+        HiddenMode::Both
+    }
+}
+
+/// Controls whether the globs in ignore files are matched case insensitively
+/// or not.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IgnoreCaseMode {
+    /// Follow git's `core.ignoreCase` config setting, which git enables when
+    /// it initializes a repository on a case insensitive file system.
+    ///
+    /// Within a git repository, the setting is read from the repository's
+    /// own config file first and from the global config second. Outside a
+    /// git repository, or when the setting is absent, globs are matched case
+    /// sensitively.
+    ///
+    /// This is the default.
+    Auto,
+    /// Always match ignore globs case insensitively.
+    Always,
+    /// Always match ignore globs case sensitively.
+    Never,
+}
+
+impl Default for IgnoreCaseMode {
+    fn default() -> IgnoreCaseMode {
+        IgnoreCaseMode::Auto
+    }
+}
+
+/// The kind of ignore file described by an [`IgnoreFileEvent`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IgnoreFileKind {
+    /// A plain `.ignore` file.
+    Ignore,
+    /// A `.gitignore` file.
+    Gitignore,
+    /// A `.git/info/exclude` file.
+    GitExclude,
+    /// The global gitignore file, usually configured via git's
+    /// `core.excludesFile` option.
+    GitGlobal,
+    /// An ignore file with a custom name, as added via
+    /// [`WalkBuilder::add_custom_ignore_filename`].
+    Custom,
+}
+
+/// An event describing a single ignore file read during a walk.
+///
+/// Events are reported to the callback registered via
+/// [`WalkBuilder::on_ignore_file`].
+#[derive(Clone, Debug)]
+pub struct IgnoreFileEvent {
+    pub(crate) path: PathBuf,
+    pub(crate) kind: IgnoreFileKind,
+    pub(crate) duration: Duration,
+    pub(crate) glob_count: u64,
+    pub(crate) cached: bool,
+}
+
+impl IgnoreFileEvent {
+    /// The path of the ignore file that was read.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The kind of ignore file that was read.
+    pub fn kind(&self) -> IgnoreFileKind {
+        self.kind
+    }
+
+    /// How long it took to read the ignore file and build a matcher from it.
+    ///
+    /// For cached matchers, this is the time spent looking the matcher up.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The total number of globs parsed from the ignore file.
+    pub fn glob_count(&self) -> u64 {
+        self.glob_count
+    }
+
+    /// Whether the matcher came from a cache built before the walk started
+    /// instead of being read just now.
+    ///
+    /// See [`WalkBuilder::prefetch_gitignores`].
+    pub fn cached(&self) -> bool {
+        self.cached
+    }
+}
+
 /// WalkBuilder builds a recursive directory iterator.
 ///
 /// The builder supports a large number of configurable options. This includes
@@ -477,6 +921,8 @@ impl DirEntryRaw {
 /// path is skipped.
 /// * Sixth, unless the path is a directory, the size of the file is compared
 /// against the max filesize limit. If it exceeds the limit, it is skipped.
+/// Likewise, its modification time is compared against any configured time
+/// filters, and it is skipped if it falls outside of them.
 /// * Seventh, if the path has made it this far then it is yielded in the
 /// iterator.
 #[derive(Clone)]
@@ -484,13 +930,27 @@ pub struct WalkBuilder {
     paths: Vec<PathBuf>,
     ig_builder: IgnoreBuilder,
     max_depth: Option<usize>,
+    min_depth: Option<usize>,
     max_filesize: Option<u64>,
+    max_total_bytes: Option<u64>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    prune_unmodified_dirs: bool,
     follow_links: bool,
     same_file_system: bool,
+    long_paths: bool,
     sorter: Option<Sorter>,
     threads: usize,
+    threads_cap: Option<usize>,
+    fd_budget: Option<FdBudget>,
     skip: Option<Arc<Handle>>,
     filter: Option<Filter>,
+    allowlist: Option<Allowlist>,
+    prune_policy: Option<PrunePolicy>,
+    dir_error_policy: DirErrorPolicy,
+    traversal: TraversalOrder,
+    prefetch_gitignores: bool,
+    capture_state: Option<Arc<Mutex<Vec<DirSnapshot>>>>,
 }
 
 #[derive(Clone)]
@@ -502,13 +962,38 @@ enum Sorter {
 #[derive(Clone)]
 struct Filter(Arc<dyn Fn(&DirEntry) -> bool + Send + Sync + 'static>);
 
+/// A decision made by a prune policy callback when a directory is about to
+/// be skipped because it matched an ignore rule.
+///
+/// See [`WalkBuilder::prune_policy`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PruneDecision {
+    /// Confirm the prune: the directory and everything beneath it is
+    /// skipped. This is what happens when no prune policy is set.
+    Prune,
+    /// Descend into the directory anyway. The directory itself is yielded,
+    /// and files and directories beneath it are still matched against
+    /// ignore rules individually.
+    Descend,
+    /// Include the directory and everything beneath it, without consulting
+    /// ignore rules for any of its children.
+    Include,
+}
+
+#[derive(Clone)]
+struct PrunePolicy(
+    Arc<dyn Fn(&Path, &Match<&Glob>) -> PruneDecision + Send + Sync + 'static>,
+);
+
 impl std::fmt::Debug for WalkBuilder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("WalkBuilder")
             .field("paths", &self.paths)
             .field("ig_builder", &self.ig_builder)
             .field("max_depth", &self.max_depth)
+            .field("min_depth", &self.min_depth)
             .field("max_filesize", &self.max_filesize)
+            .field("max_total_bytes", &self.max_total_bytes)
             .field("follow_links", &self.follow_links)
             .field("threads", &self.threads)
             .field("skip", &self.skip)
@@ -528,13 +1013,27 @@ impl WalkBuilder {
             paths: vec![path.as_ref().to_path_buf()],
             ig_builder: IgnoreBuilder::new(),
             max_depth: None,
+            min_depth: None,
             max_filesize: None,
+            max_total_bytes: None,
+            modified_after: None,
+            modified_before: None,
+            prune_unmodified_dirs: false,
             follow_links: false,
             same_file_system: false,
+            long_paths: cfg!(windows),
             sorter: None,
             threads: 0,
+            threads_cap: Some(DEFAULT_THREADS_CAP),
+            fd_budget: None,
             skip: None,
             filter: None,
+            allowlist: None,
+            prune_policy: None,
+            dir_error_policy: DirErrorPolicy::default(),
+            traversal: TraversalOrder::default(),
+            prefetch_gitignores: false,
+            capture_state: None,
         }
     }
 
@@ -546,11 +1045,21 @@ impl WalkBuilder {
         let its = self
             .paths
             .iter()
-            .map(move |p| {
+            .enumerate()
+            .map(move |(i, p)| {
                 if p == Path::new("-") {
-                    (p.to_path_buf(), None)
+                    (i, p.to_path_buf(), None)
                 } else {
-                    let mut wd = WalkDir::new(p);
+                    // The extended-length form is only used for the
+                    // traversal's filesystem calls; the paths reported to
+                    // callers keep their normal form. (See `skip_entry` and
+                    // `DirEntry::stamp_normal_path`.)
+                    let walk_root = if self.long_paths {
+                        crate::pathutil::to_long_path(p)
+                    } else {
+                        p.to_path_buf()
+                    };
+                    let mut wd = WalkDir::new(walk_root);
                     wd = wd.follow_links(follow_links || p.is_file());
                     wd = wd.same_file_system(self.same_file_system);
                     if let Some(max_depth) = max_depth {
@@ -570,20 +1079,36 @@ impl WalkBuilder {
                             }
                         }
                     }
-                    (p.to_path_buf(), Some(WalkEventIter::from(wd)))
+                    (i, p.to_path_buf(), Some(WalkEventIter::from(wd)))
                 }
             })
             .collect::<Vec<_>>()
             .into_iter();
-        let ig_root = self.ig_builder.build();
+        let ig_root = self.build_ig_root();
         Walk {
             its,
             it: None,
             ig_root: ig_root.clone(),
             ig: ig_root.clone(),
+            min_depth: self.min_depth,
             max_filesize: self.max_filesize,
+            byte_budget: self.max_total_bytes.map(ByteBudget::new),
+            budget_err: None,
+            modified_after: self.modified_after,
+            modified_before: self.modified_before,
+            prune_unmodified_dirs: self.prune_unmodified_dirs,
             skip: self.skip.clone(),
             filter: self.filter.clone(),
+            allowlist: self.allowlist.clone(),
+            prune_policy: self.prune_policy.clone(),
+            dir_error_policy: self.dir_error_policy,
+            traversal: self.traversal,
+            pending_dirs: vec![],
+            forced_root: None,
+            cur_root: 0,
+            cur_root_path: PathBuf::new(),
+            long_paths: self.long_paths,
+            capture: self.capture_state.clone(),
         }
     }
 
@@ -595,15 +1120,37 @@ impl WalkBuilder {
     pub fn build_parallel(&self) -> WalkParallel {
         WalkParallel {
             paths: self.paths.clone().into_iter(),
-            ig_root: self.ig_builder.build(),
+            ig_root: self.build_ig_root(),
             max_depth: self.max_depth,
+            min_depth: self.min_depth,
             max_filesize: self.max_filesize,
+            byte_budget: self.max_total_bytes.map(ByteBudget::new),
+            modified_after: self.modified_after,
+            modified_before: self.modified_before,
+            prune_unmodified_dirs: self.prune_unmodified_dirs,
             follow_links: self.follow_links,
             same_file_system: self.same_file_system,
+            long_paths: self.long_paths,
             threads: self.threads,
+            threads_cap: self.threads_cap,
+            fd_budget: self.fd_budget.clone(),
             skip: self.skip.clone(),
             filter: self.filter.clone(),
+            allowlist: self.allowlist.clone(),
+            prune_policy: self.prune_policy.clone(),
+            dir_error_policy: self.dir_error_policy,
+            traversal: self.traversal,
+            capture: self.capture_state.clone(),
+        }
+    }
+
+    /// Builds the root `Ignore` matcher, prefetching ignore file matchers
+    /// first if requested.
+    fn build_ig_root(&self) -> Ignore {
+        if !self.prefetch_gitignores {
+            return self.ig_builder.build();
         }
+        self.ig_builder.clone().prefetch_gitignores(&self.paths).build()
     }
 
     /// Add a file path to the iterator.
@@ -616,6 +1163,16 @@ impl WalkBuilder {
         self
     }
 
+    /// Returns the root paths that will be traversed, in the order they were
+    /// added.
+    ///
+    /// The position of each path in the returned slice corresponds to the
+    /// value reported by [`DirEntry::root_index`] for entries reached from
+    /// that root.
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
     /// The maximum depth to recurse.
     ///
     /// The default, `None`, imposes no depth restriction.
@@ -624,6 +1181,26 @@ impl WalkBuilder {
         self
     }
 
+    /// The minimum depth of entries to yield.
+    ///
+    /// Entries shallower than the minimum are not yielded, although
+    /// directories are still descended into so that deeper entries can be
+    /// found. As with `max_depth`, a root path given to this builder is at
+    /// depth `0` and its immediate children are at depth `1`.
+    ///
+    /// Note that any predicate given via `filter_entry` is not invoked for
+    /// files that are suppressed by this setting. (It is still invoked for
+    /// directories, since it determines whether to descend into them.)
+    ///
+    /// If the minimum depth exceeds the maximum depth, then nothing is
+    /// yielded.
+    ///
+    /// The default, `None`, imposes no depth restriction.
+    pub fn min_depth(&mut self, depth: Option<usize>) -> &mut WalkBuilder {
+        self.min_depth = depth;
+        self
+    }
+
     /// Whether to follow symbolic links or not.
     pub fn follow_links(&mut self, yes: bool) -> &mut WalkBuilder {
         self.follow_links = yes;
@@ -636,17 +1213,152 @@ impl WalkBuilder {
         self
     }
 
+    /// A budget on the number of concurrently open file handles.
+    ///
+    /// When set, the parallel walker acquires a permit from the budget
+    /// around each directory it opens, so that wide parallel walks on
+    /// systems with a low file descriptor limit (`ulimit -n`) don't exhaust
+    /// descriptors and fail mid-run with `EMFILE` errors. A clone of the
+    /// budget handle is available via
+    /// [`fd_budget_handle`](WalkBuilder::fd_budget_handle), so that the
+    /// file handles a caller opens for the yielded entries can be counted
+    /// against the same budget.
+    ///
+    /// Permits are held only for the duration of a single directory open,
+    /// never while waiting for more work, so a budget smaller than the
+    /// number of threads slows the walk down rather than deadlocking it.
+    /// Since each worker streams the entries of at most one directory at a
+    /// time, the number of concurrently open directory handles is still
+    /// bounded by the number of threads when it exceeds the budget.
+    ///
+    /// A budget of `0` is treated as `1`, since no progress could be made
+    /// otherwise. The budget only applies to parallel walks built via
+    /// [`build_parallel`](WalkBuilder::build_parallel); the serial walker
+    /// opens one directory handle per level of depth regardless.
+    ///
+    /// By default, there is no budget.
+    pub fn fd_budget(&mut self, budget: Option<usize>) -> &mut WalkBuilder {
+        self.fd_budget = budget.map(FdBudget::new);
+        self
+    }
+
+    /// Returns a handle to the file descriptor budget set via
+    /// [`fd_budget`](WalkBuilder::fd_budget), if any.
+    ///
+    /// The handle can be cloned freely and shared with whatever processes
+    /// the yielded entries, so that the file handles it opens draw from the
+    /// same budget as the walker's directory handles.
+    pub fn fd_budget_handle(&self) -> Option<FdBudget> {
+        self.fd_budget.clone()
+    }
+
+    /// A budget on the cumulative size, in bytes, of the regular files
+    /// yielded by the walk.
+    ///
+    /// The size of each yielded regular file (from its metadata) is added
+    /// to a shared accumulator. Once the accumulator exceeds the budget,
+    /// the walk terminates, with a [`Error::ByteBudgetExceeded`] delivered
+    /// as its final event so that callers can distinguish a truncated walk
+    /// from a complete one. This is useful for sampling a bounded amount of
+    /// data from repositories of unbounded size.
+    ///
+    /// Files whose size is unknown (e.g., because reading their metadata
+    /// failed) count as zero bytes but are still yielded. In a parallel
+    /// walk, the budget is checked per entry, so a handful of entries may
+    /// still be yielded by other threads after the budget is exceeded.
+    ///
+    /// By default, there is no budget.
+    pub fn max_total_bytes(&mut self, bytes: Option<u64>) -> &mut WalkBuilder {
+        self.max_total_bytes = bytes;
+        self
+    }
+
+    /// Only yield regular files whose modification time is at or after the
+    /// given time.
+    ///
+    /// This is useful for incremental processing, where only files modified
+    /// since some checkpoint are of interest. Files whose modification time
+    /// cannot be determined are yielded, since a failure to read metadata
+    /// here typically manifests as a more useful error downstream. Files
+    /// failing the filter are silently skipped and are not reported as
+    /// errors. Directories are not subject to this filter, although see
+    /// [`prune_unmodified_dirs`](WalkBuilder::prune_unmodified_dirs).
+    ///
+    /// The default, `None`, imposes no restriction.
+    pub fn modified_after(
+        &mut self,
+        time: Option<SystemTime>,
+    ) -> &mut WalkBuilder {
+        self.modified_after = time;
+        self
+    }
+
+    /// Only yield regular files whose modification time is at or before the
+    /// given time.
+    ///
+    /// This is the counterpart to
+    /// [`modified_after`](WalkBuilder::modified_after), and the two may be
+    /// combined to select a window of time. As with `modified_after`, files
+    /// whose modification time cannot be determined are yielded, files
+    /// failing the filter are silently skipped and directories are not
+    /// subject to the filter.
+    ///
+    /// The default, `None`, imposes no restriction.
+    pub fn modified_before(
+        &mut self,
+        time: Option<SystemTime>,
+    ) -> &mut WalkBuilder {
+        self.modified_before = time;
+        self
+    }
+
+    /// Whether to skip descending into directories whose own modification
+    /// time is older than the time given to
+    /// [`modified_after`](WalkBuilder::modified_after).
+    ///
+    /// This is a heuristic: a directory's modification time is only updated
+    /// when an entry is added, removed or renamed directly within it, so a
+    /// file rewritten in place deep inside an old directory does not bubble
+    /// its timestamp up to its ancestors. Filesystems vary in how faithfully
+    /// they maintain directory timestamps at all. Enabling this can
+    /// therefore miss modified files, which is why it is opt-in, but when
+    /// the assumption holds it avoids enumerating entire unmodified
+    /// subtrees.
+    ///
+    /// This has no effect unless `modified_after` is set.
+    ///
+    /// This is disabled by default.
+    pub fn prune_unmodified_dirs(&mut self, yes: bool) -> &mut WalkBuilder {
+        self.prune_unmodified_dirs = yes;
+        self
+    }
+
     /// The number of threads to use for traversal.
     ///
     /// Note that this only has an effect when using `build_parallel`.
     ///
     /// The default setting is `0`, which chooses the number of threads
-    /// automatically using heuristics.
+    /// automatically using heuristics. Namely, the available parallelism
+    /// reported by the system is used, limited by the cap set via
+    /// `threads_cap`.
     pub fn threads(&mut self, n: usize) -> &mut WalkBuilder {
         self.threads = n;
         self
     }
 
+    /// Set a cap on the number of threads chosen automatically.
+    ///
+    /// This only applies when the number of threads is chosen automatically,
+    /// i.e., when `threads` is set to `0` (the default). Directory traversal
+    /// tends to saturate storage well before it saturates every CPU on
+    /// machines with many cores, so the automatic choice is capped at `12`
+    /// by default. Callers on very fast storage (e.g., NVMe arrays) can
+    /// raise the cap, or lift it entirely by passing `None`.
+    pub fn threads_cap(&mut self, cap: Option<usize>) -> &mut WalkBuilder {
+        self.threads_cap = cap;
+        self
+    }
+
     /// Add a global ignore file to the matcher.
     ///
     /// This has lower precedence than all other sources of ignore rules.
@@ -684,26 +1396,96 @@ impl WalkBuilder {
         self
     }
 
+    /// Add a custom ignore file name along with options controlling how
+    /// files with that name participate in matching.
+    ///
+    /// This is like [`WalkBuilder::add_custom_ignore_filename`], except the
+    /// semantics of the ignore file can be tweaked via [`CustomIgnoreOpts`].
+    /// This is useful for emulating the dialects of custom ignore files used
+    /// by other tools. The default options correspond to standard gitignore
+    /// semantics.
+    pub fn add_custom_ignore_filename_with_opts<S: AsRef<OsStr>>(
+        &mut self,
+        file_name: S,
+        opts: CustomIgnoreOpts,
+    ) -> &mut WalkBuilder {
+        self.ig_builder.add_custom_ignore_filename_with_opts(file_name, opts);
+        self
+    }
+
     /// Add an override matcher.
     ///
     /// By default, no override matcher is used.
     ///
+    /// The matcher given is used as-is and is never rebuilt. Since an
+    /// [`Override`] is cheap to clone, a single matcher can be shared across
+    /// many walkers (and threads) without recompiling its globs.
+    ///
     /// This overrides any previous setting.
     pub fn overrides(&mut self, overrides: Override) -> &mut WalkBuilder {
         self.ig_builder.overrides(overrides);
         self
     }
 
+    /// Restrict the walk to the files listed in the given allowlist.
+    ///
+    /// When an allowlist is set, only files that are members of it are
+    /// yielded, and directories that cannot contain a member are not
+    /// descended into at all, which keeps the walk cheap even when the
+    /// allowlist is a very large manifest over a much larger tree. Paths in
+    /// the allowlist are interpreted as relative to each root of the walk.
+    ///
+    /// All other rules still apply on top: an allowlisted file that matches
+    /// an ignore rule (or is rejected by a filter predicate, file type
+    /// matcher, etc.) is still skipped. Directories are not yielded at all,
+    /// since an allowlist enumerates files. The walk's roots are exempt, as
+    /// they are with every other matcher.
+    ///
+    /// Unlike an override matcher, membership is decided by exact path
+    /// comparison rather than glob matching. See [`Allowlist`].
+    ///
+    /// By default, no allowlist is used.
+    pub fn allowlist(&mut self, allowlist: Allowlist) -> &mut WalkBuilder {
+        self.allowlist = Some(allowlist);
+        self
+    }
+
     /// Add a file type matcher.
     ///
     /// By default, no file type matcher is used.
     ///
+    /// The matcher given is used as-is and is never rebuilt. Since a
+    /// [`Types`] is cheap to clone, a single matcher can be shared across
+    /// many walkers (and threads) without recompiling its globs.
+    ///
     /// This overrides any previous setting.
     pub fn types(&mut self, types: Types) -> &mut WalkBuilder {
         self.ig_builder.types(types);
         self
     }
 
+    /// Use a pre-built global gitignore matcher.
+    ///
+    /// By default, when reading the global gitignore file is enabled (see
+    /// [`git_global`](WalkBuilder::git_global)), each call to `build` or
+    /// `build_parallel` reads and parses the file anew. Supplying a matcher
+    /// here, e.g., one from [`Gitignore::global`](crate::gitignore::Gitignore::global),
+    /// skips that work. Since a [`Gitignore`](crate::gitignore::Gitignore) is
+    /// cheap to clone, a single matcher can be shared across many walkers
+    /// (and threads).
+    ///
+    /// This has no effect if reading the global gitignore file has been
+    /// disabled via `git_global`.
+    ///
+    /// This overrides any previous setting.
+    pub fn global_gitignore(
+        &mut self,
+        gitignore: crate::gitignore::Gitignore,
+    ) -> &mut WalkBuilder {
+        self.ig_builder.global_gitignore(gitignore);
+        self
+    }
+
     /// Enables all the standard ignore filters.
     ///
     /// This toggles, as a group, all the filters that are enabled by default:
@@ -735,6 +1517,39 @@ impl WalkBuilder {
         self
     }
 
+    /// Set the strategy used to decide whether a file is hidden.
+    ///
+    /// This only has an effect when hidden file filtering is enabled via
+    /// [`WalkBuilder::hidden`].
+    ///
+    /// This defaults to [`HiddenMode::Both`].
+    pub fn hidden_mode(&mut self, mode: HiddenMode) -> &mut WalkBuilder {
+        self.ig_builder.hidden_mode(mode);
+        self
+    }
+
+    /// Set a callback that is invoked for every ignore file read during the
+    /// walk.
+    ///
+    /// The callback receives an [`IgnoreFileEvent`] describing the path of
+    /// the ignore file, its kind, how long it took to read and parse, the
+    /// number of globs it produced and whether it came from a cache built
+    /// via [`WalkBuilder::prefetch_gitignores`]. This is intended for
+    /// instrumentation, e.g., finding walks that spend most of their time
+    /// parsing ignore files.
+    ///
+    /// The callback is used by both the single threaded and parallel
+    /// walkers. In the parallel case, it may be invoked from multiple
+    /// threads simultaneously. When no callback is set (the default),
+    /// reading ignore files incurs no instrumentation overhead.
+    pub fn on_ignore_file<F>(&mut self, callback: F) -> &mut WalkBuilder
+    where
+        F: Fn(IgnoreFileEvent) + Send + Sync + 'static,
+    {
+        self.ig_builder.on_ignore_file(Arc::new(callback));
+        self
+    }
+
     /// Enables reading ignore files from parent directories.
     ///
     /// If this is enabled, then .gitignore files in parent directories of each
@@ -793,6 +1608,16 @@ impl WalkBuilder {
         self
     }
 
+    /// Set how git submodules encountered during traversal are handled.
+    ///
+    /// By default, submodules are descended into like any other directory
+    /// and the parent repository's ignore rules are applied to them, via
+    /// [`SubmoduleMode::Descend`].
+    pub fn git_submodules(&mut self, mode: SubmoduleMode) -> &mut WalkBuilder {
+        self.ig_builder.git_submodules(mode);
+        self
+    }
+
     /// Whether a git repository is required to apply git-related ignore
     /// rules (global rules, .gitignore and local exclude rules).
     ///
@@ -803,14 +1628,72 @@ impl WalkBuilder {
         self
     }
 
-    /// Process ignore files case insensitively
+    /// Set whether the globs in ignore files are matched case insensitively
+    /// or not.
+    ///
+    /// The default is [`IgnoreCaseMode::Auto`], which follows git's
+    /// `core.ignoreCase` config setting on a per repository basis. Use
+    /// [`IgnoreCaseMode::Always`] or [`IgnoreCaseMode::Never`] to override
+    /// the config.
+    pub fn ignore_case_insensitive(
+        &mut self,
+        mode: IgnoreCaseMode,
+    ) -> &mut WalkBuilder {
+        self.ig_builder.ignore_case_insensitive(mode);
+        self
+    }
+
+    /// Scan for ignore files and pre-build their matchers ahead of the walk.
+    ///
+    /// When enabled, building the walker performs a quick names-only scan of
+    /// the directories being walked and parses and compiles every `.ignore`
+    /// and `.gitignore` file found on multiple threads. The walk then uses
+    /// the pre-built matchers instead of parsing ignore files lazily as
+    /// directories are first visited. The results of a walk are unaffected;
+    /// only the timing of the parsing changes.
+    ///
+    /// This can reduce the cold-start latency of walking deep trees that
+    /// contain many ignore files, at the cost of scanning directories that
+    /// the walk itself may end up ignoring.
+    ///
+    /// This is disabled by default.
+    pub fn prefetch_gitignores(&mut self, yes: bool) -> &mut WalkBuilder {
+        self.prefetch_gitignores = yes;
+        self
+    }
+
+    /// Capture a [`WalkSnapshot`] of the directories visited by subsequent
+    /// walks built from this builder.
+    ///
+    /// When enabled, every directory that a walk descends into is recorded
+    /// along with its modification time, size and a hash of its ignore file
+    /// contents. The accumulated snapshot is retrieved with
+    /// [`WalkBuilder::take_snapshot`] and can later be checked against the
+    /// file system with a [`WalkVerifier`], so a cached file list can be
+    /// re-validated without re-walking unchanged subtrees.
+    ///
+    /// Capturing stats each visited directory once more and reads its
+    /// ignore files, so it is not free. Both the sequential and parallel
+    /// walkers capture state.
     ///
     /// This is disabled by default.
-    pub fn ignore_case_insensitive(&mut self, yes: bool) -> &mut WalkBuilder {
-        self.ig_builder.ignore_case_insensitive(yes);
+    pub fn capture_state(&mut self, yes: bool) -> &mut WalkBuilder {
+        self.capture_state =
+            if yes { Some(Arc::new(Mutex::new(vec![]))) } else { None };
         self
     }
 
+    /// Returns the snapshot captured by walks run since state capturing was
+    /// enabled (or since the last call to this method), leaving an empty
+    /// snapshot behind.
+    ///
+    /// Returns `None` if [`WalkBuilder::capture_state`] is not enabled.
+    pub fn take_snapshot(&self) -> Option<WalkSnapshot> {
+        let capture = self.capture_state.as_ref()?;
+        let dirs = std::mem::take(&mut *capture.lock().unwrap());
+        Some(WalkSnapshot { dirs })
+    }
+
     /// Set a function for sorting directory entries by their path.
     ///
     /// If a compare function is set, the resulting iterator will return all
@@ -865,10 +1748,28 @@ impl WalkBuilder {
         self
     }
 
-    /// Do not yield directory entries that are believed to correspond to
-    /// stdout.
+    /// Whether to convert absolute root paths to the Windows
+    /// extended-length form (with a `\\?\` prefix) for traversal.
     ///
-    /// This is useful when a command is invoked via shell redirection to a
+    /// Deeply nested directory trees (e.g., `node_modules`) routinely
+    /// exceed Windows' `MAX_PATH` limit, which makes filesystem calls on
+    /// their paths fail unless the extended-length form is used. When this
+    /// is enabled, absolute root paths are converted before the traversal
+    /// begins, while the paths reported by [`DirEntry::path`] remain in
+    /// their normal form. Glob matching treats the two forms as equivalent,
+    /// so ignore rules apply the same either way.
+    ///
+    /// This is enabled by default on Windows and has no effect on other
+    /// platforms.
+    pub fn long_paths(&mut self, yes: bool) -> &mut WalkBuilder {
+        self.long_paths = yes;
+        self
+    }
+
+    /// Do not yield directory entries that are believed to correspond to
+    /// stdout.
+    ///
+    /// This is useful when a command is invoked via shell redirection to a
     /// file that is also being read. For example, `grep -r foo ./ > results`
     /// might end up trying to search `results` even though it is also writing
     /// to it, which could cause an unbounded feedback loop. Setting this
@@ -901,6 +1802,325 @@ impl WalkBuilder {
         self.filter = Some(Filter(Arc::new(filter)));
         self
     }
+
+    /// Set a policy that is consulted whenever a directory is about to be
+    /// skipped because it matched an ignore rule.
+    ///
+    /// The policy receives the directory's path and the ignore match that
+    /// caused the prune, and can confirm the prune, force a descent into the
+    /// directory (in which case its children are still matched against
+    /// ignore rules individually) or force the inclusion of the entire
+    /// subtree. This makes it possible to decouple descending into a
+    /// directory from filtering the files inside of it.
+    ///
+    /// The match given is the gitignore glob responsible for the prune, when
+    /// there is one. When a directory is pruned for a reason that has no
+    /// corresponding gitignore glob (e.g., because it is hidden), then the
+    /// match given is `Match::None`.
+    ///
+    /// Both the sequential and parallel walkers honor the policy.
+    pub fn prune_policy<P>(&mut self, policy: P) -> &mut WalkBuilder
+    where
+        P: Fn(&Path, &Match<&Glob>) -> PruneDecision + Send + Sync + 'static,
+    {
+        self.prune_policy = Some(PrunePolicy(Arc::new(policy)));
+        self
+    }
+
+    /// Set the policy for handling errors that occur while reading a
+    /// directory's contents.
+    ///
+    /// The default, [`DirErrorPolicy::Warn`], reports such errors as
+    /// non-fatal `Err` entries and continues the walk, silently dropping the
+    /// unreadable subtree. [`DirErrorPolicy::Fatal`] terminates the walk
+    /// instead, and [`DirErrorPolicy::Retry`] retries the read before giving
+    /// up, which is useful on flaky network mounts.
+    ///
+    /// Both the sequential and parallel walkers honor the policy, except
+    /// that only the parallel walker supports retrying.
+    pub fn dir_error_policy(
+        &mut self,
+        policy: DirErrorPolicy,
+    ) -> &mut WalkBuilder {
+        self.dir_error_policy = policy;
+        self
+    }
+
+    /// Set the order in which directories are yielded relative to their
+    /// contents. See [`TraversalOrder`] for the semantics of each order.
+    ///
+    /// In the parallel walker, post-order means a directory is given to the
+    /// visitor only once every entry beneath it has been. Entries in
+    /// unrelated directories are still visited concurrently in no
+    /// particular order.
+    ///
+    /// The default is [`TraversalOrder::PreOrder`].
+    pub fn traversal_order(
+        &mut self,
+        order: TraversalOrder,
+    ) -> &mut WalkBuilder {
+        self.traversal = order;
+        self
+    }
+}
+
+/// A snapshot of the directories visited by a walk.
+///
+/// A snapshot is captured by enabling [`WalkBuilder::capture_state`] before
+/// walking and retrieved afterwards with [`WalkBuilder::take_snapshot`]. It
+/// records, for every directory that the walk descended into, the directory's
+/// modification time and size along with a hash of the contents of its ignore
+/// files. A [`WalkVerifier`] can later check a snapshot against the file
+/// system without re-walking anything but the directories themselves.
+///
+/// When the `serde` feature is enabled, snapshots can be serialized and
+/// deserialized, so they can be persisted alongside a cached file list.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WalkSnapshot {
+    dirs: Vec<DirSnapshot>,
+}
+
+impl WalkSnapshot {
+    /// Returns the number of directories recorded in this snapshot.
+    pub fn len(&self) -> usize {
+        self.dirs.len()
+    }
+
+    /// Returns true if this snapshot records no directories.
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty()
+    }
+
+    /// Returns the paths of the directories recorded in this snapshot, in
+    /// the order they were visited.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.dirs.iter().map(|dir| &*dir.path)
+    }
+}
+
+/// The recorded state of a single directory within a [`WalkSnapshot`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct DirSnapshot {
+    /// The path of the directory.
+    path: PathBuf,
+    /// The last modification time of the directory, if available. On all
+    /// mainstream file systems, this changes whenever an entry is added to
+    /// or removed from the directory.
+    mtime: Option<SystemTime>,
+    /// The size of the directory, as reported by its metadata.
+    size: u64,
+    /// A hash of the contents of the ignore files in this directory.
+    ignore_hash: u64,
+}
+
+/// The names of the ignore files whose contents are hashed into a
+/// directory's snapshot.
+const SNAPSHOT_IGNORE_NAMES: &[&str] = &[".ignore", ".gitignore"];
+
+/// Records the current state of the directory at the path given.
+fn snapshot_dir(path: &Path) -> DirSnapshot {
+    let (mtime, size) = match path.metadata() {
+        Ok(md) => (md.modified().ok(), md.len()),
+        Err(_) => (None, 0),
+    };
+    DirSnapshot {
+        path: path.to_path_buf(),
+        mtime,
+        size,
+        ignore_hash: snapshot_ignore_hash(path),
+    }
+}
+
+/// Hashes the contents of the ignore files in the directory given.
+///
+/// Missing files contribute nothing to the hash, so creating or deleting an
+/// ignore file changes the hash just like editing one does. This uses
+/// FNV-1a, which is plenty for change detection and keeps us dependency
+/// free.
+fn snapshot_ignore_hash(dir: &Path) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let mut hash = FNV_OFFSET;
+    let mut write = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+    for name in SNAPSHOT_IGNORE_NAMES {
+        if let Ok(contents) = std::fs::read(dir.join(name)) {
+            write(name.as_bytes());
+            write(&contents);
+        }
+    }
+    hash
+}
+
+/// A verifier that checks a [`WalkSnapshot`] against the file system.
+///
+/// This makes it cheap to re-validate the result of a prior walk: only the
+/// directories recorded in the snapshot are inspected, not the files within
+/// them. Directory modification times change whenever entries are added or
+/// removed, so a cached file list remains valid for any directory reported
+/// as unchanged.
+#[derive(Clone, Debug, Default)]
+pub struct WalkVerifier(());
+
+impl WalkVerifier {
+    /// Creates a new verifier.
+    pub fn new() -> WalkVerifier {
+        WalkVerifier(())
+    }
+
+    /// Returns the directories from the snapshot that have changed since it
+    /// was captured, in the order they were recorded.
+    ///
+    /// A directory is reported as changed when it no longer exists, when its
+    /// modification time or size differs, or when the contents of its ignore
+    /// files differ. The caller can then re-walk just the reported subtrees.
+    pub fn verify(&self, snapshot: &WalkSnapshot) -> Vec<PathBuf> {
+        let mut changed = vec![];
+        for dir in snapshot.dirs.iter() {
+            if snapshot_dir(&dir.path) != *dir {
+                changed.push(dir.path.clone());
+            }
+        }
+        changed
+    }
+}
+
+/// Tracks the cumulative size of yielded files against a configured budget.
+///
+/// The accumulator is shared by all workers in a parallel traversal. Since
+/// the budget only needs to be enforced to within one file, a relaxed
+/// atomic suffices.
+#[derive(Clone, Debug)]
+struct ByteBudget {
+    budget: u64,
+    seen: Arc<AtomicU64>,
+    notified: Arc<AtomicBool>,
+}
+
+impl ByteBudget {
+    fn new(budget: u64) -> ByteBudget {
+        ByteBudget {
+            budget,
+            seen: Arc::new(AtomicU64::new(0)),
+            notified: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Record the given yielded entry and return an error if the budget has
+    /// now been exceeded.
+    ///
+    /// Only regular files count against the budget, and files whose size is
+    /// unknown count as zero. The error is returned at most once, even when
+    /// multiple threads race past the budget.
+    fn record(&self, dent: &DirEntry) -> Option<Error> {
+        let is_file = dent.file_type().map_or(false, |ft| ft.is_file());
+        if !is_file {
+            return None;
+        }
+        let len = dent.metadata().map_or(0, |md| md.len());
+        let seen = self.seen.fetch_add(len, AtomicOrdering::Relaxed) + len;
+        if seen > self.budget
+            && !self.notified.swap(true, AtomicOrdering::Relaxed)
+        {
+            Some(Error::ByteBudgetExceeded { budget: self.budget, seen })
+        } else {
+            None
+        }
+    }
+}
+
+/// A shared budget on the number of concurrently open file handles.
+///
+/// A budget is created by [`WalkBuilder::fd_budget`]. The parallel walker
+/// draws a permit from the budget before opening each directory. Callers can
+/// obtain a clone of the handle via [`WalkBuilder::fd_budget_handle`] and
+/// draw from the same budget for the file handles they open themselves
+/// (e.g., files opened for searching), so that the combined number of open
+/// handles stays under the budget.
+///
+/// Cloning is cheap; all clones draw from the same budget.
+#[derive(Clone, Debug)]
+pub struct FdBudget {
+    inner: Arc<FdBudgetInner>,
+}
+
+#[derive(Debug)]
+struct FdBudgetInner {
+    budget: usize,
+    state: Mutex<FdBudgetState>,
+    cond: Condvar,
+}
+
+#[derive(Debug)]
+struct FdBudgetState {
+    in_use: usize,
+    high_water: usize,
+}
+
+impl FdBudget {
+    fn new(budget: usize) -> FdBudget {
+        // A budget of zero could never make progress, so treat it as one.
+        let budget = budget.max(1);
+        FdBudget {
+            inner: Arc::new(FdBudgetInner {
+                budget,
+                state: Mutex::new(FdBudgetState { in_use: 0, high_water: 0 }),
+                cond: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Block until a permit is available and return a guard that gives the
+    /// permit back to the budget when dropped.
+    ///
+    /// A permit should be held for the duration of a single operation, such
+    /// as reading a directory or searching an open file. Callers must not
+    /// acquire a permit while already holding one, since that can deadlock
+    /// when the budget is smaller than the number of contending threads.
+    pub fn acquire(&self) -> FdPermit {
+        let mut state = self.inner.state.lock().unwrap();
+        while state.in_use >= self.inner.budget {
+            state = self.inner.cond.wait(state).unwrap();
+        }
+        state.in_use += 1;
+        state.high_water = state.high_water.max(state.in_use);
+        drop(state);
+        FdPermit { budget: self.clone() }
+    }
+
+    /// Returns the peak number of permits that have been in use at the same
+    /// time.
+    ///
+    /// This is useful for tuning a budget: a high-water mark well below the
+    /// budget means the budget was never contended, while one equal to the
+    /// budget means the walk was (at least briefly) throttled by it.
+    pub fn high_water(&self) -> usize {
+        self.inner.state.lock().unwrap().high_water
+    }
+}
+
+/// A permit drawn from an [`FdBudget`].
+///
+/// Dropping the permit gives it back to the budget.
+#[derive(Debug)]
+pub struct FdPermit {
+    budget: FdBudget,
+}
+
+impl Drop for FdPermit {
+    fn drop(&mut self) {
+        let mut state = self.budget.inner.state.lock().unwrap();
+        state.in_use -= 1;
+        drop(state);
+        self.budget.inner.cond.notify_one();
+    }
 }
 
 /// Walk is a recursive directory iterator over file paths in one or more
@@ -910,13 +2130,45 @@ impl WalkBuilder {
 /// ignore files like `.gitignore` are respected. The precise matching rules
 /// and precedence is explained in the documentation for `WalkBuilder`.
 pub struct Walk {
-    its: std::vec::IntoIter<(PathBuf, Option<WalkEventIter>)>,
+    its: std::vec::IntoIter<(usize, PathBuf, Option<WalkEventIter>)>,
     it: Option<WalkEventIter>,
     ig_root: Ignore,
     ig: Ignore,
+    min_depth: Option<usize>,
     max_filesize: Option<u64>,
+    /// The budget on the cumulative size of yielded files, if any, along
+    /// with an error pending delivery after the budget was exceeded.
+    byte_budget: Option<ByteBudget>,
+    budget_err: Option<Error>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    prune_unmodified_dirs: bool,
     skip: Option<Arc<Handle>>,
     filter: Option<Filter>,
+    allowlist: Option<Allowlist>,
+    prune_policy: Option<PrunePolicy>,
+    dir_error_policy: DirErrorPolicy,
+    traversal: TraversalOrder,
+    /// In post-order, directories whose yield is deferred until their
+    /// contents have been enumerated, innermost last. A `None` entry marks
+    /// a directory that is descended into but not yielded (e.g., because it
+    /// is below the minimum depth).
+    pending_dirs: Vec<Option<DirEntry>>,
+    /// The root of a subtree whose inclusion was forced by the prune
+    /// policy. Entries beneath it bypass ignore matching entirely.
+    forced_root: Option<PathBuf>,
+    /// The index of the root currently being walked, stamped on every entry
+    /// yielded.
+    cur_root: usize,
+    /// The path of the root currently being walked, used to compute
+    /// root-relative paths for allowlist membership tests.
+    cur_root_path: PathBuf,
+    /// Whether the roots were converted to extended-length form, in which
+    /// case every entry is re-expressed in normal form before use.
+    long_paths: bool,
+    /// Where to record the state of visited directories, if capturing is
+    /// enabled.
+    capture: Option<Arc<Mutex<Vec<DirSnapshot>>>>,
 }
 
 impl Walk {
@@ -929,7 +2181,13 @@ impl Walk {
         WalkBuilder::new(path).build()
     }
 
-    fn skip_entry(&self, ent: &DirEntry) -> Result<bool, Error> {
+    /// Returns true if and only if the given depth is shallower than the
+    /// configured minimum depth, if any.
+    fn below_min_depth(&self, depth: usize) -> bool {
+        self.min_depth.map_or(false, |min| depth < min)
+    }
+
+    fn skip_entry(&mut self, ent: &mut DirEntry) -> Result<bool, Error> {
         if ent.depth() == 0 {
             return Ok(false);
         }
@@ -942,14 +2200,62 @@ impl Walk {
         // when particular filesystem operations occurred. Users of this system
         // who ensured correct file-type filters were being used could still
         // get unnecessary file access resulting in large downloads.
-        if should_skip_entry(&self.ig, ent) {
-            return Ok(true);
+        let forced = self
+            .forced_root
+            .as_ref()
+            .map_or(false, |root| ent.path().starts_with(root));
+        if !forced && should_skip_entry(&self.ig, ent) {
+            if !ent.is_dir() || self.prune_policy.is_none() {
+                return Ok(true);
+            }
+            let policy = self.prune_policy.as_ref().unwrap();
+            match consult_prune_policy(policy, &self.ig, ent) {
+                PruneDecision::Prune => return Ok(true),
+                PruneDecision::Descend => {}
+                PruneDecision::Include => {
+                    self.forced_root = Some(ent.path().to_path_buf());
+                }
+            }
         }
         if let Some(ref stdout) = self.skip {
             if path_equals(ent, stdout)? {
                 return Ok(true);
             }
         }
+        if let Some(ref allowlist) = self.allowlist {
+            let rel = ent
+                .path()
+                .strip_prefix(&self.cur_root_path)
+                .unwrap_or_else(|_| ent.path());
+            let allowed = if ent.is_dir() {
+                allowlist.is_allowed_dir(rel)
+            } else {
+                allowlist.is_allowed_file(rel)
+            };
+            if !allowed {
+                return Ok(true);
+            }
+        }
+        if !ent.is_dir() {
+            if self.modified_after.is_some() || self.modified_before.is_some()
+            {
+                if skip_modified(
+                    self.modified_after,
+                    self.modified_before,
+                    ent.path(),
+                    &ent.metadata().ok(),
+                ) {
+                    return Ok(true);
+                }
+            }
+        } else if self.prune_unmodified_dirs {
+            if let Some(after) = self.modified_after {
+                if skip_unmodified_dir(after, ent.path(), &ent.metadata().ok())
+                {
+                    return Ok(true);
+                }
+            }
+        }
         if self.max_filesize.is_some() && !ent.is_dir() {
             return Ok(skip_filesize(
                 self.max_filesize.unwrap(),
@@ -971,19 +2277,34 @@ impl Iterator for Walk {
 
     #[inline(always)]
     fn next(&mut self) -> Option<Result<DirEntry, Error>> {
+        // A file yielded previously pushed the walk over its byte budget,
+        // so deliver the error as the final event and terminate the walk.
+        if let Some(err) = self.budget_err.take() {
+            self.it = None;
+            self.its = Vec::new().into_iter();
+            return Some(Err(err));
+        }
         loop {
             let ev = match self.it.as_mut().and_then(|it| it.next()) {
                 Some(ev) => ev,
                 None => {
                     match self.its.next() {
                         None => return None,
-                        Some((_, None)) => {
-                            return Some(Ok(DirEntry::new_stdin()));
+                        Some((i, _, None)) => {
+                            // Like any other root, stdin sits at depth 0.
+                            if self.below_min_depth(0) {
+                                continue;
+                            }
+                            let mut ent = DirEntry::new_stdin();
+                            ent.root_index = i;
+                            return Some(Ok(ent));
                         }
-                        Some((path, Some(it))) => {
+                        Some((i, path, Some(it))) => {
+                            self.cur_root = i;
                             self.it = Some(it);
                             if path.is_dir() {
-                                let (ig, err) = self.ig_root.add_parents(path);
+                                let (ig, err) =
+                                    self.ig_root.add_parents(&path);
                                 self.ig = ig;
                                 if let Some(err) = err {
                                     return Some(Err(err));
@@ -991,6 +2312,7 @@ impl Iterator for Walk {
                             } else {
                                 self.ig = self.ig_root.clone();
                             }
+                            self.cur_root_path = path;
                         }
                     }
                     continue;
@@ -998,14 +2320,36 @@ impl Iterator for Walk {
             };
             match ev {
                 Err(err) => {
+                    // An error here means a directory (or a root) could not
+                    // be read. Under the fatal policy, it is the final event
+                    // of the walk, using the same termination idiom as a
+                    // byte budget error.
+                    if let DirErrorPolicy::Fatal = self.dir_error_policy {
+                        self.it = None;
+                        self.its = Vec::new().into_iter();
+                    }
                     return Some(Err(Error::from_walkdir(err)));
                 }
                 Ok(WalkEvent::Exit) => {
                     self.ig = self.ig.parent().unwrap();
+                    // In post-order, a directory is yielded once all of its
+                    // contents have been enumerated.
+                    if let TraversalOrder::PostOrder = self.traversal {
+                        if let Some(Some(ent)) = self.pending_dirs.pop() {
+                            return Some(Ok(ent));
+                        }
+                    }
                 }
                 Ok(WalkEvent::Dir(ent)) => {
+                    let post =
+                        matches!(self.traversal, TraversalOrder::PostOrder);
                     let mut ent = DirEntry::new_walkdir(ent, None);
-                    let should_skip = match self.skip_entry(&ent) {
+                    ent.root_index = self.cur_root;
+                    ent.stamp_symlink_target();
+                    if self.long_paths {
+                        ent.stamp_normal_path();
+                    }
+                    let should_skip = match self.skip_entry(&mut ent) {
                         Err(err) => return Some(Err(err)),
                         Ok(should_skip) => should_skip,
                     };
@@ -1016,22 +2360,61 @@ impl Iterator for Walk {
                         // We don't care if it errors though.
                         let (igtmp, _) = self.ig.add_child(ent.path());
                         self.ig = igtmp;
+                        // Skipped directories are not yielded in either
+                        // order, but the Exit event still pops an entry.
+                        if post {
+                            self.pending_dirs.push(None);
+                        }
                         continue;
                     }
                     let (igtmp, err) = self.ig.add_child(ent.path());
                     self.ig = igtmp;
                     ent.err = err;
+                    if let Some(ref capture) = self.capture {
+                        capture.lock().unwrap().push(snapshot_dir(ent.path()));
+                    }
+                    // A directory shallower than the minimum depth is still
+                    // descended into (which is why we pushed its ignore
+                    // rules above), but the directory itself isn't yielded.
+                    // The same goes for every directory when an allowlist
+                    // is in use, since an allowlist enumerates files.
+                    if self.below_min_depth(ent.depth())
+                        || self.allowlist.is_some()
+                    {
+                        if post {
+                            self.pending_dirs.push(None);
+                        }
+                        continue;
+                    }
+                    if post {
+                        self.pending_dirs.push(Some(ent));
+                        continue;
+                    }
                     return Some(Ok(ent));
                 }
                 Ok(WalkEvent::File(ent)) => {
-                    let ent = DirEntry::new_walkdir(ent, None);
-                    let should_skip = match self.skip_entry(&ent) {
+                    let mut ent = DirEntry::new_walkdir(ent, None);
+                    ent.root_index = self.cur_root;
+                    ent.stamp_symlink_target();
+                    if self.long_paths {
+                        ent.stamp_normal_path();
+                    }
+                    // A file shallower than the minimum depth can never be
+                    // yielded, so skip it before consulting ignore rules or
+                    // any filter predicate.
+                    if self.below_min_depth(ent.depth()) {
+                        continue;
+                    }
+                    let should_skip = match self.skip_entry(&mut ent) {
                         Err(err) => return Some(Err(err)),
                         Ok(should_skip) => should_skip,
                     };
                     if should_skip {
                         continue;
                     }
+                    if let Some(ref budget) = self.byte_budget {
+                        self.budget_err = budget.record(&ent);
+                    }
                     return Some(Ok(ent));
                 }
             }
@@ -1131,6 +2514,21 @@ impl WalkState {
 pub trait ParallelVisitorBuilder<'s> {
     /// Create per-thread `ParallelVisitor`s for `WalkParallel`.
     fn build(&mut self) -> Box<dyn ParallelVisitor + 's>;
+
+    /// Create per-thread `ParallelVisitor`s for `WalkParallel`, where
+    /// creation may fail.
+    ///
+    /// This is useful for visitors that need to open per-thread resources
+    /// (say, a database connection) whose creation is fallible. When this
+    /// fails for a worker, the walk proceeds with one fewer worker. When it
+    /// fails for every worker, [`WalkParallel::try_visit`] (or
+    /// [`WalkParallel::try_run`]) returns the error.
+    ///
+    /// By default, this delegates to
+    /// [`build`](ParallelVisitorBuilder::build) and never fails.
+    fn try_build(&mut self) -> Result<Box<dyn ParallelVisitor + 's>, Error> {
+        Ok(self.build())
+    }
 }
 
 impl<'a, 's, P: ParallelVisitorBuilder<'s>> ParallelVisitorBuilder<'s>
@@ -1139,6 +2537,10 @@ impl<'a, 's, P: ParallelVisitorBuilder<'s>> ParallelVisitorBuilder<'s>
     fn build(&mut self) -> Box<dyn ParallelVisitor + 's> {
         (**self).build()
     }
+
+    fn try_build(&mut self) -> Result<Box<dyn ParallelVisitor + 's>, Error> {
+        (**self).try_build()
+    }
 }
 
 /// Receives files and directories for the current thread.
@@ -1190,12 +2592,25 @@ pub struct WalkParallel {
     paths: std::vec::IntoIter<PathBuf>,
     ig_root: Ignore,
     max_filesize: Option<u64>,
+    byte_budget: Option<ByteBudget>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    prune_unmodified_dirs: bool,
     max_depth: Option<usize>,
+    min_depth: Option<usize>,
     follow_links: bool,
     same_file_system: bool,
+    long_paths: bool,
     threads: usize,
+    threads_cap: Option<usize>,
+    fd_budget: Option<FdBudget>,
     skip: Option<Arc<Handle>>,
     filter: Option<Filter>,
+    allowlist: Option<Allowlist>,
+    prune_policy: Option<PrunePolicy>,
+    dir_error_policy: DirErrorPolicy,
+    traversal: TraversalOrder,
+    capture: Option<Arc<Mutex<Vec<DirSnapshot>>>>,
 }
 
 impl WalkParallel {
@@ -1209,6 +2624,19 @@ impl WalkParallel {
         self.visit(&mut FnBuilder { builder: mkf })
     }
 
+    /// Like [`run`](WalkParallel::run), but returns an error when no worker
+    /// could be started.
+    ///
+    /// This can only fail when used with a [`ParallelVisitorBuilder`] whose
+    /// `try_build` implementation fails for every worker, so it is primarily
+    /// useful in combination with [`try_visit`](WalkParallel::try_visit).
+    pub fn try_run<'s, F>(self, mkf: F) -> Result<(), Error>
+    where
+        F: FnMut() -> FnVisitor<'s>,
+    {
+        self.try_visit(&mut FnBuilder { builder: mkf })
+    }
+
     /// Execute the parallel recursive directory iterator using a custom
     /// visitor.
     ///
@@ -1226,20 +2654,71 @@ impl WalkParallel {
     /// visitor runs on only one thread, this build-up can be done without
     /// synchronization. Then, once traversal is complete, all of the results
     /// can be merged together into a single data structure.
-    pub fn visit(mut self, builder: &mut dyn ParallelVisitorBuilder<'_>) {
+    pub fn visit(self, builder: &mut dyn ParallelVisitorBuilder<'_>) {
+        // An error can only occur when the builder overrides `try_build`
+        // with a fallible implementation, in which case callers should use
+        // `try_visit` instead.
+        let _ = self.try_visit(builder);
+    }
+
+    /// Like [`visit`](WalkParallel::visit), but returns an error when no
+    /// worker could be started.
+    ///
+    /// When [`ParallelVisitorBuilder::try_build`] fails for a worker, the
+    /// traversal proceeds with one fewer worker and the failure is logged.
+    /// Only when every worker fails to build is an error returned, since in
+    /// that case no traversal can happen at all. The error returned is the
+    /// one reported for the last worker.
+    pub fn try_visit(
+        mut self,
+        builder: &mut dyn ParallelVisitorBuilder<'_>,
+    ) -> Result<(), Error> {
         let threads = self.threads();
+        // Build the visitors up front so that workers that fail to build
+        // merely reduce the amount of parallelism instead of, say,
+        // panicking. Only when every worker fails to build do we give up.
+        let mut visitors = Vec::with_capacity(threads);
+        let mut build_err = None;
+        for _ in 0..threads {
+            match builder.try_build() {
+                Ok(visitor) => visitors.push(visitor),
+                Err(err) => {
+                    log::warn!(
+                        "failed to build visitor for worker thread, \
+                         continuing with fewer threads: {}",
+                        err,
+                    );
+                    build_err = Some(err);
+                }
+            }
+        }
+        let Some(first_visitor) = visitors.first_mut() else {
+            return Err(build_err.expect("at least one build attempt"));
+        };
         let mut stack = vec![];
+        let roots: Arc<Vec<PathBuf>>;
         {
-            let mut visitor = builder.build();
+            let visitor = first_visitor;
             let mut paths = Vec::new().into_iter();
             std::mem::swap(&mut paths, &mut self.paths);
+            let paths = paths.collect::<Vec<PathBuf>>();
+            roots = Arc::new(paths.clone());
+            let paths = paths.into_iter();
             // Send the initial set of root paths to the pool of workers. Note
             // that we only send directories. For files, we send to them the
             // callback directly.
-            for path in paths {
-                let (dent, root_device) = if path == Path::new("-") {
+            for (root_index, path) in paths.enumerate() {
+                let (mut dent, root_device) = if path == Path::new("-") {
                     (DirEntry::new_stdin(), None)
                 } else {
+                    // As in the single-threaded walker, the extended-length
+                    // form is only used for filesystem calls; entries are
+                    // re-expressed in normal form before use.
+                    let path = if self.long_paths {
+                        crate::pathutil::to_long_path(&path)
+                    } else {
+                        path
+                    };
                     let root_device = if !self.same_file_system {
                         None
                     } else {
@@ -1248,7 +2727,7 @@ impl WalkParallel {
                             Err(err) => {
                                 let err = Error::Io(err).with_path(path);
                                 if visitor.visit(Err(err)).is_quit() {
-                                    return;
+                                    return Ok(());
                                 }
                                 continue;
                             }
@@ -1260,40 +2739,61 @@ impl WalkParallel {
                         }
                         Err(err) => {
                             if visitor.visit(Err(err)).is_quit() {
-                                return;
+                                return Ok(());
                             }
                             continue;
                         }
                     }
                 };
+                dent.root_index = root_index;
+                dent.stamp_symlink_target();
+                if self.long_paths {
+                    dent.stamp_normal_path();
+                }
                 stack.push(Message::Work(Work {
                     dent,
                     ignore: self.ig_root.clone(),
                     root_device,
+                    forced: false,
+                    parent: None,
                 }));
             }
             // ... but there's no need to start workers if we don't need them.
             if stack.is_empty() {
-                return;
+                return Ok(());
             }
         }
         // Create the workers and then wait for them to finish.
+        let threads = visitors.len();
         let quit_now = Arc::new(AtomicBool::new(false));
         let active_workers = Arc::new(AtomicUsize::new(threads));
         let stacks = Stack::new_for_each_thread(threads, stack);
         std::thread::scope(|s| {
             let handles: Vec<_> = stacks
                 .into_iter()
-                .map(|stack| Worker {
-                    visitor: builder.build(),
+                .zip(visitors)
+                .map(|(stack, visitor)| Worker {
+                    visitor,
                     stack,
                     quit_now: quit_now.clone(),
                     active_workers: active_workers.clone(),
                     max_depth: self.max_depth,
+                    min_depth: self.min_depth,
                     max_filesize: self.max_filesize,
+                    byte_budget: self.byte_budget.clone(),
+                    modified_after: self.modified_after,
+                    modified_before: self.modified_before,
+                    prune_unmodified_dirs: self.prune_unmodified_dirs,
                     follow_links: self.follow_links,
+                    fd_budget: self.fd_budget.clone(),
                     skip: self.skip.clone(),
                     filter: self.filter.clone(),
+                    allowlist: self.allowlist.clone(),
+                    roots: roots.clone(),
+                    prune_policy: self.prune_policy.clone(),
+                    dir_error_policy: self.dir_error_policy,
+                    traversal: self.traversal,
+                    capture: self.capture.clone(),
                 })
                 .map(|worker| s.spawn(|| worker.run()))
                 .collect();
@@ -1301,14 +2801,32 @@ impl WalkParallel {
                 handle.join().unwrap();
             }
         });
+        Ok(())
     }
 
-    fn threads(&self) -> usize {
-        if self.threads == 0 {
-            2
-        } else {
-            self.threads
+    /// Returns the number of threads that this traversal will actually use.
+    ///
+    /// When an explicit non-zero number of threads was set via
+    /// `WalkBuilder::threads`, then that number is returned unchanged.
+    /// Otherwise, this returns the heuristic choice: the available
+    /// parallelism reported by the system, limited by the configured cap
+    /// (see `WalkBuilder::threads_cap`). This is useful for applications
+    /// that want to log the resolved thread count, or size other resources
+    /// to match it.
+    pub fn effective_threads(&self) -> usize {
+        if self.threads != 0 {
+            return self.threads;
         }
+        let available =
+            std::thread::available_parallelism().map_or(1, |n| n.get());
+        match self.threads_cap {
+            None => available,
+            Some(cap) => available.min(cap),
+        }
+    }
+
+    fn threads(&self) -> usize {
+        self.effective_threads()
     }
 }
 
@@ -1334,6 +2852,33 @@ struct Work {
     /// The root device number. When present, only files with the same device
     /// number should be considered.
     root_device: Option<u64>,
+    /// Whether this directory's inclusion was forced by the prune policy.
+    /// Children of forced directories bypass ignore matching entirely.
+    forced: bool,
+    /// In post-order traversal, the pending record of the directory this
+    /// work item is a child of, if any. Completing this work item
+    /// decrements the record's counter, and the directory is yielded once
+    /// its counter reaches zero.
+    parent: Option<Arc<PendingDir>>,
+}
+
+/// A directory whose yield is deferred until all of its children have been
+/// processed. This is how the parallel walker implements post-order
+/// traversal: since children are processed by arbitrary workers in
+/// arbitrary order, the last child to complete is the one that yields the
+/// directory.
+struct PendingDir {
+    /// The directory entry to yield, or `None` when the directory is
+    /// descended into but not yielded (e.g., because it is below the
+    /// minimum depth).
+    dent: Mutex<Option<DirEntry>>,
+    /// The number of outstanding completions before this directory can be
+    /// yielded: one for each child work item sent, plus one held by the
+    /// worker enumerating the directory itself.
+    remaining: AtomicUsize,
+    /// The pending record of this directory's own parent, if any, so that
+    /// completion propagates upward.
+    parent: Option<Arc<PendingDir>>,
 }
 
 impl Work {
@@ -1369,14 +2914,31 @@ impl Work {
     /// an error is returned. If there was a problem reading the ignore
     /// rules for this directory, then the error is attached to this
     /// work item's directory entry.
-    fn read_dir(&mut self) -> Result<fs::ReadDir, Error> {
-        let readdir = match fs::read_dir(self.dent.path()) {
-            Ok(readdir) => readdir,
-            Err(err) => {
-                let err = Error::from(err)
-                    .with_path(self.dent.path())
-                    .with_depth(self.dent.depth());
-                return Err(err);
+    fn read_dir(
+        &mut self,
+        policy: DirErrorPolicy,
+    ) -> Result<fs::ReadDir, Error> {
+        let mut attempts_left = match policy {
+            DirErrorPolicy::Retry { attempts, .. } => attempts,
+            _ => 0,
+        };
+        let readdir = loop {
+            match fs::read_dir(self.dent.path()) {
+                Ok(readdir) => break readdir,
+                Err(err) => {
+                    if attempts_left == 0 {
+                        let err = Error::from(err)
+                            .with_path(self.dent.path())
+                            .with_depth(self.dent.depth());
+                        return Err(err);
+                    }
+                }
+            }
+            attempts_left -= 1;
+            // The sleep happens on the worker that hit the error; other
+            // workers keep pulling work off the shared stacks meanwhile.
+            if let DirErrorPolicy::Retry { backoff, .. } = policy {
+                std::thread::sleep(backoff);
             }
         };
         let (ig, err) = self.ignore.add_child(self.dent.path());
@@ -1476,18 +3038,54 @@ struct Worker<'s> {
     /// The maximum depth of directories to descend. A value of `0` means no
     /// descension at all.
     max_depth: Option<usize>,
+    /// The minimum depth of entries to yield. Entries shallower than this
+    /// are not given to the visitor, but directories are still descended
+    /// into.
+    min_depth: Option<usize>,
     /// The maximum size a searched file can be (in bytes). If a file exceeds
     /// this size it will be skipped.
     max_filesize: Option<u64>,
+    /// The budget on the cumulative size of yielded files, if any. The
+    /// accumulator inside is shared by all workers.
+    byte_budget: Option<ByteBudget>,
+    /// Only yield regular files whose modification time is at or after this
+    /// time.
+    modified_after: Option<SystemTime>,
+    /// Only yield regular files whose modification time is at or before
+    /// this time.
+    modified_before: Option<SystemTime>,
+    /// Whether to skip descending into directories whose modification time
+    /// is older than `modified_after`.
+    prune_unmodified_dirs: bool,
     /// Whether to follow symbolic links or not. When this is enabled, loop
     /// detection is performed.
     follow_links: bool,
+    /// The budget on concurrently open file handles, if any. A permit is
+    /// drawn from it around each directory open.
+    fd_budget: Option<FdBudget>,
     /// A file handle to skip, currently is either `None` or stdout, if it's
     /// a file and it has been requested to skip files identical to stdout.
     skip: Option<Arc<Handle>>,
     /// A predicate applied to dir entries. If true, the entry and all
     /// children will be skipped.
     filter: Option<Filter>,
+    /// An allowlist of files the walk is restricted to, if any.
+    allowlist: Option<Allowlist>,
+    /// The root paths of the walk, indexed by an entry's root index. Used
+    /// to compute root-relative paths for allowlist membership tests.
+    roots: Arc<Vec<PathBuf>>,
+    /// A policy consulted when a directory is about to be pruned because it
+    /// matched an ignore rule.
+    prune_policy: Option<PrunePolicy>,
+    /// The policy for handling errors that occur while reading a directory's
+    /// contents.
+    dir_error_policy: DirErrorPolicy,
+    /// The order in which directories are yielded relative to their
+    /// contents.
+    traversal: TraversalOrder,
+    /// Where to record the state of visited directories, if capturing is
+    /// enabled.
+    capture: Option<Arc<Mutex<Vec<DirSnapshot>>>>,
 }
 
 impl<'s> Worker<'s> {
@@ -1504,10 +3102,34 @@ impl<'s> Worker<'s> {
     }
 
     fn run_one(&mut self, mut work: Work) -> WalkState {
+        let below_min_depth =
+            self.min_depth.map_or(false, |min| work.dent.depth() < min);
         // If the work is not a directory, then we can just execute the
         // caller's callback immediately and move on.
         if work.is_symlink() || !work.is_dir() {
-            return self.visitor.visit(Ok(work.dent));
+            if below_min_depth {
+                return self.complete_post_order(work.parent.take());
+            }
+            let budget_err = self
+                .byte_budget
+                .as_ref()
+                .and_then(|budget| budget.record(&work.dent));
+            let state = self.visitor.visit(Ok(work.dent));
+            if state.is_quit() {
+                return state;
+            }
+            // If this file pushed the walk over its byte budget, then
+            // deliver the error as the final event and quit, just as if the
+            // visitor had asked to.
+            if let Some(err) = budget_err {
+                let _ = self.visitor.visit(Err(err));
+                return WalkState::Quit;
+            }
+            let post_state = self.complete_post_order(work.parent.take());
+            if post_state.is_quit() {
+                return post_state;
+            }
+            return state;
         }
         if let Some(err) = work.add_parents() {
             let state = self.visitor.visit(Err(err));
@@ -1538,37 +3160,122 @@ impl<'s> Worker<'s> {
         // have sufficient read permissions to list the directory.
         // In that case we still want to provide the closure with a valid
         // entry before passing the error value.
-        let readdir = work.read_dir();
+        let readdir = {
+            // Hold a permit for the directory open itself (including the
+            // ignore files read along with it), but give it back before
+            // streaming the directory's entries, so that a visitor drawing
+            // from the same budget can't deadlock against us.
+            let _permit =
+                self.fd_budget.as_ref().map(|budget| budget.acquire());
+            work.read_dir(self.dir_error_policy)
+        };
         let depth = work.dent.depth();
-        let state = self.visitor.visit(Ok(work.dent));
-        if !state.is_continue() {
-            return state;
+        let root_index = work.dent.root_index;
+        if let Some(ref capture) = self.capture {
+            capture.lock().unwrap().push(snapshot_dir(work.dent.path()));
         }
+        // In post-order, the directory's yield is deferred until every
+        // child work item has completed. The record starts with a count of
+        // one, held by this worker until it has finished sending children.
+        // A directory shallower than the minimum depth is still descended
+        // into, but the directory itself isn't given to the visitor. The
+        // same goes for every directory when an allowlist is in use, since
+        // an allowlist enumerates files.
+        let suppress_dir = below_min_depth || self.allowlist.is_some();
+        let pending = if let TraversalOrder::PostOrder = self.traversal {
+            let dent = if suppress_dir { None } else { Some(work.dent) };
+            Some(Arc::new(PendingDir {
+                dent: Mutex::new(dent),
+                remaining: AtomicUsize::new(1),
+                parent: work.parent.take(),
+            }))
+        } else {
+            if !suppress_dir {
+                let state = self.visitor.visit(Ok(work.dent));
+                if !state.is_continue() {
+                    return state;
+                }
+            }
+            None
+        };
         if !descend {
+            let state = self.complete_post_order(pending);
+            if state.is_quit() {
+                return state;
+            }
             return WalkState::Skip;
         }
 
         let readdir = match readdir {
             Ok(readdir) => readdir,
             Err(err) => {
-                return self.visitor.visit(Err(err));
+                let state = self.visitor.visit(Err(err));
+                // Under the fatal policy, a failed directory read (after
+                // any retries) terminates the entire walk.
+                if let DirErrorPolicy::Fatal = self.dir_error_policy {
+                    return WalkState::Quit;
+                }
+                if state.is_quit() {
+                    return state;
+                }
+                let post_state = self.complete_post_order(pending);
+                if post_state.is_quit() {
+                    return post_state;
+                }
+                return state;
             }
         };
 
         if self.max_depth.map_or(false, |max| depth >= max) {
+            let state = self.complete_post_order(pending);
+            if state.is_quit() {
+                return state;
+            }
             return WalkState::Skip;
         }
         for result in readdir {
             let state = self.generate_work(
                 &work.ignore,
                 depth + 1,
+                root_index,
                 work.root_device,
+                work.forced,
+                &pending,
                 result,
             );
             if state.is_quit() {
                 return state;
             }
         }
+        // Release this worker's hold on the directory's pending record. If
+        // no children were sent (or they have all already completed), this
+        // is what yields the directory.
+        self.complete_post_order(pending)
+    }
+
+    /// Complete one unit of post-order work against the given pending
+    /// directory record, if any.
+    ///
+    /// When the record's counter reaches zero, its directory is given to
+    /// the visitor and completion propagates to the directory's own
+    /// parent, so that an empty ancestor chain unwinds correctly.
+    fn complete_post_order(
+        &mut self,
+        mut pending: Option<Arc<PendingDir>>,
+    ) -> WalkState {
+        while let Some(p) = pending {
+            if p.remaining.fetch_sub(1, AtomicOrdering::SeqCst) != 1 {
+                break;
+            }
+            let dent = p.dent.lock().unwrap().take();
+            if let Some(dent) = dent {
+                let state = self.visitor.visit(Ok(dent));
+                if !state.is_continue() {
+                    return state;
+                }
+            }
+            pending = p.parent.clone();
+        }
         WalkState::Continue
     }
 
@@ -1583,13 +3290,17 @@ impl<'s> Worker<'s> {
     /// caller's callback.
     ///
     /// `ig` is the `Ignore` matcher for the parent directory. `depth` should
-    /// be the depth of this entry. `result` should be the item yielded by
-    /// a directory iterator.
+    /// be the depth of this entry. `root_index` is the index of the root the
+    /// parent directory was reached from. `result` should be the item
+    /// yielded by a directory iterator.
     fn generate_work(
         &mut self,
         ig: &Ignore,
         depth: usize,
+        root_index: usize,
         root_device: Option<u64>,
+        forced: bool,
+        parent: &Option<Arc<PendingDir>>,
         result: Result<fs::DirEntry, io::Error>,
     ) -> WalkState {
         let fs_dent = match result {
@@ -1621,10 +3332,30 @@ impl<'s> Worker<'s> {
                 }
             }
         }
+        // Entries inherit the root of their parent directory, including
+        // entries reached by following a symlink above.
+        dent.root_index = root_index;
+        dent.stamp_symlink_target();
+        // A file shallower than the minimum depth can never be yielded, so
+        // skip it before consulting ignore rules or any filter predicate.
+        // (Directories still go through the motions below since the outcome
+        // determines whether we descend into them.)
+        if !dent.is_dir() && self.min_depth.map_or(false, |min| depth < min) {
+            return WalkState::Continue;
+        }
         // N.B. See analogous call in the single-threaded implementation about
         // why it's important for this to come before the checks below.
-        if should_skip_entry(ig, &dent) {
-            return WalkState::Continue;
+        let mut forced = forced;
+        if !forced && should_skip_entry(ig, &mut dent) {
+            if !dent.is_dir() || self.prune_policy.is_none() {
+                return WalkState::Continue;
+            }
+            let policy = self.prune_policy.as_ref().unwrap();
+            match consult_prune_policy(policy, ig, &dent) {
+                PruneDecision::Prune => return WalkState::Continue,
+                PruneDecision::Descend => {}
+                PruneDecision::Include => forced = true,
+            }
         }
         if let Some(ref stdout) = self.skip {
             let is_stdout = match path_equals(&dent, stdout) {
@@ -1635,6 +3366,29 @@ impl<'s> Worker<'s> {
                 return WalkState::Continue;
             }
         }
+        if !dent.is_dir() {
+            if self.modified_after.is_some() || self.modified_before.is_some()
+            {
+                if skip_modified(
+                    self.modified_after,
+                    self.modified_before,
+                    dent.path(),
+                    &dent.metadata().ok(),
+                ) {
+                    return WalkState::Continue;
+                }
+            }
+        } else if self.prune_unmodified_dirs {
+            if let Some(after) = self.modified_after {
+                if skip_unmodified_dir(
+                    after,
+                    dent.path(),
+                    &dent.metadata().ok(),
+                ) {
+                    return WalkState::Continue;
+                }
+            }
+        }
         let should_skip_filesize =
             if self.max_filesize.is_some() && !dent.is_dir() {
                 skip_filesize(
@@ -1651,8 +3405,38 @@ impl<'s> Worker<'s> {
             } else {
                 false
             };
-        if !should_skip_filesize && !should_skip_filtered {
-            self.send(Work { dent, ignore: ig.clone(), root_device });
+        let should_skip_allowlist = if let Some(ref allowlist) = self.allowlist
+        {
+            let rel = self
+                .roots
+                .get(root_index)
+                .and_then(|root| dent.path().strip_prefix(root).ok())
+                .unwrap_or_else(|| dent.path());
+            if dent.is_dir() {
+                !allowlist.is_allowed_dir(rel)
+            } else {
+                !allowlist.is_allowed_file(rel)
+            }
+        } else {
+            false
+        };
+        if !should_skip_filesize
+            && !should_skip_filtered
+            && !should_skip_allowlist
+        {
+            // Count the child against its parent's pending record before
+            // sending, so that the child cannot complete first and yield
+            // the directory prematurely.
+            if let Some(ref pending) = *parent {
+                pending.remaining.fetch_add(1, AtomicOrdering::SeqCst);
+            }
+            self.send(Work {
+                dent,
+                ignore: ig.clone(),
+                root_device,
+                forced,
+                parent: parent.clone(),
+            });
         }
         WalkState::Continue
     }
@@ -1793,22 +3577,110 @@ fn skip_filesize(
     }
 }
 
-fn should_skip_entry(ig: &Ignore, dent: &DirEntry) -> bool {
-    let m = ig.matched_dir_entry(dent);
-    if m.is_ignore() {
-        log::debug!("ignoring {}: {:?}", dent.path().display(), m);
-        true
-    } else if m.is_whitelist() {
-        log::debug!("whitelisting {}: {:?}", dent.path().display(), m);
-        false
-    } else {
-        false
-    }
-}
-
-/// Returns a handle to stdout for filtering search.
-///
-/// A handle is returned if and only if stdout is being redirected to a file.
+// Before calling this function, make sure that you ensure that is really
+// necessary as the arguments imply a file stat.
+fn skip_modified(
+    after: Option<SystemTime>,
+    before: Option<SystemTime>,
+    path: &Path,
+    ent: &Option<Metadata>,
+) -> bool {
+    let Some(mtime) = ent.as_ref().and_then(|md| md.modified().ok()) else {
+        return false;
+    };
+    if after.map_or(false, |after| mtime < after) {
+        log::debug!("ignoring {}: modified before threshold", path.display());
+        return true;
+    }
+    if before.map_or(false, |before| mtime > before) {
+        log::debug!("ignoring {}: modified after threshold", path.display());
+        return true;
+    }
+    false
+}
+
+/// Returns true when a directory's own modification time indicates it has
+/// not been modified since `after`. This is a heuristic for pruning, since
+/// directory timestamps only reflect changes to their direct entries. See
+/// `WalkBuilder::prune_unmodified_dirs`.
+fn skip_unmodified_dir(
+    after: SystemTime,
+    path: &Path,
+    ent: &Option<Metadata>,
+) -> bool {
+    let Some(mtime) = ent.as_ref().and_then(|md| md.modified().ok()) else {
+        return false;
+    };
+    if mtime < after {
+        log::debug!(
+            "pruning {}: directory unmodified since threshold",
+            path.display()
+        );
+        true
+    } else {
+        false
+    }
+}
+
+/// Consult the given prune policy for a directory that is about to be
+/// skipped because it matched an ignore rule.
+fn consult_prune_policy(
+    policy: &PrunePolicy,
+    ig: &Ignore,
+    dent: &DirEntry,
+) -> PruneDecision {
+    let m = ig.matched_dir_entry(dent);
+    let glob_match = match m {
+        Match::None => Match::None,
+        Match::Ignore(ref im) => {
+            im.gitignore_glob().map_or(Match::None, Match::Ignore)
+        }
+        Match::Whitelist(ref im) => {
+            im.gitignore_glob().map_or(Match::None, Match::Whitelist)
+        }
+    };
+    let decision = (policy.0)(dent.path(), &glob_match);
+    match decision {
+        PruneDecision::Prune => {}
+        PruneDecision::Descend => {
+            log::debug!(
+                "descending into {} despite ignore match",
+                dent.path().display()
+            );
+        }
+        PruneDecision::Include => {
+            log::debug!(
+                "force-including subtree of {} despite ignore match",
+                dent.path().display()
+            );
+        }
+    }
+    decision
+}
+
+fn should_skip_entry(ig: &Ignore, dent: &mut DirEntry) -> bool {
+    let m = ig.matched_dir_entry(dent);
+    if m.is_ignore() {
+        log::debug!("ignoring {}: {:?}", dent.path().display(), m);
+        true
+    } else if m.is_whitelist() {
+        log::debug!("whitelisting {}: {:?}", dent.path().display(), m);
+        // Keep the whitelist's provenance around so that callers can tell
+        // that the entry was included by an explicit rule rather than by
+        // not matching anything.
+        if let Match::Whitelist(ref im) = m {
+            dent.ignore_provenance =
+                im.gitignore_glob().map(IgnoreProvenance::from_gitignore);
+        }
+        false
+    } else {
+        false
+    }
+}
+
+/// Returns a handle to stdout for filtering search.
+///
+/// A handle is returned if and only if stdout is being redirected to a file.
 /// The handle returned corresponds to that file.
 ///
 /// This can be used to ensure that we do not attempt to search a file that we
@@ -1909,7 +3781,11 @@ mod tests {
     use std::path::Path;
     use std::sync::{Arc, Mutex};
 
-    use super::{DirEntry, WalkBuilder, WalkState};
+    use super::{
+        Allowlist, DirEntry, DirErrorPolicy, HiddenMode, IgnoreCaseMode,
+        IgnoreFileKind, PruneDecision, SubmoduleMode, TraversalOrder,
+        WalkBuilder, WalkState,
+    };
     use crate::tests::TempDir;
 
     fn wfile<P: AsRef<Path>>(path: P, contents: &str) {
@@ -2006,6 +3882,84 @@ mod tests {
         assert_eq!(got, mkpaths(expected), "parallel");
     }
 
+    #[test]
+    fn max_total_bytes() {
+        let td = tmpdir();
+        for name in ["a", "b", "c", "d", "e"] {
+            wfile_size(td.path().join(name), 100);
+        }
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder.max_total_bytes(Some(250));
+        builder.sort_by_file_name(|a, b| a.cmp(b));
+        let mut files = 0;
+        let mut budget_err = None;
+        for result in builder.build() {
+            match result {
+                Ok(dent) => {
+                    // The budget error must be the final event.
+                    assert!(budget_err.is_none());
+                    if dent.file_type().map_or(false, |ft| ft.is_file()) {
+                        files += 1;
+                    }
+                }
+                Err(err) => budget_err = Some(err),
+            }
+        }
+        // The file that pushes the walk over its budget is still yielded,
+        // so the cutoff is within one file of the budget.
+        assert_eq!(3, files);
+        match budget_err {
+            Some(crate::Error::ByteBudgetExceeded { budget, seen }) => {
+                assert_eq!(250, budget);
+                assert_eq!(300, seen);
+            }
+            err => panic!("expected byte budget error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn max_total_bytes_parallel() {
+        let td = tmpdir();
+        for i in 0..10 {
+            wfile_size(td.path().join(i.to_string()), 100);
+        }
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder.max_total_bytes(Some(450));
+        let files = Arc::new(Mutex::new(0u64));
+        let budget_errs = Arc::new(Mutex::new(vec![]));
+        builder.build_parallel().run(|| {
+            let files = files.clone();
+            let budget_errs = budget_errs.clone();
+            Box::new(move |result| {
+                match result {
+                    Ok(dent) => {
+                        if dent.file_type().map_or(false, |ft| ft.is_file()) {
+                            *files.lock().unwrap() += 1;
+                        }
+                    }
+                    Err(err) => budget_errs.lock().unwrap().push(err),
+                }
+                WalkState::Continue
+            })
+        });
+        // The budget error is delivered exactly once, even when multiple
+        // workers race past the budget.
+        let budget_errs = budget_errs.lock().unwrap();
+        assert_eq!(1, budget_errs.len());
+        match budget_errs[0] {
+            crate::Error::ByteBudgetExceeded { budget, seen } => {
+                assert_eq!(450, budget);
+                assert!(seen > 450);
+            }
+            ref err => panic!("expected byte budget error, got {:?}", err),
+        }
+        // Enough files to cover the budget must have been yielded before
+        // the walk terminated.
+        assert!(*files.lock().unwrap() >= 5);
+    }
+
     #[test]
     fn no_ignores() {
         let td = tmpdir();
@@ -2057,6 +4011,77 @@ mod tests {
         assert_paths(td.path(), &builder, &["bar", "a", "a/bar"]);
     }
 
+    /// Builds the fixture tree shared by the allowlist tests and returns
+    /// an allowlist covering a sparse subset of it.
+    fn allowlist_fixture(td: &TempDir) -> Allowlist {
+        mkdirp(td.path().join("a/b"));
+        mkdirp(td.path().join("a/c"));
+        mkdirp(td.path().join("d/e"));
+        wfile(td.path().join("a/b/one.txt"), "");
+        wfile(td.path().join("a/b/two.txt"), "");
+        wfile(td.path().join("a/c/three.txt"), "");
+        wfile(td.path().join("d/four.txt"), "");
+        wfile(td.path().join("d/e/five.txt"), "");
+        wfile(td.path().join("six.txt"), "");
+        Allowlist::new(["a/b/one.txt", "d/four.txt", "missing.txt"])
+    }
+
+    #[test]
+    fn allowlist() {
+        let td = tmpdir();
+        let allow = allowlist_fixture(&td);
+        assert_eq!(3, allow.len());
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder.allowlist(allow);
+        // Only allowlisted files that exist are yielded; directories are
+        // never yielded.
+        assert_paths(td.path(), &builder, &["a/b/one.txt", "d/four.txt"]);
+    }
+
+    #[test]
+    fn allowlist_ignores_still_apply() {
+        let td = tmpdir();
+        let allow = allowlist_fixture(&td);
+        wfile(td.path().join(".ignore"), "four.txt\n");
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder.allowlist(allow);
+        assert_paths(td.path(), &builder, &["a/b/one.txt"]);
+    }
+
+    #[test]
+    fn allowlist_prunes_dirs() {
+        let td = tmpdir();
+        let allow = allowlist_fixture(&td);
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder.allowlist(allow);
+        builder.capture_state(true);
+
+        let visited = |builder: &WalkBuilder| {
+            let snapshot = builder.take_snapshot().unwrap();
+            let mut dirs: Vec<_> = snapshot
+                .paths()
+                .map(|p| {
+                    normal_path(
+                        p.strip_prefix(td.path()).unwrap().to_str().unwrap(),
+                    )
+                })
+                .collect();
+            dirs.sort();
+            dirs
+        };
+
+        // Directories that cannot contain an allowlisted file ("a/c" and
+        // "d/e") are not descended into.
+        walk_collect(td.path(), &builder);
+        assert_eq!(visited(&builder), vec!["", "a", "a/b", "d"]);
+
+        walk_collect_entries_parallel(&builder);
+        assert_eq!(visited(&builder), vec!["", "a", "a/b", "d"]);
+    }
+
     #[test]
     fn gitignore() {
         let td = tmpdir();
@@ -2075,6 +4100,298 @@ mod tests {
         );
     }
 
+    #[test]
+    fn gitignore_ignore_case_auto() {
+        let fixture = |value: &str| {
+            let td = tmpdir();
+            mkdirp(td.path().join(".git"));
+            if !value.is_empty() {
+                wfile(
+                    td.path().join(".git/config"),
+                    &format!("[core]\n\tignoreCase = {}\n", value),
+                );
+            }
+            wfile(td.path().join(".gitignore"), "Build/\n");
+            mkdirp(td.path().join("build"));
+            wfile(td.path().join("build/out"), "");
+            wfile(td.path().join("other"), "");
+            td
+        };
+
+        let td = fixture("true");
+        assert_paths(td.path(), &WalkBuilder::new(td.path()), &["other"]);
+
+        let td = fixture("false");
+        assert_paths(
+            td.path(),
+            &WalkBuilder::new(td.path()),
+            &["build", "build/out", "other"],
+        );
+
+        // Without the setting, matching is case sensitive.
+        let td = fixture("");
+        assert_paths(
+            td.path(),
+            &WalkBuilder::new(td.path()),
+            &["build", "build/out", "other"],
+        );
+    }
+
+    #[test]
+    fn gitignore_ignore_case_override() {
+        let fixture = |value: &str| {
+            let td = tmpdir();
+            mkdirp(td.path().join(".git"));
+            wfile(
+                td.path().join(".git/config"),
+                &format!("[core]\n\tignoreCase = {}\n", value),
+            );
+            wfile(td.path().join(".gitignore"), "Build/\n");
+            mkdirp(td.path().join("build"));
+            wfile(td.path().join("build/out"), "");
+            wfile(td.path().join("other"), "");
+            td
+        };
+
+        // Always and Never both win over the repository's config.
+        let td = fixture("false");
+        assert_paths(
+            td.path(),
+            WalkBuilder::new(td.path())
+                .ignore_case_insensitive(IgnoreCaseMode::Always),
+            &["other"],
+        );
+
+        let td = fixture("true");
+        assert_paths(
+            td.path(),
+            WalkBuilder::new(td.path())
+                .ignore_case_insensitive(IgnoreCaseMode::Never),
+            &["build", "build/out", "other"],
+        );
+    }
+
+    #[test]
+    fn ignore_provenance() {
+        let td = tmpdir();
+        mkdirp(td.path().join(".git"));
+        wfile(td.path().join(".gitignore"), "*.log\n!keep.log\n");
+        wfile(td.path().join("foo.log"), "");
+        wfile(td.path().join("keep.log"), "");
+        wfile(td.path().join("bar"), "");
+
+        let check = |ents: Vec<DirEntry>| {
+            assert!(!ents.iter().any(|d| d.file_name() == "foo.log"));
+            for ent in ents {
+                if ent.file_name() == "keep.log" {
+                    let prov = ent.ignore_provenance().unwrap();
+                    assert_eq!("!keep.log", prov.original());
+                    assert_eq!(Some(2), prov.line());
+                    assert!(prov.from().unwrap().ends_with(".gitignore"));
+                } else {
+                    assert!(
+                        ent.ignore_provenance().is_none(),
+                        "unexpected provenance for {}",
+                        ent.path().display(),
+                    );
+                }
+            }
+        };
+        let serial: Vec<DirEntry> = WalkBuilder::new(td.path())
+            .build()
+            .map(|result| result.unwrap())
+            .collect();
+        check(serial);
+        check(walk_collect_entries_parallel(&WalkBuilder::new(td.path())));
+    }
+
+    /// Build a repository with a submodule `sub`, a submodule `sub/nested`
+    /// nested inside it, and a plain nested repository `plain` that isn't
+    /// listed in any `.gitmodules` file.
+    fn submodule_fixture() -> TempDir {
+        let td = tmpdir();
+        mkdirp(td.path().join(".git"));
+        wfile(
+            td.path().join(".gitmodules"),
+            "[submodule \"sub\"]\n\tpath = sub\n\turl = ../sub\n",
+        );
+        wfile(td.path().join(".ignore"), "by-parent");
+        wfile(td.path().join("a"), "");
+
+        mkdirp(td.path().join("sub"));
+        wfile(td.path().join("sub/.git"), "gitdir: ../.git/modules/sub");
+        wfile(
+            td.path().join("sub/.gitmodules"),
+            "[submodule \"nested\"]\n\tpath = nested\n\turl = ../nested\n",
+        );
+        wfile(td.path().join("sub/.ignore"), "by-sub");
+        wfile(td.path().join("sub/b"), "");
+        wfile(td.path().join("sub/by-parent"), "");
+
+        mkdirp(td.path().join("sub/nested"));
+        wfile(
+            td.path().join("sub/nested/.git"),
+            "gitdir: ../../.git/modules/sub/modules/nested",
+        );
+        wfile(td.path().join("sub/nested/c"), "");
+        wfile(td.path().join("sub/nested/by-parent"), "");
+        wfile(td.path().join("sub/nested/by-sub"), "");
+
+        mkdirp(td.path().join("plain/.git"));
+        wfile(td.path().join("plain/d"), "");
+        wfile(td.path().join("plain/by-parent"), "");
+        td
+    }
+
+    #[test]
+    fn submodules_descend() {
+        let td = submodule_fixture();
+        // The default: submodules are walked and the parent repository's
+        // ignore rules apply inside them.
+        assert_paths(
+            td.path(),
+            &WalkBuilder::new(td.path()),
+            &[
+                "a",
+                "plain",
+                "plain/d",
+                "sub",
+                "sub/b",
+                "sub/nested",
+                "sub/nested/c",
+            ],
+        );
+    }
+
+    #[test]
+    fn submodules_skip() {
+        let td = submodule_fixture();
+        let mut builder = WalkBuilder::new(td.path());
+        builder.git_submodules(SubmoduleMode::Skip);
+        // `sub` isn't walked at all, but `plain` is: it has its own `.git`
+        // without being listed in a `.gitmodules` file.
+        assert_paths(td.path(), &builder, &["a", "plain", "plain/d"]);
+    }
+
+    #[test]
+    fn submodules_descend_isolated() {
+        let td = submodule_fixture();
+        let mut builder = WalkBuilder::new(td.path());
+        builder.git_submodules(SubmoduleMode::DescendIsolated);
+        // The parent repository's rules stop at the `sub` boundary, and
+        // `sub`'s rules stop at the `sub/nested` boundary. `plain` isn't a
+        // submodule, so the parent's rules still apply inside it.
+        assert_paths(
+            td.path(),
+            &builder,
+            &[
+                "a",
+                "plain",
+                "plain/d",
+                "sub",
+                "sub/b",
+                "sub/by-parent",
+                "sub/nested",
+                "sub/nested/by-parent",
+                "sub/nested/by-sub",
+                "sub/nested/c",
+            ],
+        );
+    }
+
+    #[test]
+    fn root_index() {
+        let td = tmpdir();
+        mkdirp(td.path().join("r1/sub"));
+        mkdirp(td.path().join("r2/sub"));
+        wfile(td.path().join("r1/sub/file"), "");
+        wfile(td.path().join("r2/sub/file"), "");
+
+        let mut builder = WalkBuilder::new(td.path().join("r1"));
+        builder.add(td.path().join("r2"));
+        assert_eq!(
+            builder.roots(),
+            &[td.path().join("r1"), td.path().join("r2")],
+        );
+
+        let expected = vec![
+            (0, "r1".to_string()),
+            (0, "r1/sub".to_string()),
+            (0, "r1/sub/file".to_string()),
+            (1, "r2".to_string()),
+            (1, "r2/sub".to_string()),
+            (1, "r2/sub/file".to_string()),
+        ];
+
+        let mut got = vec![];
+        for result in builder.build() {
+            let dent = result.unwrap();
+            let path = dent.path().strip_prefix(td.path()).unwrap();
+            got.push((dent.root_index(), normal_path(path.to_str().unwrap())));
+        }
+        got.sort();
+        assert_eq!(expected, got);
+
+        let mut got = vec![];
+        for dent in walk_collect_entries_parallel(&builder) {
+            let path = dent.path().strip_prefix(td.path()).unwrap();
+            got.push((dent.root_index(), normal_path(path.to_str().unwrap())));
+        }
+        got.sort();
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn global_gitignore_prebuilt() {
+        let td = tmpdir();
+        mkdirp(td.path().join(".git"));
+        wfile(td.path().join("foo"), "");
+        wfile(td.path().join("bar"), "");
+
+        let mut gibuilder = crate::gitignore::GitignoreBuilder::new("");
+        gibuilder.add_line(None, "foo").unwrap();
+        let gi = gibuilder.build().unwrap();
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder.global_gitignore(gi);
+        assert_paths(td.path(), &builder, &["bar"]);
+
+        // Disabling global gitignore handling takes precedence over a
+        // pre-built matcher.
+        builder.git_global(false);
+        assert_paths(td.path(), &builder, &["foo", "bar"]);
+    }
+
+    #[test]
+    fn prebuilt_matchers_equivalence() {
+        let td = tmpdir();
+        wfile(td.path().join("foo.rs"), "");
+        wfile(td.path().join("bar.py"), "");
+        wfile(td.path().join("baz.log"), "");
+
+        let mut ob = crate::overrides::OverrideBuilder::new(td.path());
+        ob.add("!*.log").unwrap();
+        let overrides = ob.build().unwrap();
+
+        let mut tb = crate::types::TypesBuilder::new();
+        tb.add_defaults();
+        tb.select("rust");
+        let types = tb.build().unwrap();
+
+        // A fresh walker per "request," sharing clones of the same pre-built
+        // matchers, produces the same results every time as a walker whose
+        // matchers were built from scratch.
+        let mut fresh = WalkBuilder::new(td.path());
+        fresh.overrides(overrides.clone()).types(types.clone());
+        let expected = walk_collect(td.path(), &fresh);
+        assert_eq!(expected, vec!["foo.rs".to_string()]);
+        for _ in 0..3 {
+            let mut builder = WalkBuilder::new(td.path());
+            builder.overrides(overrides.clone()).types(types.clone());
+            assert_eq!(expected, walk_collect(td.path(), &builder));
+        }
+    }
+
     #[test]
     fn explicit_ignore() {
         let td = tmpdir();
@@ -2149,6 +4466,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn min_depth() {
+        let td = tmpdir();
+        mkdirp(td.path().join("a/b/c"));
+        wfile(td.path().join("foo"), "");
+        wfile(td.path().join("a/foo"), "");
+        wfile(td.path().join("a/b/foo"), "");
+        wfile(td.path().join("a/b/c/foo"), "");
+
+        let mut builder = WalkBuilder::new(td.path());
+        assert_paths(
+            td.path(),
+            builder.min_depth(Some(2)),
+            &["a/b", "a/foo", "a/b/c", "a/b/foo", "a/b/c/foo"],
+        );
+        assert_paths(
+            td.path(),
+            builder.min_depth(Some(3)),
+            &["a/b/c", "a/b/foo", "a/b/c/foo"],
+        );
+        // A minimum depth that exceeds the maximum depth yields nothing.
+        assert_paths(
+            td.path(),
+            builder.min_depth(Some(2)).max_depth(Some(1)),
+            &[],
+        );
+    }
+
+    #[test]
+    fn min_depth_explicit_file() {
+        let td = tmpdir();
+        wfile(td.path().join("foo"), "");
+
+        // An explicitly given file is a root, so it sits at depth 0, just
+        // like it does for max_depth.
+        let file = td.path().join("foo");
+        let mut builder = WalkBuilder::new(&file);
+        assert_paths(td.path(), &builder, &["foo"]);
+        assert_paths(td.path(), builder.min_depth(Some(0)), &["foo"]);
+        assert_paths(td.path(), builder.min_depth(Some(1)), &[]);
+    }
+
     #[test]
     fn max_filesize() {
         let td = tmpdir();
@@ -2183,12 +4542,130 @@ mod tests {
         );
     }
 
-    #[cfg(unix)] // because symlinks on windows are weird
     #[test]
-    fn symlinks() {
+    fn post_order() {
+        let td = tmpdir();
+        mkdirp(td.path().join("a/b/c"));
+        mkdirp(td.path().join("empty"));
+        wfile(td.path().join("a/b/c/foo"), "");
+        wfile(td.path().join("a/b/bar"), "");
+        wfile(td.path().join("a/baz"), "");
+        wfile(td.path().join("quux"), "");
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder.traversal_order(TraversalOrder::PostOrder);
+
+        let normalize = |dent: DirEntry| {
+            let path = dent.path().strip_prefix(td.path()).unwrap();
+            if path.as_os_str().is_empty() {
+                return None;
+            }
+            Some(normal_path(path.to_str().unwrap()))
+        };
+        let serial: Vec<String> = builder
+            .build()
+            .filter_map(|result| normalize(result.unwrap()))
+            .collect();
+        let parallel: Vec<String> = walk_collect_entries_parallel(&builder)
+            .into_iter()
+            .filter_map(normalize)
+            .collect();
+        for paths in [serial, parallel] {
+            let pos = |name: &str| {
+                paths.iter().position(|p| p == name).unwrap_or_else(|| {
+                    panic!("{name} not yielded in {paths:?}")
+                })
+            };
+            assert_eq!(8, paths.len());
+            // Every directory comes after everything beneath it. An empty
+            // directory is still yielded.
+            assert!(pos("a/b/c") > pos("a/b/c/foo"));
+            assert!(pos("a/b") > pos("a/b/c"));
+            assert!(pos("a/b") > pos("a/b/bar"));
+            assert!(pos("a") > pos("a/b"));
+            assert!(pos("a") > pos("a/baz"));
+            pos("empty");
+            pos("quux");
+        }
+    }
+
+    #[test]
+    fn post_order_skip_dir() {
         let td = tmpdir();
         mkdirp(td.path().join("a/b"));
-        symlink(td.path().join("a/b"), td.path().join("z"));
+        wfile(td.path().join("a/b/foo"), "");
+        wfile(td.path().join("bar"), "");
+
+        // A directory rejected by the filter predicate is neither descended
+        // into nor yielded, just as in pre-order.
+        let mut builder = WalkBuilder::new(td.path());
+        builder
+            .traversal_order(TraversalOrder::PostOrder)
+            .filter_entry(|dent| dent.file_name() != "b");
+        assert_paths(td.path(), &builder, &["a", "bar"]);
+    }
+
+    #[test]
+    fn modified_filters() {
+        let td = tmpdir();
+        wfile(td.path().join("old"), "");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let threshold = std::time::SystemTime::now();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        wfile(td.path().join("new"), "");
+
+        let mut builder = WalkBuilder::new(td.path());
+        assert_paths(td.path(), &builder, &["old", "new"]);
+        assert_paths(
+            td.path(),
+            builder.modified_after(Some(threshold)),
+            &["new"],
+        );
+
+        let mut builder = WalkBuilder::new(td.path());
+        assert_paths(
+            td.path(),
+            builder.modified_before(Some(threshold)),
+            &["old"],
+        );
+    }
+
+    #[test]
+    fn prune_unmodified_dirs() {
+        let td = tmpdir();
+        mkdirp(td.path().join("a"));
+        wfile(td.path().join("a/old"), "");
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let threshold = std::time::SystemTime::now();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        wfile(td.path().join("new"), "");
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder.modified_after(Some(threshold));
+        // Without the heuristic, the old directory is still entered (and
+        // yielded), but the old file inside it fails the time filter.
+        assert_paths(td.path(), &builder, &["a", "new"]);
+        // With it, the directory is pruned outright.
+        assert_paths(td.path(), builder.prune_unmodified_dirs(true), &["new"]);
+
+        // Rewriting a file in place updates the file's timestamp but not
+        // its parent directory's, which is exactly the case the heuristic
+        // misses.
+        wfile(td.path().join("a/old"), "rewritten");
+        assert_paths(td.path(), &builder, &["new"]);
+        assert_paths(
+            td.path(),
+            builder.prune_unmodified_dirs(false),
+            &["a", "new", "a/old"],
+        );
+    }
+
+    #[cfg(unix)] // because symlinks on windows are weird
+    #[test]
+    fn symlinks() {
+        let td = tmpdir();
+        mkdirp(td.path().join("a/b"));
+        symlink(td.path().join("a/b"), td.path().join("z"));
         wfile(td.path().join("a/b/foo"), "");
 
         let mut builder = WalkBuilder::new(td.path());
@@ -2200,6 +4677,64 @@ mod tests {
         );
     }
 
+    #[cfg(unix)] // because symlinks on windows are weird
+    #[test]
+    fn symlink_targets() {
+        use std::collections::HashMap;
+
+        let td = tmpdir();
+        mkdirp(td.path().join("a"));
+        wfile(td.path().join("a/foo"), "");
+        symlink("a/foo", td.path().join("rel"));
+        symlink(td.path().join("a/foo"), td.path().join("abs"));
+        symlink("does-not-exist", td.path().join("dangle"));
+
+        let assert_targets = |dents: Vec<DirEntry>| {
+            let by_name: HashMap<String, DirEntry> = dents
+                .into_iter()
+                .map(|d| (d.file_name().to_string_lossy().into_owned(), d))
+                .collect();
+
+            let foo = &by_name["foo"];
+            assert!(!foo.is_via_symlink());
+            assert_eq!(None, foo.symlink_target());
+
+            let rel = &by_name["rel"];
+            assert!(rel.is_via_symlink());
+            assert_eq!(Some(Path::new("a/foo")), rel.symlink_target());
+            assert_eq!(
+                td.path().join("a/foo").canonicalize().unwrap(),
+                rel.resolved_path().unwrap(),
+            );
+
+            let abs = &by_name["abs"];
+            assert!(abs.is_via_symlink());
+            assert_eq!(
+                Some(td.path().join("a/foo").as_path()),
+                abs.symlink_target(),
+            );
+
+            // A dangling link still reports its raw target, but it has no
+            // canonical path to resolve to.
+            let dangle = &by_name["dangle"];
+            assert!(dangle.is_via_symlink());
+            assert_eq!(
+                Some(Path::new("does-not-exist")),
+                dangle.symlink_target(),
+            );
+            assert!(dangle.resolved_path().is_err());
+        };
+
+        let dents = WalkBuilder::new(td.path())
+            .build()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_targets(dents);
+        assert_targets(walk_collect_entries_parallel(&WalkBuilder::new(
+            td.path(),
+        )));
+    }
+
     #[cfg(unix)] // because symlinks on windows are weird
     #[test]
     fn first_path_not_symlink() {
@@ -2233,6 +4768,259 @@ mod tests {
         assert_paths(td.path(), &builder.follow_links(true), &["a", "a/b"]);
     }
 
+    #[cfg(unix)] // the fixture relies on a symlink loop error
+    #[test]
+    fn dir_error_fatal_stops_serial_walk() {
+        // Use a symlink loop to provoke a directory error mid-walk. Unlike
+        // a permissions based fixture, this works even when the tests run
+        // as root.
+        let fixture = || {
+            let td = tmpdir();
+            mkdirp(td.path().join("a/b"));
+            symlink(td.path().join("a"), td.path().join("a/b/c"));
+            wfile(td.path().join("z"), "");
+            td
+        };
+        let collect = |td: &TempDir, policy: DirErrorPolicy| {
+            let mut builder = WalkBuilder::new(td.path());
+            builder
+                .follow_links(true)
+                .sort_by_file_name(|a, b| a.cmp(b))
+                .dir_error_policy(policy);
+            builder.build().collect::<Vec<_>>()
+        };
+
+        // Under the default policy, the error is reported and the walk
+        // continues on to 'z'.
+        let td = fixture();
+        let results = collect(&td, DirErrorPolicy::Warn);
+        assert_eq!(1, results.iter().filter(|r| r.is_err()).count());
+        assert!(results
+            .iter()
+            .flatten()
+            .any(|dent| dent.path() == td.path().join("z")));
+
+        // Under the fatal policy, the error is the final item yielded.
+        let td = fixture();
+        let results = collect(&td, DirErrorPolicy::Fatal);
+        assert!(results.last().unwrap().is_err());
+        assert!(!results
+            .iter()
+            .flatten()
+            .any(|dent| dent.path() == td.path().join("z")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dir_error_fatal_no_read_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let td = tmpdir();
+        mkdirp(td.path().join("locked"));
+        wfile(td.path().join("locked/secret"), "");
+        wfile(td.path().join("z"), "");
+        fs::set_permissions(
+            td.path().join("locked"),
+            fs::Permissions::from_mode(0o000),
+        )
+        .unwrap();
+        // If we're running as root, then the directory is readable anyway
+        // and this test is meaningless. See also: 'no_read_permissions'.
+        if fs::read_dir(td.path().join("locked")).is_ok() {
+            return;
+        }
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .dir_error_policy(DirErrorPolicy::Fatal);
+        let results = builder.build().collect::<Vec<_>>();
+        assert!(results.last().unwrap().is_err());
+        assert!(!results
+            .iter()
+            .flatten()
+            .any(|dent| dent.path() == td.path().join("z")));
+
+        // The parallel walker should quit after visiting the error too.
+        let errs = Arc::new(Mutex::new(vec![]));
+        builder.build_parallel().run(|| {
+            let errs = errs.clone();
+            Box::new(move |result| {
+                if let Err(err) = result {
+                    errs.lock().unwrap().push(err);
+                }
+                WalkState::Continue
+            })
+        });
+        assert_eq!(1, errs.lock().unwrap().len());
+
+        // Restore permissions so that the temporary directory can be
+        // cleaned up.
+        fs::set_permissions(
+            td.path().join("locked"),
+            fs::Permissions::from_mode(0o755),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn dir_error_retry() {
+        use std::time::Duration;
+
+        use super::Work;
+        use crate::dir::IgnoreBuilder;
+
+        // Exercise the retry loop directly on a unit of parallel work: the
+        // directory exists when the entry is created, vanishes before the
+        // read and reappears from another thread while we're retrying.
+        let td = tmpdir();
+        let path = td.path().join("flaky");
+        mkdirp(&path);
+        let dent = DirEntry::new_raw(
+            super::DirEntryRaw::from_path(1, path.clone(), false).unwrap(),
+            None,
+        );
+        let mut work = Work {
+            dent,
+            ignore: IgnoreBuilder::new().build(),
+            root_device: None,
+            forced: false,
+            parent: None,
+        };
+        fs::remove_dir(&path).unwrap();
+
+        // Without retries, the read fails outright.
+        assert!(work.read_dir(DirErrorPolicy::Warn).is_err());
+
+        let handle = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(100));
+                fs::create_dir(&path).unwrap();
+            }
+        });
+        let result = work.read_dir(DirErrorPolicy::Retry {
+            attempts: 100,
+            backoff: Duration::from_millis(10),
+        });
+        handle.join().unwrap();
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn fd_budget_bounds_directory_opens() {
+        let td = tmpdir();
+        // A tree with many directories so that, with many threads and a
+        // tiny budget, workers actually contend for permits.
+        for i in 0..20 {
+            for j in 0..5 {
+                mkdirp(td.path().join(format!("d{}/e{}", i, j)));
+                wfile(td.path().join(format!("d{}/e{}/f", i, j)), "");
+            }
+        }
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder.threads(8).fd_budget(Some(2));
+        let budget = builder.fd_budget_handle().unwrap();
+        let paths = walk_collect_parallel(td.path(), &builder);
+        // The walk completes and sees everything despite budget < threads.
+        // 20 top-level dirs, 100 subdirs and 100 files.
+        assert_eq!(220, paths.len());
+        // The high-water mark of concurrently held permits never exceeds
+        // the budget.
+        assert!(budget.high_water() >= 1);
+        assert!(budget.high_water() <= 2);
+
+        // A zero budget is treated as a budget of one.
+        let mut builder = WalkBuilder::new(td.path());
+        builder.threads(4).fd_budget(Some(0));
+        let budget = builder.fd_budget_handle().unwrap();
+        let paths = walk_collect_parallel(td.path(), &builder);
+        // 20 top-level dirs, 100 subdirs and 100 files.
+        assert_eq!(220, paths.len());
+        assert_eq!(1, budget.high_water());
+    }
+
+    #[test]
+    fn visitor_try_build_failures() {
+        use std::io;
+
+        use super::{ParallelVisitor, ParallelVisitorBuilder};
+        use crate::Error;
+
+        struct CollectVisitor(Arc<Mutex<Vec<DirEntry>>>);
+
+        impl ParallelVisitor for CollectVisitor {
+            fn visit(&mut self, entry: Result<DirEntry, Error>) -> WalkState {
+                if let Ok(dent) = entry {
+                    self.0.lock().unwrap().push(dent);
+                }
+                WalkState::Continue
+            }
+        }
+
+        struct FlakyBuilder {
+            builds: usize,
+            fail: fn(usize) -> bool,
+            dents: Arc<Mutex<Vec<DirEntry>>>,
+        }
+
+        impl<'s> ParallelVisitorBuilder<'s> for FlakyBuilder {
+            fn build(&mut self) -> Box<dyn ParallelVisitor + 's> {
+                Box::new(CollectVisitor(self.dents.clone()))
+            }
+
+            fn try_build(
+                &mut self,
+            ) -> Result<Box<dyn ParallelVisitor + 's>, Error> {
+                self.builds += 1;
+                if (self.fail)(self.builds) {
+                    Err(Error::Io(io::Error::new(
+                        io::ErrorKind::Other,
+                        "setup failed",
+                    )))
+                } else {
+                    Ok(self.build())
+                }
+            }
+        }
+
+        let td = tmpdir();
+        for name in ["a", "b", "c"] {
+            wfile(td.path().join(name), "");
+        }
+
+        // Failing to build the visitor for one worker just reduces the
+        // degree of parallelism; the walk still completes.
+        let dents = Arc::new(Mutex::new(vec![]));
+        let mut builder =
+            FlakyBuilder { builds: 0, fail: |n| n == 2, dents: dents.clone() };
+        WalkBuilder::new(td.path())
+            .threads(2)
+            .build_parallel()
+            .try_visit(&mut builder)
+            .unwrap();
+        let mut got: Vec<String> = dents
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|d| d.path().strip_prefix(td.path()).ok())
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(|p| normal_path(p.to_str().unwrap()))
+            .collect();
+        got.sort();
+        assert_eq!(got, mkpaths(&["a", "b", "c"]));
+
+        // When every worker fails to build, the error is returned.
+        let mut builder =
+            FlakyBuilder { builds: 0, fail: |_| true, dents: dents.clone() };
+        assert!(WalkBuilder::new(td.path())
+            .threads(2)
+            .build_parallel()
+            .try_visit(&mut builder)
+            .is_err());
+    }
+
     // It's a little tricky to test the 'same_file_system' option since
     // we need an environment with more than one file system. We adopt a
     // heuristic where /sys is typically a distinct volume on Linux and roll
@@ -2286,6 +5074,115 @@ mod tests {
         assert_paths(dir_path.parent().unwrap(), &builder, &["root"]);
     }
 
+    #[test]
+    fn hidden_mode_dotfiles() {
+        let td = tmpdir();
+        wfile(td.path().join(".dotfile"), "");
+        wfile(td.path().join("visible"), "");
+
+        // The default mode (and DotOnly) treat dotfiles as hidden.
+        assert_paths(td.path(), &WalkBuilder::new(td.path()), &["visible"]);
+        assert_paths(
+            td.path(),
+            WalkBuilder::new(td.path()).hidden_mode(HiddenMode::DotOnly),
+            &["visible"],
+        );
+        // In Native mode, a dotfile without the platform's hidden marker is
+        // not hidden. (On platforms without such a marker, nothing is.)
+        assert_paths(
+            td.path(),
+            WalkBuilder::new(td.path()).hidden_mode(HiddenMode::Native),
+            &[".dotfile", "visible"],
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn hidden_mode_native_attribute() {
+        let td = tmpdir();
+        wfile(td.path().join("attr.txt"), "");
+        wfile(td.path().join("visible"), "");
+        let status = std::process::Command::new("attrib")
+            .arg("+h")
+            .arg(td.path().join("attr.txt"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        for mode in [HiddenMode::Native, HiddenMode::Both] {
+            assert_paths(
+                td.path(),
+                WalkBuilder::new(td.path()).hidden_mode(mode),
+                &["visible"],
+            );
+        }
+        assert_paths(
+            td.path(),
+            WalkBuilder::new(td.path()).hidden_mode(HiddenMode::DotOnly),
+            &["attr.txt", "visible"],
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_paths_deep_tree() {
+        let td = tmpdir();
+        // Build a tree whose full path comfortably exceeds MAX_PATH (260).
+        // The directories and files within it can only be created through
+        // the extended-length form.
+        let mut deep = td.path().to_path_buf();
+        while deep.as_os_str().len() < 300 {
+            deep.push("a-reasonably-long-component");
+        }
+        mkdirp(crate::pathutil::to_long_path(&deep));
+        wfile(crate::pathutil::to_long_path(&deep.join("keep.txt")), "");
+        wfile(crate::pathutil::to_long_path(&deep.join("drop.log")), "");
+        wfile(td.path().join(".ignore"), "*.log\n");
+
+        // The deep file is found, the ignore rule applies to it and the
+        // reported paths are in normal form. (walk_collect strips the
+        // unprefixed temp dir path, so a `\\?\` prefix on any entry would
+        // fail the strip and show up in the results.)
+        let paths = walk_collect(td.path(), &WalkBuilder::new(td.path()));
+        assert!(paths.iter().any(|p| p.ends_with("keep.txt")), "{paths:?}");
+        assert!(!paths.iter().any(|p| p.ends_with("drop.log")), "{paths:?}");
+        assert!(!paths.iter().any(|p| p.contains(r"\\?\")), "{paths:?}");
+
+        // And the same for the parallel walker.
+        let paths =
+            walk_collect_parallel(td.path(), &WalkBuilder::new(td.path()));
+        assert!(paths.iter().any(|p| p.ends_with("keep.txt")), "{paths:?}");
+        assert!(!paths.iter().any(|p| p.ends_with("drop.log")), "{paths:?}");
+        assert!(!paths.iter().any(|p| p.contains(r"\\?\")), "{paths:?}");
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn hidden_mode_native_uf_hidden() {
+        let td = tmpdir();
+        wfile(td.path().join("flagged"), "");
+        wfile(td.path().join("visible"), "");
+        let status = std::process::Command::new("chflags")
+            .arg("hidden")
+            .arg(td.path().join("flagged"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        for mode in [HiddenMode::Native, HiddenMode::Both] {
+            assert_paths(
+                td.path(),
+                WalkBuilder::new(td.path()).hidden_mode(mode),
+                &["visible"],
+            );
+        }
+        assert_paths(
+            td.path(),
+            WalkBuilder::new(td.path()).hidden_mode(HiddenMode::DotOnly),
+            &["flagged", "visible"],
+        );
+    }
+
     #[test]
     fn filter() {
         let td = tmpdir();
@@ -2307,4 +5204,283 @@ mod tests {
             &["x", "x/y", "x/y/foo"],
         );
     }
+
+    #[test]
+    fn prune_policy_descend() {
+        let td = tmpdir();
+        mkdirp(td.path().join("vendor/lib"));
+        wfile(td.path().join(".ignore"), "vendor/\nvendor/**/*.txt\n");
+        wfile(td.path().join("vendor/keep.rs"), "");
+        wfile(td.path().join("vendor/notes.txt"), "");
+        wfile(td.path().join("vendor/lib/notes.txt"), "");
+        wfile(td.path().join("main.rs"), "");
+
+        // Without a policy, the whole vendor directory is pruned.
+        assert_paths(td.path(), &WalkBuilder::new(td.path()), &["main.rs"]);
+
+        // Descending into vendor yields the directory and its non-ignored
+        // children, while ignored files beneath it are still filtered.
+        let mut builder = WalkBuilder::new(td.path());
+        builder.prune_policy(|path, m| {
+            assert!(m.is_ignore());
+            if path.file_name() == Some(OsStr::new("vendor")) {
+                PruneDecision::Descend
+            } else {
+                PruneDecision::Prune
+            }
+        });
+        assert_paths(
+            td.path(),
+            &builder,
+            &["main.rs", "vendor", "vendor/keep.rs", "vendor/lib"],
+        );
+    }
+
+    #[test]
+    fn prune_policy_include() {
+        let td = tmpdir();
+        mkdirp(td.path().join("vendor/lib"));
+        wfile(td.path().join(".ignore"), "vendor/\nvendor/**/*.txt\n");
+        wfile(td.path().join("vendor/keep.rs"), "");
+        wfile(td.path().join("vendor/notes.txt"), "");
+        wfile(td.path().join("vendor/lib/notes.txt"), "");
+        wfile(td.path().join("main.rs"), "");
+
+        // Including vendor forces the entire subtree in, bypassing ignore
+        // rules for its children.
+        let mut builder = WalkBuilder::new(td.path());
+        builder.prune_policy(|_, _| PruneDecision::Include);
+        assert_paths(
+            td.path(),
+            &builder,
+            &[
+                "main.rs",
+                "vendor",
+                "vendor/keep.rs",
+                "vendor/lib",
+                "vendor/lib/notes.txt",
+                "vendor/notes.txt",
+            ],
+        );
+    }
+
+    #[test]
+    fn prune_policy_confirm() {
+        let td = tmpdir();
+        mkdirp(td.path().join("vendor"));
+        wfile(td.path().join(".ignore"), "vendor/\n");
+        wfile(td.path().join("vendor/keep.rs"), "");
+        wfile(td.path().join("main.rs"), "");
+
+        let mut builder = WalkBuilder::new(td.path());
+        builder.prune_policy(|_, _| PruneDecision::Prune);
+        assert_paths(td.path(), &builder, &["main.rs"]);
+    }
+
+    #[test]
+    fn effective_threads() {
+        let td = tmpdir();
+        let available =
+            std::thread::available_parallelism().map_or(1, |n| n.get());
+
+        // When unspecified, the heuristic uses available parallelism, capped
+        // by the default cap.
+        let walker = WalkBuilder::new(td.path()).build_parallel();
+        assert_eq!(
+            available.min(super::DEFAULT_THREADS_CAP),
+            walker.effective_threads()
+        );
+
+        // An explicit thread count is used as-is.
+        let walker = WalkBuilder::new(td.path()).threads(3).build_parallel();
+        assert_eq!(3, walker.effective_threads());
+
+        // A custom cap only applies to the automatic choice.
+        let walker =
+            WalkBuilder::new(td.path()).threads_cap(Some(1)).build_parallel();
+        assert_eq!(1, walker.effective_threads());
+
+        // Lifting the cap uses all available parallelism.
+        let walker =
+            WalkBuilder::new(td.path()).threads_cap(None).build_parallel();
+        assert_eq!(available, walker.effective_threads());
+    }
+
+    #[test]
+    fn capture_state_and_verify() {
+        use super::WalkVerifier;
+
+        let td = tmpdir();
+        mkdirp(td.path().join("a"));
+        mkdirp(td.path().join("b"));
+        wfile(td.path().join("a/foo"), "");
+        wfile(td.path().join("b/bar"), "");
+
+        let mut builder = WalkBuilder::new(td.path());
+        assert!(builder.take_snapshot().is_none());
+        builder.capture_state(true);
+        for result in builder.build() {
+            result.unwrap();
+        }
+        let snapshot = builder.take_snapshot().unwrap();
+        let mut dirs: Vec<_> = snapshot
+            .paths()
+            .map(|p| {
+                normal_path(
+                    p.strip_prefix(td.path()).unwrap().to_str().unwrap(),
+                )
+            })
+            .collect();
+        dirs.sort();
+        assert_eq!(dirs, vec!["", "a", "b"]);
+
+        // Nothing changed, so nothing is reported.
+        assert!(WalkVerifier::new().verify(&snapshot).is_empty());
+
+        // Adding a file to a directory bumps that directory's mtime. Sleep
+        // first so that file systems with coarse timestamps notice.
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        wfile(td.path().join("a/new"), "");
+        let changed = WalkVerifier::new().verify(&snapshot);
+        assert_eq!(changed, vec![td.path().join("a")]);
+
+        // Changing an ignore file is detected via its content hash, with no
+        // reliance on timestamps.
+        wfile(td.path().join("b/.gitignore"), "bar");
+        let mut changed = WalkVerifier::new().verify(&snapshot);
+        changed.sort();
+        let mut expected = vec![td.path().join("a"), td.path().join("b")];
+        expected.sort();
+        assert_eq!(changed, expected);
+
+        // A parallel walk captures the same set of directories.
+        walk_collect_entries_parallel(&builder);
+        let parallel = builder.take_snapshot().unwrap();
+        let mut dirs: Vec<_> = parallel
+            .paths()
+            .map(|p| {
+                normal_path(
+                    p.strip_prefix(td.path()).unwrap().to_str().unwrap(),
+                )
+            })
+            .collect();
+        dirs.sort();
+        assert_eq!(dirs, vec!["", "a", "b"]);
+        assert!(WalkVerifier::new().verify(&parallel).is_empty());
+    }
+
+    #[test]
+    fn prefetch_gitignores_equivalence() {
+        let td = tmpdir();
+        mkdirp(td.path().join(".git"));
+        wfile(td.path().join(".gitignore"), "foo");
+        mkdirp(td.path().join("a"));
+        wfile(td.path().join("a/.ignore"), "bar");
+        mkdirp(td.path().join("a/b"));
+        wfile(td.path().join("a/b/.gitignore"), "!foo");
+        wfile(td.path().join("foo"), "");
+        wfile(td.path().join("a/bar"), "");
+        wfile(td.path().join("a/baz"), "");
+        wfile(td.path().join("a/b/foo"), "");
+        wfile(td.path().join("a/b/quux"), "");
+
+        let mut builder = WalkBuilder::new(td.path());
+        let lazy = walk_collect(td.path(), &builder);
+        let lazy_parallel = walk_collect_parallel(td.path(), &builder);
+        builder.prefetch_gitignores(true);
+        assert_paths(
+            td.path(),
+            &builder,
+            &["a", "a/b", "a/b/foo", "a/b/quux", "a/baz"],
+        );
+        assert_eq!(lazy, walk_collect(td.path(), &builder));
+        assert_eq!(lazy_parallel, walk_collect_parallel(td.path(), &builder));
+    }
+
+    #[test]
+    fn on_ignore_file_events() {
+        let td = tmpdir();
+        mkdirp(td.path().join(".git/info"));
+        wfile(td.path().join(".git/info/exclude"), "qux");
+        wfile(td.path().join(".gitignore"), "foo\nbar");
+        wfile(td.path().join(".ignore"), "baz");
+        mkdirp(td.path().join("a"));
+        wfile(td.path().join("a/.gitignore"), "!foo");
+        wfile(td.path().join("quux"), "");
+        wfile(td.path().join("a/quux"), "");
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let mut builder = WalkBuilder::new(td.path());
+        // Keep the test hermetic: the global gitignore depends on the
+        // environment this test runs in.
+        builder.git_global(false);
+        let collected = events.clone();
+        builder.on_ignore_file(move |event| {
+            collected.lock().unwrap().push(event);
+        });
+
+        // The set of reported paths must be exactly the set of ignore
+        // files present, for both walkers.
+        let expected = vec![
+            (td.path().join(".git/info/exclude"), IgnoreFileKind::GitExclude),
+            (td.path().join(".gitignore"), IgnoreFileKind::Gitignore),
+            (td.path().join(".ignore"), IgnoreFileKind::Ignore),
+            (td.path().join("a/.gitignore"), IgnoreFileKind::Gitignore),
+        ];
+        walk_collect(td.path(), &builder);
+        let serial = std::mem::take(&mut *events.lock().unwrap());
+        walk_collect_parallel(td.path(), &builder);
+        let parallel = std::mem::take(&mut *events.lock().unwrap());
+        for events in [serial, parallel] {
+            // Only consider events for this directory tree, since building
+            // matchers for the temp directory's parents may report ignore
+            // files from the environment.
+            let mut events: Vec<_> = events
+                .into_iter()
+                .filter(|e| e.path().starts_with(td.path()))
+                .collect();
+            events.sort_by(|e1, e2| e1.path().cmp(e2.path()));
+            let got: Vec<_> = events
+                .iter()
+                .map(|e| (e.path().to_path_buf(), e.kind()))
+                .collect();
+            assert_eq!(got, expected);
+            for event in &events {
+                assert!(!event.duration().is_zero(), "{:?}", event);
+                assert!(!event.cached(), "{:?}", event);
+            }
+            let gitignore = &events[1];
+            assert_eq!(2, gitignore.glob_count());
+        }
+    }
+
+    #[test]
+    fn on_ignore_file_cached() {
+        let td = tmpdir();
+        mkdirp(td.path().join(".git"));
+        wfile(td.path().join(".gitignore"), "foo");
+        wfile(td.path().join(".ignore"), "bar");
+
+        let events = Arc::new(Mutex::new(vec![]));
+        let mut builder = WalkBuilder::new(td.path());
+        builder.git_global(false);
+        builder.prefetch_gitignores(true);
+        let collected = events.clone();
+        builder.on_ignore_file(move |event| {
+            collected.lock().unwrap().push(event);
+        });
+        walk_collect(td.path(), &builder);
+
+        let events = events.lock().unwrap();
+        let mut cached: Vec<_> = events
+            .iter()
+            .filter(|e| e.path().starts_with(td.path()) && e.cached())
+            .map(|e| e.path().to_path_buf())
+            .collect();
+        cached.sort();
+        assert_eq!(
+            cached,
+            vec![td.path().join(".gitignore"), td.path().join(".ignore")]
+        );
+    }
 }
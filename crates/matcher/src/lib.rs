@@ -1083,6 +1083,59 @@ pub trait Matcher {
         None
     }
 
+    /// If this matcher was configured with a maximum multi-line match span,
+    /// then this returns that limit in bytes.
+    ///
+    /// This is a request from the matcher to whatever is driving the search:
+    /// when searching across multiple lines, matches longer than this limit
+    /// should be refused (by reporting an error) instead of being produced.
+    /// Since refusing long matches generally requires cooperation from the
+    /// search driver, implementations must not rely on the limit being
+    /// enforced.
+    ///
+    /// By default, this returns `None`, which means matches may span any
+    /// number of bytes.
+    #[inline]
+    fn multiline_span_limit(&self) -> Option<usize> {
+        None
+    }
+
+    /// If this matcher can attribute matches to the individual patterns it
+    /// was built from, then this returns the number of patterns.
+    ///
+    /// By default, this returns `None`, which means matches cannot be
+    /// attributed to individual patterns. Note that `None` is distinct from
+    /// `Some(1)`: the latter guarantees that `pattern_index` works and that
+    /// every match belongs to the one and only pattern.
+    #[inline]
+    fn pattern_count(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the index of the first pattern that matches the given
+    /// haystack, if this matcher supports attributing matches to individual
+    /// patterns.
+    ///
+    /// This is intended to be called with the bytes of a match produced by
+    /// this matcher in order to discover which pattern produced it. When
+    /// multiple patterns match, the one with the smallest index wins.
+    ///
+    /// By default, this returns `None`, which means matches cannot be
+    /// attributed to individual patterns.
+    #[inline]
+    fn pattern_index(&self, _haystack: &[u8]) -> Option<usize> {
+        None
+    }
+
+    /// Returns the original text of the pattern at the given index, if
+    /// available.
+    ///
+    /// By default, this returns `None`.
+    #[inline]
+    fn pattern_text(&self, _index: usize) -> Option<&str> {
+        None
+    }
+
     /// Return one of the following: a confirmed line match, a candidate line
     /// match (which may be a false positive) or no match at all (which **must
     /// not** be a false negative). When reporting a confirmed or candidate
@@ -1369,6 +1422,26 @@ impl<'a, M: Matcher> Matcher for &'a M {
         (*self).line_terminator()
     }
 
+    #[inline]
+    fn multiline_span_limit(&self) -> Option<usize> {
+        (*self).multiline_span_limit()
+    }
+
+    #[inline]
+    fn pattern_count(&self) -> Option<usize> {
+        (*self).pattern_count()
+    }
+
+    #[inline]
+    fn pattern_index(&self, haystack: &[u8]) -> Option<usize> {
+        (*self).pattern_index(haystack)
+    }
+
+    #[inline]
+    fn pattern_text(&self, index: usize) -> Option<&str> {
+        (*self).pattern_text(index)
+    }
+
     #[inline]
     fn find_candidate_line(
         &self,
@@ -19,6 +19,203 @@ pub fn default_color_specs() -> Vec<UserColorSpec> {
     ]
 }
 
+/// The maximum color depth to emit to a terminal.
+///
+/// Terminals vary in how many colors they support. A color specification
+/// that exceeds what the terminal understands is rendered incorrectly, or in
+/// the worst case, shows up as raw escape codes. Setting a depth instructs a
+/// printer to quantize richer colors down to the nearest color the terminal
+/// can represent before emitting them.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ColorDepth {
+    /// Detect the supported depth from the environment.
+    ///
+    /// This inspects the `COLORTERM` and `TERM` environment variables, as
+    /// described by [`ColorDepth::detect_with_env`].
+    Auto,
+    /// 24-bit color. Nothing is quantized.
+    TrueColor,
+    /// 256 colors. RGB colors are quantized to the nearest entry of the
+    /// xterm 256-color palette (the 6x6x6 color cube or the grayscale ramp).
+    Ansi256,
+    /// 16 colors. RGB and 256-palette colors are quantized to the nearest
+    /// of the 8 standard ANSI colors.
+    Ansi16,
+}
+
+impl Default for ColorDepth {
+    fn default() -> ColorDepth {
+        ColorDepth::Auto
+    }
+}
+
+impl ColorDepth {
+    /// Detect the color depth supported by the terminal from the environment.
+    ///
+    /// `env` is called with the name of an environment variable and should
+    /// return its value, if set. (This is a parameter so that callers can
+    /// substitute an environment for testing. Pass
+    /// `|name| std::env::var(name).ok()` to use the process environment.)
+    ///
+    /// A `COLORTERM` value of `truecolor` or `24bit` indicates 24-bit
+    /// support, as does a `TERM` value containing `truecolor` or `direct`.
+    /// Otherwise, a `TERM` value containing `256color` indicates 256-color
+    /// support. Anything else is conservatively treated as a 16 color
+    /// terminal. This never returns `ColorDepth::Auto`.
+    pub fn detect_with_env<F>(env: F) -> ColorDepth
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        if let Some(v) = env("COLORTERM") {
+            if v == "truecolor" || v == "24bit" {
+                return ColorDepth::TrueColor;
+            }
+        }
+        if let Some(v) = env("TERM") {
+            if v.contains("truecolor") || v.contains("direct") {
+                return ColorDepth::TrueColor;
+            }
+            if v.contains("256color") {
+                return ColorDepth::Ansi256;
+            }
+        }
+        ColorDepth::Ansi16
+    }
+
+    /// Resolve `Auto` to a concrete depth using the process environment.
+    pub(crate) fn resolve(self) -> ColorDepth {
+        match self {
+            ColorDepth::Auto => {
+                ColorDepth::detect_with_env(|name| std::env::var(name).ok())
+            }
+            depth => depth,
+        }
+    }
+
+    /// Quantize the given color to the nearest color representable at this
+    /// depth.
+    fn quantize(self, color: &Color) -> Color {
+        match self {
+            ColorDepth::Auto | ColorDepth::TrueColor => color.clone(),
+            ColorDepth::Ansi256 => match *color {
+                Color::Rgb(r, g, b) => {
+                    Color::Ansi256(nearest_ansi256(r, g, b))
+                }
+                ref color => color.clone(),
+            },
+            ColorDepth::Ansi16 => match *color {
+                Color::Rgb(r, g, b) => nearest_ansi16(r, g, b),
+                Color::Ansi256(n) => {
+                    let (r, g, b) = ansi256_rgb(n);
+                    nearest_ansi16(r, g, b)
+                }
+                ref color => color.clone(),
+            },
+        }
+    }
+}
+
+/// The channel levels of the 6x6x6 color cube in the xterm 256-color palette.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The xterm RGB values of the 16 standard ANSI colors.
+///
+/// Entries 8-15 are the bright variants of entries 0-7.
+const ANSI16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// Returns the squared Euclidean distance between two RGB colors.
+fn distance(c1: (u8, u8, u8), c2: (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| {
+        let diff = i32::from(a) - i32::from(b);
+        (diff * diff) as u32
+    };
+    d(c1.0, c2.0) + d(c1.1, c2.1) + d(c1.2, c2.2)
+}
+
+/// Returns the index of the xterm 256-color palette entry nearest to the
+/// given RGB color.
+///
+/// Only the color cube (indices 16-231) and the grayscale ramp (indices
+/// 232-255) are considered. The first 16 entries are skipped since their
+/// values are commonly redefined by terminal themes.
+fn nearest_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let nearest_level = |v: u8| {
+        (0..CUBE.len())
+            .min_by_key(|&i| distance((v, 0, 0), (CUBE[i], 0, 0)))
+            .unwrap()
+    };
+    let (ri, gi, bi) = (nearest_level(r), nearest_level(g), nearest_level(b));
+    let cube_index = u8::try_from(16 + 36 * ri + 6 * gi + bi).unwrap();
+    let cube_rgb = (CUBE[ri], CUBE[gi], CUBE[bi]);
+
+    // The grayscale ramp runs from (8, 8, 8) to (238, 238, 238) in steps
+    // of 10. Its nearest entry to the average of the channels is the best
+    // gray candidate.
+    let avg = (u32::from(r) + u32::from(g) + u32::from(b)) / 3;
+    let gray_index = avg.saturating_sub(3).min(235) / 10;
+    let gray_level = u8::try_from(8 + 10 * gray_index).unwrap();
+    let gray_rgb = (gray_level, gray_level, gray_level);
+
+    if distance((r, g, b), cube_rgb) <= distance((r, g, b), gray_rgb) {
+        cube_index
+    } else {
+        u8::try_from(232 + gray_index).unwrap()
+    }
+}
+
+/// Returns the RGB value of the given xterm 256-color palette entry.
+fn ansi256_rgb(n: u8) -> (u8, u8, u8) {
+    match n {
+        0..=15 => ANSI16[usize::from(n)],
+        16..=231 => {
+            let i = usize::from(n - 16);
+            (CUBE[i / 36], CUBE[(i % 36) / 6], CUBE[i % 6])
+        }
+        232..=255 => {
+            let level = 8 + 10 * (n - 232);
+            (level, level, level)
+        }
+    }
+}
+
+/// Returns the standard ANSI color nearest to the given RGB color.
+///
+/// Bright variants map to the same named color as their base variant, since
+/// brightness is expressed separately (via the `intense` style) in a
+/// `ColorSpec`.
+fn nearest_ansi16(r: u8, g: u8, b: u8) -> Color {
+    let i = (0..ANSI16.len())
+        .min_by_key(|&i| distance((r, g, b), ANSI16[i]))
+        .unwrap();
+    match i % 8 {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        _ => Color::White,
+    }
+}
+
 /// An error that can occur when parsing color specifications.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum ColorError {
@@ -51,7 +248,8 @@ impl std::fmt::Display for ColorError {
             ColorError::UnrecognizedOutType(ref name) => write!(
                 f,
                 "unrecognized output type '{}'. Choose from: \
-                 path, line, column, match.",
+                 path, line, context-line, context-before, context-after, \
+                 column, match, separator.",
                 name,
             ),
             ColorError::UnrecognizedSpecType(ref name) => write!(
@@ -71,7 +269,8 @@ impl std::fmt::Display for ColorError {
             ColorError::InvalidFormat(ref original) => write!(
                 f,
                 "invalid color spec format: '{}'. Valid format \
-                 is '(path|line|column|match):(fg|bg|style):(value)'.",
+                 is '(path|line|context-line|context-before|context-after\
+                 |column|match|separator):(fg|bg|style):(value)'.",
                 original,
             ),
         }
@@ -88,8 +287,12 @@ impl std::fmt::Display for ColorError {
 pub struct ColorSpecs {
     path: ColorSpec,
     line: ColorSpec,
+    context_line: ColorSpec,
+    context_before: ColorSpec,
+    context_after: ColorSpec,
     column: ColorSpec,
     matched: ColorSpec,
+    separator: ColorSpec,
 }
 
 /// A single color specification provided by the user.
@@ -99,7 +302,8 @@ pub struct ColorSpecs {
 /// The format of a `Spec` is a triple: `{type}:{attribute}:{value}`. Each
 /// component is defined as follows:
 ///
-/// * `{type}` can be one of `path`, `line`, `column` or `match`.
+/// * `{type}` can be one of `path`, `line`, `context-line`, `context-before`,
+///   `context-after`, `column`, `match` or `separator`.
 /// * `{attribute}` can be one of `fg`, `bg` or `style`. `{attribute}` may also
 ///   be the special value `none`, in which case, `{value}` can be omitted.
 /// * `{value}` is either a color name (for `fg`/`bg`) or a style instruction.
@@ -179,8 +383,12 @@ enum SpecValue {
 enum OutType {
     Path,
     Line,
+    ContextLine,
+    ContextBefore,
+    ContextAfter,
     Column,
     Match,
+    Separator,
 }
 
 /// The specification type.
@@ -208,14 +416,47 @@ impl ColorSpecs {
     /// specifications.
     pub fn new(specs: &[UserColorSpec]) -> ColorSpecs {
         let mut merged = ColorSpecs::default();
+        let mut saw_context_line = false;
+        let mut saw_context_before = false;
+        let mut saw_context_after = false;
         for spec in specs {
             match spec.ty {
                 OutType::Path => spec.merge_into(&mut merged.path),
                 OutType::Line => spec.merge_into(&mut merged.line),
+                OutType::ContextLine => {
+                    saw_context_line = true;
+                    spec.merge_into(&mut merged.context_line);
+                }
+                OutType::ContextBefore => {
+                    saw_context_before = true;
+                    spec.merge_into(&mut merged.context_before);
+                }
+                OutType::ContextAfter => {
+                    saw_context_after = true;
+                    spec.merge_into(&mut merged.context_after);
+                }
                 OutType::Column => spec.merge_into(&mut merged.column),
                 OutType::Match => spec.merge_into(&mut merged.matched),
+                OutType::Separator => {
+                    spec.merge_into(&mut merged.separator)
+                }
             }
         }
+        // When no `context-line` spec is given, line numbers for contextual
+        // lines are styled just like line numbers for matching lines. This
+        // keeps configurations written before `context-line` existed looking
+        // the same.
+        if !saw_context_line {
+            merged.context_line = merged.line.clone();
+        }
+        // Similarly, before and after context lines are styled like any
+        // other contextual line unless they are explicitly distinguished.
+        if !saw_context_before {
+            merged.context_before = merged.context_line.clone();
+        }
+        if !saw_context_after {
+            merged.context_after = merged.context_line.clone();
+        }
         merged
     }
 
@@ -238,6 +479,33 @@ impl ColorSpecs {
         &self.line
     }
 
+    /// Return the color specification for coloring line numbers of
+    /// contextual lines.
+    ///
+    /// When no `context-line` specification was given, this is equivalent to
+    /// the specification returned by `line`.
+    pub fn context_line(&self) -> &ColorSpec {
+        &self.context_line
+    }
+
+    /// Return the color specification for coloring line numbers of
+    /// contextual lines that appear before a match.
+    ///
+    /// When no `context-before` specification was given, this is equivalent
+    /// to the specification returned by `context_line`.
+    pub fn context_before(&self) -> &ColorSpec {
+        &self.context_before
+    }
+
+    /// Return the color specification for coloring line numbers of
+    /// contextual lines that appear after a match.
+    ///
+    /// When no `context-after` specification was given, this is equivalent
+    /// to the specification returned by `context_line`.
+    pub fn context_after(&self) -> &ColorSpec {
+        &self.context_after
+    }
+
     /// Return the color specification for coloring column numbers.
     pub fn column(&self) -> &ColorSpec {
         &self.column
@@ -247,6 +515,44 @@ impl ColorSpecs {
     pub fn matched(&self) -> &ColorSpec {
         &self.matched
     }
+
+    /// Return the color specification for coloring separators. This applies
+    /// to both the context separator (typically `--`) and the separator
+    /// printed between sets of search results.
+    ///
+    /// By default, separators are unstyled.
+    pub fn separator(&self) -> &ColorSpec {
+        &self.separator
+    }
+
+    /// Return a copy of these specifications with every color quantized to
+    /// the nearest color representable at the given depth.
+    ///
+    /// For `ColorDepth::Auto` and `ColorDepth::TrueColor`, this is a no-op.
+    /// Named ANSI colors are never changed, since every color terminal
+    /// understands them.
+    pub fn downgrade(&self, depth: ColorDepth) -> ColorSpecs {
+        let quantize = |spec: &ColorSpec| {
+            let mut spec = spec.clone();
+            if let Some(fg) = spec.fg().map(|c| depth.quantize(c)) {
+                spec.set_fg(Some(fg));
+            }
+            if let Some(bg) = spec.bg().map(|c| depth.quantize(c)) {
+                spec.set_bg(Some(bg));
+            }
+            spec
+        };
+        ColorSpecs {
+            path: quantize(&self.path),
+            line: quantize(&self.line),
+            context_line: quantize(&self.context_line),
+            context_before: quantize(&self.context_before),
+            context_after: quantize(&self.context_after),
+            column: quantize(&self.column),
+            matched: quantize(&self.matched),
+            separator: quantize(&self.separator),
+        }
+    }
 }
 
 impl UserColorSpec {
@@ -338,8 +644,12 @@ impl std::str::FromStr for OutType {
         match &*s.to_lowercase() {
             "path" => Ok(OutType::Path),
             "line" => Ok(OutType::Line),
+            "context-line" => Ok(OutType::ContextLine),
+            "context-before" => Ok(OutType::ContextBefore),
+            "context-after" => Ok(OutType::ContextAfter),
             "column" => Ok(OutType::Column),
             "match" => Ok(OutType::Match),
+            "separator" => Ok(OutType::Separator),
             _ => Err(ColorError::UnrecognizedOutType(s.to_string())),
         }
     }
@@ -374,3 +684,179 @@ impl std::str::FromStr for Style {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_context_line() {
+        let spec: UserColorSpec = "context-line:fg:yellow".parse().unwrap();
+        assert_eq!(spec.ty, OutType::ContextLine);
+        assert_eq!(spec.value, SpecValue::Fg(Color::Yellow));
+
+        let err = "context:fg:yellow".parse::<UserColorSpec>().unwrap_err();
+        assert_eq!(
+            err,
+            ColorError::UnrecognizedOutType("context".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_separator() {
+        let spec: UserColorSpec = "separator:fg:yellow".parse().unwrap();
+        assert_eq!(spec.ty, OutType::Separator);
+        assert_eq!(spec.value, SpecValue::Fg(Color::Yellow));
+
+        let err = "sep:fg:yellow".parse::<UserColorSpec>().unwrap_err();
+        assert_eq!(err, ColorError::UnrecognizedOutType("sep".to_string()));
+    }
+
+    #[test]
+    fn separator_defaults_to_no_color() {
+        let specs = ColorSpecs::default_with_color();
+        assert_eq!(specs.separator(), &ColorSpec::default());
+    }
+
+    #[test]
+    fn context_line_defaults_to_line() {
+        let specs = ColorSpecs::new(&["line:fg:green".parse().unwrap()]);
+        assert_eq!(specs.context_line(), specs.line());
+    }
+
+    #[test]
+    fn context_before_after_default_to_context_line() {
+        let specs = ColorSpecs::new(&[
+            "line:fg:green".parse().unwrap(),
+            "context-line:fg:yellow".parse().unwrap(),
+        ]);
+        assert_eq!(specs.context_before(), specs.context_line());
+        assert_eq!(specs.context_after(), specs.context_line());
+
+        // ... and transitively to `line` when nothing context related is
+        // given.
+        let specs = ColorSpecs::new(&["line:fg:green".parse().unwrap()]);
+        assert_eq!(specs.context_before(), specs.line());
+        assert_eq!(specs.context_after(), specs.line());
+    }
+
+    #[test]
+    fn context_before_after_distinct() {
+        let specs = ColorSpecs::new(&[
+            "context-line:fg:yellow".parse().unwrap(),
+            "context-before:fg:blue".parse().unwrap(),
+            "context-after:fg:red".parse().unwrap(),
+        ]);
+        assert_eq!(specs.context_line().fg(), Some(&Color::Yellow));
+        assert_eq!(specs.context_before().fg(), Some(&Color::Blue));
+        assert_eq!(specs.context_after().fg(), Some(&Color::Red));
+    }
+
+    // Every entry of the 6x6x6 color cube quantizes to itself.
+    #[test]
+    fn quantize_cube_exact() {
+        for n in 16u8..=231 {
+            let (r, g, b) = ansi256_rgb(n);
+            assert_eq!(n, nearest_ansi256(r, g, b), "({}, {}, {})", r, g, b);
+        }
+    }
+
+    // Every entry of the grayscale ramp quantizes to itself.
+    #[test]
+    fn quantize_grayscale_exact() {
+        for n in 232u8..=255 {
+            let (r, g, b) = ansi256_rgb(n);
+            assert_eq!(n, nearest_ansi256(r, g, b), "({}, {}, {})", r, g, b);
+        }
+    }
+
+    #[test]
+    fn quantize_cube_nearest() {
+        // Pure red is in the cube: (255, 0, 0) is entry 196.
+        assert_eq!(196, nearest_ansi256(255, 0, 0));
+        // A slightly off red still maps to the same entry.
+        assert_eq!(196, nearest_ansi256(250, 10, 10));
+        // A color nearer to a gray than to any cube entry uses the ramp:
+        // (100, 100, 100) is closest to the (98, 98, 98) ramp entry.
+        assert_eq!(241, nearest_ansi256(100, 100, 100));
+        // Extremes: black and white are cube corners.
+        assert_eq!(16, nearest_ansi256(0, 0, 0));
+        assert_eq!(231, nearest_ansi256(255, 255, 255));
+    }
+
+    #[test]
+    fn quantize_ansi16() {
+        assert_eq!(Color::Red, nearest_ansi16(255, 0, 0));
+        assert_eq!(Color::Black, nearest_ansi16(0, 0, 0));
+        assert_eq!(Color::White, nearest_ansi16(255, 255, 255));
+        // Mid grays are nearest to bright black, whose base color is black.
+        assert_eq!(Color::Black, nearest_ansi16(120, 120, 120));
+        // Orange is nearest to yellow.
+        assert_eq!(Color::Yellow, nearest_ansi16(255, 127, 0));
+    }
+
+    #[test]
+    fn quantize_depths() {
+        let rgb = Color::Rgb(0xFF, 0x7F, 0x00);
+        assert_eq!(rgb, ColorDepth::Auto.quantize(&rgb));
+        assert_eq!(rgb, ColorDepth::TrueColor.quantize(&rgb));
+        assert_eq!(Color::Ansi256(208), ColorDepth::Ansi256.quantize(&rgb));
+        assert_eq!(Color::Yellow, ColorDepth::Ansi16.quantize(&rgb));
+        // A 256-palette color on a 16 color terminal degrades to a named
+        // color, but is passed through at higher depths.
+        let c256 = Color::Ansi256(196);
+        assert_eq!(c256, ColorDepth::Ansi256.quantize(&c256));
+        assert_eq!(Color::Red, ColorDepth::Ansi16.quantize(&c256));
+        // Named colors always pass through.
+        assert_eq!(Color::Green, ColorDepth::Ansi16.quantize(&Color::Green));
+    }
+
+    #[test]
+    fn detect_depth() {
+        let detect = |colorterm: Option<&str>, term: Option<&str>| {
+            ColorDepth::detect_with_env(|name| match name {
+                "COLORTERM" => colorterm.map(str::to_string),
+                "TERM" => term.map(str::to_string),
+                _ => None,
+            })
+        };
+        assert_eq!(
+            ColorDepth::TrueColor,
+            detect(Some("truecolor"), Some("xterm-256color"))
+        );
+        assert_eq!(ColorDepth::TrueColor, detect(Some("24bit"), None));
+        assert_eq!(
+            ColorDepth::Ansi256,
+            detect(None, Some("xterm-256color"))
+        );
+        assert_eq!(ColorDepth::Ansi16, detect(None, Some("vt100")));
+        assert_eq!(ColorDepth::Ansi16, detect(None, None));
+    }
+
+    #[test]
+    fn downgrade_specs() {
+        let specs = ColorSpecs::new(&[
+            "match:fg:0xff,0x7f,0x00".parse().unwrap(),
+            "line:fg:green".parse().unwrap(),
+        ]);
+        let downgraded = specs.downgrade(ColorDepth::Ansi256);
+        assert_eq!(downgraded.matched().fg(), Some(&Color::Ansi256(208)));
+        assert_eq!(downgraded.line().fg(), Some(&Color::Green));
+
+        let downgraded = specs.downgrade(ColorDepth::Ansi16);
+        assert_eq!(downgraded.matched().fg(), Some(&Color::Yellow));
+        assert_eq!(downgraded.line().fg(), Some(&Color::Green));
+
+        assert_eq!(specs, specs.downgrade(ColorDepth::TrueColor));
+    }
+
+    #[test]
+    fn context_line_distinct_from_line() {
+        let specs = ColorSpecs::new(&[
+            "line:fg:green".parse().unwrap(),
+            "context-line:fg:yellow".parse().unwrap(),
+        ]);
+        assert_eq!(specs.line().fg(), Some(&Color::Green));
+        assert_eq!(specs.context_line().fg(), Some(&Color::Yellow));
+    }
+}
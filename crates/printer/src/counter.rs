@@ -2,59 +2,172 @@ use std::io::{self, Write};
 
 use termcolor::{ColorSpec, HyperlinkSpec, WriteColor};
 
-/// A writer that counts the number of bytes that have been successfully
-/// written.
+/// A writer that counts the number of bytes and lines that have been
+/// successfully written.
+///
+/// Lines are counted by counting occurrences of the line terminator byte
+/// (which is `\n` by default) in the bytes written. A line terminator never
+/// needs to arrive in the same `write` call as the line it terminates, so
+/// callers may split their writes however they like.
+///
+/// Counts come in two flavors. The "current" counts, [`count`](CounterWriter::count)
+/// and [`line_count`](CounterWriter::line_count), reflect everything written
+/// since construction or since the last call to
+/// [`reset_count`](CounterWriter::reset_count), whichever is more recent.
+/// The "total" counts, [`total_count`](CounterWriter::total_count) and
+/// [`total_line_count`](CounterWriter::total_line_count), reflect everything
+/// written since construction and are unaffected by `reset_count`. The
+/// printers in this crate use `reset_count` to delineate per-file output, so
+/// the current counts answer "did this file print anything?" while the total
+/// counts answer "did anything print at all?"
+///
+/// Optional caps on the total counts may be set via
+/// [`max_bytes`](CounterWriter::max_bytes) and
+/// [`max_lines`](CounterWriter::max_lines). Once a cap has been met or
+/// exceeded, subsequent writes return an error of kind
+/// [`io::ErrorKind::WriteZero`]. The write that crosses a cap is itself
+/// written in full, so output is only ever truncated at a write boundary.
 #[derive(Clone, Debug)]
-pub(crate) struct CounterWriter<W> {
+pub struct CounterWriter<W> {
     wtr: W,
     count: u64,
     total_count: u64,
+    line_count: u64,
+    total_line_count: u64,
+    line_terminator: u8,
+    max_bytes: Option<u64>,
+    max_lines: Option<u64>,
 }
 
 impl<W: Write> CounterWriter<W> {
-    pub(crate) fn new(wtr: W) -> CounterWriter<W> {
-        CounterWriter { wtr, count: 0, total_count: 0 }
+    /// Create a new writer that counts the bytes and lines written to `wtr`.
+    pub fn new(wtr: W) -> CounterWriter<W> {
+        CounterWriter {
+            wtr,
+            count: 0,
+            total_count: 0,
+            line_count: 0,
+            total_line_count: 0,
+            line_terminator: b'\n',
+            max_bytes: None,
+            max_lines: None,
+        }
     }
 }
 
 impl<W> CounterWriter<W> {
     /// Returns the total number of bytes written since construction or the
-    /// last time `reset` was called.
+    /// last time `reset_count` was called.
     #[inline]
-    pub(crate) fn count(&self) -> u64 {
+    pub fn count(&self) -> u64 {
         self.count
     }
 
     /// Returns the total number of bytes written since construction.
     #[inline]
-    pub(crate) fn total_count(&self) -> u64 {
+    pub fn total_count(&self) -> u64 {
         self.total_count + self.count
     }
 
-    /// Resets the number of bytes written to `0`.
+    /// Returns the total number of line terminators written since
+    /// construction or the last time `reset_count` was called.
     #[inline]
-    pub(crate) fn reset_count(&mut self) {
+    pub fn line_count(&self) -> u64 {
+        self.line_count
+    }
+
+    /// Returns the total number of line terminators written since
+    /// construction.
+    #[inline]
+    pub fn total_line_count(&self) -> u64 {
+        self.total_line_count + self.line_count
+    }
+
+    /// Resets the number of bytes and lines written to `0`.
+    ///
+    /// The amounts accumulated so far are folded into the totals reported
+    /// by `total_count` and `total_line_count`, which are not reset.
+    #[inline]
+    pub fn reset_count(&mut self) {
         self.total_count += self.count;
         self.count = 0;
+        self.total_line_count += self.line_count;
+        self.line_count = 0;
+    }
+
+    /// Set the byte used to terminate lines for the purposes of line
+    /// counting.
+    ///
+    /// This is `\n` by default. Note that when output uses `\r\n` line
+    /// terminators, counting `\n` bytes still counts lines correctly.
+    pub fn line_terminator(&mut self, byte: u8) {
+        self.line_terminator = byte;
+    }
+
+    /// Set a cap on the total number of bytes written.
+    ///
+    /// Once `total_count` meets or exceeds the cap, subsequent writes
+    /// return an error of kind [`io::ErrorKind::WriteZero`]. The cap
+    /// compares against the total count, so it is unaffected by
+    /// `reset_count`.
+    ///
+    /// By default, no cap is set.
+    pub fn max_bytes(&mut self, limit: Option<u64>) {
+        self.max_bytes = limit;
     }
 
+    /// Set a cap on the total number of lines written.
+    ///
+    /// Once `total_line_count` meets or exceeds the cap, subsequent writes
+    /// return an error of kind [`io::ErrorKind::WriteZero`]. The cap
+    /// compares against the total count, so it is unaffected by
+    /// `reset_count`.
+    ///
+    /// By default, no cap is set.
+    pub fn max_lines(&mut self, limit: Option<u64>) {
+        self.max_lines = limit;
+    }
+
+    /// Returns a mutable reference to the underlying writer.
     #[inline]
-    pub(crate) fn get_mut(&mut self) -> &mut W {
+    pub fn get_mut(&mut self) -> &mut W {
         &mut self.wtr
     }
 
+    /// Consume this counter and return the underlying writer.
     #[inline]
-    pub(crate) fn into_inner(self) -> W {
+    pub fn into_inner(self) -> W {
         self.wtr
     }
+
+    /// Returns true if and only if a configured cap has been met or
+    /// exceeded.
+    fn over_limit(&self) -> bool {
+        self.max_bytes.map_or(false, |max| self.total_count() >= max)
+            || self
+                .max_lines
+                .map_or(false, |max| self.total_line_count() >= max)
+    }
 }
 
 impl<W: Write> Write for CounterWriter<W> {
     // A high match count ad hoc benchmark flagged this as a hot spot.
     #[inline(always)]
     fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        if !buf.is_empty()
+            && (self.max_bytes.is_some() || self.max_lines.is_some())
+            && self.over_limit()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "maximum amount of output reached",
+            ));
+        }
         let n = self.wtr.write(buf)?;
         self.count += n as u64;
+        let term = self.line_terminator;
+        self.line_count +=
+            buf[..n].iter().filter(|&&byte| byte == term).count() as u64;
         Ok(n)
     }
 
@@ -95,3 +208,72 @@ impl<W: WriteColor> WriteColor for CounterWriter<W> {
         self.wtr.is_synchronous()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::CounterWriter;
+
+    #[test]
+    fn counts_bytes_and_lines() {
+        let mut wtr = CounterWriter::new(vec![]);
+        wtr.write_all(b"foo\nbar").unwrap();
+        assert_eq!(wtr.count(), 7);
+        assert_eq!(wtr.line_count(), 1);
+        // A line terminator split across write calls still counts.
+        wtr.write_all(b"\n").unwrap();
+        assert_eq!(wtr.line_count(), 2);
+        wtr.write_all(b"baz\nquux\n").unwrap();
+        assert_eq!(wtr.count(), 17);
+        assert_eq!(wtr.line_count(), 4);
+    }
+
+    #[test]
+    fn reset_preserves_totals() {
+        let mut wtr = CounterWriter::new(vec![]);
+        wtr.write_all(b"foo\n").unwrap();
+        wtr.reset_count();
+        assert_eq!(wtr.count(), 0);
+        assert_eq!(wtr.line_count(), 0);
+        wtr.write_all(b"quux\n").unwrap();
+        assert_eq!(wtr.count(), 5);
+        assert_eq!(wtr.line_count(), 1);
+        assert_eq!(wtr.total_count(), 9);
+        assert_eq!(wtr.total_line_count(), 2);
+    }
+
+    #[test]
+    fn custom_line_terminator() {
+        let mut wtr = CounterWriter::new(vec![]);
+        wtr.line_terminator(b'\x00');
+        wtr.write_all(b"foo\nbar\x00baz\x00").unwrap();
+        assert_eq!(wtr.line_count(), 2);
+    }
+
+    #[test]
+    fn max_lines_stops_subsequent_writes() {
+        let mut wtr = CounterWriter::new(vec![]);
+        wtr.max_lines(Some(2));
+        // The write crossing the cap succeeds in full...
+        wtr.write_all(b"foo\nbar\nbaz\n").unwrap();
+        assert_eq!(wtr.line_count(), 3);
+        // ... but subsequent writes fail.
+        let err = wtr.write_all(b"quux\n").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+        // The cap compares against totals, so resetting doesn't lift it.
+        wtr.reset_count();
+        assert!(wtr.write_all(b"quux\n").is_err());
+        assert_eq!(wtr.into_inner(), b"foo\nbar\nbaz\n");
+    }
+
+    #[test]
+    fn max_bytes_stops_subsequent_writes() {
+        let mut wtr = CounterWriter::new(vec![]);
+        wtr.max_bytes(Some(4));
+        wtr.write_all(b"foobar").unwrap();
+        let err = wtr.write_all(b"baz").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+        assert_eq!(wtr.into_inner(), b"foobar");
+    }
+}
@@ -0,0 +1,477 @@
+use std::{
+    collections::VecDeque,
+    io,
+    path::Path,
+    process::{Child, Command, Stdio},
+};
+
+use {
+    grep_matcher::Matcher,
+    grep_searcher::{Searcher, Sink, SinkFinish, SinkMatch},
+};
+
+use crate::util::find_iter_at_in_context;
+
+/// The configuration for the exec sink.
+///
+/// This is manipulated by the ExecBuilder and then referenced by the actual
+/// implementation. Once a sink is built, the configuration is frozen and
+/// cannot changed.
+#[derive(Clone, Debug)]
+struct Config {
+    command: Vec<String>,
+    per_match: bool,
+    max_procs: usize,
+    dry_run: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config { command: vec![], per_match: false, max_procs: 1, dry_run: false }
+    }
+}
+
+/// A builder for a sink that runs an external command for search results.
+///
+/// The command is given as a template: in each argument, the placeholders
+/// `{path}`, `{line}`, `{column}` and `{match}` are replaced with the
+/// corresponding details of a match before the command is run. Depending
+/// on the configuration, the command runs once per file with a match or
+/// once per match.
+#[derive(Clone, Debug)]
+pub struct ExecBuilder {
+    config: Config,
+}
+
+impl ExecBuilder {
+    /// Return a new builder for configuring the exec sink.
+    pub fn new() -> ExecBuilder {
+        ExecBuilder { config: Config::default() }
+    }
+
+    /// Create an exec "printer" that writes dry-run output to the given
+    /// writer.
+    ///
+    /// The writer is only used when dry-run mode is enabled via
+    /// [`ExecBuilder::dry_run`]; commands that are actually run inherit this
+    /// process' stdout and stderr.
+    pub fn build<W: io::Write>(&self, wtr: W) -> Exec<W> {
+        Exec { config: self.config.clone(), wtr }
+    }
+
+    /// Set the command template to run.
+    ///
+    /// The first element is the program and the remaining elements are its
+    /// arguments. In each element, the placeholder `{path}` is replaced with
+    /// the path of the file searched (empty when there is none), `{line}`
+    /// and `{column}` with the line and column numbers at which a match
+    /// starts (`{line}` is empty when the searcher does not count lines),
+    /// and `{match}` with the matched text.
+    ///
+    /// When the template is empty (the default), nothing is run.
+    pub fn command<I, S>(&mut self, argv: I) -> &mut ExecBuilder
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.command = argv.into_iter().map(|s| s.into()).collect();
+        self
+    }
+
+    /// When enabled, the command runs once for each match found. When
+    /// disabled (the default), the command runs once for each file with at
+    /// least one match, with the match placeholders filled in from the first
+    /// match found.
+    pub fn per_match(&mut self, yes: bool) -> &mut ExecBuilder {
+        self.config.per_match = yes;
+        self
+    }
+
+    /// Set the maximum number of commands that run concurrently.
+    ///
+    /// When the limit is reached, spawning the next command first waits for
+    /// the oldest running command to exit. A limit of `0` is treated as `1`.
+    ///
+    /// This defaults to `1`, i.e., commands run one after the other.
+    pub fn max_procs(&mut self, limit: usize) -> &mut ExecBuilder {
+        self.config.max_procs = limit;
+        self
+    }
+
+    /// When enabled, commands are not run. Instead, each command line that
+    /// would have been run is written to the writer, one per line.
+    ///
+    /// This is disabled by default.
+    pub fn dry_run(&mut self, yes: bool) -> &mut ExecBuilder {
+        self.config.dry_run = yes;
+        self
+    }
+}
+
+/// An exec "printer" that runs an external command for search results
+/// instead of printing them.
+///
+/// This type is generic over `W`, which represents any implementation of
+/// the standard library `io::Write` trait. The writer only receives output
+/// in dry-run mode.
+#[derive(Debug)]
+pub struct Exec<W> {
+    config: Config,
+    wtr: W,
+}
+
+impl<W: io::Write> Exec<W> {
+    /// Return an implementation of `Sink` for the exec printer.
+    ///
+    /// This does not associate the search with any file path, so the
+    /// `{path}` placeholder expands to an empty string.
+    pub fn sink<'s, M: Matcher>(&'s mut self, matcher: M) -> ExecSink<'static, 's, M, W> {
+        ExecSink {
+            matcher,
+            exec: self,
+            path: None,
+            match_count: 0,
+            first_match: None,
+            running: VecDeque::new(),
+            failures: vec![],
+        }
+    }
+
+    /// Return an implementation of `Sink` associated with a file path.
+    ///
+    /// The `{path}` placeholder in the command template expands to the path
+    /// given.
+    pub fn sink_with_path<'p, 's, M, P>(
+        &'s mut self,
+        matcher: M,
+        path: &'p P,
+    ) -> ExecSink<'p, 's, M, W>
+    where
+        M: Matcher,
+        P: ?Sized + AsRef<Path>,
+    {
+        ExecSink {
+            matcher,
+            exec: self,
+            path: Some(path.as_ref()),
+            match_count: 0,
+            first_match: None,
+            running: VecDeque::new(),
+            failures: vec![],
+        }
+    }
+
+    /// Return a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wtr
+    }
+
+    /// Consume this printer and return back ownership of the underlying
+    /// writer.
+    pub fn into_inner(self) -> W {
+        self.wtr
+    }
+}
+
+/// The details of a single match used to fill in the command template.
+#[derive(Debug)]
+struct MatchSpec {
+    line: Option<u64>,
+    column: u64,
+    text: Vec<u8>,
+}
+
+/// An implementation of `Sink` that runs an external command for search
+/// results.
+///
+/// Commands are spawned while the search runs (subject to the concurrency
+/// limit), and `finish` waits for all of them to exit. When any command
+/// could not be spawned or exited unsuccessfully, `finish` reports an error
+/// that includes the offending command lines.
+#[derive(Debug)]
+pub struct ExecSink<'p, 's, M: Matcher, W> {
+    matcher: M,
+    exec: &'s mut Exec<W>,
+    path: Option<&'p Path>,
+    match_count: u64,
+    first_match: Option<MatchSpec>,
+    running: VecDeque<(String, Child)>,
+    failures: Vec<String>,
+}
+
+impl<'p, 's, M: Matcher, W: io::Write> ExecSink<'p, 's, M, W> {
+    /// Fill in the command template for the given match.
+    fn interpolate(&self, spec: &MatchSpec) -> Vec<String> {
+        let path = self
+            .path
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let line = spec.line.map(|n| n.to_string()).unwrap_or_default();
+        let column = spec.column.to_string();
+        let text = String::from_utf8_lossy(&spec.text).into_owned();
+        self.exec
+            .config
+            .command
+            .iter()
+            .map(|arg| {
+                arg.replace("{path}", &path)
+                    .replace("{line}", &line)
+                    .replace("{column}", &column)
+                    .replace("{match}", &text)
+            })
+            .collect()
+    }
+
+    /// Run the command for the given match, or write its command line in
+    /// dry-run mode. Spawn failures are collected and reported via `finish`.
+    fn run(&mut self, spec: &MatchSpec) -> io::Result<()> {
+        let argv = self.interpolate(spec);
+        if argv.is_empty() {
+            return Ok(());
+        }
+        let cmdline = argv.join(" ");
+        if self.exec.config.dry_run {
+            return writeln!(self.exec.wtr, "{}", cmdline);
+        }
+        let limit = std::cmp::max(1, self.exec.config.max_procs);
+        while self.running.len() >= limit {
+            self.reap_oldest();
+        }
+        let result = Command::new(&argv[0])
+            .args(&argv[1..])
+            .stdin(Stdio::null())
+            .spawn();
+        match result {
+            Ok(child) => self.running.push_back((cmdline, child)),
+            Err(err) => {
+                self.failures.push(format!("`{}`: {}", cmdline, err))
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for the oldest running command to exit and record its status if
+    /// it failed.
+    fn reap_oldest(&mut self) {
+        let Some((cmdline, mut child)) = self.running.pop_front() else {
+            return;
+        };
+        match child.wait() {
+            Ok(status) if status.success() => {}
+            Ok(status) => {
+                self.failures.push(format!("`{}`: {}", cmdline, status))
+            }
+            Err(err) => self.failures.push(format!("`{}`: {}", cmdline, err)),
+        }
+    }
+}
+
+impl<'p, 's, M: Matcher, W: io::Write> Sink for ExecSink<'p, 's, M, W> {
+    type Error = io::Error;
+
+    fn matched(
+        &mut self,
+        searcher: &Searcher,
+        mat: &SinkMatch<'_>,
+    ) -> Result<bool, io::Error> {
+        self.match_count += 1;
+        // In per-file mode, only the first match contributes placeholder
+        // values, so re-discovering matches for subsequent reports would be
+        // wasted work.
+        if !self.exec.config.per_match && self.first_match.is_some() {
+            return Ok(true);
+        }
+
+        let line_term = searcher.line_terminator().as_byte();
+        let buf = mat.buffer();
+        let range = mat.bytes_range_in_buffer();
+        let mut specs = vec![];
+        let per_match = self.exec.config.per_match;
+        find_iter_at_in_context(
+            searcher,
+            &self.matcher,
+            buf,
+            range.clone(),
+            |m| {
+                let line = mat.line_number().map(|n| {
+                    let preceding = &buf[range.start..m.start()];
+                    let newlines = preceding
+                        .iter()
+                        .filter(|&&b| b == line_term)
+                        .count();
+                    n + newlines as u64
+                });
+                let line_start = buf[..m.start()]
+                    .iter()
+                    .rposition(|&b| b == line_term)
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+                let column = (m.start() - line_start) as u64 + 1;
+                specs.push(MatchSpec {
+                    line,
+                    column,
+                    text: buf[m].to_vec(),
+                });
+                per_match
+            },
+        )?;
+        for spec in specs {
+            if per_match {
+                self.run(&spec)?;
+            } else {
+                self.first_match = Some(spec);
+                break;
+            }
+        }
+        Ok(true)
+    }
+
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        self.match_count = 0;
+        self.first_match = None;
+        Ok(true)
+    }
+
+    fn finish(
+        &mut self,
+        _searcher: &Searcher,
+        _finish: &SinkFinish,
+    ) -> Result<(), io::Error> {
+        if !self.exec.config.per_match && self.match_count > 0 {
+            let spec = self.first_match.take().unwrap_or(MatchSpec {
+                line: None,
+                column: 1,
+                text: vec![],
+            });
+            self.run(&spec)?;
+        }
+        while !self.running.is_empty() {
+            self.reap_oldest();
+        }
+        if self.failures.is_empty() {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "{} command(s) failed: {}",
+                self.failures.len(),
+                self.failures.join("; "),
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grep_regex::RegexMatcher;
+    use grep_searcher::SearcherBuilder;
+
+    use super::ExecBuilder;
+
+    const SHERLOCK: &'static str = "\
+For the Doctor Watsons of this world, as opposed to the Sherlock
+Holmeses, success in the province of detective work must always
+be, to a very large extent, the result of luck. Sherlock Holmes
+can extract a clew from a wisp of straw or a flake of cigar ash;
+but Doctor Watson has to have it taken out for him and dusted,
+and exhibited clearly, with a label attached.
+";
+
+    fn dry_run_output(exec: &mut super::Exec<Vec<u8>>) -> String {
+        String::from_utf8(exec.get_mut().to_owned()).unwrap()
+    }
+
+    #[test]
+    fn dry_run_per_match() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut exec = ExecBuilder::new()
+            .command(["edit", "{path}:{line}:{column}", "{match}"])
+            .per_match(true)
+            .dry_run(true)
+            .build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                exec.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+
+        let got = dry_run_output(&mut exec);
+        let expected = "\
+edit sherlock:1:16 Watson
+edit sherlock:5:12 Watson
+";
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn dry_run_per_file() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut exec = ExecBuilder::new()
+            .command(["edit", "{path}:{line}:{column}"])
+            .dry_run(true)
+            .build(vec![]);
+        let mut searcher = SearcherBuilder::new().build();
+        searcher
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                exec.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+        // A search without a match runs nothing.
+        searcher
+            .search_reader(
+                &matcher,
+                "no matches here\n".as_bytes(),
+                exec.sink_with_path(&matcher, "empty"),
+            )
+            .unwrap();
+
+        let got = dry_run_output(&mut exec);
+        assert_eq!("edit sherlock:1:16\n", got);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn runs_harmless_command() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut exec = ExecBuilder::new()
+            .command(["true", "{path}"])
+            .per_match(true)
+            .max_procs(4)
+            .build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                exec.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn failed_command_reported() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut exec =
+            ExecBuilder::new().command(["false", "{path}"]).build(vec![]);
+        let err = SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                exec.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("`false sherlock`"),
+            "unexpected error: {}",
+            err
+        );
+    }
+}
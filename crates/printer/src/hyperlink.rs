@@ -24,6 +24,7 @@ pub struct HyperlinkConfig(Arc<HyperlinkConfigInner>);
 struct HyperlinkConfigInner {
     env: HyperlinkEnvironment,
     format: HyperlinkFormat,
+    link_line_numbers: bool,
 }
 
 impl HyperlinkConfig {
@@ -32,7 +33,26 @@ impl HyperlinkConfig {
         env: HyperlinkEnvironment,
         format: HyperlinkFormat,
     ) -> HyperlinkConfig {
-        HyperlinkConfig(Arc::new(HyperlinkConfigInner { env, format }))
+        HyperlinkConfig(Arc::new(HyperlinkConfigInner {
+            env,
+            format,
+            link_line_numbers: false,
+        }))
+    }
+
+    /// Set whether printed line numbers should be hyperlinked.
+    ///
+    /// When enabled and a hyperlink format is active, the standard printer
+    /// wraps each printed line number (on matching and contextual lines) in
+    /// a hyperlink using the same format with that line number filled in.
+    /// The path heading keeps its existing hyperlink.
+    ///
+    /// This is disabled by default, in which case only paths (or, without
+    /// headings, each line's entire prelude) are hyperlinked.
+    pub fn link_line_numbers(self, yes: bool) -> HyperlinkConfig {
+        let mut inner = (*self.0).clone();
+        inner.link_line_numbers = yes;
+        HyperlinkConfig(Arc::new(inner))
     }
 
     /// Returns the hyperlink environment in this configuration.
@@ -44,6 +64,12 @@ impl HyperlinkConfig {
     pub(crate) fn format(&self) -> &HyperlinkFormat {
         &self.0.format
     }
+
+    /// Returns true when printed line numbers should each carry their own
+    /// hyperlink.
+    pub(crate) fn links_line_numbers(&self) -> bool {
+        self.0.link_line_numbers && !self.0.format.is_empty()
+    }
 }
 
 /// A hyperlink format with variables.
@@ -67,6 +93,8 @@ impl HyperlinkConfig {
 pub struct HyperlinkFormat {
     parts: Vec<Part>,
     is_line_dependent: bool,
+    is_column_dependent: bool,
+    path_only_parts: Vec<Part>,
 }
 
 impl HyperlinkFormat {
@@ -90,6 +118,40 @@ impl HyperlinkFormat {
     pub(crate) fn is_line_dependent(&self) -> bool {
         self.is_line_dependent
     }
+
+    /// Returns true if this format contains a `{line}` variable, and thus
+    /// requires a line number to interpolate in full.
+    ///
+    /// When a line number is unavailable (for example, in summary output
+    /// like `--files-with-matches` or `--count`), such a format degrades to
+    /// a file-level link when one can be derived from it, and otherwise no
+    /// hyperlink is emitted at all.
+    pub fn requires_line(&self) -> bool {
+        self.is_line_dependent
+    }
+
+    /// Returns true if this format contains a `{column}` variable, and thus
+    /// requires a column number to interpolate in full.
+    ///
+    /// When a line number is available but a column number is not, the
+    /// `{column}` variable is interpolated with `1` (the first column of
+    /// the line).
+    pub fn requires_column(&self) -> bool {
+        self.is_column_dependent
+    }
+
+    /// Returns the parts to use for a file-level hyperlink when this format
+    /// requires a line number but none is available.
+    ///
+    /// This returns `None` when no file-level link can be derived from the
+    /// format, in which case no hyperlink should be emitted at all.
+    pub(crate) fn path_only_parts(&self) -> Option<&[Part]> {
+        if self.path_only_parts.is_empty() {
+            None
+        } else {
+            Some(&self.path_only_parts)
+        }
+    }
 }
 
 impl std::str::FromStr for HyperlinkFormat {
@@ -371,9 +433,41 @@ impl FormatBuilder {
         Ok(HyperlinkFormat {
             parts: self.parts.clone(),
             is_line_dependent: self.parts.contains(&Part::Line),
+            is_column_dependent: self.parts.contains(&Part::Column),
+            path_only_parts: self.path_only_parts(),
         })
     }
 
+    /// Computes the parts to use for a file-level hyperlink when the format
+    /// requires a line number but none is available (e.g., in summary
+    /// output).
+    ///
+    /// This is the prefix of the format up to the first line or column
+    /// variable, with the static text separating the path from the line
+    /// number (e.g., a `:` or `#`) trimmed. When that prefix doesn't
+    /// include the `{path}` variable, it can't identify a file, so this
+    /// returns nothing and no hyperlink should be emitted at all.
+    fn path_only_parts(&self) -> Vec<Part> {
+        let mut prefix = self
+            .parts
+            .iter()
+            .take_while(|p| !matches!(p, Part::Line | Part::Column))
+            .cloned()
+            .collect::<Vec<Part>>();
+        if prefix.len() == self.parts.len() {
+            // No line or column variable, so the format itself is already a
+            // file-level link and needs no separate prefix.
+            return vec![];
+        }
+        if matches!(prefix.last(), Some(Part::Text(_))) {
+            prefix.pop();
+        }
+        if !prefix.contains(&Part::Path) {
+            prefix.clear();
+        }
+        prefix
+    }
+
     /// Validate that the format is well-formed.
     fn validate(&self) -> Result<(), HyperlinkFormatError> {
         use self::HyperlinkFormatErrorKind::*;
@@ -446,7 +540,7 @@ impl FormatBuilder {
 /// A sequence of these corresponds to a complete format. (Not all sequences
 /// are valid.)
 #[derive(Clone, Debug, Eq, PartialEq)]
-enum Part {
+pub(crate) enum Part {
     /// Static text.
     ///
     /// We use `Vec<u8>` here (and more generally treat a format string as a
@@ -484,6 +578,9 @@ impl Part {
             ),
             Part::Path => dest.extend_from_slice(&values.path.0),
             Part::Line => {
+                // A missing line number degrades to a file-level link (or
+                // to no link) before interpolation, so this is only reached
+                // when a line number is available.
                 let line = DecimalFormatter::new(values.line.unwrap_or(1));
                 dest.extend_from_slice(line.as_bytes());
             }
@@ -532,7 +629,9 @@ impl<'a> Values<'a> {
     /// Sets the line number for these values.
     ///
     /// If a line number is not set and a hyperlink format contains a `{line}`
-    /// variable, then it is interpolated with the value of `1` automatically.
+    /// variable, then the hyperlink degrades to a file-level link derived
+    /// from the format (or to no hyperlink at all when the format has no
+    /// such prefix). A placeholder line number is never interpolated.
     pub(crate) fn line(mut self, line: Option<u64>) -> Values<'a> {
         self.line = line;
         self
@@ -590,9 +689,23 @@ impl Interpolator {
         {
             return Ok(InterpolatorStatus::inactive());
         }
+        let format = self.config.format();
+        // When the format requires a line number but none is available (as
+        // in summary output like `--files-with-matches` or `--count`),
+        // degrade to a file-level link when one can be derived from the
+        // format, and emit no hyperlink at all otherwise. A placeholder
+        // line number is never interpolated.
+        let parts = if format.requires_line() && values.line.is_none() {
+            match format.path_only_parts() {
+                Some(parts) => parts,
+                None => return Ok(InterpolatorStatus::inactive()),
+            }
+        } else {
+            &*format.parts
+        };
         let mut buf = self.buf.borrow_mut();
         buf.clear();
-        for part in self.config.format().parts.iter() {
+        for part in parts.iter() {
             part.interpolate_to(self.config.environment(), values, &mut buf);
         }
         let spec = HyperlinkSpec::open(&buf);
@@ -1006,4 +1119,46 @@ mod tests {
             err(InvalidVariable("bar{{".to_string())),
         );
     }
+
+    #[test]
+    fn required_variables() {
+        let format = HyperlinkFormat::from_str("vscode").unwrap();
+        assert!(format.requires_line());
+        assert!(format.requires_column());
+
+        let format = HyperlinkFormat::from_str("kitty").unwrap();
+        assert!(format.requires_line());
+        assert!(!format.requires_column());
+
+        let format = HyperlinkFormat::from_str("file://{path}").unwrap();
+        assert!(!format.requires_line());
+        assert!(!format.requires_column());
+    }
+
+    #[test]
+    fn path_only_parts() {
+        let path_only = |format: &str| {
+            HyperlinkFormat::from_str(format).unwrap().path_only_parts().map(
+                |parts| {
+                    parts.iter().map(|p| p.to_string()).collect::<String>()
+                },
+            )
+        };
+        // The static text separating the path from the line number is
+        // trimmed along with the line and column variables.
+        assert_eq!(
+            path_only("vscode"),
+            Some("vscode://file{path}".to_string())
+        );
+        assert_eq!(
+            path_only("kitty"),
+            Some("file://{host}{path}".to_string())
+        );
+        assert_eq!(path_only("grep+"), Some("grep+://{path}".to_string()));
+        // When the line number precedes the path, there is no usable
+        // file-level prefix, so the hyperlink is suppressed entirely.
+        assert_eq!(path_only("foo://{line}/{path}"), None);
+        // A format without a line number needs no separate prefix.
+        assert_eq!(path_only("file://{path}"), None);
+    }
 }
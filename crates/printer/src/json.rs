@@ -1,7 +1,7 @@
 use std::{
     io::{self, Write},
     path::Path,
-    time::Instant,
+    time::Duration,
 };
 
 use {
@@ -13,7 +13,10 @@ use {
 };
 
 use crate::{
-    counter::CounterWriter, jsont, stats::Stats, util::find_iter_at_in_context,
+    counter::CounterWriter,
+    jsont,
+    stats::Stats,
+    util::{find_iter_at_in_context, TimeSource},
 };
 
 /// The configuration for the JSON printer.
@@ -25,15 +28,47 @@ use crate::{
 struct Config {
     pretty: bool,
     max_matches: Option<u64>,
+    max_matches_per_line: Option<usize>,
     always_begin_end: bool,
+    report_binary_skips: bool,
+    emit_meta: bool,
+    inline_context: Option<usize>,
+    stats_precision: Option<u8>,
+    omit_elapsed: bool,
+    slow_file_threshold: Option<Duration>,
+    file_metadata: bool,
+    time_source: TimeSource,
+    output_bom: bool,
 }
 
 impl Default for Config {
     fn default() -> Config {
-        Config { pretty: false, max_matches: None, always_begin_end: false }
+        Config {
+            pretty: false,
+            max_matches: None,
+            max_matches_per_line: None,
+            always_begin_end: false,
+            report_binary_skips: false,
+            emit_meta: false,
+            inline_context: None,
+            stats_precision: None,
+            omit_elapsed: false,
+            slow_file_threshold: None,
+            file_metadata: false,
+            time_source: TimeSource::default(),
+            output_bom: false,
+        }
     }
 }
 
+/// The version of the JSON schema emitted by this printer.
+///
+/// This is reported in the `meta` message (see [`JSONBuilder::emit_meta`])
+/// and should be bumped whenever the shape of any message changes, e.g.,
+/// when a field is added to an existing message type or a new message type
+/// is introduced.
+const SCHEMA_VERSION: u64 = 3;
+
 /// A builder for a JSON lines printer.
 ///
 /// The builder permits configuring how the printer behaves. The JSON printer
@@ -63,6 +98,7 @@ impl JSONBuilder {
             config: self.config.clone(),
             wtr: CounterWriter::new(wtr),
             matches: vec![],
+            meta_printed: false,
         }
     }
 
@@ -77,6 +113,29 @@ impl JSONBuilder {
         self
     }
 
+    /// Include file metadata in each `begin` message.
+    ///
+    /// When enabled, the `begin` message's data object gains `size`,
+    /// `modified` and `file_type` fields describing the file being searched.
+    /// `size` is the length of the file in bytes, `modified` is the file's
+    /// last modification time as an RFC 3339 timestamp in UTC and
+    /// `file_type` is one of `file`, `symlink` or `other`. This saves
+    /// consumers of the stream from having to stat each file themselves.
+    ///
+    /// The metadata comes from a single `std::fs::metadata` call per file,
+    /// unless the caller supplied metadata it already had via
+    /// [`JSON::sink_with_path_and_metadata`], in which case no call is made
+    /// at all. Since `std::fs::metadata` follows symbolic links, `symlink`
+    /// is only ever reported for caller supplied metadata. When the
+    /// metadata can't be read (e.g., the file disappeared between the walk
+    /// and the search), the fields are serialized as `null`.
+    ///
+    /// This is disabled by default.
+    pub fn file_metadata(&mut self, yes: bool) -> &mut JSONBuilder {
+        self.config.file_metadata = yes;
+        self
+    }
+
     /// Set the maximum amount of matches that are printed.
     ///
     /// If multi line search is enabled and a match spans multiple lines, then
@@ -87,6 +146,23 @@ impl JSONBuilder {
         self
     }
 
+    /// Set the maximum number of submatches reported for each match message.
+    ///
+    /// Pathological inputs such as minified files can have thousands of
+    /// matches on a single line, which bloats the `submatches` array. When
+    /// this is set, only the first `limit` submatches are emitted and the
+    /// message gains a `submatches_omitted` field with the number left out.
+    /// Match counts reported in statistics remain accurate.
+    ///
+    /// By default, there is no limit.
+    pub fn max_matches_per_line(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut JSONBuilder {
+        self.config.max_matches_per_line = limit;
+        self
+    }
+
     /// When enabled, the `begin` and `end` messages are always emitted, even
     /// when no match is found.
     ///
@@ -98,6 +174,146 @@ impl JSONBuilder {
         self.config.always_begin_end = yes;
         self
     }
+
+    /// Report files that were skipped because binary data was detected.
+    ///
+    /// When enabled and the searcher's binary detection method is to quit,
+    /// a search that stopped on binary data without reporting any matches
+    /// emits a `binary_skip` message. Its data object has `path` and
+    /// `binary_offset` fields. This message is emitted even when the
+    /// `begin` and `end` messages are suppressed for lack of a match, so
+    /// consumers can tell a clean file apart from one that was never fully
+    /// searched.
+    ///
+    /// This is disabled by default.
+    pub fn report_binary_skips(&mut self, yes: bool) -> &mut JSONBuilder {
+        self.config.report_binary_skips = yes;
+        self
+    }
+
+    /// When enabled, the first message written by the printer is a `meta`
+    /// message describing the schema of the output. It is emitted exactly
+    /// once per printer, regardless of how many searches are executed with
+    /// it. Its `data` payload has the following fields:
+    ///
+    /// * `schema_version` - The version of the schema, as also reported by
+    ///   [`JSON::schema_version`].
+    /// * `printer` - The name of the crate producing the output.
+    /// * `features` - The names of enabled configuration options that alter
+    ///   the shape of subsequent messages.
+    ///
+    /// This is disabled by default, in which case the output is byte
+    /// identical to what was produced before this option existed.
+    pub fn emit_meta(&mut self, yes: bool) -> &mut JSONBuilder {
+        self.config.emit_meta = yes;
+        self
+    }
+
+    /// When set, contextual lines are embedded directly into `match`
+    /// messages instead of being emitted as standalone `context` messages.
+    ///
+    /// Each `match` message gains `before_context` and `after_context`
+    /// arrays with at most the given number of lines on each side. Each
+    /// entry carries the line's `line_number` and its text (or bytes) like
+    /// a normal `context` message, but without submatch information. The
+    /// number of contextual lines reported is still bounded by the
+    /// searcher's own context configuration. When the contextual lines of
+    /// adjacent matches overlap, the shared lines are repeated in each
+    /// match message.
+    ///
+    /// Since the trailing context of a match is only known once subsequent
+    /// lines have been seen, enabling this defers each match message until
+    /// its trailing context is complete or the search ends.
+    ///
+    /// This is disabled by default.
+    pub fn inline_context(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut JSONBuilder {
+        self.config.inline_context = limit;
+        self
+    }
+
+    /// Set the number of fractional digits used when rendering the `elapsed`
+    /// duration in `stats` objects.
+    ///
+    /// When set, the `nanos` field is rounded to the given number of
+    /// fractional digits of a second, and the `human` rendering uses exactly
+    /// that many digits after the decimal point. This reduces the noise in
+    /// output that is diffed across runs, at the expense of timing
+    /// resolution.
+    ///
+    /// By default, no rounding is performed.
+    pub fn stats_precision(
+        &mut self,
+        precision: Option<u8>,
+    ) -> &mut JSONBuilder {
+        self.config.stats_precision = precision;
+        self
+    }
+
+    /// When enabled, the `elapsed` field is left out of `stats` objects
+    /// entirely.
+    ///
+    /// The elapsed time is the only non-deterministic piece of this
+    /// printer's output. With it omitted, repeated searches of the same data
+    /// with the same configuration produce byte identical output, which is
+    /// useful for tests and CI pipelines that compare output across runs.
+    ///
+    /// This is disabled by default.
+    pub fn omit_elapsed(&mut self, yes: bool) -> &mut JSONBuilder {
+        self.config.omit_elapsed = yes;
+        self
+    }
+
+    /// When enabled, write a UTF-8 byte order mark before the first byte of
+    /// output.
+    ///
+    /// This exists for parity with the standard and summary printers, where
+    /// it helps Windows tools that misinterpret UTF-8 output without a BOM.
+    /// Note that a BOM is not legal at the start of a JSON text, so most
+    /// consumers of this printer's output should leave this disabled. When
+    /// enabled, the BOM is written exactly once per printer, immediately
+    /// before the first message, and not at all when no messages are
+    /// emitted.
+    ///
+    /// This is disabled by default.
+    pub fn output_bom(&mut self, yes: bool) -> &mut JSONBuilder {
+        self.config.output_bom = yes;
+        self
+    }
+
+    /// Set a threshold above which a search is reported as slow.
+    ///
+    /// When set, a message with `"type":"slow_file"` is emitted at the end
+    /// of any single search whose elapsed time exceeds the threshold, just
+    /// before the corresponding `end` message. It reports the path, the
+    /// elapsed time, the number of bytes searched and the search rate in
+    /// bytes per second.
+    ///
+    /// This is disabled by default.
+    pub fn slow_file_threshold(
+        &mut self,
+        threshold: Option<Duration>,
+    ) -> &mut JSONBuilder {
+        self.config.slow_file_threshold = threshold;
+        self
+    }
+
+    /// Set the source of time used to measure how long each search took.
+    ///
+    /// The function given should report the time elapsed since some fixed
+    /// origin of its own choosing; only differences between readings are
+    /// used. The default reads the system's monotonic clock. This exists
+    /// principally so that tests can exercise slow file reporting
+    /// deterministically.
+    pub fn time_source<F>(&mut self, f: F) -> &mut JSONBuilder
+    where
+        F: Fn() -> Duration + Send + Sync + 'static,
+    {
+        self.config.time_source = TimeSource::new(f);
+        self
+    }
 }
 
 /// The JSON printer, which emits results in a JSON lines format.
@@ -194,6 +410,13 @@ impl JSONBuilder {
 /// This section documents the wire format emitted by this printer, starting
 /// with the four types of messages.
 ///
+/// Within every message and object, fields are always serialized in the
+/// order in which they are documented here. Consumers may rely on this
+/// ordering: given identical search results and printer configuration, the
+/// output is deterministic, except for the elapsed durations reported in
+/// [`stats` objects](#object-stats). (See [`JSONBuilder::omit_elapsed`] for
+/// making the output fully deterministic.)
+///
 /// Each message has its own format, and is contained inside an envelope that
 /// indicates the type of message. The envelope has these fields:
 ///
@@ -248,8 +471,16 @@ impl JSONBuilder {
 /// * **line_number** - If the searcher has been configured to report line
 ///   numbers, then this corresponds to the line number of the first line
 ///   in `lines`. If no line numbers are available, then this is `null`.
+/// * **line_number_approximate** - This field is only present, and set to
+///   `true`, when the searcher converted at least one binary byte to a line
+///   terminator before this match was found. In that case, `line_number` may
+///   not correspond to the line a user sees when opening the raw contents of
+///   the file in an editor.
 /// * **absolute_offset** - The absolute byte offset corresponding to the start
-///   of `lines` in the data being searched.
+///   of `lines` in the data being searched. If the searcher transcoded the
+///   data before searching (e.g., because of a UTF-16 byte-order mark), then
+///   this is an offset into the transcoded data, exactly as reported by the
+///   standard printer's byte offsets.
 /// * **submatches** - An array of [`submatch` objects](#object-submatch)
 ///   corresponding to matches in `lines`. The offsets included in each
 ///   `submatch` correspond to byte offsets into `lines`. (If `lines` is base64
@@ -257,14 +488,24 @@ impl JSONBuilder {
 ///   decoding.) The `submatch` objects are guaranteed to be sorted by their
 ///   starting offsets. Note that it is possible for this array to be empty,
 ///   for example, when searching reports inverted matches.
+/// * **submatches_omitted** - This field is only present, and set to the
+///   number of submatches left out, when the printer was configured with a
+///   per-line submatch limit via [`JSONBuilder::max_matches_per_line`] and
+///   the limit was exceeded.
+/// * **before_context**, **after_context** - These fields are only present
+///   when the printer was configured to embed contextual lines into match
+///   messages via [`JSONBuilder::inline_context`]. Each is an array of
+///   objects with `lines` and `line_number` fields describing the
+///   contextual lines surrounding the match. When these fields are present,
+///   standalone `context` messages are suppressed.
 ///
 /// #### Message: **context**
 ///
 /// This message indicates that a contextual line has been found. A contextual
 /// line is a line that doesn't contain a match, but is generally adjacent to
 /// a line that does contain a match. The precise way in which contextual lines
-/// are reported is determined by the searcher. It has these fields, which are
-/// exactly the same fields found in a [`match`](#message-match):
+/// are reported is determined by the searcher. It has these fields, most of
+/// which are exactly the same fields found in a [`match`](#message-match):
 ///
 /// * **path** - An
 ///   [arbitrary data object](#object-arbitrary-data)
@@ -279,6 +520,9 @@ impl JSONBuilder {
 ///   in `lines`. If no line numbers are available, then this is `null`.
 /// * **absolute_offset** - The absolute byte offset corresponding to the start
 ///   of `lines` in the data being searched.
+/// * **kind** - One of the strings `before`, `after` or `other`, indicating
+///   whether this contextual line appeared before a match, after a match, or
+///   in some other configuration (e.g., via the "passthru" feature).
 /// * **submatches** - An array of [`submatch` objects](#object-submatch)
 ///   corresponding to matches in `lines`. The offsets included in each
 ///   `submatch` correspond to byte offsets into `lines`. (If `lines` is base64
@@ -315,7 +559,9 @@ impl JSONBuilder {
 /// a search. It has these fields:
 ///
 /// * **elapsed** - A [`duration` object](#object-duration) describing the
-///   length of time that elapsed while performing the search.
+///   length of time that elapsed while performing the search. This field is
+///   left out entirely when the printer is configured with
+///   [`JSONBuilder::omit_elapsed`].
 /// * **searches** - The number of searches that have run. For this printer,
 ///   this value is always `1`. (Implementations may emit additional message
 ///   types that use this same `stats` object that represents summary
@@ -331,6 +577,10 @@ impl JSONBuilder {
 /// * **matches** - The total number of matches. There may be multiple matches
 ///   per line. When matches may contain multiple lines, each match is counted
 ///   only once, regardless of how many lines it spans.
+/// * **retries** - The total number of retried searches. This printer never
+///   retries a search itself, so this is `0` unless the application driving
+///   the printer retries searches (e.g., because of transient I/O errors) and
+///   records them here.
 ///
 /// #### Object: **duration**
 ///
@@ -346,6 +596,10 @@ impl JSONBuilder {
 /// * **human** - A human readable string describing the length of the
 ///   duration. The format of the string is itself unspecified.
 ///
+/// When the printer is configured with [`JSONBuilder::stats_precision`],
+/// both `nanos` and `human` are rounded to the configured number of
+/// fractional digits of a second.
+///
 /// #### Object: **arbitrary data**
 ///
 /// This object is used whenever arbitrary data needs to be represented as a
@@ -415,6 +669,7 @@ impl JSONBuilder {
 ///     "lines": {"text": "can extract a clew from a wisp of straw or a flake of cigar ash;\n"},
 ///     "line_number": 4,
 ///     "absolute_offset": 193,
+///     "kind": "before",
 ///     "submatches": []
 ///   }
 /// }
@@ -442,7 +697,8 @@ impl JSONBuilder {
 ///       "bytes_searched": 367,
 ///       "bytes_printed": 1151,
 ///       "matched_lines": 2,
-///       "matches": 2
+///       "matches": 2,
+///       "retries": 0
 ///     }
 ///   }
 /// }
@@ -452,6 +708,7 @@ pub struct JSON<W> {
     config: Config,
     wtr: CounterWriter<W>,
     matches: Vec<Match>,
+    meta_printed: bool,
 }
 
 impl<W: io::Write> JSON<W> {
@@ -469,16 +726,20 @@ impl<W: io::Write> JSON<W> {
         &'s mut self,
         matcher: M,
     ) -> JSONSink<'static, 's, M, W> {
+        let start_time = self.config.time_source.now();
         JSONSink {
             matcher,
             json: self,
             path: None,
-            start_time: Instant::now(),
+            metadata: None,
+            start_time,
             match_count: 0,
             after_context_remaining: 0,
             binary_byte_offset: None,
             begin_printed: false,
             stats: Stats::new(),
+            pending: None,
+            before_context: vec![],
         }
     }
 
@@ -495,16 +756,42 @@ impl<W: io::Write> JSON<W> {
         M: Matcher,
         P: ?Sized + AsRef<Path>,
     {
+        self.sink_with_path_and_metadata(matcher, path, None)
+    }
+
+    /// Like [`sink_with_path`](JSON::sink_with_path), but additionally
+    /// associates the sink with metadata the caller already has for the
+    /// file, e.g., from a directory walk.
+    ///
+    /// This only matters when [`JSONBuilder::file_metadata`] is enabled: the
+    /// supplied metadata is used for the `begin` message instead of calling
+    /// `std::fs::metadata`, which both avoids a redundant stat and permits
+    /// reporting a `file_type` of `symlink` when the metadata came from a
+    /// call that doesn't follow symbolic links.
+    pub fn sink_with_path_and_metadata<'p, 's, M, P>(
+        &'s mut self,
+        matcher: M,
+        path: &'p P,
+        metadata: Option<&'p std::fs::Metadata>,
+    ) -> JSONSink<'p, 's, M, W>
+    where
+        M: Matcher,
+        P: ?Sized + AsRef<Path>,
+    {
+        let start_time = self.config.time_source.now();
         JSONSink {
             matcher,
             json: self,
             path: Some(path.as_ref()),
-            start_time: Instant::now(),
+            metadata,
+            start_time,
             match_count: 0,
             after_context_remaining: 0,
             binary_byte_offset: None,
             begin_printed: false,
             stats: Stats::new(),
+            pending: None,
+            before_context: vec![],
         }
     }
 
@@ -514,6 +801,20 @@ impl<W: io::Write> JSON<W> {
         &mut self,
         message: &jsont::Message<'_>,
     ) -> io::Result<()> {
+        if self.config.output_bom && !self.has_written() {
+            self.wtr.write_all("\u{FEFF}".as_bytes())?;
+        }
+        if self.config.emit_meta && !self.meta_printed {
+            // Set the flag before writing, since the meta message itself is
+            // written through this very routine.
+            self.meta_printed = true;
+            let meta = jsont::Meta {
+                schema_version: SCHEMA_VERSION,
+                printer: "grep-printer",
+                features: self.schema_features(),
+            };
+            self.write_message(&jsont::Message::Meta(meta))?;
+        }
         if self.config.pretty {
             json::to_writer_pretty(&mut self.wtr, message)?;
         } else {
@@ -522,9 +823,47 @@ impl<W: io::Write> JSON<W> {
         self.wtr.write(&[b'\n'])?;
         Ok(())
     }
+
+    /// Returns the names of enabled configuration options that alter the
+    /// shape of the messages emitted by this printer.
+    fn schema_features(&self) -> Vec<&'static str> {
+        let mut features = vec![];
+        if self.config.max_matches_per_line.is_some() {
+            // Match messages may carry a `submatches_omitted` field.
+            features.push("max_matches_per_line");
+        }
+        if self.config.inline_context.is_some() {
+            // Match messages carry `before_context` and `after_context`
+            // fields and standalone `context` messages are suppressed.
+            features.push("inline_context");
+        }
+        if self.config.omit_elapsed {
+            // Stats objects have no `elapsed` field.
+            features.push("omit_elapsed");
+        }
+        if self.config.slow_file_threshold.is_some() {
+            // Slow searches emit a `slow_file` message before `end`.
+            features.push("slow_file_threshold");
+        }
+        if self.config.file_metadata {
+            // Begin messages carry `size`, `modified` and `file_type`
+            // fields.
+            features.push("file_metadata");
+        }
+        features
+    }
 }
 
 impl<W> JSON<W> {
+    /// Returns the version of the JSON schema emitted by this printer.
+    ///
+    /// The version is bumped whenever the shape of any message changes. It
+    /// is reported in the `meta` message when [`JSONBuilder::emit_meta`] is
+    /// enabled.
+    pub const fn schema_version() -> u64 {
+        SCHEMA_VERSION
+    }
+
     /// Returns true if and only if this printer has written at least one byte
     /// to the underlying writer during any of the previous searches.
     pub fn has_written(&self) -> bool {
@@ -561,12 +900,59 @@ pub struct JSONSink<'p, 's, M: Matcher, W> {
     matcher: M,
     json: &'s mut JSON<W>,
     path: Option<&'p Path>,
-    start_time: Instant,
+    metadata: Option<&'p std::fs::Metadata>,
+    start_time: Duration,
     match_count: u64,
     after_context_remaining: u64,
     binary_byte_offset: Option<u64>,
     begin_printed: bool,
     stats: Stats,
+    pending: Option<PendingMatch>,
+    before_context: Vec<PendingLine>,
+}
+
+/// A match message that has been deferred until its trailing context has
+/// been collected, along with the contextual lines collected so far.
+///
+/// This is only used when [`JSONBuilder::inline_context`] is enabled, in
+/// which case the data reported to the sink must be copied since it only
+/// lives for the duration of the `matched` or `context` call.
+#[derive(Debug)]
+struct PendingMatch {
+    lines: Vec<u8>,
+    line_number: Option<u64>,
+    line_number_approximate: bool,
+    absolute_offset: u64,
+    submatches: Vec<(usize, usize)>,
+    submatches_omitted: usize,
+    before: Vec<PendingLine>,
+    after: Vec<PendingLine>,
+}
+
+impl PendingMatch {
+    /// Returns the absolute offset one past the end of this match and any
+    /// trailing contextual lines collected so far.
+    fn end_offset(&self) -> u64 {
+        match self.after.last() {
+            Some(line) => line.end_offset(),
+            None => self.absolute_offset + self.lines.len() as u64,
+        }
+    }
+}
+
+/// A contextual line buffered for inclusion in a deferred match message.
+#[derive(Clone, Debug)]
+struct PendingLine {
+    bytes: Vec<u8>,
+    line_number: Option<u64>,
+    absolute_offset: u64,
+}
+
+impl PendingLine {
+    /// Returns the absolute offset one past the end of this line.
+    fn end_offset(&self) -> u64 {
+        self.absolute_offset + self.bytes.len() as u64
+    }
 }
 
 impl<'p, 's, M: Matcher, W: io::Write> JSONSink<'p, 's, M, W> {
@@ -669,12 +1055,86 @@ impl<'p, 's, M: Matcher, W: io::Write> JSONSink<'p, 's, M, W> {
         self.match_count > limit
     }
 
+    /// Write the pending match message, if one exists.
+    ///
+    /// This is only used when [`JSONBuilder::inline_context`] is enabled.
+    fn flush_pending(&mut self) -> io::Result<()> {
+        let Some(pending) = self.pending.take() else { return Ok(()) };
+        let submatches: Vec<jsont::SubMatch<'_>> = pending
+            .submatches
+            .iter()
+            .map(|&(start, end)| jsont::SubMatch {
+                m: &pending.lines[start..end],
+                start,
+                end,
+            })
+            .collect();
+        fn context_lines(
+            lines: &[PendingLine],
+        ) -> Vec<jsont::ContextLine<'_>> {
+            lines
+                .iter()
+                .map(|line| jsont::ContextLine {
+                    lines: &line.bytes,
+                    line_number: line.line_number,
+                })
+                .collect()
+        }
+        let msg = jsont::Message::Match(jsont::Match {
+            path: self.path,
+            lines: &pending.lines,
+            line_number: pending.line_number,
+            line_number_approximate: pending.line_number_approximate,
+            absolute_offset: pending.absolute_offset,
+            submatches: &submatches,
+            submatches_omitted: pending.submatches_omitted,
+            before_context: Some(context_lines(&pending.before)),
+            after_context: Some(context_lines(&pending.after)),
+        });
+        self.json.write_message(&msg)
+    }
+
+    /// Buffer a contextual line for inclusion in the `before_context` of
+    /// the next match message.
+    ///
+    /// If the line isn't adjacent to the lines already buffered, then the
+    /// buffer is restarted, since only lines contiguous with a match belong
+    /// to its context.
+    fn push_before_context(&mut self, line: PendingLine) {
+        let limit = self.json.config.inline_context.unwrap();
+        if let Some(last) = self.before_context.last() {
+            if last.end_offset() != line.absolute_offset {
+                self.before_context.clear();
+            }
+        }
+        self.before_context.push(line);
+        if self.before_context.len() > limit {
+            self.before_context.remove(0);
+        }
+    }
+
     /// Write the "begin" message.
     fn write_begin_message(&mut self) -> io::Result<()> {
         if self.begin_printed {
             return Ok(());
         }
-        let msg = jsont::Message::Begin(jsont::Begin { path: self.path });
+        let metadata = if !self.json.config.file_metadata {
+            None
+        } else {
+            let owned;
+            let md = match self.metadata {
+                Some(md) => Some(md),
+                None => {
+                    owned = self
+                        .path
+                        .and_then(|path| std::fs::metadata(path).ok());
+                    owned.as_ref()
+                }
+            };
+            Some(file_metadata(md))
+        };
+        let msg =
+            jsont::Message::Begin(jsont::Begin { path: self.path, metadata });
         self.json.write_message(&msg)?;
         self.begin_printed = true;
         Ok(())
@@ -692,14 +1152,13 @@ impl<'p, 's, M: Matcher, W: io::Write> Sink for JSONSink<'p, 's, M, W> {
         self.write_begin_message()?;
 
         self.match_count += 1;
-        // When we've exceeded our match count, then the remaining context
-        // lines should not be reset, but instead, decremented. This avoids a
-        // bug where we display more matches than a configured limit. The main
-        // idea here is that 'matched' might be called again while printing
-        // an after-context line. In that case, we should treat this as a
-        // contextual line rather than a matching line for the purposes of
-        // termination.
-        if self.match_more_than_limit() {
+        // Once we've exceeded our match count, any further match can only be
+        // reported because it falls within the after-context window of a
+        // previous match. Treat it as a contextual line for the purposes of
+        // termination (decrementing the remaining window instead of
+        // resetting it), as otherwise we would display more matches than a
+        // configured limit.
+        if self.match_more_than_limit() && mat.is_context_extension() {
             self.after_context_remaining =
                 self.after_context_remaining.saturating_sub(1);
         } else {
@@ -714,13 +1173,62 @@ impl<'p, 's, M: Matcher, W: io::Write> Sink for JSONSink<'p, 's, M, W> {
         self.stats.add_matches(self.json.matches.len() as u64);
         self.stats.add_matched_lines(mat.lines().count() as u64);
 
+        if self.json.config.inline_context.is_some() {
+            self.flush_pending()?;
+            let mut spans: Vec<(usize, usize)> = self
+                .json
+                .matches
+                .iter()
+                .map(|m| (m.start(), m.end()))
+                .collect();
+            let mut submatches_omitted = 0;
+            if let Some(limit) = self.json.config.max_matches_per_line {
+                if spans.len() > limit {
+                    submatches_omitted = spans.len() - limit;
+                    spans.truncate(limit);
+                }
+            }
+            // Contextual lines only belong to this match's `before_context`
+            // when they directly precede it.
+            let mut before = std::mem::take(&mut self.before_context);
+            let contiguous = before.last().map_or(false, |line| {
+                line.end_offset() == mat.absolute_byte_offset()
+            });
+            if !contiguous {
+                before.clear();
+            }
+            self.pending = Some(PendingMatch {
+                lines: mat.bytes().to_vec(),
+                line_number: mat.line_number(),
+                line_number_approximate: mat.approximate_line_number(),
+                absolute_offset: mat.absolute_byte_offset(),
+                submatches: spans,
+                submatches_omitted,
+                before,
+                after: vec![],
+            });
+            return Ok(!self.should_quit());
+        }
+
         let submatches = SubMatches::new(mat.bytes(), &self.json.matches);
+        let mut subs = submatches.as_slice();
+        let mut submatches_omitted = 0;
+        if let Some(limit) = self.json.config.max_matches_per_line {
+            if subs.len() > limit {
+                submatches_omitted = subs.len() - limit;
+                subs = &subs[..limit];
+            }
+        }
         let msg = jsont::Message::Match(jsont::Match {
             path: self.path,
             lines: mat.bytes(),
             line_number: mat.line_number(),
+            line_number_approximate: mat.approximate_line_number(),
             absolute_offset: mat.absolute_byte_offset(),
-            submatches: submatches.as_slice(),
+            submatches: subs,
+            submatches_omitted,
+            before_context: None,
+            after_context: None,
         });
         self.json.write_message(&msg)?;
         Ok(!self.should_quit())
@@ -738,17 +1246,51 @@ impl<'p, 's, M: Matcher, W: io::Write> Sink for JSONSink<'p, 's, M, W> {
             self.after_context_remaining =
                 self.after_context_remaining.saturating_sub(1);
         }
+        if let Some(limit) = self.json.config.inline_context {
+            let line = PendingLine {
+                bytes: ctx.bytes().to_vec(),
+                line_number: ctx.line_number(),
+                absolute_offset: ctx.absolute_byte_offset(),
+            };
+            let absorbed = match self.pending {
+                Some(ref mut pending)
+                    if pending.after.len() < limit
+                        && pending.end_offset() == line.absolute_offset =>
+                {
+                    pending.after.push(line.clone());
+                    true
+                }
+                _ => false,
+            };
+            if !absorbed {
+                // The previous match, if any, can't collect any more
+                // trailing context, so emit it now.
+                self.flush_pending()?;
+            }
+            // A line may fall in both the trailing context of the previous
+            // match and the leading context of the next one, so it is also
+            // buffered for the next match.
+            self.push_before_context(line);
+            return Ok(!self.should_quit());
+        }
+
         let submatches = if searcher.invert_match() {
             self.record_matches(searcher, ctx.bytes(), 0..ctx.bytes().len())?;
             SubMatches::new(ctx.bytes(), &self.json.matches)
         } else {
             SubMatches::empty()
         };
+        let kind = match *ctx.kind() {
+            SinkContextKind::Before => "before",
+            SinkContextKind::After => "after",
+            SinkContextKind::Other => "other",
+        };
         let msg = jsont::Message::Context(jsont::Context {
             path: self.path,
             lines: ctx.bytes(),
             line_number: ctx.line_number(),
             absolute_offset: ctx.absolute_byte_offset(),
+            kind,
             submatches: submatches.as_slice(),
         });
         self.json.write_message(&msg)?;
@@ -774,10 +1316,12 @@ impl<'p, 's, M: Matcher, W: io::Write> Sink for JSONSink<'p, 's, M, W> {
 
     fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
         self.json.wtr.reset_count();
-        self.start_time = Instant::now();
+        self.start_time = self.json.config.time_source.now();
         self.match_count = 0;
         self.after_context_remaining = 0;
         self.binary_byte_offset = None;
+        self.pending = None;
+        self.before_context.clear();
         if self.json.config.max_matches == Some(0) {
             return Ok(false);
         }
@@ -791,15 +1335,33 @@ impl<'p, 's, M: Matcher, W: io::Write> Sink for JSONSink<'p, 's, M, W> {
 
     fn finish(
         &mut self,
-        _searcher: &Searcher,
+        searcher: &Searcher,
         finish: &SinkFinish,
     ) -> Result<(), io::Error> {
+        // This is reported even when the begin/end envelope was suppressed
+        // for lack of a match: a skipped file would otherwise leave no trace
+        // in the stream at all.
+        if self.json.config.report_binary_skips
+            && self.match_count == 0
+            && searcher.binary_detection().quit_byte().is_some()
+        {
+            if let Some(offset) = finish.binary_byte_offset() {
+                let msg = jsont::Message::BinarySkip(jsont::BinarySkip {
+                    path: self.path,
+                    binary_offset: offset,
+                });
+                self.json.write_message(&msg)?;
+            }
+        }
         if !self.begin_printed {
             return Ok(());
         }
+        self.flush_pending()?;
 
         self.binary_byte_offset = finish.binary_byte_offset();
-        self.stats.add_elapsed(self.start_time.elapsed());
+        let elapsed =
+            self.json.config.time_source.now().saturating_sub(self.start_time);
+        self.stats.add_elapsed(elapsed);
         self.stats.add_searches(1);
         if self.match_count > 0 {
             self.stats.add_searches_with_match(1);
@@ -807,10 +1369,25 @@ impl<'p, 's, M: Matcher, W: io::Write> Sink for JSONSink<'p, 's, M, W> {
         self.stats.add_bytes_searched(finish.byte_count());
         self.stats.add_bytes_printed(self.json.wtr.count());
 
+        if let Some(threshold) = self.json.config.slow_file_threshold {
+            if elapsed > threshold {
+                let msg = jsont::Message::SlowFile(jsont::SlowFile {
+                    path: self.path,
+                    elapsed,
+                    precision: self.json.config.stats_precision,
+                    bytes_searched: finish.byte_count(),
+                });
+                self.json.write_message(&msg)?;
+            }
+        }
         let msg = jsont::Message::End(jsont::End {
             path: self.path,
             binary_offset: finish.binary_byte_offset(),
-            stats: self.stats.clone(),
+            stats: jsont::Stats {
+                stats: self.stats.clone(),
+                precision: self.json.config.stats_precision,
+                omit_elapsed: self.json.config.omit_elapsed,
+            },
         });
         self.json.write_message(&msg)?;
         Ok(())
@@ -867,6 +1444,61 @@ impl<'a> SubMatches<'a> {
     }
 }
 
+/// Converts file metadata into its JSON representation.
+///
+/// `None` means the metadata couldn't be read, in which case every field is
+/// `null`.
+fn file_metadata(md: Option<&std::fs::Metadata>) -> jsont::FileMetadata {
+    let Some(md) = md else {
+        return jsont::FileMetadata {
+            size: None,
+            modified: None,
+            file_type: None,
+        };
+    };
+    let ft = md.file_type();
+    let file_type = if ft.is_file() {
+        "file"
+    } else if ft.is_symlink() {
+        "symlink"
+    } else {
+        "other"
+    };
+    jsont::FileMetadata {
+        size: Some(md.len()),
+        modified: md.modified().ok().and_then(rfc3339),
+        file_type: Some(file_type),
+    }
+}
+
+/// Formats the given time as an RFC 3339 timestamp in UTC, e.g.,
+/// `2023-06-01T12:34:56Z`.
+///
+/// `None` is returned for times before the Unix epoch, which keeps this
+/// routine simple and costs nothing in practice.
+fn rfc3339(time: std::time::SystemTime) -> Option<String> {
+    let since = time.duration_since(std::time::UNIX_EPOCH).ok()?;
+    let secs = since.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (hh, mm, ss) = (rem / 3_600, (rem % 3_600) / 60, rem % 60);
+    // Convert days since the epoch to a civil date. This is the
+    // `civil_from_days` algorithm from Howard Hinnant's collection of
+    // public domain calendrical algorithms.
+    let z = (days as i64) + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hh, mm, ss,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use grep_matcher::LineTerminator;
@@ -888,6 +1520,86 @@ and exhibited clearly, with a label attached.
         String::from_utf8(printer.get_mut().to_owned()).unwrap()
     }
 
+    #[test]
+    fn meta_once_per_printer() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut printer = JSONBuilder::new().emit_meta(true).build(vec![]);
+        let mut searcher = SearcherBuilder::new().build();
+        searcher
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+        searcher
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, "sherlock-again"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        // The meta message is emitted exactly once per printer, not once
+        // per search.
+        let metas: Vec<&str> = got
+            .lines()
+            .filter(|line| line.starts_with(r#"{"type":"meta","#))
+            .collect();
+        assert_eq!(1, metas.len());
+        assert_eq!(metas[0], got.lines().next().unwrap());
+        assert_eq!(
+            format!(
+                r#"{{"type":"meta","data":{{"schema_version":{},"printer":"grep-printer","features":[]}}}}"#,
+                JSON::<Vec<u8>>::schema_version(),
+            ),
+            metas[0],
+        );
+    }
+
+    #[test]
+    fn no_meta_is_legacy_output() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+
+        let mut legacy = JSONBuilder::new().build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                legacy.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+        let legacy = printer_contents(&mut legacy);
+
+        let mut printer = JSONBuilder::new().emit_meta(true).build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        // With the meta message enabled, the rest of the output is byte
+        // identical to the legacy output (modulo elapsed timings).
+        let stripped =
+            got.lines().skip(1).collect::<Vec<&str>>().join("\n") + "\n";
+        let strip_elapsed = |s: &str| {
+            s.lines()
+                .map(|line| match line.find(r#""elapsed""#) {
+                    None => line.to_string(),
+                    Some(i) => line[..i].to_string(),
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+        assert_eq!(strip_elapsed(&legacy), strip_elapsed(&stripped));
+    }
+
     #[test]
     fn binary_detection() {
         use grep_searcher::BinaryDetection;
@@ -916,6 +1628,235 @@ and exhibited clearly, with a label attached.\
         assert!(last.contains(r#""binary_offset":212,"#));
     }
 
+    #[test]
+    fn report_binary_skips() {
+        use grep_searcher::BinaryDetection;
+
+        // The NUL byte appears before anything matches, so by default the
+        // file leaves no trace in the stream at all.
+        const BINARY: &'static [u8] = b"nothing here\x00\nSherlock\n";
+
+        let matcher = RegexMatcher::new(r"Sherlock").unwrap();
+        let mut printer = JSONBuilder::new().build(vec![]);
+        SearcherBuilder::new()
+            .binary_detection(BinaryDetection::quit(b'\x00'))
+            .build()
+            .search_reader(
+                &matcher,
+                BINARY,
+                printer.sink_with_path(&matcher, "h.bin"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+        assert!(got.is_empty());
+
+        let mut printer =
+            JSONBuilder::new().report_binary_skips(true).build(vec![]);
+        SearcherBuilder::new()
+            .binary_detection(BinaryDetection::quit(b'\x00'))
+            .build()
+            .search_reader(
+                &matcher,
+                BINARY,
+                printer.sink_with_path(&matcher, "h.bin"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+        assert_eq!(got.lines().count(), 1);
+        assert!(got.contains(r#""type":"binary_skip""#));
+        assert!(got.contains(r#""path":{"text":"h.bin"}"#));
+        assert!(got.contains(r#""binary_offset":12"#));
+    }
+
+    #[test]
+    fn line_number_approximate() {
+        use grep_searcher::BinaryDetection;
+
+        // The NUL byte sits between the two matches, so only the second
+        // match's line number is approximate.
+        let haystack = b"Watson\x00Holmes\nWatson again\n";
+
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut printer = JSONBuilder::new().build(vec![]);
+        SearcherBuilder::new()
+            .line_number(true)
+            .binary_detection(BinaryDetection::convert(b'\x00'))
+            .build()
+            .search_reader(&matcher, &haystack[..], printer.sink(&matcher))
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        let matches: Vec<&str> = got
+            .lines()
+            .filter(|line| line.contains(r#""type":"match""#))
+            .collect();
+        assert_eq!(matches.len(), 2);
+        assert!(!matches[0].contains(r#""line_number_approximate""#));
+        assert!(matches[1].contains(r#""line_number_approximate":true"#));
+    }
+
+    #[test]
+    fn context_kind() {
+        let haystack = "\
+a
+b
+c
+";
+        let matcher = RegexMatcher::new(r"b").unwrap();
+        let mut printer = JSONBuilder::new().build(vec![]);
+        SearcherBuilder::new()
+            .line_number(true)
+            .before_context(1)
+            .after_context(1)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        let contexts: Vec<&str> = got
+            .lines()
+            .filter(|line| line.contains(r#""type":"context""#))
+            .collect();
+        assert_eq!(contexts.len(), 2);
+        assert!(contexts[0].contains(r#""kind":"before""#), "{}", contexts[0]);
+        assert!(contexts[1].contains(r#""kind":"after""#), "{}", contexts[1]);
+    }
+
+    #[test]
+    fn inline_context_end_of_file() {
+        let haystack = "\
+a
+b
+hit
+";
+        let matcher = RegexMatcher::new(r"hit").unwrap();
+        let mut printer =
+            JSONBuilder::new().inline_context(Some(2)).build(vec![]);
+        SearcherBuilder::new()
+            .line_number(true)
+            .before_context(2)
+            .after_context(2)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        // Standalone context messages are suppressed.
+        assert!(!got.contains(r#""type":"context""#), "{}", got);
+        let m = got
+            .lines()
+            .find(|line| line.contains(r#""type":"match""#))
+            .unwrap();
+        assert!(
+            m.contains(
+                r#""before_context":[{"lines":{"text":"a\n"},"line_number":1},{"lines":{"text":"b\n"},"line_number":2}]"#
+            ),
+            "{}",
+            m,
+        );
+        // The match is on the last line, so there is no trailing context.
+        assert!(m.contains(r#""after_context":[]"#), "{}", m);
+    }
+
+    #[test]
+    fn inline_context_overlapping_matches() {
+        let haystack = "\
+a
+x1
+b
+x2
+c
+";
+        let matcher = RegexMatcher::new(r"x").unwrap();
+        let mut printer =
+            JSONBuilder::new().inline_context(Some(1)).build(vec![]);
+        SearcherBuilder::new()
+            .line_number(true)
+            .before_context(1)
+            .after_context(1)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        assert!(!got.contains(r#""type":"context""#), "{}", got);
+        let matches: Vec<&str> = got
+            .lines()
+            .filter(|line| line.contains(r#""type":"match""#))
+            .collect();
+        assert_eq!(2, matches.len());
+        // The line between the two matches is repeated in the trailing
+        // context of the first match and the leading context of the second.
+        assert!(
+            matches[0].contains(
+                r#""before_context":[{"lines":{"text":"a\n"},"line_number":1}]"#
+            ),
+            "{}",
+            matches[0],
+        );
+        assert!(
+            matches[0].contains(
+                r#""after_context":[{"lines":{"text":"b\n"},"line_number":3}]"#
+            ),
+            "{}",
+            matches[0],
+        );
+        assert!(
+            matches[1].contains(
+                r#""before_context":[{"lines":{"text":"b\n"},"line_number":3}]"#
+            ),
+            "{}",
+            matches[1],
+        );
+        assert!(
+            matches[1].contains(
+                r#""after_context":[{"lines":{"text":"c\n"},"line_number":5}]"#
+            ),
+            "{}",
+            matches[1],
+        );
+    }
+
+    #[test]
+    fn max_matches_per_line() {
+        let haystack = "a".repeat(1000) + "\n";
+        let matcher = RegexMatcher::new(r"a").unwrap();
+        let mut printer =
+            JSONBuilder::new().max_matches_per_line(Some(5)).build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        let m = got
+            .lines()
+            .find(|line| line.contains(r#""type":"match""#))
+            .unwrap();
+        assert_eq!(5, m.matches(r#""match":"#).count());
+        assert!(m.contains(r#""submatches_omitted":995"#));
+        // Stats still count every match.
+        let end =
+            got.lines().find(|line| line.contains(r#""type":"end""#)).unwrap();
+        assert!(end.contains(r#""matches":1000"#));
+    }
+
     #[test]
     fn max_matches() {
         let matcher = RegexMatcher::new(r"Watson").unwrap();
@@ -1024,4 +1965,311 @@ e
             got.lines().nth(1).unwrap(),
         );
     }
+
+    #[test]
+    fn absolute_offset_transcoded() {
+        // "Homer\nSimpson\n" encoded as UTF-16LE with a BOM. The searcher
+        // transcodes the data before searching, so absolute_offset refers
+        // to the transcoded data. This is the same fixture used by the
+        // standard printer's byte_offset_transcoded test, and both report
+        // offset 6 for the start of "Simpson".
+        let mut haystack = vec![0xFF, 0xFE];
+        for byte in "Homer\nSimpson\n".bytes() {
+            haystack.extend_from_slice(&[byte, 0x00]);
+        }
+        let matcher = RegexMatcher::new("Simpson").unwrap();
+        let mut printer = JSONBuilder::new().build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(&matcher, &*haystack, printer.sink(&matcher))
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let mat = got.lines().nth(1).unwrap();
+        assert!(
+            mat.contains(r#""absolute_offset":6"#),
+            "missing transcoded offset in '{}'",
+            mat,
+        );
+        assert!(
+            mat.contains(r#""lines":{"text":"Simpson\n"}"#),
+            "missing transcoded line in '{}'",
+            mat,
+        );
+    }
+    #[test]
+    fn deterministic_with_elapsed_omitted() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut runs = vec![];
+        for _ in 0..2 {
+            let mut printer =
+                JSONBuilder::new().omit_elapsed(true).build(vec![]);
+            SearcherBuilder::new()
+                .line_number(true)
+                .build()
+                .search_reader(
+                    &matcher,
+                    SHERLOCK,
+                    printer.sink_with_path(&matcher, "sherlock"),
+                )
+                .unwrap();
+            runs.push(printer_contents(&mut printer));
+        }
+        // With the elapsed time omitted, two runs over the same data are
+        // byte identical.
+        assert_eq!(runs[0], runs[1]);
+        assert!(!runs[0].contains(r#""elapsed""#));
+    }
+
+    #[test]
+    fn stats_precision_rounds_elapsed() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut printer =
+            JSONBuilder::new().stats_precision(Some(3)).build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        let end = got
+            .lines()
+            .find(|line| line.starts_with(r#"{"type":"end""#))
+            .unwrap();
+        let msg: serde_json::Value = serde_json::from_str(end).unwrap();
+        let elapsed = &msg["data"]["stats"]["elapsed"];
+        // Rounding to 3 fractional digits means the nanos are a whole
+        // number of milliseconds and the human rendering has exactly 3
+        // digits after the decimal point.
+        assert_eq!(0, elapsed["nanos"].as_u64().unwrap() % 1_000_000);
+        let human = elapsed["human"].as_str().unwrap();
+        let frac = human.strip_suffix("s").unwrap().split('.').nth(1).unwrap();
+        assert_eq!(3, frac.len());
+    }
+
+    #[test]
+    fn end_message_field_order() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut printer = JSONBuilder::new().build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        // The serialized field order is documented and fixed, so that
+        // output can be diffed across runs and versions.
+        let end = got
+            .lines()
+            .find(|line| line.starts_with(r#"{"type":"end""#))
+            .unwrap();
+        let keys = [
+            r#""path""#,
+            r#""binary_offset""#,
+            r#""stats""#,
+            r#""elapsed""#,
+            r#""secs""#,
+            r#""nanos""#,
+            r#""human""#,
+            r#""searches""#,
+            r#""searches_with_match""#,
+            r#""bytes_searched""#,
+            r#""bytes_printed""#,
+            r#""matched_lines""#,
+            r#""matches""#,
+            r#""retries""#,
+            r#""identical_replacements""#,
+            r#""duplicates_skipped""#,
+            r#""per_pattern""#,
+        ];
+        let mut at = 0;
+        for key in keys {
+            let i = end[at..].find(key).unwrap_or_else(|| {
+                panic!("{} out of order in '{}'", key, end)
+            });
+            at += i + key.len();
+        }
+    }
+
+    #[test]
+    fn slow_file_threshold() {
+        use std::{
+            sync::{
+                atomic::{AtomicU64, Ordering},
+                Arc,
+            },
+            time::Duration,
+        };
+
+        // Each reading of the fake clock advances it by two seconds, so
+        // every search appears to take exactly two seconds.
+        let ticks = Arc::new(AtomicU64::new(0));
+        let mut builder = JSONBuilder::new();
+        builder.slow_file_threshold(Some(Duration::from_secs(1))).time_source(
+            move || Duration::from_secs(ticks.fetch_add(2, Ordering::SeqCst)),
+        );
+        let mut printer = builder.build(vec![]);
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, &"sherlock"),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let bytes = SHERLOCK.len() as u64;
+        let slow: Vec<&str> = got
+            .lines()
+            .filter(|line| line.contains(r#""type":"slow_file""#))
+            .collect();
+        assert_eq!(1, slow.len());
+        assert!(slow[0].contains(r#""path":{"text":"sherlock"}"#));
+        assert!(slow[0].contains(r#""human":"2.000000s""#));
+        assert!(slow[0].contains(&format!(r#""bytes_searched":{}"#, bytes)));
+        assert!(slow[0].contains(&format!(r#""bytes_per_sec":{}"#, bytes / 2)));
+        // The slow file message comes right before the final end message.
+        let lines: Vec<&str> = got.lines().collect();
+        assert!(lines[lines.len() - 1].contains(r#""type":"end""#));
+        assert!(lines[lines.len() - 2].contains(r#""type":"slow_file""#));
+    }
+
+    #[test]
+    fn rfc3339_formatting() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let fmt =
+            |secs| super::rfc3339(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(Some("1970-01-01T00:00:00Z".to_string()), fmt(0));
+        assert_eq!(Some("1970-01-02T00:00:00Z".to_string()), fmt(86_400));
+        assert_eq!(
+            Some("2023-11-14T22:13:20Z".to_string()),
+            fmt(1_700_000_000),
+        );
+        // Check a leap day for good measure.
+        assert_eq!(
+            Some("2024-02-29T12:00:00Z".to_string()),
+            fmt(1_709_208_000),
+        );
+    }
+
+    #[test]
+    fn file_metadata_from_stat() {
+        let dir = std::env::temp_dir().join("rg-printer-json-md-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sherlock");
+        std::fs::write(&path, SHERLOCK).unwrap();
+
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut printer = JSONBuilder::new().file_metadata(true).build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, &path),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        let begin = got.lines().next().unwrap();
+        let msg: serde_json::Value = serde_json::from_str(begin).unwrap();
+        assert_eq!("begin", msg["type"]);
+        assert_eq!(SHERLOCK.len() as u64, msg["data"]["size"]);
+        assert_eq!("file", msg["data"]["file_type"]);
+        // The modification time comes from the file system, so only check
+        // its shape against the expected timestamp.
+        let modified = std::fs::metadata(&path)
+            .unwrap()
+            .modified()
+            .ok()
+            .and_then(super::rfc3339)
+            .unwrap();
+        assert_eq!(modified, msg["data"]["modified"]);
+    }
+
+    #[test]
+    fn file_metadata_null_when_unreadable() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut printer = JSONBuilder::new().file_metadata(true).build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, "does/not/exist/sherlock"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        // The path disappeared between the walk and the search, so the
+        // metadata fields are null rather than missing.
+        let begin = got.lines().next().unwrap();
+        let msg: serde_json::Value = serde_json::from_str(begin).unwrap();
+        assert_eq!("begin", msg["type"]);
+        assert!(msg["data"]["size"].is_null());
+        assert!(msg["data"]["modified"].is_null());
+        assert!(msg["data"]["file_type"].is_null());
+    }
+
+    #[test]
+    fn file_metadata_supplied() {
+        let dir = std::env::temp_dir().join("rg-printer-json-md-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sherlock-supplied");
+        std::fs::write(&path, SHERLOCK).unwrap();
+        let md = std::fs::metadata(&path).unwrap();
+
+        // The supplied metadata is used even though the path given to the
+        // sink doesn't exist, so no stat call can have been made.
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut printer = JSONBuilder::new().file_metadata(true).build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path_and_metadata(
+                    &matcher,
+                    "does/not/exist/sherlock",
+                    Some(&md),
+                ),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        let begin = got.lines().next().unwrap();
+        let msg: serde_json::Value = serde_json::from_str(begin).unwrap();
+        assert_eq!(SHERLOCK.len() as u64, msg["data"]["size"]);
+        assert_eq!("file", msg["data"]["file_type"]);
+
+        // Without the option enabled, the fields aren't even present.
+        let mut printer = JSONBuilder::new().build(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path_and_metadata(
+                    &matcher,
+                    &path,
+                    Some(&md),
+                ),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+        let begin = got.lines().next().unwrap();
+        let msg: serde_json::Value = serde_json::from_str(begin).unwrap();
+        assert!(msg["data"].get("size").is_none());
+    }
 }
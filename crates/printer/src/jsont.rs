@@ -9,10 +9,13 @@
 use std::{borrow::Cow, path::Path};
 
 pub(crate) enum Message<'a> {
+    Meta(Meta),
     Begin(Begin<'a>),
     End(End<'a>),
     Match(Match<'a>),
     Context(Context<'a>),
+    SlowFile(SlowFile<'a>),
+    BinarySkip(BinarySkip<'a>),
 }
 
 impl<'a> serde::Serialize for Message<'a> {
@@ -24,6 +27,10 @@ impl<'a> serde::Serialize for Message<'a> {
 
         let mut state = s.serialize_struct("Message", 2)?;
         match *self {
+            Message::Meta(ref msg) => {
+                state.serialize_field("type", &"meta")?;
+                state.serialize_field("data", msg)?;
+            }
             Message::Begin(ref msg) => {
                 state.serialize_field("type", &"begin")?;
                 state.serialize_field("data", msg)?;
@@ -40,13 +47,43 @@ impl<'a> serde::Serialize for Message<'a> {
                 state.serialize_field("type", &"context")?;
                 state.serialize_field("data", msg)?;
             }
+            Message::SlowFile(ref msg) => {
+                state.serialize_field("type", &"slow_file")?;
+                state.serialize_field("data", msg)?;
+            }
+            Message::BinarySkip(ref msg) => {
+                state.serialize_field("type", &"binary_skip")?;
+                state.serialize_field("data", msg)?;
+            }
         }
         state.end()
     }
 }
 
+pub(crate) struct Meta {
+    pub(crate) schema_version: u64,
+    pub(crate) printer: &'static str,
+    pub(crate) features: Vec<&'static str>,
+}
+
+impl serde::Serialize for Meta {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = s.serialize_struct("Meta", 3)?;
+        state.serialize_field("schema_version", &self.schema_version)?;
+        state.serialize_field("printer", &self.printer)?;
+        state.serialize_field("features", &self.features)?;
+        state.end()
+    }
+}
+
 pub(crate) struct Begin<'a> {
     pub(crate) path: Option<&'a Path>,
+    pub(crate) metadata: Option<FileMetadata>,
 }
 
 impl<'a> serde::Serialize for Begin<'a> {
@@ -56,16 +93,31 @@ impl<'a> serde::Serialize for Begin<'a> {
     ) -> Result<S::Ok, S::Error> {
         use serde::ser::SerializeStruct;
 
-        let mut state = s.serialize_struct("Begin", 1)?;
+        let fields = if self.metadata.is_some() { 4 } else { 1 };
+        let mut state = s.serialize_struct("Begin", fields)?;
         state.serialize_field("path", &self.path.map(Data::from_path))?;
+        if let Some(ref md) = self.metadata {
+            state.serialize_field("size", &md.size)?;
+            state.serialize_field("modified", &md.modified)?;
+            state.serialize_field("file_type", &md.file_type)?;
+        }
         state.end()
     }
 }
 
+/// File metadata attached to a `begin` message when the printer was
+/// configured to include it. Every field is `None` when reading the
+/// metadata failed, in which case the fields serialize as `null`.
+pub(crate) struct FileMetadata {
+    pub(crate) size: Option<u64>,
+    pub(crate) modified: Option<String>,
+    pub(crate) file_type: Option<&'static str>,
+}
+
 pub(crate) struct End<'a> {
     pub(crate) path: Option<&'a Path>,
     pub(crate) binary_offset: Option<u64>,
-    pub(crate) stats: crate::stats::Stats,
+    pub(crate) stats: Stats,
 }
 
 impl<'a> serde::Serialize for End<'a> {
@@ -83,12 +135,169 @@ impl<'a> serde::Serialize for End<'a> {
     }
 }
 
+/// Statistics paired with the rendering options configured on the printer.
+///
+/// This exists so that the printer can control how the `elapsed` duration is
+/// rendered without changing the serialization of the crate-public `Stats`
+/// type.
+pub(crate) struct Stats {
+    pub(crate) stats: crate::stats::Stats,
+    pub(crate) precision: Option<u8>,
+    pub(crate) omit_elapsed: bool,
+}
+
+impl serde::Serialize for Stats {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let fields = if self.omit_elapsed { 10 } else { 11 };
+        let mut state = s.serialize_struct("Stats", fields)?;
+        if !self.omit_elapsed {
+            let elapsed = Elapsed {
+                duration: self.stats.elapsed(),
+                precision: self.precision,
+            };
+            state.serialize_field("elapsed", &elapsed)?;
+        }
+        state.serialize_field("searches", &self.stats.searches())?;
+        state.serialize_field(
+            "searches_with_match",
+            &self.stats.searches_with_match(),
+        )?;
+        state
+            .serialize_field("bytes_searched", &self.stats.bytes_searched())?;
+        state.serialize_field("bytes_printed", &self.stats.bytes_printed())?;
+        state.serialize_field("matched_lines", &self.stats.matched_lines())?;
+        state.serialize_field("matches", &self.stats.matches())?;
+        state.serialize_field("retries", &self.stats.retries())?;
+        state.serialize_field(
+            "identical_replacements",
+            &self.stats.identical_replacements(),
+        )?;
+        state.serialize_field(
+            "duplicates_skipped",
+            &self.stats.duplicates_skipped(),
+        )?;
+        state.serialize_field("per_pattern", &self.stats.per_pattern())?;
+        state.end()
+    }
+}
+
+/// A warning that a single search exceeded the printer's slow file
+/// threshold.
+pub(crate) struct SlowFile<'a> {
+    pub(crate) path: Option<&'a Path>,
+    pub(crate) elapsed: std::time::Duration,
+    pub(crate) precision: Option<u8>,
+    pub(crate) bytes_searched: u64,
+}
+
+/// A message emitted when a search quit on binary data before finding any
+/// match, leaving the file unreported by the usual messages.
+pub(crate) struct BinarySkip<'a> {
+    pub(crate) path: Option<&'a Path>,
+    pub(crate) binary_offset: u64,
+}
+
+impl<'a> serde::Serialize for BinarySkip<'a> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = s.serialize_struct("BinarySkip", 2)?;
+        state.serialize_field("path", &self.path.map(Data::from_path))?;
+        state.serialize_field("binary_offset", &self.binary_offset)?;
+        state.end()
+    }
+}
+
+impl<'a> serde::Serialize for SlowFile<'a> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = s.serialize_struct("SlowFile", 4)?;
+        state.serialize_field("path", &self.path.map(Data::from_path))?;
+        let elapsed =
+            Elapsed { duration: self.elapsed, precision: self.precision };
+        state.serialize_field("elapsed", &elapsed)?;
+        state.serialize_field("bytes_searched", &self.bytes_searched)?;
+        let secs = self.elapsed.as_secs_f64();
+        let rate = if secs > 0.0 {
+            (self.bytes_searched as f64 / secs) as u64
+        } else {
+            0
+        };
+        state.serialize_field("bytes_per_sec", &rate)?;
+        state.end()
+    }
+}
+
+/// An elapsed duration, optionally rounded to a fixed number of fractional
+/// digits.
+struct Elapsed {
+    duration: std::time::Duration,
+    precision: Option<u8>,
+}
+
+impl Elapsed {
+    /// Returns the duration rounded to the configured number of fractional
+    /// digits, or the duration unchanged when no precision is set.
+    fn rounded(&self) -> std::time::Duration {
+        let Some(precision) = self.precision else { return self.duration };
+        if precision >= 9 {
+            return self.duration;
+        }
+        let granularity = 10u64.pow(9 - u32::from(precision));
+        let nanos = u64::from(self.duration.subsec_nanos());
+        let nanos = ((nanos + granularity / 2) / granularity) * granularity;
+        std::time::Duration::new(self.duration.as_secs(), 0)
+            + std::time::Duration::from_nanos(nanos)
+    }
+}
+
+impl serde::Serialize for Elapsed {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let duration = self.rounded();
+        let mut state = s.serialize_struct("Duration", 3)?;
+        state.serialize_field("secs", &duration.as_secs())?;
+        state.serialize_field("nanos", &duration.subsec_nanos())?;
+        let human = match self.precision {
+            None => crate::util::NiceDuration(duration).to_string(),
+            Some(precision) => {
+                let fractional =
+                    (duration.subsec_nanos() as f64) / 1_000_000_000.0;
+                let seconds = duration.as_secs() as f64 + fractional;
+                format!("{:0.*}s", usize::from(precision), seconds)
+            }
+        };
+        state.serialize_field("human", &human)?;
+        state.end()
+    }
+}
+
 pub(crate) struct Match<'a> {
     pub(crate) path: Option<&'a Path>,
     pub(crate) lines: &'a [u8],
     pub(crate) line_number: Option<u64>,
+    pub(crate) line_number_approximate: bool,
     pub(crate) absolute_offset: u64,
     pub(crate) submatches: &'a [SubMatch<'a>],
+    pub(crate) submatches_omitted: usize,
+    pub(crate) before_context: Option<Vec<ContextLine<'a>>>,
+    pub(crate) after_context: Option<Vec<ContextLine<'a>>>,
 }
 
 impl<'a> serde::Serialize for Match<'a> {
@@ -102,8 +311,52 @@ impl<'a> serde::Serialize for Match<'a> {
         state.serialize_field("path", &self.path.map(Data::from_path))?;
         state.serialize_field("lines", &Data::from_bytes(self.lines))?;
         state.serialize_field("line_number", &self.line_number)?;
+        // Only written when true, to keep the common case lean.
+        if self.line_number_approximate {
+            state.serialize_field(
+                "line_number_approximate",
+                &self.line_number_approximate,
+            )?;
+        }
         state.serialize_field("absolute_offset", &self.absolute_offset)?;
         state.serialize_field("submatches", &self.submatches)?;
+        // Only written when submatches were actually dropped due to a
+        // per-line limit, to keep the common case lean.
+        if self.submatches_omitted > 0 {
+            state.serialize_field(
+                "submatches_omitted",
+                &self.submatches_omitted,
+            )?;
+        }
+        // Only written when the printer is configured to embed contextual
+        // lines into match messages.
+        if let Some(ref lines) = self.before_context {
+            state.serialize_field("before_context", lines)?;
+        }
+        if let Some(ref lines) = self.after_context {
+            state.serialize_field("after_context", lines)?;
+        }
+        state.end()
+    }
+}
+
+/// A contextual line embedded in a `match` message, as opposed to being
+/// emitted as a standalone `context` message.
+pub(crate) struct ContextLine<'a> {
+    pub(crate) lines: &'a [u8],
+    pub(crate) line_number: Option<u64>,
+}
+
+impl<'a> serde::Serialize for ContextLine<'a> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = s.serialize_struct("ContextLine", 2)?;
+        state.serialize_field("lines", &Data::from_bytes(self.lines))?;
+        state.serialize_field("line_number", &self.line_number)?;
         state.end()
     }
 }
@@ -113,6 +366,7 @@ pub(crate) struct Context<'a> {
     pub(crate) lines: &'a [u8],
     pub(crate) line_number: Option<u64>,
     pub(crate) absolute_offset: u64,
+    pub(crate) kind: &'static str,
     pub(crate) submatches: &'a [SubMatch<'a>],
 }
 
@@ -123,11 +377,12 @@ impl<'a> serde::Serialize for Context<'a> {
     ) -> Result<S::Ok, S::Error> {
         use serde::ser::SerializeStruct;
 
-        let mut state = s.serialize_struct("Context", 5)?;
+        let mut state = s.serialize_struct("Context", 6)?;
         state.serialize_field("path", &self.path.map(Data::from_path))?;
         state.serialize_field("lines", &Data::from_bytes(self.lines))?;
         state.serialize_field("line_number", &self.line_number)?;
         state.serialize_field("absolute_offset", &self.absolute_offset)?;
+        state.serialize_field("kind", &self.kind)?;
         state.serialize_field("submatches", &self.submatches)?;
         state.end()
     }
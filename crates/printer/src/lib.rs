@@ -61,15 +61,23 @@ assert_eq!(output, expected);
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
 pub use crate::{
-    color::{default_color_specs, ColorError, ColorSpecs, UserColorSpec},
+    color::{
+        default_color_specs, ColorDepth, ColorError, ColorSpecs,
+        UserColorSpec,
+    },
     hyperlink::{
         HyperlinkConfig, HyperlinkEnvironment, HyperlinkFormat,
         HyperlinkFormatError,
     },
+    counter::CounterWriter,
+    exec::{Exec, ExecBuilder, ExecSink},
+    patch::{FormatPatchConfig, Patch, PatchBuilder, PatchSink},
     path::{PathPrinter, PathPrinterBuilder},
+    quiet::{Quiet, QuietBuilder, QuietSink},
     standard::{Standard, StandardBuilder, StandardSink},
-    stats::Stats,
+    stats::{write_stats, Stats, StatsRenderOptions},
     summary::{Summary, SummaryBuilder, SummaryKind, SummarySink},
+    util::PathDisplay,
 };
 
 #[cfg(feature = "serde")]
@@ -91,13 +99,16 @@ mod macros;
 
 mod color;
 mod counter;
+mod exec;
 mod hyperlink;
 mod hyperlink_aliases;
 #[cfg(feature = "serde")]
 mod json;
 #[cfg(feature = "serde")]
 mod jsont;
+mod patch;
 mod path;
+mod quiet;
 mod standard;
 mod stats;
 mod summary;
@@ -0,0 +1,1438 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use {
+    grep_matcher::Matcher,
+    grep_searcher::{
+        Searcher, Sink, SinkContext, SinkContextKind, SinkError, SinkFinish,
+        SinkMatch,
+    },
+};
+
+use crate::{
+    counter::CounterWriter,
+    stats::Stats,
+    util::{PrinterPath, Replacer},
+};
+
+/// The configuration for the patch printer.
+///
+/// This is manipulated by the PatchBuilder and then referenced by the actual
+/// implementation. Once a printer is built, the configuration is frozen and
+/// cannot changed.
+#[derive(Clone, Debug, Default)]
+struct Config {
+    replacement: Option<Vec<u8>>,
+    minimized_hunks: bool,
+    allow_file_deletion: bool,
+    format_patch: Option<FormatPatchConfig>,
+    stats: bool,
+}
+
+/// The configuration for a `git format-patch` style envelope around the
+/// diffs emitted by the patch printer.
+///
+/// When an envelope is requested via [`PatchBuilder::format_patch`], the
+/// printer emits a mbox-style header block (`From`, `Date` and `Subject`
+/// headers) before the diff and a `-- ` signature trailer after it, such
+/// that the output can be applied directly with `git am`.
+#[derive(Debug, Clone)]
+pub struct FormatPatchConfig {
+    /// The subject template. The placeholders `{path}` and `{match_count}`
+    /// are replaced with the file path and the number of matched lines,
+    /// respectively. When one envelope is emitted for the whole run, `{path}`
+    /// expands to the path of the first file with a match and
+    /// `{match_count}` to the total number of matched lines.
+    pub subject: String,
+    /// The author recorded in the `From` header, e.g.,
+    /// `ripgrep <rg@localhost>`.
+    pub author: String,
+    /// When enabled, one envelope is emitted for each file with a match.
+    /// When disabled, a single envelope is emitted for the whole run.
+    pub per_file: bool,
+}
+
+impl Default for FormatPatchConfig {
+    fn default() -> FormatPatchConfig {
+        FormatPatchConfig {
+            subject: "apply ripgrep replacements".to_string(),
+            author: "ripgrep <ripgrep@localhost>".to_string(),
+            per_file: false,
+        }
+    }
+}
+
+/// A builder for the "patch" printer.
+///
+/// The patch printer emits the results of a search-and-replace as a unified
+/// diff that, when applied, performs the replacement. A replacement should
+/// be set via the `replacement` method; when absent, the emitted diff
+/// deletes every matching line.
+#[derive(Clone, Debug)]
+pub struct PatchBuilder {
+    config: Config,
+}
+
+impl PatchBuilder {
+    /// Return a new builder for configuring the patch printer.
+    pub fn new() -> PatchBuilder {
+        PatchBuilder { config: Config::default() }
+    }
+
+    /// Create a patch printer that writes results to the given writer.
+    pub fn build<W: io::Write>(&self, wtr: W) -> Patch<W> {
+        Patch {
+            config: self.config.clone(),
+            wtr: CounterWriter::new(wtr),
+            buf: vec![],
+            total_match_count: 0,
+            first_path: None,
+        }
+    }
+
+    /// Set the bytes that will be used to replace each occurrence of a match
+    /// found.
+    ///
+    /// The replacement bytes given may include references to capturing groups,
+    /// which may either be in index form (e.g., `$2`) or can reference named
+    /// capturing groups if present in the original pattern (e.g., `$foo`).
+    ///
+    /// When no replacement is given, the emitted diff deletes every matching
+    /// line.
+    pub fn replacement(
+        &mut self,
+        replacement: Option<Vec<u8>>,
+    ) -> &mut PatchBuilder {
+        self.config.replacement = replacement;
+        self
+    }
+
+    /// Minimize hunks by emitting unchanged leading and trailing lines of
+    /// a multi-line replacement as context.
+    ///
+    /// The replacement for a match spanning several lines is emitted as a
+    /// block of `-` lines followed by a block of `+` lines, even when the
+    /// replacement leaves most of those lines untouched. When this is
+    /// enabled, lines at the beginning and end of a match that the
+    /// replacement reproduces exactly (including the line terminator) are
+    /// emitted as ` ` context lines instead, and the counts in the hunk's
+    /// `@@` header shrink accordingly.
+    ///
+    /// This is disabled by default.
+    pub fn minimized_hunks(&mut self, yes: bool) -> &mut PatchBuilder {
+        self.config.minimized_hunks = yes;
+        self
+    }
+
+    /// Emit diffs that delete a file outright when the replacement empties
+    /// it.
+    ///
+    /// A diff that removes every line of a file still leaves an empty file
+    /// behind when applied. When this is enabled and the post-replacement
+    /// content of a file would be empty (e.g., an empty replacement with a
+    /// pattern matching every line), the file header uses the `/dev/null`
+    /// convention from `git diff` instead, so that `git apply` removes the
+    /// file.
+    ///
+    /// The printer considers a file emptied when the matched lines account
+    /// for every byte that was searched and neither the replacement nor any
+    /// context lines contribute anything to the new side of the diff. When
+    /// the caller knows the whole file was covered but the printer cannot
+    /// confirm it on its own, it can assert as much via
+    /// [`PatchSink::mark_whole_file`].
+    ///
+    /// This is disabled by default.
+    pub fn allow_file_deletion(&mut self, yes: bool) -> &mut PatchBuilder {
+        self.config.allow_file_deletion = yes;
+        self
+    }
+
+    /// Set the `git format-patch` style envelope configuration.
+    ///
+    /// When set, the diffs emitted are wrapped in a mbox-style envelope with
+    /// `From`, `Date` and `Subject` headers and a `-- ` signature trailer,
+    /// such that the output can be applied directly with `git am`. Depending
+    /// on the configuration, either one envelope is emitted per file with a
+    /// match, or a single envelope is emitted for the whole run. In the
+    /// latter case, [`Patch::finish`] must be called once all searches have
+    /// completed in order to flush the envelope.
+    ///
+    /// This is disabled by default, in which case bare diffs are emitted.
+    pub fn format_patch(
+        &mut self,
+        config: Option<FormatPatchConfig>,
+    ) -> &mut PatchBuilder {
+        self.config.format_patch = config;
+        self
+    }
+
+    /// Enable the gathering of various aggregate statistics.
+    ///
+    /// When this is enabled (it's disabled by default), statistics will be
+    /// gathered for all uses of the patch printer, and can be accessed via
+    /// the sink's [`PatchSink::stats`] method.
+    pub fn stats(&mut self, yes: bool) -> &mut PatchBuilder {
+        self.config.stats = yes;
+        self
+    }
+}
+
+/// The patch printer, which emits the results of a search-and-replace as a
+/// unified diff.
+///
+/// Hunks contain whatever contextual lines the searcher reports, so callers
+/// that intend to apply the diff with standard tooling should configure the
+/// searcher with at least one line of context. (`git apply` rejects hunks
+/// without any context unless `--unidiff-zero` is given.) Line numbers must
+/// be enabled in the searcher; searches without them report an error.
+///
+/// This type is generic over `W`, which represents any implementation of
+/// the standard library `io::Write` trait.
+#[derive(Debug)]
+pub struct Patch<W> {
+    config: Config,
+    wtr: CounterWriter<W>,
+    /// The diffs buffered for a single run-level envelope.
+    buf: Vec<u8>,
+    /// The total number of matched lines across all files.
+    total_match_count: u64,
+    /// The path of the first file with a match.
+    first_path: Option<Vec<u8>>,
+}
+
+impl<W: io::Write> Patch<W> {
+    /// Return an implementation of `Sink` associated with a file path.
+    ///
+    /// Unlike other printers, the patch printer requires a file path, since
+    /// a diff without one cannot be applied.
+    pub fn sink_with_path<'p, 's, M, P>(
+        &'s mut self,
+        matcher: M,
+        path: &'p P,
+    ) -> PatchSink<'p, 's, M, W>
+    where
+        M: Matcher,
+        P: ?Sized + AsRef<Path>,
+    {
+        let stats = if self.config.stats { Some(Stats::new()) } else { None };
+        PatchSink {
+            matcher,
+            patch: self,
+            path: PrinterPath::new(path.as_ref()),
+            replacer: Replacer::new(),
+            hunks: vec![],
+            hunk: None,
+            match_count: 0,
+            hunks_written: 0,
+            matched_bytes: 0,
+            new_line_count: 0,
+            whole_file: false,
+            offset: 0,
+            base_offset: 0,
+            stats,
+        }
+    }
+
+    /// Write any pending output.
+    ///
+    /// This must be called once all searches have completed when a single
+    /// envelope for the whole run has been requested via
+    /// [`PatchBuilder::format_patch`]. It is a no-op otherwise.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let config = self.config.format_patch.clone().expect(
+            "diffs are only buffered when a run-level envelope is requested",
+        );
+        let path = self.first_path.take().unwrap_or_default();
+        write_envelope(&mut self.wtr, &config, &path, self.total_match_count)?;
+        self.wtr.write_all(&self.buf)?;
+        write_trailer(&mut self.wtr)?;
+        self.buf.clear();
+        self.total_match_count = 0;
+        Ok(())
+    }
+
+    /// Returns true if and only if this printer has written at least one
+    /// byte to the underlying writer during any of the previous searches.
+    pub fn has_written(&self) -> bool {
+        self.wtr.total_count() > 0
+    }
+
+    /// Return a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut()
+    }
+
+    /// Consume this printer and return back ownership of the underlying
+    /// writer.
+    pub fn into_inner(self) -> W {
+        self.wtr.into_inner()
+    }
+}
+
+impl Patch<termcolor::Buffer> {
+    /// Print everything written to this printer's buffer so far and clear
+    /// the buffer for reuse.
+    ///
+    /// This is intended for use with parallel search. Writing a diff for
+    /// each file directly to a shared writer from multiple threads would
+    /// interleave hunks from different files. Instead, give each worker its
+    /// own `Patch<termcolor::Buffer>` printer and flush the buffer into a
+    /// shared [`termcolor::BufferWriter`] after each file, which prints the
+    /// entire buffer atomically.
+    ///
+    /// The byte totals reported by [`Patch::has_written`] accumulate across
+    /// flushes.
+    pub fn flush_into(
+        &mut self,
+        bufwtr: &termcolor::BufferWriter,
+    ) -> io::Result<()> {
+        bufwtr.print(self.wtr.get_mut())?;
+        self.wtr.get_mut().clear();
+        Ok(())
+    }
+}
+
+/// An implementation of `Sink` associated with a matcher and a file path for
+/// the patch printer.
+#[derive(Debug)]
+pub struct PatchSink<'p, 's, M: Matcher, W: io::Write> {
+    matcher: M,
+    patch: &'s mut Patch<W>,
+    path: PrinterPath<'p>,
+    replacer: Replacer<M>,
+    /// The rendered hunks for this file.
+    hunks: Vec<u8>,
+    /// The hunk currently being accumulated, if any.
+    hunk: Option<Hunk>,
+    /// The number of matched lines in this file.
+    match_count: u64,
+    /// The number of hunks rendered for this file. Hunks whose replacement
+    /// is identical to the original text are skipped and not counted here.
+    hunks_written: u64,
+    /// The total number of bytes of matched lines seen in this search, used
+    /// to detect replacements that empty out the file entirely.
+    matched_bytes: u64,
+    /// The total number of lines contributed to the new side of the diff in
+    /// this search, including context lines.
+    new_line_count: u64,
+    /// Whether the caller declared that searches on this sink cover every
+    /// line of the file. See [`PatchSink::mark_whole_file`].
+    whole_file: bool,
+    /// The cumulative difference between the number of new and old lines
+    /// emitted by completed hunks, used to compute hunk start lines on the
+    /// new side of the diff.
+    offset: i64,
+    /// The value `offset` is reset to at the beginning of each search. This
+    /// is zero unless the caller set one via
+    /// [`PatchSink::set_base_line_offset`].
+    base_offset: i64,
+    /// Aggregate statistics, when enabled.
+    stats: Option<Stats>,
+}
+
+/// A single hunk under construction.
+#[derive(Debug)]
+struct Hunk {
+    /// The line number of the first line of this hunk on the old side.
+    old_start: u64,
+    /// The number of lines of this hunk on the old side.
+    old_count: u64,
+    /// The number of lines of this hunk on the new side.
+    new_count: u64,
+    /// The rendered `-`, `+` and ` ` lines of this hunk.
+    lines: Vec<u8>,
+    /// The raw bytes of the lines removed by this hunk, used to detect hunks
+    /// whose replacement is identical to the original text.
+    removed: Vec<u8>,
+    /// The raw bytes of the lines added by this hunk.
+    added: Vec<u8>,
+}
+
+impl<'p, 's, M: Matcher, W: io::Write> PatchSink<'p, 's, M, W> {
+    /// Returns true if and only if this printer received a match in the
+    /// previous search.
+    ///
+    /// This is unaffected by the result of searches before the previous
+    /// search.
+    ///
+    /// Note that this reports whether any matches were found, not whether
+    /// any hunks were emitted. When every replacement is identical to the
+    /// text it replaces, this returns true even though no output was
+    /// produced. Use [`PatchSink::hunks_written`] to tell the difference.
+    pub fn has_match(&self) -> bool {
+        self.match_count > 0
+    }
+
+    /// Returns the number of hunks emitted in the previous search.
+    ///
+    /// Hunks whose replacement is identical to the original text are
+    /// skipped and not counted here.
+    pub fn hunks_written(&self) -> u64 {
+        self.hunks_written
+    }
+
+    /// Return a reference to the stats produced by the printer for all
+    /// searches executed on this sink.
+    ///
+    /// This only returns stats if they were requested via
+    /// [`PatchBuilder::stats`].
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    /// Declare that the searches on this sink cover every line of the file.
+    ///
+    /// The file deletion detection enabled by
+    /// [`PatchBuilder::allow_file_deletion`] normally requires the matched
+    /// lines to account for every byte that was searched. When the caller
+    /// searched with a pattern that is known to match every line, calling
+    /// this lifts that requirement: a deletion diff is emitted whenever
+    /// nothing is contributed to the new side of the diff, even when the
+    /// printer cannot itself confirm full coverage.
+    ///
+    /// This has no effect unless file deletion was enabled on the builder.
+    /// It applies to all searches on this sink.
+    pub fn mark_whole_file(&mut self) {
+        self.whole_file = true;
+    }
+
+    /// Set the line offset that hunk start lines on the new side of the diff
+    /// begin at.
+    ///
+    /// The hunk headers emitted by a search only account for the lines added
+    /// and removed by that search. When several passes of search-and-replace
+    /// are run over the same file (e.g., with a different pattern or
+    /// replacement per pass), the line numbers on the new side of a later
+    /// pass's hunks don't know about an earlier pass's insertions or
+    /// deletions. To chain passes, seed the sink for each pass with the
+    /// offset reported by [`PatchSink::line_offset`] on the previous pass's
+    /// sink.
+    ///
+    /// The offset given applies to all subsequent searches on this sink.
+    /// It is `0` by default.
+    pub fn set_base_line_offset(&mut self, offset: i64) {
+        self.base_offset = offset;
+        self.offset = offset;
+    }
+
+    /// Returns the cumulative difference between the number of lines added
+    /// and removed by the previous search, including any offset set via
+    /// [`PatchSink::set_base_line_offset`].
+    ///
+    /// This is intended to be read after a search completes and fed to
+    /// `set_base_line_offset` on the sink handling the next pass over the
+    /// same file.
+    pub fn line_offset(&self) -> i64 {
+        self.offset
+    }
+
+    /// Return the hunk being accumulated, starting a new one at the given
+    /// line number (on the old side) if there is none or if the given line
+    /// is not contiguous with it.
+    fn hunk_at(&mut self, line_number: u64) -> &mut Hunk {
+        let contiguous = self.hunk.as_ref().map_or(false, |hunk| {
+            line_number == hunk.old_start + hunk.old_count
+        });
+        if !contiguous {
+            self.flush_hunk();
+            self.hunk = Some(Hunk {
+                old_start: line_number,
+                old_count: 0,
+                new_count: 0,
+                lines: vec![],
+                removed: vec![],
+                added: vec![],
+            });
+        }
+        self.hunk.as_mut().unwrap()
+    }
+
+    /// Render the hunk currently being accumulated, if any, into the hunk
+    /// buffer for this file.
+    ///
+    /// Hunks that wouldn't change anything, i.e., whose removed and added
+    /// lines are byte-identical, are silently dropped. Without this, a
+    /// replacement that happens to equal the original text (e.g., `$0`)
+    /// would produce hunks that change nothing.
+    fn flush_hunk(&mut self) {
+        let Some(hunk) = self.hunk.take() else { return };
+        if hunk.removed == hunk.added {
+            // A hunk containing only context lines (e.g., from a stray
+            // context break) isn't a replacement, so don't count it.
+            if !hunk.removed.is_empty() {
+                if let Some(ref mut stats) = self.stats {
+                    stats.add_identical_replacements(1);
+                }
+            }
+            return;
+        }
+        self.hunks_written += 1;
+        // A hunk that deletes lines without adding any is anchored to the
+        // line preceding the deletion on the new side.
+        let new_start = if hunk.new_count == 0 {
+            hunk.old_start as i64 + self.offset - 1
+        } else {
+            hunk.old_start as i64 + self.offset
+        };
+        self.hunks.extend_from_slice(
+            format!(
+                "@@ -{},{} +{},{} @@\n",
+                hunk.old_start, hunk.old_count, new_start, hunk.new_count,
+            )
+            .as_bytes(),
+        );
+        self.hunks.extend_from_slice(&hunk.lines);
+        self.offset += hunk.new_count as i64 - hunk.old_count as i64;
+    }
+
+    /// Compute the replacement for the given matched line(s), if a
+    /// replacement was configured.
+    ///
+    /// To access the result of a replacement, use `replacer.replacement()`.
+    fn replace(
+        &mut self,
+        searcher: &Searcher,
+        bytes: &[u8],
+        range: std::ops::Range<usize>,
+    ) -> io::Result<()> {
+        self.replacer.clear();
+        if let Some(ref replacement) = self.patch.config.replacement {
+            self.replacer.replace_all(
+                searcher,
+                &self.matcher,
+                bytes,
+                range,
+                replacement,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'p, 's, M: Matcher, W: io::Write> Sink for PatchSink<'p, 's, M, W> {
+    type Error = io::Error;
+
+    fn matched(
+        &mut self,
+        searcher: &Searcher,
+        mat: &SinkMatch<'_>,
+    ) -> Result<bool, io::Error> {
+        let line_number = match mat.line_number() {
+            Some(line_number) => line_number,
+            None => {
+                unreachable!("line numbers are checked in 'begin'")
+            }
+        };
+        self.replace(searcher, mat.buffer(), mat.bytes_range_in_buffer())?;
+
+        let line_term = searcher.line_terminator().as_byte();
+        let old_lines = split_lines(mat.bytes(), line_term);
+        let new_bytes = self
+            .replacer
+            .replacement()
+            .map(|(bytes, _)| bytes.to_vec())
+            .unwrap_or_default();
+        let new_lines = split_lines(&new_bytes, line_term);
+
+        self.match_count += old_lines.len() as u64;
+        self.matched_bytes += mat.bytes().len() as u64;
+        self.new_line_count += new_lines.len() as u64;
+        // When a fully identical replacement would trim down to nothing,
+        // skip the trim and emit the usual `-`/`+` pairs so that
+        // `flush_hunk` can recognize (and drop) the no-op hunk.
+        let (prefix, suffix) =
+            if self.patch.config.minimized_hunks && old_lines != new_lines {
+                common_affix(
+                    &old_lines,
+                    &new_lines,
+                    mat.bytes().last() == Some(&line_term),
+                    new_bytes.last() == Some(&line_term),
+                )
+            } else {
+                (0, 0)
+            };
+        let hunk = self.hunk_at(line_number);
+        for line in old_lines[..prefix].iter() {
+            hunk.lines.push(b' ');
+            hunk.lines.extend_from_slice(line);
+            hunk.lines.push(b'\n');
+            hunk.old_count += 1;
+            hunk.new_count += 1;
+        }
+        for line in old_lines[prefix..old_lines.len() - suffix].iter() {
+            hunk.lines.push(b'-');
+            hunk.lines.extend_from_slice(line);
+            hunk.lines.push(b'\n');
+            hunk.removed.extend_from_slice(line);
+            hunk.removed.push(b'\n');
+            hunk.old_count += 1;
+        }
+        for line in new_lines[prefix..new_lines.len() - suffix].iter() {
+            hunk.lines.push(b'+');
+            hunk.lines.extend_from_slice(line);
+            hunk.lines.push(b'\n');
+            hunk.added.extend_from_slice(line);
+            hunk.added.push(b'\n');
+            hunk.new_count += 1;
+        }
+        for line in old_lines[old_lines.len() - suffix..].iter() {
+            hunk.lines.push(b' ');
+            hunk.lines.extend_from_slice(line);
+            hunk.lines.push(b'\n');
+            hunk.old_count += 1;
+            hunk.new_count += 1;
+        }
+        Ok(true)
+    }
+
+    fn context(
+        &mut self,
+        searcher: &Searcher,
+        ctx: &SinkContext<'_>,
+    ) -> Result<bool, io::Error> {
+        let line_term = searcher.line_terminator().as_byte();
+        // Lines reported as context survive the replacement (including
+        // passthru lines, which aren't part of any hunk), so any of them
+        // rules out file deletion.
+        self.new_line_count +=
+            split_lines(ctx.bytes(), line_term).len() as u64;
+        if let SinkContextKind::Other = *ctx.kind() {
+            return Ok(true);
+        }
+        let line_number = match ctx.line_number() {
+            Some(line_number) => line_number,
+            None => {
+                unreachable!("line numbers are checked in 'begin'")
+            }
+        };
+        let hunk = self.hunk_at(line_number);
+        for line in split_lines(ctx.bytes(), line_term).iter() {
+            hunk.lines.push(b' ');
+            hunk.lines.extend_from_slice(line);
+            hunk.lines.push(b'\n');
+            hunk.old_count += 1;
+            hunk.new_count += 1;
+        }
+        Ok(true)
+    }
+
+    fn context_break(
+        &mut self,
+        _searcher: &Searcher,
+    ) -> Result<bool, io::Error> {
+        self.flush_hunk();
+        Ok(true)
+    }
+
+    fn begin(&mut self, searcher: &Searcher) -> Result<bool, io::Error> {
+        if !searcher.line_number() {
+            return Err(io::Error::error_message(
+                "patch printer requires the searcher to count line numbers",
+            ));
+        }
+        self.patch.wtr.reset_count();
+        self.hunks.clear();
+        self.hunk = None;
+        self.match_count = 0;
+        self.hunks_written = 0;
+        self.matched_bytes = 0;
+        self.new_line_count = 0;
+        self.offset = self.base_offset;
+        Ok(true)
+    }
+
+    fn finish(
+        &mut self,
+        _searcher: &Searcher,
+        finish: &SinkFinish,
+    ) -> Result<(), io::Error> {
+        self.flush_hunk();
+        // Checking the hunk buffer rather than the match count keeps the
+        // `--- / +++` header out of the output entirely when every hunk was
+        // dropped for being a no-op: a header followed by zero hunks is a
+        // confusing (and unappliable) file section.
+        if self.hunks.is_empty() {
+            return Ok(());
+        }
+
+        // A diff that removes every line still leaves an empty file behind
+        // when applied, so when file deletion is allowed and the replacement
+        // empties the file out entirely, use the `/dev/null` convention to
+        // make `git apply` remove it. A partially searched file (i.e., when
+        // binary data was detected) never qualifies, since the unsearched
+        // remainder survives.
+        let deleted = self.patch.config.allow_file_deletion
+            && self.new_line_count == 0
+            && finish.binary_byte_offset().is_none()
+            && (self.whole_file || self.matched_bytes == finish.byte_count());
+
+        let path = self.path.as_bytes();
+        let mut diff = vec![];
+        diff.extend_from_slice(b"diff --git a/");
+        diff.extend_from_slice(path);
+        diff.extend_from_slice(b" b/");
+        diff.extend_from_slice(path);
+        diff.push(b'\n');
+        if deleted {
+            diff.extend_from_slice(b"deleted file mode 100644\n");
+        }
+        diff.extend_from_slice(b"--- a/");
+        diff.extend_from_slice(path);
+        diff.push(b'\n');
+        if deleted {
+            diff.extend_from_slice(b"+++ /dev/null\n");
+        } else {
+            diff.extend_from_slice(b"+++ b/");
+            diff.extend_from_slice(path);
+            diff.push(b'\n');
+        }
+        diff.extend_from_slice(&self.hunks);
+
+        match self.patch.config.format_patch {
+            None => {
+                self.patch.wtr.write_all(&diff)?;
+            }
+            Some(ref config) if config.per_file => {
+                write_envelope(
+                    &mut self.patch.wtr,
+                    config,
+                    path,
+                    self.match_count,
+                )?;
+                self.patch.wtr.write_all(&diff)?;
+                write_trailer(&mut self.patch.wtr)?;
+            }
+            Some(_) => {
+                if self.patch.first_path.is_none() {
+                    self.patch.first_path =
+                        Some(self.path.as_bytes().to_vec());
+                }
+                self.patch.total_match_count += self.match_count;
+                self.patch.buf.extend_from_slice(&diff);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write a `git format-patch` style mbox header block for a patch covering
+/// the given path with the given number of matched lines.
+fn write_envelope<W: io::Write>(
+    mut wtr: W,
+    config: &FormatPatchConfig,
+    path: &[u8],
+    match_count: u64,
+) -> io::Result<()> {
+    let subject = config
+        .subject
+        .replace("{path}", &String::from_utf8_lossy(path))
+        .replace("{match_count}", &match_count.to_string());
+    write!(
+        wtr,
+        "From 0000000000000000000000000000000000000000 \
+         Mon Sep 17 00:00:00 2001\n\
+         From: {}\n\
+         Date: Thu, 1 Jan 1970 00:00:00 +0000\n\
+         Subject: [PATCH] {}\n\n---\n",
+        config.author, subject,
+    )
+}
+
+/// Write a `git format-patch` style signature trailer.
+fn write_trailer<W: io::Write>(mut wtr: W) -> io::Result<()> {
+    write!(wtr, "-- \n{}\n\n", env!("CARGO_PKG_VERSION"))
+}
+
+/// Compute the number of common leading and trailing lines of the given
+/// old and new line vectors. The prefix and suffix reported never overlap.
+///
+/// `old_term` and `new_term` indicate whether the data each line vector was
+/// split from ended with a line terminator. Since `split_lines` drops
+/// terminators, the final lines of each side compare equal only when their
+/// terminators agree too.
+fn common_affix(
+    old: &[&[u8]],
+    new: &[&[u8]],
+    old_term: bool,
+    new_term: bool,
+) -> (usize, usize) {
+    let eq = |i: usize, j: usize| {
+        old[i] == new[j]
+            && (i + 1 < old.len() || old_term)
+                == (j + 1 < new.len() || new_term)
+    };
+    let min = std::cmp::min(old.len(), new.len());
+    let mut prefix = 0;
+    while prefix < min && eq(prefix, prefix) {
+        prefix += 1;
+    }
+    let mut suffix = 0;
+    while suffix < min - prefix
+        && eq(old.len() - 1 - suffix, new.len() - 1 - suffix)
+    {
+        suffix += 1;
+    }
+    (prefix, suffix)
+}
+
+/// Split the given data into lines, where each line excludes the line
+/// terminator. A trailing line terminator does not produce an empty final
+/// line.
+fn split_lines(data: &[u8], line_term: u8) -> Vec<&[u8]> {
+    let mut data = data;
+    if data.last() == Some(&line_term) {
+        data = &data[..data.len() - 1];
+    }
+    if data.is_empty() {
+        return vec![];
+    }
+    data.split(|&b| b == line_term).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use grep_regex::RegexMatcher;
+    use grep_searcher::SearcherBuilder;
+
+    use super::*;
+
+    const SHERLOCK: &'static str = "\
+For the Doctor Watsons of this world, as opposed to the Sherlock
+Holmeses, success in the province of detective work must always
+be, to an extent, the result of luck. Sherlock Holmes
+can extract a clew from a wisp of straw or a flake of cigar ash;
+but Doctor Watson has to have it taken out for him and dusted,
+and exhibited clearly, with a label attached.
+";
+
+    fn printer_contents(printer: &mut Patch<Vec<u8>>) -> String {
+        String::from_utf8(printer.get_mut().to_owned()).unwrap()
+    }
+
+    fn search(
+        printer: &mut Patch<Vec<u8>>,
+        pattern: &str,
+        path: &str,
+        haystack: &str,
+        context: usize,
+    ) {
+        let matcher = RegexMatcher::new(pattern).unwrap();
+        SearcherBuilder::new()
+            .line_number(true)
+            .before_context(context)
+            .after_context(context)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink_with_path(&matcher, path),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn replacement() {
+        let mut printer = PatchBuilder::new()
+            .replacement(Some(b"Moriarty".to_vec()))
+            .build(vec![]);
+        search(&mut printer, "Sherlock", "sherlock", SHERLOCK, 1);
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+diff --git a/sherlock b/sherlock
+--- a/sherlock
++++ b/sherlock
+@@ -1,4 +1,4 @@
+-For the Doctor Watsons of this world, as opposed to the Sherlock
++For the Doctor Watsons of this world, as opposed to the Moriarty
+ Holmeses, success in the province of detective work must always
+-be, to an extent, the result of luck. Sherlock Holmes
++be, to an extent, the result of luck. Moriarty Holmes
+ can extract a clew from a wisp of straw or a flake of cigar ash;
+";
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn deletion() {
+        let mut printer = PatchBuilder::new().build(vec![]);
+        search(&mut printer, "Sherlock", "sherlock", SHERLOCK, 0);
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+diff --git a/sherlock b/sherlock
+--- a/sherlock
++++ b/sherlock
+@@ -1,1 +0,0 @@
+-For the Doctor Watsons of this world, as opposed to the Sherlock
+@@ -3,1 +1,0 @@
+-be, to an extent, the result of luck. Sherlock Holmes
+";
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn file_deletion() {
+        use std::process::Command;
+
+        let mut printer =
+            PatchBuilder::new().allow_file_deletion(true).build(vec![]);
+        search(&mut printer, ".*", "sherlock", SHERLOCK, 0);
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+diff --git a/sherlock b/sherlock
+deleted file mode 100644
+--- a/sherlock
++++ /dev/null
+@@ -1,6 +0,0 @@
+-For the Doctor Watsons of this world, as opposed to the Sherlock
+-Holmeses, success in the province of detective work must always
+-be, to an extent, the result of luck. Sherlock Holmes
+-can extract a clew from a wisp of straw or a flake of cigar ash;
+-but Doctor Watson has to have it taken out for him and dusted,
+-and exhibited clearly, with a label attached.
+";
+        assert_eq!(expected, got);
+
+        // And applying the diff removes the file. Skip the application
+        // check when git isn't available.
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!(
+            "grep-printer-deletion-test-{}",
+            std::process::id(),
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("sherlock"), SHERLOCK).unwrap();
+        std::fs::write(dir.join("rg.patch"), &got).unwrap();
+        let out = Command::new("git")
+            .args(["apply", "rg.patch"])
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "git apply failed: {}",
+            String::from_utf8_lossy(&out.stderr),
+        );
+        assert!(!dir.join("sherlock").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_deletion_requires_emptying() {
+        // A replacement that deletes some lines but not all of them emits an
+        // ordinary diff, even when file deletion is allowed.
+        let mut printer =
+            PatchBuilder::new().allow_file_deletion(true).build(vec![]);
+        search(&mut printer, "Sherlock", "sherlock", SHERLOCK, 0);
+        let got = printer_contents(&mut printer);
+        assert!(got.contains("+++ b/sherlock\n"), "got: {got}");
+        assert!(!got.contains("/dev/null"), "got: {got}");
+
+        // Likewise when every line matches but the replacement isn't empty.
+        let mut printer = PatchBuilder::new()
+            .allow_file_deletion(true)
+            .replacement(Some(b"redacted".to_vec()))
+            .build(vec![]);
+        search(&mut printer, ".*", "sherlock", SHERLOCK, 0);
+        let got = printer_contents(&mut printer);
+        assert!(got.contains("+++ b/sherlock\n"), "got: {got}");
+        assert!(!got.contains("/dev/null"), "got: {got}");
+    }
+
+    #[test]
+    fn mark_whole_file() {
+        // The explicit mark is the caller's assertion that its pattern
+        // matched every line, making the deletion independent of the
+        // printer's own coverage accounting.
+        let mut printer =
+            PatchBuilder::new().allow_file_deletion(true).build(vec![]);
+        let matcher = RegexMatcher::new(".*").unwrap();
+        let mut sink = printer.sink_with_path(&matcher, "sherlock");
+        sink.mark_whole_file();
+        SearcherBuilder::new()
+            .line_number(true)
+            .build()
+            .search_reader(&matcher, SHERLOCK.as_bytes(), &mut sink)
+            .unwrap();
+        drop(sink);
+        let got = printer_contents(&mut printer);
+        assert!(got.contains("+++ /dev/null\n"), "got: {got}");
+
+        // Without `allow_file_deletion`, the mark has no effect.
+        let mut printer = PatchBuilder::new().build(vec![]);
+        let mut sink = printer.sink_with_path(&matcher, "sherlock");
+        sink.mark_whole_file();
+        SearcherBuilder::new()
+            .line_number(true)
+            .build()
+            .search_reader(&matcher, SHERLOCK.as_bytes(), &mut sink)
+            .unwrap();
+        drop(sink);
+        let got = printer_contents(&mut printer);
+        assert!(got.contains("+++ b/sherlock\n"), "got: {got}");
+    }
+
+    #[test]
+    fn replacement_changes_line_count() {
+        let mut printer = PatchBuilder::new()
+            .replacement(Some(b"Sherlock\nSherlock".to_vec()))
+            .build(vec![]);
+        search(&mut printer, "Sherlock", "sherlock", SHERLOCK, 0);
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+diff --git a/sherlock b/sherlock
+--- a/sherlock
++++ b/sherlock
+@@ -1,1 +1,2 @@
+-For the Doctor Watsons of this world, as opposed to the Sherlock
++For the Doctor Watsons of this world, as opposed to the Sherlock
++Sherlock
+@@ -3,1 +4,2 @@
+-be, to an extent, the result of luck. Sherlock Holmes
++be, to an extent, the result of luck. Sherlock
++Sherlock Holmes
+";
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn multi_pass_line_offsets() {
+        use std::process::Command;
+
+        // First pass: split line 1 in two, which shifts everything below it
+        // down by one line.
+        let mut printer1 = PatchBuilder::new()
+            .replacement(Some(b"Doctor\nWatsons".to_vec()))
+            .build(vec![]);
+        let matcher1 = RegexMatcher::new("Doctor Watsons").unwrap();
+        let mut sink1 = printer1.sink_with_path(&matcher1, "sherlock");
+        SearcherBuilder::new()
+            .line_number(true)
+            .before_context(1)
+            .after_context(1)
+            .build()
+            .search_reader(&matcher1, SHERLOCK.as_bytes(), &mut sink1)
+            .unwrap();
+        let offset = sink1.line_offset();
+        assert_eq!(1, offset);
+        drop(sink1);
+
+        // Second pass: chain the offset so that the hunk start lines on the
+        // new side account for the first pass's insertion.
+        let mut printer2 = PatchBuilder::new()
+            .replacement(Some(b"Moriarty".to_vec()))
+            .build(vec![]);
+        let matcher2 = RegexMatcher::new("Sherlock Holmes").unwrap();
+        let mut sink2 = printer2.sink_with_path(&matcher2, "sherlock");
+        sink2.set_base_line_offset(offset);
+        SearcherBuilder::new()
+            .line_number(true)
+            .before_context(1)
+            .after_context(1)
+            .build()
+            .search_reader(&matcher2, SHERLOCK.as_bytes(), &mut sink2)
+            .unwrap();
+        assert_eq!(1, sink2.line_offset());
+        drop(sink2);
+
+        let combined =
+            printer_contents(&mut printer1) + &printer_contents(&mut printer2);
+        // The match is on line 3 of the old file, but on line 4 of the new
+        // one thanks to the chained offset. (The hunk starts one line above
+        // the match because of the context line.)
+        assert!(combined.contains("@@ -2,3 +3,3 @@"), "got: {combined}");
+
+        // And the combined diff applies cleanly. Skip the application check
+        // when git isn't available.
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!(
+            "grep-printer-multi-pass-test-{}",
+            std::process::id(),
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("sherlock"), SHERLOCK).unwrap();
+        std::fs::write(dir.join("rg.patch"), &combined).unwrap();
+        let out = Command::new("git")
+            .args(["apply", "rg.patch"])
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "git apply failed: {}",
+            String::from_utf8_lossy(&out.stderr),
+        );
+        let got = std::fs::read_to_string(dir.join("sherlock")).unwrap();
+        let expected = SHERLOCK
+            .replace("Doctor Watsons", "Doctor\nWatsons")
+            .replace("Sherlock Holmes", "Moriarty");
+        assert_eq!(expected, got);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn minimized_hunks() {
+        use std::process::Command;
+
+        let haystack = "\
+alpha
+beta
+gamma
+delta
+epsilon
+";
+        let mut printer = PatchBuilder::new()
+            .replacement(Some(b"alpha\nbeta\nGAMMA\ndelta\nepsilon".to_vec()))
+            .minimized_hunks(true)
+            .build(vec![]);
+        let matcher = RegexMatcher::new(r"(?s)alpha.*epsilon").unwrap();
+        SearcherBuilder::new()
+            .line_number(true)
+            .multi_line(true)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink_with_path(&matcher, "greek"),
+            )
+            .unwrap();
+
+        // Only line 3 of the five matched lines changes, so the rest
+        // become context rather than remove/add pairs.
+        let got = printer_contents(&mut printer);
+        let expected = "\
+diff --git a/greek b/greek
+--- a/greek
++++ b/greek
+@@ -1,5 +1,5 @@
+ alpha
+ beta
+-gamma
++GAMMA
+ delta
+ epsilon
+";
+        assert_eq!(expected, got);
+
+        // And the minimized hunk still applies cleanly. Skip the
+        // application check when git isn't available.
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!(
+            "grep-printer-minimized-test-{}",
+            std::process::id(),
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("greek"), haystack).unwrap();
+        std::fs::write(dir.join("rg.patch"), &got).unwrap();
+        let out = Command::new("git")
+            .args(["apply", "rg.patch"])
+            .current_dir(&dir)
+            .output()
+            .unwrap();
+        assert!(
+            out.status.success(),
+            "git apply failed: {}",
+            String::from_utf8_lossy(&out.stderr),
+        );
+        let applied = std::fs::read_to_string(dir.join("greek")).unwrap();
+        assert_eq!(haystack.replace("gamma", "GAMMA"), applied);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn minimized_hunks_line_count_change() {
+        let haystack = "\
+alpha
+beta
+gamma
+delta
+epsilon
+";
+        let mut printer = PatchBuilder::new()
+            .replacement(Some(
+                b"alpha\nbeta\nGAMMA\nGAMMA2\ndelta\nepsilon".to_vec(),
+            ))
+            .minimized_hunks(true)
+            .build(vec![]);
+        let matcher = RegexMatcher::new(r"(?s)alpha.*epsilon").unwrap();
+        SearcherBuilder::new()
+            .line_number(true)
+            .multi_line(true)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink_with_path(&matcher, "greek"),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+diff --git a/greek b/greek
+--- a/greek
++++ b/greek
+@@ -1,5 +1,6 @@
+ alpha
+ beta
+-gamma
++GAMMA
++GAMMA2
+ delta
+ epsilon
+";
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn identical_replacement() {
+        // A replacement equal to the original text produces no output at
+        // all: no hunks, and crucially no file header either.
+        let mut printer = PatchBuilder::new()
+            .replacement(Some(b"$0".to_vec()))
+            .stats(true)
+            .build(vec![]);
+        let matcher = RegexMatcher::new("Sherlock").unwrap();
+        let mut sink = printer.sink_with_path(&matcher, "sherlock");
+        SearcherBuilder::new()
+            .line_number(true)
+            .before_context(1)
+            .after_context(1)
+            .build()
+            .search_reader(&matcher, SHERLOCK.as_bytes(), &mut sink)
+            .unwrap();
+        assert!(sink.has_match());
+        assert_eq!(0, sink.hunks_written());
+        // Both matches fall into a single contiguous hunk.
+        assert_eq!(1, sink.stats().unwrap().identical_replacements());
+        drop(sink);
+
+        let got = printer_contents(&mut printer);
+        assert_eq!("", got);
+    }
+
+    #[test]
+    fn identical_replacement_mixed() {
+        // When only some hunks are no-ops, the others are still emitted.
+        // `$1` reproduces a `Sherlock Holmes` match exactly, but erases a
+        // `Watsons` match.
+        let mut printer = PatchBuilder::new()
+            .replacement(Some(b"$1".to_vec()))
+            .build(vec![]);
+        search(
+            &mut printer,
+            "(Sherlock Holmes)|Watsons",
+            "sherlock",
+            SHERLOCK,
+            0,
+        );
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+diff --git a/sherlock b/sherlock
+--- a/sherlock
++++ b/sherlock
+@@ -1,1 +1,1 @@
+-For the Doctor Watsons of this world, as opposed to the Sherlock
++For the Doctor  of this world, as opposed to the Sherlock
+";
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn format_patch_per_file() {
+        let mut printer = PatchBuilder::new()
+            .replacement(Some(b"Moriarty".to_vec()))
+            .format_patch(Some(FormatPatchConfig {
+                subject: "replace in {path} ({match_count} lines)".to_string(),
+                per_file: true,
+                ..FormatPatchConfig::default()
+            }))
+            .build(vec![]);
+        search(&mut printer, "Sherlock", "sherlock", SHERLOCK, 1);
+
+        let got = printer_contents(&mut printer);
+        assert!(
+            got.starts_with("From 0000000000000000000000000000000000000000")
+        );
+        assert!(got.contains("From: ripgrep <ripgrep@localhost>\n"));
+        assert!(
+            got.contains("Subject: [PATCH] replace in sherlock (2 lines)\n")
+        );
+        assert!(got.contains("\n-- \n"));
+    }
+
+    #[test]
+    fn format_patch_per_run() {
+        let mut printer = PatchBuilder::new()
+            .replacement(Some(b"Moriarty".to_vec()))
+            .format_patch(Some(FormatPatchConfig {
+                subject: "replace ({match_count} lines)".to_string(),
+                ..FormatPatchConfig::default()
+            }))
+            .build(vec![]);
+        search(&mut printer, "Sherlock", "sherlock1", SHERLOCK, 1);
+        search(&mut printer, "Sherlock", "sherlock2", SHERLOCK, 1);
+        // Nothing is written until the run is finished.
+        assert!(!printer.has_written());
+        printer.finish().unwrap();
+
+        let got = printer_contents(&mut printer);
+        assert_eq!(
+            1,
+            got.matches("From 0000000000000000000000000000000000000000")
+                .count()
+        );
+        assert!(got.contains("Subject: [PATCH] replace (4 lines)\n"));
+        assert!(got.contains("diff --git a/sherlock1 b/sherlock1\n"));
+        assert!(got.contains("diff --git a/sherlock2 b/sherlock2\n"));
+    }
+
+    #[test]
+    fn flush_into_clears_buffer_and_keeps_totals() {
+        let bufwtr =
+            termcolor::BufferWriter::stdout(termcolor::ColorChoice::Never);
+        let mut printer =
+            PatchBuilder::new().build(termcolor::Buffer::no_color());
+        let matcher = RegexMatcher::new("a").unwrap();
+        SearcherBuilder::new()
+            .line_number(true)
+            .build()
+            .search_reader(
+                &matcher,
+                "a\n".as_bytes(),
+                printer.sink_with_path(&matcher, "f"),
+            )
+            .unwrap();
+        assert!(!printer.get_mut().as_slice().is_empty());
+
+        // This prints the (tiny) diff to stdout, which is the only thing a
+        // BufferWriter can write to.
+        printer.flush_into(&bufwtr).unwrap();
+        // The buffer is cleared for reuse, but the totals survive.
+        assert!(printer.get_mut().as_slice().is_empty());
+        assert!(printer.has_written());
+    }
+
+    #[test]
+    fn parallel_flush_does_not_interleave() {
+        use std::sync::{Arc, Mutex};
+
+        let merged = Arc::new(Mutex::new(String::new()));
+        let mut handles = vec![];
+        for i in 0..4 {
+            let merged = Arc::clone(&merged);
+            handles.push(std::thread::spawn(move || {
+                let path = format!("file{}", i);
+                let mut printer =
+                    PatchBuilder::new().build(termcolor::Buffer::no_color());
+                let matcher = RegexMatcher::new("Sherlock").unwrap();
+                SearcherBuilder::new()
+                    .line_number(true)
+                    .build()
+                    .search_reader(
+                        &matcher,
+                        SHERLOCK.as_bytes(),
+                        printer.sink_with_path(&matcher, &path),
+                    )
+                    .unwrap();
+                // Record what this thread would flush, mimicking the
+                // atomic append that BufferWriter::print performs on its
+                // underlying writer. (An actual BufferWriter prints to
+                // stdout or stderr, which a test shouldn't pollute.)
+                let mut merged = merged.lock().unwrap();
+                merged.push_str(
+                    std::str::from_utf8(printer.get_mut().as_slice()).unwrap(),
+                );
+            }));
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // Every section from one `diff --git` header to the next must
+        // belong to a single file.
+        let merged = merged.lock().unwrap();
+        let sections: Vec<&str> =
+            merged.split("diff --git ").skip(1).collect();
+        assert_eq!(4, sections.len());
+        for section in sections {
+            let name = section
+                .split_whitespace()
+                .next()
+                .unwrap()
+                .strip_prefix("a/")
+                .unwrap();
+            assert!(section.contains(&format!("--- a/{}\n", name)));
+            assert!(section.contains(&format!("+++ b/{}\n", name)));
+            for other in 0..4 {
+                let other = format!("file{}", other);
+                if other != name {
+                    assert!(!section.contains(&other));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn format_patch_applies_with_git_am() {
+        use std::process::Command;
+
+        // Skip this test when git isn't available.
+        if Command::new("git").arg("--version").output().is_err() {
+            return;
+        }
+        let dir = std::env::temp_dir()
+            .join(format!("grep-printer-patch-test-{}", std::process::id(),));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let git = |args: &[&str]| {
+            let out = Command::new("git")
+                .args(args)
+                .current_dir(&dir)
+                .output()
+                .unwrap();
+            assert!(
+                out.status.success(),
+                "git {:?} failed: {}",
+                args,
+                String::from_utf8_lossy(&out.stderr),
+            );
+        };
+        git(&["init", "-q"]);
+        git(&["config", "user.name", "test"]);
+        git(&["config", "user.email", "test@example.com"]);
+        std::fs::write(dir.join("sherlock"), SHERLOCK).unwrap();
+        git(&["add", "sherlock"]);
+        git(&["commit", "-q", "-m", "add sherlock"]);
+
+        let mut printer = PatchBuilder::new()
+            .replacement(Some(b"Moriarty".to_vec()))
+            .format_patch(Some(FormatPatchConfig::default()))
+            .build(vec![]);
+        search(&mut printer, "Sherlock", "sherlock", SHERLOCK, 1);
+        printer.finish().unwrap();
+        std::fs::write(dir.join("rg.patch"), printer.get_mut()).unwrap();
+
+        git(&["am", "rg.patch"]);
+        let got = std::fs::read_to_string(dir.join("sherlock")).unwrap();
+        assert_eq!(SHERLOCK.replace("Sherlock", "Moriarty"), got);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,382 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use {
+    grep_matcher::Matcher,
+    grep_searcher::{Searcher, Sink, SinkFinish, SinkMatch},
+};
+
+use crate::{stats::Stats, util::find_iter_at_in_context};
+
+/// The configuration for the quiet printer.
+///
+/// This is manipulated by the QuietBuilder and then referenced by the actual
+/// implementation. Once a printer is built, the configuration is frozen and
+/// cannot changed.
+///
+/// Note that the matched flag lives in the configuration so that every
+/// printer built from the same builder (or a clone of it) shares one flag.
+#[derive(Clone, Debug)]
+struct Config {
+    stats: bool,
+    matched: Arc<AtomicBool>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config { stats: false, matched: Arc::new(AtomicBool::new(false)) }
+    }
+}
+
+/// A builder for the "quiet" printer.
+///
+/// The quiet printer writes nothing. Its only observable effect is setting a
+/// shared flag as soon as any search it participates in finds a match, which
+/// makes it useful for implementing exit-status-only semantics (like grep's
+/// `-q/--quiet` flag) across many files and threads.
+#[derive(Clone, Debug)]
+pub struct QuietBuilder {
+    config: Config,
+}
+
+impl QuietBuilder {
+    /// Return a new builder for configuring the quiet printer.
+    pub fn new() -> QuietBuilder {
+        QuietBuilder { config: Config::default() }
+    }
+
+    /// Build a quiet printer.
+    ///
+    /// Every printer built from this builder (including clones of it) shares
+    /// the same matched flag, so a match found through any of them stops the
+    /// searches driven by all of them. This is how one flag is shared across
+    /// the per-thread printers of a parallel search.
+    pub fn build(&self) -> Quiet {
+        let stats = if self.config.stats { Some(Stats::new()) } else { None };
+        Quiet { config: self.config.clone(), stats }
+    }
+
+    /// Enable the gathering of various aggregate statistics.
+    ///
+    /// When this is enabled (it's disabled by default), statistics will be
+    /// gathered for all uses of the quiet printer, even though nothing is
+    /// ever printed. Since accurate statistics require visiting every match,
+    /// enabling them also disables the early termination that the quiet
+    /// printer otherwise performs: searches run to completion and the
+    /// matched flag merely records that something matched.
+    pub fn stats(&mut self, yes: bool) -> &mut QuietBuilder {
+        self.config.stats = yes;
+        self
+    }
+
+    /// Use the given flag for reporting matches instead of a fresh one.
+    ///
+    /// This is useful when the flag outlives the printer, e.g., when an
+    /// orchestrator owns it and consults it to decide whether to schedule
+    /// more files at all.
+    pub fn matched_flag(
+        &mut self,
+        flag: Arc<AtomicBool>,
+    ) -> &mut QuietBuilder {
+        self.config.matched = flag;
+        self
+    }
+}
+
+/// The quiet printer, which never prints anything.
+///
+/// On the first match found through any of its sinks, it sets a shared flag
+/// (see [`Quiet::matched_flag`]) and, unless statistics were requested,
+/// terminates the search that found the match. Once the flag is set,
+/// subsequent searches through this printer (or any printer sharing the
+/// flag) stop before reading anything.
+#[derive(Debug)]
+pub struct Quiet {
+    config: Config,
+    stats: Option<Stats>,
+}
+
+impl Quiet {
+    /// Return a quiet printer with a default configuration.
+    pub fn new() -> Quiet {
+        QuietBuilder::new().build()
+    }
+
+    /// Return an implementation of `Sink` for the quiet printer.
+    ///
+    /// Since the quiet printer never prints, there is no variant of this
+    /// method that associates the search with a file path.
+    pub fn sink<'s, M: Matcher>(&'s mut self, matcher: M) -> QuietSink<'s, M> {
+        QuietSink {
+            matcher,
+            quiet: self,
+            match_count: 0,
+            start_time: Instant::now(),
+        }
+    }
+
+    /// Returns true if and only if a match was found through this printer or
+    /// any printer sharing its matched flag.
+    pub fn has_match(&self) -> bool {
+        self.config.matched.load(Ordering::SeqCst)
+    }
+
+    /// Return the flag that is set as soon as anything matches.
+    ///
+    /// An orchestrator can poll this flag to avoid scheduling searches whose
+    /// results no longer matter, and can hand clones of it to the builders
+    /// of printers whose searches should stop as well.
+    pub fn matched_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.config.matched)
+    }
+
+    /// Return a reference to the stats produced by the printer for all
+    /// searches executed on it.
+    ///
+    /// This only returns stats if they were requested via the
+    /// [`QuietBuilder::stats`] method.
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+}
+
+/// An implementation of `Sink` associated with the quiet printer.
+///
+/// This type is generic over `M`, which represents the matcher used in the
+/// search. The matcher is only consulted when statistics are enabled, in
+/// order to count every match rather than every matching line.
+#[derive(Debug)]
+pub struct QuietSink<'s, M: Matcher> {
+    matcher: M,
+    quiet: &'s mut Quiet,
+    match_count: u64,
+    start_time: Instant,
+}
+
+impl<'s, M: Matcher> QuietSink<'s, M> {
+    /// Returns true if and only if a match was found through this printer or
+    /// any printer sharing its matched flag.
+    pub fn has_match(&self) -> bool {
+        self.quiet.has_match()
+    }
+}
+
+impl<'s, M: Matcher> Sink for QuietSink<'s, M> {
+    type Error = io::Error;
+
+    fn matched(
+        &mut self,
+        searcher: &Searcher,
+        mat: &SinkMatch<'_>,
+    ) -> Result<bool, io::Error> {
+        self.quiet.config.matched.store(true, Ordering::SeqCst);
+        let stats = match self.quiet.stats {
+            None => return Ok(false),
+            Some(ref mut stats) => stats,
+        };
+        let buf = mat.buffer();
+        let range = mat.bytes_range_in_buffer();
+        let mut count = 0;
+        find_iter_at_in_context(searcher, &self.matcher, buf, range, |_| {
+            count += 1;
+            true
+        })?;
+        self.match_count += count;
+        stats.add_matches(count);
+        stats.add_matched_lines(mat.lines().count() as u64);
+        Ok(true)
+    }
+
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        self.match_count = 0;
+        self.start_time = Instant::now();
+        // Accurate statistics require searching everything, but otherwise
+        // a match anywhere makes this search's outcome irrelevant.
+        if self.quiet.stats.is_none() && self.quiet.has_match() {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn finish(
+        &mut self,
+        _searcher: &Searcher,
+        finish: &SinkFinish,
+    ) -> Result<(), io::Error> {
+        if let Some(ref mut stats) = self.quiet.stats {
+            stats.add_elapsed(self.start_time.elapsed());
+            stats.add_searches(1);
+            if self.match_count > 0 {
+                stats.add_searches_with_match(1);
+            }
+            stats.add_bytes_searched(finish.byte_count());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use grep_regex::RegexMatcher;
+    use grep_searcher::SearcherBuilder;
+
+    use super::QuietBuilder;
+
+    const SHERLOCK: &'static str = "\
+For the Doctor Watsons of this world, as opposed to the Sherlock
+Holmeses, success in the province of detective work must always
+be, to a very large extent, the result of luck. Sherlock Holmes
+can extract a clew from a wisp of straw or a flake of cigar ash;
+but Doctor Watson has to have it taken out for him and dusted,
+and exhibited clearly, with a label attached.
+";
+
+    /// A reader that yields one chunk of non-matching data and then errors,
+    /// for asserting that a search stopped early. (The searcher sniffs a
+    /// reader for a BOM before the sink is consulted, so the first read
+    /// always happens; anything past it means the search actually ran.)
+    struct PoisonedReader {
+        chunk: &'static [u8],
+    }
+
+    impl PoisonedReader {
+        fn new() -> PoisonedReader {
+            PoisonedReader { chunk: b"nothing to see\n" }
+        }
+    }
+
+    impl io::Read for PoisonedReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.chunk.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::Other, "poisoned"));
+            }
+            let len = std::cmp::min(buf.len(), self.chunk.len());
+            buf[..len].copy_from_slice(&self.chunk[..len]);
+            self.chunk = &self.chunk[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn quiet_stops_in_flight_searches() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut quiet = QuietBuilder::new().build();
+        let mut searcher = SearcherBuilder::new().build();
+
+        // Without the flag set, the search runs to completion and hits the
+        // reader's error.
+        assert!(searcher
+            .search_reader(
+                &matcher,
+                PoisonedReader::new(),
+                quiet.sink(&matcher)
+            )
+            .is_err());
+        assert!(!quiet.has_match());
+
+        searcher
+            .search_reader(&matcher, SHERLOCK.as_bytes(), quiet.sink(&matcher))
+            .unwrap();
+        assert!(quiet.has_match());
+
+        // Once the flag is set, `begin` returns false, so a subsequent
+        // search through the same printer stops before reading past the
+        // initial sniff and never sees the error.
+        searcher
+            .search_reader(
+                &matcher,
+                PoisonedReader::new(),
+                quiet.sink(&matcher),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn quiet_flag_shared_across_printers() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let builder = QuietBuilder::new();
+        let mut quiet1 = builder.build();
+        let mut quiet2 = builder.clone().build();
+        let mut searcher = SearcherBuilder::new().build();
+
+        searcher
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                quiet1.sink(&matcher),
+            )
+            .unwrap();
+        // The match found through the first printer is visible through the
+        // second, and stops its searches too.
+        assert!(quiet2.has_match());
+        searcher
+            .search_reader(
+                &matcher,
+                PoisonedReader::new(),
+                quiet2.sink(&matcher),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn quiet_skips_scheduling_once_matched() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut quiet = QuietBuilder::new().build();
+        let flag = quiet.matched_flag();
+        let mut searcher = SearcherBuilder::new().build();
+
+        // An orchestrator consults the flag before scheduling each file, so
+        // only the first file (in scheduling order) is searched.
+        let files = [SHERLOCK, SHERLOCK, SHERLOCK];
+        let mut searched = 0;
+        for haystack in files {
+            if flag.load(std::sync::atomic::Ordering::SeqCst) {
+                break;
+            }
+            searched += 1;
+            searcher
+                .search_reader(
+                    &matcher,
+                    haystack.as_bytes(),
+                    quiet.sink(&matcher),
+                )
+                .unwrap();
+        }
+        assert_eq!(1, searched);
+        assert!(quiet.has_match());
+    }
+
+    #[test]
+    fn quiet_with_stats() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut quiet = QuietBuilder::new().stats(true).build();
+        let mut searcher = SearcherBuilder::new().build();
+
+        // With stats enabled, searches run to completion even after the
+        // flag is set, and statistics accumulate across searches.
+        for haystack in [SHERLOCK, SHERLOCK] {
+            searcher
+                .search_reader(
+                    &matcher,
+                    haystack.as_bytes(),
+                    quiet.sink(&matcher),
+                )
+                .unwrap();
+        }
+        assert!(quiet.has_match());
+        let stats = quiet.stats().unwrap();
+        assert_eq!(2, stats.searches());
+        assert_eq!(2, stats.searches_with_match());
+        assert_eq!(4, stats.matches());
+        assert_eq!(4, stats.matched_lines());
+        assert_eq!(2 * SHERLOCK.len() as u64, stats.bytes_searched());
+    }
+}
@@ -4,7 +4,7 @@ use std::{
     io::{self, Write},
     path::Path,
     sync::Arc,
-    time::Instant,
+    time::Duration,
 };
 
 use {
@@ -18,13 +18,14 @@ use {
 };
 
 use crate::{
-    color::ColorSpecs,
+    color::{ColorDepth, ColorSpecs},
     counter::CounterWriter,
     hyperlink::{self, HyperlinkConfig},
     stats::Stats,
     util::{
-        find_iter_at_in_context, trim_ascii_prefix, trim_line_terminator,
-        DecimalFormatter, PrinterPath, Replacer, Sunk,
+        find_iter_at_in_context, slow_file_warning, trim_ascii_prefix,
+        trim_line_terminator, DecimalFormatter, PathDisplay, PrinterPath,
+        Replacer, SlowFileWriter, Sunk, TimeSource,
     },
 };
 
@@ -36,6 +37,7 @@ use crate::{
 #[derive(Debug, Clone)]
 struct Config {
     colors: ColorSpecs,
+    color_depth: ColorDepth,
     hyperlink: HyperlinkConfig,
     stats: bool,
     heading: bool,
@@ -46,22 +48,39 @@ struct Config {
     replacement: Arc<Option<Vec<u8>>>,
     max_columns: Option<u64>,
     max_columns_preview: bool,
+    match_window: Option<usize>,
+    match_window_count: usize,
+    long_line_warnings: bool,
     max_matches: Option<u64>,
+    max_matches_per_line: Option<usize>,
+    dedupe_lines: bool,
+    dedupe_lines_limit: usize,
     column: bool,
     byte_offset: bool,
     trim_ascii: bool,
+    mark_approximate_lines: bool,
+    report_binary_skips: bool,
     separator_search: Arc<Option<Vec<u8>>>,
     separator_context: Arc<Option<Vec<u8>>>,
     separator_field_match: Arc<Vec<u8>>,
     separator_field_context: Arc<Vec<u8>>,
+    before_context_prefix: Option<Arc<Vec<u8>>>,
+    after_context_prefix: Option<Arc<Vec<u8>>>,
     separator_path: Option<u8>,
     path_terminator: Option<u8>,
+    path_display: PathDisplay,
+    file_trailer: Option<Vec<u8>>,
+    slow_file_threshold: Option<Duration>,
+    slow_file_writer: Option<SlowFileWriter>,
+    time_source: TimeSource,
+    output_bom: bool,
 }
 
 impl Default for Config {
     fn default() -> Config {
         Config {
             colors: ColorSpecs::default(),
+            color_depth: ColorDepth::default(),
             hyperlink: HyperlinkConfig::default(),
             stats: false,
             heading: false,
@@ -72,16 +91,32 @@ impl Default for Config {
             replacement: Arc::new(None),
             max_columns: None,
             max_columns_preview: false,
+            match_window: None,
+            match_window_count: 3,
+            long_line_warnings: false,
             max_matches: None,
+            max_matches_per_line: None,
+            dedupe_lines: false,
+            dedupe_lines_limit: 10_000,
             column: false,
             byte_offset: false,
             trim_ascii: false,
+            mark_approximate_lines: false,
+            report_binary_skips: false,
             separator_search: Arc::new(None),
             separator_context: Arc::new(Some(b"--".to_vec())),
             separator_field_match: Arc::new(b":".to_vec()),
             separator_field_context: Arc::new(b"-".to_vec()),
+            before_context_prefix: None,
+            after_context_prefix: None,
             separator_path: None,
             path_terminator: None,
+            path_display: PathDisplay::default(),
+            file_trailer: None,
+            slow_file_threshold: None,
+            slow_file_writer: None,
+            time_source: TimeSource::default(),
+            output_bom: false,
         }
     }
 }
@@ -128,8 +163,10 @@ impl StandardBuilder {
     /// select the `termcolor::NoColor` wrapper to avoid needing to import
     /// from `termcolor` explicitly.
     pub fn build<W: WriteColor>(&self, wtr: W) -> Standard<W> {
+        let mut config = self.config.clone();
+        config.colors = config.colors.downgrade(config.color_depth.resolve());
         Standard {
-            config: self.config.clone(),
+            config,
             wtr: RefCell::new(CounterWriter::new(wtr)),
             matches: vec![],
         }
@@ -169,6 +206,22 @@ impl StandardBuilder {
         self
     }
 
+    /// Set the maximum color depth to emit.
+    ///
+    /// When a color specification requires more colors than the effective
+    /// depth provides, it is quantized to the nearest representable color
+    /// when this printer is built. For example, a 24-bit color specification
+    /// like `match:fg:0xff,0x7f,0x00` is mapped to the nearest entry of the
+    /// 256-color palette when the depth is [`ColorDepth::Ansi256`].
+    ///
+    /// The default is [`ColorDepth::Auto`], which detects the depth from the
+    /// `COLORTERM` and `TERM` environment variables when the printer is
+    /// built.
+    pub fn color_depth(&mut self, depth: ColorDepth) -> &mut StandardBuilder {
+        self.config.color_depth = depth;
+        self
+    }
+
     /// Set the configuration to use for hyperlinks output by this printer.
     ///
     /// Regardless of the hyperlink format provided here, whether hyperlinks
@@ -208,6 +261,52 @@ impl StandardBuilder {
         self
     }
 
+    /// Set a threshold above which a search is reported as slow.
+    ///
+    /// When set, a warning line is emitted at the end of any single search
+    /// whose elapsed time exceeds the threshold, containing the path, the
+    /// elapsed time, the number of bytes searched and the search rate in
+    /// bytes per second. The warning is written to the writer set via
+    /// [`StandardBuilder::slow_file_writer`], or to the main writer prefixed
+    /// with `# slow: ` when no such writer was set.
+    ///
+    /// This is disabled by default.
+    pub fn slow_file_threshold(
+        &mut self,
+        threshold: Option<Duration>,
+    ) -> &mut StandardBuilder {
+        self.config.slow_file_threshold = threshold;
+        self
+    }
+
+    /// Set a secondary writer that slow file warnings are written to.
+    ///
+    /// This has no effect unless a threshold is set via
+    /// [`StandardBuilder::slow_file_threshold`]. When no secondary writer is
+    /// set (the default), warnings are written to the main writer.
+    pub fn slow_file_writer(
+        &mut self,
+        wtr: Option<Box<dyn io::Write + Send>>,
+    ) -> &mut StandardBuilder {
+        self.config.slow_file_writer = wtr.map(SlowFileWriter::new);
+        self
+    }
+
+    /// Set the source of time used to measure how long each search took.
+    ///
+    /// The function given should report the time elapsed since some fixed
+    /// origin of its own choosing; only differences between readings are
+    /// used. The default reads the system's monotonic clock. This exists
+    /// principally so that tests can exercise slow file reporting
+    /// deterministically.
+    pub fn time_source<F>(&mut self, f: F) -> &mut StandardBuilder
+    where
+        F: Fn() -> Duration + Send + Sync + 'static,
+    {
+        self.config.time_source = TimeSource::new(f);
+        self
+    }
+
     /// Enable the use of "headings" in the printer.
     ///
     /// When this is enabled, and if a file path has been given to the printer,
@@ -223,6 +322,30 @@ impl StandardBuilder {
         self
     }
 
+    /// Set a trailer line to print after all of a file's matches.
+    ///
+    /// The trailer is only printed when headings are enabled (see
+    /// [`StandardBuilder::heading`]) and at least one match was printed for
+    /// the file. The following placeholders are interpolated in the trailer:
+    /// `{matches}` is replaced with the total number of matches, and
+    /// `{matched_lines}` with the total number of lines participating in a
+    /// match. `{path}` is replaced with the file path, if one was given to
+    /// the printer, and respects the configured path terminator (see
+    /// [`StandardBuilder::path_terminator`]).
+    ///
+    /// Placeholders are replaced verbatim, so any singular/plural handling is
+    /// up to the trailer given. The trailer is colored with the `line` color
+    /// specification.
+    ///
+    /// By default, no trailer is printed.
+    pub fn file_trailer(
+        &mut self,
+        trailer: Option<Vec<u8>>,
+    ) -> &mut StandardBuilder {
+        self.config.file_trailer = trailer;
+        self
+    }
+
     /// When enabled, if a path was given to the printer, then it is shown in
     /// the output (either as a heading or as a prefix to each matching line).
     /// When disabled, then no paths are ever included in the output even when
@@ -326,6 +449,88 @@ impl StandardBuilder {
         self
     }
 
+    /// Set the number of bytes of a long line shown around each match.
+    ///
+    /// This is only used when a line exceeds the limit set by `max_columns`
+    /// and at least one match falls on the line. When set, instead of
+    /// omitting the line entirely (or printing a preview of its beginning,
+    /// as with `max_columns_preview`), a window of approximately the given
+    /// number of bytes centered on each match is printed, with `…` markers
+    /// standing in for the elided portions. Overlapping windows are merged.
+    /// This makes matches deep inside very long lines (e.g., in minified
+    /// files) visible without printing the whole line.
+    ///
+    /// The number of windows printed per line is capped by
+    /// [`StandardBuilder::match_window_count`], and matches beyond the cap
+    /// are reported in a trailing marker. The column number reported in the
+    /// prelude is unaffected by windowing: it always refers to the true
+    /// position of the match on the line.
+    ///
+    /// This takes precedence over `max_columns_preview` for lines with
+    /// matches. Lines without matches (e.g., context lines) fall back to
+    /// the usual long line handling.
+    ///
+    /// This is disabled by default.
+    pub fn match_window(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut StandardBuilder {
+        self.config.match_window = limit;
+        self
+    }
+
+    /// Set the maximum number of match windows printed per line.
+    ///
+    /// This is only used when a window size is set via
+    /// [`StandardBuilder::match_window`]. A value of `0` is treated as `1`.
+    ///
+    /// The default is `3`.
+    pub fn match_window_count(
+        &mut self,
+        limit: usize,
+    ) -> &mut StandardBuilder {
+        self.config.match_window_count = limit;
+        self
+    }
+
+    /// When enabled, print a warning whenever the searcher reports that it
+    /// skipped a line because it was longer than the searcher's configured
+    /// maximum line length.
+    ///
+    /// Skipped lines are reported by the searcher via
+    /// `grep_searcher::Sink::line_too_long`, which only occurs when a
+    /// maximum line length is set on the searcher. The warning includes the
+    /// approximate length of the skipped line and the offset at which it
+    /// started.
+    ///
+    /// This is disabled by default, in which case skipped lines are silently
+    /// ignored by this printer.
+    pub fn long_line_warnings(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.long_line_warnings = yes;
+        self
+    }
+
+    /// Set the maximum number of matches that are highlighted on each line.
+    ///
+    /// Pathological inputs such as minified files can have thousands of
+    /// matches on a single line, where emitting a color escape for every
+    /// match is costly and not useful. When this is set, only the first
+    /// `limit` matches on a line are highlighted, followed by a dim
+    /// `… +N more matches` marker. The line contents themselves are still
+    /// printed in full, and match counts reported in statistics remain
+    /// accurate.
+    ///
+    /// This only has an effect when coloring is enabled.
+    ///
+    /// By default, there is no limit.
+    pub fn max_matches_per_line(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut StandardBuilder {
+        self.config.max_matches_per_line = limit;
+        self
+    }
+
     /// Set the maximum amount of matching lines that are printed.
     ///
     /// If multi line search is enabled and a match spans multiple lines, then
@@ -336,6 +541,41 @@ impl StandardBuilder {
         self
     }
 
+    /// Print each distinct matching line at most once per search.
+    ///
+    /// When enabled, a matching line whose bytes are identical to a
+    /// previously printed matching line in the same search is suppressed.
+    /// After the search finishes, a trailer is printed for each line with
+    /// suppressed repeats indicating how many times it repeated. This is
+    /// useful for, e.g., log files that contain the same matching line many
+    /// thousands of times.
+    ///
+    /// Suppressed lines are tracked by a 128-bit hash of their contents, so
+    /// memory use is independent of the lengths of the matching lines. See
+    /// [`StandardBuilder::dedupe_lines_limit`] for bounding the number of
+    /// distinct lines tracked.
+    ///
+    /// This is disabled by default.
+    pub fn dedupe_lines(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.dedupe_lines = yes;
+        self
+    }
+
+    /// Set the maximum number of distinct matching lines tracked when line
+    /// deduplication is enabled.
+    ///
+    /// If a search reports more than this many distinct matching lines, then
+    /// deduplication stops tracking new lines for the remainder of that
+    /// search and prints them as they are. Lines already being tracked
+    /// continue to be suppressed and reported in the trailer. This bounds the
+    /// memory used by [`StandardBuilder::dedupe_lines`].
+    ///
+    /// The default limit is 10,000 distinct lines.
+    pub fn dedupe_lines_limit(&mut self, limit: usize) -> &mut StandardBuilder {
+        self.config.dedupe_lines_limit = limit;
+        self
+    }
+
     /// Print the column number of the first match in a line.
     ///
     /// This option is convenient for use with `per_match` which will print a
@@ -353,7 +593,10 @@ impl StandardBuilder {
     /// Print the absolute byte offset of the beginning of each line printed.
     ///
     /// The absolute byte offset starts from the beginning of each search and
-    /// is zero based.
+    /// is zero based. If the searcher transcoded the data before searching
+    /// (e.g., because of a UTF-16 byte-order mark), then the offset is into
+    /// the transcoded data, exactly as reported by the JSON printer's
+    /// `absolute_offset` field.
     ///
     /// If the `only_matching` option is set, then this will print the absolute
     /// byte offset of the beginning of each match.
@@ -371,6 +614,43 @@ impl StandardBuilder {
         self
     }
 
+    /// When enabled, line numbers for matches that occur after a binary byte
+    /// was converted to a line terminator are written with a trailing `~`.
+    ///
+    /// Converted bytes do not create new lines, but they may merge or split
+    /// the lines a user sees when opening the raw contents of a file in an
+    /// editor. The `~` gives users an indication that such line numbers are
+    /// approximate. This only has an effect when the searcher is configured
+    /// with [`BinaryDetection::convert`](grep_searcher::BinaryDetection).
+    ///
+    /// Enabling this also keeps printing matches after binary data has been
+    /// detected and converted, instead of suppressing the remainder of the
+    /// search with a `binary file matches` message.
+    ///
+    /// This is disabled by default.
+    pub fn mark_approximate_lines(
+        &mut self,
+        yes: bool,
+    ) -> &mut StandardBuilder {
+        self.config.mark_approximate_lines = yes;
+        self
+    }
+
+    /// Report files that were skipped because binary data was detected.
+    ///
+    /// When the searcher's binary detection method is to quit, a file in
+    /// which binary data is found before any match produces no output at
+    /// all; the existing `stopped searching binary file` message is only
+    /// printed when a match was found first. When this is enabled, the
+    /// no-match case prints a `binary file skipped (offset N)` message
+    /// instead of staying silent.
+    ///
+    /// This is disabled by default.
+    pub fn report_binary_skips(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.report_binary_skips = yes;
+        self
+    }
+
     /// Set the separator used between sets of search results.
     ///
     /// When this is set, then it will be printed on its own line immediately
@@ -441,6 +721,40 @@ impl StandardBuilder {
         self
     }
 
+    /// Set the field separator used specifically for context lines that
+    /// appear before a match.
+    ///
+    /// This overrides the separator set by `separator_field_context` for
+    /// before-context lines only. This is useful for consumers (such as
+    /// editor integrations) that want to render before and after context
+    /// differently.
+    ///
+    /// By default, this is not set, and before-context lines use the
+    /// separator given to `separator_field_context` (`-` by default).
+    pub fn before_context_prefix(
+        &mut self,
+        prefix: Vec<u8>,
+    ) -> &mut StandardBuilder {
+        self.config.before_context_prefix = Some(Arc::new(prefix));
+        self
+    }
+
+    /// Set the field separator used specifically for context lines that
+    /// appear after a match.
+    ///
+    /// This overrides the separator set by `separator_field_context` for
+    /// after-context lines only.
+    ///
+    /// By default, this is not set, and after-context lines use the
+    /// separator given to `separator_field_context` (`-` by default).
+    pub fn after_context_prefix(
+        &mut self,
+        prefix: Vec<u8>,
+    ) -> &mut StandardBuilder {
+        self.config.after_context_prefix = Some(Arc::new(prefix));
+        self
+    }
+
     /// Set the path separator used when printing file paths.
     ///
     /// When a printer is configured with a file path, and when a match is
@@ -474,6 +788,39 @@ impl StandardBuilder {
         self.config.path_terminator = terminator;
         self
     }
+
+    /// Set the path display configuration used by this printer.
+    ///
+    /// This controls transformations applied to file paths before they are
+    /// printed, such as canonicalization or abbreviating a home directory
+    /// prefix with `~`. See [`PathDisplay`](crate::PathDisplay).
+    ///
+    /// Transformations only apply to how paths are displayed. Hyperlinks
+    /// continue to carry the full path.
+    ///
+    /// By default, paths are printed as given.
+    pub fn path_display(
+        &mut self,
+        display: PathDisplay,
+    ) -> &mut StandardBuilder {
+        self.config.path_display = display;
+        self
+    }
+
+    /// When enabled, write a UTF-8 byte order mark before the first byte of
+    /// output.
+    ///
+    /// Some Windows tools (such as PowerShell pipelines or legacy editors)
+    /// misinterpret UTF-8 output unless it begins with a BOM. The BOM is
+    /// written exactly once per printer, immediately before the first byte
+    /// of output, even when the printer is used for multiple searches. When
+    /// a printer produces no output at all, no BOM is written either.
+    ///
+    /// This is disabled by default.
+    pub fn output_bom(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.output_bom = yes;
+        self
+    }
 }
 
 /// The standard printer, which implements grep-like formatting, including
@@ -533,17 +880,21 @@ impl<W: WriteColor> Standard<W> {
             hyperlink::Interpolator::new(&self.config.hyperlink);
         let stats = if self.config.stats { Some(Stats::new()) } else { None };
         let needs_match_granularity = self.needs_match_granularity();
+        let start_time = self.config.time_source.now();
         StandardSink {
             matcher,
             standard: self,
             replacer: Replacer::new(),
             interpolator,
             path: None,
-            start_time: Instant::now(),
+            start_time,
             match_count: 0,
             after_context_remaining: 0,
             binary_byte_offset: None,
             stats,
+            dedupe: None,
+            trailer_matches: 0,
+            trailer_matched_lines: 0,
             needs_match_granularity,
         }
     }
@@ -568,19 +919,24 @@ impl<W: WriteColor> Standard<W> {
             hyperlink::Interpolator::new(&self.config.hyperlink);
         let stats = if self.config.stats { Some(Stats::new()) } else { None };
         let ppath = PrinterPath::new(path.as_ref())
+            .with_display(&self.config.path_display)
             .with_separator(self.config.separator_path);
         let needs_match_granularity = self.needs_match_granularity();
+        let start_time = self.config.time_source.now();
         StandardSink {
             matcher,
             standard: self,
             replacer: Replacer::new(),
             interpolator,
             path: Some(ppath),
-            start_time: Instant::now(),
+            start_time,
             match_count: 0,
             after_context_remaining: 0,
             binary_byte_offset: None,
             stats,
+            dedupe: None,
+            trailer_matches: 0,
+            trailer_matched_lines: 0,
             needs_match_granularity,
         }
     }
@@ -604,8 +960,18 @@ impl<W: WriteColor> Standard<W> {
         || self.config.per_match
         // Emitting only the match requires finding each match.
         || self.config.only_matching
+        // Rendering windows around each match on long lines requires
+        // finding each match.
+        || self.config.match_window.is_some()
         // Computing certain statistics requires finding each match.
         || self.config.stats
+        // A file trailer reporting the number of matches requires finding
+        // each match.
+        || self
+            .config
+            .file_trailer
+            .as_ref()
+            .map_or(false, |t| t.contains_str("{matches}"))
     }
 }
 
@@ -657,14 +1023,96 @@ pub struct StandardSink<'p, 's, M: Matcher, W> {
     replacer: Replacer<M>,
     interpolator: hyperlink::Interpolator,
     path: Option<PrinterPath<'p>>,
-    start_time: Instant,
+    start_time: Duration,
     match_count: u64,
     after_context_remaining: u64,
     binary_byte_offset: Option<u64>,
     stats: Option<Stats>,
+    dedupe: Option<DedupeLines>,
+    trailer_matches: u64,
+    trailer_matched_lines: u64,
     needs_match_granularity: bool,
 }
 
+/// State for deduplicating identical matching lines within a single search.
+///
+/// Lines are tracked by a 128-bit hash of their bytes instead of the bytes
+/// themselves, so memory use is independent of the lengths of the matching
+/// lines. Once the number of distinct lines tracked reaches the configured
+/// limit, new lines are no longer tracked (and thus print normally), while
+/// lines already tracked continue to be suppressed.
+#[derive(Debug)]
+struct DedupeLines {
+    seen: std::collections::HashMap<(u64, u64), DedupeEntry>,
+    limit: usize,
+}
+
+/// The state recorded for each distinct matching line being deduplicated.
+#[derive(Debug)]
+struct DedupeEntry {
+    /// The order in which this line was first seen, for reporting trailers
+    /// in order of first occurrence.
+    index: usize,
+    /// The line number of the first occurrence, if line numbers are enabled.
+    line_number: Option<u64>,
+    /// The number of suppressed repeats of this line.
+    suppressed: u64,
+}
+
+impl DedupeLines {
+    /// Create a new empty deduplication state.
+    fn new(limit: usize) -> DedupeLines {
+        DedupeLines { seen: std::collections::HashMap::new(), limit }
+    }
+
+    /// Record the matching line given and return true if it is a repeat of a
+    /// previously recorded line and should be suppressed.
+    fn should_suppress(&mut self, mat: &SinkMatch<'_>) -> bool {
+        use std::collections::hash_map::Entry;
+
+        let index = self.seen.len();
+        let hash = hash128(mat.bytes());
+        match self.seen.entry(hash) {
+            Entry::Occupied(mut entry) => {
+                entry.get_mut().suppressed += 1;
+                true
+            }
+            Entry::Vacant(entry) => {
+                if index < self.limit {
+                    entry.insert(DedupeEntry {
+                        index,
+                        line_number: mat.line_number(),
+                        suppressed: 0,
+                    });
+                }
+                false
+            }
+        }
+    }
+
+    /// Returns true if at least one line repeat was suppressed.
+    fn has_suppressed(&self) -> bool {
+        self.seen.values().any(|entry| entry.suppressed > 0)
+    }
+}
+
+/// Returns a 128-bit hash of the bytes given.
+///
+/// This is built from two runs of the standard library's default hasher with
+/// distinct initial states. The default hasher is resistant enough to
+/// collisions that 128 bits makes accidentally conflating two distinct lines
+/// implausible at any realistic haystack size.
+fn hash128(bytes: &[u8]) -> (u64, u64) {
+    use std::hash::Hasher;
+
+    let mut hasher1 = std::collections::hash_map::DefaultHasher::new();
+    hasher1.write(bytes);
+    let mut hasher2 = std::collections::hash_map::DefaultHasher::new();
+    hasher2.write_u8(1);
+    hasher2.write(bytes);
+    (hasher1.finish(), hasher2.finish())
+}
+
 impl<'p, 's, M: Matcher, W: WriteColor> StandardSink<'p, 's, M, W> {
     /// Returns true if and only if this printer received a match in the
     /// previous search.
@@ -813,14 +1261,13 @@ impl<'p, 's, M: Matcher, W: WriteColor> Sink for StandardSink<'p, 's, M, W> {
         mat: &SinkMatch<'_>,
     ) -> Result<bool, io::Error> {
         self.match_count += 1;
-        // When we've exceeded our match count, then the remaining context
-        // lines should not be reset, but instead, decremented. This avoids a
-        // bug where we display more matches than a configured limit. The main
-        // idea here is that 'matched' might be called again while printing
-        // an after-context line. In that case, we should treat this as a
-        // contextual line rather than a matching line for the purposes of
-        // termination.
-        if self.match_more_than_limit() {
+        // Once we've exceeded our match count, any further match can only be
+        // reported because it falls within the after-context window of a
+        // previous match. Treat it as a contextual line for the purposes of
+        // termination (decrementing the remaining window instead of
+        // resetting it), as otherwise we would display more matches than a
+        // configured limit.
+        if self.match_more_than_limit() && mat.is_context_extension() {
             self.after_context_remaining =
                 self.after_context_remaining.saturating_sub(1);
         } else {
@@ -838,12 +1285,26 @@ impl<'p, 's, M: Matcher, W: WriteColor> Sink for StandardSink<'p, 's, M, W> {
             stats.add_matches(self.standard.matches.len() as u64);
             stats.add_matched_lines(mat.lines().count() as u64);
         }
+        if self.standard.config.file_trailer.is_some() {
+            self.trailer_matches += self.standard.matches.len() as u64;
+            self.trailer_matched_lines += mat.lines().count() as u64;
+        }
         if searcher.binary_detection().convert_byte().is_some() {
-            if self.binary_byte_offset.is_some() {
+            // When approximate line numbers are marked, we keep printing
+            // after binary data has been converted instead of suppressing
+            // the rest of the file.
+            if self.binary_byte_offset.is_some()
+                && !self.standard.config.mark_approximate_lines
+            {
                 return Ok(false);
             }
         }
 
+        if let Some(ref mut dedupe) = self.dedupe {
+            if dedupe.should_suppress(mat) {
+                return Ok(!self.should_quit());
+            }
+        }
         StandardImpl::from_match(searcher, self, mat).sink()?;
         Ok(!self.should_quit())
     }
@@ -865,7 +1326,9 @@ impl<'p, 's, M: Matcher, W: WriteColor> Sink for StandardSink<'p, 's, M, W> {
             self.replace(searcher, ctx.bytes(), 0..ctx.bytes().len())?;
         }
         if searcher.binary_detection().convert_byte().is_some() {
-            if self.binary_byte_offset.is_some() {
+            if self.binary_byte_offset.is_some()
+                && !self.standard.config.mark_approximate_lines
+            {
                 return Ok(false);
             }
         }
@@ -900,12 +1363,32 @@ impl<'p, 's, M: Matcher, W: WriteColor> Sink for StandardSink<'p, 's, M, W> {
         Ok(true)
     }
 
+    fn line_too_long(
+        &mut self,
+        searcher: &Searcher,
+        absolute_byte_offset: u64,
+        len: u64,
+    ) -> Result<bool, io::Error> {
+        if self.standard.config.long_line_warnings {
+            StandardImpl::new(searcher, self)
+                .write_long_line_message(absolute_byte_offset, len)?;
+        }
+        Ok(true)
+    }
+
     fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
         self.standard.wtr.borrow_mut().reset_count();
-        self.start_time = Instant::now();
+        self.start_time = self.standard.config.time_source.now();
         self.match_count = 0;
         self.after_context_remaining = 0;
         self.binary_byte_offset = None;
+        self.trailer_matches = 0;
+        self.trailer_matched_lines = 0;
+        self.dedupe = if self.standard.config.dedupe_lines {
+            Some(DedupeLines::new(self.standard.config.dedupe_lines_limit))
+        } else {
+            None
+        };
         if self.standard.config.max_matches == Some(0) {
             return Ok(false);
         }
@@ -918,10 +1401,47 @@ impl<'p, 's, M: Matcher, W: WriteColor> Sink for StandardSink<'p, 's, M, W> {
         finish: &SinkFinish,
     ) -> Result<(), io::Error> {
         if let Some(offset) = self.binary_byte_offset {
-            StandardImpl::new(searcher, self).write_binary_message(offset)?;
+            // When approximate line numbers are marked, matches after the
+            // binary data were printed as usual, so the message would be
+            // misleading.
+            let converted = searcher
+                .binary_detection()
+                .convert_byte()
+                .is_some();
+            if !(converted && self.standard.config.mark_approximate_lines) {
+                StandardImpl::new(searcher, self)
+                    .write_binary_message(offset)?;
+            }
+        }
+        if self.dedupe.as_ref().map_or(false, DedupeLines::has_suppressed) {
+            StandardImpl::new(searcher, self).write_dedupe_trailer()?;
+        }
+        if self.match_count > 0 {
+            StandardImpl::new(searcher, self).write_file_trailer()?;
+        }
+        let elapsed = self
+            .standard
+            .config
+            .time_source
+            .now()
+            .saturating_sub(self.start_time);
+        if let Some(threshold) = self.standard.config.slow_file_threshold {
+            if elapsed > threshold {
+                let path = self.path.as_ref().map(|p| p.as_path());
+                let line =
+                    slow_file_warning(path, elapsed, finish.byte_count());
+                match self.standard.config.slow_file_writer {
+                    Some(ref wtr) => wtr.write_line(line.as_bytes())?,
+                    None => self
+                        .standard
+                        .wtr
+                        .borrow_mut()
+                        .write_all(line.as_bytes())?,
+                }
+            }
         }
         if let Some(stats) = self.stats.as_mut() {
-            stats.add_elapsed(self.start_time.elapsed());
+            stats.add_elapsed(elapsed);
             stats.add_searches(1);
             if self.match_count > 0 {
                 stats.add_searches_with_match(1);
@@ -1298,12 +1818,32 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         if self.exceeds_max_columns(bytes) {
             self.write_exceeded_line(bytes, line, matches, &mut 0)
         } else {
+            let mut omitted = 0;
+            let matches = match self.config().max_matches_per_line {
+                Some(limit) if matches.len() > limit => {
+                    omitted = matches.len() - limit;
+                    &matches[..limit]
+                }
+                _ => matches,
+            };
             self.write_colored_matches(bytes, line, matches, &mut 0)?;
+            if omitted > 0 {
+                self.write_omitted_matches_marker(omitted)?;
+            }
             self.write_line_term()?;
             Ok(())
         }
     }
 
+    /// Write a dim marker indicating how many matches on the current line
+    /// were not highlighted due to the per-line match limit.
+    fn write_omitted_matches_marker(&self, omitted: usize) -> io::Result<()> {
+        let mut spec = ColorSpec::new();
+        spec.set_dimmed(true);
+        let marker = format!(" … +{} more matches", omitted);
+        self.write_spec(&spec, marker.as_bytes())
+    }
+
     /// Write the `line` portion of `bytes`, with appropriate coloring for
     /// each `match`, starting at `match_index`.
     ///
@@ -1358,6 +1898,9 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         matches: &[Match],
         match_index: &mut usize,
     ) -> io::Result<()> {
+        if self.write_windowed_line(bytes, line, matches, match_index)? {
+            return Ok(());
+        }
         if self.config().max_columns_preview {
             let original = line;
             let end = bytes[line]
@@ -1415,6 +1958,78 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         Ok(())
     }
 
+    /// Write a windowed rendering of a line that exceeds the maximum number
+    /// of columns: one window of bytes around each match on the line, with
+    /// `…` markers standing in for the elided portions.
+    ///
+    /// Returns `false` without writing anything when windowing is disabled
+    /// or when no match falls on the line, in which case the caller should
+    /// fall back to the usual long line handling.
+    fn write_windowed_line(
+        &self,
+        bytes: &[u8],
+        mut line: Match,
+        matches: &[Match],
+        match_index: &mut usize,
+    ) -> io::Result<bool> {
+        let window = match self.config().match_window {
+            None => return Ok(false),
+            Some(window) => window,
+        };
+        self.trim_line_terminator(bytes, &mut line);
+        let relevant: Vec<Match> = matches
+            .iter()
+            .copied()
+            .filter(|m| m.start() < line.end() && m.end() > line.start())
+            .collect();
+        if relevant.is_empty() {
+            return Ok(false);
+        }
+        // One window per match, starting shortly before the match so that
+        // some leading context is visible. Windows that overlap or abut are
+        // merged.
+        let mut windows: Vec<Match> = vec![];
+        for m in relevant.iter() {
+            let start =
+                cmp::max(line.start(), m.start().saturating_sub(window / 2));
+            // A window always covers its entire match, even when the match
+            // itself is longer than the window size.
+            let end =
+                cmp::min(line.end(), cmp::max(start + window, m.end()));
+            match windows.last_mut() {
+                Some(last) if start <= last.end() => {
+                    *last = last.with_end(cmp::max(last.end(), end));
+                }
+                _ => windows.push(Match::new(start, end)),
+            }
+        }
+        let count = cmp::max(1, self.config().match_window_count);
+        let windows = &windows[..cmp::min(windows.len(), count)];
+        for (i, &w) in windows.iter().enumerate() {
+            if i > 0 || w.start() > line.start() {
+                self.write("…".as_bytes())?;
+            }
+            self.write_colored_matches(bytes, w, matches, match_index)?;
+        }
+        let last_end = windows.last().unwrap().end();
+        if last_end < line.end() {
+            self.write("…".as_bytes())?;
+        }
+        let remaining =
+            relevant.iter().filter(|m| m.start() >= last_end).count();
+        if remaining > 0 {
+            let tense = if remaining == 1 { "match" } else { "matches" };
+            write!(
+                self.wtr().borrow_mut(),
+                " [... {} more {}]",
+                remaining,
+                tense,
+            )?;
+        }
+        self.write_line_term()?;
+        Ok(true)
+    }
+
     /// If this printer has a file path associated with it, then this will
     /// write that path to the underlying writer followed by a line terminator.
     /// (If a path terminator is set, then that is used instead of the line
@@ -1436,10 +2051,13 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         if this_search_written {
             return Ok(());
         }
+        let ever_written = self.wtr().borrow().total_count() > 0;
+        if !ever_written && self.config().output_bom {
+            self.write("\u{FEFF}".as_bytes())?;
+        }
         if let Some(ref sep) = *self.config().separator_search {
-            let ever_written = self.wtr().borrow().total_count() > 0;
             if ever_written {
-                self.write(sep)?;
+                self.write_spec(self.config().colors.separator(), sep)?;
                 self.write_line_term()?;
             }
         }
@@ -1449,8 +2067,119 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         Ok(())
     }
 
+    /// Write the trailer summarizing matching lines that were suppressed by
+    /// line deduplication.
+    ///
+    /// Trailer lines are reported in order of each line's first occurrence.
+    /// Suppressed lines without line numbers (i.e., when the searcher has
+    /// line numbers disabled) are rolled up into a single aggregate line.
+    fn write_dedupe_trailer(&self) -> io::Result<()> {
+        let Some(ref dedupe) = self.sink.dedupe else { return Ok(()) };
+        let mut entries = dedupe
+            .seen
+            .values()
+            .filter(|entry| entry.suppressed > 0)
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.index);
+
+        let mut unnumbered = 0;
+        for entry in entries {
+            let Some(line_number) = entry.line_number else {
+                unnumbered += entry.suppressed;
+                continue;
+            };
+            if let Some(path) = self.path() {
+                self.write_path_hyperlink(path)?;
+                self.write(b": ")?;
+            }
+            let msg = format!(
+                "(line {} repeated {} more times)",
+                line_number, entry.suppressed,
+            );
+            self.write(msg.as_bytes())?;
+            self.write_line_term()?;
+        }
+        if unnumbered > 0 {
+            if let Some(path) = self.path() {
+                self.write_path_hyperlink(path)?;
+                self.write(b": ")?;
+            }
+            let msg = format!(
+                "({} duplicate matching lines suppressed)",
+                unnumbered,
+            );
+            self.write(msg.as_bytes())?;
+            self.write_line_term()?;
+        }
+        Ok(())
+    }
+
+    /// Write the per-file trailer line, if one has been configured.
+    ///
+    /// The trailer is only written in heading mode. Its `{matches}` and
+    /// `{matched_lines}` placeholders are replaced with this file's totals,
+    /// while `{path}` is written like any other file path, i.e., with a
+    /// hyperlink and path terminator when so configured.
+    fn write_file_trailer(&self) -> io::Result<()> {
+        let config = self.config();
+        let Some(ref template) = config.file_trailer else { return Ok(()) };
+        if !config.heading {
+            return Ok(());
+        }
+        let trailer = template
+            .replace("{matches}", self.sink.trailer_matches.to_string())
+            .replace(
+                "{matched_lines}",
+                self.sink.trailer_matched_lines.to_string(),
+            );
+        let mut rest: &[u8] = &trailer;
+        while let Some(i) = rest.find("{path}") {
+            self.write_spec(config.colors.line(), &rest[..i])?;
+            if let Some(path) = self.path() {
+                self.write_path_hyperlink(path)?;
+                if let Some(term) = config.path_terminator {
+                    self.write(&[term])?;
+                }
+            }
+            rest = &rest[i + "{path}".len()..];
+        }
+        self.write_spec(config.colors.line(), rest)?;
+        self.write_line_term()?;
+        Ok(())
+    }
+
+    fn write_long_line_message(&self, offset: u64, len: u64) -> io::Result<()> {
+        if let Some(path) = self.path() {
+            self.write_path_hyperlink(path)?;
+            self.write(b": ")?;
+        }
+        let msg = format!(
+            "WARNING: skipped line longer than the maximum line length \
+             ({} bytes at offset {})",
+            len, offset,
+        );
+        self.write(msg.as_bytes())?;
+        self.write_line_term()?;
+        Ok(())
+    }
+
     fn write_binary_message(&self, offset: u64) -> io::Result<()> {
         if self.sink.match_count == 0 {
+            // Without a match, the file was skipped silently, which is only
+            // reported when asked for (and only for the quit detection
+            // method, since conversion searches the whole file).
+            let config = &self.sink.standard.config;
+            if config.report_binary_skips
+                && self.searcher.binary_detection().quit_byte().is_some()
+            {
+                if let Some(path) = self.path() {
+                    self.write_path_hyperlink(path)?;
+                    self.write(b": ")?;
+                }
+                let msg = format!("binary file skipped (offset {})", offset);
+                self.write(msg.as_bytes())?;
+                self.write_line_term()?;
+            }
             return Ok(());
         }
 
@@ -1462,29 +2191,31 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
             }
             let remainder = format!(
                 "WARNING: stopped searching binary file after match \
-                 (found {:?} byte around offset {})\n",
+                 (found {:?} byte around offset {})",
                 [byte].as_bstr(),
                 offset,
             );
             self.write(remainder.as_bytes())?;
+            self.write_line_term()?;
         } else if let Some(byte) = bin.convert_byte() {
             if let Some(path) = self.path() {
                 self.write_path_hyperlink(path)?;
                 self.write(b": ")?;
             }
             let remainder = format!(
-                "binary file matches (found {:?} byte around offset {})\n",
+                "binary file matches (found {:?} byte around offset {})",
                 [byte].as_bstr(),
                 offset,
             );
             self.write(remainder.as_bytes())?;
+            self.write_line_term()?;
         }
         Ok(())
     }
 
     fn write_context_separator(&self) -> io::Result<()> {
         if let Some(ref sep) = *self.config().separator_context {
-            self.write(sep)?;
+            self.write_spec(self.config().colors.separator(), sep)?;
             self.write_line_term()?;
         }
         Ok(())
@@ -1588,11 +2319,18 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
     /// Return the appropriate field separator based on whether we are emitting
     /// matching or contextual lines.
     fn separator_field(&self) -> &[u8] {
-        if self.is_context() {
-            &self.config().separator_field_context
-        } else {
-            &self.config().separator_field_match
-        }
+        let config = self.config();
+        let Some(kind) = self.sunk.context_kind() else {
+            return &config.separator_field_match;
+        };
+        let override_prefix = match *kind {
+            SinkContextKind::Before => config.before_context_prefix.as_ref(),
+            SinkContextKind::After => config.after_context_prefix.as_ref(),
+            SinkContextKind::Other => None,
+        };
+        override_prefix
+            .map(|prefix| &***prefix)
+            .unwrap_or(&config.separator_field_context)
     }
 
     /// Returns true if and only if the given line exceeds the maximum number
@@ -1634,6 +2372,11 @@ struct PreludeWriter<'a, M: Matcher, W> {
     next_separator: PreludeSeparator,
     field_separator: &'a [u8],
     interp_status: hyperlink::InterpolatorStatus,
+    /// The position of the line this prelude belongs to, remembered so that
+    /// individual fields can hyperlink themselves when line number linking
+    /// is enabled.
+    line_number: Option<u64>,
+    column: Option<u64>,
 }
 
 /// A type of separator used in the prelude
@@ -1655,9 +2398,18 @@ impl<'a, M: Matcher, W: WriteColor> PreludeWriter<'a, M, W> {
             next_separator: PreludeSeparator::None,
             field_separator: std.separator_field(),
             interp_status: hyperlink::InterpolatorStatus::inactive(),
+            line_number: None,
+            column: None,
         }
     }
 
+    /// Returns true when each prelude field should carry its own hyperlink
+    /// instead of one hyperlink spanning the entire prelude.
+    #[inline(always)]
+    fn link_fields(&self) -> bool {
+        self.config().hyperlink.links_line_numbers()
+    }
+
     /// Starts the prelude with a hyperlink when applicable.
     ///
     /// If a heading was written, and the hyperlink format is invariant on
@@ -1671,6 +2423,15 @@ impl<'a, M: Matcher, W: WriteColor> PreludeWriter<'a, M, W> {
         column: Option<u64>,
     ) -> io::Result<()> {
         let Some(path) = self.std.path() else { return Ok(()) };
+        if self.link_fields() {
+            // Each field hyperlinks itself. Remember the position so that
+            // the fields can interpolate it. Opening a hyperlink spanning
+            // the prelude here as well would nest OSC 8 sequences, which
+            // terminals do not handle.
+            self.line_number = line_number;
+            self.column = column;
+            return Ok(());
+        }
         if self.config().hyperlink.format().is_line_dependent()
             || !self.config().heading
         {
@@ -1704,7 +2465,17 @@ impl<'a, M: Matcher, W: WriteColor> PreludeWriter<'a, M, W> {
         }
         let Some(path) = self.std.path() else { return Ok(()) };
         self.write_separator()?;
-        self.std.write_path(path)?;
+        if self.link_fields() {
+            let status = self.std.start_hyperlink(
+                path,
+                self.line_number,
+                self.column,
+            )?;
+            self.std.write_path(path)?;
+            self.std.end_hyperlink(status)?;
+        } else {
+            self.std.write_path(path)?;
+        }
 
         self.next_separator = if self.config().path_terminator.is_some() {
             PreludeSeparator::PathTerminator
@@ -1720,7 +2491,30 @@ impl<'a, M: Matcher, W: WriteColor> PreludeWriter<'a, M, W> {
         let Some(line_number) = line else { return Ok(()) };
         self.write_separator()?;
         let n = DecimalFormatter::new(line_number);
-        self.std.write_spec(self.config().colors.line(), n.as_bytes())?;
+        let colors = &self.config().colors;
+        let spec = match self.std.sunk.context_kind() {
+            None => colors.line(),
+            Some(&SinkContextKind::Before) => colors.context_before(),
+            Some(&SinkContextKind::After) => colors.context_after(),
+            Some(&SinkContextKind::Other) => colors.context_line(),
+        };
+        // The hyperlink is opened before setting the color and closed after
+        // resetting it, so that the escape sequences nest correctly.
+        let status = match (self.link_fields(), self.std.path()) {
+            (true, Some(path)) => self.std.start_hyperlink(
+                path,
+                Some(line_number),
+                self.column,
+            )?,
+            _ => hyperlink::InterpolatorStatus::inactive(),
+        };
+        self.std.write_spec(spec, n.as_bytes())?;
+        self.std.end_hyperlink(status)?;
+        if self.config().mark_approximate_lines
+            && self.std.sunk.approximate_line_number()
+        {
+            self.std.write(b"~")?;
+        }
         self.next_separator = PreludeSeparator::FieldSeparator;
         Ok(())
     }
@@ -1786,7 +2580,7 @@ mod tests {
     use grep_searcher::SearcherBuilder;
     use termcolor::{Ansi, NoColor};
 
-    use super::{ColorSpecs, Standard, StandardBuilder};
+    use super::{ColorDepth, ColorSpecs, Standard, StandardBuilder};
 
     const SHERLOCK: &'static str = "\
 For the Doctor Watsons of this world, as opposed to the Sherlock
@@ -1865,13 +2659,53 @@ and exhibited clearly, with a label attached.\
     }
 
     #[test]
-    fn reports_stats() {
-        use std::time::Duration;
+    fn report_binary_skips() {
+        use grep_searcher::BinaryDetection;
 
-        let matcher = RegexMatcher::new("Sherlock|opposed").unwrap();
-        let mut printer =
-            StandardBuilder::new().stats(true).build(NoColor::new(vec![]));
-        let stats = {
+        // The NUL byte appears before anything matches, so by default the
+        // file is skipped without a trace.
+        const BINARY: &'static [u8] = b"nothing here\x00\nSherlock\n";
+
+        let matcher = RegexMatcher::new(r"Sherlock").unwrap();
+        let mut printer = StandardBuilder::new().build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .binary_detection(BinaryDetection::quit(b'\x00'))
+            .build()
+            .search_reader(
+                &matcher,
+                BINARY,
+                printer.sink_with_path(&matcher, "h.bin"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+        assert_eq!("", got);
+
+        let mut printer = StandardBuilder::new()
+            .report_binary_skips(true)
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .binary_detection(BinaryDetection::quit(b'\x00'))
+            .build()
+            .search_reader(
+                &matcher,
+                BINARY,
+                printer.sink_with_path(&matcher, "h.bin"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+        assert_eq!("h.bin: binary file skipped (offset 12)\n", got);
+    }
+
+    #[test]
+    fn reports_stats() {
+        use std::time::Duration;
+
+        let matcher = RegexMatcher::new("Sherlock|opposed").unwrap();
+        let mut printer =
+            StandardBuilder::new().stats(true).build(NoColor::new(vec![]));
+        let stats = {
             let mut sink = printer.sink(&matcher);
             SearcherBuilder::new()
                 .line_number(false)
@@ -2082,6 +2916,50 @@ and exhibited clearly, with a label attached.
         assert_eq_printed!(expected, got);
     }
 
+    #[test]
+    fn output_bom() {
+        let matcher = RegexMatcher::new("Watson").unwrap();
+        let mut printer = StandardBuilder::new()
+            .output_bom(true)
+            .heading(true)
+            .build(NoColor::new(vec![]));
+        for path in ["sherlock1", "sherlock2"] {
+            SearcherBuilder::new()
+                .line_number(false)
+                .build()
+                .search_reader(
+                    &matcher,
+                    SHERLOCK.as_bytes(),
+                    printer.sink_with_path(&matcher, path),
+                )
+                .unwrap();
+        }
+
+        let got = printer_contents(&mut printer);
+        // The BOM is emitted exactly once, before the first heading.
+        assert!(got.starts_with("\u{FEFF}sherlock1\n"), "{:?}", got);
+        assert_eq!(1, got.matches('\u{FEFF}').count());
+    }
+
+    #[test]
+    fn output_bom_no_output() {
+        let matcher = RegexMatcher::new("NADA").unwrap();
+        let mut printer = StandardBuilder::new()
+            .output_bom(true)
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("", got);
+    }
+
     #[test]
     fn separator_field() {
         let matcher = RegexMatcher::new("Watson").unwrap();
@@ -2113,6 +2991,37 @@ sherlock^^and exhibited clearly, with a label attached.
         assert_eq_printed!(expected, got);
     }
 
+    #[test]
+    fn before_after_context_prefix() {
+        let matcher = RegexMatcher::new("Watson").unwrap();
+        let mut printer = StandardBuilder::new()
+            .before_context_prefix(b"<<".to_vec())
+            .after_context_prefix(b">>".to_vec())
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .before_context(1)
+            .after_context(1)
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                printer.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+sherlock:For the Doctor Watsons of this world, as opposed to the Sherlock
+sherlock>>Holmeses, success in the province of detective work must always
+--
+sherlock<<can extract a clew from a wisp of straw or a flake of cigar ash;
+sherlock:but Doctor Watson has to have it taken out for him and dusted,
+sherlock>>and exhibited clearly, with a label attached.
+";
+        assert_eq_printed!(expected, got);
+    }
+
     #[test]
     fn separator_path() {
         let matcher = RegexMatcher::new("Watson").unwrap();
@@ -2185,6 +3094,93 @@ but Doctor Watson has to have it taken out for him and dusted,
         assert_eq_printed!(expected, got);
     }
 
+    #[test]
+    fn heading_file_trailer() {
+        let matcher = RegexMatcher::new("Watson|Sherlock").unwrap();
+        let mut printer = StandardBuilder::new()
+            .heading(true)
+            .file_trailer(Some(
+                b"  \xe2\x94\x94\xe2\x94\x80 {matches} matches on \
+                  {matched_lines} lines in {path}"
+                    .to_vec(),
+            ))
+            .build(NoColor::new(vec![]));
+        let mut searcher = SearcherBuilder::new().line_number(false).build();
+        searcher
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                printer.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+        searcher
+            .search_reader(
+                &matcher,
+                "but Doctor Watson has to have it taken out\n".as_bytes(),
+                printer.sink_with_path(&matcher, "watson"),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+sherlock
+For the Doctor Watsons of this world, as opposed to the Sherlock
+be, to a very large extent, the result of luck. Sherlock Holmes
+but Doctor Watson has to have it taken out for him and dusted,
+  └─ 4 matches on 3 lines in sherlock
+watson
+but Doctor Watson has to have it taken out
+  └─ 1 matches on 1 lines in watson
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn heading_file_trailer_no_heading() {
+        let matcher = RegexMatcher::new("Watson").unwrap();
+        let mut printer = StandardBuilder::new()
+            .heading(false)
+            .file_trailer(Some(b"  {matches} matches in {path}".to_vec()))
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                printer.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+sherlock:For the Doctor Watsons of this world, as opposed to the Sherlock
+sherlock:but Doctor Watson has to have it taken out for him and dusted,
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn heading_file_trailer_no_match() {
+        let matcher = RegexMatcher::new("zzzzzzzzzz").unwrap();
+        let mut printer = StandardBuilder::new()
+            .heading(true)
+            .file_trailer(Some(b"  {matches} matches in {path}".to_vec()))
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                printer.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("", got);
+    }
+
     #[test]
     fn no_heading() {
         let matcher = RegexMatcher::new("Watson").unwrap();
@@ -2377,6 +3373,56 @@ Watson
         assert_eq_printed!(expected, got);
     }
 
+    #[test]
+    fn line_number_approximate() {
+        use grep_searcher::BinaryDetection;
+
+        // The NUL byte sits between the two matches, so only the second
+        // match's line number is approximate.
+        let haystack = "Watson\x00Holmes\nWatson again\n";
+        let matcher = RegexMatcher::new("Watson").unwrap();
+        let mut printer = StandardBuilder::new()
+            .mark_approximate_lines(true)
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .binary_detection(BinaryDetection::convert(0))
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+1:Watson
+2~:Watson again
+";
+        assert_eq_printed!(expected, got);
+
+        // Without opting in, the default behavior is unchanged: the match
+        // after the binary data is suppressed.
+        let mut printer = StandardBuilder::new().build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .binary_detection(BinaryDetection::convert(0))
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+binary file matches (found \"\\0\" byte around offset 6)
+";
+        assert_eq_printed!(expected, got);
+    }
+
     #[test]
     fn line_number_multi_line() {
         let matcher = RegexMatcher::new("(?s)Watson.+Watson").unwrap();
@@ -2505,6 +3551,32 @@ Watson
         assert_eq_printed!(expected, got);
     }
 
+    #[test]
+    fn byte_offset_transcoded() {
+        // "Homer\nSimpson\n" encoded as UTF-16LE with a BOM. The searcher
+        // transcodes the data before searching, so the byte offsets (and
+        // the replaced output) refer to the transcoded data. The JSON
+        // printer reports the same offset in its absolute_offset field
+        // (see its absolute_offset_transcoded test).
+        let mut haystack = vec![0xFF, 0xFE];
+        for byte in "Homer\nSimpson\n".bytes() {
+            haystack.extend_from_slice(&[byte, 0x00]);
+        }
+        let matcher = RegexMatcher::new("Simpson").unwrap();
+        let mut printer = StandardBuilder::new()
+            .byte_offset(true)
+            .replacement(Some(b"Maggie".to_vec()))
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .build()
+            .search_reader(&matcher, &*haystack, printer.sink(&matcher))
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("6:Maggie\n", got);
+    }
+
     #[test]
     fn max_columns() {
         let matcher = RegexMatcher::new("ash|dusted").unwrap();
@@ -2555,90 +3627,105 @@ and exhibited clearly, with a label attached.
     }
 
     #[test]
-    fn max_columns_with_count() {
-        let matcher = RegexMatcher::new("cigar|ash|dusted").unwrap();
+    fn match_window_long_line_both_ends() {
+        // A single line of about 1MB with matches at both ends.
+        let mut haystack = String::new();
+        haystack.push_str("start_needle");
+        haystack.push_str(&"a".repeat(1 << 20));
+        haystack.push_str("end_needle\n");
+
+        let matcher = RegexMatcher::new("needle").unwrap();
         let mut printer = StandardBuilder::new()
-            .stats(true)
-            .max_columns(Some(63))
+            .max_columns(Some(100))
+            .match_window(Some(20))
+            .column(true)
             .build(NoColor::new(vec![]));
         SearcherBuilder::new()
             .line_number(false)
             .build()
             .search_reader(
                 &matcher,
-                SHERLOCK.as_bytes(),
+                haystack.as_bytes(),
                 printer.sink(&matcher),
             )
             .unwrap();
 
+        // The reported column refers to the true position of the first
+        // match, while the rendered line shows one window per match.
         let got = printer_contents(&mut printer);
-        let expected = "\
-[Omitted long line with 2 matches]
-but Doctor Watson has to have it taken out for him and dusted,
-";
+        let expected = "7:start_needleaaaaaaaa…aaaaaaend_needle\n";
         assert_eq_printed!(expected, got);
     }
 
     #[test]
-    fn max_columns_with_count_preview_no_match() {
-        let matcher = RegexMatcher::new("exhibited|has to have it").unwrap();
+    fn match_window_count() {
+        let mut haystack = String::new();
+        haystack.push_str(&"a".repeat(10));
+        for _ in 0..3 {
+            haystack.push_str("needle");
+            haystack.push_str(&"a".repeat(100));
+        }
+        haystack.push('\n');
+
+        let matcher = RegexMatcher::new("needle").unwrap();
         let mut printer = StandardBuilder::new()
-            .stats(true)
-            .max_columns(Some(46))
-            .max_columns_preview(true)
+            .max_columns(Some(50))
+            .match_window(Some(10))
+            .match_window_count(2)
             .build(NoColor::new(vec![]));
         SearcherBuilder::new()
             .line_number(false)
             .build()
             .search_reader(
                 &matcher,
-                SHERLOCK.as_bytes(),
+                haystack.as_bytes(),
                 printer.sink(&matcher),
             )
             .unwrap();
 
         let got = printer_contents(&mut printer);
-        let expected = "\
-but Doctor Watson has to have it taken out for [... 0 more matches]
-and exhibited clearly, with a label attached.
-";
+        let expected = "…aaaaaneedle…aaaaaneedle… [... 1 more match]\n";
         assert_eq_printed!(expected, got);
     }
 
     #[test]
-    fn max_columns_with_count_preview_one_match() {
-        let matcher = RegexMatcher::new("exhibited|dusted").unwrap();
+    fn match_window_color() {
+        let mut haystack = String::new();
+        haystack.push_str(&"a".repeat(10));
+        haystack.push_str("needle");
+        haystack.push_str(&"a".repeat(10));
+        haystack.push('\n');
+
+        let matcher = RegexMatcher::new("needle").unwrap();
         let mut printer = StandardBuilder::new()
-            .stats(true)
-            .max_columns(Some(46))
-            .max_columns_preview(true)
-            .build(NoColor::new(vec![]));
+            .color_specs(ColorSpecs::new(&["match:fg:red".parse().unwrap()]))
+            .max_columns(Some(20))
+            .match_window(Some(10))
+            .build(Ansi::new(vec![]));
         SearcherBuilder::new()
             .line_number(false)
             .build()
             .search_reader(
                 &matcher,
-                SHERLOCK.as_bytes(),
+                haystack.as_bytes(),
                 printer.sink(&matcher),
             )
             .unwrap();
 
-        let got = printer_contents(&mut printer);
-        let expected = "\
-but Doctor Watson has to have it taken out for [... 1 more match]
-and exhibited clearly, with a label attached.
-";
+        // The match is highlighted at its position in the windowed output,
+        // not at its original offset in the line.
+        let got = printer_contents_ansi(&mut printer);
+        let expected =
+            "…aaaaa\x1b[0m\x1b[31mneedle\x1b[0m…\n";
         assert_eq_printed!(expected, got);
     }
 
     #[test]
-    fn max_columns_with_count_preview_two_matches() {
-        let matcher =
-            RegexMatcher::new("exhibited|dusted|has to have it").unwrap();
+    fn max_columns_with_count() {
+        let matcher = RegexMatcher::new("cigar|ash|dusted").unwrap();
         let mut printer = StandardBuilder::new()
             .stats(true)
-            .max_columns(Some(46))
-            .max_columns_preview(true)
+            .max_columns(Some(63))
             .build(NoColor::new(vec![]));
         SearcherBuilder::new()
             .line_number(false)
@@ -2652,14 +3739,93 @@ and exhibited clearly, with a label attached.
 
         let got = printer_contents(&mut printer);
         let expected = "\
-but Doctor Watson has to have it taken out for [... 1 more match]
-and exhibited clearly, with a label attached.
+[Omitted long line with 2 matches]
+but Doctor Watson has to have it taken out for him and dusted,
 ";
         assert_eq_printed!(expected, got);
     }
 
     #[test]
-    fn max_columns_multi_line() {
+    fn max_columns_with_count_preview_no_match() {
+        let matcher = RegexMatcher::new("exhibited|has to have it").unwrap();
+        let mut printer = StandardBuilder::new()
+            .stats(true)
+            .max_columns(Some(46))
+            .max_columns_preview(true)
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+but Doctor Watson has to have it taken out for [... 0 more matches]
+and exhibited clearly, with a label attached.
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn max_columns_with_count_preview_one_match() {
+        let matcher = RegexMatcher::new("exhibited|dusted").unwrap();
+        let mut printer = StandardBuilder::new()
+            .stats(true)
+            .max_columns(Some(46))
+            .max_columns_preview(true)
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+but Doctor Watson has to have it taken out for [... 1 more match]
+and exhibited clearly, with a label attached.
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn max_columns_with_count_preview_two_matches() {
+        let matcher =
+            RegexMatcher::new("exhibited|dusted|has to have it").unwrap();
+        let mut printer = StandardBuilder::new()
+            .stats(true)
+            .max_columns(Some(46))
+            .max_columns_preview(true)
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+but Doctor Watson has to have it taken out for [... 1 more match]
+and exhibited clearly, with a label attached.
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn max_columns_multi_line() {
         let matcher = RegexMatcher::new("(?s)ash.+dusted").unwrap();
         let mut printer = StandardBuilder::new()
             .max_columns(Some(63))
@@ -3381,6 +4547,96 @@ Holmeses, success in the province of detective work must always
         assert_eq_printed!(expected, got);
     }
 
+    #[test]
+    fn replacement_passthru_column() {
+        let matcher = RegexMatcher::new(r"Sherlock").unwrap();
+        let mut printer = StandardBuilder::new()
+            .column(true)
+            .replacement(Some(b"FooBar".to_vec()))
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .passthru(true)
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        // Columns refer to the position of the match before the replacement
+        // is applied, and non-matching lines pass through without one.
+        let expected = "\
+1:57:For the Doctor Watsons of this world, as opposed to the FooBar
+2-Holmeses, success in the province of detective work must always
+3:49:be, to a very large extent, the result of luck. FooBar Holmes
+4-can extract a clew from a wisp of straw or a flake of cigar ash;
+5-but Doctor Watson has to have it taken out for him and dusted,
+6-and exhibited clearly, with a label attached.
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn replacement_passthru_color() {
+        let haystack = "\
+a
+b
+c
+";
+        let matcher = RegexMatcher::new(r"b").unwrap();
+        let mut printer = StandardBuilder::new()
+            .color_specs(ColorSpecs::new(&["match:fg:red".parse().unwrap()]))
+            .replacement(Some(b"B!".to_vec()))
+            .build(Ansi::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .passthru(true)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents_ansi(&mut printer);
+        // The replaced span gets the match color, while non-matching lines
+        // pass through with no escapes at all.
+        let expected = "\
+a
+\x1b[0m\x1b[31mB!\x1b[0m
+c
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn replacement_passthru_stats() {
+        let matcher = RegexMatcher::new(r"Sherlock|Doctor (\w+)").unwrap();
+        let mut printer = StandardBuilder::new()
+            .stats(true)
+            .replacement(Some(b"doctah $1 MD".to_vec()))
+            .build(NoColor::new(vec![]));
+        let stats = {
+            let mut sink = printer.sink(&matcher);
+            SearcherBuilder::new()
+                .line_number(true)
+                .passthru(true)
+                .build()
+                .search_reader(&matcher, SHERLOCK.as_bytes(), &mut sink)
+                .unwrap();
+            sink.stats().unwrap().clone()
+        };
+        // Even though every line is printed and matches are replaced,
+        // matched_lines reflects only the lines matching the original
+        // pattern.
+        assert_eq!(stats.matched_lines(), 3);
+        assert_eq!(stats.matches(), 4);
+    }
+
     #[test]
     fn replacement() {
         let matcher = RegexMatcher::new(r"Sherlock|Doctor (\w+)").unwrap();
@@ -3804,6 +5060,334 @@ and xxx clearly, with a label attached.
         assert!(!got.is_empty());
     }
 
+    #[test]
+    fn context_line_number_color() {
+        let haystack = "\
+a
+b
+c
+";
+        let matcher = RegexMatcherBuilder::new().build(r"b").unwrap();
+        let mut printer = StandardBuilder::new()
+            .color_specs(ColorSpecs::new(&[
+                "line:fg:green".parse().unwrap(),
+                "context-line:fg:yellow".parse().unwrap(),
+            ]))
+            .build(Ansi::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .before_context(1)
+            .after_context(1)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents_ansi(&mut printer);
+        let expected = "\
+\x1b[0m\x1b[33m1\x1b[0m-a
+\x1b[0m\x1b[32m2\x1b[0m:b
+\x1b[0m\x1b[33m3\x1b[0m-c
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn context_before_after_color() {
+        let haystack = "\
+a
+b
+c
+";
+        let matcher = RegexMatcherBuilder::new().build(r"b").unwrap();
+        let mut printer = StandardBuilder::new()
+            .color_specs(ColorSpecs::new(&[
+                "line:fg:green".parse().unwrap(),
+                "context-before:fg:blue".parse().unwrap(),
+                "context-after:fg:red".parse().unwrap(),
+            ]))
+            .build(Ansi::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .before_context(1)
+            .after_context(1)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents_ansi(&mut printer);
+        let expected = "\
+\x1b[0m\x1b[34m1\x1b[0m-a
+\x1b[0m\x1b[32m2\x1b[0m:b
+\x1b[0m\x1b[31m3\x1b[0m-c
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn hyperlink_line_numbers_heading() {
+        use crate::hyperlink::{
+            HyperlinkConfig, HyperlinkEnvironment, HyperlinkFormat,
+        };
+
+        // Hyperlink paths are canonicalized, so the searched path must
+        // actually exist for links to be emitted at all.
+        let path = std::env::temp_dir()
+            .join("grep-printer-standard-hyperlink-heading-test");
+        std::fs::write(&path, "").unwrap();
+        let canon = path.canonicalize().unwrap();
+        let url = canon.to_str().unwrap().to_string();
+        let display = canon.to_str().unwrap().to_string();
+
+        let haystack = "\
+a
+b
+c
+";
+        let matcher = RegexMatcherBuilder::new().build(r"b").unwrap();
+        let format = "foo://x{path}:{line}".parse::<HyperlinkFormat>().unwrap();
+        let config = HyperlinkConfig::new(HyperlinkEnvironment::new(), format)
+            .link_line_numbers(true);
+        let mut printer = StandardBuilder::new()
+            .color_specs(ColorSpecs::new(&[
+                "line:fg:green".parse().unwrap(),
+                "context-before:fg:blue".parse().unwrap(),
+                "context-after:fg:red".parse().unwrap(),
+            ]))
+            .hyperlink(config)
+            .heading(true)
+            .build(Ansi::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .before_context(1)
+            .after_context(1)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink_with_path(&matcher, &canon),
+            )
+            .unwrap();
+
+        let got = printer_contents_ansi(&mut printer);
+        // The heading has no line number, so its hyperlink degrades to a
+        // file-level link, while each line number carries its own hyperlink
+        // with its line filled in. The OSC 8 sequences surround the color
+        // set/reset sequences so that the two nest correctly.
+        let (open, st, close) = ("\x1b]8;;", "\x1b\\", "\x1b]8;;\x1b\\");
+        let expected = format!(
+            "{open}foo://x{url}{st}\x1b[0m{display}\x1b[0m{close}\n\
+             {open}foo://x{url}:1{st}\x1b[0m\x1b[34m1\x1b[0m{close}-a\n\
+             {open}foo://x{url}:2{st}\x1b[0m\x1b[32m2\x1b[0m{close}:b\n\
+             {open}foo://x{url}:3{st}\x1b[0m\x1b[31m3\x1b[0m{close}-c\n",
+        );
+        assert_eq_printed!(expected, got);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn hyperlink_line_numbers_no_heading() {
+        use crate::hyperlink::{
+            HyperlinkConfig, HyperlinkEnvironment, HyperlinkFormat,
+        };
+
+        let path = std::env::temp_dir()
+            .join("grep-printer-standard-hyperlink-no-heading-test");
+        std::fs::write(&path, "").unwrap();
+        let canon = path.canonicalize().unwrap();
+        let url = canon.to_str().unwrap().to_string();
+        let display = canon.to_str().unwrap().to_string();
+
+        let matcher = RegexMatcherBuilder::new().build(r"b").unwrap();
+        let format = "foo://x{path}:{line}".parse::<HyperlinkFormat>().unwrap();
+        let config = HyperlinkConfig::new(HyperlinkEnvironment::new(), format)
+            .link_line_numbers(true);
+        let mut printer = StandardBuilder::new()
+            .color_specs(ColorSpecs::new(&["line:fg:green".parse().unwrap()]))
+            .hyperlink(config)
+            .heading(false)
+            .build(Ansi::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .build()
+            .search_reader(
+                &matcher,
+                "a\nb\n".as_bytes(),
+                printer.sink_with_path(&matcher, &canon),
+            )
+            .unwrap();
+
+        let got = printer_contents_ansi(&mut printer);
+        // Without headings, the path and the line number each carry their
+        // own hyperlink rather than one hyperlink spanning the prelude.
+        let (open, st, close) = ("\x1b]8;;", "\x1b\\", "\x1b]8;;\x1b\\");
+        let expected = format!(
+            "{open}foo://x{url}:2{st}\x1b[0m{display}\x1b[0m{close}:\
+             {open}foo://x{url}:2{st}\x1b[0m\x1b[32m2\x1b[0m{close}:b\n",
+        );
+        assert_eq_printed!(expected, got);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn path_display_home_tilde() {
+        use crate::hyperlink::{
+            HyperlinkConfig, HyperlinkEnvironment, HyperlinkFormat,
+        };
+        use crate::util::PathDisplay;
+
+        // The home directory is injected rather than read from the real
+        // environment, but the searched path must actually exist since
+        // hyperlink paths are canonicalized.
+        let home = std::env::temp_dir()
+            .join("grep-printer-standard-path-display-test");
+        std::fs::create_dir_all(&home).unwrap();
+        let home = home.canonicalize().unwrap();
+        let path = home.join("hay");
+        std::fs::write(&path, "").unwrap();
+        let url = path.to_str().unwrap().to_string();
+
+        let matcher = RegexMatcherBuilder::new().build(r"b").unwrap();
+        let format = "foo://x{path}".parse::<HyperlinkFormat>().unwrap();
+        let config = HyperlinkConfig::new(HyperlinkEnvironment::new(), format);
+        let mut display = PathDisplay::new();
+        display.home_tilde(true).home_dir(Some(home.clone()));
+        let mut printer = StandardBuilder::new()
+            .hyperlink(config)
+            .path_display(display)
+            .heading(true)
+            .build(Ansi::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .build()
+            .search_reader(
+                &matcher,
+                "a\nb\n".as_bytes(),
+                printer.sink_with_path(&matcher, &path),
+            )
+            .unwrap();
+
+        let got = printer_contents_ansi(&mut printer);
+        // The visible path is abbreviated with `~`, but the hyperlink still
+        // carries the real absolute path.
+        let (open, st, close) = ("\x1b]8;;", "\x1b\\", "\x1b]8;;\x1b\\");
+        let expected = format!(
+            "{open}foo://x{url}{st}\x1b[0m~/hay\x1b[0m{close}\n\
+             \x1b[0m2\x1b[0m:b\n",
+        );
+        assert_eq_printed!(expected, got);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_dir(&home);
+    }
+
+    #[test]
+    fn max_matches_per_line() {
+        let haystack = "a".repeat(1000) + "\n";
+        let matcher = RegexMatcherBuilder::new().build(r"a").unwrap();
+        let mut printer = StandardBuilder::new()
+            .color_specs(ColorSpecs::new(&["match:fg:red".parse().unwrap()]))
+            .max_matches_per_line(Some(5))
+            .build(Ansi::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents_ansi(&mut printer);
+        // Only the first five matches are highlighted. Since they're
+        // adjacent, they come out as a single colored span. The rest of the
+        // line is printed as-is, followed by a dim omission marker.
+        let mut expected = String::from("\x1b[0m\x1b[31maaaaa\x1b[0m");
+        expected.push_str(&"a".repeat(995));
+        expected.push_str("\x1b[0m\x1b[2m … +995 more matches\x1b[0m\n");
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn separator_color() {
+        let haystack = "\
+a
+b
+c
+d
+e
+f
+";
+        let matcher = RegexMatcherBuilder::new().build(r"b|e").unwrap();
+        let mut printer = StandardBuilder::new()
+            .color_specs(ColorSpecs::new(&[
+                "line:fg:green".parse().unwrap(),
+                "separator:fg:red".parse().unwrap(),
+            ]))
+            .build(Ansi::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .after_context(1)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents_ansi(&mut printer);
+        let expected = "\
+\x1b[0m\x1b[32m2\x1b[0m:b
+\x1b[0m\x1b[32m3\x1b[0m-c
+\x1b[0m\x1b[31m--\x1b[0m
+\x1b[0m\x1b[32m5\x1b[0m:e
+\x1b[0m\x1b[32m6\x1b[0m-f
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn color_depth_quantizes_rgb() {
+        let matcher = RegexMatcher::new("abc").unwrap();
+        let run = |depth: ColorDepth| {
+            let mut printer = StandardBuilder::new()
+                .color_specs(ColorSpecs::new(&[
+                    "match:fg:0xff,0x7f,0x00".parse().unwrap(),
+                ]))
+                .color_depth(depth)
+                .build(Ansi::new(vec![]));
+            SearcherBuilder::new()
+                .line_number(false)
+                .build()
+                .search_reader(
+                    &matcher,
+                    &b"abc\n"[..],
+                    printer.sink(&matcher),
+                )
+                .unwrap();
+            printer_contents_ansi(&mut printer)
+        };
+
+        // The same truecolor spec renders differently at each depth: the
+        // RGB color is emitted verbatim, quantized to the nearest 256-color
+        // palette entry and quantized to the nearest named ANSI color,
+        // respectively.
+        let got = run(ColorDepth::TrueColor);
+        assert_eq_printed!("\x1b[0m\x1b[38;2;255;127;0mabc\x1b[0m\n", got);
+        let got = run(ColorDepth::Ansi256);
+        assert_eq_printed!("\x1b[0m\x1b[38;5;208mabc\x1b[0m\n", got);
+        let got = run(ColorDepth::Ansi16);
+        assert_eq_printed!("\x1b[0m\x1b[33mabc\x1b[0m\n", got);
+    }
+
     #[test]
     fn regression_after_context_with_match() {
         let haystack = "\
@@ -3839,4 +5423,136 @@ e
         let expected = "4:d\n5-e\n6:d\n";
         assert_eq_printed!(expected, got);
     }
+
+    #[test]
+    fn dedupe_lines() {
+        let matcher = RegexMatcher::new("foo").unwrap();
+        let haystack = "\
+foo one
+bar
+foo one
+foo two
+foo one
+foo two
+";
+        let mut printer = StandardBuilder::new()
+            .dedupe_lines(true)
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+1:foo one
+4:foo two
+(line 1 repeated 2 more times)
+(line 4 repeated 1 more times)
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn dedupe_lines_no_line_numbers() {
+        let matcher = RegexMatcher::new("foo").unwrap();
+        let haystack = "foo one\nbar\nfoo one\nfoo two\nfoo one\n";
+        let mut printer = StandardBuilder::new()
+            .dedupe_lines(true)
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(false)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let expected = "\
+foo one
+foo two
+(2 duplicate matching lines suppressed)
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn dedupe_lines_limit() {
+        let matcher = RegexMatcher::new("foo").unwrap();
+        let haystack = "foo one\nfoo two\nfoo one\nfoo two\n";
+        let mut printer = StandardBuilder::new()
+            .dedupe_lines(true)
+            .dedupe_lines_limit(1)
+            .build(NoColor::new(vec![]));
+        SearcherBuilder::new()
+            .line_number(true)
+            .build()
+            .search_reader(
+                &matcher,
+                haystack.as_bytes(),
+                printer.sink(&matcher),
+            )
+            .unwrap();
+
+        // Only the first distinct line is tracked, so 'foo two' prints every
+        // time while 'foo one' is still deduplicated.
+        let got = printer_contents(&mut printer);
+        let expected = "\
+1:foo one
+2:foo two
+4:foo two
+(line 1 repeated 1 more times)
+";
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn slow_file_threshold() {
+        use std::{
+            sync::{
+                atomic::{AtomicU64, Ordering},
+                Arc,
+            },
+            time::Duration,
+        };
+
+        // Each reading of the fake clock advances it by two seconds, so
+        // every search appears to take exactly two seconds.
+        let ticks = Arc::new(AtomicU64::new(0));
+        let mut builder = StandardBuilder::new();
+        builder.slow_file_threshold(Some(Duration::from_secs(1))).time_source(
+            move || Duration::from_secs(ticks.fetch_add(2, Ordering::SeqCst)),
+        );
+        let mut printer = builder.build(NoColor::new(vec![]));
+        let matcher = RegexMatcher::new("Watson").unwrap();
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK.as_bytes(),
+                printer.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+
+        let got = printer_contents(&mut printer);
+        let bytes = SHERLOCK.len() as u64;
+        let expected = format!(
+            "\
+sherlock:1:For the Doctor Watsons of this world, as opposed to the Sherlock
+sherlock:5:but Doctor Watson has to have it taken out for him and dusted,
+# slow: sherlock: 2.000000s elapsed, {} bytes searched, {} bytes/sec
+",
+            bytes,
+            bytes / 2,
+        );
+        assert_eq_printed!(expected, got);
+    }
 }
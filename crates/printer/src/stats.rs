@@ -1,8 +1,11 @@
 use std::{
+    io,
     ops::{Add, AddAssign},
     time::Duration,
 };
 
+use termcolor::{ColorSpec, WriteColor};
+
 use crate::util::NiceDuration;
 
 /// Summary statistics produced at the end of a search.
@@ -18,6 +21,10 @@ pub struct Stats {
     bytes_printed: u64,
     matched_lines: u64,
     matches: u64,
+    retries: u64,
+    identical_replacements: u64,
+    duplicates_skipped: u64,
+    per_pattern: Vec<u64>,
 }
 
 impl Stats {
@@ -68,6 +75,33 @@ impl Stats {
         self.matches
     }
 
+    /// Return the total number of retried searches.
+    ///
+    /// This is always `0` unless the caller of a printer explicitly adds to
+    /// it, e.g., because transient errors were retried while reading a
+    /// haystack.
+    pub fn retries(&self) -> u64 {
+        self.retries
+    }
+
+    /// Return the total number of replacements that were suppressed because
+    /// they were identical to the text they replaced.
+    ///
+    /// This is always `0` unless a printer that elides no-op replacements
+    /// (e.g., the patch printer) was used.
+    pub fn identical_replacements(&self) -> u64 {
+        self.identical_replacements
+    }
+
+    /// Return the total number of haystacks that were skipped because they
+    /// had already been searched via another path.
+    ///
+    /// This is always `0` unless the caller of a printer explicitly adds to
+    /// it, e.g., because duplicate files were detected and skipped.
+    pub fn duplicates_skipped(&self) -> u64 {
+        self.duplicates_skipped
+    }
+
     /// Add to the elapsed time.
     pub fn add_elapsed(&mut self, duration: Duration) {
         self.elapsed.0 += duration;
@@ -102,6 +136,43 @@ impl Stats {
     pub fn add_matches(&mut self, n: u64) {
         self.matches += n;
     }
+
+    /// Add to the total number of retried searches.
+    pub fn add_retries(&mut self, n: u64) {
+        self.retries += n;
+    }
+
+    /// Add to the total number of suppressed identical replacements.
+    pub fn add_identical_replacements(&mut self, n: u64) {
+        self.identical_replacements += n;
+    }
+
+    /// Add to the total number of skipped duplicate haystacks.
+    pub fn add_duplicates_skipped(&mut self, n: u64) {
+        self.duplicates_skipped += n;
+    }
+
+    /// Return the per-pattern match counts, indexed by pattern.
+    ///
+    /// This is empty unless a printer that attributes matches to individual
+    /// patterns (e.g., the summary printer with `count_per_pattern` enabled)
+    /// recorded counts. Patterns that never matched are represented by
+    /// explicit zero entries.
+    pub fn per_pattern(&self) -> &[u64] {
+        &self.per_pattern
+    }
+
+    /// Add the given per-pattern match counts, element-wise.
+    ///
+    /// The counts recorded here grow to accommodate the longest slice given.
+    pub fn add_per_pattern(&mut self, counts: &[u64]) {
+        if self.per_pattern.len() < counts.len() {
+            self.per_pattern.resize(counts.len(), 0);
+        }
+        for (total, &n) in self.per_pattern.iter_mut().zip(counts.iter()) {
+            *total += n;
+        }
+    }
 }
 
 impl Add for Stats {
@@ -115,7 +186,8 @@ impl Add for Stats {
 impl<'a> Add<&'a Stats> for Stats {
     type Output = Stats;
 
-    fn add(self, rhs: &'a Stats) -> Stats {
+    fn add(mut self, rhs: &'a Stats) -> Stats {
+        self.add_per_pattern(&rhs.per_pattern);
         Stats {
             elapsed: NiceDuration(self.elapsed.0 + rhs.elapsed.0),
             searches: self.searches + rhs.searches,
@@ -125,6 +197,12 @@ impl<'a> Add<&'a Stats> for Stats {
             bytes_printed: self.bytes_printed + rhs.bytes_printed,
             matched_lines: self.matched_lines + rhs.matched_lines,
             matches: self.matches + rhs.matches,
+            retries: self.retries + rhs.retries,
+            identical_replacements: self.identical_replacements
+                + rhs.identical_replacements,
+            duplicates_skipped: self.duplicates_skipped
+                + rhs.duplicates_skipped,
+            per_pattern: self.per_pattern,
         }
     }
 }
@@ -144,6 +222,231 @@ impl<'a> AddAssign<&'a Stats> for Stats {
         self.bytes_printed += rhs.bytes_printed;
         self.matched_lines += rhs.matched_lines;
         self.matches += rhs.matches;
+        self.retries += rhs.retries;
+        self.identical_replacements += rhs.identical_replacements;
+        self.duplicates_skipped += rhs.duplicates_skipped;
+        self.add_per_pattern(&rhs.per_pattern);
+    }
+}
+
+/// Options for rendering statistics in a human readable format.
+///
+/// This is used by [`write_stats`]. The default options correspond to the
+/// trailer printed by ripgrep's `--stats` flag: a multi-line layout with raw
+/// byte counts, fractional seconds and no colors.
+#[derive(Clone, Debug)]
+pub struct StatsRenderOptions {
+    human_bytes: bool,
+    byte_precision: usize,
+    human_durations: bool,
+    compact: bool,
+    label_spec: ColorSpec,
+    number_spec: ColorSpec,
+}
+
+impl Default for StatsRenderOptions {
+    fn default() -> StatsRenderOptions {
+        StatsRenderOptions {
+            human_bytes: false,
+            byte_precision: 2,
+            human_durations: false,
+            compact: false,
+            label_spec: ColorSpec::new(),
+            number_spec: ColorSpec::new(),
+        }
+    }
+}
+
+impl StatsRenderOptions {
+    /// Return a new set of options with the default configuration.
+    pub fn new() -> StatsRenderOptions {
+        StatsRenderOptions::default()
+    }
+
+    /// When enabled, render byte counts with binary units, e.g., `1.50MiB`
+    /// instead of `1572864`.
+    ///
+    /// This is disabled by default.
+    pub fn human_bytes(&mut self, yes: bool) -> &mut StatsRenderOptions {
+        self.human_bytes = yes;
+        self
+    }
+
+    /// Set the number of decimal digits used when rendering byte counts with
+    /// binary units. This has no effect unless
+    /// [`human_bytes`](StatsRenderOptions::human_bytes) is enabled.
+    ///
+    /// The default is `2`.
+    pub fn byte_precision(
+        &mut self,
+        precision: usize,
+    ) -> &mut StatsRenderOptions {
+        self.byte_precision = precision;
+        self
+    }
+
+    /// When enabled, render durations in a compound form, e.g., `1m32s`
+    /// instead of `92.000000` seconds.
+    ///
+    /// This is disabled by default.
+    pub fn human_durations(&mut self, yes: bool) -> &mut StatsRenderOptions {
+        self.human_durations = yes;
+        self
+    }
+
+    /// When enabled, render all statistics on a single comma-separated line
+    /// instead of one statistic per line.
+    ///
+    /// This is disabled by default.
+    pub fn compact(&mut self, yes: bool) -> &mut StatsRenderOptions {
+        self.compact = yes;
+        self
+    }
+
+    /// Set the color used for the labels, e.g., the `matches` in
+    /// `5 matches`.
+    ///
+    /// The default is no color.
+    pub fn label_color(&mut self, spec: ColorSpec) -> &mut StatsRenderOptions {
+        self.label_spec = spec;
+        self
+    }
+
+    /// Set the color used for the numbers, e.g., the `5` in `5 matches`.
+    ///
+    /// The default is no color.
+    pub fn number_color(
+        &mut self,
+        spec: ColorSpec,
+    ) -> &mut StatsRenderOptions {
+        self.number_spec = spec;
+        self
+    }
+
+    /// Render the given byte count according to these options.
+    ///
+    /// `what` should describe the count, e.g., `searched`. The label
+    /// returned includes a `bytes` prefix when raw counts are rendered,
+    /// since the number itself carries no unit in that case.
+    fn byte_item(&self, count: u64, what: &str) -> (String, String) {
+        if self.human_bytes {
+            (
+                human_readable_bytes(count, self.byte_precision),
+                what.to_string(),
+            )
+        } else {
+            (count.to_string(), format!("bytes {}", what))
+        }
+    }
+
+    /// Render the given duration according to these options.
+    fn duration_item(&self, duration: Duration) -> (String, String) {
+        if self.human_durations {
+            (human_readable_duration(duration), "spent searching".to_string())
+        } else {
+            (
+                format!("{:0.6}", duration.as_secs_f64()),
+                "seconds spent searching".to_string(),
+            )
+        }
+    }
+}
+
+/// Write the given statistics in a human readable format.
+///
+/// This renders the same trailer that ripgrep prints for its `--stats` flag,
+/// with the layout, units and colors controlled by the options given. See
+/// [`StatsRenderOptions`] for the available knobs.
+///
+/// Colors are only emitted when the underlying writer supports them and a
+/// non-default color was configured.
+pub fn write_stats<W: WriteColor>(
+    mut wtr: W,
+    stats: &Stats,
+    opts: &StatsRenderOptions,
+) -> io::Result<()> {
+    let (printed, printed_label) =
+        opts.byte_item(stats.bytes_printed(), "printed");
+    let (searched, searched_label) =
+        opts.byte_item(stats.bytes_searched(), "searched");
+    let (elapsed, elapsed_label) = opts.duration_item(stats.elapsed());
+    let items = [
+        (stats.matches().to_string(), "matches".to_string()),
+        (stats.matched_lines().to_string(), "matched lines".to_string()),
+        (
+            stats.searches_with_match().to_string(),
+            "files contained matches".to_string(),
+        ),
+        (stats.searches().to_string(), "files searched".to_string()),
+        (stats.retries().to_string(), "searches retried".to_string()),
+        (printed, printed_label),
+        (searched, searched_label),
+        (elapsed, elapsed_label),
+    ];
+    for (i, (number, label)) in items.iter().enumerate() {
+        if opts.compact && i > 0 {
+            wtr.write_all(b", ")?;
+        }
+        write_colored(&mut wtr, &opts.number_spec, number)?;
+        wtr.write_all(b" ")?;
+        write_colored(&mut wtr, &opts.label_spec, label)?;
+        if !opts.compact {
+            wtr.write_all(b"\n")?;
+        }
+    }
+    if opts.compact {
+        wtr.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Write the given text in the given color, unless the color spec is a
+/// no-op, in which case no escape sequences are emitted at all.
+fn write_colored<W: WriteColor>(
+    wtr: &mut W,
+    spec: &ColorSpec,
+    text: &str,
+) -> io::Result<()> {
+    if spec.is_none() {
+        return wtr.write_all(text.as_bytes());
+    }
+    wtr.set_color(spec)?;
+    wtr.write_all(text.as_bytes())?;
+    wtr.reset()
+}
+
+/// Render a byte count with binary units, e.g., `1.50MiB`.
+///
+/// Counts below `1024` are rendered exactly, as a plain number of bytes.
+fn human_readable_bytes(count: u64, precision: usize) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+    if count < 1024 {
+        return format!("{}B", count);
+    }
+    let mut value = (count as f64) / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.*}{}", precision, value, UNITS[unit])
+}
+
+/// Render a duration in a compound form, e.g., `1m32s`.
+///
+/// Durations shorter than a minute are rendered as fractional seconds.
+fn human_readable_duration(duration: Duration) -> String {
+    let total = duration.as_secs();
+    if total < 60 {
+        return format!("{:0.3}s", duration.as_secs_f64());
+    }
+    let (hours, minutes, seconds) =
+        (total / 3600, (total % 3600) / 60, total % 60);
+    if hours > 0 {
+        format!("{}h{}m{}s", hours, minutes, seconds)
+    } else {
+        format!("{}m{}s", minutes, seconds)
     }
 }
 
@@ -155,7 +458,7 @@ impl serde::Serialize for Stats {
     ) -> Result<S::Ok, S::Error> {
         use serde::ser::SerializeStruct;
 
-        let mut state = s.serialize_struct("Stats", 7)?;
+        let mut state = s.serialize_struct("Stats", 11)?;
         state.serialize_field("elapsed", &self.elapsed)?;
         state.serialize_field("searches", &self.searches)?;
         state.serialize_field(
@@ -166,6 +469,161 @@ impl serde::Serialize for Stats {
         state.serialize_field("bytes_printed", &self.bytes_printed)?;
         state.serialize_field("matched_lines", &self.matched_lines)?;
         state.serialize_field("matches", &self.matches)?;
+        state.serialize_field("retries", &self.retries)?;
+        state.serialize_field(
+            "identical_replacements",
+            &self.identical_replacements,
+        )?;
+        state
+            .serialize_field("duplicates_skipped", &self.duplicates_skipped)?;
+        state.serialize_field("per_pattern", &self.per_pattern)?;
         state.end()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use termcolor::{Ansi, Color, NoColor};
+
+    use super::*;
+
+    fn example() -> Stats {
+        let mut stats = Stats::new();
+        stats.add_matches(8);
+        stats.add_matched_lines(4);
+        stats.add_searches_with_match(2);
+        stats.add_searches(3);
+        stats.add_bytes_printed(100);
+        stats.add_bytes_searched(2048);
+        stats.add_elapsed(Duration::from_millis(500));
+        stats
+    }
+
+    fn render(stats: &Stats, opts: &StatsRenderOptions) -> String {
+        let mut wtr = NoColor::new(vec![]);
+        write_stats(&mut wtr, stats, opts).unwrap();
+        String::from_utf8(wtr.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn multi_line() {
+        let got = render(&example(), &StatsRenderOptions::new());
+        let expected = "\
+8 matches
+4 matched lines
+2 files contained matches
+3 files searched
+0 searches retried
+100 bytes printed
+2048 bytes searched
+0.500000 seconds spent searching
+";
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn compact() {
+        let mut opts = StatsRenderOptions::new();
+        opts.compact(true);
+        let got = render(&example(), &opts);
+        let expected = "\
+8 matches, 4 matched lines, 2 files contained matches, 3 files searched, \
+0 searches retried, 100 bytes printed, 2048 bytes searched, \
+0.500000 seconds spent searching
+";
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn human_units() {
+        let mut stats = example();
+        stats.add_bytes_searched(3 * (1 << 20) - 2048);
+        stats.add_elapsed(Duration::from_secs(92));
+        let mut opts = StatsRenderOptions::new();
+        opts.human_bytes(true).byte_precision(1).human_durations(true);
+        let got = render(&stats, &opts);
+        let expected = "\
+8 matches
+4 matched lines
+2 files contained matches
+3 files searched
+0 searches retried
+100B printed
+3.0MiB searched
+1m32s spent searching
+";
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn multi_line_colored() {
+        let mut opts = StatsRenderOptions::new();
+        opts.number_color(
+            ColorSpec::new().set_fg(Some(Color::Magenta)).clone(),
+        );
+        opts.label_color(ColorSpec::new().set_fg(Some(Color::Green)).clone());
+        let mut wtr = Ansi::new(vec![]);
+        write_stats(&mut wtr, &example(), &opts).unwrap();
+        let got = String::from_utf8(wtr.into_inner()).unwrap();
+        let first = got.lines().next().unwrap();
+        assert_eq!(
+            "\x1b[0m\x1b[35m8\x1b[0m \x1b[0m\x1b[32mmatches\x1b[0m",
+            first,
+        );
+        assert_eq!(8, got.lines().count());
+    }
+
+    #[test]
+    fn compact_colored() {
+        let mut opts = StatsRenderOptions::new();
+        opts.compact(true).number_color(
+            ColorSpec::new().set_fg(Some(Color::Magenta)).clone(),
+        );
+        let mut wtr = Ansi::new(vec![]);
+        write_stats(&mut wtr, &example(), &opts).unwrap();
+        let got = String::from_utf8(wtr.into_inner()).unwrap();
+        assert!(got.starts_with("\x1b[0m\x1b[35m8\x1b[0m matches, "));
+        assert_eq!(1, got.lines().count());
+    }
+
+    #[test]
+    fn human_bytes_preserve_magnitude_ordering() {
+        // Decodes a rendering from `human_readable_bytes` back into an
+        // approximate byte count.
+        fn decode(rendered: &str) -> f64 {
+            let units =
+                [("KiB", 1u64 << 10), ("MiB", 1 << 20), ("GiB", 1 << 30)];
+            for (suffix, factor) in units {
+                if let Some(number) = rendered.strip_suffix(suffix) {
+                    return number.parse::<f64>().unwrap() * (factor as f64);
+                }
+            }
+            rendered.strip_suffix("B").unwrap().parse().unwrap()
+        }
+
+        // A cheap deterministic sweep over several orders of magnitude,
+        // in lieu of pulling in a property testing framework.
+        let mut counts = vec![];
+        let mut seed = 0xdecafbad_u64;
+        for _ in 0..1000 {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            counts.push(seed >> (32 + (seed % 28) as u32));
+        }
+        counts.sort();
+        for precision in [0, 1, 2, 6] {
+            let decoded: Vec<f64> = counts
+                .iter()
+                .map(|&n| decode(&human_readable_bytes(n, precision)))
+                .collect();
+            for pair in decoded.windows(2) {
+                assert!(
+                    pair[0] <= pair[1],
+                    "precision {}: {} > {}",
+                    precision,
+                    pair[0],
+                    pair[1],
+                );
+            }
+        }
+    }
+}
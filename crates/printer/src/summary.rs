@@ -1,15 +1,16 @@
 use std::{
     cell::RefCell,
+    collections::HashMap,
     io::{self, Write},
-    path::Path,
+    path::{Path, PathBuf},
     sync::Arc,
-    time::Instant,
+    time::Duration,
 };
 
 use {
     grep_matcher::Matcher,
     grep_searcher::{Searcher, Sink, SinkError, SinkFinish, SinkMatch},
-    termcolor::{ColorSpec, NoColor, WriteColor},
+    termcolor::{NoColor, WriteColor},
 };
 
 use crate::{
@@ -17,7 +18,10 @@ use crate::{
     counter::CounterWriter,
     hyperlink::{self, HyperlinkConfig},
     stats::Stats,
-    util::{find_iter_at_in_context, PrinterPath},
+    util::{
+        find_iter_at_in_context, slow_file_warning, PathDisplay, PrinterPath,
+        SlowFileWriter, TimeSource,
+    },
 };
 
 /// The configuration for the summary printer.
@@ -34,9 +38,18 @@ struct Config {
     path: bool,
     max_matches: Option<u64>,
     exclude_zero: bool,
+    report_binary_skips: bool,
+    count_per_pattern: bool,
+    show_pattern_text: bool,
+    rollup_depth: Option<usize>,
     separator_field: Arc<Vec<u8>>,
     separator_path: Option<u8>,
     path_terminator: Option<u8>,
+    path_display: PathDisplay,
+    slow_file_threshold: Option<Duration>,
+    slow_file_writer: Option<SlowFileWriter>,
+    time_source: TimeSource,
+    output_bom: bool,
 }
 
 impl Default for Config {
@@ -49,9 +62,18 @@ impl Default for Config {
             path: true,
             max_matches: None,
             exclude_zero: true,
+            report_binary_skips: false,
+            count_per_pattern: false,
+            show_pattern_text: false,
+            rollup_depth: None,
             separator_field: Arc::new(b":".to_vec()),
             separator_path: None,
             path_terminator: None,
+            path_display: PathDisplay::default(),
+            slow_file_threshold: None,
+            slow_file_writer: None,
+            time_source: TimeSource::default(),
+            output_bom: false,
         }
     }
 }
@@ -169,6 +191,7 @@ impl SummaryBuilder {
         Summary {
             config: self.config.clone(),
             wtr: RefCell::new(CounterWriter::new(wtr)),
+            rollup: RefCell::new(HashMap::new()),
         }
     }
 
@@ -259,6 +282,52 @@ impl SummaryBuilder {
         self
     }
 
+    /// Set a threshold above which a search is reported as slow.
+    ///
+    /// When set, a warning line is emitted at the end of any single search
+    /// whose elapsed time exceeds the threshold, containing the path, the
+    /// elapsed time, the number of bytes searched and the search rate in
+    /// bytes per second. The warning is written to the writer set via
+    /// [`SummaryBuilder::slow_file_writer`], or to the main writer prefixed
+    /// with `# slow: ` when no such writer was set.
+    ///
+    /// This is disabled by default.
+    pub fn slow_file_threshold(
+        &mut self,
+        threshold: Option<Duration>,
+    ) -> &mut SummaryBuilder {
+        self.config.slow_file_threshold = threshold;
+        self
+    }
+
+    /// Set a secondary writer that slow file warnings are written to.
+    ///
+    /// This has no effect unless a threshold is set via
+    /// [`SummaryBuilder::slow_file_threshold`]. When no secondary writer is
+    /// set (the default), warnings are written to the main writer.
+    pub fn slow_file_writer(
+        &mut self,
+        wtr: Option<Box<dyn io::Write + Send>>,
+    ) -> &mut SummaryBuilder {
+        self.config.slow_file_writer = wtr.map(SlowFileWriter::new);
+        self
+    }
+
+    /// Set the source of time used to measure how long each search took.
+    ///
+    /// The function given should report the time elapsed since some fixed
+    /// origin of its own choosing; only differences between readings are
+    /// used. The default reads the system's monotonic clock. This exists
+    /// principally so that tests can exercise slow file reporting
+    /// deterministically.
+    pub fn time_source<F>(&mut self, f: F) -> &mut SummaryBuilder
+    where
+        F: Fn() -> Duration + Send + Sync + 'static,
+    {
+        self.config.time_source = TimeSource::new(f);
+        self
+    }
+
     /// When enabled, if a path was given to the printer, then it is shown in
     /// the output (either as a heading or as a prefix to each matching line).
     /// When disabled, then no paths are ever included in the output even when
@@ -297,6 +366,81 @@ impl SummaryBuilder {
         self
     }
 
+    /// Report files that were skipped because binary data was detected.
+    ///
+    /// When enabled and the searcher's binary detection method is to quit,
+    /// a search that stopped on binary data without reporting any matches
+    /// prints a `binary file skipped (offset N)` line instead of producing
+    /// no output at all. This makes it possible to tell a clean file apart
+    /// from one that was never fully searched.
+    ///
+    /// This is disabled by default.
+    pub fn report_binary_skips(&mut self, yes: bool) -> &mut SummaryBuilder {
+        self.config.report_binary_skips = yes;
+        self
+    }
+
+    /// Break counts down by pattern in `Count` and `CountMatches` modes.
+    ///
+    /// When enabled and the matcher supports attributing matches to the
+    /// individual patterns it was built from, each search prints one
+    /// `path:pattern:count` line per pattern instead of a single aggregate
+    /// count. Patterns that never matched are printed with an explicit `0`
+    /// count. Each pattern is identified by its index, unless
+    /// [`show_pattern_text`](SummaryBuilder::show_pattern_text) is enabled.
+    ///
+    /// When the matcher cannot attribute matches to patterns, this falls
+    /// back to the aggregate count and emits a warning (once per process)
+    /// through the `log` crate.
+    ///
+    /// The per-pattern counts are also recorded in [`Stats`], when statistics
+    /// are enabled.
+    ///
+    /// This setting has no effect in modes other than `Count` and
+    /// `CountMatches`, and is disabled by default.
+    pub fn count_per_pattern(&mut self, yes: bool) -> &mut SummaryBuilder {
+        self.config.count_per_pattern = yes;
+        self
+    }
+
+    /// Show the original pattern text instead of the pattern index in
+    /// per-pattern counts.
+    ///
+    /// This only takes effect when
+    /// [`count_per_pattern`](SummaryBuilder::count_per_pattern) is enabled
+    /// and the matcher can report the text of its patterns. Otherwise, the
+    /// pattern index is printed.
+    ///
+    /// This is disabled by default.
+    pub fn show_pattern_text(&mut self, yes: bool) -> &mut SummaryBuilder {
+        self.config.show_pattern_text = yes;
+        self
+    }
+
+    /// Roll counts up by directory instead of reporting them per file.
+    ///
+    /// When this is set to a depth `N` and the mode is either `Count` or
+    /// `CountMatches`, then per-file counts are not printed as each search
+    /// finishes. Instead, each count is accumulated into a bucket keyed by
+    /// the first `N` components of the file path given to the printer. Paths
+    /// with fewer than `N` components are accumulated under their own full
+    /// path. The accumulated counts are printed, one bucket per line and
+    /// sorted in descending order of count, when [`Summary::finish_run`] is
+    /// called. Callers should therefore call `finish_run` once after all
+    /// searches sharing this printer have completed.
+    ///
+    /// This setting has no effect in modes other than `Count` and
+    /// `CountMatches`.
+    ///
+    /// This is disabled by default.
+    pub fn rollup_depth(
+        &mut self,
+        depth: Option<usize>,
+    ) -> &mut SummaryBuilder {
+        self.config.rollup_depth = depth;
+        self
+    }
+
     /// Set the separator used between fields for the `Count` and
     /// `CountMatches` modes.
     ///
@@ -336,6 +480,39 @@ impl SummaryBuilder {
         self.config.path_terminator = terminator;
         self
     }
+
+    /// Set the path display configuration used by this printer.
+    ///
+    /// This controls transformations applied to file paths before they are
+    /// printed, such as canonicalization or abbreviating a home directory
+    /// prefix with `~`. See [`PathDisplay`](crate::PathDisplay).
+    ///
+    /// Transformations only apply to how paths are displayed. Hyperlinks
+    /// continue to carry the full path.
+    ///
+    /// By default, paths are printed as given.
+    pub fn path_display(
+        &mut self,
+        display: PathDisplay,
+    ) -> &mut SummaryBuilder {
+        self.config.path_display = display;
+        self
+    }
+
+    /// When enabled, write a UTF-8 byte order mark before the first byte of
+    /// output.
+    ///
+    /// Some Windows tools (such as PowerShell pipelines or legacy editors)
+    /// misinterpret UTF-8 output unless it begins with a BOM. The BOM is
+    /// written exactly once per printer, immediately before the first byte
+    /// of output, even when the printer is used for multiple searches. When
+    /// a printer produces no output at all, no BOM is written either.
+    ///
+    /// This is disabled by default.
+    pub fn output_bom(&mut self, yes: bool) -> &mut SummaryBuilder {
+        self.config.output_bom = yes;
+        self
+    }
 }
 
 /// The summary printer, which emits aggregate results from a search.
@@ -354,6 +531,7 @@ impl SummaryBuilder {
 pub struct Summary<W> {
     config: Config,
     wtr: RefCell<CounterWriter<W>>,
+    rollup: RefCell<HashMap<PathBuf, u64>>,
 }
 
 impl<W: WriteColor> Summary<W> {
@@ -403,15 +581,18 @@ impl<W: WriteColor> Summary<W> {
         } else {
             None
         };
+        let per_pattern = per_pattern_counts(&self.config, &matcher);
+        let start_time = self.config.time_source.now();
         SummarySink {
             matcher,
             summary: self,
             interpolator,
             path: None,
-            start_time: Instant::now(),
+            start_time,
             match_count: 0,
             binary_byte_offset: None,
             stats,
+            per_pattern,
         }
     }
 
@@ -439,17 +620,86 @@ impl<W: WriteColor> Summary<W> {
             None
         };
         let ppath = PrinterPath::new(path.as_ref())
+            .with_display(&self.config.path_display)
             .with_separator(self.config.separator_path);
+        let per_pattern = per_pattern_counts(&self.config, &matcher);
+        let start_time = self.config.time_source.now();
         SummarySink {
             matcher,
             summary: self,
             interpolator,
             path: Some(ppath),
-            start_time: Instant::now(),
+            start_time,
             match_count: 0,
             binary_byte_offset: None,
             stats,
+            per_pattern,
+        }
+    }
+
+    /// Print any counts accumulated via the `rollup_depth` setting.
+    ///
+    /// When rollups are enabled, per-file counts are withheld from the output
+    /// and accumulated across all searches executed with this printer. This
+    /// prints one line per rollup bucket in descending order of count (ties
+    /// are broken by the bucket path, ascending) and clears the accumulated
+    /// state. Callers should invoke this once, after all searches sharing
+    /// this printer have completed.
+    ///
+    /// When rollups are disabled or no counts were accumulated, this prints
+    /// nothing.
+    pub fn finish_run(&mut self) -> io::Result<()> {
+        let rollup = std::mem::take(&mut *self.rollup.borrow_mut());
+        if rollup.is_empty() {
+            return Ok(());
+        }
+        let mut buckets: Vec<(PathBuf, u64)> = rollup.into_iter().collect();
+        buckets.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        for (path, count) in buckets {
+            let ppath = PrinterPath::new(&path)
+                .with_display(&self.config.path_display)
+                .with_separator(self.config.separator_path);
+            self.write_path(&ppath)?;
+            self.write_path_terminator(&self.config.separator_field)?;
+            let mut wtr = self.wtr.borrow_mut();
+            wtr.write_all(count.to_string().as_bytes())?;
+            wtr.write_all(b"\n")?;
         }
+        Ok(())
+    }
+
+    /// Write the given path to the underlying writer in the configured path
+    /// color.
+    ///
+    /// All summary modes route their path rendering through this helper and
+    /// [`Summary::write_path_terminator`] so that the path color spec and
+    /// the path terminator are applied consistently. (The path separator
+    /// mapping is applied when the given `PrinterPath` is built.)
+    fn write_path(&self, ppath: &PrinterPath<'_>) -> io::Result<()> {
+        self.write_output_bom()?;
+        let mut wtr = self.wtr.borrow_mut();
+        wtr.set_color(self.config.colors.path())?;
+        wtr.write_all(ppath.as_bytes())?;
+        wtr.reset()?;
+        Ok(())
+    }
+
+    /// Write the configured path terminator, or the given separator when no
+    /// path terminator is set.
+    fn write_path_terminator(&self, sep: &[u8]) -> io::Result<()> {
+        match self.config.path_terminator {
+            Some(term) => self.wtr.borrow_mut().write_all(&[term]),
+            None => self.wtr.borrow_mut().write_all(sep),
+        }
+    }
+
+    /// Write the UTF-8 BOM if it was requested and nothing has been written
+    /// to this printer yet.
+    fn write_output_bom(&self) -> io::Result<()> {
+        if self.config.output_bom && !self.has_written() {
+            self.wtr.borrow_mut().write_all("\u{FEFF}".as_bytes())?;
+        }
+        Ok(())
     }
 }
 
@@ -472,6 +722,40 @@ impl<W> Summary<W> {
     }
 }
 
+/// Returns zeroed per-pattern count buckets when per-pattern counting is
+/// enabled and applicable, and the given matcher supports attributing matches
+/// to the patterns it was built from.
+///
+/// When per-pattern counting was requested but the matcher doesn't support
+/// attribution, this returns `None` (falling back to aggregate counts) and
+/// warns through the `log` crate, at most once per process.
+fn per_pattern_counts<M: Matcher>(
+    config: &Config,
+    matcher: &M,
+) -> Option<Vec<u64>> {
+    if !config.count_per_pattern {
+        return None;
+    }
+    if !matches!(config.kind, SummaryKind::Count | SummaryKind::CountMatches)
+    {
+        return None;
+    }
+    match matcher.pattern_count() {
+        Some(len) => Some(vec![0; len]),
+        None => {
+            static WARN_ONCE: std::sync::Once = std::sync::Once::new();
+            WARN_ONCE.call_once(|| {
+                log::warn!(
+                    "per-pattern counts were requested, but the matcher \
+                     does not support attributing matches to patterns; \
+                     falling back to aggregate counts",
+                );
+            });
+            None
+        }
+    }
+}
+
 /// An implementation of `Sink` associated with a matcher and an optional file
 /// path for the summary printer.
 ///
@@ -491,10 +775,11 @@ pub struct SummarySink<'p, 's, M: Matcher, W> {
     summary: &'s mut Summary<W>,
     interpolator: hyperlink::Interpolator,
     path: Option<PrinterPath<'p>>,
-    start_time: Instant,
+    start_time: Duration,
     match_count: u64,
     binary_byte_offset: Option<u64>,
     stats: Option<Stats>,
+    per_pattern: Option<Vec<u64>>,
 }
 
 impl<'p, 's, M: Matcher, W: WriteColor> SummarySink<'p, 's, M, W> {
@@ -557,6 +842,20 @@ impl<'p, 's, M: Matcher, W: WriteColor> SummarySink<'p, 's, M, W> {
         self.match_count >= limit
     }
 
+    /// If rollups are enabled and this sink has a file path, then this
+    /// accumulates the given count into the rollup bucket for that path and
+    /// returns true. Otherwise, this is a no-op that returns false, and the
+    /// count should be printed as usual.
+    fn rollup_count(&mut self, count: u64) -> bool {
+        let Some(depth) = self.summary.config.rollup_depth else {
+            return false;
+        };
+        let Some(ref path) = self.path else { return false };
+        let key: PathBuf = path.as_path().components().take(depth).collect();
+        *self.summary.rollup.borrow_mut().entry(key).or_insert(0) += count;
+        true
+    }
+
     /// If this printer has a file path associated with it, then this will
     /// write that path to the underlying writer followed by a line terminator.
     /// (If a path terminator is set, then that is used instead of the line
@@ -564,11 +863,9 @@ impl<'p, 's, M: Matcher, W: WriteColor> SummarySink<'p, 's, M, W> {
     fn write_path_line(&mut self, searcher: &Searcher) -> io::Result<()> {
         if self.path.is_some() {
             self.write_path()?;
-            if let Some(term) = self.summary.config.path_terminator {
-                self.write(&[term])?;
-            } else {
-                self.write_line_term(searcher)?;
-            }
+            self.summary.write_path_terminator(
+                searcher.line_terminator().as_bytes(),
+            )?;
         }
         Ok(())
     }
@@ -580,11 +877,8 @@ impl<'p, 's, M: Matcher, W: WriteColor> SummarySink<'p, 's, M, W> {
     fn write_path_field(&mut self) -> io::Result<()> {
         if self.path.is_some() {
             self.write_path()?;
-            if let Some(term) = self.summary.config.path_terminator {
-                self.write(&[term])?;
-            } else {
-                self.write(&self.summary.config.separator_field)?;
-            }
+            let sep = self.summary.config.separator_field.clone();
+            self.summary.write_path_terminator(&sep)?;
         }
         Ok(())
     }
@@ -595,10 +889,7 @@ impl<'p, 's, M: Matcher, W: WriteColor> SummarySink<'p, 's, M, W> {
     fn write_path(&mut self) -> io::Result<()> {
         if self.path.is_some() {
             let status = self.start_hyperlink()?;
-            self.write_spec(
-                self.summary.config.colors.path(),
-                self.path.as_ref().unwrap().as_bytes(),
-            )?;
+            self.summary.write_path(self.path.as_ref().unwrap())?;
             self.end_hyperlink(status)?;
         }
         Ok(())
@@ -624,23 +915,57 @@ impl<'p, 's, M: Matcher, W: WriteColor> SummarySink<'p, 's, M, W> {
         self.interpolator.finish(status, &mut *self.summary.wtr.borrow_mut())
     }
 
+    /// Write one `path:pattern:count` line for each pattern, including
+    /// patterns that never matched. The explicit zero rows are the point:
+    /// they make the breakdown usable without knowing the pattern list.
+    fn write_per_pattern_counts(
+        &mut self,
+        searcher: &Searcher,
+        counts: &[u64],
+    ) -> io::Result<()> {
+        for (index, &count) in counts.iter().enumerate() {
+            self.write_path_field()?;
+            let text = if self.summary.config.show_pattern_text {
+                self.matcher.pattern_text(index).map(|p| p.to_string())
+            } else {
+                None
+            };
+            match text {
+                Some(ref text) => self.write(text.as_bytes())?,
+                None => self.write(index.to_string().as_bytes())?,
+            }
+            let sep = self.summary.config.separator_field.clone();
+            self.write(&sep)?;
+            self.write(count.to_string().as_bytes())?;
+            self.write_line_term(searcher)?;
+        }
+        Ok(())
+    }
+
     /// Write the line terminator configured on the given searcher.
     fn write_line_term(&self, searcher: &Searcher) -> io::Result<()> {
         self.write(searcher.line_terminator().as_bytes())
     }
 
-    /// Write the given bytes using the give style.
-    fn write_spec(&self, spec: &ColorSpec, buf: &[u8]) -> io::Result<()> {
-        self.summary.wtr.borrow_mut().set_color(spec)?;
-        self.write(buf)?;
-        self.summary.wtr.borrow_mut().reset()?;
-        Ok(())
-    }
-
     /// Write all of the given bytes.
     fn write(&self, buf: &[u8]) -> io::Result<()> {
+        self.summary.write_output_bom()?;
         self.summary.wtr.borrow_mut().write_all(buf)
     }
+
+    /// Write a slow file warning for the search that just finished.
+    fn write_slow_file_warning(
+        &self,
+        elapsed: Duration,
+        bytes: u64,
+    ) -> io::Result<()> {
+        let path = self.path.as_ref().map(|p| p.as_path());
+        let line = slow_file_warning(path, elapsed, bytes);
+        match self.summary.config.slow_file_writer {
+            Some(ref wtr) => wtr.write_line(line.as_bytes()),
+            None => self.write(line.as_bytes()),
+        }
+    }
 }
 
 impl<'p, 's, M: Matcher, W: WriteColor> Sink for SummarySink<'p, 's, M, W> {
@@ -679,6 +1004,31 @@ impl<'p, 's, M: Matcher, W: WriteColor> Sink for SummarySink<'p, 's, M, W> {
         } else {
             self.match_count += 1;
         }
+        if let Some(mut counts) = self.per_pattern.take() {
+            // In CountMatches mode, each individual match is attributed to
+            // the first pattern that matches its text. In Count mode, each
+            // group of matching lines counts once, just like the aggregate.
+            if self.summary.config.kind == SummaryKind::CountMatches {
+                let buf = mat.buffer();
+                let range = mat.bytes_range_in_buffer();
+                let matcher = &self.matcher;
+                find_iter_at_in_context(searcher, matcher, buf, range, |m| {
+                    let idx =
+                        matcher.pattern_index(&buf[m.start()..m.end()]);
+                    if let Some(c) = idx.and_then(|i| counts.get_mut(i)) {
+                        *c += 1;
+                    }
+                    true
+                })?;
+            } else if let Some(c) = self
+                .matcher
+                .pattern_index(mat.bytes())
+                .and_then(|i| counts.get_mut(i))
+            {
+                *c += 1;
+            }
+            self.per_pattern = Some(counts);
+        }
         if let Some(ref mut stats) = self.stats {
             stats.add_matches(sink_match_count);
             stats.add_matched_lines(mat.lines().count() as u64);
@@ -713,9 +1063,14 @@ impl<'p, 's, M: Matcher, W: WriteColor> Sink for SummarySink<'p, 's, M, W> {
             )));
         }
         self.summary.wtr.borrow_mut().reset_count();
-        self.start_time = Instant::now();
+        self.start_time = self.summary.config.time_source.now();
         self.match_count = 0;
         self.binary_byte_offset = None;
+        if let Some(ref mut counts) = self.per_pattern {
+            for count in counts.iter_mut() {
+                *count = 0;
+            }
+        }
         if self.summary.config.max_matches == Some(0) {
             return Ok(false);
         }
@@ -729,14 +1084,28 @@ impl<'p, 's, M: Matcher, W: WriteColor> Sink for SummarySink<'p, 's, M, W> {
         finish: &SinkFinish,
     ) -> Result<(), io::Error> {
         self.binary_byte_offset = finish.binary_byte_offset();
+        let elapsed = self
+            .summary
+            .config
+            .time_source
+            .now()
+            .saturating_sub(self.start_time);
+        if let Some(threshold) = self.summary.config.slow_file_threshold {
+            if elapsed > threshold {
+                self.write_slow_file_warning(elapsed, finish.byte_count())?;
+            }
+        }
         if let Some(ref mut stats) = self.stats {
-            stats.add_elapsed(self.start_time.elapsed());
+            stats.add_elapsed(elapsed);
             stats.add_searches(1);
             if self.match_count > 0 {
                 stats.add_searches_with_match(1);
             }
             stats.add_bytes_searched(finish.byte_count());
             stats.add_bytes_printed(self.summary.wtr.borrow().count());
+            if let Some(ref counts) = self.per_pattern {
+                stats.add_per_pattern(counts);
+            }
         }
         // If our binary detection method says to quit after seeing binary
         // data, then we shouldn't print any results at all, even if we've
@@ -757,35 +1126,53 @@ impl<'p, 's, M: Matcher, W: WriteColor> Sink for SummarySink<'p, 's, M, W> {
         // It's not possible to fix this without also potentially significantly
         // impacting the performance of Quiet or PathWithMatch, so we accept
         // the bug.
-        if self.binary_byte_offset.is_some()
-            && searcher.binary_detection().quit_byte().is_some()
-        {
-            // Squash the match count. The statistics reported will still
-            // contain the match count, but the "official" match count should
-            // be zero.
-            self.match_count = 0;
-            return Ok(());
+        if let Some(offset) = self.binary_byte_offset {
+            if searcher.binary_detection().quit_byte().is_some() {
+                // Squash the match count. The statistics reported will still
+                // contain the match count, but the "official" match count
+                // should be zero.
+                self.match_count = 0;
+                if self.summary.config.report_binary_skips {
+                    self.write_path_field()?;
+                    let msg =
+                        format!("binary file skipped (offset {})", offset);
+                    self.write(msg.as_bytes())?;
+                    self.write_line_term(searcher)?;
+                }
+                return Ok(());
+            }
         }
 
         let show_count =
             !self.summary.config.exclude_zero || self.match_count > 0;
         match self.summary.config.kind {
             SummaryKind::Count => {
-                if show_count {
-                    self.write_path_field()?;
-                    self.write(self.match_count.to_string().as_bytes())?;
-                    self.write_line_term(searcher)?;
+                if show_count && !self.rollup_count(self.match_count) {
+                    if let Some(counts) = self.per_pattern.take() {
+                        self.write_per_pattern_counts(searcher, &counts)?;
+                        self.per_pattern = Some(counts);
+                    } else {
+                        self.write_path_field()?;
+                        self.write(self.match_count.to_string().as_bytes())?;
+                        self.write_line_term(searcher)?;
+                    }
                 }
             }
             SummaryKind::CountMatches => {
-                if show_count {
-                    self.write_path_field()?;
-                    let stats = self
-                        .stats
-                        .as_ref()
-                        .expect("CountMatches should enable stats tracking");
-                    self.write(stats.matches().to_string().as_bytes())?;
-                    self.write_line_term(searcher)?;
+                let matches = self
+                    .stats
+                    .as_ref()
+                    .expect("CountMatches should enable stats tracking")
+                    .matches();
+                if show_count && !self.rollup_count(matches) {
+                    if let Some(counts) = self.per_pattern.take() {
+                        self.write_per_pattern_counts(searcher, &counts)?;
+                        self.per_pattern = Some(counts);
+                    } else {
+                        self.write_path_field()?;
+                        self.write(matches.to_string().as_bytes())?;
+                        self.write_line_term(searcher)?;
+                    }
                 }
             }
             SummaryKind::PathWithMatch => {
@@ -808,7 +1195,9 @@ impl<'p, 's, M: Matcher, W: WriteColor> Sink for SummarySink<'p, 's, M, W> {
 mod tests {
     use grep_regex::RegexMatcher;
     use grep_searcher::SearcherBuilder;
-    use termcolor::NoColor;
+    use termcolor::{Ansi, NoColor, WriteColor};
+
+    use crate::color::ColorSpecs;
 
     use super::{Summary, SummaryBuilder, SummaryKind};
 
@@ -825,6 +1214,236 @@ and exhibited clearly, with a label attached.
         String::from_utf8(printer.get_mut().get_ref().to_owned()).unwrap()
     }
 
+    fn printer_contents_ansi(printer: &mut Summary<Ansi<Vec<u8>>>) -> String {
+        String::from_utf8(printer.get_mut().get_ref().to_owned()).unwrap()
+    }
+
+    /// The four path-printing summary kinds, along with a pattern that
+    /// produces output for each and the count printed, if any.
+    const PATH_KINDS: [(SummaryKind, &'static str, Option<&'static str>); 4] = [
+        (SummaryKind::Count, r"Watson", Some("2")),
+        (SummaryKind::CountMatches, r"Watson", Some("2")),
+        (SummaryKind::PathWithMatch, r"Watson", None),
+        (SummaryKind::PathWithoutMatch, r"NADA", None),
+    ];
+
+    fn search<W: WriteColor>(printer: &mut Summary<W>, pattern: &str) {
+        let matcher = RegexMatcher::new(pattern).unwrap();
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, "foo/sherlock"),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn kinds_with_color() {
+        let specs = ColorSpecs::new(&["path:fg:magenta".parse().unwrap()]);
+        for (kind, pattern, count) in PATH_KINDS {
+            let mut printer = SummaryBuilder::new()
+                .kind(kind)
+                .color_specs(specs.clone())
+                .build(Ansi::new(vec![]));
+            search(&mut printer, pattern);
+            let got = printer_contents_ansi(&mut printer);
+
+            let expected = match count {
+                Some(count) => format!(
+                    "\x1b[0m\x1b[35mfoo/sherlock\x1b[0m:{}\n",
+                    count
+                ),
+                None => "\x1b[0m\x1b[35mfoo/sherlock\x1b[0m\n".to_string(),
+            };
+            assert_eq_printed!(expected, got);
+        }
+    }
+
+    #[test]
+    fn kinds_with_path_terminator() {
+        for (kind, pattern, count) in PATH_KINDS {
+            let mut printer = SummaryBuilder::new()
+                .kind(kind)
+                .path_terminator(Some(b'\x00'))
+                .build_no_color(vec![]);
+            search(&mut printer, pattern);
+            let got = printer_contents(&mut printer);
+
+            let expected = match count {
+                Some(count) => format!("foo/sherlock\x00{}\n", count),
+                None => "foo/sherlock\x00".to_string(),
+            };
+            assert_eq_printed!(expected, got);
+        }
+    }
+
+    #[test]
+    fn output_bom() {
+        let mut printer = SummaryBuilder::new()
+            .kind(SummaryKind::Count)
+            .output_bom(true)
+            .build_no_color(vec![]);
+        search(&mut printer, r"Watson");
+        search(&mut printer, r"Watson");
+        let got = printer_contents(&mut printer);
+        // The BOM is emitted exactly once, before the first path.
+        assert_eq_printed!("\u{FEFF}foo/sherlock:2\nfoo/sherlock:2\n", got);
+    }
+
+    #[test]
+    fn output_bom_no_output() {
+        let mut printer = SummaryBuilder::new()
+            .kind(SummaryKind::Count)
+            .output_bom(true)
+            .build_no_color(vec![]);
+        search(&mut printer, r"NADA");
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("", got);
+    }
+
+    #[test]
+    fn kinds_with_separator_path() {
+        for (kind, pattern, count) in PATH_KINDS {
+            let mut printer = SummaryBuilder::new()
+                .kind(kind)
+                .separator_path(Some(b'\\'))
+                .build_no_color(vec![]);
+            search(&mut printer, pattern);
+            let got = printer_contents(&mut printer);
+
+            let expected = match count {
+                Some(count) => format!("foo\\sherlock:{}\n", count),
+                None => "foo\\sherlock\n".to_string(),
+            };
+            assert_eq_printed!(expected, got);
+        }
+    }
+
+    #[cfg(unix)] // depends on symlink support
+    #[test]
+    fn path_display_canonicalize() {
+        use crate::util::PathDisplay;
+
+        // Search through a symlinked directory and ask for the canonical
+        // (symlink-free) path to be displayed.
+        let td = std::env::temp_dir()
+            .join("grep-printer-summary-path-display-test");
+        std::fs::create_dir_all(td.join("real")).unwrap();
+        std::fs::write(td.join("real/sherlock"), "").unwrap();
+        let _ = std::fs::remove_file(td.join("link"));
+        std::os::unix::fs::symlink(td.join("real"), td.join("link")).unwrap();
+        let canon = td.join("link/sherlock").canonicalize().unwrap();
+        assert!(canon.ends_with("real/sherlock"));
+
+        let mut display = PathDisplay::new();
+        display.canonicalize(true);
+        let mut printer = SummaryBuilder::new()
+            .kind(SummaryKind::Count)
+            .path_display(display)
+            .build_no_color(vec![]);
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, &td.join("link/sherlock")),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+
+        let expected = format!("{}:2\n", canon.to_str().unwrap());
+        assert_eq_printed!(expected, got);
+        let _ = std::fs::remove_dir_all(&td);
+    }
+
+    #[test]
+    fn hyperlink_count_degrades_to_file_link() {
+        use crate::hyperlink::{
+            HyperlinkConfig, HyperlinkEnvironment, HyperlinkFormat,
+        };
+
+        // Hyperlink paths are canonicalized, so the searched path must
+        // actually exist for links to be emitted at all.
+        let path = std::env::temp_dir()
+            .join("grep-printer-summary-hyperlink-count-test");
+        std::fs::write(&path, "").unwrap();
+        let canon = path.canonicalize().unwrap();
+        let url = canon.to_str().unwrap().to_string();
+        let display = canon.to_str().unwrap().to_string();
+
+        // In count mode there is no line number, so formats that require
+        // one degrade to a file-level link derived from the format.
+        let cases = [
+            ("vscode", format!("vscode://file{url}")),
+            ("kitty", format!("file://{url}")),
+            ("grep+", format!("grep+://{url}")),
+        ];
+        for (alias, link) in cases {
+            let format = alias.parse::<HyperlinkFormat>().unwrap();
+            let config =
+                HyperlinkConfig::new(HyperlinkEnvironment::new(), format);
+            let matcher = RegexMatcher::new(r"Watson").unwrap();
+            let mut printer = SummaryBuilder::new()
+                .kind(SummaryKind::Count)
+                .hyperlink(config)
+                .build(Ansi::new(vec![]));
+            SearcherBuilder::new()
+                .build()
+                .search_reader(
+                    &matcher,
+                    SHERLOCK,
+                    printer.sink_with_path(&matcher, &canon),
+                )
+                .unwrap();
+
+            let got = printer_contents_ansi(&mut printer);
+            let (open, st, close) = ("\x1b]8;;", "\x1b\\", "\x1b]8;;\x1b\\");
+            let expected =
+                format!("{open}{link}{st}\x1b[0m{display}\x1b[0m{close}:2\n");
+            assert_eq_printed!(expected, got);
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn hyperlink_count_suppressed_without_file_link() {
+        use crate::hyperlink::{
+            HyperlinkConfig, HyperlinkEnvironment, HyperlinkFormat,
+        };
+
+        let path = std::env::temp_dir()
+            .join("grep-printer-summary-hyperlink-suppressed-test");
+        std::fs::write(&path, "").unwrap();
+        let canon = path.canonicalize().unwrap();
+        let display = canon.to_str().unwrap().to_string();
+
+        // The line number precedes the path, so no file-level link can be
+        // derived from the format and no hyperlink is emitted at all.
+        let format = "foo://{line}/{path}".parse::<HyperlinkFormat>().unwrap();
+        let config = HyperlinkConfig::new(HyperlinkEnvironment::new(), format);
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut printer = SummaryBuilder::new()
+            .kind(SummaryKind::Count)
+            .hyperlink(config)
+            .build(Ansi::new(vec![]));
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, &canon),
+            )
+            .unwrap();
+
+        let got = printer_contents_ansi(&mut printer);
+        let expected = format!("\x1b[0m{display}\x1b[0m:2\n");
+        assert_eq_printed!(expected, got);
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[test]
     fn path_with_match_error() {
         let matcher = RegexMatcher::new(r"Watson").unwrap();
@@ -1023,6 +1642,61 @@ and exhibited clearly, with a label attached.
         assert_eq_printed!("1\n", got);
     }
 
+    #[test]
+    fn count_rollup_depth() {
+        let matcher = RegexMatcher::new(r"Watson").unwrap();
+        let mut printer = SummaryBuilder::new()
+            .kind(SummaryKind::Count)
+            .rollup_depth(Some(2))
+            .build_no_color(vec![]);
+        let paths = [
+            "crates/printer/summary.rs",
+            "crates/printer/standard.rs",
+            "crates/ignore/walk.rs",
+            "sherlock",
+        ];
+        for path in paths {
+            SearcherBuilder::new()
+                .build()
+                .search_reader(
+                    &matcher,
+                    SHERLOCK,
+                    printer.sink_with_path(&matcher, path),
+                )
+                .unwrap();
+        }
+        printer.finish_run().unwrap();
+
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!(
+            "crates/printer:4\ncrates/ignore:2\nsherlock:2\n",
+            got
+        );
+    }
+
+    #[test]
+    fn count_matches_rollup_depth() {
+        let matcher = RegexMatcher::new(r"Watson|Sherlock").unwrap();
+        let mut printer = SummaryBuilder::new()
+            .kind(SummaryKind::CountMatches)
+            .rollup_depth(Some(2))
+            .build_no_color(vec![]);
+        for path in ["a/b/c", "a/b/d", "a"] {
+            SearcherBuilder::new()
+                .build()
+                .search_reader(
+                    &matcher,
+                    SHERLOCK,
+                    printer.sink_with_path(&matcher, path),
+                )
+                .unwrap();
+        }
+        printer.finish_run().unwrap();
+
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("a/b:8\na:4\n", got);
+    }
+
     #[test]
     fn count_matches() {
         let matcher = RegexMatcher::new(r"Watson|Sherlock").unwrap();
@@ -1163,4 +1837,191 @@ and exhibited clearly, with a label attached.
         // mush on to find all matches.
         assert_eq!(3, match_count);
     }
+
+    #[test]
+    fn report_binary_skips() {
+        use grep_searcher::BinaryDetection;
+
+        // The NUL byte appears before anything matches, so by default the
+        // file is skipped without a trace.
+        const BINARY: &'static [u8] = b"nothing here\x00\nSherlock\n";
+
+        let matcher = RegexMatcher::new(r"Sherlock").unwrap();
+        let mut printer = SummaryBuilder::new()
+            .kind(SummaryKind::PathWithMatch)
+            .build_no_color(vec![]);
+        SearcherBuilder::new()
+            .binary_detection(BinaryDetection::quit(b'\x00'))
+            .build()
+            .search_reader(
+                &matcher,
+                BINARY,
+                printer.sink_with_path(&matcher, "h.bin"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("", got);
+
+        let mut printer = SummaryBuilder::new()
+            .kind(SummaryKind::PathWithMatch)
+            .report_binary_skips(true)
+            .build_no_color(vec![]);
+        SearcherBuilder::new()
+            .binary_detection(BinaryDetection::quit(b'\x00'))
+            .build()
+            .search_reader(
+                &matcher,
+                BINARY,
+                printer.sink_with_path(&matcher, "h.bin"),
+            )
+            .unwrap();
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("h.bin:binary file skipped (offset 12)\n", got);
+    }
+
+    #[test]
+    fn count_per_pattern() {
+        use grep_regex::RegexMatcherBuilder;
+
+        let matcher = RegexMatcherBuilder::new()
+            .build_many(&["Watson", "NADA"])
+            .unwrap();
+        let mut printer = SummaryBuilder::new()
+            .kind(SummaryKind::Count)
+            .count_per_pattern(true)
+            .build_no_color(vec![]);
+        SearcherBuilder::new()
+            .build()
+            .search_reader(
+                &matcher,
+                SHERLOCK,
+                printer.sink_with_path(&matcher, "sherlock"),
+            )
+            .unwrap();
+
+        // The pattern that never matches still gets an explicit zero row.
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("sherlock:0:2\nsherlock:1:0\n", got);
+    }
+
+    #[test]
+    fn count_matches_per_pattern_with_text() {
+        use grep_regex::RegexMatcherBuilder;
+
+        let matcher = RegexMatcherBuilder::new()
+            .build_many(&["Watson", "Sherlock"])
+            .unwrap();
+        let mut printer = SummaryBuilder::new()
+            .kind(SummaryKind::CountMatches)
+            .count_per_pattern(true)
+            .show_pattern_text(true)
+            .build_no_color(vec![]);
+        {
+            let mut sink = printer.sink_with_path(&matcher, "sherlock");
+            SearcherBuilder::new()
+                .build()
+                .search_reader(&matcher, SHERLOCK, &mut sink)
+                .unwrap();
+            // The per-pattern counts are recorded in the stats too.
+            assert_eq!(&[2, 2], sink.stats().unwrap().per_pattern());
+        }
+
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("sherlock:Watson:2\nsherlock:Sherlock:2\n", got);
+    }
+
+    #[test]
+    fn slow_file_threshold() {
+        use std::{
+            sync::{
+                atomic::{AtomicU64, Ordering},
+                Arc,
+            },
+            time::Duration,
+        };
+
+        // Each reading of the fake clock advances it by two seconds, so
+        // every search appears to take exactly two seconds.
+        let ticks = Arc::new(AtomicU64::new(0));
+        let mut builder = SummaryBuilder::new();
+        builder
+            .kind(SummaryKind::Count)
+            .slow_file_threshold(Some(Duration::from_secs(1)))
+            .time_source(move || {
+                Duration::from_secs(ticks.fetch_add(2, Ordering::SeqCst))
+            });
+        let mut printer = builder.build_no_color(vec![]);
+        search(&mut printer, r"Watson");
+
+        let got = printer_contents(&mut printer);
+        let bytes = SHERLOCK.len() as u64;
+        let expected = format!(
+            "# slow: foo/sherlock: 2.000000s elapsed, \
+             {} bytes searched, {} bytes/sec\nfoo/sherlock:2\n",
+            bytes,
+            bytes / 2,
+        );
+        assert_eq_printed!(expected, got);
+    }
+
+    #[test]
+    fn slow_file_threshold_secondary_writer() {
+        use std::{
+            io,
+            sync::{
+                atomic::{AtomicU64, Ordering},
+                Arc, Mutex,
+            },
+            time::Duration,
+        };
+
+        #[derive(Clone)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+
+        impl io::Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let warnings = Shared(Arc::new(Mutex::new(vec![])));
+        let ticks = Arc::new(AtomicU64::new(0));
+        let mut builder = SummaryBuilder::new();
+        builder
+            .kind(SummaryKind::Count)
+            .slow_file_threshold(Some(Duration::from_secs(1)))
+            .slow_file_writer(Some(Box::new(warnings.clone())))
+            .time_source(move || {
+                Duration::from_secs(ticks.fetch_add(2, Ordering::SeqCst))
+            });
+        let mut printer = builder.build_no_color(vec![]);
+        search(&mut printer, r"Watson");
+
+        // The warning goes to the secondary writer, so the main output is
+        // untouched.
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("foo/sherlock:2\n", got);
+        let warning =
+            String::from_utf8(warnings.0.lock().unwrap().clone()).unwrap();
+        assert!(warning.starts_with("# slow: foo/sherlock: 2.000000s"));
+    }
+
+    #[test]
+    fn slow_file_threshold_not_exceeded() {
+        use std::time::Duration;
+
+        let mut builder = SummaryBuilder::new();
+        builder
+            .kind(SummaryKind::Count)
+            .slow_file_threshold(Some(Duration::from_secs(3600)));
+        let mut printer = builder.build_no_color(vec![]);
+        search(&mut printer, r"Watson");
+
+        let got = printer_contents(&mut printer);
+        assert_eq_printed!("foo/sherlock:2\n", got);
+    }
 }
@@ -1,4 +1,11 @@
-use std::{borrow::Cow, cell::OnceCell, fmt, io, path::Path, time};
+use std::{
+    borrow::Cow,
+    cell::OnceCell,
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time,
+};
 
 use {
     bstr::ByteVec,
@@ -164,6 +171,7 @@ pub(crate) struct Sunk<'a> {
     bytes: &'a [u8],
     absolute_byte_offset: u64,
     line_number: Option<u64>,
+    approximate_line_number: bool,
     context_kind: Option<&'a SinkContextKind>,
     matches: &'a [Match],
     original_matches: &'a [Match],
@@ -176,6 +184,7 @@ impl<'a> Sunk<'a> {
             bytes: &[],
             absolute_byte_offset: 0,
             line_number: None,
+            approximate_line_number: false,
             context_kind: None,
             matches: &[],
             original_matches: &[],
@@ -194,6 +203,7 @@ impl<'a> Sunk<'a> {
             bytes,
             absolute_byte_offset: sunk.absolute_byte_offset(),
             line_number: sunk.line_number(),
+            approximate_line_number: sunk.approximate_line_number(),
             context_kind: None,
             matches,
             original_matches,
@@ -212,6 +222,7 @@ impl<'a> Sunk<'a> {
             bytes,
             absolute_byte_offset: sunk.absolute_byte_offset(),
             line_number: sunk.line_number(),
+            approximate_line_number: false,
             context_kind: Some(sunk.kind()),
             matches,
             original_matches,
@@ -252,6 +263,77 @@ impl<'a> Sunk<'a> {
     pub(crate) fn line_number(&self) -> Option<u64> {
         self.line_number
     }
+
+    #[inline]
+    pub(crate) fn approximate_line_number(&self) -> bool {
+        self.approximate_line_number
+    }
+}
+
+/// Configuration for transformations applied to file paths before they are
+/// displayed.
+///
+/// This is given to printers that emit file paths, e.g., via
+/// [`StandardBuilder::path_display`](crate::StandardBuilder::path_display)
+/// or [`SummaryBuilder::path_display`](crate::SummaryBuilder::path_display).
+/// The default configuration applies no transformation.
+#[derive(Clone, Debug, Default)]
+pub struct PathDisplay {
+    canonicalize: bool,
+    home_tilde: bool,
+    home_dir: Option<PathBuf>,
+}
+
+impl PathDisplay {
+    /// Create a new path display configuration that applies no
+    /// transformation.
+    pub fn new() -> PathDisplay {
+        PathDisplay::default()
+    }
+
+    /// When enabled, paths are canonicalized before being printed.
+    ///
+    /// Canonicalization produces an absolute path with all symbolic links
+    /// resolved. If it fails (e.g., because the file has been removed since
+    /// it was searched), then the path is printed as given.
+    ///
+    /// This is disabled by default.
+    pub fn canonicalize(&mut self, yes: bool) -> &mut PathDisplay {
+        self.canonicalize = yes;
+        self
+    }
+
+    /// When enabled, a path beginning with the user's home directory has
+    /// that prefix abbreviated to `~` when displayed.
+    ///
+    /// This only changes how a path is displayed. In particular, hyperlinks
+    /// continue to carry the full path, since consumers of hyperlinks can't
+    /// be expected to resolve `~` themselves.
+    ///
+    /// This is disabled by default.
+    pub fn home_tilde(&mut self, yes: bool) -> &mut PathDisplay {
+        self.home_tilde = yes;
+        self
+    }
+
+    /// Set the home directory to use for `~` abbreviation.
+    ///
+    /// When `None` (the default), the home directory is read from the `HOME`
+    /// (on Unix) or `USERPROFILE` (on Windows) environment variable. Setting
+    /// it explicitly is chiefly useful for tests.
+    pub fn home_dir(&mut self, path: Option<PathBuf>) -> &mut PathDisplay {
+        self.home_dir = path;
+        self
+    }
+
+    /// Returns the home directory to abbreviate, if one is available.
+    fn home(&self) -> Option<PathBuf> {
+        if let Some(ref home) = self.home_dir {
+            return Some(home.clone());
+        }
+        let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+        std::env::var_os(var).map(PathBuf::from)
+    }
 }
 
 /// A simple encapsulation of a file path used by a printer.
@@ -281,8 +363,11 @@ pub(crate) struct PrinterPath<'a> {
     // stable yet. Those would let us achieve the same end portably. (As long
     // as we keep our UTF-8 requirement on Windows.)
     #[cfg(not(unix))]
-    path: &'a Path,
+    path: Cow<'a, Path>,
     bytes: Cow<'a, [u8]>,
+    /// The path to use for hyperlinks when it diverges from the displayed
+    /// path, e.g., when the display is abbreviated with `~`.
+    hyperlink_path: Option<PathBuf>,
     hyperlink: OnceCell<Option<HyperlinkPath>>,
 }
 
@@ -291,15 +376,62 @@ impl<'a> PrinterPath<'a> {
     pub(crate) fn new(path: &'a Path) -> PrinterPath<'a> {
         PrinterPath {
             #[cfg(not(unix))]
-            path,
+            path: Cow::Borrowed(path),
             // N.B. This is zero-cost on Unix and requires at least a UTF-8
             // check on Windows. This doesn't allocate on Windows unless the
             // path is invalid UTF-8 (which is exceptionally rare).
             bytes: Vec::from_path_lossy(path),
+            hyperlink_path: None,
             hyperlink: OnceCell::new(),
         }
     }
 
+    /// Apply the given display transformations to this path.
+    ///
+    /// This should be applied before any separator replacement via
+    /// `PrinterPath::with_separator`, since the path used for hyperlinks is
+    /// captured here.
+    pub(crate) fn with_display(
+        mut self,
+        display: &PathDisplay,
+    ) -> PrinterPath<'a> {
+        if display.canonicalize {
+            if let Ok(canon) = self.as_path().canonicalize() {
+                self.bytes =
+                    Cow::Owned(Vec::from_path_lossy(&canon).into_owned());
+                #[cfg(not(unix))]
+                {
+                    self.path = Cow::Owned(canon);
+                }
+            }
+        }
+        if display.home_tilde {
+            if let Some(home) = display.home() {
+                let home = Vec::from_path_lossy(&home);
+                let is_sep =
+                    |b: u8| b == b'/' || (cfg!(windows) && b == b'\\');
+                // A trailing separator on the home directory would otherwise
+                // defeat the prefix check below.
+                let home: &[u8] = match home.last() {
+                    Some(&last) if is_sep(last) => &home[..home.len() - 1],
+                    _ => &home,
+                };
+                if !home.is_empty()
+                    && self.bytes.starts_with(home)
+                    && self.bytes.get(home.len()).map_or(true, |&b| is_sep(b))
+                {
+                    // Hyperlinks must keep the real path even though the
+                    // displayed one is abbreviated.
+                    self.hyperlink_path = Some(self.as_path().to_path_buf());
+                    let mut abbrev = b"~".to_vec();
+                    abbrev.extend_from_slice(&self.bytes[home.len()..]);
+                    self.bytes = Cow::Owned(abbrev);
+                }
+            }
+        }
+        self
+    }
+
     /// Set the separator on this path.
     ///
     /// When set, `PrinterPath::as_bytes` will return the path provided but
@@ -339,7 +471,13 @@ impl<'a> PrinterPath<'a> {
     /// silent but is logged.
     pub(crate) fn as_hyperlink(&self) -> Option<&HyperlinkPath> {
         self.hyperlink
-            .get_or_init(|| HyperlinkPath::from_path(self.as_path()))
+            .get_or_init(|| {
+                let path = self
+                    .hyperlink_path
+                    .as_deref()
+                    .unwrap_or_else(|| self.as_path());
+                HyperlinkPath::from_path(path)
+            })
             .as_ref()
     }
 
@@ -352,7 +490,7 @@ impl<'a> PrinterPath<'a> {
         }
         #[cfg(not(unix))]
         fn imp<'p>(p: &'p PrinterPath<'_>) -> &'p Path {
-            p.path
+            &p.path
         }
         imp(self)
     }
@@ -397,6 +535,97 @@ impl serde::Serialize for NiceDuration {
     }
 }
 
+/// A swappable source of time used to measure how long a search took.
+///
+/// A source reports the time elapsed since some fixed origin of its own
+/// choosing, so only differences between readings are meaningful. The
+/// default source reads the system's monotonic clock. Printer builders can
+/// replace it with a deterministic source for testing slow file reporting.
+#[derive(Clone)]
+pub(crate) struct TimeSource(Arc<dyn Fn() -> time::Duration + Send + Sync>);
+
+impl TimeSource {
+    /// Create a time source from the given function.
+    pub(crate) fn new<F>(f: F) -> TimeSource
+    where
+        F: Fn() -> time::Duration + Send + Sync + 'static,
+    {
+        TimeSource(Arc::new(f))
+    }
+
+    /// Returns the time elapsed since this source's origin.
+    pub(crate) fn now(&self) -> time::Duration {
+        (self.0)()
+    }
+}
+
+impl Default for TimeSource {
+    fn default() -> TimeSource {
+        let origin = time::Instant::now();
+        TimeSource::new(move || origin.elapsed())
+    }
+}
+
+impl fmt::Debug for TimeSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TimeSource").finish()
+    }
+}
+
+/// A shared writer for slow file warnings.
+///
+/// Warnings from any number of sinks funnel into a single writer, so it is
+/// wrapped in a mutex. Cloning this type returns a handle to the same
+/// underlying writer.
+#[derive(Clone)]
+pub(crate) struct SlowFileWriter(Arc<Mutex<Box<dyn io::Write + Send>>>);
+
+impl SlowFileWriter {
+    /// Create a shared writer from the given writer.
+    pub(crate) fn new(wtr: Box<dyn io::Write + Send>) -> SlowFileWriter {
+        SlowFileWriter(Arc::new(Mutex::new(wtr)))
+    }
+
+    /// Write the given warning line in its entirety and flush it.
+    pub(crate) fn write_line(&self, line: &[u8]) -> io::Result<()> {
+        let mut wtr = self.0.lock().unwrap();
+        wtr.write_all(line)?;
+        wtr.flush()
+    }
+}
+
+impl fmt::Debug for SlowFileWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SlowFileWriter").finish()
+    }
+}
+
+/// Format a slow file warning line for a search of the given path that took
+/// the given amount of time to search the given number of bytes.
+pub(crate) fn slow_file_warning(
+    path: Option<&Path>,
+    elapsed: time::Duration,
+    bytes: u64,
+) -> String {
+    let secs = elapsed.as_secs_f64();
+    let rate = if secs > 0.0 { (bytes as f64 / secs) as u64 } else { 0 };
+    match path {
+        Some(path) => format!(
+            "# slow: {}: {} elapsed, {} bytes searched, {} bytes/sec\n",
+            path.display(),
+            NiceDuration(elapsed),
+            bytes,
+            rate,
+        ),
+        None => format!(
+            "# slow: {} elapsed, {} bytes searched, {} bytes/sec\n",
+            NiceDuration(elapsed),
+            bytes,
+            rate,
+        ),
+    }
+}
+
 /// A simple formatter for converting `u64` values to ASCII byte strings.
 ///
 /// This avoids going through the formatting machinery which seems to
@@ -1,5 +1,6 @@
-use regex_syntax::hir::{
-    self, ClassBytesRange, ClassUnicodeRange, Hir, HirKind,
+use regex_syntax::{
+    ast::{self, Ast},
+    hir::{self, ClassBytesRange, ClassUnicodeRange, Hir, HirKind},
 };
 
 use crate::error::{Error, ErrorKind};
@@ -52,6 +53,88 @@ pub(crate) fn check(expr: &Hir, byte: u8) -> Result<(), Error> {
     Ok(())
 }
 
+/// Returns the span of the first literal occurrence of `byte` in the given
+/// AST, as byte offsets into the pattern the AST was parsed from.
+///
+/// This looks at literals both in sequence position and inside character
+/// classes, which covers every construct that `check` (and the line
+/// terminator stripper) can report an error for. Escape sequences count: in
+/// `a[\n]z`, the span covers the two bytes of `\n`. Returns `None` when the
+/// byte only occurs in some other form, e.g., as part of a class range or a
+/// Perl class.
+pub(crate) fn find_byte_span(ast: &Ast, byte: u8) -> Option<(usize, usize)> {
+    assert!(byte.is_ascii(), "sought byte must be ASCII");
+    let ch = char::from(byte);
+    let lit_span = |lit: &ast::Literal| {
+        if lit.c == ch {
+            Some((lit.span.start.offset, lit.span.end.offset))
+        } else {
+            None
+        }
+    };
+    match *ast {
+        Ast::Empty(_)
+        | Ast::Flags(_)
+        | Ast::Dot(_)
+        | Ast::Assertion(_)
+        | Ast::ClassUnicode(_)
+        | Ast::ClassPerl(_) => None,
+        Ast::Literal(ref x) => lit_span(x),
+        Ast::ClassBracketed(ref x) => find_byte_span_class_set(&x.kind, byte),
+        Ast::Repetition(ref x) => find_byte_span(&x.ast, byte),
+        Ast::Group(ref x) => find_byte_span(&x.ast, byte),
+        Ast::Alternation(ref alt) => {
+            alt.asts.iter().find_map(|x| find_byte_span(x, byte))
+        }
+        Ast::Concat(ref concat) => {
+            concat.asts.iter().find_map(|x| find_byte_span(x, byte))
+        }
+    }
+}
+
+fn find_byte_span_class_set(
+    set: &ast::ClassSet,
+    byte: u8,
+) -> Option<(usize, usize)> {
+    match *set {
+        ast::ClassSet::Item(ref item) => {
+            find_byte_span_class_set_item(item, byte)
+        }
+        ast::ClassSet::BinaryOp(ref x) => {
+            find_byte_span_class_set(&x.lhs, byte)
+                .or_else(|| find_byte_span_class_set(&x.rhs, byte))
+        }
+    }
+}
+
+fn find_byte_span_class_set_item(
+    item: &ast::ClassSetItem,
+    byte: u8,
+) -> Option<(usize, usize)> {
+    let ch = char::from(byte);
+    match *item {
+        ast::ClassSetItem::Empty(_)
+        | ast::ClassSetItem::Ascii(_)
+        | ast::ClassSetItem::Unicode(_)
+        | ast::ClassSetItem::Perl(_)
+        | ast::ClassSetItem::Range(_) => None,
+        ast::ClassSetItem::Literal(ref x) => {
+            if x.c == ch {
+                Some((x.span.start.offset, x.span.end.offset))
+            } else {
+                None
+            }
+        }
+        ast::ClassSetItem::Bracketed(ref x) => {
+            find_byte_span_class_set(&x.kind, byte)
+        }
+        ast::ClassSetItem::Union(ref union) => union
+            .items
+            .iter()
+            .find_map(|x| find_byte_span_class_set_item(x, byte)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use regex_syntax::Parser;
@@ -80,4 +163,31 @@ mod tests {
         assert!(!check(r"[^\x00]", 0));
         assert!(!check(r"[\x00a]", 0));
     }
+
+    /// Returns the span reported for the given byte in the given pattern.
+    fn span(pattern: &str, byte: u8) -> Option<(usize, usize)> {
+        let ast = regex_syntax::ast::parse::Parser::new()
+            .parse(pattern)
+            .unwrap();
+        super::find_byte_span(&ast, byte)
+    }
+
+    #[test]
+    fn spans() {
+        assert_eq!(span(r"\x00", 0), Some((0, 4)));
+        assert_eq!(span(r"a\x00b", 0), Some((1, 5)));
+        assert_eq!(span(r"ab|\x00", 0), Some((3, 7)));
+        assert_eq!(span(r"(\x00)?", 0), Some((1, 5)));
+
+        // Literals inside classes are found, including escape sequences.
+        assert_eq!(span("a[\\n]z", b'\n'), Some((2, 4)));
+        assert_eq!(span(r"[a[\x00]]", 0), Some((3, 7)));
+
+        // The first occurrence wins.
+        assert_eq!(span(r"\x00a\x00", 0), Some((0, 4)));
+
+        // Occurrences in other forms aren't reported.
+        assert_eq!(span(r"[\x00-\x01]", 0), None);
+        assert_eq!(span(r"abc", 0), None);
+    }
 }
@@ -40,6 +40,9 @@ pub(crate) struct Config {
     pub(crate) word: bool,
     pub(crate) fixed_strings: bool,
     pub(crate) whole_line: bool,
+    pub(crate) snap_to_graphemes: bool,
+    pub(crate) max_multiline_span: Option<usize>,
+    pub(crate) glob_syntax: bool,
 }
 
 impl Default for Config {
@@ -64,6 +67,9 @@ impl Default for Config {
             word: false,
             fixed_strings: false,
             whole_line: false,
+            snap_to_graphemes: false,
+            max_multiline_span: None,
+            glob_syntax: false,
         }
     }
 }
@@ -91,6 +97,34 @@ impl Config {
         analysis.any_literal() && !analysis.any_uppercase()
     }
 
+    /// Returns the given pattern with verbose mode whitespace and comments
+    /// stripped, via a round trip through the AST printer. Ignorable
+    /// whitespace and comments never make it into the AST, so printing it
+    /// yields the pattern without them while preserving its semantics.
+    ///
+    /// Returns `None` when there is nothing to strip (the printed form is
+    /// identical to the input), when the pattern fails to parse or when
+    /// patterns are treated as literal strings.
+    pub(crate) fn normalize_pattern(&self, pattern: &str) -> Option<String> {
+        if self.fixed_strings || self.glob_syntax {
+            return None;
+        }
+        let ast = ast::parse::ParserBuilder::new()
+            .nest_limit(self.nest_limit)
+            .octal(self.octal)
+            .ignore_whitespace(self.ignore_whitespace)
+            .build()
+            .parse(pattern)
+            .ok()?;
+        let mut printed = String::new();
+        ast::print::Printer::new().print(&ast, &mut printed).ok()?;
+        if printed == pattern {
+            None
+        } else {
+            Some(printed)
+        }
+    }
+
     /// Returns whether the given patterns should be treated as "fixed strings"
     /// literals. This is different from just querying the `fixed_strings` knob
     /// in that if the knob is false, this will still return true in some cases
@@ -125,6 +159,19 @@ impl Config {
         // In this case, the only way we can hand construct the Hir is if none
         // of the patterns contain meta characters. If they do, then we need to
         // send them through the standard parsing/translation process.
+        if self.ignore_whitespace {
+            // Verbose mode gives whitespace and `#` a special meaning, and
+            // neither is a meta character, so patterns containing them are
+            // not indistinguishable from literals. (This is irrelevant when
+            // `fixed_strings` is set, since verbose mode is then disabled
+            // entirely.)
+            for p in patterns.iter() {
+                if p.as_ref().chars().any(|ch| ch.is_whitespace() || ch == '#')
+                {
+                    return false;
+                }
+            }
+        }
         for p in patterns.iter() {
             let p = p.as_ref();
             if p.chars().any(regex_syntax::is_meta_character) {
@@ -167,10 +214,30 @@ impl ConfiguredHIR {
         config: Config,
         patterns: &[P],
     ) -> Result<ConfiguredHIR, Error> {
+        // When glob syntax is enabled, translate each pattern to its
+        // equivalent regex up front, so that everything downstream (literal
+        // extraction, case folding, word mode) sees an ordinary regex.
+        // `fixed_strings` takes precedence, since it means the pattern has
+        // no syntax to translate at all.
+        let translated: Option<Vec<String>> =
+            if config.glob_syntax && !config.fixed_strings {
+                let mut regexes = vec![];
+                for p in patterns.iter() {
+                    regexes.push(glob_to_regex(p.as_ref())?);
+                }
+                Some(regexes)
+            } else {
+                None
+            };
+        let patterns: Vec<&str> = match translated {
+            Some(ref regexes) => regexes.iter().map(|p| p.as_str()).collect(),
+            None => patterns.iter().map(|p| p.as_ref()).collect(),
+        };
+        let patterns = &*patterns;
         let hir = if config.is_fixed_strings(patterns) {
             let mut alts = vec![];
             for p in patterns.iter() {
-                alts.push(Hir::literal(p.as_ref().as_bytes()));
+                alts.push(Hir::literal(p.as_bytes()));
             }
             log::debug!(
                 "assembling HIR from {} fixed string literals",
@@ -180,21 +247,81 @@ impl ConfiguredHIR {
             hir
         } else {
             let mut alts = vec![];
-            for p in patterns.iter() {
+            for &p in patterns.iter() {
                 alts.push(if config.fixed_strings {
-                    format!("(?:{})", regex_syntax::escape(p.as_ref()))
+                    format!("(?:{})", regex_syntax::escape(p))
                 } else {
-                    format!("(?:{})", p.as_ref())
+                    format!("(?:{})", p)
                 });
             }
-            let pattern = alts.join("|");
-            let ast = ast::parse::ParserBuilder::new()
-                .nest_limit(config.nest_limit)
-                .octal(config.octal)
-                .ignore_whitespace(config.ignore_whitespace)
-                .build()
-                .parse(&pattern)
-                .map_err(Error::generic)?;
+            let mut pattern = alts.join("|");
+            // When there's exactly one pattern, spans in the assembled
+            // alternation can be mapped back to the caller's original
+            // pattern by accounting for the `(?:` prefix. (With
+            // `fixed_strings`, escaping may have shifted offsets, but that
+            // configuration can't produce any of the errors annotated
+            // below since the pattern is a literal.)
+            let mut original = if patterns.len() == 1 && !config.fixed_strings
+            {
+                Some(patterns[0])
+            } else {
+                None
+            };
+            let annotate = |err: Error,
+                            span: Option<(usize, usize)>,
+                            original: Option<&str>,
+                            pattern: &str| {
+                let Some((start, end)) = span else { return err };
+                match original {
+                    Some(orig) if 3 <= start && end <= 3 + orig.len() => err
+                        .with_span(start - 3, end - 3)
+                        .with_pattern(orig.to_string()),
+                    _ => err
+                        .with_span(start, end)
+                        .with_pattern(pattern.to_string()),
+                }
+            };
+            let parse = |pattern: &str| {
+                ast::parse::ParserBuilder::new()
+                    .nest_limit(config.nest_limit)
+                    .octal(config.octal)
+                    // With fixed_strings, every pattern is escaped into a
+                    // literal, but `regex_syntax::escape` leaves whitespace
+                    // alone since it isn't a meta character. Verbose mode
+                    // would then eat it, so it must not apply here.
+                    .ignore_whitespace(
+                        config.ignore_whitespace && !config.fixed_strings,
+                    )
+                    .build()
+                    .parse(pattern)
+            };
+            let mut ast = parse(&pattern).map_err(|err| {
+                let span = (err.span().start.offset, err.span().end.offset);
+                annotate(Error::generic(err), Some(span), original, &pattern)
+            })?;
+            // When the pattern uses verbose mode, the printed AST differs
+            // from the input: ignorable whitespace and comments aren't part
+            // of the AST. Rebuild from the normalized pattern in that case,
+            // so that the spans and patterns attached to any errors below
+            // refer to the readable single-line form instead of pointing
+            // into a multi-line pattern full of comments.
+            let mut printed = String::new();
+            if ast::print::Printer::new().print(&ast, &mut printed).is_ok()
+                && printed != pattern
+            {
+                pattern = printed;
+                original = None;
+                ast = parse(&pattern).map_err(|err| {
+                    let span =
+                        (err.span().start.offset, err.span().end.offset);
+                    annotate(
+                        Error::generic(err),
+                        Some(span),
+                        original,
+                        &pattern,
+                    )
+                })?;
+            }
             let analysis = AstAnalysis::from_ast(&ast);
             let mut hir = hir::translate::TranslatorBuilder::new()
                 .utf8(false)
@@ -206,9 +333,25 @@ impl ConfiguredHIR {
                 .unicode(config.unicode)
                 .build()
                 .translate(&pattern, &ast)
-                .map_err(Error::generic)?;
+                .map_err(|err| {
+                    let span =
+                        (err.span().start.offset, err.span().end.offset);
+                    annotate(
+                        Error::generic(err),
+                        Some(span),
+                        original,
+                        &pattern,
+                    )
+                })?;
             if let Some(byte) = config.ban {
-                ban::check(&hir, byte)?;
+                ban::check(&hir, byte).map_err(|err| {
+                    annotate(
+                        err,
+                        ban::find_byte_span(&ast, byte),
+                        original,
+                        &pattern,
+                    )
+                })?;
             }
             // We don't need to do this for the fixed-strings case above
             // because is_fixed_strings will return false if any pattern
@@ -221,7 +364,21 @@ impl ConfiguredHIR {
             // actually take a little time. It's not huge, but it's noticeable.
             hir = match config.line_terminator {
                 None => hir,
-                Some(line_term) => strip_from_match(hir, line_term)?,
+                Some(line_term) => {
+                    strip_from_match(hir, line_term).map_err(|err| {
+                        // The stripper reports which literal couldn't be
+                        // removed; find where it occurs in the pattern.
+                        let span = match *err.kind() {
+                            crate::error::ErrorKind::NotAllowed(ref lit) => {
+                                lit.bytes().next().and_then(|byte| {
+                                    ban::find_byte_span(&ast, byte)
+                                })
+                            }
+                            _ => None,
+                        };
+                        annotate(err, span, original, &pattern)
+                    })?
+                }
             };
             hir
         };
@@ -301,6 +458,85 @@ impl ConfiguredHIR {
         }
     }
 
+    /// Returns true if and only if every match of this expression is
+    /// guaranteed to start at the beginning of a line (or of the haystack).
+    ///
+    /// This is only used to test the anchoring analysis. The fast path
+    /// itself relies on `exact_line_literal`, which checks the anchors
+    /// structurally.
+    #[cfg(test)]
+    pub(crate) fn is_line_anchored_start(&self) -> bool {
+        let set = self.hir.properties().look_set_prefix();
+        set.contains(hir::Look::Start)
+            || set.contains(hir::Look::StartLF)
+            || set.contains(hir::Look::StartCRLF)
+    }
+
+    /// Returns true if and only if every match of this expression is
+    /// guaranteed to end at the end of a line (or of the haystack).
+    ///
+    /// Like `is_line_anchored_start`, this only exists for tests.
+    #[cfg(test)]
+    pub(crate) fn is_line_anchored_end(&self) -> bool {
+        let set = self.hir.properties().look_set_suffix();
+        set.contains(hir::Look::End)
+            || set.contains(hir::Look::EndLF)
+            || set.contains(hir::Look::EndCRLF)
+    }
+
+    /// Returns the literal bytes matched when this entire expression is a
+    /// single literal anchored at both ends of a line, e.g., `^foo$` or the
+    /// result of passing `foo` through `into_whole_line`. For such an
+    /// expression, a line matches if and only if it is byte-for-byte equal
+    /// to the literal returned.
+    ///
+    /// The anchors must be exactly the line anchors implied by this
+    /// configuration. Haystack anchors (`\A`/`\z`) and anchors with a
+    /// different line terminator convention (e.g., from an inline `(?R)`
+    /// flag) don't qualify, since byte equality against a line would then
+    /// disagree with the regex engine. Case insensitive expressions are
+    /// naturally excluded as well: case folding turns foldable literals into
+    /// character classes during translation, so anything that survives as a
+    /// plain literal here can be compared byte-for-byte.
+    pub(crate) fn exact_line_literal(&self) -> Option<Vec<u8>> {
+        let hir::HirKind::Concat(ref subs) = *self.hir.kind() else {
+            return None;
+        };
+        let start = self.line_anchor_start();
+        let end = self.line_anchor_end();
+        let mut i = 0;
+        let mut starts = 0;
+        while let Some(sub) = subs.get(i) {
+            match *sub.kind() {
+                hir::HirKind::Look(look) if look == start => {
+                    starts += 1;
+                    i += 1;
+                }
+                _ => break,
+            }
+        }
+        let hir::HirKind::Literal(hir::Literal(ref bytes)) =
+            *subs.get(i)?.kind()
+        else {
+            return None;
+        };
+        i += 1;
+        let mut ends = 0;
+        while let Some(sub) = subs.get(i) {
+            match *sub.kind() {
+                hir::HirKind::Look(look) if look == end => {
+                    ends += 1;
+                    i += 1;
+                }
+                _ => return None,
+            }
+        }
+        if starts == 0 || ends == 0 || i != subs.len() {
+            return None;
+        }
+        Some(bytes.to_vec())
+    }
+
     /// Turns this configured HIR into an equivalent one, but where it must
     /// match at the start and end of a line.
     pub(crate) fn into_whole_line(self) -> ConfiguredHIR {
@@ -351,6 +587,22 @@ impl ConfiguredHIR {
 
 /// Returns true if the given literal string contains any byte from the line
 /// terminator given.
+/// Translate the given glob into an equivalent regex pattern.
+///
+/// The glob's `literal_separator` option is left disabled, since the regex
+/// is matched against line content rather than file paths, and `/` has no
+/// special meaning there. Errors come from globset's glob parser, with the
+/// offending glob attached as the error's pattern.
+fn glob_to_regex(pattern: &str) -> Result<String, Error> {
+    let glob = globset::GlobBuilder::new(pattern)
+        .literal_separator(false)
+        .build()
+        .map_err(|err| {
+            Error::generic(err).with_pattern(pattern.to_string())
+        })?;
+    Ok(glob.regex().to_string())
+}
+
 fn has_line_terminator(lineterm: LineTerminator, literal: &str) -> bool {
     if lineterm.is_crlf() {
         literal.as_bytes().iter().copied().any(|b| b == b'\r' || b == b'\n')
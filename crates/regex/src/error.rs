@@ -6,11 +6,17 @@
 #[derive(Clone, Debug)]
 pub struct Error {
     kind: ErrorKind,
+    /// The span of the offending construct, as byte offsets into `pattern`.
+    span: Option<(usize, usize)>,
+    /// The pattern the span refers to. This is the caller's original pattern
+    /// whenever the span could be mapped back to it, and otherwise the
+    /// assembled alternation that was actually parsed.
+    pattern: Option<String>,
 }
 
 impl Error {
     pub(crate) fn new(kind: ErrorKind) -> Error {
-        Error { kind }
+        Error { kind, span: None, pattern: None }
     }
 
     pub(crate) fn regex(err: regex_automata::meta::BuildError) -> Error {
@@ -18,7 +24,7 @@ impl Error {
             let kind = ErrorKind::Regex(format!(
                 "compiled regex exceeds size limit of {size_limit}",
             ));
-            Error { kind }
+            Error::new(kind)
         } else if let Some(ref err) = err.syntax_error() {
             Error::generic(err)
         } else {
@@ -27,13 +33,66 @@ impl Error {
     }
 
     pub(crate) fn generic<E: std::error::Error>(err: E) -> Error {
-        Error { kind: ErrorKind::Regex(err.to_string()) }
+        Error::new(ErrorKind::Regex(err.to_string()))
+    }
+
+    /// Attach the span of the offending construct to this error.
+    ///
+    /// The offsets are byte offsets into the pattern attached via
+    /// `with_pattern`, when one is attached.
+    pub(crate) fn with_span(mut self, start: usize, end: usize) -> Error {
+        self.span = Some((start, end));
+        self
+    }
+
+    /// Attach the pattern that the span of this error refers to.
+    pub(crate) fn with_pattern(mut self, pattern: String) -> Error {
+        self.pattern = Some(pattern);
+        self
     }
 
     /// Return the kind of this error.
     pub fn kind(&self) -> &ErrorKind {
         &self.kind
     }
+
+    /// Return the span of the construct that caused this error, if available.
+    ///
+    /// The span is a half-open range of byte offsets into the pattern given
+    /// to the matcher builder. When multiple patterns were given, the
+    /// offsets refer to the single alternation assembled from them.
+    pub fn span(&self) -> Option<(usize, usize)> {
+        self.span
+    }
+
+    /// Renders a caret diagnostic pointing at this error's span, but only
+    /// when doing so is likely to be legible. Otherwise, this is a no-op.
+    fn fmt_span(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let (Some((start, end)), Some(ref pattern)) =
+            (self.span, self.pattern.as_ref())
+        else {
+            return Ok(());
+        };
+        // Caret alignment below only works out when every byte occupies
+        // one column. Long or multi-line patterns wouldn't render sensibly
+        // anyway, so restrict the diagnostic to short ASCII single-line
+        // patterns and let the message stand on its own otherwise.
+        if !pattern.is_ascii()
+            || pattern.len() > 80
+            || pattern.contains('\n')
+            || start > end
+            || end > pattern.len()
+        {
+            return Ok(());
+        }
+        let carets = if start == end { 1 } else { end - start };
+        write!(
+            f,
+            "\n{pattern}\n{empty:pad$}{empty:^<carets$}",
+            empty = "",
+            pad = start,
+        )
+    }
 }
 
 /// The kind of an error that can occur.
@@ -71,9 +130,13 @@ impl std::fmt::Display for Error {
         use bstr::ByteSlice;
 
         match self.kind {
+            // The underlying message for a syntax error already contains its
+            // own caret diagnostic (rendered by regex-syntax), so don't pile
+            // a second one on top of it.
             ErrorKind::Regex(ref s) => write!(f, "{}", s),
             ErrorKind::NotAllowed(ref lit) => {
-                write!(f, "the literal {:?} is not allowed in a regex", lit)
+                write!(f, "the literal {:?} is not allowed in a regex", lit)?;
+                self.fmt_span(f)
             }
             ErrorKind::InvalidLineTerminator(byte) => {
                 write!(
@@ -87,7 +150,8 @@ impl std::fmt::Display for Error {
                     f,
                     "pattern contains {byte:?} but it is impossible to match",
                     byte = [byte].as_bstr(),
-                )
+                )?;
+                self.fmt_span(f)
             }
         }
     }
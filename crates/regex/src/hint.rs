@@ -0,0 +1,141 @@
+/// Returns a fully escaped rendering of the given pattern when it looks like
+/// the pattern was intended to match a literal string.
+///
+/// This detects common escaping mistakes, such as searching for
+/// `foo.bar(baz)` and expecting it to match literally even though `.`, `(`
+/// and `)` are meta characters. The heuristics used are conservative: the
+/// moment the pattern contains any construct suggesting the regex syntax was
+/// intentional (a quantifier, an alternation, a character class, an anchor or
+/// an escape sequence class like `\d`), no suggestion is made. Otherwise, a
+/// suggestion is made when the pattern contains an unescaped `.` surrounded
+/// by identifier characters, or a bare group with no alternation and no
+/// quantifier.
+///
+/// If the pattern is invalid or already matches its own literal
+/// interpretation, then `None` is returned.
+pub(crate) fn literalization_hint(pattern: &str) -> Option<String> {
+    // If the pattern isn't valid regex syntax, then there is no regex
+    // interpretation to compare against. (Building a matcher from it would
+    // have failed anyway.)
+    regex_syntax::Parser::new().parse(pattern).ok()?;
+    let escaped = regex_syntax::escape(pattern);
+    // If escaping changes nothing, then the pattern is already its own
+    // literal interpretation.
+    if escaped == pattern {
+        return None;
+    }
+
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let is_quantifier = |c: char| matches!(c, '*' | '+' | '?');
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut suspicious = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let prev = if i == 0 { None } else { Some(chars[i - 1]) };
+        let next = chars.get(i + 1).copied();
+        match chars[i] {
+            '\\' => {
+                // Escape sequence classes and assertions like `\d` or `\b`
+                // signal regex intent, while escaped punctuation like `\.`
+                // signals the user knows how to escape.
+                if next.map_or(false, |c| c.is_ascii_alphanumeric()) {
+                    return None;
+                }
+                i += 2;
+                continue;
+            }
+            // Any overt regex construct means the syntax was probably
+            // intentional.
+            '[' | '|' | '^' | '$' => return None,
+            c if is_quantifier(c) => return None,
+            '{' => {
+                // A counted repetition like `a{2}`. A `{` not followed by a
+                // digit is just a literal brace.
+                if next.map_or(false, |c| c.is_ascii_digit()) {
+                    return None;
+                }
+            }
+            '.' => {
+                // `.` with a quantifier, like in `foo.*bar`, is regex
+                // intent, but `.` sandwiched between identifier characters
+                // probably wasn't meant to match *any* character.
+                if next.map_or(false, |c| is_quantifier(c) || c == '{') {
+                    return None;
+                }
+                if prev.map_or(false, is_word) && next.map_or(false, is_word)
+                {
+                    suspicious = true;
+                }
+            }
+            '(' => {
+                // `(?...)` is explicit group syntax.
+                if next == Some('?') {
+                    return None;
+                }
+            }
+            ')' => {
+                // A quantified group is regex intent. Otherwise, a bare
+                // group serves no purpose in a literal search, so the parens
+                // were probably meant literally. (An alternation inside the
+                // group would have already returned above.)
+                if next.map_or(false, |c| is_quantifier(c) || c == '{') {
+                    return None;
+                }
+                suspicious = true;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if suspicious {
+        Some(escaped)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::literalization_hint;
+
+    #[test]
+    fn suggests() {
+        let hint = |pattern| literalization_hint(pattern);
+
+        assert_eq!(
+            Some(r"foo\.bar\(baz\)".to_string()),
+            hint("foo.bar(baz)")
+        );
+        assert_eq!(Some(r"version\.parse".to_string()), hint("version.parse"));
+        assert_eq!(Some(r"main\(\)".to_string()), hint("main()"));
+        assert_eq!(Some(r"a\(b\)c".to_string()), hint("a(b)c"));
+    }
+
+    #[test]
+    fn does_not_suggest() {
+        let hint = |pattern| literalization_hint(pattern);
+
+        // Already literal.
+        assert_eq!(None, hint("foobar"));
+        assert_eq!(None, hint("foo bar"));
+        // Quantifiers signal intent.
+        assert_eq!(None, hint("foo.*bar"));
+        assert_eq!(None, hint("foo.+bar"));
+        assert_eq!(None, hint("foo.?"));
+        assert_eq!(None, hint("colou?r"));
+        assert_eq!(None, hint("foo.{2}bar"));
+        assert_eq!(None, hint("(foo)+"));
+        // Alternations, classes, anchors and escape classes signal intent.
+        assert_eq!(None, hint("(foo|bar)"));
+        assert_eq!(None, hint("[fb]oo"));
+        assert_eq!(None, hint("^foo.bar"));
+        assert_eq!(None, hint(r"foo\d"));
+        assert_eq!(None, hint("(?i)foo.bar"));
+        // Escaped meta characters mean the user knows how to escape.
+        assert_eq!(None, hint(r"foo\.bar"));
+        // A `.` not surrounded by identifier characters is too ambiguous.
+        assert_eq!(None, hint("ends."));
+        // Invalid patterns have no regex interpretation to compare with.
+        assert_eq!(None, hint("foo(bar"));
+    }
+}
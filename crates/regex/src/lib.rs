@@ -6,13 +6,16 @@ An implementation of `grep-matcher`'s `Matcher` trait for Rust's regex engine.
 pub use crate::{
     error::{Error, ErrorKind},
     matcher::{RegexCaptures, RegexMatcher, RegexMatcherBuilder},
+    report::{OptimizationReport, OptimizationSkip},
 };
 
 mod ast;
 mod ban;
 mod config;
 mod error;
+mod hint;
 mod literal;
 mod matcher;
 mod non_matching;
+mod report;
 mod strip;
@@ -7,7 +7,7 @@ use {
     },
 };
 
-use crate::{config::ConfiguredHIR, error::Error};
+use crate::{config::ConfiguredHIR, error::Error, report::OptimizationSkip};
 
 /// A type that encapsulates "inner" literal extractiong from a regex.
 ///
@@ -40,6 +40,9 @@ use crate::{config::ConfiguredHIR, error::Error};
 #[derive(Clone, Debug)]
 pub(crate) struct InnerLiterals {
     seq: Seq,
+    /// When extraction was declined up front, the reason why. This is
+    /// surfaced through the matcher's optimization report.
+    skip: Option<OptimizationSkip>,
 }
 
 impl InnerLiterals {
@@ -60,7 +63,9 @@ impl InnerLiterals {
                 "skipping inner literal extraction, \
                  no line terminator is set"
             );
-            return InnerLiterals::none();
+            return InnerLiterals::declined(
+                OptimizationSkip::NoLineTerminator,
+            );
         }
         // If we believe the regex is already accelerated, then just let
         // the regex engine do its thing. We'll skip the inner literal
@@ -76,7 +81,9 @@ impl InnerLiterals {
                     "skipping inner literal extraction, \
                      existing regex is believed to already be accelerated",
                 );
-                return InnerLiterals::none();
+                return InnerLiterals::declined(
+                    OptimizationSkip::DeferredToRegexEngine,
+                );
             }
         }
         // In this case, we pretty much know that the regex engine will handle
@@ -86,16 +93,36 @@ impl InnerLiterals {
                 "skipping inner literal extraction, \
                  found alternation of literals, deferring to regex engine",
             );
-            return InnerLiterals::none();
+            return InnerLiterals::declined(
+                OptimizationSkip::DeferredToRegexEngine,
+            );
         }
         let seq = Extractor::new().extract_untagged(chir.hir());
-        InnerLiterals { seq }
+        InnerLiterals { seq, skip: None }
+    }
+
+    /// Returns an infinite set of inner literals, such that it can never
+    /// produce a matcher, recording the reason extraction was declined for
+    /// the optimization report.
+    fn declined(skip: OptimizationSkip) -> InnerLiterals {
+        InnerLiterals { seq: Seq::infinite(), skip: Some(skip) }
     }
 
-    /// Returns a infinite set of inner literals, such that it can never
-    /// produce a matcher.
-    pub(crate) fn none() -> InnerLiterals {
-        InnerLiterals { seq: Seq::infinite() }
+    /// Returns the literals that `one_regex` would build its regex from, or
+    /// the reason there are none. This is what the matcher's optimization
+    /// report exposes.
+    pub(crate) fn report_literals(
+        &self,
+    ) -> Result<Vec<Vec<u8>>, OptimizationSkip> {
+        if let Some(ref skip) = self.skip {
+            return Err(skip.clone());
+        }
+        match self.seq.literals() {
+            Some(lits) if !lits.is_empty() => {
+                Ok(lits.iter().map(|lit| lit.as_bytes().to_vec()).collect())
+            }
+            _ => Err(OptimizationSkip::NoUsefulLiterals),
+        }
     }
 
     /// If it is deemed advantageous to do so (via various suspicious
@@ -232,23 +259,111 @@ impl Extractor {
 
     /// Extract a sequence from the given alternation.
     ///
-    /// This short circuits once the union turns into an infinite sequence.
+    /// This short circuits once any single child sequence is infinite, since
+    /// a union with an infinite sequence is itself always infinite and no
+    /// amount of factoring below can pull a guaranteed literal out of it.
     fn extract_alternation<'a, I: Iterator<Item = &'a Hir>>(
         &self,
         it: I,
     ) -> TSeq {
-        let mut seq = TSeq::empty();
+        let mut arms = vec![];
         for hir in it {
-            // Once our 'seq' is infinite, every subsequent union
-            // operation on it will itself always result in an
-            // infinite sequence. Thus, it can never change and we can
-            // short-circuit.
+            let arm = self.extract(hir);
+            if !arm.is_finite() {
+                return TSeq::infinite();
+            }
+            arms.push(arm);
+        }
+        // Factoring below needs the complete set of literals matched by the
+        // alternation, so snapshot it before 'union' gets a chance to trim
+        // the arms. This is only possible when every arm is an exact prefix
+        // sequence, i.e., its literals are precisely the strings it matches.
+        let all_lits: Option<Vec<Vec<u8>>> = if arms
+            .iter()
+            .all(|arm| arm.prefix && arm.is_exact() && !arm.is_empty())
+        {
+            Some(
+                arms.iter()
+                    .flat_map(|arm| arm.literals().unwrap())
+                    .map(|lit| lit.as_bytes().to_vec())
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        let mut seq = TSeq::empty();
+        for arm in arms.iter_mut() {
+            seq = self.union(seq, arm);
+            // Once our 'seq' is infinite, every subsequent union operation
+            // on it will itself always result in an infinite sequence. Thus,
+            // it can never change and we can short-circuit.
             if !seq.is_finite() {
                 break;
             }
-            seq = self.union(seq, &mut self.extract(hir));
         }
-        seq
+        if seq.is_finite() {
+            return seq;
+        }
+        // The union blew past our limits. This commonly happens for large
+        // generated alternations, e.g., `(000_foo|001_foo|...|499_foo)`,
+        // where a literal common to every arm still makes for a great
+        // candidate even though the arms themselves are too numerous to
+        // represent. So before giving up, try to factor one out.
+        match all_lits {
+            None => seq,
+            Some(lits) => self.factor_alternation(&lits),
+        }
+    }
+
+    /// Attempt to factor a literal common to every arm out of an alternation
+    /// whose union of literals blew past our limits.
+    ///
+    /// `lits` must be the complete set of literals matched by the
+    /// alternation. A common suffix is preferred because it can remain exact:
+    /// it is a guaranteed tail of whatever the alternation matches, so a
+    /// literal following the alternation in a concatenation still gets
+    /// appended to it. (The sequence is tagged as "not a prefix" so that it
+    /// is never itself appended to literals preceding the alternation.) A
+    /// common prefix is yielded as an ordinary inexact prefix literal. When
+    /// neither exists, the resulting sequence is infinite, just as if no
+    /// factoring had been attempted.
+    fn factor_alternation(&self, lits: &[Vec<u8>]) -> TSeq {
+        let (mut prefix, mut suffix) = match lits.first() {
+            None => return TSeq::infinite(),
+            Some(lit) => (lit.as_slice(), lit.as_slice()),
+        };
+        for lit in lits[1..].iter() {
+            let plen = prefix
+                .iter()
+                .zip(lit.iter())
+                .take_while(|(x, y)| x == y)
+                .count();
+            prefix = &prefix[..plen];
+            let slen = suffix
+                .iter()
+                .rev()
+                .zip(lit.iter().rev())
+                .take_while(|(x, y)| x == y)
+                .count();
+            suffix = &suffix[suffix.len() - slen..];
+        }
+        if !suffix.is_empty() && suffix.len() >= prefix.len() {
+            // Keep the tail when enforcing the literal length limit so that
+            // the factored literal remains a suffix.
+            let start = suffix.len().saturating_sub(self.limit_literal_len);
+            let mut seq = TSeq::singleton(self::Literal::exact(
+                suffix[start..].to_vec(),
+            ));
+            seq.make_not_prefix();
+            seq
+        } else if !prefix.is_empty() {
+            let mut seq =
+                TSeq::singleton(self::Literal::inexact(prefix.to_vec()));
+            self.enforce_literal_len(&mut seq);
+            seq
+        } else {
+            TSeq::infinite()
+        }
     }
 
     /// Extract a sequence of literals from the given repetition. We do our
@@ -383,6 +498,17 @@ impl Extractor {
     /// infinite sequence with `seq1`.
     fn cross(&self, mut seq1: TSeq, mut seq2: TSeq) -> TSeq {
         if !seq2.prefix {
+            // A sequence that is exact but not a prefix arises from
+            // factoring a common suffix out of an alternation. Its literals
+            // are guaranteed tails of the concatenation matched so far, so
+            // when nothing useful has been accumulated yet, adopt it as-is.
+            // Keeping it exact gives literals that follow in the
+            // concatenation a chance to be appended to it.
+            if seq2.is_exact()
+                && seq1.min_literal_len().map_or(true, |len| len == 0)
+            {
+                return seq2;
+            }
             return seq1.choose(seq2);
         }
         if seq1
@@ -716,6 +842,37 @@ mod tests {
         );
     }
 
+    // These test that a literal common to every arm of an alternation too
+    // big to represent literal-by-literal gets factored out, instead of the
+    // alternation poisoning extraction entirely. This tends to come up with
+    // generated patterns.
+    #[test]
+    fn factoring() {
+        let arms = |f: &dyn Fn(usize) -> String| {
+            (0..500).map(|i| f(i)).collect::<Vec<String>>().join("|")
+        };
+
+        // A common suffix combines with the literal following the
+        // alternation.
+        let pat = format!("({})bar", arms(&|i| format!("{:03}_foo", i)));
+        assert_eq!(e(pat), seq([E("_foobar")]));
+        // ... and stands on its own when nothing follows.
+        let pat = format!("({})", arms(&|i| format!("{:03}_foo", i)));
+        assert_eq!(e(pat), seq([E("_foo")]));
+        // A common prefix is factored too, but nothing can be appended to
+        // it since each arm continues differently.
+        let pat = format!("({})bar", arms(&|i| format!("foo_{:03}", i)));
+        assert_eq!(e(pat), seq([I("foo_")]));
+        // Nested alternations are factored by recursion before the outer
+        // alternation is considered.
+        let inner = arms(&|i| format!("{:03}_foo", i));
+        let pat = format!("(a({})|b({}))", inner, inner);
+        assert_eq!(e(pat), seq([I("_foo")]));
+        // No common prefix or suffix means extraction still gives up.
+        let pat = format!("({})", arms(&|i| format!("{:03}", i)));
+        assert_eq!(e(pat), Seq::infinite());
+    }
+
     #[test]
     fn literal() {
         assert_eq!(exact(["a"]), e("a"));
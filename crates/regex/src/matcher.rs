@@ -1,3 +1,5 @@
+use std::sync::{Arc, OnceLock};
+
 use {
     grep_matcher::{
         ByteSet, Captures, LineMatchKind, LineTerminator, Match, Matcher,
@@ -9,7 +11,12 @@ use {
     },
 };
 
-use crate::{config::Config, error::Error, literal::InnerLiterals};
+use crate::{
+    config::Config,
+    error::Error,
+    literal::InnerLiterals,
+    report::{self, OptimizationReport, OptimizationSkip},
+};
 
 /// A builder for constructing a `Matcher` using regular expressions.
 ///
@@ -55,12 +62,29 @@ impl RegexMatcherBuilder {
         patterns: &[P],
     ) -> Result<RegexMatcher, Error> {
         let mut chir = self.config.build_many(patterns)?;
+        let alternation_literal =
+            chir.hir().properties().is_alternation_literal();
         // 'whole_line' is a strict subset of 'word', so when it is enabled,
         // we don't need to both with any specific to word matching.
+        let mut verify_word = false;
+        let mut word_wrapper = Err(OptimizationSkip::NotRequested);
         if chir.config().whole_line {
             chir = chir.into_whole_line();
         } else if chir.config().word {
-            chir = chir.into_word();
+            // When the HIR is a plain alternation of literals (e.g., from -F
+            // with many patterns), wrapping it in word assertions would force
+            // the entire alternation through the regex engine's look-around
+            // handling and foil literal optimizations. Instead, we leave the
+            // alternation untouched and check the bytes adjacent to each
+            // candidate match ourselves.
+            if alternation_literal {
+                verify_word = true;
+                word_wrapper =
+                    Err(OptimizationSkip::LiteralAlternationPreferred);
+            } else {
+                chir = chir.into_word();
+                word_wrapper = Ok(());
+            }
         }
         let regex = chir.to_regex()?;
         log::trace!("final regex: {:?}", chir.hir().to_string());
@@ -75,13 +99,57 @@ impl RegexMatcherBuilder {
         // then run the original regex on only that line. (In this case, the
         // regex engine is likely to handle this case for us since it's so
         // simple, but the idea applies.)
-        let fast_line_regex = InnerLiterals::new(&chir, &regex).one_regex()?;
+        let inner_literals = InnerLiterals::new(&chir, &regex);
+        let fast_line_regex = inner_literals.one_regex()?;
+
+        // When the entire pattern is a literal anchored at both ends of a
+        // line, a line matches if and only if it equals the literal. Remember
+        // the literal so that candidate line searching can use byte equality
+        // instead of the regex engine.
+        let exact_line_literal = chir.exact_line_literal();
+
+        // Record which of the strategies above were engaged and why the
+        // others weren't, so that the decisions made here can be inspected
+        // after the fact.
+        let report = OptimizationReport {
+            exact_line_literal: match exact_line_literal {
+                Some(ref lit) => Ok(lit.clone()),
+                None if !chir.config().whole_line => {
+                    Err(OptimizationSkip::NotRequested)
+                }
+                None => Err(report::not_literal(patterns)),
+            },
+            inner_literals: inner_literals.report_literals(),
+            literal_alternation: if alternation_literal {
+                Ok(())
+            } else {
+                Err(report::not_literal(patterns))
+            },
+            word_wrapper,
+            crlf_wrapper: if chir.config().crlf {
+                Ok(())
+            } else {
+                Err(OptimizationSkip::NotRequested)
+            },
+        };
 
         // We override the line terminator in case the configured HIR doesn't
         // support it.
         let mut config = self.config.clone();
         config.line_terminator = chir.line_terminator();
-        Ok(RegexMatcher { config, regex, fast_line_regex, non_matching_bytes })
+        let patterns =
+            patterns.iter().map(|p| p.as_ref().to_string()).collect();
+        Ok(RegexMatcher {
+            config,
+            regex,
+            fast_line_regex,
+            non_matching_bytes,
+            exact_line_literal,
+            patterns,
+            pattern_matchers: Arc::new(OnceLock::new()),
+            verify_word,
+            report,
+        })
     }
 
     /// Build a new matcher from a plain alternation of literals.
@@ -348,17 +416,91 @@ impl RegexMatcherBuilder {
     /// Whether the patterns should be treated as literal strings or not. When
     /// this is active, all characters, including ones that would normally be
     /// special regex meta characters, are matched literally.
+    ///
+    /// This composes with [`word`](RegexMatcherBuilder::word). When both are
+    /// enabled, word boundaries are checked against the bytes adjacent to
+    /// each candidate occurrence instead of being encoded into the pattern
+    /// itself, so even very large sets of literals stay cheap to compile.
     pub fn fixed_strings(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
         self.config.fixed_strings = yes;
         self
     }
 
+    /// Whether the patterns should be treated as shell globs or not. When
+    /// this is active, each pattern is translated to an equivalent regex
+    /// via globset's glob parser before any other processing, so `*`
+    /// matches any number of characters, `?` matches exactly one, `[abc]`
+    /// is a character class and `\` escapes a metacharacter.
+    ///
+    /// As is conventional for globs, the translated regex is anchored: the
+    /// glob must match the entire subject, not merely a substring of it.
+    /// Since the subject here is line content and not a file path, `*` and
+    /// `?` match `/` like any other character.
+    ///
+    /// All other builder options (case folding, word mode and so on)
+    /// compose with the translated pattern as they would with a hand
+    /// written regex. [`fixed_strings`](RegexMatcherBuilder::fixed_strings)
+    /// takes precedence over this option, since a literal string has no
+    /// glob syntax to translate.
+    ///
+    /// This is disabled by default.
+    pub fn glob_syntax(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        self.config.glob_syntax = yes;
+        self
+    }
+
     /// Whether each pattern should match the entire line or not. This is
     /// equivalent to surrounding the pattern with `(?m:^)` and `(?m:$)`.
     pub fn whole_line(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
         self.config.whole_line = yes;
         self
     }
+
+    /// Whether reported match spans should be widened to the nearest
+    /// grapheme cluster boundaries or not.
+    ///
+    /// With Unicode case insensitive matching, a match can legitimately
+    /// begin or end in the middle of a grapheme cluster. For example, a
+    /// case insensitive search for `e` matches the `e` in a decomposed `é`
+    /// (`e` followed by U+0301), and downstream consumers that slice the
+    /// haystack at the reported offsets (say, to insert color codes) end up
+    /// splitting the cluster. When this is enabled, match spans are adjusted
+    /// outward to the nearest cluster boundaries before being reported.
+    ///
+    /// Spans are only ever widened and never shrunk, so a reported span
+    /// always contains the real match. The adjustment inspects only the
+    /// lines overlapping the match, so its cost is bounded by line length.
+    ///
+    /// This is disabled by default.
+    pub fn snap_to_graphemes(
+        &mut self,
+        yes: bool,
+    ) -> &mut RegexMatcherBuilder {
+        self.config.snap_to_graphemes = yes;
+        self
+    }
+
+    /// Set a maximum length, in bytes, for matches that span multiple lines.
+    ///
+    /// Multi-line search cannot be done incrementally, so an unbounded
+    /// pattern like `(?s)BEGIN.*END` on a haystack where `END` never appears
+    /// forces the searcher to buffer everything from `BEGIN` onward. When a
+    /// limit is set, the searcher refuses matches longer than the limit by
+    /// reporting an error for the haystack being searched, instead of
+    /// silently truncating or buffering without bound.
+    ///
+    /// This only takes effect when searching across multiple lines. Note
+    /// that the limit is enforced by whatever is driving the search (e.g.,
+    /// `grep-searcher`), not by the matcher itself.
+    ///
+    /// This is unlimited by default.
+    pub fn max_multiline_span(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut RegexMatcherBuilder {
+        self.config.max_multiline_span = limit;
+        self
+    }
 }
 
 /// An implementation of the `Matcher` trait using Rust's standard regex
@@ -377,6 +519,29 @@ pub struct RegexMatcher {
     fast_line_regex: Option<Regex>,
     /// A set of bytes that will never appear in a match.
     non_matching_bytes: ByteSet,
+    /// When the entire pattern is a single literal anchored at both ends of
+    /// a line (e.g., `^foo$` or `foo` with `whole_line` enabled), the
+    /// literal itself. A line matches if and only if it equals the literal,
+    /// so candidate line searching can use byte equality instead of the
+    /// regex engine.
+    exact_line_literal: Option<Vec<u8>>,
+    /// The original patterns this matcher was built from. These are kept
+    /// around so that matches can be attributed to individual patterns.
+    patterns: Vec<String>,
+    /// Per-pattern matchers, built lazily on first use since attributing
+    /// matches to patterns is rarely needed. `None` inside the lock means
+    /// that building them failed (which should be impossible given that the
+    /// combined matcher was built from the same patterns, but we don't rely
+    /// on it).
+    pattern_matchers: Arc<OnceLock<Option<Vec<RegexMatcher>>>>,
+    /// When enabled, `regex` matches any occurrence of the patterns and word
+    /// boundaries are verified after the fact by inspecting the bytes
+    /// adjacent to each candidate match. This is used in lieu of `into_word`
+    /// when the patterns form a plain alternation of literals.
+    verify_word: bool,
+    /// A report of the optimization strategies engaged (or not) when this
+    /// matcher was built.
+    report: OptimizationReport,
 }
 
 impl RegexMatcher {
@@ -401,6 +566,228 @@ impl RegexMatcher {
     pub fn new_line_matcher(pattern: &str) -> Result<RegexMatcher, Error> {
         RegexMatcherBuilder::new().line_terminator(Some(b'\n')).build(pattern)
     }
+
+    /// Returns a fully escaped rendering of the given pattern when it looks
+    /// like the pattern was intended to match a literal string.
+    ///
+    /// For example, a user searching for `foo.bar(baz)` probably wants the
+    /// meta characters `.`, `(` and `)` to match literally. For that pattern,
+    /// this returns `foo\.bar\(baz\)`, which tools can surface in a "did you
+    /// mean" suggestion when a search produces no matches.
+    ///
+    /// The heuristics used are conservative. A suggestion is made only when
+    /// the pattern contains an unescaped `.` surrounded by identifier
+    /// characters, or a bare group with no alternation and no quantifier,
+    /// and nothing else in the pattern (e.g., `foo.*bar`) suggests the regex
+    /// syntax was intentional. If the pattern is invalid or already matches
+    /// its own literal interpretation, then `None` is returned.
+    pub fn literalization_hint(pattern: &str) -> Option<String> {
+        crate::hint::literalization_hint(pattern)
+    }
+
+    /// Returns a report of the optimization strategies engaged (or not)
+    /// when this matcher was built.
+    ///
+    /// This is useful for diagnosing why a search is slower than expected:
+    /// for example, a single pattern with a meta character in it prevents a
+    /// whole set from being treated as plain literals, and the report says
+    /// which pattern and where. The report describes decisions already made
+    /// during construction; inspecting it has no effect on matching.
+    pub fn optimization_report(&self) -> &OptimizationReport {
+        &self.report
+    }
+
+    /// Returns this matcher's pattern with verbose mode whitespace and
+    /// comments removed, if there were any to remove.
+    ///
+    /// Patterns written in verbose mode (the `x` flag, whether inline via
+    /// `(?x)` or set with [`RegexMatcherBuilder::ignore_whitespace`]) may
+    /// span many lines and contain comments, which makes them unwieldy in
+    /// error messages and debug output. This re-prints the parsed pattern
+    /// via the AST printer, which drops ignorable whitespace and comments
+    /// while preserving the pattern's semantics. When this matcher was built
+    /// from multiple patterns, each is normalized individually and the
+    /// results are joined with `|`.
+    ///
+    /// `None` is returned when no pattern uses verbose mode, or when the
+    /// patterns are treated as literal strings.
+    pub fn normalized_pattern(&self) -> Option<String> {
+        let mut any = false;
+        let mut norms = Vec::with_capacity(self.patterns.len());
+        for p in self.patterns.iter() {
+            match self.config.normalize_pattern(p) {
+                None => norms.push(p.clone()),
+                Some(norm) => {
+                    any = true;
+                    norms.push(norm);
+                }
+            }
+        }
+        if any {
+            Some(norms.join("|"))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the maximum multi-line match span, in bytes, if one was set
+    /// via [`RegexMatcherBuilder::max_multiline_span`].
+    pub fn multiline_span_limit(&self) -> Option<usize> {
+        self.config.max_multiline_span
+    }
+
+    /// Returns the literal bytes matched when this matcher's entire pattern
+    /// is a single literal anchored at both ends of a line, e.g., `^foo$` or
+    /// `foo` with [`RegexMatcherBuilder::whole_line`] enabled.
+    ///
+    /// For such a matcher, a line matches if and only if it is byte-for-byte
+    /// equal to the literal returned, which permits searching lines with an
+    /// equality check instead of the regex engine. `None` is returned for
+    /// anything else, including case insensitive patterns, since case
+    /// folding makes a byte comparison incorrect.
+    pub fn exact_line_literal(&self) -> Option<&[u8]> {
+        self.exact_line_literal.as_deref()
+    }
+
+    /// Finds the start offset of the first line in the haystack that is
+    /// byte-for-byte equal to the given literal. This is only correct for
+    /// matchers whose whole pattern is a literal anchored at both ends of a
+    /// line.
+    fn find_exact_line(&self, lit: &[u8], haystack: &[u8]) -> Option<usize> {
+        use bstr::ByteSlice;
+
+        let crlf = self.config.crlf;
+        for i in haystack.find_iter(lit) {
+            let at_start = i == 0
+                || haystack[i - 1] == b'\n'
+                || (crlf && haystack[i - 1] == b'\r');
+            if !at_start {
+                continue;
+            }
+            let j = i + lit.len();
+            let at_end = j == haystack.len()
+                || haystack[j] == b'\n'
+                || (crlf && haystack[j] == b'\r');
+            if at_end {
+                return Some(i);
+            }
+        }
+        None
+    }
+
+    /// Returns the matchers used for attributing matches to individual
+    /// patterns, building them on first use.
+    fn pattern_matchers(&self) -> Option<&[RegexMatcher]> {
+        self.pattern_matchers
+            .get_or_init(|| {
+                let mut builder = RegexMatcherBuilder::new();
+                builder.config = self.config.clone();
+                let mut matchers = Vec::with_capacity(self.patterns.len());
+                for pattern in self.patterns.iter() {
+                    matchers.push(builder.build(pattern).ok()?);
+                }
+                Some(matchers)
+            })
+            .as_deref()
+    }
+
+    /// Widens the given match to grapheme cluster boundaries when the
+    /// `snap_to_graphemes` option is enabled.
+    #[inline]
+    fn snap(&self, haystack: &[u8], m: Match) -> Match {
+        if !self.config.snap_to_graphemes {
+            return m;
+        }
+        snap_to_graphemes(haystack, m)
+    }
+
+    /// Finds the next match at or after `at`, skipping candidates that do
+    /// not fall on word boundaries when `verify_word` is enabled.
+    #[inline]
+    fn find_verified(&self, haystack: &[u8], mut at: usize) -> Option<Match> {
+        while at <= haystack.len() {
+            let input = Input::new(haystack).span(at..haystack.len());
+            let m = self.regex.find(input)?;
+            let m = Match::new(m.start(), m.end());
+            if !self.verify_word
+                || is_word_match(haystack, m, self.config.unicode)
+            {
+                return Some(m);
+            }
+            // This candidate abuts a word character, so it isn't a real
+            // match. A real match may still start inside of it (e.g., when
+            // one literal is a suffix of another), so only nudge the search
+            // forward by one.
+            at = m.start() + 1;
+        }
+        None
+    }
+}
+
+/// Returns true if and only if the bytes adjacent to the given match satisfy
+/// the same "half" word boundary assertions that `ConfiguredHIR::into_word`
+/// would have encoded into the regex. That is, the match must not be
+/// immediately preceded or followed by a word character. Unlike `\b`, the
+/// match itself need not start or end with a word character.
+///
+/// When `unicode` is disabled, only ASCII word characters are considered.
+/// When it's enabled, adjacent bytes that aren't part of a valid UTF-8
+/// encoding fail the boundary check, just like the regex engine's Unicode
+/// look-around assertions do.
+fn is_word_match(haystack: &[u8], m: Match, unicode: bool) -> bool {
+    if unicode {
+        let (ch, size) = bstr::decode_last_utf8(&haystack[..m.start()]);
+        match ch {
+            None if size > 0 => return false,
+            Some(ch) if regex_syntax::is_word_character(ch) => return false,
+            _ => {}
+        }
+        let (ch, size) = bstr::decode_utf8(&haystack[m.end()..]);
+        match ch {
+            None if size > 0 => false,
+            Some(ch) if regex_syntax::is_word_character(ch) => false,
+            _ => true,
+        }
+    } else {
+        if m.start() > 0 && regex_syntax::is_word_byte(haystack[m.start() - 1])
+        {
+            return false;
+        }
+        m.end() >= haystack.len()
+            || !regex_syntax::is_word_byte(haystack[m.end()])
+    }
+}
+
+/// Widens the given match to the nearest grapheme cluster boundaries.
+///
+/// The start of the match is moved backward to the start of the cluster
+/// containing it and the end is moved forward to the end of the cluster
+/// containing it, so the result always contains the original match. Only the
+/// lines overlapping the match are inspected, which bounds the cost of the
+/// adjustment by line length rather than haystack length.
+fn snap_to_graphemes(haystack: &[u8], m: Match) -> Match {
+    use bstr::ByteSlice;
+
+    let line_start =
+        haystack[..m.start()].rfind_byte(b'\n').map_or(0, |i| i + 1);
+    let line_end = haystack[m.end()..]
+        .find_byte(b'\n')
+        .map_or(haystack.len(), |i| m.end() + i);
+    let mut start = m.start();
+    let mut end = m.end();
+    for (s, e, _) in haystack[line_start..line_end].grapheme_indices() {
+        let (s, e) = (line_start + s, line_start + e);
+        if s < m.start() && m.start() < e {
+            start = s;
+        }
+        if s < m.end() && m.end() < e {
+            end = e;
+        }
+        if s >= m.end() {
+            break;
+        }
+    }
+    Match::new(start, end)
 }
 
 // This implementation just dispatches on the internal matcher impl except
@@ -416,8 +803,7 @@ impl Matcher for RegexMatcher {
         haystack: &[u8],
         at: usize,
     ) -> Result<Option<Match>, NoError> {
-        let input = Input::new(haystack).span(at..haystack.len());
-        Ok(self.regex.find(input).map(|m| Match::new(m.start(), m.end())))
+        Ok(self.find_verified(haystack, at).map(|m| self.snap(haystack, m)))
     }
 
     #[inline]
@@ -444,8 +830,21 @@ impl Matcher for RegexMatcher {
     where
         F: FnMut(Match) -> Result<bool, E>,
     {
+        if self.verify_word {
+            let mut at = 0;
+            while let Some(m) = self.find_verified(haystack, at) {
+                at = if m.is_empty() { m.end() + 1 } else { m.end() };
+                match matched(self.snap(haystack, m)) {
+                    Ok(true) => continue,
+                    Ok(false) => return Ok(Ok(())),
+                    Err(err) => return Ok(Err(err)),
+                }
+            }
+            return Ok(Ok(()));
+        }
         for m in self.regex.find_iter(haystack) {
-            match matched(Match::new(m.start(), m.end())) {
+            match matched(self.snap(haystack, Match::new(m.start(), m.end())))
+            {
                 Ok(true) => continue,
                 Ok(false) => return Ok(Ok(())),
                 Err(err) => return Ok(Err(err)),
@@ -461,6 +860,18 @@ impl Matcher for RegexMatcher {
         at: usize,
         caps: &mut RegexCaptures,
     ) -> Result<bool, NoError> {
+        let mut at = at;
+        if self.verify_word {
+            match self.find_verified(haystack, at) {
+                None => {
+                    caps.captures_mut().set_pattern(None);
+                    return Ok(false);
+                }
+                // Re-running the search at the verified match's start
+                // position is guaranteed to rediscover the same match.
+                Some(m) => at = m.start(),
+            }
+        }
         let input = Input::new(haystack).span(at..haystack.len());
         let caps = caps.captures_mut();
         self.regex.search_captures(&input, caps);
@@ -473,6 +884,9 @@ impl Matcher for RegexMatcher {
         haystack: &[u8],
         at: usize,
     ) -> Result<Option<usize>, NoError> {
+        if self.verify_word {
+            return Ok(self.find_verified(haystack, at).map(|m| m.end()));
+        }
         let input = Input::new(haystack).span(at..haystack.len());
         Ok(self.regex.search_half(&input).map(|hm| hm.offset()))
     }
@@ -487,11 +901,42 @@ impl Matcher for RegexMatcher {
         self.config.line_terminator
     }
 
+    #[inline]
+    fn multiline_span_limit(&self) -> Option<usize> {
+        self.config.max_multiline_span
+    }
+
+    #[inline]
+    fn pattern_count(&self) -> Option<usize> {
+        Some(self.patterns.len())
+    }
+
+    #[inline]
+    fn pattern_index(&self, haystack: &[u8]) -> Option<usize> {
+        if self.patterns.len() == 1 {
+            return Some(0);
+        }
+        let matchers = self.pattern_matchers()?;
+        // Use the Matcher impl rather than the raw regex so that word
+        // boundary verification, when enabled, is applied here too.
+        matchers.iter().position(|m| m.is_match(haystack).unwrap_or(false))
+    }
+
+    #[inline]
+    fn pattern_text(&self, index: usize) -> Option<&str> {
+        self.patterns.get(index).map(|p| p.as_str())
+    }
+
     #[inline]
     fn find_candidate_line(
         &self,
         haystack: &[u8],
     ) -> Result<Option<LineMatchKind>, NoError> {
+        if let Some(ref lit) = self.exact_line_literal {
+            return Ok(self
+                .find_exact_line(lit, haystack)
+                .map(LineMatchKind::Confirmed));
+        }
         Ok(match self.fast_line_regex {
             Some(ref regex) => {
                 let input = Input::new(haystack);
@@ -567,6 +1012,73 @@ mod tests {
         assert!(!matcher.is_match(b"abc -2 foo").unwrap());
     }
 
+    // Test that word mode composes with fixed strings. Since fixed string
+    // alternations verify word boundaries by inspecting the bytes around
+    // each candidate match instead of encoding the boundaries into the
+    // regex, we check that both approaches agree. The `\d{5}never` branch
+    // in the regex variant can't match any of the haystacks, but it does
+    // prevent the pattern from being recognized as a literal alternation,
+    // which forces the encoded-boundary path.
+    #[test]
+    fn word_fixed_strings() {
+        let haystacks: &[&[u8]] = &[
+            b"foo",
+            b"foo bar",
+            b"bar foo",
+            b"_foo",
+            b"foo_",
+            b"3foo",
+            b"foo7",
+            b"xfoofoo foo",
+            b"foo -2 bar",
+            b"x-2",
+            b"-2.5",
+            "\u{e9}foo".as_bytes(),
+            "foo\u{e9}".as_bytes(),
+            " \u{e9} ".as_bytes(),
+            "\u{e9}\u{e9}".as_bytes(),
+            "\u{65e5}\u{e9}".as_bytes(),
+            "-\u{e9}-".as_bytes(),
+            b"\xFF\xFFfoo\xFF",
+        ];
+        for unicode in [true, false] {
+            let fixed = RegexMatcherBuilder::new()
+                .word(true)
+                .unicode(unicode)
+                .fixed_strings(true)
+                .build_many(&["foo", "-2", "\u{e9}"])
+                .unwrap();
+            let regex = RegexMatcherBuilder::new()
+                .word(true)
+                .unicode(unicode)
+                .build(r"foo|-2|\u{E9}|\d{5}never")
+                .unwrap();
+            for &hay in haystacks.iter() {
+                let mut expected = vec![];
+                regex
+                    .find_iter(hay, |m| {
+                        expected.push(m);
+                        true
+                    })
+                    .unwrap();
+                let mut got = vec![];
+                fixed
+                    .find_iter(hay, |m| {
+                        got.push(m);
+                        true
+                    })
+                    .unwrap();
+                assert_eq!(
+                    expected,
+                    got,
+                    "mismatch for {:?} (unicode: {})",
+                    bstr::BStr::new(hay),
+                    unicode,
+                );
+            }
+        }
+    }
+
     // Test that enabling a line terminator prevents it from matching through
     // said line terminator.
     #[test]
@@ -593,6 +1105,186 @@ mod tests {
             .is_err())
     }
 
+    // Test that the multiline span limit is exposed from the matcher.
+    #[test]
+    fn multiline_span_limit() {
+        let matcher = RegexMatcherBuilder::new()
+            .max_multiline_span(Some(1 << 20))
+            .build(r"(?s)BEGIN.*END")
+            .unwrap();
+        assert_eq!(Some(1 << 20), matcher.multiline_span_limit());
+
+        // Unlimited by default.
+        let matcher = RegexMatcher::new(r"(?s)BEGIN.*END").unwrap();
+        assert_eq!(None, matcher.multiline_span_limit());
+    }
+
+    // Test that errors report the span of the offending construct in the
+    // original pattern.
+    #[test]
+    fn error_spans() {
+        // A literal `\n` inside a class when a line terminator is set. The
+        // span covers the two bytes of the escape sequence.
+        let err = RegexMatcherBuilder::new()
+            .line_terminator(Some(b'\n'))
+            .build(r"ab[\n]z")
+            .unwrap_err();
+        assert_eq!(err.span(), Some((3, 5)));
+        // ... and the rendered message points at it.
+        let msg = err.to_string();
+        assert!(msg.contains("ab[\\n]z\n   ^^"), "bad message: {msg:?}");
+
+        // A banned byte.
+        let err = RegexMatcherBuilder::new()
+            .ban_byte(Some(0))
+            .build(r"ab\x00z")
+            .unwrap_err();
+        assert_eq!(err.span(), Some((2, 6)));
+
+        // A syntax error after a valid prefix.
+        let err = RegexMatcherBuilder::new().build(r"abc[z-a]").unwrap_err();
+        assert_eq!(err.span(), Some((4, 7)));
+    }
+
+    // Test that verbose mode patterns can be normalized to a single line
+    // and that normalization preserves their semantics, including a `#`
+    // inside a class, which (escaped) is a literal hash and not a comment.
+    #[test]
+    fn verbose_normalization() {
+        let pattern = "(?x)
+            foo     # a literal prefix
+            [\\#b]   # a class containing a hash
+            \\d+     # one or more digits
+        ";
+        let matcher = RegexMatcher::new(pattern).unwrap();
+        let norm = matcher.normalized_pattern().unwrap();
+        assert!(!norm.contains('\n'), "still multi-line: {norm:?}");
+        assert!(!norm.contains("comment"), "comment survived: {norm:?}");
+
+        // Re-parsing the normalized pattern (without verbose mode) yields
+        // the same matcher behavior.
+        let renorm = RegexMatcher::new(&norm).unwrap();
+        let haystacks: &[&[u8]] =
+            &[b"foo#123", b"foob9", b"foo 123", b"xfoo#1y", b"foo#"];
+        for &hay in haystacks.iter() {
+            assert_eq!(
+                matcher.find(hay).unwrap(),
+                renorm.find(hay).unwrap(),
+                "mismatch for {:?}",
+                bstr::BStr::new(hay),
+            );
+        }
+
+        // Patterns without verbose noise have nothing to normalize.
+        let matcher = RegexMatcher::new(r"foo[#b]\d+").unwrap();
+        assert_eq!(None, matcher.normalized_pattern());
+    }
+
+    // Test that errors for verbose mode patterns are reported in terms of
+    // the normalized pattern instead of pointing into a multi-line mess.
+    #[test]
+    fn verbose_error_spans() {
+        let err = RegexMatcherBuilder::new()
+            .line_terminator(Some(b'\n'))
+            .build("(?x)ab [\\n] z # trailing comment\n")
+            .unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains(r"ab[\n]z"), "bad message: {msg:?}");
+        assert!(!msg.contains("comment"), "bad message: {msg:?}");
+    }
+
+    // Test that verbose mode does not eat the whitespace of patterns that
+    // are escaped because fixed strings are enabled. (Escaping leaves
+    // whitespace untouched, since it is not a meta character.)
+    #[test]
+    fn verbose_fixed_strings() {
+        // Case insensitivity forces the escape-and-parse path rather than
+        // assembling literals into an HIR directly.
+        let matcher = RegexMatcherBuilder::new()
+            .fixed_strings(true)
+            .case_insensitive(true)
+            .ignore_whitespace(true)
+            .build("(?x) foo # bar")
+            .unwrap();
+        assert!(matcher.is_match(b"(?X) FOO # BAR").unwrap());
+        assert!(!matcher.is_match(b"FOO").unwrap());
+
+        // Without fixed strings, a whitespace-bearing pattern must still
+        // honor verbose mode, even though it contains no meta characters.
+        let matcher = RegexMatcherBuilder::new()
+            .ignore_whitespace(true)
+            .build("foo bar")
+            .unwrap();
+        assert!(matcher.is_match(b"foobar").unwrap());
+        assert!(!matcher.is_match(b"foo bar").unwrap());
+    }
+
+    #[test]
+    fn glob_syntax() {
+        let glob = |pattern: &str| {
+            RegexMatcherBuilder::new()
+                .glob_syntax(true)
+                .build(pattern)
+                .unwrap()
+        };
+
+        // `*` matches any number of characters, including `/` and none at
+        // all, but the glob must cover the entire subject.
+        let matcher = glob("*.rs");
+        assert!(matcher.is_match(b"main.rs").unwrap());
+        assert!(matcher.is_match(b"src/main.rs").unwrap());
+        assert!(matcher.is_match(b".rs").unwrap());
+        assert!(!matcher.is_match(b"main.rs.bak").unwrap());
+
+        // `?` matches exactly one character.
+        let matcher = glob("foo?bar");
+        assert!(matcher.is_match(b"fooxbar").unwrap());
+        assert!(!matcher.is_match(b"foobar").unwrap());
+
+        // Character classes.
+        let matcher = glob("[abc].txt");
+        assert!(matcher.is_match(b"a.txt").unwrap());
+        assert!(matcher.is_match(b"c.txt").unwrap());
+        assert!(!matcher.is_match(b"d.txt").unwrap());
+
+        // An escaped metacharacter is matched literally.
+        let matcher = glob(r"\*.rs");
+        assert!(matcher.is_match(b"*.rs").unwrap());
+        assert!(!matcher.is_match(b"a.rs").unwrap());
+    }
+
+    #[test]
+    fn glob_syntax_composes() {
+        // Other builder options apply to the translated pattern.
+        let matcher = RegexMatcherBuilder::new()
+            .glob_syntax(true)
+            .case_insensitive(true)
+            .build("*.RS")
+            .unwrap();
+        assert!(matcher.is_match(b"main.rs").unwrap());
+
+        // Fixed strings take precedence, so glob metacharacters are
+        // literal.
+        let matcher = RegexMatcherBuilder::new()
+            .glob_syntax(true)
+            .fixed_strings(true)
+            .build("*.rs")
+            .unwrap();
+        assert!(matcher.is_match(b"*.rs").unwrap());
+        assert!(!matcher.is_match(b"main.rs").unwrap());
+    }
+
+    #[test]
+    fn glob_syntax_error() {
+        let err = RegexMatcherBuilder::new()
+            .glob_syntax(true)
+            .build("[abc")
+            .unwrap_err();
+        // The error comes from globset's glob parser, with the offending
+        // glob attached as the error's pattern.
+        assert!(err.to_string().contains("character class"), "{err}");
+    }
+
     // Test that enabling CRLF permits `$` to match at the end of a line.
     #[test]
     fn line_terminator_crlf() {
@@ -617,6 +1309,15 @@ mod tests {
             .build(r"abc$")
             .unwrap();
         assert!(matcher.is_match(b"abc\r\n").unwrap());
+
+        // An explicit optional `\r` is permitted and behaves just like `$`
+        // on its own.
+        let matcher = RegexMatcherBuilder::new()
+            .multi_line(true)
+            .crlf(true)
+            .build(r"abc\r?$")
+            .unwrap();
+        assert!(matcher.is_match(b"abc\r\n").unwrap());
     }
 
     // Test that smart case works.
@@ -665,4 +1366,348 @@ mod tests {
         let m = matcher.find_candidate_line(b"afoo ").unwrap().unwrap();
         assert!(is_candidate(m));
     }
+
+    // Test that match spans are widened to grapheme cluster boundaries when
+    // snapping is enabled, and only then.
+    #[test]
+    fn snap_to_graphemes() {
+        // A decomposed `é`: `e` followed by a combining acute accent. A
+        // search for `e` matches only the base character.
+        let haystack = "xe\u{0301}y".as_bytes();
+
+        let matcher = RegexMatcherBuilder::new().build(r"e").unwrap();
+        assert_eq!(Some(Match::new(1, 2)), matcher.find(haystack).unwrap());
+
+        // With snapping, the span is widened to cover the whole cluster.
+        let matcher = RegexMatcherBuilder::new()
+            .snap_to_graphemes(true)
+            .build(r"e")
+            .unwrap();
+        assert_eq!(Some(Match::new(1, 4)), matcher.find(haystack).unwrap());
+
+        // A match ending between a base character and its combining accent
+        // has only its end widened.
+        let matcher = RegexMatcherBuilder::new()
+            .snap_to_graphemes(true)
+            .build(r"xe")
+            .unwrap();
+        assert_eq!(Some(Match::new(0, 4)), matcher.find(haystack).unwrap());
+
+        // Matches already on cluster boundaries are reported unchanged.
+        let matcher = RegexMatcherBuilder::new()
+            .snap_to_graphemes(true)
+            .build(r"x")
+            .unwrap();
+        assert_eq!(Some(Match::new(0, 1)), matcher.find(haystack).unwrap());
+    }
+
+    // Test that snapping applies to iteration and respects line boundaries.
+    #[test]
+    fn snap_to_graphemes_iter() {
+        let haystack = "e\u{0301}\ne\u{0301}\n".as_bytes();
+        let matcher = RegexMatcherBuilder::new()
+            .snap_to_graphemes(true)
+            .build(r"e")
+            .unwrap();
+        let mut matches = vec![];
+        matcher
+            .find_iter(haystack, |m| {
+                matches.push(m);
+                true
+            })
+            .unwrap();
+        assert_eq!(vec![Match::new(0, 3), Match::new(4, 7)], matches);
+    }
+
+    // Test that a pattern consisting of a literal anchored at both ends of
+    // a line is recognized, and that it isn't recognized when anchors are
+    // missing, the pattern isn't a literal or case folding applies.
+    #[test]
+    fn exact_line_literal() {
+        // -x style wrapping of a plain literal.
+        let matcher = RegexMatcherBuilder::new()
+            .multi_line(true)
+            .whole_line(true)
+            .build("foo")
+            .unwrap();
+        assert_eq!(Some(&b"foo"[..]), matcher.exact_line_literal());
+
+        // An explicitly anchored literal.
+        let matcher = RegexMatcherBuilder::new()
+            .multi_line(true)
+            .build("^foo$")
+            .unwrap();
+        assert_eq!(Some(&b"foo"[..]), matcher.exact_line_literal());
+
+        // Wrapping an already anchored pattern must not defeat detection.
+        let matcher = RegexMatcherBuilder::new()
+            .multi_line(true)
+            .whole_line(true)
+            .build("^foo$")
+            .unwrap();
+        assert_eq!(Some(&b"foo"[..]), matcher.exact_line_literal());
+
+        // Anchored, but not a literal.
+        let matcher = RegexMatcherBuilder::new()
+            .multi_line(true)
+            .build(r"^fo\w$")
+            .unwrap();
+        assert_eq!(None, matcher.exact_line_literal());
+
+        // Anchored on one end only.
+        let matcher =
+            RegexMatcherBuilder::new().multi_line(true).build("^foo").unwrap();
+        assert_eq!(None, matcher.exact_line_literal());
+
+        // Case folding turns the literal into character classes.
+        let matcher = RegexMatcherBuilder::new()
+            .multi_line(true)
+            .case_insensitive(true)
+            .whole_line(true)
+            .build("foo")
+            .unwrap();
+        assert_eq!(None, matcher.exact_line_literal());
+
+        // Haystack anchors are not line anchors.
+        let matcher = RegexMatcherBuilder::new().build("^foo$").unwrap();
+        assert_eq!(None, matcher.exact_line_literal());
+    }
+
+    // Test the line anchoring analysis on the configured HIR directly.
+    #[test]
+    fn line_anchored_analysis() {
+        let chir = |multi_line: bool, pattern: &str| {
+            let mut config = crate::config::Config::default();
+            config.multi_line = multi_line;
+            config.build_many(&[pattern]).unwrap()
+        };
+
+        let hir = chir(true, "^foo$");
+        assert!(hir.is_line_anchored_start());
+        assert!(hir.is_line_anchored_end());
+
+        let hir = chir(true, "^foo");
+        assert!(hir.is_line_anchored_start());
+        assert!(!hir.is_line_anchored_end());
+
+        // Without multi-line mode, the anchors apply to the haystack, but
+        // that still anchors every match to a line boundary.
+        let hir = chir(false, "^foo$");
+        assert!(hir.is_line_anchored_start());
+        assert!(hir.is_line_anchored_end());
+
+        // Only some branches of the alternation are anchored.
+        let hir = chir(true, "^foo$|bar");
+        assert!(!hir.is_line_anchored_start());
+        assert!(!hir.is_line_anchored_end());
+
+        let hir = chir(true, "foo");
+        assert!(!hir.is_line_anchored_start());
+        assert!(!hir.is_line_anchored_end());
+    }
+
+    // Test that the byte equality fast path for whole-line literals agrees
+    // with the general regex engine.
+    #[test]
+    fn exact_line_fast_path_agreement() {
+        let haystacks: &[&[u8]] = &[
+            b"foo",
+            b"foo\n",
+            b"afoo\nfoo\nfoob",
+            b"xfoo\nfooy",
+            b"foofoo\nfoo foo",
+            b"\nfoo\n",
+            b"bar\r\nfoo\r\nbaz",
+            b"foo\r\n",
+        ];
+        for crlf in [false, true] {
+            let matcher = RegexMatcherBuilder::new()
+                .multi_line(true)
+                .crlf(crlf)
+                .whole_line(true)
+                .build("foo")
+                .unwrap();
+            assert_eq!(Some(&b"foo"[..]), matcher.exact_line_literal());
+            for &hay in haystacks.iter() {
+                let got = matcher.find_candidate_line(hay).unwrap();
+                let expected = matcher.shortest_match(hay).unwrap();
+                match (got, expected) {
+                    (None, None) => {}
+                    (Some(LineMatchKind::Confirmed(offset)), Some(_)) => {
+                        // The offset must point into the matching line.
+                        assert!(
+                            matcher
+                                .is_match(line_containing(hay, offset))
+                                .unwrap(),
+                            "bad offset {} for {:?} (crlf: {})",
+                            offset,
+                            bstr::BStr::new(hay),
+                            crlf,
+                        );
+                    }
+                    _ => panic!(
+                        "fast path disagreement for {:?} (crlf: {}): \
+                         got {:?}, engine found match: {}",
+                        bstr::BStr::new(hay),
+                        crlf,
+                        got,
+                        expected.is_some(),
+                    ),
+                }
+            }
+        }
+    }
+
+    // Returns the line of the haystack containing the given offset, without
+    // its line terminator.
+    fn line_containing(haystack: &[u8], offset: usize) -> &[u8] {
+        let start = haystack[..offset]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        let end = haystack[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(haystack.len(), |i| offset + i);
+        let line = &haystack[start..end];
+        line.strip_suffix(b"\r").unwrap_or(line)
+    }
+
+    // Test that the optimization report describes the strategies engaged for
+    // a plain literal pattern.
+    #[test]
+    fn optimization_report_literal() {
+        let matcher = RegexMatcherBuilder::new()
+            .line_terminator(Some(b'\n'))
+            .build("foo")
+            .unwrap();
+        let report = matcher.optimization_report();
+        // A lone literal is trivially an alternation of literals, so inner
+        // literal extraction defers to the regex engine's own searcher.
+        assert_eq!(Ok(()), report.literal_alternation());
+        assert_eq!(
+            Err(&OptimizationSkip::DeferredToRegexEngine),
+            report.inner_literals(),
+        );
+        assert_eq!(
+            Err(&OptimizationSkip::NotRequested),
+            report.exact_line_literal(),
+        );
+        assert_eq!(
+            Err(&OptimizationSkip::NotRequested),
+            report.word_wrapper()
+        );
+        assert_eq!(
+            Err(&OptimizationSkip::NotRequested),
+            report.crlf_wrapper()
+        );
+
+        // Without a line terminator (i.e., multi-line mode), the inner
+        // literal optimization is never valid.
+        let matcher = RegexMatcherBuilder::new().build(r"\w+foo").unwrap();
+        assert_eq!(
+            Err(&OptimizationSkip::NoLineTerminator),
+            matcher.optimization_report().inner_literals(),
+        );
+    }
+
+    // Test that skipped strategies report which pattern is to blame and
+    // where its first meta character is.
+    #[test]
+    fn optimization_report_not_literal() {
+        let matcher = RegexMatcherBuilder::new()
+            .line_terminator(Some(b'\n'))
+            .build_many(&["foo", "a+b"])
+            .unwrap();
+        assert_eq!(
+            Err(&OptimizationSkip::NotLiteral {
+                pattern_index: 1,
+                first_meta_offset: Some(1),
+            }),
+            matcher.optimization_report().literal_alternation(),
+        );
+
+        // When the pattern text is literal but the configuration produces a
+        // non-literal expression (here, via case folding), there's no meta
+        // character to point at.
+        let matcher = RegexMatcherBuilder::new()
+            .line_terminator(Some(b'\n'))
+            .case_insensitive(true)
+            .build("foo")
+            .unwrap();
+        assert_eq!(
+            Err(&OptimizationSkip::NotLiteral {
+                pattern_index: 0,
+                first_meta_offset: None,
+            }),
+            matcher.optimization_report().literal_alternation(),
+        );
+    }
+
+    // Test that the inner literal prefilter reports the literals it
+    // extracted. The `[A-Z]` is what inhibits the regex engine's own inner
+    // literal optimization, making this extractor's work visible.
+    #[test]
+    fn optimization_report_inner_literals() {
+        let matcher = RegexMatcherBuilder::new()
+            .line_terminator(Some(b'\n'))
+            .build(r"\s+(Sherlock|[A-Z]atso[a-z]|Moriarty)\s+")
+            .unwrap();
+        let report = matcher.optimization_report();
+        let lits = report.inner_literals().unwrap();
+        assert!(
+            lits.iter().any(|lit| lit == b"Sherlock"),
+            "bad literals: {lits:?}",
+        );
+    }
+
+    // Test that the word wrapper reports whether word matching was encoded
+    // into the pattern or verified by hand.
+    #[test]
+    fn optimization_report_word() {
+        // A literal alternation keeps its literals and verifies word
+        // boundaries by inspecting the bytes around candidate matches.
+        let matcher = RegexMatcherBuilder::new()
+            .word(true)
+            .fixed_strings(true)
+            .build_many(&["foo", "bar"])
+            .unwrap();
+        let report = matcher.optimization_report();
+        assert_eq!(Ok(()), report.literal_alternation());
+        assert_eq!(
+            Err(&OptimizationSkip::LiteralAlternationPreferred),
+            report.word_wrapper(),
+        );
+
+        // A non-literal pattern gets wrapped in word assertions.
+        let matcher =
+            RegexMatcherBuilder::new().word(true).build(r"fo\d").unwrap();
+        assert_eq!(Ok(()), matcher.optimization_report().word_wrapper());
+    }
+
+    // Test that the exact line literal strategy reports the literal that
+    // lines are compared against.
+    #[test]
+    fn optimization_report_exact_line_literal() {
+        let matcher = RegexMatcherBuilder::new()
+            .line_terminator(Some(b'\n'))
+            .whole_line(true)
+            .build("foo")
+            .unwrap();
+        let report = matcher.optimization_report();
+        assert_eq!(Ok(&b"foo"[..]), report.exact_line_literal());
+
+        let matcher = RegexMatcherBuilder::new()
+            .line_terminator(Some(b'\n'))
+            .whole_line(true)
+            .build("fo+")
+            .unwrap();
+        assert_eq!(
+            Err(&OptimizationSkip::NotLiteral {
+                pattern_index: 0,
+                first_meta_offset: Some(2),
+            }),
+            matcher.optimization_report().exact_line_literal(),
+        );
+    }
 }
@@ -0,0 +1,145 @@
+/*!
+Provides a report of the optimization strategies engaged when building a
+matcher.
+*/
+
+/// A report of the optimization strategies considered while building a
+/// [`RegexMatcher`](crate::RegexMatcher).
+///
+/// Building a matcher involves a pile of heuristics for picking a search
+/// strategy, and when a fast path silently doesn't apply (say, because one
+/// pattern in a set contains a meta character), the only observable effect
+/// is a mysterious slowdown. This report makes those decisions visible:
+/// each strategy is reported as either engaged (along with any artifacts it
+/// produced, such as the extracted literals) or skipped with a
+/// machine-readable reason.
+///
+/// A report is assembled during construction and describes decisions already
+/// made; inspecting it has no effect on matching. It is available via
+/// [`RegexMatcher::optimization_report`](crate::RegexMatcher::optimization_report).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OptimizationReport {
+    pub(crate) exact_line_literal: Result<Vec<u8>, OptimizationSkip>,
+    pub(crate) inner_literals: Result<Vec<Vec<u8>>, OptimizationSkip>,
+    pub(crate) literal_alternation: Result<(), OptimizationSkip>,
+    pub(crate) word_wrapper: Result<(), OptimizationSkip>,
+    pub(crate) crlf_wrapper: Result<(), OptimizationSkip>,
+}
+
+impl OptimizationReport {
+    /// Reports on matching lines by comparing them for byte equality against
+    /// a single literal, bypassing the regex engine entirely.
+    ///
+    /// This engages when the entire pattern is a literal that is anchored at
+    /// both ends of a line (i.e., `-F -x`). When engaged, the literal
+    /// compared against is returned.
+    pub fn exact_line_literal(&self) -> Result<&[u8], &OptimizationSkip> {
+        match self.exact_line_literal {
+            Ok(ref lit) => Ok(lit),
+            Err(ref skip) => Err(skip),
+        }
+    }
+
+    /// Reports on the inner literal prefilter: literals plucked out of the
+    /// pattern that are searched with vectorized routines to find candidate
+    /// lines, which are then confirmed with the real regex.
+    ///
+    /// When engaged, the extracted literal sequence is returned.
+    pub fn inner_literals(&self) -> Result<&[Vec<u8>], &OptimizationSkip> {
+        match self.inner_literals {
+            Ok(ref lits) => Ok(lits),
+            Err(ref skip) => Err(skip),
+        }
+    }
+
+    /// Reports on whether the entire pattern set was recognized as a plain
+    /// alternation of literals.
+    ///
+    /// When it is, the regex engine is expected to use its own multi-literal
+    /// searcher (e.g., Aho-Corasick or a vectorized algorithm) instead of a
+    /// general regex engine, and the matcher takes care not to foil that,
+    /// e.g., by verifying word boundaries itself rather than wrapping the
+    /// pattern in look-around. See [`OptimizationReport::word_wrapper`].
+    pub fn literal_alternation(&self) -> Result<(), &OptimizationSkip> {
+        self.literal_alternation.as_ref().map(|_| ())
+    }
+
+    /// Reports on whether word matching (`-w`) was implemented by wrapping
+    /// the pattern in word boundary assertions.
+    ///
+    /// Note that for word matching, the wrapper is the slow path: when the
+    /// pattern set is a plain alternation of literals, the wrapper is
+    /// deliberately skipped (with
+    /// [`OptimizationSkip::LiteralAlternationPreferred`]) and candidate
+    /// matches have their neighboring bytes checked by hand instead, which
+    /// preserves the regex engine's multi-literal searcher.
+    pub fn word_wrapper(&self) -> Result<(), &OptimizationSkip> {
+        self.word_wrapper.as_ref().map(|_| ())
+    }
+
+    /// Reports on whether the pattern was rewritten to treat `\r?$` as a
+    /// line anchor, for matching CRLF terminated lines without including
+    /// the `\r` in matches.
+    pub fn crlf_wrapper(&self) -> Result<(), &OptimizationSkip> {
+        self.crlf_wrapper.as_ref().map(|_| ())
+    }
+}
+
+/// A machine-readable reason for why an optimization strategy was not
+/// engaged when building a matcher.
+///
+/// See [`OptimizationReport`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OptimizationSkip {
+    /// The strategy only applies with a builder configuration that was not
+    /// requested. For example, the exact line literal strategy only applies
+    /// when `whole_line` is enabled.
+    NotRequested,
+    /// The strategy requires the patterns to be plain literals, and at least
+    /// one of them isn't.
+    NotLiteral {
+        /// The index (in the patterns given to the builder) of the first
+        /// pattern that is not a plain literal.
+        pattern_index: usize,
+        /// The byte offset of the first regex meta character in that
+        /// pattern. This is `None` when the pattern text itself contains no
+        /// meta characters but the configuration still produces a non-literal
+        /// pattern, e.g., when case folding is enabled.
+        first_meta_offset: Option<usize>,
+    },
+    /// The strategy requires a line terminator to be configured, and none
+    /// was. (Notably, this is the case in multi-line mode.)
+    NoLineTerminator,
+    /// The strategy was deliberately skipped because the regex engine is
+    /// believed to handle the pattern well on its own.
+    DeferredToRegexEngine,
+    /// No literals that would beat simply running the regex engine could be
+    /// extracted from the pattern.
+    NoUsefulLiterals,
+    /// The strategy was skipped in favor of keeping the pattern set a plain
+    /// alternation of literals, which the regex engine can search with a
+    /// dedicated multi-literal algorithm.
+    LiteralAlternationPreferred,
+}
+
+/// Returns the `NotLiteral` skip reason for the given pattern set.
+///
+/// This reports the first pattern containing a regex meta character, along
+/// with the byte offset of that character. When no pattern contains a meta
+/// character (so the non-literal structure must come from the configuration,
+/// e.g., case folding), the first pattern is blamed with no offset.
+pub(crate) fn not_literal<P: AsRef<str>>(patterns: &[P]) -> OptimizationSkip {
+    for (pattern_index, pattern) in patterns.iter().enumerate() {
+        let meta = pattern
+            .as_ref()
+            .char_indices()
+            .find(|&(_, ch)| regex_syntax::is_meta_character(ch));
+        if let Some((first_meta_offset, _)) = meta {
+            return OptimizationSkip::NotLiteral {
+                pattern_index,
+                first_meta_offset: Some(first_meta_offset),
+            };
+        }
+    }
+    OptimizationSkip::NotLiteral { pattern_index: 0, first_meta_offset: None }
+}
@@ -41,13 +41,120 @@ pub(crate) fn strip_from_match(
     line_term: LineTerminator,
 ) -> Result<Hir, Error> {
     if line_term.is_crlf() {
-        let expr1 = strip_from_match_ascii(expr, b'\r')?;
-        strip_from_match_ascii(expr1, b'\n')
+        strip_crlf(expr)
     } else {
         strip_from_match_ascii(expr, line_term.as_byte())
     }
 }
 
+/// Strip a CRLF line terminator from the given expression.
+///
+/// This is like `strip_from_match_ascii`, except it deals with both bytes of
+/// the terminator in a single pass. Handling them together permits two things
+/// that the one-byte-at-a-time approach can't do. Firstly, an explicit `\r\n`
+/// literal in a pattern is reported as such, instead of as a confusing error
+/// about `\r` alone. Secondly, a repetition that can match the empty string
+/// (like `\r?`) is rewritten to match nothing instead of resulting in an
+/// error. The rewrite agrees with how the searcher handles a CRLF terminator:
+/// the entire `\r\n` is stripped from a line before matching, so `foo\r?$`
+/// behaves precisely like `foo$`.
+fn strip_crlf(expr: Hir) -> Result<Hir, Error> {
+    let invalid = |lit: &str| {
+        Err(Error::new(ErrorKind::NotAllowed(lit.to_string())))
+    };
+    Ok(match expr.into_kind() {
+        HirKind::Empty => Hir::empty(),
+        HirKind::Literal(hir::Literal(lit)) => {
+            if lit.windows(2).any(|pair| pair == b"\r\n") {
+                return invalid("\r\n");
+            }
+            if lit.iter().any(|&b| b == b'\r') {
+                return invalid("\r");
+            }
+            if lit.iter().any(|&b| b == b'\n') {
+                return invalid("\n");
+            }
+            Hir::literal(lit)
+        }
+        HirKind::Class(hir::Class::Unicode(mut cls)) => {
+            if cls.ranges().is_empty() {
+                return Ok(Hir::class(hir::Class::Unicode(cls)));
+            }
+            let had_nl = cls
+                .ranges()
+                .iter()
+                .any(|r| r.start() <= '\n' && '\n' <= r.end());
+            let remove = hir::ClassUnicode::new([
+                hir::ClassUnicodeRange::new('\n', '\n'),
+                hir::ClassUnicodeRange::new('\r', '\r'),
+            ]);
+            cls.difference(&remove);
+            if cls.ranges().is_empty() {
+                return invalid(if had_nl { "\n" } else { "\r" });
+            }
+            Hir::class(hir::Class::Unicode(cls))
+        }
+        HirKind::Class(hir::Class::Bytes(mut cls)) => {
+            if cls.ranges().is_empty() {
+                return Ok(Hir::class(hir::Class::Bytes(cls)));
+            }
+            let had_nl = cls
+                .ranges()
+                .iter()
+                .any(|r| r.start() <= b'\n' && b'\n' <= r.end());
+            let remove = hir::ClassBytes::new([
+                hir::ClassBytesRange::new(b'\n', b'\n'),
+                hir::ClassBytesRange::new(b'\r', b'\r'),
+            ]);
+            cls.difference(&remove);
+            if cls.ranges().is_empty() {
+                return invalid(if had_nl { "\n" } else { "\r" });
+            }
+            Hir::class(hir::Class::Bytes(cls))
+        }
+        HirKind::Look(x) => Hir::look(x),
+        HirKind::Repetition(mut x) => {
+            // If the sub-expression can't be stripped but the repetition
+            // can match the empty string, then the repetition as a whole can
+            // be rewritten to match nothing. The sub-expression could only
+            // ever match a line terminator byte, and a line terminator can
+            // never appear in the haystack given to the regex.
+            let min = x.min;
+            match strip_crlf(*x.sub) {
+                Ok(sub) => {
+                    x.sub = Box::new(sub);
+                    Hir::repetition(x)
+                }
+                Err(err) => {
+                    if min == 0 {
+                        Hir::empty()
+                    } else {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+        HirKind::Capture(mut x) => {
+            x.sub = Box::new(strip_crlf(*x.sub)?);
+            Hir::capture(x)
+        }
+        HirKind::Concat(xs) => {
+            let xs = xs
+                .into_iter()
+                .map(strip_crlf)
+                .collect::<Result<Vec<Hir>, Error>>()?;
+            Hir::concat(xs)
+        }
+        HirKind::Alternation(xs) => {
+            let xs = xs
+                .into_iter()
+                .map(strip_crlf)
+                .collect::<Result<Vec<Hir>, Error>>()?;
+            Hir::alternation(xs)
+        }
+    })
+}
+
 /// The implementation of strip_from_match. The given byte must be ASCII.
 /// This function returns an error otherwise. It also returns an error if
 /// it couldn't remove `\n` from the given regex without leaving an empty
@@ -123,7 +230,7 @@ mod tests {
     use regex_syntax::Parser;
 
     use super::{strip_from_match, LineTerminator};
-    use crate::error::Error;
+    use crate::error::{Error, ErrorKind};
 
     fn roundtrip(pattern: &str, byte: u8) -> String {
         roundtrip_line_term(pattern, LineTerminator::byte(byte)).unwrap()
@@ -167,4 +274,43 @@ mod tests {
         assert!(roundtrip_err(r"\u{A}", b'\n').is_err());
         assert!(roundtrip_err("\n", b'\n').is_err());
     }
+
+    #[test]
+    fn crlf() {
+        // A repetition of a line terminator byte that can match the empty
+        // string is rewritten to match nothing at all. Since the searcher
+        // strips the entire `\r\n` from each line, `foo\r?$` behaves exactly
+        // like `foo$`.
+        assert_eq!(roundtrip_crlf(r"foo\r?"), "(?:foo)");
+        assert_eq!(roundtrip_crlf(r"foo\r*"), "(?:foo)");
+        assert_eq!(roundtrip_crlf(r"foo(?:\r\n)?"), "(?:foo)");
+
+        // Patterns that require matching a line terminator byte still fail.
+        assert!(roundtrip_line_term(r"foo\r+", LineTerminator::crlf())
+            .is_err());
+        assert!(
+            roundtrip_line_term(r"foo\r", LineTerminator::crlf()).is_err()
+        );
+
+        // An explicit `\r\n` literal is reported as such, and not as a
+        // confusing error about `\r` alone.
+        let err = roundtrip_line_term(r"foo\r\nbar", LineTerminator::crlf())
+            .unwrap_err();
+        match *err.kind() {
+            ErrorKind::NotAllowed(ref lit) => assert_eq!(lit, "\r\n"),
+            ref kind => panic!("unexpected error kind: {:?}", kind),
+        }
+        let err = roundtrip_line_term(r"foo\r", LineTerminator::crlf())
+            .unwrap_err();
+        match *err.kind() {
+            ErrorKind::NotAllowed(ref lit) => assert_eq!(lit, "\r"),
+            ref kind => panic!("unexpected error kind: {:?}", kind),
+        }
+        let err = roundtrip_line_term("[\n]", LineTerminator::crlf())
+            .unwrap_err();
+        match *err.kind() {
+            ErrorKind::NotAllowed(ref lit) => assert_eq!(lit, "\n"),
+            ref kind => panic!("unexpected error kind: {:?}", kind),
+        }
+    }
 }
@@ -0,0 +1,169 @@
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{self, Read, Seek},
+    path::Path,
+};
+
+/// A registry of in-process decompressors.
+///
+/// A registry maps file extensions and magic bytes to factories that wrap a
+/// [`File`] in a decompressing reader. When a registry is attached to a
+/// searcher via [`SearcherBuilder::decompression`], files searched via
+/// [`Searcher::search_path`] that match a registered decompressor are
+/// transparently decompressed before being searched. Everything downstream
+/// of the decompressor---binary detection, line numbers, byte offsets and
+/// [`SinkFinish::byte_count`]---operates on the decompressed stream. The
+/// size of the compressed file is reported separately via
+/// [`SinkFinish::compressed_byte_count`].
+///
+/// A decompressor is selected by matching the file path's extension first.
+/// When no extension matches and at least one decompressor was registered
+/// with magic bytes, the beginning of the file is read and compared against
+/// each registered magic.
+///
+/// Note that this is unrelated to the decompression support in the
+/// `grep-cli` crate, which shells out to external commands like `gzip`.
+///
+/// [`SearcherBuilder::decompression`]:
+/// crate::SearcherBuilder::decompression
+/// [`Searcher::search_path`]: crate::Searcher::search_path
+/// [`SinkFinish::byte_count`]: crate::SinkFinish::byte_count
+/// [`SinkFinish::compressed_byte_count`]:
+/// crate::SinkFinish::compressed_byte_count
+#[derive(Default)]
+pub struct DecompressionRegistry {
+    entries: Vec<Entry>,
+    /// The length of the longest registered magic, which bounds how much of
+    /// a file needs to be read for sniffing.
+    max_magic_len: usize,
+}
+
+/// A factory for wrapping a file in a decompressing reader.
+type Factory =
+    Box<dyn Fn(File) -> io::Result<Box<dyn Read + Send>> + Send + Sync>;
+
+/// A single registered decompressor.
+struct Entry {
+    extensions: Vec<OsString>,
+    magics: Vec<Vec<u8>>,
+    factory: Factory,
+}
+
+impl std::fmt::Debug for DecompressionRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecompressionRegistry")
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+impl DecompressionRegistry {
+    /// Return a new empty registry.
+    pub fn new() -> DecompressionRegistry {
+        DecompressionRegistry::default()
+    }
+
+    /// Return a registry populated with this crate's built-in decompressors.
+    ///
+    /// Currently, the only built-in decompressor is gzip, which is available
+    /// when the `gzip` feature is enabled. Without that feature, this is
+    /// equivalent to [`DecompressionRegistry::new`]. Decompressors for other
+    /// formats (zstd, xz, ...) can be added with
+    /// [`DecompressionRegistry::register`].
+    pub fn with_defaults() -> DecompressionRegistry {
+        #[allow(unused_mut)]
+        let mut registry = DecompressionRegistry::new();
+        #[cfg(feature = "gzip")]
+        registry.register(&["gz"], &[b"\x1f\x8b"], |file| {
+            Ok(Box::new(flate2::read::MultiGzDecoder::new(file)))
+        });
+        registry
+    }
+
+    /// Register a decompressor for the given file extensions (without the
+    /// leading `.`) and magic bytes.
+    ///
+    /// The factory given is called with the opened file whenever a searched
+    /// path has one of the extensions given, or, when no registered
+    /// extension matches, whenever the file starts with one of the magic
+    /// byte sequences given. Either list may be empty to match on the other
+    /// criterion alone.
+    pub fn register<F>(
+        &mut self,
+        extensions: &[&str],
+        magics: &[&[u8]],
+        factory: F,
+    ) -> &mut DecompressionRegistry
+    where
+        F: Fn(File) -> io::Result<Box<dyn Read + Send>> + Send + Sync + 'static,
+    {
+        for magic in magics.iter() {
+            self.max_magic_len = self.max_magic_len.max(magic.len());
+        }
+        self.entries.push(Entry {
+            extensions: extensions.iter().map(OsString::from).collect(),
+            magics: magics.iter().map(|m| m.to_vec()).collect(),
+            factory: Box::new(factory),
+        });
+        self
+    }
+
+    /// Wrap the given file in a decompressing reader if a registered
+    /// decompressor matches.
+    ///
+    /// On a match, this returns the decompressing reader along with the size
+    /// of the compressed file. Otherwise, the file is handed back unchanged.
+    pub(crate) fn reader(
+        &self,
+        path: &Path,
+        file: File,
+    ) -> io::Result<Result<(Box<dyn Read + Send>, u64), File>> {
+        let entry = match self.find(path, &file)? {
+            None => return Ok(Err(file)),
+            Some(entry) => entry,
+        };
+        let compressed_len = file.metadata()?.len();
+        let rdr = (entry.factory)(file)?;
+        Ok(Ok((rdr, compressed_len)))
+    }
+
+    /// Find the registered decompressor matching the given path, if any.
+    ///
+    /// When magic bytes need to be sniffed, the file's position is restored
+    /// to the beginning afterward.
+    fn find(&self, path: &Path, file: &File) -> io::Result<Option<&Entry>> {
+        if let Some(ext) = path.extension() {
+            for entry in self.entries.iter() {
+                if entry.extensions.iter().any(|e| e.as_os_str() == ext) {
+                    return Ok(Some(entry));
+                }
+            }
+        }
+        if self.max_magic_len == 0 {
+            return Ok(None);
+        }
+        let mut buf = vec![0u8; self.max_magic_len];
+        let mut len = 0;
+        // `Read` and `Seek` are implemented for `&File`, so sniffing doesn't
+        // need ownership of the file.
+        let mut rdr = file;
+        while len < buf.len() {
+            match rdr.read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        rdr.seek(io::SeekFrom::Start(0))?;
+        for entry in self.entries.iter() {
+            for magic in entry.magics.iter() {
+                if !magic.is_empty() && buf[..len].starts_with(magic) {
+                    return Ok(Some(entry));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
@@ -84,20 +84,22 @@ searches stdin.
 #![deny(missing_docs)]
 
 pub use crate::{
+    decompress::DecompressionRegistry,
     lines::{LineIter, LineStep},
     searcher::{
-        BinaryDetection, ConfigError, Encoding, MmapChoice, Searcher,
-        SearcherBuilder,
+        BinaryDetection, ConfigError, Encoding, MmapAdvice, MmapChoice,
+        Searcher, SearcherBuilder, SearcherConfigSummary,
     },
     sink::{
         sinks, Sink, SinkContext, SinkContextKind, SinkError, SinkFinish,
-        SinkMatch,
+        SinkMatch, Tee, TeeStop,
     },
 };
 
 #[macro_use]
 mod macros;
 
+mod decompress;
 mod line_buffer;
 mod lines;
 mod searcher;
@@ -92,6 +92,9 @@ struct Config {
     buffer_alloc: BufferAllocation,
     /// When set, the presence of the given byte indicates binary content.
     binary: BinaryDetection,
+    /// When set, lines longer than this many bytes are skipped entirely
+    /// instead of being buffered.
+    max_line_len: Option<usize>,
 }
 
 impl Default for Config {
@@ -101,6 +104,7 @@ impl Default for Config {
             lineterm: b'\n',
             buffer_alloc: BufferAllocation::default(),
             binary: BinaryDetection::default(),
+            max_line_len: None,
         }
     }
 }
@@ -127,6 +131,9 @@ impl LineBufferBuilder {
             end: 0,
             absolute_byte_offset: 0,
             binary_byte_offset: None,
+            convert_offsets: vec![],
+            skipping: None,
+            skipped_lines: vec![],
         }
     }
 
@@ -200,6 +207,29 @@ impl LineBufferBuilder {
         self.config.binary = detection;
         self
     }
+
+    /// Set the maximum line length enforced by this buffer.
+    ///
+    /// When set, a line whose length exceeds this limit is not buffered.
+    /// Instead, its bytes are discarded as they are read and the line is
+    /// replaced in the buffer's contents by a single line terminator, so
+    /// that line counting of subsequent lines is unaffected. Each skipped
+    /// line is recorded and can be retrieved via `skipped_lines`.
+    ///
+    /// Note that the limit is enforced as data is buffered. A line that
+    /// exceeds the limit but becomes fully resident in the buffer within a
+    /// single read may still be exposed to callers. The limit is therefore
+    /// principally a bound on memory usage: the buffer is never grown to
+    /// accommodate a single long line.
+    ///
+    /// By default, no limit is set.
+    pub(crate) fn max_line_len(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut LineBufferBuilder {
+        self.config.max_line_len = limit;
+        self
+    }
 }
 
 /// A line buffer reader efficiently reads a line oriented buffer from an
@@ -240,6 +270,22 @@ impl<'b, R: io::Read> LineBufferReader<'b, R> {
         self.line_buffer.binary_byte_offset()
     }
 
+    /// The absolute byte offsets, in ascending order, at which binary bytes
+    /// were converted to line terminators in the current contents of the
+    /// buffer. Line counting uses this to avoid treating converted bytes as
+    /// new lines.
+    pub(crate) fn convert_offsets(&self) -> &[u64] {
+        self.line_buffer.convert_offsets()
+    }
+
+    /// The lines that were skipped by the most recent call to `fill` because
+    /// they exceeded the configured maximum line length. Each entry is the
+    /// absolute byte offset at which the skipped line started and the length
+    /// of the line, in bytes, excluding its terminator.
+    pub(crate) fn skipped_lines(&self) -> &[(u64, u64)] {
+        self.line_buffer.skipped_lines()
+    }
+
     /// Fill the contents of this buffer by discarding the part of the buffer
     /// that has been consumed. The free space created by discarding the
     /// consumed part of the buffer is then filled with new data from the
@@ -320,6 +366,20 @@ pub(crate) struct LineBuffer {
     /// If binary data was found, this records the absolute byte offset at
     /// which it was first detected.
     binary_byte_offset: Option<u64>,
+    /// The absolute byte offsets at which a binary byte was converted to a
+    /// line terminator. Offsets corresponding to consumed data are dropped
+    /// when the buffer is rolled. Callers use this to avoid counting
+    /// converted bytes as new lines.
+    convert_offsets: Vec<u64>,
+    /// When present, a line exceeding the maximum line length is in the
+    /// process of being discarded. The tuple is the absolute byte offset at
+    /// which the line started and the number of bytes of the line seen so
+    /// far.
+    skipping: Option<(u64, u64)>,
+    /// The lines skipped by the most recent call to `fill`, as pairs of the
+    /// absolute byte offset at which the line started and the length of the
+    /// line in bytes, excluding its terminator.
+    skipped_lines: Vec<(u64, u64)>,
 }
 
 impl LineBuffer {
@@ -338,6 +398,9 @@ impl LineBuffer {
         self.end = 0;
         self.absolute_byte_offset = 0;
         self.binary_byte_offset = None;
+        self.convert_offsets.clear();
+        self.skipping = None;
+        self.skipped_lines.clear();
     }
 
     /// The absolute byte offset which corresponds to the starting offsets
@@ -357,6 +420,19 @@ impl LineBuffer {
         self.binary_byte_offset
     }
 
+    /// The absolute byte offsets, in ascending order, at which binary bytes
+    /// were converted to line terminators in the current contents of this
+    /// buffer.
+    fn convert_offsets(&self) -> &[u64] {
+        &self.convert_offsets
+    }
+
+    /// The lines skipped by the most recent call to `fill` because they
+    /// exceeded the maximum line length.
+    fn skipped_lines(&self) -> &[(u64, u64)] {
+        &self.skipped_lines
+    }
+
     /// Return the contents of this buffer.
     fn buffer(&self) -> &[u8] {
         &self.buf[self.pos..self.last_lineterm]
@@ -404,6 +480,7 @@ impl LineBuffer {
     /// error if the buffer must be expanded past its allocation limit, as
     /// governed by the buffer allocation strategy.
     fn fill<R: io::Read>(&mut self, mut rdr: R) -> Result<bool, io::Error> {
+        self.skipped_lines.clear();
         // If the binary detection heuristic tells us to quit once binary data
         // has been observed, then we no longer read new data and reach EOF
         // once the current buffer has been consumed.
@@ -411,17 +488,40 @@ impl LineBuffer {
             return Ok(!self.buffer().is_empty());
         }
 
+        // The position in `buf` (after rolling) immediately following the
+        // last line terminator in the buffer, which is where any incomplete
+        // line begins. `last_lineterm` can't serve this purpose because
+        // rolling the buffer sets it past any incomplete line, so that
+        // buffered data remains visible if an error occurs below.
+        let mut linestart = self.last_lineterm.saturating_sub(self.pos);
         self.roll();
         assert_eq!(self.pos, 0);
         loop {
             self.ensure_capacity()?;
-            let readlen = rdr.read(self.free_buffer().as_bytes_mut())?;
+            let mut readlen = rdr.read(self.free_buffer().as_bytes_mut())?;
             if readlen == 0 {
+                // If we hit EOF in the middle of skipping an overlong line,
+                // then the skipped line was the final line. Record it, but
+                // don't bother with a stand-in line terminator since there
+                // are no subsequent lines to count.
+                if let Some((offset, len)) = self.skipping.take() {
+                    self.skipped_lines.push((offset, len));
+                }
                 // We're only done reading for good once the caller has
                 // consumed everything.
                 self.last_lineterm = self.end;
                 return Ok(!self.buffer().is_empty());
             }
+            if self.skipping.is_some() {
+                // We're discarding the remainder of an overlong line. The
+                // bytes read above sit in the free portion of the buffer,
+                // and only the bytes following the end of the overlong line
+                // (if it ends at all) are kept.
+                readlen = self.discard_skipped(readlen);
+                if readlen == 0 {
+                    continue;
+                }
+            }
 
             // Get a mutable view into the bytes we've just read. These are
             // the bytes that we do binary detection on, and also the bytes we
@@ -447,9 +547,16 @@ impl LineBuffer {
                     }
                 }
                 BinaryDetection::Convert(byte) => {
-                    if let Some(i) =
-                        replace_bytes(newbytes, byte, self.config.lineterm)
-                    {
+                    // self.pos is zero here (we rolled above), so the
+                    // absolute offset of `newbytes` starts at `oldend`.
+                    let base = self.absolute_byte_offset + oldend as u64;
+                    if let Some(i) = replace_bytes(
+                        newbytes,
+                        byte,
+                        self.config.lineterm,
+                        base,
+                        &mut self.convert_offsets,
+                    ) {
                         // Record only the first binary offset.
                         if self.binary_byte_offset.is_none() {
                             self.binary_byte_offset = Some(
@@ -462,8 +569,35 @@ impl LineBuffer {
             }
 
             // Update our `last_lineterm` positions if we read one.
-            if let Some(i) = newbytes.rfind_byte(self.config.lineterm) {
-                self.last_lineterm = oldend + i + 1;
+            let found_lineterm =
+                match newbytes.rfind_byte(self.config.lineterm) {
+                    Some(i) => {
+                        self.last_lineterm = oldend + i + 1;
+                        linestart = self.last_lineterm;
+                        true
+                    }
+                    None => false,
+                };
+            // If the incomplete line at the end of the buffer has grown
+            // beyond the maximum line length, then discard it and skip the
+            // rest of the line as it is read, instead of growing the buffer
+            // to accommodate it.
+            if let Some(max) = self.config.max_line_len {
+                let partial = self.end - linestart;
+                if partial > max {
+                    let offset = self.absolute_byte_offset + linestart as u64;
+                    self.skipping = Some((offset, partial as u64));
+                    self.end = linestart;
+                    self.last_lineterm = linestart;
+                    // Serve any complete lines already in the buffer. The
+                    // skip resumes on the next call to fill.
+                    if !self.buffer().is_empty() {
+                        return Ok(true);
+                    }
+                    continue;
+                }
+            }
+            if found_lineterm {
                 return Ok(true);
             }
             // At this point, if we couldn't find a line terminator, then we
@@ -471,6 +605,38 @@ impl LineBuffer {
         }
     }
 
+    /// Discard bytes belonging to a line that is being skipped because it
+    /// exceeded the maximum line length.
+    ///
+    /// This examines the `readlen` bytes just read into the free portion of
+    /// the buffer, which begins at `self.end`. If the line being skipped ends
+    /// within those bytes, then the skipped line is recorded, a line
+    /// terminator is written at `self.end` to stand in for it (so that line
+    /// counting of subsequent lines is unaffected) and the bytes following
+    /// the skipped line are shifted down to directly follow the stand-in.
+    /// The number of bytes at `self.end` that should be treated as newly
+    /// read data is returned. If the line being skipped does not end within
+    /// the bytes given, then everything is discarded and `0` is returned.
+    fn discard_skipped(&mut self, readlen: usize) -> usize {
+        let newbytes = &self.buf[self.end..self.end + readlen];
+        match newbytes.find_byte(self.config.lineterm) {
+            None => {
+                self.skipping.as_mut().unwrap().1 += readlen as u64;
+                0
+            }
+            Some(i) => {
+                let (offset, len) = self.skipping.take().unwrap();
+                self.skipped_lines.push((offset, len + i as u64));
+                self.buf[self.end] = self.config.lineterm;
+                self.buf.copy_within(
+                    self.end + i + 1..self.end + readlen,
+                    self.end + 1,
+                );
+                1 + (readlen - (i + 1))
+            }
+        }
+    }
+
     /// Roll the unconsumed parts of the buffer to the front.
     ///
     /// This operation is idempotent.
@@ -478,6 +644,10 @@ impl LineBuffer {
     /// After rolling, `last_lineterm` and `end` point to the same location,
     /// and `pos` is always set to `0`.
     fn roll(&mut self) {
+        let idx = self
+            .convert_offsets
+            .partition_point(|&offset| offset < self.absolute_byte_offset);
+        self.convert_offsets.drain(..idx);
         if self.pos == self.end {
             self.pos = 0;
             self.last_lineterm = 0;
@@ -523,23 +693,34 @@ impl LineBuffer {
 }
 
 /// Replaces `src` with `replacement` in bytes, and return the offset of the
-/// first replacement, if one exists.
+/// first replacement, if one exists. The absolute offset (that is, `base`
+/// plus the position within `bytes`) of every replacement made is pushed
+/// onto `offsets`.
 fn replace_bytes(
     mut bytes: &mut [u8],
     src: u8,
     replacement: u8,
+    base: u64,
+    offsets: &mut Vec<u64>,
 ) -> Option<usize> {
     if src == replacement {
         return None;
     }
+    let mut pos = 0;
     let first_pos = bytes.find_byte(src)?;
     bytes[first_pos] = replacement;
+    offsets.push(base + first_pos as u64);
+    pos += first_pos + 1;
     bytes = &mut bytes[first_pos + 1..];
     while let Some(i) = bytes.find_byte(src) {
         bytes[i] = replacement;
+        offsets.push(base + (pos + i) as u64);
+        pos += i + 1;
         bytes = &mut bytes[i + 1..];
         while bytes.get(0) == Some(&src) {
             bytes[0] = replacement;
+            offsets.push(base + pos as u64);
+            pos += 1;
             bytes = &mut bytes[1..];
         }
     }
@@ -569,19 +750,35 @@ and exhibited clearly, with a label attached.\
         slice: &str,
         src: u8,
         replacement: u8,
-    ) -> (String, Option<usize>) {
+    ) -> (String, Option<usize>, Vec<u64>) {
         let mut dst = Vec::from(slice);
-        let result = replace_bytes(&mut dst, src, replacement);
-        (dst.into_string().unwrap(), result)
+        let mut offsets = vec![];
+        let result = replace_bytes(&mut dst, src, replacement, 0, &mut offsets);
+        (dst.into_string().unwrap(), result, offsets)
     }
 
     #[test]
     fn replace() {
-        assert_eq!(replace_str("abc", b'b', b'z'), (s("azc"), Some(1)));
-        assert_eq!(replace_str("abb", b'b', b'z'), (s("azz"), Some(1)));
-        assert_eq!(replace_str("aba", b'a', b'z'), (s("zbz"), Some(0)));
-        assert_eq!(replace_str("bbb", b'b', b'z'), (s("zzz"), Some(0)));
-        assert_eq!(replace_str("bac", b'b', b'z'), (s("zac"), Some(0)));
+        assert_eq!(
+            replace_str("abc", b'b', b'z'),
+            (s("azc"), Some(1), vec![1])
+        );
+        assert_eq!(
+            replace_str("abb", b'b', b'z'),
+            (s("azz"), Some(1), vec![1, 2])
+        );
+        assert_eq!(
+            replace_str("aba", b'a', b'z'),
+            (s("zbz"), Some(0), vec![0, 2])
+        );
+        assert_eq!(
+            replace_str("bbb", b'b', b'z'),
+            (s("zzz"), Some(0), vec![0, 1, 2])
+        );
+        assert_eq!(
+            replace_str("bac", b'b', b'z'),
+            (s("zac"), Some(0), vec![0])
+        );
     }
 
     #[test]
@@ -766,6 +963,76 @@ and exhibited clearly, with a label attached.\
         assert_eq!(rdr.bstr(), "");
     }
 
+    #[test]
+    fn buffer_max_line_len() {
+        let bytes = "homer\naaaaaaaaaaaaaaaaaaaaaaaaa\nmaggie\n";
+        let mut linebuf = LineBufferBuilder::new()
+            .capacity(8)
+            .max_line_len(Some(10))
+            .build();
+        let mut rdr = LineBufferReader::new(bytes.as_bytes(), &mut linebuf);
+
+        let mut got = vec![];
+        let mut skipped = vec![];
+        while rdr.fill().unwrap() {
+            got.push_str(rdr.buffer());
+            skipped.extend_from_slice(rdr.skipped_lines());
+            rdr.consume_all();
+        }
+        skipped.extend_from_slice(rdr.skipped_lines());
+        // The skipped line is replaced by an empty line so that subsequent
+        // lines keep their line numbers.
+        assert_eq!("homer\n\nmaggie\n", got.as_bstr());
+        assert_eq!(vec![(6, 25)], skipped);
+    }
+
+    #[test]
+    fn buffer_max_line_len_at_eof() {
+        let bytes = "homer\naaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+        let mut linebuf = LineBufferBuilder::new()
+            .capacity(8)
+            .max_line_len(Some(10))
+            .build();
+        let mut rdr = LineBufferReader::new(bytes.as_bytes(), &mut linebuf);
+
+        let mut got = vec![];
+        let mut skipped = vec![];
+        while rdr.fill().unwrap() {
+            got.push_str(rdr.buffer());
+            skipped.extend_from_slice(rdr.skipped_lines());
+            rdr.consume_all();
+        }
+        skipped.extend_from_slice(rdr.skipped_lines());
+        assert_eq!("homer\n", got.as_bstr());
+        assert_eq!(vec![(6, 30)], skipped);
+    }
+
+    #[test]
+    fn buffer_max_line_len_bounded_alloc() {
+        // A long line must never cause the buffer to grow to fit it. The
+        // limit here is less than the capacity, so the buffer should never
+        // grow at all.
+        let mut bytes = "homer\n".to_string();
+        bytes.push_str(&"a".repeat(1 << 20));
+        bytes.push_str("\nmaggie\n");
+        let mut linebuf = LineBufferBuilder::new()
+            .capacity(4096)
+            .max_line_len(Some(100))
+            .build();
+        let mut rdr = LineBufferReader::new(bytes.as_bytes(), &mut linebuf);
+
+        let mut got = vec![];
+        let mut skipped = vec![];
+        while rdr.fill().unwrap() {
+            got.push_str(rdr.buffer());
+            skipped.extend_from_slice(rdr.skipped_lines());
+            rdr.consume_all();
+        }
+        assert_eq!("homer\n\nmaggie\n", got.as_bstr());
+        assert_eq!(vec![(6, 1 << 20)], skipped);
+        assert_eq!(4096, rdr.line_buffer.buf.len());
+    }
+
     #[test]
     fn buffer_binary_none() {
         let bytes = "homer\nli\x00sa\nmaggie\n";
@@ -26,10 +26,13 @@ pub(crate) struct Core<'s, M: 's, S> {
     binary: bool,
     pos: usize,
     absolute_byte_offset: u64,
+    start_offset: u64,
     binary_byte_offset: Option<usize>,
     line_number: Option<u64>,
     last_line_counted: usize,
     last_line_visited: usize,
+    convert_offsets: Vec<u64>,
+    first_convert_offset: Option<u64>,
     after_context_left: usize,
     has_sunk: bool,
     has_matched: bool,
@@ -42,8 +45,19 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
         sink: S,
         binary: bool,
     ) -> Core<'s, M, S> {
-        let line_number =
-            if searcher.config.line_number { Some(1) } else { None };
+        // When searching begins at a non-zero offset, the number of
+        // preceding lines is unknowable, so no line numbers are reported
+        // unless the caller has provided the starting line number itself.
+        let start_offset = searcher.skipped_byte_count.get();
+        let line_number = if !searcher.config.line_number {
+            None
+        } else if let Some(n) = searcher.config.start_line_number {
+            Some(n)
+        } else if start_offset > 0 {
+            None
+        } else {
+            Some(1)
+        };
         let core = Core {
             config: &searcher.config,
             matcher,
@@ -51,15 +65,19 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
             sink,
             binary,
             pos: 0,
-            absolute_byte_offset: 0,
+            absolute_byte_offset: start_offset,
+            start_offset,
             binary_byte_offset: None,
             line_number,
             last_line_counted: 0,
             last_line_visited: 0,
+            convert_offsets: vec![],
+            first_convert_offset: None,
             after_context_left: 0,
             has_sunk: false,
             has_matched: false,
         };
+        searcher.set_after_context_remaining(0);
         if !core.searcher.multi_line_with_matcher(&core.matcher) {
             if core.is_line_by_line_fast() {
                 log::trace!("searcher core: will use fast line searcher");
@@ -78,6 +96,13 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
         self.pos = pos;
     }
 
+    /// Sets the number of "after" context lines still owed, mirroring the
+    /// value on the searcher so that sinks can query it.
+    fn set_after_context_left(&mut self, left: usize) {
+        self.after_context_left = left;
+        self.searcher.set_after_context_remaining(left as u64);
+    }
+
     pub(crate) fn binary_byte_offset(&self) -> Option<u64> {
         self.binary_byte_offset.map(|offset| offset as u64)
     }
@@ -98,7 +123,22 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
         &mut self,
         binary_byte_offset: u64,
     ) -> Result<bool, S::Error> {
-        self.sink.binary_data(&self.searcher, binary_byte_offset)
+        self.sink.binary_data(
+            &self.searcher,
+            self.start_offset + binary_byte_offset,
+        )
+    }
+
+    pub(crate) fn line_too_long(
+        &mut self,
+        absolute_byte_offset: u64,
+        len: u64,
+    ) -> Result<bool, S::Error> {
+        self.sink.line_too_long(
+            &self.searcher,
+            self.start_offset + absolute_byte_offset,
+            len,
+        )
     }
 
     pub(crate) fn begin(&mut self) -> Result<bool, S::Error> {
@@ -112,7 +152,13 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
     ) -> Result<(), S::Error> {
         self.sink.finish(
             &self.searcher,
-            &SinkFinish { byte_count, binary_byte_offset },
+            &SinkFinish {
+                byte_count,
+                binary_byte_offset: binary_byte_offset
+                    .map(|offset| self.start_offset + offset),
+                decoded: self.searcher.decoded(),
+                compressed_byte_count: self.searcher.compressed_byte_count(),
+            },
         )
     }
 
@@ -472,15 +518,19 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
                 bytes: linebuf,
                 absolute_byte_offset: offset,
                 line_number: self.line_number,
+                approximate_line_number: self
+                    .first_convert_offset
+                    .map_or(false, |o| o < offset),
                 buffer: buf,
                 bytes_range_in_buffer: range.start()..range.end(),
+                is_context_extension: self.after_context_left >= 1,
             },
         )?;
         if !keepgoing {
             return Ok(false);
         }
         self.last_line_visited = range.end();
-        self.after_context_left = self.config.after_context;
+        self.set_after_context_left(self.config.after_context);
         self.has_sunk = true;
         Ok(true)
     }
@@ -541,7 +591,7 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
             return Ok(false);
         }
         self.last_line_visited = range.end();
-        self.after_context_left -= 1;
+        self.set_after_context_left(self.after_context_left - 1);
         self.has_sunk = true;
         Ok(true)
     }
@@ -590,18 +640,47 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
         }
     }
 
+    /// Set the absolute byte offsets at which binary bytes were converted to
+    /// line terminators in the current buffer. Converted bytes do not create
+    /// new lines, so line counting subtracts them from the terminator count.
+    pub(crate) fn set_convert_offsets(&mut self, offsets: &[u64]) {
+        if self.first_convert_offset.is_none() {
+            self.first_convert_offset = offsets.first().copied();
+        }
+        self.convert_offsets.clear();
+        self.convert_offsets.extend_from_slice(offsets);
+    }
+
     fn count_lines(&mut self, buf: &[u8], upto: usize) {
-        if let Some(ref mut line_number) = self.line_number {
+        if let Some(mut line_number) = self.line_number {
             if self.last_line_counted >= upto {
                 return;
             }
             let slice = &buf[self.last_line_counted..upto];
             let count = lines::count(slice, self.config.line_term.as_byte());
-            *line_number += count;
+            line_number += count;
+            line_number -= self.convert_count(self.last_line_counted, upto);
+            self.line_number = Some(line_number);
             self.last_line_counted = upto;
         }
     }
 
+    /// Returns the number of line terminators in the given buffer range that
+    /// exist only because a binary byte was converted to a line terminator.
+    ///
+    /// The range bounds are relative to the current buffer, i.e., the same
+    /// coordinate space as `last_line_counted`.
+    fn convert_count(&self, start: usize, end: usize) -> u64 {
+        if self.convert_offsets.is_empty() {
+            return 0;
+        }
+        let start = self.absolute_byte_offset + start as u64;
+        let end = self.absolute_byte_offset + end as u64;
+        let i = self.convert_offsets.partition_point(|&o| o < start);
+        let j = self.convert_offsets.partition_point(|&o| o < end);
+        (j - i) as u64
+    }
+
     fn is_line_by_line_fast(&self) -> bool {
         debug_assert!(!self.searcher.multi_line_with_matcher(&self.matcher));
 
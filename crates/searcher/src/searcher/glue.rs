@@ -57,6 +57,15 @@ where
             Err(err) => return Err(S::Error::error_io(err)),
             Ok(didread) => didread,
         };
+        // Converted binary bytes must not be counted as new lines, so tell
+        // the core where conversions happened in the current buffer.
+        self.core.set_convert_offsets(self.rdr.convert_offsets());
+        for i in 0..self.rdr.skipped_lines().len() {
+            let (offset, len) = self.rdr.skipped_lines()[i];
+            if !self.core.line_too_long(offset, len)? {
+                return Ok(false);
+            }
+        }
         if !already_binary {
             if let Some(offset) = self.rdr.binary_byte_offset() {
                 if !self.core.binary_data(offset)? {
@@ -135,6 +144,7 @@ pub(crate) struct MultiLine<'s, M, S> {
     core: Core<'s, M, S>,
     slice: &'s [u8],
     last_match: Option<Range>,
+    span_limit: Option<usize>,
 }
 
 impl<'s, M: Matcher, S: Sink> MultiLine<'s, M, S> {
@@ -146,11 +156,13 @@ impl<'s, M: Matcher, S: Sink> MultiLine<'s, M, S> {
     ) -> MultiLine<'s, M, S> {
         debug_assert!(searcher.multi_line_with_matcher(&matcher));
 
+        let span_limit = matcher.multiline_span_limit();
         MultiLine {
             config: &searcher.config,
             core: Core::new(searcher, matcher, write_to, true),
             slice,
             last_match: None,
+            span_limit,
         }
     }
 
@@ -319,7 +331,21 @@ impl<'s, M: Matcher, S: Sink> MultiLine<'s, M, S> {
         match self.core.matcher().find(&self.slice[self.core.pos()..]) {
             Err(err) => Err(S::Error::error_message(err)),
             Ok(None) => Ok(None),
-            Ok(Some(m)) => Ok(Some(m.offset(self.core.pos()))),
+            Ok(Some(m)) => {
+                let m = m.offset(self.core.pos());
+                // When the matcher requests a span limit, matches longer
+                // than the limit are refused by erroring out instead of
+                // being silently truncated.
+                if let Some(limit) = self.span_limit {
+                    if m.len() > limit {
+                        return Err(S::Error::error_message(format!(
+                            "line span limit exceeded at offset {}",
+                            m.start(),
+                        )));
+                    }
+                }
+                Ok(Some(m))
+            }
         }
     }
 
@@ -780,6 +806,44 @@ d
             .test();
     }
 
+    #[test]
+    fn binary_convert_line_numbers1() {
+        // Converted NUL bytes split lines in the buffer, but they do not
+        // create new lines: line numbers count only the configured line
+        // terminator.
+        let haystack = "a\x00b\x00c\nd\n";
+        let matcher = RegexMatcher::new("d");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new()
+            .binary_detection(BinaryDetection::convert(0))
+            .line_number(true)
+            .build();
+        searcher
+            .search_reader(&matcher, haystack.as_bytes(), &mut sink)
+            .unwrap();
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("2~:6:d\n\nbyte count:8\nbinary offset:1\n", got);
+    }
+
+    #[test]
+    fn binary_convert_line_numbers2() {
+        // All three matches here are on "lines" produced by conversion, so
+        // they all report the same line number.
+        let haystack = "a\x00a\x00a\nb\n";
+        let matcher = RegexMatcher::new("a");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new()
+            .binary_detection(BinaryDetection::convert(0))
+            .line_number(true)
+            .build();
+        searcher
+            .search_reader(&matcher, haystack.as_bytes(), &mut sink)
+            .unwrap();
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        let exp = "1:0:a\n1~:2:a\n1~:4:a\n\nbyte count:8\nbinary offset:1\n";
+        assert_eq!(exp, got);
+    }
+
     #[test]
     fn passthru_sherlock1() {
         let exp = "\
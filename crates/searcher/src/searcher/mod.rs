@@ -1,9 +1,10 @@
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     cmp,
     fs::File,
     io::{self, Read},
     path::Path,
+    sync::Arc,
 };
 
 use {
@@ -12,6 +13,7 @@ use {
 };
 
 use crate::{
+    decompress::DecompressionRegistry,
     line_buffer::{
         self, alloc_error, BufferAllocation, LineBuffer, LineBufferBuilder,
         LineBufferReader, DEFAULT_BUFFER_CAPACITY,
@@ -20,7 +22,7 @@ use crate::{
     sink::{Sink, SinkError},
 };
 
-pub use self::mmap::MmapChoice;
+pub use self::mmap::{MmapAdvice, MmapChoice};
 
 mod core;
 mod glue;
@@ -90,6 +92,10 @@ impl BinaryDetection {
     /// byte and replaced with the line terminator. In effect, the caller is
     /// guaranteed to never observe this byte while searching.
     ///
+    /// Converted bytes do not create new lines. That is, when line numbers
+    /// are reported, they count only occurrences of the configured line
+    /// terminator in the original data.
+    ///
     /// When searching is performed with the entire contents mapped into
     /// memory, then this setting has no effect and is ignored.
     pub fn convert(binary_byte: u8) -> BinaryDetection {
@@ -145,6 +151,89 @@ impl Encoding {
     }
 }
 
+/// A summary of a searcher's effective configuration.
+///
+/// A summary is created via [`Searcher::config_summary`]. It is principally
+/// useful for logging the settings in effect for a particular search, e.g.,
+/// to make search results reproducible. To that end, every field is a plain
+/// value that is cheap to compare and print.
+///
+/// Note that settings that are resolved on a per-haystack basis are reported
+/// as configured and not as resolved. For example, when memory maps are set
+/// to be used automatically, the summary reports `auto` since the actual
+/// decision is made separately for each haystack searched.
+///
+/// When the `serde` feature is enabled, this type implements
+/// `serde::Serialize`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearcherConfigSummary {
+    /// The line terminator, in ASCII-escaped form. e.g., `\n` or `\r\n`.
+    pub line_terminator: String,
+    /// Whether matching is inverted.
+    pub invert_match: bool,
+    /// The number of lines of context reported after a match.
+    pub after_context: usize,
+    /// The number of lines of context reported before a match.
+    pub before_context: usize,
+    /// Whether unbounded context ("passthru") is enabled.
+    pub passthru: bool,
+    /// Whether line numbers are counted.
+    pub line_number: bool,
+    /// The maximum amount of heap memory to use, if any.
+    pub heap_limit: Option<usize>,
+    /// The maximum length of a line, in bytes, if a limit was set.
+    pub max_line_len: Option<usize>,
+    /// The memory map strategy. This is one of `auto` or `never`. Since the
+    /// `auto` strategy is resolved for each haystack individually, it is
+    /// reported as the tri-state it is instead of being flattened into
+    /// whether a memory map is used or not.
+    pub memory_map: &'static str,
+    /// The binary detection strategy. This is one of `none`, `quit` or
+    /// `convert`.
+    pub binary_detection: &'static str,
+    /// The byte used by the `quit` or `convert` binary detection strategies,
+    /// if one of them is in use.
+    pub binary_byte: Option<u8>,
+    /// Whether matching across multiple lines is enabled.
+    pub multi_line: bool,
+    /// The name of the encoding that all input is transcoded from, if an
+    /// encoding was explicitly configured.
+    pub encoding: Option<String>,
+    /// Whether automatic transcoding based on a BOM is enabled.
+    pub bom_sniffing: bool,
+    /// Whether the search stops at the first non-matching line after a
+    /// matching line.
+    pub stop_on_nonmatch: bool,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SearcherConfigSummary {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        s: S,
+    ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = s.serialize_struct("SearcherConfigSummary", 15)?;
+        state.serialize_field("line_terminator", &self.line_terminator)?;
+        state.serialize_field("invert_match", &self.invert_match)?;
+        state.serialize_field("after_context", &self.after_context)?;
+        state.serialize_field("before_context", &self.before_context)?;
+        state.serialize_field("passthru", &self.passthru)?;
+        state.serialize_field("line_number", &self.line_number)?;
+        state.serialize_field("heap_limit", &self.heap_limit)?;
+        state.serialize_field("max_line_len", &self.max_line_len)?;
+        state.serialize_field("memory_map", &self.memory_map)?;
+        state.serialize_field("binary_detection", &self.binary_detection)?;
+        state.serialize_field("binary_byte", &self.binary_byte)?;
+        state.serialize_field("multi_line", &self.multi_line)?;
+        state.serialize_field("encoding", &self.encoding)?;
+        state.serialize_field("bom_sniffing", &self.bom_sniffing)?;
+        state.serialize_field("stop_on_nonmatch", &self.stop_on_nonmatch)?;
+        state.end()
+    }
+}
+
 /// The internal configuration of a searcher. This is shared among several
 /// search related types, but is only ever written to by the SearcherBuilder.
 #[derive(Clone, Debug)]
@@ -166,8 +255,17 @@ pub struct Config {
     /// When not given, no explicit limit is enforced. When set to `0`, then
     /// only the memory map search strategy is available.
     heap_limit: Option<usize>,
+    /// The maximum length of a line, in bytes. Lines longer than this are
+    /// skipped instead of being searched.
+    max_line_len: Option<usize>,
     /// The memory map strategy.
     mmap: MmapChoice,
+    /// The advice given to the OS about the access pattern of memory mapped
+    /// searches.
+    mmap_advice: MmapAdvice,
+    /// The initial capacity, in bytes, of the line buffer used for
+    /// incremental searches over a reader.
+    buffer_capacity: usize,
     /// The binary data detection strategy.
     binary: BinaryDetection,
     /// Whether to enable matching across multiple lines.
@@ -180,6 +278,18 @@ pub struct Config {
     /// Whether to stop searching when a non-matching line is found after a
     /// matching line.
     stop_on_nonmatch: bool,
+    /// The absolute byte offset at which to begin searching.
+    start_offset: u64,
+    /// The line number to report for the first line searched, when line
+    /// numbers are enabled and a starting line number is known.
+    start_line_number: Option<u64>,
+    /// Whether to round a non-zero starting offset forward to the next line
+    /// boundary.
+    round_start_offset: bool,
+    /// A registry of in-process decompressors that, when present, is
+    /// consulted by `search_path` to transparently decompress matching
+    /// files before searching them.
+    decompression: Option<Arc<DecompressionRegistry>>,
 }
 
 impl Default for Config {
@@ -192,12 +302,19 @@ impl Default for Config {
             passthru: false,
             line_number: true,
             heap_limit: None,
+            max_line_len: None,
             mmap: MmapChoice::default(),
+            mmap_advice: MmapAdvice::default(),
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
             binary: BinaryDetection::default(),
             multi_line: false,
             encoding: None,
             bom_sniffing: true,
             stop_on_nonmatch: false,
+            start_offset: 0,
+            start_line_number: None,
+            round_start_offset: false,
+            decompression: None,
         }
     }
 }
@@ -216,17 +333,20 @@ impl Config {
         let mut builder = LineBufferBuilder::new();
         builder
             .line_terminator(self.line_term.as_byte())
-            .binary_detection(self.binary.0);
+            .binary_detection(self.binary.0)
+            .max_line_len(self.max_line_len);
 
         if let Some(limit) = self.heap_limit {
-            let (capacity, additional) = if limit <= DEFAULT_BUFFER_CAPACITY {
+            let (capacity, additional) = if limit <= self.buffer_capacity {
                 (limit, 0)
             } else {
-                (DEFAULT_BUFFER_CAPACITY, limit - DEFAULT_BUFFER_CAPACITY)
+                (self.buffer_capacity, limit - self.buffer_capacity)
             };
             builder
                 .capacity(capacity)
                 .buffer_alloc(BufferAllocation::Error(additional));
+        } else {
+            builder.capacity(self.buffer_capacity);
         }
         builder.build()
     }
@@ -330,6 +450,10 @@ impl SearcherBuilder {
             decode_buffer: RefCell::new(vec![0; 8 * (1 << 10)]),
             line_buffer: RefCell::new(self.config.line_buffer()),
             multi_line_buffer: RefCell::new(vec![]),
+            after_context_remaining: Cell::new(0),
+            decoded: Cell::new(false),
+            compressed: Cell::new(None),
+            skipped_byte_count: Cell::new(0),
         }
     }
 
@@ -459,6 +583,41 @@ impl SearcherBuilder {
         self
     }
 
+    /// Set the maximum length of a line, in bytes.
+    ///
+    /// When set, a line longer than this limit is not searched. Instead, its
+    /// bytes are discarded as they are read, without buffering the line, and
+    /// the event is reported to the sink via [`Sink::line_too_long`] along
+    /// with the line's offset and length. Searching then continues with the
+    /// next line, and line numbers of subsequent lines are unaffected.
+    ///
+    /// This limit is the searcher's defense against pathological inputs such
+    /// as a single multi-gigabyte line: without it, the internal line buffer
+    /// must grow to fit the longest line in the haystack. With it, the buffer
+    /// is never grown to accommodate a single long line.
+    ///
+    /// There are some caveats to be aware of:
+    ///
+    /// * The limit only applies to incremental searches over a reader. It has
+    ///   no effect when searching via a memory map or a caller provided
+    ///   slice, since the haystack is then already fully in memory.
+    /// * The limit is enforced as data is buffered. A line that exceeds the
+    ///   limit but happens to become fully buffered in a single refill may
+    ///   still be searched.
+    /// * A skipped line is replaced by an empty line, so that line counting
+    ///   is unaffected. Byte offsets reported by the searcher after a skipped
+    ///   line count the skipped line as that single empty line, and binary
+    ///   detection is not applied to the discarded bytes.
+    ///
+    /// By default, no limit is set.
+    pub fn max_line_len(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut SearcherBuilder {
+        self.config.max_line_len = limit;
+        self
+    }
+
     /// Set the strategy to employ use of memory maps.
     ///
     /// Currently, there are only two strategies that can be employed:
@@ -497,6 +656,39 @@ impl SearcherBuilder {
         self
     }
 
+    /// Set the advice given to the operating system about the access pattern
+    /// of memory mapped searches.
+    ///
+    /// This has no effect unless memory maps are enabled via `memory_map`
+    /// and actually used for a search. See [`MmapAdvice`] for the details,
+    /// including how to measure its effect.
+    ///
+    /// By default, mappings are advised as sequentially accessed and are
+    /// never eagerly populated.
+    pub fn mmap_advice(&mut self, advice: MmapAdvice) -> &mut SearcherBuilder {
+        self.config.mmap_advice = advice;
+        self
+    }
+
+    /// Set the initial capacity, in bytes, of the line buffer used for
+    /// incremental searches over a reader.
+    ///
+    /// The buffer grows as needed to fit the longest line in the haystack
+    /// (subject to `heap_limit` and `max_line_len`), so this only tunes how
+    /// much is read per `read` call before any growing happens. A bigger
+    /// buffer can reduce syscall overhead on fast storage at the cost of
+    /// memory, since one buffer is alive per searcher.
+    ///
+    /// This has no effect on searches via a memory map or a caller provided
+    /// slice. When a `heap_limit` smaller than this capacity is set, the
+    /// heap limit wins.
+    ///
+    /// The default capacity is 64 KiB.
+    pub fn buffer_capacity(&mut self, bytes: usize) -> &mut SearcherBuilder {
+        self.config.buffer_capacity = bytes;
+        self
+    }
+
     /// Set the binary detection strategy.
     ///
     /// The binary detection strategy determines not only how the searcher
@@ -564,6 +756,96 @@ impl SearcherBuilder {
         self.config.stop_on_nonmatch = stop_on_nonmatch;
         self
     }
+
+    /// Set the absolute byte offset at which to begin searching.
+    ///
+    /// When set to a non-zero value, the searcher skips the first `offset`
+    /// bytes of the haystack before searching, while continuing to report
+    /// absolute byte offsets relative to the beginning of the haystack. This
+    /// makes it possible to search, for example, only the tail of a huge log
+    /// file and still report offsets that agree with the file itself. The
+    /// skipped bytes are not included in the byte count reported to
+    /// [`SinkFinish`](crate::SinkFinish).
+    ///
+    /// The offset may point into the middle of a line, in which case the
+    /// first line searched is truncated. To begin searching at the first
+    /// line boundary at or after the offset instead, enable
+    /// [`round_start_offset`](SearcherBuilder::round_start_offset).
+    ///
+    /// Since the searcher cannot know how many lines precede the starting
+    /// offset, searches that begin at a non-zero offset report no line
+    /// numbers unless a starting line number is provided via
+    /// [`start_line_number`](SearcherBuilder::start_line_number).
+    ///
+    /// When the haystack is decoded before searching (because of transcoding
+    /// or decompression), the offset refers to the decoded data.
+    ///
+    /// By default, this is `0`: searching begins at the start of the
+    /// haystack.
+    pub fn start_offset(&mut self, offset: u64) -> &mut SearcherBuilder {
+        self.config.start_offset = offset;
+        self
+    }
+
+    /// Set the line number of the first line searched.
+    ///
+    /// This is chiefly useful in combination with
+    /// [`start_offset`](SearcherBuilder::start_offset): a caller that knows
+    /// how many lines precede the starting offset can provide the line
+    /// number of the first line at or after it, and the searcher will report
+    /// line numbers as if the entire haystack had been searched. When
+    /// searching begins at a non-zero offset and no starting line number is
+    /// given, no line numbers are reported.
+    ///
+    /// This has no effect when line numbers are disabled.
+    ///
+    /// By default, this is `None`.
+    pub fn start_line_number(
+        &mut self,
+        line_number: Option<u64>,
+    ) -> &mut SearcherBuilder {
+        self.config.start_line_number = line_number;
+        self
+    }
+
+    /// Whether to round a non-zero starting offset forward to the next line
+    /// boundary.
+    ///
+    /// When enabled and the configured
+    /// [`start_offset`](SearcherBuilder::start_offset) points into the
+    /// middle of a line, the remainder of that line is skipped and searching
+    /// begins at the first complete line. When the offset already falls on a
+    /// line boundary, nothing extra is skipped. Byte offsets reported to the
+    /// sink account for the extra skipped bytes.
+    ///
+    /// This is disabled by default.
+    pub fn round_start_offset(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.round_start_offset = yes;
+        self
+    }
+
+    /// Set a registry of in-process decompressors.
+    ///
+    /// When set, files searched via [`Searcher::search_path`] whose
+    /// extension or magic bytes match a registered decompressor are
+    /// transparently decompressed before being searched. Binary detection,
+    /// line numbers and all byte counts and offsets reported to the sink
+    /// refer to the decompressed stream; the size of the compressed file is
+    /// reported separately via
+    /// [`SinkFinish::compressed_byte_count`](crate::SinkFinish::compressed_byte_count).
+    ///
+    /// Since decompressed data has to be read as a stream, memory maps are
+    /// never used for decompressed files, and multi line searches of them
+    /// read the entire decompressed contents on to the heap.
+    ///
+    /// This is not set by default.
+    pub fn decompression(
+        &mut self,
+        registry: Option<Arc<DecompressionRegistry>>,
+    ) -> &mut SearcherBuilder {
+        self.config.decompression = registry;
+        self
+    }
 }
 
 /// A searcher executes searches over a haystack and writes results to a caller
@@ -602,6 +884,33 @@ pub struct Searcher {
     /// performed incrementally, and need the entire haystack in memory at
     /// once.
     multi_line_buffer: RefCell<Vec<u8>>,
+    /// The number of "after" context lines still owed from the most recently
+    /// reported match in the search currently executing.
+    ///
+    /// This is stored in a `Cell` (like the buffers above, the `Searcher`
+    /// is lent out immutably to sinks) and updated by the search
+    /// implementation so that `Sink` implementations can query it.
+    after_context_remaining: Cell<u64>,
+    /// Whether the search currently executing decodes its source data,
+    /// either because an encoding was explicitly configured or because a
+    /// byte-order mark was found.
+    ///
+    /// This is set at the start of each search and reported to sinks via
+    /// `SinkFinish::decoded`.
+    decoded: Cell<bool>,
+    /// The size, in bytes, of the compressed file being searched, when the
+    /// search currently executing reads through an in-process decompressor.
+    ///
+    /// This is set by `search_path` around the search of a decompressed file
+    /// and reported to sinks via `SinkFinish::compressed_byte_count`.
+    compressed: Cell<Option<u64>>,
+    /// The total number of bytes skipped at the beginning of the haystack by
+    /// the search currently executing, when a starting offset is configured.
+    ///
+    /// This includes any bytes skipped to round the starting offset forward
+    /// to a line boundary, and is capped at the haystack length. It is used
+    /// as the base for the absolute byte offsets reported to sinks.
+    skipped_byte_count: Cell<u64>,
 }
 
 impl Searcher {
@@ -633,6 +942,25 @@ impl Searcher {
     {
         let path = path.as_ref();
         let file = File::open(path).map_err(S::Error::error_io)?;
+        if let Some(registry) = self.config.decompression.clone() {
+            match registry.reader(path, file).map_err(S::Error::error_io)? {
+                Ok((rdr, compressed_len)) => {
+                    log::trace!("{:?}: searching via decompressor", path);
+                    self.compressed.set(Some(compressed_len));
+                    let result = self.search_reader(matcher, rdr, write_to);
+                    self.compressed.set(None);
+                    return result;
+                }
+                Err(file) => {
+                    return self.search_file_maybe_path(
+                        matcher,
+                        Some(path),
+                        &file,
+                        write_to,
+                    );
+                }
+            }
+        }
         self.search_file_maybe_path(matcher, Some(path), &file, write_to)
     }
 
@@ -668,17 +996,25 @@ impl Searcher {
     {
         if let Some(mmap) = self.config.mmap.open(file, path) {
             log::trace!("{:?}: searching via memory map", path);
+            self.config.mmap_advice.advise(&mmap);
             return self.search_slice(matcher, &mmap, write_to);
         }
         // Fast path for multi-line searches of files when memory maps are not
         // enabled. This pre-allocates a buffer roughly the size of the file,
         // which isn't possible when searching an arbitrary std::io::Read.
-        if self.multi_line_with_matcher(&matcher) {
+        // (When a starting offset is configured, the generic reader path is
+        // used instead, since it knows how to skip over the offset.)
+        if self.multi_line_with_matcher(&matcher)
+            && self.config.start_offset == 0
+        {
             log::trace!(
                 "{:?}: reading entire file on to heap for mulitline",
                 path
             );
-            self.fill_multi_line_buffer_from_file::<S>(file)?;
+            self.fill_multi_line_buffer_from_file::<S>(
+                file,
+                matcher.multiline_span_limit(),
+            )?;
             log::trace!("{:?}: searching via multiline strategy", path);
             MultiLine::new(
                 self,
@@ -704,6 +1040,12 @@ impl Searcher {
     /// one should try to use higher level APIs (e.g., searching by file or
     /// file path) so that memory maps can be used if they are available and
     /// enabled.
+    ///
+    /// If the source data is decoded before searching (because an encoding
+    /// was configured or a byte-order mark was found), then all byte counts
+    /// and absolute byte offsets reported to the sink refer to the decoded
+    /// data and not the source bytes. See
+    /// [`SinkFinish::decoded`](crate::SinkFinish::decoded).
     pub fn search_reader<M, R, S>(
         &mut self,
         matcher: M,
@@ -717,17 +1059,27 @@ impl Searcher {
     {
         self.check_config(&matcher).map_err(S::Error::error_config)?;
 
+        let (decoded, read_from) =
+            self.reader_needs_transcoding::<_, S>(read_from)?;
+        self.decoded.set(decoded);
+
         let mut decode_buffer = self.decode_buffer.borrow_mut();
         let decoder = self
             .decode_builder
             .build_with_buffer(read_from, &mut *decode_buffer)
             .map_err(S::Error::error_io)?;
+        // The starting offset is skipped after decoding, so that it refers
+        // to the same data as the offsets reported to the sink.
+        let decoder = self.skip_start_offset_reader::<_, S>(decoder)?;
 
         if self.multi_line_with_matcher(&matcher) {
             log::trace!(
                 "generic reader: reading everything to heap for multiline"
             );
-            self.fill_multi_line_buffer_from_reader::<_, S>(decoder)?;
+            self.fill_multi_line_buffer_from_reader::<_, S>(
+                decoder,
+                matcher.multiline_span_limit(),
+            )?;
             log::trace!("generic reader: searching via multiline strategy");
             MultiLine::new(
                 self,
@@ -759,12 +1111,16 @@ impl Searcher {
         self.check_config(&matcher).map_err(S::Error::error_config)?;
 
         // We can search the slice directly, unless we need to do transcoding.
+        // (The generic reader handles the starting offset itself, after
+        // decoding.)
         if self.slice_needs_transcoding(slice) {
             log::trace!(
                 "slice reader: needs transcoding, using generic reader"
             );
             return self.search_reader(matcher, slice, write_to);
         }
+        let slice = self.skip_start_offset_slice(slice);
+        self.decoded.set(false);
         if self.multi_line_with_matcher(&matcher) {
             log::trace!("slice reader: searching via multiline strategy");
             MultiLine::new(self, matcher, slice, write_to).run()
@@ -774,6 +1130,43 @@ impl Searcher {
         }
     }
 
+    /// Execute a search over a list of non-contiguous slices, as if they
+    /// were one contiguous buffer, and write the results to the given sink.
+    ///
+    /// Absolute byte offsets reported to the sink are offsets into the
+    /// logical concatenation of the slices. Lines and matches that straddle
+    /// a slice boundary are stitched together in the searcher's internal
+    /// line buffer, so the extra memory used is bounded by the longest line
+    /// (or the heap limit, if one was set) rather than the total length of
+    /// the slices.
+    ///
+    /// The exception is multi line search, which cannot be done
+    /// incrementally. When multi line search is enabled, the slices are
+    /// concatenated on the heap before searching begins, just as with
+    /// [`search_reader`](Searcher::search_reader).
+    pub fn search_slices<M, S>(
+        &mut self,
+        matcher: M,
+        slices: &[&[u8]],
+        write_to: S,
+    ) -> Result<(), S::Error>
+    where
+        M: Matcher,
+        S: Sink,
+    {
+        match *slices {
+            // With zero or one slices, the contiguous slice search applies
+            // directly (and can avoid copying even for multiline searches).
+            [] => self.search_slice(matcher, b"", write_to),
+            [slice] => self.search_slice(matcher, slice, write_to),
+            _ => self.search_reader(
+                matcher,
+                SliceChainReader::new(slices),
+                write_to,
+            ),
+        }
+    }
+
     /// Set the binary detection method used on this searcher.
     pub fn set_binary_detection(&mut self, detection: BinaryDetection) {
         self.config.binary = detection.clone();
@@ -805,6 +1198,137 @@ impl Searcher {
         self.config.encoding.is_some()
             || (self.config.bom_sniffing && slice_has_bom(slice))
     }
+
+    /// Skip over the configured starting offset in the given slice.
+    ///
+    /// This returns the subslice at which searching should begin and records
+    /// the number of bytes skipped, so that the offsets reported to the sink
+    /// can be made absolute with respect to the original haystack.
+    fn skip_start_offset_slice<'b>(&self, slice: &'b [u8]) -> &'b [u8] {
+        use bstr::ByteSlice;
+
+        let mut skip =
+            cmp::min(slice.len() as u64, self.config.start_offset) as usize;
+        if self.config.round_start_offset && skip > 0 {
+            let line_term = self.config.line_term.as_byte();
+            // When the byte just before the starting offset is a line
+            // terminator, the offset already falls on a line boundary.
+            if slice[skip - 1] != line_term {
+                skip = match slice[skip..].find_byte(line_term) {
+                    Some(i) => skip + i + 1,
+                    None => slice.len(),
+                };
+            }
+        }
+        self.skipped_byte_count.set(skip as u64);
+        &slice[skip..]
+    }
+
+    /// Skip over the configured starting offset in the given reader.
+    ///
+    /// This returns a reader positioned where searching should begin and
+    /// records the number of bytes skipped, as with
+    /// `skip_start_offset_slice`. Any bytes read past the effective starting
+    /// offset while looking for a line boundary are re-joined with the
+    /// remainder of the reader.
+    fn skip_start_offset_reader<R: io::Read, S: Sink>(
+        &self,
+        mut read_from: R,
+    ) -> Result<io::Chain<io::Cursor<Vec<u8>>, R>, S::Error> {
+        use bstr::ByteSlice;
+
+        let offset = self.config.start_offset;
+        let mut skipped = 0;
+        let mut leftover = vec![];
+        if offset > 0 && !self.config.round_start_offset {
+            skipped =
+                io::copy(&mut (&mut read_from).take(offset), &mut io::sink())
+                    .map_err(S::Error::error_io)?;
+        } else if offset > 0 {
+            // Skip to one byte before the starting offset, so that the byte
+            // just before it can be inspected: when it is a line terminator,
+            // the starting offset already falls on a line boundary and no
+            // rounding is needed.
+            let line_term = self.config.line_term.as_byte();
+            skipped = io::copy(
+                &mut (&mut read_from).take(offset - 1),
+                &mut io::sink(),
+            )
+            .map_err(S::Error::error_io)?;
+            let mut byte = [0u8; 1];
+            if skipped == offset - 1
+                && read_from.read(&mut byte).map_err(S::Error::error_io)? > 0
+            {
+                skipped += 1;
+                if byte[0] != line_term {
+                    let mut buf = [0u8; 8 * (1 << 10)];
+                    loop {
+                        let n = read_from
+                            .read(&mut buf)
+                            .map_err(S::Error::error_io)?;
+                        if n == 0 {
+                            break;
+                        }
+                        match buf[..n].find_byte(line_term) {
+                            Some(i) => {
+                                skipped += (i + 1) as u64;
+                                leftover = buf[i + 1..n].to_vec();
+                                break;
+                            }
+                            None => skipped += n as u64,
+                        }
+                    }
+                }
+            }
+        }
+        self.skipped_byte_count.set(skipped);
+        Ok(io::Cursor::new(leftover).chain(read_from))
+    }
+
+    /// Returns true if and only if data from the given reader needs to be
+    /// transcoded, along with a reader that yields the source bytes in full.
+    ///
+    /// Transcoding is detected the same way `slice_needs_transcoding`
+    /// detects it: an explicitly configured encoding always transcodes,
+    /// and otherwise the first few bytes of the reader are peeked at to
+    /// look for a byte-order mark. The peeked bytes are re-joined with the
+    /// remainder of the reader, so no source data is lost.
+    fn reader_needs_transcoding<R: io::Read, S: Sink>(
+        &self,
+        mut read_from: R,
+    ) -> Result<(bool, io::Chain<io::Cursor<Vec<u8>>, R>), S::Error> {
+        let mut peeked = vec![];
+        if self.config.encoding.is_none() && self.config.bom_sniffing {
+            // The longest BOM we can detect (UTF-8's) is 3 bytes.
+            let mut buf = [0u8; 3];
+            let mut len = 0;
+            while len < buf.len() {
+                match read_from.read(&mut buf[len..]) {
+                    Ok(0) => break,
+                    Ok(n) => len += n,
+                    Err(ref err)
+                        if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(S::Error::error_io(err)),
+                }
+            }
+            peeked.extend_from_slice(&buf[..len]);
+        }
+        let needs = self.slice_needs_transcoding(&peeked);
+        Ok((needs, io::Cursor::new(peeked).chain(read_from)))
+    }
+
+    /// Returns true if and only if the search currently executing decodes
+    /// its source data. This is reported to sinks via `SinkFinish`.
+    pub(crate) fn decoded(&self) -> bool {
+        self.decoded.get()
+    }
+
+    /// Returns the size, in bytes, of the compressed file being searched,
+    /// when the search currently executing reads through an in-process
+    /// decompressor.
+    pub(crate) fn compressed_byte_count(&self) -> Option<u64> {
+        self.compressed.get()
+    }
 }
 
 /// The following methods permit querying the configuration of a searcher.
@@ -845,6 +1369,13 @@ impl Searcher {
         self.config.multi_line
     }
 
+    /// Returns the maximum line length enforced by this searcher, if one
+    /// was set.
+    #[inline]
+    pub fn max_line_len(&self) -> Option<usize> {
+        self.config.max_line_len
+    }
+
     /// Returns true if and only if this searcher is configured to stop when in
     /// finds a non-matching line after a matching one.
     #[inline]
@@ -887,6 +1418,27 @@ impl Searcher {
         self.config.after_context
     }
 
+    /// Returns the number of "after" context lines still owed from the most
+    /// recently reported match in the current search.
+    ///
+    /// This is set to [`Searcher::after_context`] each time a match is
+    /// reported to a [`Sink`], and decremented for each "after" context line
+    /// reported thereafter. Note that a match reported while a previous
+    /// match's window is still open resets the window. Use
+    /// [`SinkMatch::is_context_extension`](crate::SinkMatch::is_context_extension)
+    /// to detect that case.
+    #[inline]
+    pub fn after_context_remaining(&self) -> u64 {
+        self.after_context_remaining.get()
+    }
+
+    /// Sets the number of "after" context lines still owed. This is only
+    /// used by the search implementation.
+    #[inline]
+    pub(crate) fn set_after_context_remaining(&self, remaining: u64) {
+        self.after_context_remaining.set(remaining);
+    }
+
     /// Returns the number of "before" context lines to report. When context
     /// reporting is not enabled, this returns `0`.
     #[inline]
@@ -900,19 +1452,62 @@ impl Searcher {
         self.config.passthru
     }
 
+    /// Returns a summary of this searcher's effective configuration.
+    ///
+    /// This is principally useful for logging the settings in effect for a
+    /// particular search, e.g., to make search results reproducible.
+    pub fn config_summary(&self) -> SearcherConfigSummary {
+        let config = &self.config;
+        let (binary_detection, binary_byte) = match config.binary.0 {
+            line_buffer::BinaryDetection::None => ("none", None),
+            line_buffer::BinaryDetection::Quit(b) => ("quit", Some(b)),
+            line_buffer::BinaryDetection::Convert(b) => ("convert", Some(b)),
+        };
+        SearcherConfigSummary {
+            line_terminator: config
+                .line_term
+                .as_bytes()
+                .escape_ascii()
+                .to_string(),
+            invert_match: config.invert_match,
+            after_context: config.after_context,
+            before_context: config.before_context,
+            passthru: config.passthru,
+            line_number: config.line_number,
+            heap_limit: config.heap_limit,
+            max_line_len: config.max_line_len,
+            memory_map: if config.mmap.is_enabled() {
+                "auto"
+            } else {
+                "never"
+            },
+            binary_detection,
+            binary_byte,
+            multi_line: config.multi_line,
+            encoding: config.encoding.as_ref().map(|e| e.0.name().to_string()),
+            bom_sniffing: config.bom_sniffing,
+            stop_on_nonmatch: config.stop_on_nonmatch,
+        }
+    }
+
     /// Fill the buffer for use with multi-line searching from the given file.
     /// This reads from the file until EOF or until an error occurs. If the
-    /// contents exceed the configured heap limit, then an error is returned.
+    /// contents exceed the configured heap limit or the matcher's multi-line
+    /// span limit, then an error is returned.
     fn fill_multi_line_buffer_from_file<S: Sink>(
         &self,
         file: &File,
+        span_limit: Option<usize>,
     ) -> Result<(), S::Error> {
         assert!(self.config.multi_line);
 
+        let (decoded, source) = self.reader_needs_transcoding::<_, S>(file)?;
+        self.decoded.set(decoded);
+
         let mut decode_buffer = self.decode_buffer.borrow_mut();
         let mut read_from = self
             .decode_builder
-            .build_with_buffer(file, &mut *decode_buffer)
+            .build_with_buffer(source, &mut *decode_buffer)
             .map_err(S::Error::error_io)?;
 
         // If we don't have a heap limit, then we can defer to std's
@@ -922,7 +1517,7 @@ impl Searcher {
         //
         // If we're transcoding, then our pre-allocation might not be exact,
         // but is probably still better than nothing.
-        if self.config.heap_limit.is_none() {
+        if self.config.heap_limit.is_none() && span_limit.is_none() {
             let mut buf = self.multi_line_buffer.borrow_mut();
             buf.clear();
             let cap =
@@ -931,41 +1526,47 @@ impl Searcher {
             read_from.read_to_end(&mut *buf).map_err(S::Error::error_io)?;
             return Ok(());
         }
-        self.fill_multi_line_buffer_from_reader::<_, S>(read_from)
+        self.fill_multi_line_buffer_from_reader::<_, S>(read_from, span_limit)
     }
 
     /// Fill the buffer for use with multi-line searching from the given
     /// reader. This reads from the reader until EOF or until an error occurs.
-    /// If the contents exceed the configured heap limit, then an error is
-    /// returned.
+    /// If the contents exceed the configured heap limit or the matcher's
+    /// multi-line span limit, then an error is returned.
     fn fill_multi_line_buffer_from_reader<R: io::Read, S: Sink>(
         &self,
         mut read_from: R,
+        span_limit: Option<usize>,
     ) -> Result<(), S::Error> {
         assert!(self.config.multi_line);
 
         let mut buf = self.multi_line_buffer.borrow_mut();
         buf.clear();
 
-        // If we don't have a heap limit, then we can defer to std's
-        // read_to_end implementation...
-        let heap_limit = match self.config.heap_limit {
-            Some(heap_limit) => heap_limit,
-            None => {
+        // If we don't have any limits, then we can defer to std's read_to_end
+        // implementation. With a span limit, we read one byte more than the
+        // limit so that contents exceeding the limit can be distinguished
+        // from contents of exactly the limit. (A match can never be longer
+        // than the contents, so buffering more than that is never useful.)
+        let cap = match (self.config.heap_limit, span_limit) {
+            (None, None) => {
                 read_from
                     .read_to_end(&mut *buf)
                     .map_err(S::Error::error_io)?;
                 return Ok(());
             }
+            (None, Some(span)) => span.saturating_add(1),
+            (Some(heap), None) => heap,
+            (Some(heap), Some(span)) => cmp::min(heap, span.saturating_add(1)),
         };
-        if heap_limit == 0 {
-            return Err(S::Error::error_io(alloc_error(heap_limit)));
+        if cap == 0 {
+            return Err(S::Error::error_io(alloc_error(cap)));
         }
 
         // ... otherwise we need to roll our own. This is likely quite a bit
         // slower than what is optimal, but we avoid worry about memory safety
         // until there's a compelling reason to speed this up.
-        buf.resize(cmp::min(DEFAULT_BUFFER_CAPACITY, heap_limit), 0);
+        buf.resize(cmp::min(DEFAULT_BUFFER_CAPACITY, cap), 0);
         let mut pos = 0;
         loop {
             let nread = match read_from.read(&mut buf[pos..]) {
@@ -982,18 +1583,63 @@ impl Searcher {
 
             pos += nread;
             if buf[pos..].is_empty() {
-                let additional = heap_limit - buf.len();
-                if additional == 0 {
-                    return Err(S::Error::error_io(alloc_error(heap_limit)));
+                if let Some(span) = span_limit {
+                    if pos > span {
+                        return Err(S::Error::error_message(format!(
+                            "line span limit exceeded at offset {}",
+                            span,
+                        )));
+                    }
+                }
+                if buf.len() == cap {
+                    return Err(S::Error::error_io(alloc_error(cap)));
                 }
-                let limit = buf.len() + additional;
                 let doubled = 2 * buf.len();
-                buf.resize(cmp::min(doubled, limit), 0);
+                buf.resize(cmp::min(doubled, cap), 0);
             }
         }
     }
 }
 
+/// A reader that yields the bytes of a list of slices, one after another, as
+/// if they were one contiguous buffer.
+///
+/// This is used to implement `Searcher::search_slices` on top of the
+/// incremental reader search, which stitches lines that straddle slice
+/// boundaries together in the searcher's line buffer.
+#[derive(Debug)]
+struct SliceChainReader<'s> {
+    /// The slices yet to be read, in order.
+    slices: &'s [&'s [u8]],
+    /// The position within the first slice in `slices`.
+    pos: usize,
+}
+
+impl<'s> SliceChainReader<'s> {
+    /// Create a new reader that chains the given slices.
+    fn new(slices: &'s [&'s [u8]]) -> SliceChainReader<'s> {
+        SliceChainReader { slices, pos: 0 }
+    }
+}
+
+impl<'s> io::Read for SliceChainReader<'s> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(slice) = self.slices.first() {
+            let remaining = &slice[self.pos..];
+            if remaining.is_empty() {
+                self.slices = &self.slices[1..];
+                self.pos = 0;
+                continue;
+            }
+            let n = cmp::min(buf.len(), remaining.len());
+            buf[..n].copy_from_slice(&remaining[..n]);
+            self.pos += n;
+            return Ok(n);
+        }
+        Ok(0)
+    }
+}
+
 /// Returns true if and only if the given slice begins with a UTF-8 or UTF-16
 /// BOM.
 ///
@@ -1035,6 +1681,251 @@ mod tests {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn search_slices_match_at_chunk_boundary() {
+        let matcher = RegexMatcher::new("foo");
+        let mut sink = KitchenSink::new();
+        let mut searcher = Searcher::new();
+        let slices = &[&b"fo"[..], &b"o\nbar\n"[..]];
+        searcher.search_slices(matcher, slices, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("1:0:foo\n\nbyte count:8\n", got);
+    }
+
+    #[test]
+    fn search_slices_line_spans_three_chunks() {
+        let matcher = RegexMatcher::new("aaabbbccc");
+        let mut sink = KitchenSink::new();
+        let mut searcher = Searcher::new();
+        let slices = &[&b"xxx\naaa"[..], &b"bbb"[..], &b"ccc\nyyy\n"[..]];
+        searcher.search_slices(matcher, slices, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("2:4:aaabbbccc\n\nbyte count:18\n", got);
+    }
+
+    #[test]
+    fn search_slices_crlf_split_across_boundary() {
+        let matcher = RegexMatcher::new("foo");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new()
+            .line_terminator(LineTerminator::crlf())
+            .build();
+        let slices = &[&b"foo\r"[..], &b"\nbar\r\n"[..]];
+        searcher.search_slices(matcher, slices, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("1:0:foo\r\n\nbyte count:10\n", got);
+    }
+
+    #[test]
+    fn search_slices_multi_line() {
+        let matcher = RegexMatcher::new("foo\nbar");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new().multi_line(true).build();
+        let slices = &[&b"fo"[..], &b"o\nba"[..], &b"r\n"[..]];
+        searcher.search_slices(matcher, slices, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("1:0:foo\n2:4:bar\n\nbyte count:8\n", got);
+    }
+
+    // Five lines at absolute offsets 0, 6, 12, 20 and 26, for a total of 31
+    // bytes. Offset 14 points into the middle of the "charlie" line.
+    const NUMBERED: &[u8] = b"alpha\nbravo\ncharlie\ndelta\necho\n";
+
+    #[test]
+    fn start_offset_slice() {
+        let matcher = RegexMatcher::new("delta");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new()
+            .start_offset(14)
+            .start_line_number(Some(3))
+            .build();
+        searcher.search_slice(matcher, NUMBERED, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("4:20:delta\n\nbyte count:17\n", got);
+    }
+
+    #[test]
+    fn start_offset_slice_rounded() {
+        // Rounding skips the remainder of the "charlie" line, so only
+        // "delta" matches and the skipped bytes aren't counted.
+        let matcher = RegexMatcher::new("charlie|delta");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new()
+            .start_offset(14)
+            .round_start_offset(true)
+            .start_line_number(Some(4))
+            .build();
+        searcher.search_slice(matcher, NUMBERED, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("4:20:delta\n\nbyte count:11\n", got);
+    }
+
+    #[test]
+    fn start_offset_slice_rounding_on_boundary_is_a_no_op() {
+        let matcher = RegexMatcher::new("charlie");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new()
+            .start_offset(12)
+            .round_start_offset(true)
+            .start_line_number(Some(3))
+            .build();
+        searcher.search_slice(matcher, NUMBERED, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("3:12:charlie\n\nbyte count:19\n", got);
+    }
+
+    #[test]
+    fn start_offset_without_line_number() {
+        let matcher = RegexMatcher::new("delta");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new().start_offset(14).build();
+        searcher.search_slice(matcher, NUMBERED, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("20:delta\n\nbyte count:17\n", got);
+    }
+
+    #[test]
+    fn start_offset_reader() {
+        let matcher = RegexMatcher::new("delta");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new()
+            .start_offset(14)
+            .start_line_number(Some(3))
+            .build();
+        searcher.search_reader(matcher, NUMBERED, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("4:20:delta\n\nbyte count:17\n", got);
+    }
+
+    #[test]
+    fn start_offset_reader_rounded() {
+        let matcher = RegexMatcher::new("charlie|delta");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new()
+            .start_offset(14)
+            .round_start_offset(true)
+            .start_line_number(Some(4))
+            .build();
+        searcher.search_reader(matcher, NUMBERED, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("4:20:delta\n\nbyte count:11\n", got);
+    }
+
+    #[test]
+    fn start_offset_reader_rounding_on_boundary_is_a_no_op() {
+        let matcher = RegexMatcher::new("charlie");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new()
+            .start_offset(12)
+            .round_start_offset(true)
+            .start_line_number(Some(3))
+            .build();
+        searcher.search_reader(matcher, NUMBERED, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("3:12:charlie\n\nbyte count:19\n", got);
+    }
+
+    #[test]
+    fn start_offset_multi_line() {
+        let matcher = RegexMatcher::new("delta\necho");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new()
+            .multi_line(true)
+            .start_offset(14)
+            .round_start_offset(true)
+            .start_line_number(Some(4))
+            .build();
+        searcher.search_slice(matcher, NUMBERED, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("4:20:delta\n5:26:echo\n\nbyte count:11\n", got);
+    }
+
+    #[test]
+    fn start_offset_past_end_of_haystack() {
+        let matcher = RegexMatcher::new("delta");
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new().start_offset(1000).build();
+        searcher.search_slice(matcher, NUMBERED, &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("\nbyte count:0\n", got);
+    }
+
+    #[test]
+    fn multi_line_span_limit_reader_exceeded() {
+        let mut matcher = RegexMatcher::new("(?s)BEGIN.*END");
+        matcher.set_multiline_span_limit(Some(100));
+        let mut haystack = b"BEGIN\n".to_vec();
+        haystack.extend_from_slice(&vec![b'z'; 1000]);
+        haystack.push(b'\n');
+
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new().multi_line(true).build();
+        let err = searcher
+            .search_reader(&matcher, &*haystack, &mut sink)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("line span limit exceeded at offset"),
+            "unexpected error: {}",
+            err
+        );
+
+        // A failed search should not poison the searcher. Other haystacks
+        // in the same run (e.g., other files) still get searched.
+        let mut sink = KitchenSink::new();
+        searcher
+            .search_reader(&matcher, &b"BEGIN ok END\n"[..], &mut sink)
+            .unwrap();
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("1:0:BEGIN ok END\n\nbyte count:13\n", got);
+    }
+
+    #[test]
+    fn multi_line_span_limit_match_under_limit() {
+        let mut matcher = RegexMatcher::new("(?s)BEGIN.*END");
+        matcher.set_multiline_span_limit(Some(100));
+        let haystack = b"BEGIN\nmiddle\nEND\n";
+
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new().multi_line(true).build();
+        searcher.search_reader(&matcher, &haystack[..], &mut sink).unwrap();
+
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("1:0:BEGIN\n2:6:middle\n3:13:END\n\nbyte count:17\n", got);
+    }
+
+    #[test]
+    fn multi_line_span_limit_slice_match_refused() {
+        // Slice searches have no intermediate buffer to cap, so the limit
+        // is enforced on the span of each reported match instead.
+        let mut matcher = RegexMatcher::new("(?s)BEGIN.*END");
+        matcher.set_multiline_span_limit(Some(10));
+        let haystack = b"BEGIN\nzzzzzzzz\nEND\n";
+
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new().multi_line(true).build();
+        let err = searcher
+            .search_slice(&matcher, &haystack[..], &mut sink)
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("line span limit exceeded at offset 0"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
     #[test]
     fn uft8_bom_sniffing() {
         // See: https://github.com/BurntSushi/ripgrep/issues/1638
@@ -1049,6 +1940,253 @@ mod tests {
         assert!(res.is_ok());
 
         let sink_output = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
-        assert_eq!(sink_output, "1:0:foo\nbyte count:3\n");
+        assert_eq!(sink_output, "1:0:foo\nbyte count:3\ndecoded:true\n");
+    }
+
+    #[test]
+    fn utf16le_bom_transcoded_offsets() {
+        // "Homer\nSimpson\n" encoded as UTF-16LE with a BOM. Offsets and
+        // byte counts reported to the sink refer to the transcoded data,
+        // so "Simpson" starts at offset 6 even though its first source
+        // byte is at offset 14.
+        let mut haystack = vec![0xFF, 0xFE];
+        for byte in "Homer\nSimpson\n".bytes() {
+            haystack.extend_from_slice(&[byte, 0x00]);
+        }
+        let matcher = RegexMatcher::new("Simpson");
+
+        let mut sink = KitchenSink::new();
+        let mut searcher = SearcherBuilder::new().build();
+        searcher.search_reader(&matcher, &*haystack, &mut sink).unwrap();
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("2:6:Simpson\n\nbyte count:14\ndecoded:true\n", got);
+
+        // Searching the same haystack as a slice detects the BOM and
+        // reports identical (transcoded) offsets.
+        let mut sink = KitchenSink::new();
+        searcher.search_slice(&matcher, &haystack, &mut sink).unwrap();
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("2:6:Simpson\n\nbyte count:14\ndecoded:true\n", got);
+
+        // And a slice without a BOM is not decoded at all.
+        let mut sink = KitchenSink::new();
+        searcher
+            .search_slice(&matcher, b"Homer\nSimpson\n", &mut sink)
+            .unwrap();
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("2:6:Simpson\n\nbyte count:14\n", got);
+    }
+
+    #[test]
+    fn decompression_registry() {
+        // A fake "compressed" format: a 4 byte magic header followed by the
+        // raw data. The decompressor just strips the header.
+        let mut registry = DecompressionRegistry::new();
+        registry.register(&["fake"], &[b"FAKE"], |mut file| {
+            let mut magic = [0u8; 4];
+            file.read_exact(&mut magic)?;
+            Ok(Box::new(file))
+        });
+        let registry = Arc::new(registry);
+
+        let dir = std::env::temp_dir().join(format!(
+            "grep-searcher-decompress-test-{}",
+            std::process::id(),
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut compressed = b"FAKE".to_vec();
+        compressed.extend_from_slice(b"Homer\nSimpson\n");
+
+        let matcher = RegexMatcher::new("Simpson");
+        let mut searcher = SearcherBuilder::new()
+            .decompression(Some(Arc::clone(&registry)))
+            .build();
+
+        // Matching by extension. Line numbers, offsets and the byte count
+        // all refer to the decompressed stream, while the compressed size
+        // is reported alongside.
+        let path = dir.join("haystack.fake");
+        std::fs::write(&path, &compressed).unwrap();
+        let mut sink = KitchenSink::new();
+        searcher.search_path(&matcher, &path, &mut sink).unwrap();
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!(
+            "2:6:Simpson\n\nbyte count:14\ncompressed byte count:18\n",
+            got
+        );
+
+        // Matching by magic bytes when the extension is unknown.
+        let path = dir.join("haystack.bin");
+        std::fs::write(&path, &compressed).unwrap();
+        let mut sink = KitchenSink::new();
+        searcher.search_path(&matcher, &path, &mut sink).unwrap();
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!(
+            "2:6:Simpson\n\nbyte count:14\ncompressed byte count:18\n",
+            got
+        );
+
+        // A file matching neither is searched as-is, with no compressed
+        // byte count.
+        let path = dir.join("haystack.txt");
+        std::fs::write(&path, b"Homer\nSimpson\n").unwrap();
+        let mut sink = KitchenSink::new();
+        searcher.search_path(&matcher, &path, &mut sink).unwrap();
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!("2:6:Simpson\n\nbyte count:14\n", got);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn decompression_gzip() {
+        use std::io::Write;
+
+        let mut enc = flate2::write::GzEncoder::new(
+            vec![],
+            flate2::Compression::default(),
+        );
+        enc.write_all(b"Homer\nSimpson\n").unwrap();
+        let compressed = enc.finish().unwrap();
+
+        let dir = std::env::temp_dir()
+            .join(format!("grep-searcher-gzip-test-{}", std::process::id(),));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("haystack.gz");
+        std::fs::write(&path, &compressed).unwrap();
+
+        let matcher = RegexMatcher::new("Simpson");
+        let mut searcher = SearcherBuilder::new()
+            .decompression(Some(Arc::new(
+                DecompressionRegistry::with_defaults(),
+            )))
+            .build();
+        let mut sink = KitchenSink::new();
+        searcher.search_path(&matcher, &path, &mut sink).unwrap();
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!(
+            format!(
+                "2:6:Simpson\n\nbyte count:14\ncompressed byte count:{}\n",
+                compressed.len()
+            ),
+            got
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn search_reader_max_line_len() {
+        let matcher = RegexMatcher::new("needle");
+        let mut haystack = vec![];
+        haystack.extend_from_slice(b"aaa needle one\n");
+        haystack.extend_from_slice(&vec![b'z'; 10 << 20]);
+        haystack.push(b'\n');
+        haystack.extend_from_slice(b"bbb needle two\n");
+
+        let mut sink = KitchenSink::new();
+        let mut searcher =
+            SearcherBuilder::new().max_line_len(Some(1000)).build();
+        searcher.search_reader(matcher, &*haystack, &mut sink).unwrap();
+
+        // The 10MB line is skipped and replaced with a single empty line,
+        // so the line numbers of subsequent matches remain correct while
+        // the byte offsets reflect the compacted stream.
+        let got = String::from_utf8(sink.as_bytes().to_vec()).unwrap();
+        assert_eq!(
+            "1:0:aaa needle one\n3:16:bbb needle two\n\nbyte count:31\n",
+            got
+        );
+    }
+
+    #[test]
+    fn after_context_extensions() {
+        use crate::sink::{Sink, SinkMatch};
+
+        // Records, for every reported match, its line number, whether it
+        // extends a previous match's after-context window and how much of
+        // that window was left.
+        struct ExtensionSink(Vec<(u64, bool, u64)>);
+
+        impl Sink for ExtensionSink {
+            type Error = io::Error;
+
+            fn matched(
+                &mut self,
+                searcher: &Searcher,
+                mat: &SinkMatch<'_>,
+            ) -> Result<bool, io::Error> {
+                self.0.push((
+                    mat.line_number().unwrap(),
+                    mat.is_context_extension(),
+                    searcher.after_context_remaining(),
+                ));
+                Ok(true)
+            }
+        }
+
+        // Matches on lines 1 and 2 (1 apart), 4 (2 apart) and 9 (5 apart),
+        // with 3 lines of after-context. The match on line 9 is the only
+        // one (besides the first) that falls outside the window of the
+        // match preceding it.
+        let haystack = "m\nm\nx\nm\nx\nx\nx\nx\nm\n";
+        let matcher = RegexMatcher::new("m");
+        let mut sink = ExtensionSink(vec![]);
+        let mut searcher =
+            SearcherBuilder::new().line_number(true).after_context(3).build();
+        searcher
+            .search_reader(matcher, haystack.as_bytes(), &mut sink)
+            .unwrap();
+        assert_eq!(
+            vec![(1, false, 0), (2, true, 3), (4, true, 2), (9, false, 0)],
+            sink.0
+        );
+    }
+
+    #[test]
+    fn config_summary_reflects_builder() {
+        let searcher = SearcherBuilder::new()
+            .line_terminator(LineTerminator::crlf())
+            .invert_match(true)
+            .line_number(false)
+            .multi_line(true)
+            .before_context(2)
+            .after_context(3)
+            .heap_limit(Some(100))
+            .max_line_len(Some(4096))
+            .binary_detection(BinaryDetection::quit(0))
+            .encoding(Some(Encoding::new("utf-16le").unwrap()))
+            .stop_on_nonmatch(true)
+            .build();
+        let summary = searcher.config_summary();
+        assert_eq!(r"\r\n", summary.line_terminator);
+        assert!(summary.invert_match);
+        assert!(!summary.line_number);
+        assert!(summary.multi_line);
+        assert_eq!(2, summary.before_context);
+        assert_eq!(3, summary.after_context);
+        assert!(!summary.passthru);
+        assert_eq!(Some(100), summary.heap_limit);
+        assert_eq!(Some(4096), summary.max_line_len);
+        assert_eq!("quit", summary.binary_detection);
+        assert_eq!(Some(0), summary.binary_byte);
+        assert_eq!(Some("UTF-16LE".to_string()), summary.encoding);
+        assert!(summary.bom_sniffing);
+        assert!(summary.stop_on_nonmatch);
+    }
+
+    #[test]
+    fn config_summary_mmap_tri_state() {
+        let searcher = SearcherBuilder::new().build();
+        assert_eq!("never", searcher.config_summary().memory_map);
+
+        // The automatic strategy is resolved per haystack, so the summary
+        // must report it as-is rather than as a boolean.
+        let choice = unsafe { MmapChoice::auto() };
+        let searcher = SearcherBuilder::new().memory_map(choice).build();
+        assert_eq!("auto", searcher.config_summary().memory_map);
     }
 }
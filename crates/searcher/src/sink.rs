@@ -1,9 +1,9 @@
 use std::io;
 
-use grep_matcher::LineTerminator;
+use grep_matcher::{LineTerminator, Match};
 
 use crate::{
-    lines::LineIter,
+    lines::{LineIter, LineStep},
     searcher::{ConfigError, Searcher},
 };
 
@@ -193,6 +193,32 @@ pub trait Sink {
         Ok(true)
     }
 
+    /// This method is called whenever a maximum line length is configured on
+    /// the searcher and a line exceeding that length is skipped. The absolute
+    /// byte offset at which the skipped line started is given, along with an
+    /// estimate of the line's length in bytes (excluding its terminator). The
+    /// length is an estimate because a skipped line that ends at EOF has no
+    /// terminator delimiting it.
+    ///
+    /// If this returns `true`, then searching continues with the line
+    /// following the skipped line. If this returns `false`, then searching
+    /// is stopped immediately and `finish` is called.
+    ///
+    /// If this returns an error, then searching is stopped immediately,
+    /// `finish` is not called and the error is bubbled back up to the caller
+    /// of the searcher.
+    ///
+    /// By default, it does nothing and returns `true`.
+    #[inline]
+    fn line_too_long(
+        &mut self,
+        _searcher: &Searcher,
+        _absolute_byte_offset: u64,
+        _len: u64,
+    ) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
     /// This method is called when a search has begun, before any search is
     /// executed. By default, this does nothing.
     ///
@@ -260,6 +286,16 @@ impl<'a, S: Sink> Sink for &'a mut S {
         (**self).binary_data(searcher, binary_byte_offset)
     }
 
+    #[inline]
+    fn line_too_long(
+        &mut self,
+        searcher: &Searcher,
+        absolute_byte_offset: u64,
+        len: u64,
+    ) -> Result<bool, S::Error> {
+        (**self).line_too_long(searcher, absolute_byte_offset, len)
+    }
+
     #[inline]
     fn begin(&mut self, searcher: &Searcher) -> Result<bool, S::Error> {
         (**self).begin(searcher)
@@ -313,6 +349,16 @@ impl<S: Sink + ?Sized> Sink for Box<S> {
         (**self).binary_data(searcher, binary_byte_offset)
     }
 
+    #[inline]
+    fn line_too_long(
+        &mut self,
+        searcher: &Searcher,
+        absolute_byte_offset: u64,
+        len: u64,
+    ) -> Result<bool, S::Error> {
+        (**self).line_too_long(searcher, absolute_byte_offset, len)
+    }
+
     #[inline]
     fn begin(&mut self, searcher: &Searcher) -> Result<bool, S::Error> {
         (**self).begin(searcher)
@@ -328,6 +374,185 @@ impl<S: Sink + ?Sized> Sink for Box<S> {
     }
 }
 
+/// The stop behavior of a [`Tee`] sink.
+///
+/// This controls when a `Tee` instructs the searcher to stop in response to
+/// one of its underlying sinks asking the searcher to stop.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TeeStop {
+    /// Stop the search as soon as either underlying sink asks to stop.
+    Any,
+    /// Continue the search until both underlying sinks have asked to stop.
+    ///
+    /// Once a sink has asked to stop, it no longer receives any callbacks
+    /// other than `finish`, even while the search continues for the benefit
+    /// of the other sink.
+    All,
+}
+
+/// A sink that broadcasts everything it receives to two underlying sinks.
+///
+/// This permits, for example, executing a single search while writing its
+/// results with two different printers simultaneously. Every callback is
+/// delegated to both sinks in the order given to [`Tee::new`].
+///
+/// The error types of the underlying sinks may differ. Errors are reconciled
+/// by boxing them into a `std::io::Error`, which is the error type of this
+/// sink.
+///
+/// By default, the search stops only when both underlying sinks have asked
+/// it to stop. Use [`Tee::stop`] to stop as soon as either sink asks.
+#[derive(Clone, Debug)]
+pub struct Tee<S1, S2> {
+    sink1: S1,
+    sink2: S2,
+    stop: TeeStop,
+    done1: bool,
+    done2: bool,
+}
+
+impl<S1: Sink, S2: Sink> Tee<S1, S2> {
+    /// Create a new sink that delegates everything it receives to the two
+    /// sinks given, with [`TeeStop::All`] stop behavior.
+    pub fn new(sink1: S1, sink2: S2) -> Tee<S1, S2> {
+        Tee { sink1, sink2, stop: TeeStop::All, done1: false, done2: false }
+    }
+
+    /// Set the stop behavior of this sink.
+    ///
+    /// This determines whether the search stops as soon as either underlying
+    /// sink asks it to stop, or only once both have. The default is
+    /// [`TeeStop::All`].
+    pub fn stop(mut self, stop: TeeStop) -> Tee<S1, S2> {
+        self.stop = stop;
+        self
+    }
+
+    /// Consume this sink and return the two underlying sinks.
+    pub fn into_inner(self) -> (S1, S2) {
+        (self.sink1, self.sink2)
+    }
+}
+
+impl<S1: Sink, S2: Sink> Tee<S1, S2>
+where
+    S1::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    S2::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    /// Delegate a single callback to each underlying sink that is still
+    /// active, and report whether the search should continue according to
+    /// this sink's stop behavior.
+    fn delegate(
+        &mut self,
+        f1: impl FnOnce(&mut S1) -> Result<bool, S1::Error>,
+        f2: impl FnOnce(&mut S2) -> Result<bool, S2::Error>,
+    ) -> Result<bool, io::Error> {
+        if !self.done1 {
+            let keep_going = f1(&mut self.sink1)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            self.done1 = !keep_going;
+        }
+        if !self.done2 {
+            let keep_going = f2(&mut self.sink2)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+            self.done2 = !keep_going;
+        }
+        Ok(match self.stop {
+            TeeStop::Any => !self.done1 && !self.done2,
+            TeeStop::All => !self.done1 || !self.done2,
+        })
+    }
+}
+
+impl<S1: Sink, S2: Sink> Sink for Tee<S1, S2>
+where
+    S1::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    S2::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    type Error = io::Error;
+
+    #[inline]
+    fn matched(
+        &mut self,
+        searcher: &Searcher,
+        mat: &SinkMatch<'_>,
+    ) -> Result<bool, io::Error> {
+        self.delegate(
+            |sink| sink.matched(searcher, mat),
+            |sink| sink.matched(searcher, mat),
+        )
+    }
+
+    #[inline]
+    fn context(
+        &mut self,
+        searcher: &Searcher,
+        context: &SinkContext<'_>,
+    ) -> Result<bool, io::Error> {
+        self.delegate(
+            |sink| sink.context(searcher, context),
+            |sink| sink.context(searcher, context),
+        )
+    }
+
+    #[inline]
+    fn context_break(
+        &mut self,
+        searcher: &Searcher,
+    ) -> Result<bool, io::Error> {
+        self.delegate(
+            |sink| sink.context_break(searcher),
+            |sink| sink.context_break(searcher),
+        )
+    }
+
+    #[inline]
+    fn binary_data(
+        &mut self,
+        searcher: &Searcher,
+        binary_byte_offset: u64,
+    ) -> Result<bool, io::Error> {
+        self.delegate(
+            |sink| sink.binary_data(searcher, binary_byte_offset),
+            |sink| sink.binary_data(searcher, binary_byte_offset),
+        )
+    }
+
+    #[inline]
+    fn line_too_long(
+        &mut self,
+        searcher: &Searcher,
+        absolute_byte_offset: u64,
+        len: u64,
+    ) -> Result<bool, io::Error> {
+        self.delegate(
+            |sink| sink.line_too_long(searcher, absolute_byte_offset, len),
+            |sink| sink.line_too_long(searcher, absolute_byte_offset, len),
+        )
+    }
+
+    #[inline]
+    fn begin(&mut self, searcher: &Searcher) -> Result<bool, io::Error> {
+        self.done1 = false;
+        self.done2 = false;
+        self.delegate(|sink| sink.begin(searcher), |sink| sink.begin(searcher))
+    }
+
+    #[inline]
+    fn finish(
+        &mut self,
+        searcher: &Searcher,
+        sink_finish: &SinkFinish,
+    ) -> Result<(), io::Error> {
+        self.sink1
+            .finish(searcher, sink_finish)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        self.sink2
+            .finish(searcher, sink_finish)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    }
+}
+
 /// Summary data reported at the end of a search.
 ///
 /// This reports data such as the total number of bytes searched and the
@@ -340,15 +565,52 @@ impl<S: Sink + ?Sized> Sink for Box<S> {
 pub struct SinkFinish {
     pub(crate) byte_count: u64,
     pub(crate) binary_byte_offset: Option<u64>,
+    pub(crate) decoded: bool,
+    pub(crate) compressed_byte_count: Option<u64>,
 }
 
 impl SinkFinish {
     /// Return the total number of bytes searched.
+    ///
+    /// When the source data was decoded (see [`SinkFinish::decoded`]), this
+    /// counts the decoded bytes that were searched, not the source bytes.
+    /// Similarly, when a compressed file was searched through an in-process
+    /// decompressor (see [`SinkFinish::compressed_byte_count`]), this counts
+    /// the decompressed bytes.
     #[inline]
     pub fn byte_count(&self) -> u64 {
         self.byte_count
     }
 
+    /// Return the size, in bytes, of the compressed file that was searched,
+    /// if the search read through an in-process decompressor.
+    ///
+    /// This is only set when a [`DecompressionRegistry`] was attached
+    /// to the searcher and the searched file matched one of its
+    /// decompressors. All other byte counts and offsets reported to the
+    /// sink, including [`SinkFinish::byte_count`], refer to the
+    /// decompressed stream.
+    ///
+    /// [`DecompressionRegistry`]: crate::DecompressionRegistry
+    #[inline]
+    pub fn compressed_byte_count(&self) -> Option<u64> {
+        self.compressed_byte_count
+    }
+
+    /// Returns true if and only if the source data was decoded before being
+    /// searched, either because an encoding was explicitly configured on the
+    /// searcher or because a byte-order mark (BOM) was found.
+    ///
+    /// When this is true, all byte counts and absolute byte offsets reported
+    /// during this search refer to the decoded data, which is the data as it
+    /// would be printed. They cannot be used to index into the source bytes.
+    /// (Stripping a UTF-8 BOM counts as decoding: the offsets refer to the
+    /// data with the BOM removed.)
+    #[inline]
+    pub fn decoded(&self) -> bool {
+        self.decoded
+    }
+
     /// If binary detection is enabled and if binary data was found, then this
     /// returns the absolute byte offset of the first detected byte of binary
     /// data.
@@ -368,8 +630,10 @@ pub struct SinkMatch<'b> {
     pub(crate) bytes: &'b [u8],
     pub(crate) absolute_byte_offset: u64,
     pub(crate) line_number: Option<u64>,
+    pub(crate) approximate_line_number: bool,
     pub(crate) buffer: &'b [u8],
     pub(crate) bytes_range_in_buffer: std::ops::Range<usize>,
+    pub(crate) is_context_extension: bool,
 }
 
 impl<'b> SinkMatch<'b> {
@@ -393,10 +657,66 @@ impl<'b> SinkMatch<'b> {
         LineIter::new(self.line_term.as_byte(), self.bytes)
     }
 
+    /// Return an iterator over the lines in this match, along with the
+    /// portion of `mat` that falls within each line.
+    ///
+    /// `mat` must be a span relative to [`SinkMatch::bytes`], e.g., as found
+    /// by running a matcher over `bytes()`. It is a parameter because the
+    /// searcher does not record exact match spans: in multi line mode,
+    /// adjacent matches are merged into a single range of whole lines before
+    /// being reported, so callers that need the spans are expected to
+    /// re-discover them.
+    ///
+    /// Each item consists of the line's number (when available), the line's
+    /// bytes including its terminator, and the part of `mat` that intersects
+    /// the line, with positions relative to the start of that line. The
+    /// intersection is `None` when no part of `mat` falls within the line.
+    /// A match ending exactly at a line terminator does not spill into the
+    /// following line, and an empty match belongs to the line containing its
+    /// position.
+    pub fn lines_with_spans(
+        &self,
+        mat: Match,
+    ) -> impl Iterator<Item = (Option<u64>, &'b [u8], Option<Match>)> + 'b
+    {
+        let bytes = self.bytes;
+        let mut stepper =
+            LineStep::new(self.line_term.as_byte(), 0, bytes.len());
+        let mut line_number = self.line_number;
+        std::iter::from_fn(move || {
+            let (start, end) = stepper.next(bytes)?;
+            let number = line_number;
+            if let Some(ref mut n) = line_number {
+                *n += 1;
+            }
+            let span = if mat.is_empty() {
+                if start <= mat.start() && mat.start() < end {
+                    Some(Match::zero(mat.start() - start))
+                } else {
+                    None
+                }
+            } else {
+                let s = std::cmp::max(mat.start(), start);
+                let e = std::cmp::min(mat.end(), end);
+                if s < e {
+                    Some(Match::new(s - start, e - start))
+                } else {
+                    None
+                }
+            };
+            Some((number, &bytes[start..end], span))
+        })
+    }
+
     /// Returns the absolute byte offset of the start of this match. This
     /// offset is absolute in that it is relative to the very beginning of the
     /// input in a search, and can never be relied upon to be a valid index
     /// into an in-memory slice.
+    ///
+    /// When the source data is decoded before searching (because of an
+    /// explicitly configured encoding or a byte-order mark), this offset is
+    /// relative to the beginning of the decoded data, not the source bytes.
+    /// See [`SinkFinish::decoded`].
     #[inline]
     pub fn absolute_byte_offset(&self) -> u64 {
         self.absolute_byte_offset
@@ -411,6 +731,20 @@ impl<'b> SinkMatch<'b> {
         self.line_number
     }
 
+    /// Returns true when at least one binary byte was converted to a line
+    /// terminator before the start of this match.
+    ///
+    /// This only ever returns true when binary detection is configured
+    /// via [`BinaryDetection::convert`](crate::BinaryDetection::convert).
+    /// Converted bytes do not create new lines, but they may merge or split
+    /// the lines a user sees when opening the raw contents of the haystack
+    /// in an editor. So once a conversion has occurred, line numbers
+    /// reported for subsequent matches should be considered approximate.
+    #[inline]
+    pub fn approximate_line_number(&self) -> bool {
+        self.approximate_line_number
+    }
+
     /// Exposes as much of the underlying buffer that was search as possible.
     #[inline]
     pub fn buffer(&self) -> &'b [u8] {
@@ -423,6 +757,23 @@ impl<'b> SinkMatch<'b> {
     pub fn bytes_range_in_buffer(&self) -> std::ops::Range<usize> {
         self.bytes_range_in_buffer.clone()
     }
+
+    /// Returns true when this match was reported while the "after" context
+    /// window of a previous match was still open.
+    ///
+    /// In that case, the searcher never stopped reporting lines between the
+    /// previous match and this one: this match occupies a line that would
+    /// have been reported as "after" context anyway. This is useful for
+    /// implementing match count limits, where such a match should extend the
+    /// previous match's context window rather than count against the limit.
+    ///
+    /// This only ever returns true when a non-zero amount of "after" context
+    /// is configured via
+    /// [`SearcherBuilder::after_context`](crate::SearcherBuilder::after_context).
+    #[inline]
+    pub fn is_context_extension(&self) -> bool {
+        self.is_context_extension
+    }
 }
 
 /// The type of context reported by a searcher.
@@ -661,3 +1012,129 @@ pub mod sinks {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{searcher::SearcherBuilder, testutil::RegexMatcher};
+
+    use super::*;
+
+    const HAYSTACK: &str = "\
+For the Doctor Watsons of this world, as opposed to the Sherlock Holmeses,
+be, to a very large extent, the result of luck. Sherlock Holmes
+can extract a clew from a wisp of straw or a flake of cigar ash;
+but Doctor Watson has to have it taken out for him and dusted,
+and exhibited clearly, with a label attached.
+";
+
+    /// Run a search for `Sherlock` over `HAYSTACK` with the given sink.
+    fn search<S: Sink<Error = io::Error>>(sink: S) {
+        let matcher = RegexMatcher::new("Sherlock");
+        SearcherBuilder::new()
+            .build()
+            .search_reader(&matcher, HAYSTACK.as_bytes(), sink)
+            .unwrap();
+    }
+
+    /// A sink that collects the matching lines it has seen, and optionally
+    /// stops the search after seeing a fixed number of them.
+    fn collect(
+        lines: &mut Vec<(u64, String)>,
+        limit: Option<usize>,
+    ) -> impl Sink<Error = io::Error> + '_ {
+        sinks::UTF8(move |lineno, line| {
+            lines.push((lineno, line.to_string()));
+            Ok(limit.map_or(true, |limit| lines.len() < limit))
+        })
+    }
+
+    /// Build a `SinkMatch` over the given bytes directly, as if the searcher
+    /// had reported it starting at line 1.
+    fn sink_match(bytes: &[u8]) -> SinkMatch<'_> {
+        SinkMatch {
+            line_term: LineTerminator::byte(b'\n'),
+            bytes,
+            absolute_byte_offset: 0,
+            line_number: Some(1),
+            approximate_line_number: false,
+            buffer: bytes,
+            bytes_range_in_buffer: 0..bytes.len(),
+            is_context_extension: false,
+        }
+    }
+
+    #[test]
+    fn lines_with_spans_mid_line() {
+        let m = sink_match(b"foobar\nbazquux\n");
+        let got: Vec<_> = m.lines_with_spans(Match::new(3, 10)).collect();
+        assert_eq!(
+            vec![
+                (Some(1), &b"foobar\n"[..], Some(Match::new(3, 7))),
+                (Some(2), &b"bazquux\n"[..], Some(Match::new(0, 3))),
+            ],
+            got
+        );
+    }
+
+    #[test]
+    fn lines_with_spans_ends_at_line_terminator() {
+        let m = sink_match(b"foobar\nbazquux\n");
+        let got: Vec<_> = m.lines_with_spans(Match::new(3, 7)).collect();
+        assert_eq!(
+            vec![
+                (Some(1), &b"foobar\n"[..], Some(Match::new(3, 7))),
+                (Some(2), &b"bazquux\n"[..], None),
+            ],
+            got
+        );
+    }
+
+    #[test]
+    fn lines_with_spans_empty_match_at_boundary() {
+        let m = sink_match(b"foobar\nbazquux\n");
+        let got: Vec<_> = m.lines_with_spans(Match::zero(7)).collect();
+        assert_eq!(
+            vec![
+                (Some(1), &b"foobar\n"[..], None),
+                (Some(2), &b"bazquux\n"[..], Some(Match::zero(0))),
+            ],
+            got
+        );
+    }
+
+    #[test]
+    fn tee_matches_separate_runs() {
+        let (mut expected1, mut expected2) = (vec![], vec![]);
+        search(collect(&mut expected1, None));
+        search(collect(&mut expected2, None));
+
+        let (mut got1, mut got2) = (vec![], vec![]);
+        search(Tee::new(collect(&mut got1, None), collect(&mut got2, None)));
+
+        assert_eq!(expected1, got1);
+        assert_eq!(expected2, got2);
+        assert_eq!(2, got1.len());
+    }
+
+    #[test]
+    fn tee_stop_all() {
+        let (mut got1, mut got2) = (vec![], vec![]);
+        search(
+            Tee::new(collect(&mut got1, Some(1)), collect(&mut got2, None))
+                .stop(TeeStop::All),
+        );
+        assert_eq!(1, got1.len());
+        assert_eq!(2, got2.len());
+    }
+
+    #[test]
+    fn tee_stop_any() {
+        let (mut got1, mut got2) = (vec![], vec![]);
+        search(
+            Tee::new(collect(&mut got1, Some(1)), collect(&mut got2, None))
+                .stop(TeeStop::Any),
+        );
+        assert_eq!(1, got1.len());
+        assert_eq!(1, got2.len());
+    }
+}
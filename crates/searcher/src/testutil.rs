@@ -25,6 +25,7 @@ pub(crate) struct RegexMatcher {
     regex: Regex,
     line_term: Option<LineTerminator>,
     every_line_is_candidate: bool,
+    multiline_span_limit: Option<usize>,
 }
 
 impl RegexMatcher {
@@ -34,7 +35,12 @@ impl RegexMatcher {
             .multi_line(true) // permits ^ and $ to match at \n boundaries
             .build()
             .unwrap();
-        RegexMatcher { regex, line_term: None, every_line_is_candidate: false }
+        RegexMatcher {
+            regex,
+            line_term: None,
+            every_line_is_candidate: false,
+            multiline_span_limit: None,
+        }
     }
 
     /// Forcefully set the line terminator of this matcher.
@@ -58,6 +64,17 @@ impl RegexMatcher {
         self.every_line_is_candidate = yes;
         self
     }
+
+    /// Set a maximum span, in bytes, for multi-line matches.
+    ///
+    /// By default, this matcher has no span limit set.
+    pub(crate) fn set_multiline_span_limit(
+        &mut self,
+        limit: Option<usize>,
+    ) -> &mut RegexMatcher {
+        self.multiline_span_limit = limit;
+        self
+    }
 }
 
 impl Matcher for RegexMatcher {
@@ -83,6 +100,10 @@ impl Matcher for RegexMatcher {
         self.line_term
     }
 
+    fn multiline_span_limit(&self) -> Option<usize> {
+        self.multiline_span_limit
+    }
+
     fn find_candidate_line(
         &self,
         haystack: &[u8],
@@ -140,7 +161,9 @@ impl Sink for KitchenSink {
         let mut byte_offset = mat.absolute_byte_offset();
         for line in mat.lines() {
             if let Some(ref mut n) = line_number {
-                write!(self.0, "{}:", n)?;
+                let approx =
+                    if mat.approximate_line_number() { "~" } else { "" };
+                write!(self.0, "{}{}:", n, approx)?;
                 *n += 1;
             }
 
@@ -185,6 +208,12 @@ impl Sink for KitchenSink {
         if let Some(offset) = sink_finish.binary_byte_offset() {
             writeln!(self.0, "binary offset:{}", offset)?;
         }
+        if sink_finish.decoded() {
+            writeln!(self.0, "decoded:true")?;
+        }
+        if let Some(count) = sink_finish.compressed_byte_count() {
+            writeln!(self.0, "compressed byte count:{}", count)?;
+        }
         Ok(())
     }
 }
@@ -782,6 +782,38 @@ rgtest!(f993_null_data, |dir: Dir, mut cmd: TestCommand| {
     eqnice!(expected, cmd.stdout());
 });
 
+// Colors and context separators must respect the NUL record terminator:
+// separators are NUL terminated like any other record and color resets are
+// written before the terminator, so that consumers like `xargs -0` can split
+// the output on NUL.
+rgtest!(f993_null_data_colors, |dir: Dir, mut cmd: TestCommand| {
+    dir.create("test", "zzz foo\x00skip\x00ctx\x00bar foo\x00");
+    cmd.args(&[
+        "--null-data",
+        "-A1",
+        "--color=always",
+        "--colors=path:none",
+        "foo",
+        "test",
+    ]);
+
+    let got = cmd.stdout();
+    assert!(got.ends_with('\x00'), "got: {:?}", got);
+    let records =
+        got.trim_end_matches('\x00').split('\x00').collect::<Vec<&str>>();
+    // A match, its trailing context, a context separator (the "ctx" record
+    // breaks the context runs apart) and the final match.
+    assert_eq!(4, records.len(), "got: {:?}", records);
+    for record in records.iter() {
+        assert!(!record.contains('\n'), "got: {:?}", record);
+    }
+    // The color reset must come before the record terminator.
+    eqnice!("zzz \x1b[0m\x1b[1m\x1b[31mfoo\x1b[0m", records[0]);
+    eqnice!("skip", records[1]);
+    eqnice!("\x1b[0m--\x1b[0m", records[2]);
+    eqnice!("bar \x1b[0m\x1b[1m\x1b[31mfoo\x1b[0m", records[3]);
+});
+
 // See: https://github.com/BurntSushi/ripgrep/issues/1078
 //
 // N.B. There are many more tests in the grep-printer crate.
@@ -1172,3 +1204,38 @@ rgtest!(stop_on_nonmatch, |dir: Dir, mut cmd: TestCommand| {
     cmd.args(&["--stop-on-nonmatch", "[235]"]);
     eqnice!("test:line2\ntest:line3\n", cmd.stdout());
 });
+
+// --dedupe-links should search a file only once, even when it is reachable
+// through multiple hard links. File identity is only available on Unix.
+#[cfg(unix)]
+rgtest!(dedupe_links_hard_link, |dir: Dir, mut cmd: TestCommand| {
+    dir.create_dir("a");
+    dir.create_dir("b");
+    dir.create("a/test", "foo\n");
+    std::fs::hard_link(dir.path().join("a/test"), dir.path().join("b/test"))
+        .unwrap();
+
+    // Without the flag, both links are searched.
+    cmd.args(&["--sort", "path", "foo"]);
+    eqnice!("a/test:foo\nb/test:foo\n", cmd.stdout());
+
+    let mut cmd = dir.command();
+    cmd.args(&["--sort", "path", "--dedupe-links", "foo"]);
+    eqnice!("a/test:foo\n", cmd.stdout());
+});
+
+// --dedupe-links should also collapse files seen through overlapping search
+// roots, e.g., `rg foo dir dir/sub`.
+#[cfg(unix)]
+rgtest!(dedupe_links_overlapping_roots, |dir: Dir, mut cmd: TestCommand| {
+    dir.create_dir("dir/sub");
+    dir.create("dir/sub/test", "foo\n");
+
+    // Without the flag, the file is reported once per root.
+    cmd.args(&["--sort", "path", "foo", "dir", "dir/sub"]);
+    eqnice!("dir/sub/test:foo\ndir/sub/test:foo\n", cmd.stdout());
+
+    let mut cmd = dir.command();
+    cmd.args(&["--sort", "path", "--dedupe-links", "foo", "dir", "dir/sub"]);
+    eqnice!("dir/sub/test:foo\n", cmd.stdout());
+});
@@ -87,6 +87,7 @@ struct Context {
     lines: Data,
     line_number: Option<u64>,
     absolute_offset: u64,
+    kind: String,
     submatches: Vec<SubMatch>,
 }
 
@@ -163,6 +164,7 @@ rgtest!(basic, |dir: Dir, mut cmd: TestCommand| {
             ),
             line_number: Some(2),
             absolute_offset: 65,
+            kind: "before".to_string(),
             submatches: vec![],
         }
     );
@@ -186,7 +188,7 @@ rgtest!(basic, |dir: Dir, mut cmd: TestCommand| {
     assert_eq!(msgs[3].unwrap_end().path, Some(Data::text("sherlock")));
     assert_eq!(msgs[3].unwrap_end().binary_offset, None);
     assert_eq!(msgs[4].unwrap_summary().stats.searches_with_match, 1);
-    assert_eq!(msgs[4].unwrap_summary().stats.bytes_printed, 494);
+    assert_eq!(msgs[4].unwrap_summary().stats.bytes_printed, 510);
 });
 
 rgtest!(quiet_stats, |dir: Dir, mut cmd: TestCommand| {
@@ -371,3 +373,69 @@ rgtest!(r1412_look_behind_match_missing, |dir: Dir, mut cmd: TestCommand| {
     assert_eq!(m.lines, Data::text("bar\n"));
     assert_eq!(m.submatches.len(), 1);
 });
+
+// Tests that the standard printer and the JSON printer agree on the
+// `(line, offset)` position of every match in a NUL-heavy haystack searched
+// with `--text`, and that `--binary` (which converts NUL bytes to line
+// terminators) reports the same line numbers. Converted NUL bytes do not
+// create new lines, so line numbers count only the configured line
+// terminator no matter which output mode is used.
+rgtest!(binary_line_numbers_agree, |dir: Dir, mut cmd: TestCommand| {
+    let mut hay = vec![];
+    let mut bar_offsets = vec![];
+    for i in 0..100 {
+        let line = format!("foo{}\x00bar\x00baz{}\n", i, i);
+        let bar = line.find("bar").unwrap();
+        bar_offsets.push((hay.len() + bar) as u64);
+        hay.extend_from_slice(line.as_bytes());
+    }
+    dir.create_bytes("hay", &hay);
+
+    let json_positions = |cmd: &mut TestCommand| -> Vec<(u64, u64)> {
+        json_decode(&cmd.stdout())
+            .iter()
+            .filter_map(|msg| match *msg {
+                Message::Match(ref m) => {
+                    Some((m.line_number.unwrap(), m.absolute_offset))
+                }
+                _ => None,
+            })
+            .collect()
+    };
+
+    // With --text, both printers emit one entry per matching line and must
+    // agree exactly on every `(line, offset)` pair.
+    let stdout = cmd
+        .args(&["--no-mmap", "-a", "-n", "-b", "--no-filename", "bar", "hay"])
+        .stdout();
+    let std_positions: Vec<(u64, u64)> = stdout
+        .lines()
+        .map(|line| {
+            let mut fields = line.splitn(3, ':');
+            let lineno = fields.next().unwrap().parse().unwrap();
+            let offset = fields.next().unwrap().parse().unwrap();
+            (lineno, offset)
+        })
+        .collect();
+    assert_eq!(100, std_positions.len());
+
+    let mut cmd = dir.command();
+    cmd.args(&["--no-mmap", "-a", "--json", "bar", "hay"]);
+    assert_eq!(std_positions, json_positions(&mut cmd));
+
+    // With --binary, NUL bytes are converted to line terminators, so each
+    // match's offset points at the converted sub-line. But conversion must
+    // not change line numbers.
+    let mut cmd = dir.command();
+    cmd.args(&["--no-mmap", "--binary", "--json", "bar", "hay"]);
+    let bin_positions = json_positions(&mut cmd);
+    assert_eq!(100, bin_positions.len());
+    for (i, (&(line, _), &(bline, boffset))) in
+        std_positions.iter().zip(bin_positions.iter()).enumerate()
+    {
+        assert_eq!(line, bline);
+        // The converted sub-line starts at `bar` itself, since the NUL just
+        // before it became the previous sub-line's terminator.
+        assert_eq!(bar_offsets[i], boffset);
+    }
+});
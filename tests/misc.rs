@@ -225,6 +225,34 @@ but Watson, Doctor has to have it taken out for him and dusted,
     eqnice!(expected, cmd.stdout());
 });
 
+// Emulates a sed-style full file rewrite: every line is printed, with the
+// replacement applied to matching lines and non-matching lines passed
+// through verbatim. This is what 'sed s/Sherlock/FooBar/g sherlock' prints.
+rgtest!(replace_passthru, |dir: Dir, mut cmd: TestCommand| {
+    dir.create("sherlock", SHERLOCK);
+    cmd.args(&["--passthru", "-r", "FooBar", "Sherlock", "sherlock"]);
+
+    let expected = SHERLOCK.replace("Sherlock", "FooBar");
+    eqnice!(expected, cmd.stdout());
+});
+
+rgtest!(replace_passthru_line_numbers, |dir: Dir, mut cmd: TestCommand| {
+    dir.create("sherlock", SHERLOCK);
+    cmd.args(&["-n", "--passthru", "-r", "FooBar", "Sherlock", "sherlock"]);
+
+    // Line numbers remain continuous across matching and non-matching
+    // lines, with the usual ':' and '-' separators distinguishing them.
+    let expected = "\
+1:For the Doctor Watsons of this world, as opposed to the FooBar
+2-Holmeses, success in the province of detective work must always
+3:be, to a very large extent, the result of luck. FooBar Holmes
+4-can extract a clew from a wisp of straw or a flake of cigar ash;
+5-but Doctor Watson has to have it taken out for him and dusted,
+6-and exhibited clearly, with a label attached.
+";
+    eqnice!(expected, cmd.stdout());
+});
+
 rgtest!(replace_with_only_matching, |dir: Dir, mut cmd: TestCommand| {
     dir.create("sherlock", SHERLOCK);
     cmd.arg("-o").arg("-r").arg("$1").arg(r"of (\w+)").arg("sherlock");
@@ -1128,3 +1156,27 @@ rgtest!(sortr_accessed, |dir: Dir, mut cmd: TestCommand| {
     let expected = "dir/d:test\nb:test\ndir/c:test\na:test\n";
     eqnice!(expected, cmd.args(["--sortr", "accessed", "test"]).stdout());
 });
+
+rgtest!(stats_matched_directories, |dir: Dir, mut cmd: TestCommand| {
+    dir.create_dir("a/b");
+    dir.create_dir("c");
+    dir.create("a/one", SHERLOCK);
+    dir.create("a/two", SHERLOCK);
+    dir.create("a/b/three", SHERLOCK);
+    dir.create("c/nope", "nothing to see here\n");
+
+    let lines = cmd.arg("--stats").arg("Sherlock").stdout();
+    assert!(lines.contains("3 files contained matches"));
+    assert!(lines.contains("2 directories contained matches"));
+});
+
+rgtest!(dedupe_lines, |dir: Dir, mut cmd: TestCommand| {
+    dir.create("log", "foo one\nbar\nfoo one\nfoo two\nfoo one\n");
+
+    let expected = "\
+1:foo one
+4:foo two
+(line 1 repeated 2 more times)
+";
+    eqnice!(expected, cmd.args(["-n", "--dedupe-lines", "foo", "log"]).stdout());
+});